@@ -0,0 +1,84 @@
+# Email strings, English. This catalog is the fallback for every other locale,
+# so every key must be here. Placeholders are written { $name }.
+
+## Shared
+footer-tagline = Caterpillar Clay - Handmade Pottery
+footer-preferences = Email preferences or unsubscribe
+greeting = Hi { $name },
+order-id = Order ID: { $id }
+button-shop-now = SHOP NOW
+button-view-product = VIEW PRODUCT
+
+## Order emails
+order-confirmation-subject = Order Confirmation - #{ $id }
+order-confirmation-title = Thank you for your order!
+order-confirmation-received = We've received your order and are getting it ready for you.
+order-confirmation-total = Total: ${ $total }
+order-confirmation-next = We'll send you another email when your order ships.
+
+order-shipped-subject = Your Order Has Shipped - #{ $id }
+order-shipped-title = Your order is on its way!
+order-shipped-intro = Great news! Your order has shipped.
+order-shipped-tracking = Tracking Number:
+order-shipped-track = You can track your package using the tracking number above.
+
+order-delivered-subject = Your Order Has Been Delivered - #{ $id }
+order-delivered-title = Your order has arrived!
+order-delivered-intro = Your Caterpillar Clay order has been delivered!
+order-delivered-outro = We hope you love your new pottery. If you have any questions or concerns, please don't hesitate to reach out.
+
+also-bought-title = Customers also bought
+
+refund-subject = Refund Processed - #{ $id }
+refund-title = Your refund has been processed
+refund-intro = We've processed a refund for your order.
+refund-amount = Refund Amount: ${ $amount }
+refund-timing = The refund should appear on your statement within 5-10 business days, depending on your bank.
+refund-questions = If you have any questions, please don't hesitate to reach out.
+
+payment-link-subject = Payment Request - #{ $id }
+payment-link-title = Your order is ready for payment
+payment-link-intro = We've put together your order. Use the link below to pay securely with Stripe.
+payment-link-total = Total: ${ $total }
+payment-link-button = Pay now
+payment-link-outro = We'll start on your order as soon as payment comes through.
+
+## Newsletter
+newsletter-confirm-subject = Confirm your Caterpillar Clay subscription
+newsletter-confirm-title = Confirm your subscription
+newsletter-confirm-intro = Please confirm you'd like to hear about new pottery from Caterpillar Clay.
+newsletter-confirm-button = CONFIRM SUBSCRIPTION
+newsletter-confirm-ignore = If you didn't sign up, you can ignore this email and you won't hear from us again.
+
+newsletter-welcome-subject = Welcome to Caterpillar Clay!
+newsletter-welcome-title = Welcome to Caterpillar Clay!
+newsletter-welcome-thanks = Thank you for subscribing to our newsletter!
+newsletter-welcome-first = You'll be the first to know when we add new handmade pottery pieces to our shop.
+newsletter-welcome-craft = Each piece is crafted with care, featuring hand-painted designs inspired by local flowers.
+
+new-arrival-subject = New Arrival: { $name } - Caterpillar Clay
+new-arrival-title = New Arrival!
+new-arrivals-subject = { $count } New Arrivals - Caterpillar Clay
+new-arrivals-title = New Arrivals!
+new-arrivals-intro = Check out our latest handmade pottery pieces
+
+back-in-stock-subject = Back in Stock: { $name } - Caterpillar Clay
+back-in-stock-title = Back in Stock!
+back-in-stock-intro = Good news! This item is available again. Grab it before it's gone!
+back-in-stock-many-subject = { $count } Items Back in Stock - Caterpillar Clay
+back-in-stock-many-intro = Good news! These items are available again
+
+win-back-subject = We miss you - new pieces at Caterpillar Clay
+win-back-title = It's been a while!
+win-back-intro = We've made some new pieces since your last order. Here are a few we think you'll like.
+win-back-discount = Use code { $code } for { $percent }% off your next order.
+win-back-discount-expires = Valid once, until { $date }.
+
+## Notify Me restock alerts
+restock-alert-subject = It's Back! { $name } is in stock - Caterpillar Clay
+restock-alert-style-subject = It's Back! { $name } - { $style } is in stock - Caterpillar Clay
+restock-alert-title = It's Back!
+restock-alert-intro = The item you wanted is back in stock
+restock-alert-styles = Available Styles:
+restock-alert-hurry = Grab it before it's gone again!
+restock-alert-footer = You received this email because you signed up to be notified when this item was back in stock. This is a one-time notification.
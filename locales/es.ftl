@@ -0,0 +1,83 @@
+# Email strings, Spanish. Missing keys fall back to en.ftl.
+
+## Shared
+footer-tagline = Caterpillar Clay - Cerámica hecha a mano
+footer-preferences = Preferencias de correo o darse de baja
+greeting = Hola { $name }:
+order-id = N.º de pedido: { $id }
+button-shop-now = COMPRAR AHORA
+button-view-product = VER PRODUCTO
+
+## Order emails
+order-confirmation-subject = Confirmación de pedido - #{ $id }
+order-confirmation-title = ¡Gracias por tu pedido!
+order-confirmation-received = Hemos recibido tu pedido y lo estamos preparando.
+order-confirmation-total = Total: ${ $total }
+order-confirmation-next = Te enviaremos otro correo cuando tu pedido salga.
+
+order-shipped-subject = Tu pedido ha sido enviado - #{ $id }
+order-shipped-title = ¡Tu pedido está en camino!
+order-shipped-intro = ¡Buenas noticias! Tu pedido ha sido enviado.
+order-shipped-tracking = Número de seguimiento:
+order-shipped-track = Puedes seguir tu paquete con el número de seguimiento de arriba.
+
+order-delivered-subject = Tu pedido ha sido entregado - #{ $id }
+order-delivered-title = ¡Tu pedido ha llegado!
+order-delivered-intro = ¡Tu pedido de Caterpillar Clay ha sido entregado!
+order-delivered-outro = Esperamos que te encante tu nueva cerámica. Si tienes alguna pregunta o inquietud, no dudes en escribirnos.
+
+also-bought-title = Otros clientes también compraron
+
+refund-subject = Reembolso procesado - #{ $id }
+refund-title = Tu reembolso ha sido procesado
+refund-intro = Hemos procesado un reembolso de tu pedido.
+refund-amount = Importe del reembolso: ${ $amount }
+refund-timing = El reembolso debería aparecer en tu estado de cuenta en 5 a 10 días hábiles, según tu banco.
+refund-questions = Si tienes alguna pregunta, no dudes en escribirnos.
+
+payment-link-subject = Solicitud de pago - #{ $id }
+payment-link-title = Tu pedido está listo para pagar
+payment-link-intro = Hemos preparado tu pedido. Usa el enlace de abajo para pagar de forma segura con Stripe.
+payment-link-total = Total: ${ $total }
+payment-link-button = Pagar ahora
+payment-link-outro = Empezaremos con tu pedido en cuanto recibamos el pago.
+
+## Newsletter
+newsletter-confirm-subject = Confirma tu suscripción a Caterpillar Clay
+newsletter-confirm-title = Confirma tu suscripción
+newsletter-confirm-intro = Confirma que quieres recibir novedades sobre la cerámica de Caterpillar Clay.
+newsletter-confirm-button = CONFIRMAR SUSCRIPCIÓN
+newsletter-confirm-ignore = Si no te suscribiste, puedes ignorar este correo y no volverás a saber de nosotros.
+
+newsletter-welcome-subject = ¡Bienvenido a Caterpillar Clay!
+newsletter-welcome-title = ¡Bienvenido a Caterpillar Clay!
+newsletter-welcome-thanks = ¡Gracias por suscribirte a nuestro boletín!
+newsletter-welcome-first = Serás el primero en enterarte cuando agreguemos nuevas piezas de cerámica hecha a mano a la tienda.
+newsletter-welcome-craft = Cada pieza está hecha con cuidado, con diseños pintados a mano inspirados en flores locales.
+
+new-arrival-subject = Novedad: { $name } - Caterpillar Clay
+new-arrival-title = ¡Novedad!
+new-arrivals-subject = { $count } novedades - Caterpillar Clay
+new-arrivals-title = ¡Novedades!
+new-arrivals-intro = Descubre nuestras últimas piezas de cerámica hecha a mano
+
+back-in-stock-subject = De nuevo disponible: { $name } - Caterpillar Clay
+back-in-stock-title = ¡De nuevo disponible!
+back-in-stock-intro = ¡Buenas noticias! Este artículo vuelve a estar disponible. ¡Consíguelo antes de que se agote!
+back-in-stock-many-subject = { $count } artículos de nuevo disponibles - Caterpillar Clay
+back-in-stock-many-intro = ¡Buenas noticias! Estos artículos vuelven a estar disponibles
+
+win-back-subject = Te echamos de menos - nuevas piezas en Caterpillar Clay
+win-back-title = ¡Ha pasado un tiempo!
+win-back-intro = Hemos hecho nuevas piezas desde tu último pedido. Aquí tienes algunas que creemos que te gustarán.
+win-back-discount = Usa el código { $code } para obtener un { $percent }% de descuento en tu próximo pedido.
+win-back-discount-expires = Válido una vez, hasta el { $date }.
+
+## Notify Me restock alerts
+restock-alert-subject = ¡Ha vuelto! { $name } está disponible - Caterpillar Clay
+restock-alert-style-subject = ¡Ha vuelto! { $name } - { $style } está disponible - Caterpillar Clay
+restock-alert-title = ¡Ha vuelto!
+restock-alert-intro = El artículo que querías vuelve a estar disponible
+restock-alert-styles = Estilos disponibles:
+restock-alert-hurry = ¡Consíguelo antes de que se agote otra vez!
+restock-alert-footer = Recibiste este correo porque pediste que te avisáramos cuando este artículo volviera a estar disponible. Es un aviso único.
@@ -0,0 +1,9 @@
+-- Gift options on orders (gift message, gift wrap fee, hide prices on packing slip)
+ALTER TABLE orders ADD COLUMN is_gift INTEGER DEFAULT 0;
+ALTER TABLE orders ADD COLUMN gift_message TEXT DEFAULT NULL;
+ALTER TABLE orders ADD COLUMN gift_wrap INTEGER DEFAULT 0;
+ALTER TABLE orders ADD COLUMN gift_wrap_cents INTEGER DEFAULT 0;
+
+-- Configurable gift wrap fee (cents)
+INSERT OR IGNORE INTO site_settings (key, value, updated_ts) VALUES
+    ('gift_wrap_fee_cents', '500', strftime('%s', 'now'));
@@ -0,0 +1,3 @@
+-- Stripe Radar risk assessment for the order's charge
+ALTER TABLE orders ADD COLUMN risk_level TEXT DEFAULT NULL;
+ALTER TABLE orders ADD COLUMN risk_score INTEGER DEFAULT NULL;
@@ -0,0 +1,2 @@
+-- Carrier-reported delivery ETA (Unix timestamp) from Shippo tracking updates
+ALTER TABLE orders ADD COLUMN delivery_eta_ts INTEGER DEFAULT NULL;
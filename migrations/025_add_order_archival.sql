@@ -0,0 +1,9 @@
+-- Order archival: old orders are flagged and stripped of customer PII by the retention job
+ALTER TABLE orders ADD COLUMN archived INTEGER DEFAULT 0;
+ALTER TABLE orders ADD COLUMN archived_ts INTEGER DEFAULT NULL;
+
+CREATE INDEX IF NOT EXISTS idx_orders_archived_created ON orders(archived, created_ts);
+
+-- Orders older than this many years are archived (0 disables the job)
+INSERT OR IGNORE INTO site_settings (key, value, updated_ts) VALUES
+    ('order_retention_years', '7', strftime('%s', 'now'));
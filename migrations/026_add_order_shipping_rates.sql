@@ -0,0 +1,3 @@
+-- Shipping rate audit trail: the Shippo rate quoted at checkout and the one used to buy the label (JSON)
+ALTER TABLE orders ADD COLUMN checkout_rate TEXT DEFAULT NULL;
+ALTER TABLE orders ADD COLUMN label_rate TEXT DEFAULT NULL;
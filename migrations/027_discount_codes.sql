@@ -0,0 +1,20 @@
+-- Discount codes redeemable at checkout
+CREATE TABLE IF NOT EXISTS discount_codes (
+    id TEXT PRIMARY KEY,
+    code TEXT UNIQUE NOT NULL,
+    percent_off INTEGER DEFAULT NULL,
+    amount_off_cents INTEGER DEFAULT NULL,
+    min_subtotal_cents INTEGER DEFAULT 0,
+    max_redemptions INTEGER DEFAULT NULL,
+    times_redeemed INTEGER DEFAULT 0,
+    expires_ts INTEGER DEFAULT NULL,
+    is_active INTEGER DEFAULT 1,
+    created_ts INTEGER NOT NULL,
+    updated_ts INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_discount_codes_code ON discount_codes(code);
+
+-- Code used on an order and the amount it took off
+ALTER TABLE orders ADD COLUMN promo_code TEXT DEFAULT NULL;
+ALTER TABLE orders ADD COLUMN discount_cents INTEGER DEFAULT 0;
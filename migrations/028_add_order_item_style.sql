@@ -0,0 +1,3 @@
+-- Style chosen for an order item; the name is copied so it survives the style being deleted
+ALTER TABLE order_items ADD COLUMN style_id TEXT REFERENCES product_styles(id) ON DELETE SET NULL;
+ALTER TABLE order_items ADD COLUMN style_name TEXT DEFAULT NULL;
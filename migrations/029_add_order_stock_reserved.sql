@@ -0,0 +1,2 @@
+-- Stock is reserved when the order is created; this tracks whether it is still held
+ALTER TABLE orders ADD COLUMN stock_reserved INTEGER DEFAULT 0;
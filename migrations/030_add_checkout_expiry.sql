@@ -0,0 +1,3 @@
+-- When the Stripe Checkout session for an order expires, and why an order was cancelled
+ALTER TABLE orders ADD COLUMN checkout_expires_ts INTEGER DEFAULT NULL;
+ALTER TABLE orders ADD COLUMN cancel_reason TEXT DEFAULT NULL;
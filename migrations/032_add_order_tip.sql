@@ -0,0 +1,2 @@
+-- Optional "support the artist" tip, kept separate from merchandise revenue
+ALTER TABLE orders ADD COLUMN tip_cents INTEGER DEFAULT 0;
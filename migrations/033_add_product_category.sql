@@ -0,0 +1,3 @@
+-- Optional category used to suggest complementary products at checkout
+ALTER TABLE products ADD COLUMN category TEXT DEFAULT NULL;
+CREATE INDEX IF NOT EXISTS idx_products_category ON products(category);
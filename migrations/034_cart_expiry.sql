@@ -0,0 +1,5 @@
+-- Pending orders that hold stock without a live Checkout session are cancelled after this many hours (0 disables the job)
+INSERT OR IGNORE INTO site_settings (key, value, updated_ts) VALUES
+    ('cart_ttl_hours', '24', strftime('%s', 'now'));
+
+CREATE INDEX IF NOT EXISTS idx_orders_status_reserved ON orders(status, stock_reserved);
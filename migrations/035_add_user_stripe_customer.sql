@@ -0,0 +1,3 @@
+-- Stripe Customer per user so returning customers can reuse saved cards
+ALTER TABLE users ADD COLUMN stripe_customer_id TEXT DEFAULT NULL;
+CREATE INDEX IF NOT EXISTS idx_users_stripe_customer ON users(stripe_customer_id);
@@ -0,0 +1,8 @@
+-- Webhook deliveries already processed, so retried deliveries are skipped
+CREATE TABLE IF NOT EXISTS webhook_events (
+    provider TEXT NOT NULL,
+    event_id TEXT NOT NULL,
+    event_type TEXT NOT NULL,
+    received_ts INTEGER NOT NULL,
+    PRIMARY KEY (provider, event_id)
+);
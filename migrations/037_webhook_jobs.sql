@@ -0,0 +1,15 @@
+-- Webhook side effects are processed from this queue with retry/backoff.
+-- status: pending, processing, done, dead (gave up after too many attempts)
+CREATE TABLE IF NOT EXISTS webhook_jobs (
+    id TEXT PRIMARY KEY,
+    kind TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_ts INTEGER NOT NULL,
+    last_error TEXT,
+    created_ts INTEGER NOT NULL,
+    updated_ts INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_webhook_jobs_status_next ON webhook_jobs(status, next_attempt_ts);
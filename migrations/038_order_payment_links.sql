@@ -0,0 +1,3 @@
+-- Stripe Payment Link sent to the customer for an order paid outside the storefront checkout
+ALTER TABLE orders ADD COLUMN payment_link_id TEXT DEFAULT NULL;
+ALTER TABLE orders ADD COLUMN payment_link_url TEXT DEFAULT NULL;
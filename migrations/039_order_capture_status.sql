@@ -0,0 +1,4 @@
+-- Manual capture: payment authorized at checkout, captured when the label is bought
+ALTER TABLE orders ADD COLUMN capture_status TEXT DEFAULT NULL;
+ALTER TABLE orders ADD COLUMN authorized_ts INTEGER DEFAULT NULL;
+CREATE INDEX IF NOT EXISTS idx_orders_capture_status ON orders(capture_status, authorized_ts);
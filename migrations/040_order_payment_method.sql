@@ -0,0 +1,4 @@
+-- How each order was paid, for the payment method breakdown on the dashboard
+ALTER TABLE orders ADD COLUMN payment_method_type TEXT DEFAULT NULL;
+ALTER TABLE orders ADD COLUMN card_brand TEXT DEFAULT NULL;
+ALTER TABLE orders ADD COLUMN payment_wallet TEXT DEFAULT NULL;
@@ -0,0 +1,2 @@
+-- Delayed payment methods (bank debits): Checkout completed but funds haven't cleared yet
+ALTER TABLE orders ADD COLUMN payment_processing_ts INTEGER DEFAULT NULL;
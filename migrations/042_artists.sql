@@ -0,0 +1,19 @@
+-- Stripe Connect marketplace: artists sell through their own connected accounts
+CREATE TABLE IF NOT EXISTS artists (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    email TEXT,
+    stripe_account_id TEXT UNIQUE,
+    charges_enabled INTEGER NOT NULL DEFAULT 0,
+    payouts_enabled INTEGER NOT NULL DEFAULT 0,
+    created_ts INTEGER NOT NULL,
+    updated_ts INTEGER NOT NULL
+);
+
+-- Products owned by an artist (NULL = sold by the shop itself)
+ALTER TABLE products ADD COLUMN artist_id TEXT DEFAULT NULL REFERENCES artists(id);
+
+-- Which artist an order's payment was sent to, and the shop's cut of it
+ALTER TABLE orders ADD COLUMN artist_id TEXT DEFAULT NULL;
+ALTER TABLE orders ADD COLUMN application_fee_cents INTEGER DEFAULT NULL;
+CREATE INDEX IF NOT EXISTS idx_orders_artist ON orders(artist_id);
@@ -0,0 +1,23 @@
+-- Recurring products ("pottery of the month") billed through Stripe Subscriptions
+ALTER TABLE products ADD COLUMN billing_interval TEXT DEFAULT NULL;          -- month or year; NULL = one-time
+ALTER TABLE products ADD COLUMN stripe_recurring_price_id TEXT DEFAULT NULL;
+
+CREATE TABLE IF NOT EXISTS subscriptions (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL REFERENCES users(id),
+    product_id TEXT NOT NULL REFERENCES products(id),
+    style_id TEXT REFERENCES product_styles(id),
+    shipping_address TEXT NOT NULL,
+    stripe_subscription_id TEXT UNIQUE,
+    status TEXT NOT NULL DEFAULT 'incomplete',
+    current_period_end INTEGER,
+    cancel_at_period_end INTEGER NOT NULL DEFAULT 0,
+    created_ts INTEGER NOT NULL,
+    updated_ts INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_subscriptions_user ON subscriptions(user_id);
+
+-- Each paid billing cycle becomes an order to fulfil; the invoice makes it idempotent
+ALTER TABLE orders ADD COLUMN subscription_id TEXT DEFAULT NULL;
+ALTER TABLE orders ADD COLUMN stripe_invoice_id TEXT DEFAULT NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_orders_stripe_invoice ON orders(stripe_invoice_id);
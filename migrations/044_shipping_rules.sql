@@ -0,0 +1,19 @@
+-- Shop-defined shipping rates, matched on the destination before asking Shippo.
+-- Zone lists are JSON arrays; an empty list matches any value.
+CREATE TABLE IF NOT EXISTS shipping_rules (
+    id TEXT PRIMARY KEY,
+    carrier TEXT NOT NULL,
+    service TEXT NOT NULL,
+    countries TEXT NOT NULL DEFAULT '[]',
+    states TEXT NOT NULL DEFAULT '[]',
+    zip_prefixes TEXT NOT NULL DEFAULT '[]',
+    -- 'flat' charges flat_cents; 'weight' charges the first tier the parcel fits in
+    rate_type TEXT NOT NULL DEFAULT 'flat',
+    flat_cents INTEGER DEFAULT NULL,
+    weight_tiers TEXT NOT NULL DEFAULT '[]',
+    estimated_days INTEGER DEFAULT NULL,
+    sort_order INTEGER NOT NULL DEFAULT 0,
+    is_active INTEGER NOT NULL DEFAULT 1,
+    created_ts INTEGER NOT NULL,
+    updated_ts INTEGER NOT NULL
+);
@@ -0,0 +1,2 @@
+-- Shippo transaction behind the purchased label, needed to void (refund) it
+ALTER TABLE orders ADD COLUMN shippo_transaction_id TEXT DEFAULT NULL;
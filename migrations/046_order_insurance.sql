@@ -0,0 +1,3 @@
+-- Shippo shipment insurance bought with the label
+ALTER TABLE orders ADD COLUMN insurance_amount_cents INTEGER DEFAULT NULL;  -- declared value covered
+ALTER TABLE orders ADD COLUMN insurance_cost_cents INTEGER DEFAULT NULL;    -- what the coverage added to the label
@@ -0,0 +1,2 @@
+-- Signature the carrier collects on delivery (standard/adult), chosen when the label is bought
+ALTER TABLE orders ADD COLUMN signature_confirmation TEXT DEFAULT NULL;
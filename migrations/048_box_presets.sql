@@ -0,0 +1,16 @@
+-- Shipping boxes the shop actually packs with. Rates are quoted for the box
+-- an order fits in rather than the bare item dimensions.
+CREATE TABLE IF NOT EXISTS box_presets (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    length_cm REAL NOT NULL,
+    width_cm REAL NOT NULL,
+    height_cm REAL NOT NULL,
+    empty_weight_grams INTEGER NOT NULL DEFAULT 0,
+    created_ts INTEGER NOT NULL,
+    updated_ts INTEGER NOT NULL
+);
+
+-- Box a product or style should ship in when it fits
+ALTER TABLE products ADD COLUMN box_preset_id TEXT DEFAULT NULL REFERENCES box_presets(id);
+ALTER TABLE product_styles ADD COLUMN box_preset_id TEXT DEFAULT NULL REFERENCES box_presets(id);
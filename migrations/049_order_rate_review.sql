@@ -0,0 +1,3 @@
+-- Set when checkout charged a fallback shipping rate because Shippo was unreachable;
+-- cleared once an admin has checked the real postage
+ALTER TABLE orders ADD COLUMN needs_rate_review INTEGER NOT NULL DEFAULT 0;
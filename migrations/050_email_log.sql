@@ -0,0 +1,19 @@
+-- Every email the shop tried to send, for answering "did it go out?"
+CREATE TABLE IF NOT EXISTS email_log (
+    id TEXT PRIMARY KEY,
+    recipient TEXT NOT NULL,
+    -- e.g. order_shipped, newsletter_new_product
+    email_type TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    -- smtp or resend
+    provider TEXT NOT NULL,
+    -- sent or failed
+    status TEXT NOT NULL,
+    -- Resend email ID, or the SMTP server's reply
+    provider_id TEXT DEFAULT NULL,
+    error TEXT DEFAULT NULL,
+    created_ts INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_email_log_created ON email_log(created_ts);
+CREATE INDEX IF NOT EXISTS idx_email_log_recipient ON email_log(recipient);
@@ -0,0 +1,7 @@
+-- Double opt-in: new subscribers stay pending until they follow the link in the
+-- confirmation email. Existing subscribers already opted in, so they stay confirmed.
+ALTER TABLE newsletter_subscribers ADD COLUMN status TEXT NOT NULL DEFAULT 'confirmed';
+ALTER TABLE newsletter_subscribers ADD COLUMN confirm_token TEXT DEFAULT NULL;
+ALTER TABLE newsletter_subscribers ADD COLUMN confirmed_ts INTEGER DEFAULT NULL;
+
+CREATE INDEX IF NOT EXISTS idx_newsletter_confirm_token ON newsletter_subscribers(confirm_token);
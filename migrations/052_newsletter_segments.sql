@@ -0,0 +1,9 @@
+-- Saved subscriber filters product notifications can be sent to instead of every subscriber
+CREATE TABLE IF NOT EXISTS newsletter_segments (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    -- JSON SegmentFilters
+    filters TEXT NOT NULL DEFAULT '{}',
+    created_ts INTEGER NOT NULL,
+    updated_ts INTEGER NOT NULL
+);
@@ -0,0 +1,22 @@
+-- Custom newsletter emails composed in the admin, sent now or at a scheduled time
+CREATE TABLE IF NOT EXISTS newsletter_campaigns (
+    id TEXT PRIMARY KEY,
+    subject TEXT NOT NULL,
+    body TEXT NOT NULL,
+    -- html or markdown
+    body_format TEXT NOT NULL DEFAULT 'html',
+    -- JSON array of featured product IDs
+    product_ids TEXT NOT NULL DEFAULT '[]',
+    -- Send only to this segment; NULL sends to every confirmed subscriber
+    segment_id TEXT DEFAULT NULL,
+    -- draft, scheduled, sending, sent or cancelled
+    status TEXT NOT NULL DEFAULT 'draft',
+    scheduled_ts INTEGER DEFAULT NULL,
+    sent_ts INTEGER DEFAULT NULL,
+    recipient_count INTEGER DEFAULT NULL,
+    sent_count INTEGER DEFAULT NULL,
+    created_ts INTEGER NOT NULL,
+    updated_ts INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_newsletter_campaigns_created ON newsletter_campaigns(created_ts);
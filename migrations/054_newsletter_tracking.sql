@@ -0,0 +1,22 @@
+-- Links in sent campaigns, rewritten to go through the click tracker
+CREATE TABLE IF NOT EXISTS newsletter_links (
+    id TEXT PRIMARY KEY,
+    campaign_id TEXT NOT NULL,
+    url TEXT NOT NULL,
+    created_ts INTEGER NOT NULL,
+    UNIQUE (campaign_id, url)
+);
+
+-- Opens (tracking pixel loads) and clicks per campaign and subscriber
+CREATE TABLE IF NOT EXISTS newsletter_events (
+    id TEXT PRIMARY KEY,
+    campaign_id TEXT NOT NULL,
+    subscriber_id TEXT NOT NULL,
+    -- open or click
+    event_type TEXT NOT NULL,
+    link_id TEXT DEFAULT NULL,
+    created_ts INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_newsletter_events_campaign ON newsletter_events(campaign_id, event_type);
+CREATE INDEX IF NOT EXISTS idx_newsletter_events_subscriber ON newsletter_events(subscriber_id, created_ts);
@@ -0,0 +1,7 @@
+-- Email categories each subscriber wants; everyone starts opted into all of them
+ALTER TABLE newsletter_subscribers ADD COLUMN wants_new_drops INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE newsletter_subscribers ADD COLUMN wants_restocks INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE newsletter_subscribers ADD COLUMN wants_sales INTEGER NOT NULL DEFAULT 1;
+
+-- Category a campaign is sent under: new_drops, restocks or sales
+ALTER TABLE newsletter_campaigns ADD COLUMN category TEXT NOT NULL DEFAULT 'sales';
@@ -0,0 +1,10 @@
+-- Addresses that bounced or complained, reported by Resend webhooks. Batch sends skip them
+-- to protect the sending domain's reputation.
+CREATE TABLE IF NOT EXISTS email_suppressions (
+    email TEXT PRIMARY KEY,
+    -- bounce or complaint
+    reason TEXT NOT NULL,
+    -- Bounce type or message from Resend
+    detail TEXT DEFAULT NULL,
+    created_ts INTEGER NOT NULL
+);
@@ -0,0 +1,4 @@
+-- Language emails are written in (see LOCALES in services/i18n.rs); NULL sends English
+ALTER TABLE users ADD COLUMN locale TEXT DEFAULT NULL;
+ALTER TABLE newsletter_subscribers ADD COLUMN locale TEXT DEFAULT NULL;
+ALTER TABLE product_notifications ADD COLUMN locale TEXT DEFAULT NULL;
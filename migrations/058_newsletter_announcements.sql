@@ -0,0 +1,18 @@
+-- Product drop and restock announcements queued from the admin and sent in the background
+CREATE TABLE IF NOT EXISTS newsletter_announcements (
+    id TEXT PRIMARY KEY,
+    -- new or restock
+    notify_type TEXT NOT NULL,
+    -- JSON array of announced product IDs
+    product_ids TEXT NOT NULL DEFAULT '[]',
+    -- Use the single-product template rather than the multi-product one
+    single_product INTEGER NOT NULL DEFAULT 0,
+    -- Send only to this segment; NULL sends to every confirmed subscriber
+    segment_id TEXT DEFAULT NULL,
+    -- queued, sending or sent
+    status TEXT NOT NULL DEFAULT 'queued',
+    recipient_count INTEGER DEFAULT NULL,
+    sent_count INTEGER DEFAULT NULL,
+    created_ts INTEGER NOT NULL,
+    updated_ts INTEGER NOT NULL
+);
@@ -0,0 +1,4 @@
+-- Resend delivery events (delivered, opened, bounced, ...) advance email_log.status past "sent"
+ALTER TABLE email_log ADD COLUMN delivery_ts INTEGER DEFAULT NULL;
+
+CREATE INDEX IF NOT EXISTS idx_email_log_provider_id ON email_log(provider_id);
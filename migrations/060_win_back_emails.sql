@@ -0,0 +1,15 @@
+-- Win-back emails sent to customers who stopped ordering; one per lapse
+CREATE TABLE IF NOT EXISTS win_back_emails (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    email TEXT NOT NULL,
+    -- The customer's most recent paid order when the email went out; a newer order
+    -- followed by another lapse makes them eligible again
+    last_order_ts INTEGER NOT NULL,
+    -- Single-use code generated for this customer, if discounts were on
+    discount_code TEXT DEFAULT NULL,
+    product_count INTEGER NOT NULL DEFAULT 0,
+    sent_ts INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_win_back_emails_user_id ON win_back_emails(user_id);
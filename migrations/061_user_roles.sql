@@ -0,0 +1,5 @@
+-- Staff roles replace the single admin flag: owner, fulfillment or marketing.
+-- is_admin stays set for any staff role so existing queries keep working.
+ALTER TABLE users ADD COLUMN role TEXT DEFAULT NULL;
+
+UPDATE users SET role = 'owner' WHERE is_admin = 1;
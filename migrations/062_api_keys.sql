@@ -0,0 +1,14 @@
+-- Admin API keys for scripts; only a SHA-256 hash of each key is stored
+CREATE TABLE IF NOT EXISTS api_keys (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    -- First characters of the key, shown so admins can tell keys apart
+    key_prefix TEXT NOT NULL,
+    key_hash TEXT UNIQUE NOT NULL,
+    -- JSON array of permissions (view_catalog, edit_catalog, fulfillment, ...)
+    scopes TEXT NOT NULL DEFAULT '[]',
+    created_by TEXT REFERENCES users(id),
+    last_used_ts INTEGER DEFAULT NULL,
+    revoked_ts INTEGER DEFAULT NULL,
+    created_ts INTEGER NOT NULL
+);
@@ -0,0 +1,21 @@
+-- Who changed what through the admin API
+CREATE TABLE IF NOT EXISTS audit_log (
+    id TEXT PRIMARY KEY,
+    -- Staff user, or the creator of the API key used; NULL when admin auth is off
+    user_id TEXT DEFAULT NULL,
+    -- Email of the staff user, or api-key:<prefix>
+    actor TEXT NOT NULL,
+    method TEXT NOT NULL,
+    -- Route pattern, e.g. /products/{id}
+    route TEXT NOT NULL,
+    -- ID from the path, if the route has one
+    target_id TEXT DEFAULT NULL,
+    -- Fields sent in the request body, e.g. price_cents=2400, stock_quantity=3
+    summary TEXT DEFAULT NULL,
+    -- HTTP status of the response; 403 means the role wasn't allowed
+    status INTEGER NOT NULL,
+    created_ts INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_audit_log_created_ts ON audit_log(created_ts);
+CREATE INDEX IF NOT EXISTS idx_audit_log_target_id ON audit_log(target_id);
@@ -0,0 +1,22 @@
+-- Authenticator app (TOTP) secrets for staff, used to confirm destructive admin actions
+CREATE TABLE IF NOT EXISTS admin_totp (
+    user_id TEXT PRIMARY KEY REFERENCES users(id),
+    -- Base32 secret shared with the authenticator app
+    secret TEXT NOT NULL,
+    -- NULL until the first code is confirmed
+    enabled_ts INTEGER DEFAULT NULL,
+    -- Last 30-second time step accepted, so a code can't be used twice
+    last_step INTEGER NOT NULL DEFAULT 0,
+    created_ts INTEGER NOT NULL
+);
+
+-- Short-lived elevated-session tokens issued after a TOTP code or a fresh sign-in;
+-- only a SHA-256 hash of each token is stored
+CREATE TABLE IF NOT EXISTS admin_step_up_tokens (
+    token_hash TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL REFERENCES users(id),
+    expires_ts INTEGER NOT NULL,
+    created_ts INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_admin_step_up_tokens_expires_ts ON admin_step_up_tokens(expires_ts);
@@ -0,0 +1,3 @@
+-- Set when the user's Clerk account is deleted; deactivated users can't sign in
+-- and lose any staff role, but their orders are kept
+ALTER TABLE users ADD COLUMN deactivated_ts INTEGER DEFAULT NULL;
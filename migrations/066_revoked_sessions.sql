@@ -0,0 +1,12 @@
+-- Clerk sessions that were revoked, ended or removed (from the Clerk webhook).
+-- A verified JWT whose sid is listed here is rejected even before it expires.
+CREATE TABLE IF NOT EXISTS revoked_sessions (
+    session_id TEXT PRIMARY KEY,
+    -- Clerk user ID the session belonged to
+    clerk_user_id TEXT DEFAULT NULL,
+    -- Clerk event type, e.g. session.revoked
+    reason TEXT NOT NULL,
+    revoked_ts INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_revoked_sessions_revoked_ts ON revoked_sessions(revoked_ts);
@@ -0,0 +1,7 @@
+-- Products a customer saved for later, shown on their account page
+CREATE TABLE IF NOT EXISTS wishlist_items (
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    product_id TEXT NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+    created_ts INTEGER NOT NULL,
+    PRIMARY KEY (user_id, product_id)
+);
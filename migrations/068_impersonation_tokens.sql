@@ -0,0 +1,16 @@
+-- Short-lived tokens an owner issues to view the storefront API as a customer.
+-- Only a SHA-256 hash of each token is stored.
+CREATE TABLE IF NOT EXISTS impersonation_tokens (
+    token_hash TEXT PRIMARY KEY,
+    -- Customer being impersonated
+    user_id TEXT NOT NULL REFERENCES users(id),
+    -- Owner who issued it
+    created_by TEXT NOT NULL REFERENCES users(id),
+    -- Why, e.g. the support ticket
+    reason TEXT NOT NULL,
+    expires_ts INTEGER NOT NULL,
+    revoked_ts INTEGER DEFAULT NULL,
+    created_ts INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_impersonation_tokens_user_id ON impersonation_tokens(user_id);
@@ -0,0 +1,18 @@
+-- Admin sign-ins and failed admin authentication, for the activity overview
+-- on the dashboard. Kept for 90 days.
+CREATE TABLE IF NOT EXISTS admin_auth_events (
+    id TEXT PRIMARY KEY,
+    -- sign_in, failure or lockout
+    kind TEXT NOT NULL,
+    user_id TEXT DEFAULT NULL REFERENCES users(id),
+    -- Email of the account, when known
+    actor TEXT DEFAULT NULL,
+    ip TEXT DEFAULT NULL,
+    -- Session a sign-in started; one sign_in row per session
+    session_id TEXT DEFAULT NULL UNIQUE,
+    -- What failed, e.g. "wrong step-up code"
+    detail TEXT DEFAULT NULL,
+    created_ts INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_admin_auth_events_kind_created_ts ON admin_auth_events(kind, created_ts);
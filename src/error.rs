@@ -6,6 +6,9 @@ use axum::{
 use serde_json::json;
 use thiserror::Error;
 
+use crate::middleware::request_id::current_request_id;
+use crate::services::mailer::EmailError;
+
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error("Database error: {0}")]
@@ -26,11 +29,28 @@ pub enum AppError {
     #[error("Internal error: {0}")]
     Internal(String),
 
+    #[error("Too many requests: {0}")]
+    TooManyRequests(String),
+
     #[error("External service error: {0}")]
     ExternalService(String),
 
     #[error("Storage error: {0}")]
     Storage(String),
+
+    #[error(transparent)]
+    Email(#[from] EmailError),
+
+    #[error("Insufficient stock for {name}")]
+    InsufficientStock {
+        product_id: String,
+        style_id: Option<String>,
+        name: String,
+        available: i64,
+    },
+
+    #[error("Order subtotal is below the minimum")]
+    BelowMinimumOrder { minimum_cents: i32, subtotal_cents: i32 },
 }
 
 impl IntoResponse for AppError {
@@ -42,13 +62,56 @@ impl IntoResponse for AppError {
             AppError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg.as_str()),
             AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg.as_str()),
             AppError::Internal(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg.as_str()),
+            AppError::TooManyRequests(msg) => (StatusCode::TOO_MANY_REQUESTS, msg.as_str()),
             AppError::ExternalService(msg) => (StatusCode::BAD_GATEWAY, msg.as_str()),
             AppError::Storage(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg.as_str()),
+            AppError::Email(_) => (StatusCode::BAD_GATEWAY, "Failed to send email"),
+            AppError::InsufficientStock { .. } => (StatusCode::CONFLICT, ""),
+            AppError::BelowMinimumOrder { .. } => (StatusCode::BAD_REQUEST, ""),
         };
 
-        tracing::error!("Error response: {} - {}", status, self);
+        let request_id = current_request_id();
+        match &request_id {
+            Some(id) => tracing::error!("Error response: {} - {} (request {})", status, self, id),
+            None => tracing::error!("Error response: {} - {}", status, self),
+        }
+
+        // Stock conflicts carry enough detail for the cart to adjust quantities
+        let body = match &self {
+            AppError::InsufficientStock { product_id, style_id, name, available } => json!({
+                "error": format!("Insufficient stock for {}", name),
+                "code": "insufficient_stock",
+                "product_id": product_id,
+                "style_id": style_id,
+                "available": available,
+            }),
+            AppError::BelowMinimumOrder { minimum_cents, subtotal_cents } => {
+                let remaining_cents = minimum_cents - subtotal_cents;
+                json!({
+                    "error": format!(
+                        "Add ${:.2} more to reach the ${:.2} order minimum",
+                        remaining_cents as f64 / 100.0,
+                        *minimum_cents as f64 / 100.0
+                    ),
+                    "code": "below_minimum_order",
+                    "minimum_cents": minimum_cents,
+                    "subtotal_cents": subtotal_cents,
+                    "remaining_cents": remaining_cents,
+                })
+            }
+            _ => json!({ "error": message }),
+        };
+
+        // Customers can quote this when reporting a failure
+        let body = match (body, request_id) {
+            (serde_json::Value::Object(mut fields), Some(id)) => {
+                fields.insert("request_id".to_string(), json!(id));
+                serde_json::Value::Object(fields)
+            }
+            (body, _) => body,
+        };
 
-        (status, Json(json!({ "error": message }))).into_response()
+        (status, Json(body)).into_response()
     }
 }
 
@@ -0,0 +1,74 @@
+use libsql::Connection;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, AppResult};
+use crate::jobs::campaigns::featured_products;
+use crate::models::{NewsletterAnnouncement, NewsletterSegment, WebhookJob};
+use crate::routes::AppState;
+
+#[derive(Serialize, Deserialize)]
+struct AnnouncementJob {
+    announcement_id: String,
+}
+
+/// Queue the job that sends an announcement
+pub async fn queue_announcement(conn: &Connection, announcement_id: &str) -> AppResult<()> {
+    let payload = serde_json::to_string(&AnnouncementJob {
+        announcement_id: announcement_id.to_string(),
+    })
+    .map_err(|e| AppError::Internal(format!("Failed to serialize announcement job: {}", e)))?;
+
+    WebhookJob::enqueue(conn, WebhookJob::KIND_NEWSLETTER_ANNOUNCEMENT, &payload).await?;
+    Ok(())
+}
+
+/// Send a queued product announcement to its recipients. An announcement is only
+/// ever claimed for sending once.
+pub async fn send_announcement(state: &AppState, conn: &Connection, payload: &str) -> AppResult<()> {
+    let job: AnnouncementJob = serde_json::from_str(payload)
+        .map_err(|e| AppError::Internal(format!("Invalid announcement job: {}", e)))?;
+
+    let announcement = match NewsletterAnnouncement::find_by_id(conn, &job.announcement_id).await? {
+        Some(announcement) if announcement.status == "queued" => announcement,
+        _ => return Ok(()),
+    };
+
+    let resend = state.resend.as_ref().ok_or_else(|| {
+        AppError::Internal("Email not configured. Set RESEND_API_KEY or SMTP_PASS.".to_string())
+    })?;
+
+    // Resolve everything that can fail before claiming, so a claimed announcement always finishes
+    let subscribers = NewsletterSegment::recipients(conn, announcement.segment_id.as_deref()).await?;
+    let products = featured_products(state, conn, &announcement.product_ids).await?;
+
+    if !NewsletterAnnouncement::claim_for_sending(conn, &announcement.id, subscribers.len() as i32).await? {
+        return Ok(());
+    }
+
+    let sent_count = match (announcement.notify_type.as_str(), announcement.single_product, products.first()) {
+        (_, _, None) => 0,
+        ("new", true, Some((product, image_url))) => {
+            resend
+                .send_batch_new_product_notification(&subscribers, product, image_url.as_deref())
+                .await?
+        }
+        ("restock", true, Some((product, image_url))) => {
+            resend
+                .send_batch_back_in_stock_notification(&subscribers, product, image_url.as_deref())
+                .await?
+        }
+        ("new", false, _) => resend.send_batch_multi_product_new(&subscribers, &products).await?,
+        _ => resend.send_batch_multi_product_restock(&subscribers, &products).await?,
+    };
+
+    NewsletterAnnouncement::mark_sent(conn, &announcement.id, sent_count as i32).await?;
+    tracing::info!(
+        "Sent {} announcement {} to {} of {} subscribers",
+        announcement.notify_type,
+        announcement.id,
+        sent_count,
+        subscribers.len()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,86 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::error::{AppError, AppResult};
+use crate::models::NewsletterSubscriber;
+use crate::routes::AppState;
+
+/// How often the Resend Audience is reconciled with the subscriber table
+const AUDIENCE_SYNC_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// What a sync changed
+#[derive(Debug, Default, Serialize)]
+pub struct AudienceSyncSummary {
+    /// Confirmed subscribers added to the audience
+    pub added: usize,
+    /// Audience contacts removed because they are no longer subscribed here
+    pub removed: usize,
+    /// Subscribers removed here because they unsubscribed from a Resend broadcast
+    pub unsubscribed: usize,
+}
+
+/// Periodically reconcile the Resend Audience, catching anything the inline
+/// subscribe/unsubscribe updates missed
+pub fn spawn_audience_sync_job(state: AppState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(AUDIENCE_SYNC_INTERVAL);
+        loop {
+            interval.tick().await;
+            match run_audience_sync(&state).await {
+                Ok(summary) if summary.added + summary.removed + summary.unsubscribed == 0 => {}
+                Ok(summary) => tracing::info!(
+                    "Audience sync added {}, removed {}, unsubscribed {}",
+                    summary.added,
+                    summary.removed,
+                    summary.unsubscribed
+                ),
+                Err(e) => tracing::error!("Audience sync failed: {}", e),
+            }
+        }
+    });
+}
+
+/// Make the Resend Audience match the confirmed subscribers. Unsubscribes made from
+/// a Resend broadcast are applied here first; otherwise the local table wins.
+pub async fn run_audience_sync(state: &AppState) -> AppResult<AudienceSyncSummary> {
+    let mut summary = AudienceSyncSummary::default();
+
+    let resend = match &state.resend {
+        Some(resend) if resend.has_audience() => resend,
+        _ => return Ok(summary),
+    };
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let contacts = resend.list_contacts().await?;
+
+    for contact in contacts.iter().filter(|c| c.unsubscribed) {
+        if NewsletterSubscriber::unsubscribe_by_email(&conn, &contact.email).await? {
+            summary.unsubscribed += 1;
+        }
+    }
+
+    let subscribers = NewsletterSubscriber::get_all(&conn).await?;
+    let local: HashSet<String> = subscribers.iter().map(|s| s.email.to_lowercase()).collect();
+    let mut remote = HashSet::new();
+
+    for contact in &contacts {
+        let email = contact.email.to_lowercase();
+        if contact.unsubscribed {
+            resend.remove_contact(&contact.email).await?;
+        } else if !local.contains(&email) {
+            resend.remove_contact(&contact.email).await?;
+            summary.removed += 1;
+        } else {
+            remote.insert(email);
+        }
+    }
+
+    for subscriber in subscribers.iter().filter(|s| !remote.contains(&s.email.to_lowercase())) {
+        resend.add_contact(&subscriber.email).await?;
+        summary.added += 1;
+    }
+
+    Ok(summary)
+}
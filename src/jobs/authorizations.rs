@@ -0,0 +1,56 @@
+use std::time::Duration;
+
+use crate::error::{AppError, AppResult};
+use crate::models::Order;
+use crate::routes::webhooks::void_authorized_order;
+use crate::routes::AppState;
+
+/// How often uncaptured authorizations are checked
+const AUTHORIZATION_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+const SECONDS_PER_HOUR: i64 = 60 * 60;
+
+/// Periodically release authorizations that were never captured, before the card hold lapses
+pub fn spawn_authorization_expiry_job(state: AppState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(AUTHORIZATION_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            match run_authorization_expiry(&state).await {
+                Ok(0) => {}
+                Ok(count) => tracing::info!("Cancelled {} expired payment authorizations", count),
+                Err(e) => tracing::error!("Authorization expiry failed: {}", e),
+            }
+        }
+    });
+}
+
+/// Cancel the PaymentIntent and the order for every authorization older than the
+/// configured window. Returns the number of orders cancelled.
+pub async fn run_authorization_expiry(state: &AppState) -> AppResult<u64> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    let cutoff_ts = now - state.config.stripe_authorization_hours * SECONDS_PER_HOUR;
+
+    let mut cancelled = 0;
+    for order in Order::list_expired_authorizations(&conn, cutoff_ts).await? {
+        if let Some(ref payment_intent_id) = order.stripe_payment_intent_id {
+            // Leave the order alone if Stripe still holds the funds; try again next run
+            if let Err(e) = state.stripe.cancel_payment_intent(payment_intent_id).await {
+                tracing::error!("Failed to cancel authorization for order {}: {}", order.id, e);
+                continue;
+            }
+        }
+
+        match void_authorized_order(&conn, &order.id, "authorization_expired").await {
+            Ok(()) => cancelled += 1,
+            Err(e) => tracing::error!("Failed to cancel order {} after authorization expiry: {}", order.id, e),
+        }
+    }
+
+    Ok(cancelled)
+}
@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+
+use libsql::Connection;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, AppResult};
+use crate::models::{NewsletterCampaign, NewsletterLink, NewsletterSegment, Product, ProductImage, WebhookJob};
+use crate::routes::AppState;
+use crate::services::i18n::DEFAULT_LOCALE;
+use crate::services::resend::{markdown_to_html, trackable_links};
+
+#[derive(Serialize, Deserialize)]
+struct CampaignJob {
+    campaign_id: String,
+    /// The send time this job was queued for; a rescheduled campaign ignores older jobs
+    scheduled_ts: i64,
+}
+
+/// The campaign body as HTML
+pub fn campaign_body_html(body: &str, body_format: &str) -> String {
+    match body_format {
+        "markdown" => markdown_to_html(body),
+        _ => body.to_string(),
+    }
+}
+
+/// Featured products with their first image, skipping any that no longer exist
+pub async fn featured_products(
+    state: &AppState,
+    conn: &Connection,
+    product_ids: &[String],
+) -> AppResult<Vec<(Product, Option<String>)>> {
+    let mut products = Vec::new();
+    for product_id in product_ids {
+        if let Some(product) = Product::find_by_id(conn, product_id).await? {
+            let images = ProductImage::list_by_product(conn, product_id).await?;
+            let first_image_url = images.first().map(|img| state.storage.public_url(&img.image_path));
+            products.push((product, first_image_url));
+        }
+    }
+    Ok(products)
+}
+
+/// Schedule a campaign and queue the job that sends it at `scheduled_ts`
+pub async fn schedule_campaign(conn: &Connection, campaign_id: &str, scheduled_ts: i64) -> AppResult<()> {
+    if !NewsletterCampaign::schedule(conn, campaign_id, scheduled_ts).await? {
+        return Err(AppError::BadRequest("Only draft or scheduled campaigns can be scheduled".to_string()));
+    }
+
+    let payload = serde_json::to_string(&CampaignJob {
+        campaign_id: campaign_id.to_string(),
+        scheduled_ts,
+    })
+    .map_err(|e| AppError::Internal(format!("Failed to serialize campaign job: {}", e)))?;
+
+    WebhookJob::enqueue_at(conn, WebhookJob::KIND_NEWSLETTER_CAMPAIGN, &payload, scheduled_ts).await?;
+    Ok(())
+}
+
+/// Send a scheduled campaign to its recipients. Cancelled or rescheduled campaigns
+/// are skipped, and a campaign is only ever claimed for sending once.
+pub async fn send_scheduled_campaign(state: &AppState, conn: &Connection, payload: &str) -> AppResult<()> {
+    let job: CampaignJob = serde_json::from_str(payload)
+        .map_err(|e| AppError::Internal(format!("Invalid campaign job: {}", e)))?;
+
+    let campaign = match NewsletterCampaign::find_by_id(conn, &job.campaign_id).await? {
+        Some(campaign) if campaign.status == "scheduled" && campaign.scheduled_ts == Some(job.scheduled_ts) => campaign,
+        _ => return Ok(()),
+    };
+
+    let resend = state.resend.as_ref().ok_or_else(|| {
+        AppError::Internal("Email not configured. Set RESEND_API_KEY or SMTP_PASS.".to_string())
+    })?;
+
+    // Resolve everything that can fail before claiming, so a claimed campaign always finishes
+    let subscribers: Vec<_> = NewsletterSegment::recipients(conn, campaign.segment_id.as_deref())
+        .await?
+        .into_iter()
+        .filter(|s| s.wants(&campaign.category))
+        .collect();
+    let products = featured_products(state, conn, &campaign.product_ids).await?;
+    let body_html = campaign_body_html(&campaign.body, &campaign.body_format);
+
+    // One tracked link per distinct URL, shared by every subscriber's copy
+    let mut links = HashMap::new();
+    let branding = resend.branding().await;
+    for href in trackable_links(&resend.render_campaign(&body_html, &products, "", &branding, DEFAULT_LOCALE)) {
+        let url = href.replace("&amp;", "&");
+        let link = NewsletterLink::get_or_create(conn, &campaign.id, &url).await?;
+        links.insert(href, link.id);
+    }
+
+    if !NewsletterCampaign::claim_for_sending(conn, &campaign.id, job.scheduled_ts).await? {
+        return Ok(());
+    }
+
+    let sent_count = resend
+        .send_batch_campaign(&campaign, &subscribers, &body_html, &products, &links)
+        .await?;
+
+    NewsletterCampaign::mark_sent(conn, &campaign.id, subscribers.len() as i32, sent_count as i32).await?;
+    tracing::info!(
+        "Sent campaign {} to {} of {} subscribers",
+        campaign.id,
+        sent_count,
+        subscribers.len()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,69 @@
+use std::time::Duration;
+
+use crate::error::{AppError, AppResult};
+use crate::models::{Order, Setting};
+use crate::routes::AppState;
+
+/// How often stale carts are swept
+const CART_CLEANUP_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+const SECONDS_PER_HOUR: i64 = 60 * 60;
+
+/// Periodically cancel abandoned pending orders so their stock goes back on sale
+pub fn spawn_cart_cleanup_job(state: AppState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CART_CLEANUP_INTERVAL);
+        loop {
+            interval.tick().await;
+            match run_cart_cleanup(&state).await {
+                Ok(0) => {}
+                Ok(count) => tracing::info!("Cart cleanup cancelled {} abandoned orders", count),
+                Err(e) => tracing::error!("Cart cleanup failed: {}", e),
+            }
+        }
+    });
+}
+
+/// Cancel pending orders whose stock reservation has outlived the configured TTL.
+/// Returns the number of orders cancelled.
+pub async fn run_cart_cleanup(state: &AppState) -> AppResult<u64> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+
+    let ttl_hours = Setting::get_cart_ttl_hours(&conn).await?;
+    if ttl_hours <= 0 {
+        return Ok(0);
+    }
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+
+    let stale = Order::list_stale_pending_ids(&conn, now, now - ttl_hours * SECONDS_PER_HOUR).await?;
+
+    let mut cancelled = 0;
+    for (order_id, payment_intent_id) in stale {
+        // Stop the customer paying for an order that's about to be cancelled.
+        // Simulated payments have no intent in Stripe to cancel.
+        if let Some(ref payment_intent_id) = payment_intent_id.filter(|_| state.mock_payments.is_none()) {
+            match state.stripe.cancel_unpaid_payment_intent(payment_intent_id).await {
+                Ok(true) => {}
+                // Paid after all; the webhook will mark the order
+                Ok(false) => continue,
+                Err(e) => {
+                    tracing::error!("Failed to cancel payment intent for abandoned order {}: {}", order_id, e);
+                    continue;
+                }
+            }
+        }
+
+        // The order may have been paid or cancelled since it was listed
+        match Order::cancel_abandoned(&conn, &order_id).await {
+            Ok(_) => cancelled += 1,
+            Err(AppError::BadRequest(_)) | Err(AppError::NotFound(_)) => {}
+            Err(e) => tracing::error!("Failed to cancel abandoned order {}: {}", order_id, e),
+        }
+    }
+
+    Ok(cancelled)
+}
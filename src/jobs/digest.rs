@@ -0,0 +1,72 @@
+use std::time::Duration;
+
+use crate::error::{AppError, AppResult};
+use crate::jobs::campaigns::featured_products;
+use crate::models::{NewsletterSubscriber, Product, Setting};
+use crate::routes::AppState;
+
+/// How often the job checks whether the digest is due
+const DIGEST_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+const SECONDS_PER_WEEK: i64 = 7 * 24 * 60 * 60;
+
+/// Check hourly and send the weekly new-arrivals digest on its configured day
+pub fn spawn_weekly_digest_job(state: AppState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(DIGEST_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            match run_weekly_digest(&state).await {
+                Ok(0) => {}
+                Ok(count) => tracing::info!("Sent weekly digest to {} subscribers", count),
+                Err(e) => tracing::error!("Weekly digest failed: {}", e),
+            }
+        }
+    });
+}
+
+/// Email subscribers one digest of the products added in the past week. Runs at most
+/// once per send day; weeks without new products are skipped. Returns the number sent.
+pub async fn run_weekly_digest(state: &AppState) -> AppResult<usize> {
+    let resend = match &state.resend {
+        Some(resend) => resend,
+        None => return Ok(0),
+    };
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let digest = Setting::get_weekly_digest(&conn).await?;
+    let today = chrono::Utc::now().format("%A").to_string().to_lowercase();
+    if !digest.enabled || today != digest.send_day {
+        return Ok(0);
+    }
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+
+    // Anything sent in the last six days was today's digest (or a send day that moved)
+    let last_sent_ts: i64 = Setting::get(&conn, "weekly_digest_last_sent_ts")
+        .await?
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    if now - last_sent_ts < SECONDS_PER_WEEK - 24 * 60 * 60 {
+        return Ok(0);
+    }
+
+    // Claim this week before sending so a slow send is never repeated
+    Setting::set(&conn, "weekly_digest_last_sent_ts", &now.to_string()).await?;
+
+    let product_ids: Vec<String> = Product::list_created_since(&conn, now - SECONDS_PER_WEEK)
+        .await?
+        .into_iter()
+        .map(|p| p.id)
+        .collect();
+    if product_ids.is_empty() {
+        return Ok(0);
+    }
+
+    let products = featured_products(state, &conn, &product_ids).await?;
+    let subscribers = NewsletterSubscriber::get_all(&conn).await?;
+    resend.send_batch_multi_product_new(&subscribers, &products).await
+}
@@ -0,0 +1,17 @@
+pub mod announcements;
+pub mod audience;
+pub mod authorizations;
+pub mod campaigns;
+pub mod cart_cleanup;
+pub mod digest;
+pub mod retention;
+pub mod webhooks;
+pub mod win_back;
+
+pub use audience::spawn_audience_sync_job;
+pub use authorizations::spawn_authorization_expiry_job;
+pub use cart_cleanup::spawn_cart_cleanup_job;
+pub use digest::spawn_weekly_digest_job;
+pub use retention::spawn_retention_job;
+pub use webhooks::spawn_webhook_worker;
+pub use win_back::spawn_win_back_job;
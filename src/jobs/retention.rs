@@ -0,0 +1,44 @@
+use libsql::Database;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::error::{AppError, AppResult};
+use crate::models::{Order, Setting};
+
+/// How often the retention sweep runs
+const RETENTION_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+const SECONDS_PER_YEAR: i64 = 365 * 24 * 60 * 60;
+
+/// Run the order retention sweep once a day in the background
+pub fn spawn_retention_job(db: Arc<Database>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(RETENTION_INTERVAL);
+        loop {
+            interval.tick().await;
+            match run_retention(&db).await {
+                Ok(0) => {}
+                Ok(count) => tracing::info!("Retention job archived {} orders", count),
+                Err(e) => tracing::error!("Retention job failed: {}", e),
+            }
+        }
+    });
+}
+
+/// Archive and anonymize orders older than the configured retention period.
+/// Returns the number of orders archived.
+pub async fn run_retention(db: &Database) -> AppResult<u64> {
+    let conn = db.connect().map_err(AppError::from)?;
+
+    let years = Setting::get_order_retention_years(&conn).await?;
+    if years <= 0 {
+        return Ok(0);
+    }
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+
+    Order::archive_before(&conn, now - years * SECONDS_PER_YEAR).await
+}
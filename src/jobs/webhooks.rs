@@ -0,0 +1,276 @@
+use libsql::Connection;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::error::{AppError, AppResult};
+use crate::jobs::announcements::send_announcement;
+use crate::jobs::campaigns::{featured_products, send_scheduled_campaign};
+use crate::models::{Order, Product, Setting, User, WebhookJob};
+use crate::routes::webhooks::{process_shippo_event, process_stripe_event};
+use crate::routes::AppState;
+
+/// How often the queue is polled for due jobs
+const WEBHOOK_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Jobs handled per poll
+const WEBHOOK_BATCH_SIZE: i64 = 20;
+
+/// How often finished jobs are purged, and how long they're kept
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const DONE_JOB_RETENTION_SECS: i64 = 30 * 24 * 60 * 60;
+
+/// "Customers also bought" picks shown in order confirmation and delivered emails
+const ALSO_BOUGHT_COUNT: i64 = 3;
+
+/// Customer emails sent from webhook side effects
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderEmail {
+    Confirmation,
+    Refund,
+    Delivered,
+    /// Sent to admins rather than the customer
+    RefundFailed,
+    /// Sent to admins when an order is paid
+    NewOrderAlert,
+}
+
+#[derive(Serialize, Deserialize)]
+struct EmailJob {
+    email: OrderEmail,
+    order_id: String,
+    #[serde(default)]
+    reason: Option<String>,
+}
+
+/// Queue a customer email for an order. Does nothing when email isn't configured
+/// or the order has no customer (e.g. archived).
+pub async fn enqueue_order_email(
+    state: &AppState,
+    conn: &Connection,
+    email: OrderEmail,
+    order: &Order,
+) -> AppResult<()> {
+    if state.email.is_none() || order.user_id.is_none() {
+        return Ok(());
+    }
+
+    let payload = serde_json::to_string(&EmailJob {
+        email,
+        order_id: order.id.clone(),
+        reason: None,
+    })
+    .map_err(|e| AppError::Internal(format!("Failed to serialize email job: {}", e)))?;
+
+    WebhookJob::enqueue(conn, WebhookJob::KIND_EMAIL, &payload).await?;
+    Ok(())
+}
+
+/// Queue an alert to every admin that an order's refund failed
+pub async fn enqueue_refund_failed_alert(
+    state: &AppState,
+    conn: &Connection,
+    order: &Order,
+    reason: Option<&str>,
+) -> AppResult<()> {
+    if state.email.is_none() {
+        return Ok(());
+    }
+
+    let payload = serde_json::to_string(&EmailJob {
+        email: OrderEmail::RefundFailed,
+        order_id: order.id.clone(),
+        reason: reason.map(|r| r.to_string()),
+    })
+    .map_err(|e| AppError::Internal(format!("Failed to serialize email job: {}", e)))?;
+
+    WebhookJob::enqueue(conn, WebhookJob::KIND_EMAIL, &payload).await?;
+    Ok(())
+}
+
+/// Queue a new-order email to every admin, if order alerts are turned on in settings
+pub async fn enqueue_new_order_alert(state: &AppState, conn: &Connection, order: &Order) -> AppResult<()> {
+    if state.email.is_none() || !Setting::get_order_alerts(conn).await?.enabled {
+        return Ok(());
+    }
+
+    let payload = serde_json::to_string(&EmailJob {
+        email: OrderEmail::NewOrderAlert,
+        order_id: order.id.clone(),
+        reason: None,
+    })
+    .map_err(|e| AppError::Internal(format!("Failed to serialize email job: {}", e)))?;
+
+    WebhookJob::enqueue(conn, WebhookJob::KIND_EMAIL, &payload).await?;
+    Ok(())
+}
+
+/// Process queued webhook side effects in the background
+pub fn spawn_webhook_worker(state: AppState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(WEBHOOK_POLL_INTERVAL);
+        let mut last_purge = std::time::Instant::now();
+        loop {
+            interval.tick().await;
+            if let Err(e) = run_due_webhook_jobs(&state).await {
+                tracing::error!("Webhook worker failed: {}", e);
+            }
+
+            if last_purge.elapsed() >= PURGE_INTERVAL {
+                last_purge = std::time::Instant::now();
+                if let Err(e) = purge_done_jobs(&state).await {
+                    tracing::error!("Failed to purge finished webhook jobs: {}", e);
+                }
+            }
+        }
+    });
+}
+
+/// Run every job that is due. Returns the number that completed.
+pub async fn run_due_webhook_jobs(state: &AppState) -> AppResult<u64> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let mut completed = 0;
+
+    for job in WebhookJob::list_due(&conn, WEBHOOK_BATCH_SIZE).await? {
+        if !WebhookJob::claim(&conn, &job.id).await? {
+            continue;
+        }
+
+        match run_job(state, &conn, &job).await {
+            Ok(()) => {
+                WebhookJob::complete(&conn, &job.id).await?;
+                completed += 1;
+            }
+            // Retrying won't fix a rejected address or a malformed email
+            Err(AppError::Email(e)) if !e.is_transient() => {
+                WebhookJob::fail_permanently(&conn, &job, &e.to_string()).await?;
+                tracing::error!("Webhook job {} ({}) failed permanently: {}", job.id, job.kind, e);
+            }
+            Err(e) => {
+                let status = WebhookJob::fail(&conn, &job, &e.to_string()).await?;
+                if status == "dead" {
+                    tracing::error!("Webhook job {} ({}) gave up after {} attempts: {}", job.id, job.kind, job.attempts + 1, e);
+                } else {
+                    tracing::warn!("Webhook job {} ({}) failed, will retry: {}", job.id, job.kind, e);
+                }
+            }
+        }
+    }
+
+    Ok(completed)
+}
+
+async fn purge_done_jobs(state: &AppState) -> AppResult<u64> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    WebhookJob::purge_done_before(&conn, now - DONE_JOB_RETENTION_SECS).await
+}
+
+async fn run_job(state: &AppState, conn: &Connection, job: &WebhookJob) -> AppResult<()> {
+    match job.kind.as_str() {
+        WebhookJob::KIND_STRIPE_EVENT => process_stripe_event(state, conn, &job.payload).await,
+        WebhookJob::KIND_SHIPPO_EVENT => process_shippo_event(state, conn, &job.payload).await,
+        WebhookJob::KIND_EMAIL => send_order_email(state, conn, &job.payload).await,
+        WebhookJob::KIND_NEWSLETTER_CAMPAIGN => send_scheduled_campaign(state, conn, &job.payload).await,
+        WebhookJob::KIND_NEWSLETTER_ANNOUNCEMENT => send_announcement(state, conn, &job.payload).await,
+        other => Err(AppError::Internal(format!("Unknown webhook job kind: {}", other))),
+    }
+}
+
+async fn send_order_email(state: &AppState, conn: &Connection, payload: &str) -> AppResult<()> {
+    let job: EmailJob = serde_json::from_str(payload)
+        .map_err(|e| AppError::Internal(format!("Invalid email job: {}", e)))?;
+
+    let email_service = match state.email {
+        Some(ref email_service) => email_service,
+        None => return Ok(()),
+    };
+
+    let order = match Order::find_by_id(conn, &job.order_id).await? {
+        Some(order) => order,
+        None => {
+            tracing::warn!("Order {} no longer exists, dropping {:?} email", job.order_id, job.email);
+            return Ok(());
+        }
+    };
+
+    if let OrderEmail::RefundFailed = job.email {
+        let mut result = Ok(());
+        for admin in User::list_admins(conn).await? {
+            if let Err(e) = email_service
+                .send_refund_failed_alert(&admin.email, &order, job.reason.as_deref())
+                .await
+            {
+                tracing::error!("Failed to send refund alert to {}: {}", admin.email, e);
+                result = Err(e);
+            }
+        }
+        return result;
+    }
+
+    if let OrderEmail::NewOrderAlert = job.email {
+        let pick_list = if Setting::get_order_alerts(conn).await?.include_pick_list {
+            Order::get_items_for_orders(conn, std::slice::from_ref(&order.id))
+                .await?
+                .remove(&order.id)
+        } else {
+            None
+        };
+
+        let mut result = Ok(());
+        for admin in User::list_admins(conn).await? {
+            if let Err(e) = email_service
+                .send_new_order_alert(&admin.email, &order, pick_list.as_deref())
+                .await
+            {
+                tracing::error!("Failed to send new order alert to {}: {}", admin.email, e);
+                result = Err(e);
+            }
+        }
+        return result;
+    }
+
+    let user = match order.user_id {
+        Some(ref user_id) => User::find_by_id(conn, user_id).await?,
+        None => None,
+    };
+    let user = match user {
+        Some(user) => user,
+        None => return Ok(()),
+    };
+
+    let name = user.name.as_deref().unwrap_or("Customer");
+    let locale = user.email_locale();
+    match job.email {
+        OrderEmail::Confirmation => {
+            let recommendations = also_bought(state, conn, &order.id).await;
+            email_service.send_order_confirmation(&user.email, &order, name, &recommendations, locale).await
+        }
+        OrderEmail::Refund => email_service.send_refund_confirmation(&user.email, &order, name, locale).await,
+        OrderEmail::Delivered => {
+            let recommendations = also_bought(state, conn, &order.id).await;
+            email_service.send_order_delivered(&user.email, &order, name, &recommendations, locale).await
+        }
+        OrderEmail::RefundFailed | OrderEmail::NewOrderAlert => Ok(()),
+    }
+}
+
+/// Products often bought with this order's items, with their first image. Errors
+/// only drop the block, never the email.
+async fn also_bought(state: &AppState, conn: &Connection, order_id: &str) -> Vec<(Product, Option<String>)> {
+    let picks = match Product::list_bought_with(conn, order_id, ALSO_BOUGHT_COUNT).await {
+        Ok(products) => products,
+        Err(e) => {
+            tracing::warn!("Failed to load also-bought products for order {}: {}", order_id, e);
+            return Vec::new();
+        }
+    };
+    let product_ids: Vec<String> = picks.into_iter().map(|p| p.id).collect();
+    featured_products(state, conn, &product_ids).await.unwrap_or_else(|e| {
+        tracing::warn!("Failed to load also-bought images for order {}: {}", order_id, e);
+        Vec::new()
+    })
+}
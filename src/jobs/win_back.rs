@@ -0,0 +1,125 @@
+use std::time::Duration;
+
+use uuid::Uuid;
+
+use crate::error::{AppError, AppResult};
+use crate::jobs::campaigns::featured_products;
+use crate::models::{
+    CreateDiscountCode, DiscountCode, EmailSuppression, NewsletterSubscriber, Order, Product, Setting, WinBackEmail,
+};
+use crate::routes::AppState;
+
+/// How often lapsed customers are looked for
+const WIN_BACK_CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Most win-back emails sent in one run; the rest go out on the next
+const WIN_BACK_MAX_PER_RUN: usize = 200;
+
+/// New pieces shown in each email
+const WIN_BACK_PRODUCTS: usize = 4;
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Daily, email customers who haven't ordered in the configured number of months
+pub fn spawn_win_back_job(state: AppState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(WIN_BACK_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            match run_win_back(&state).await {
+                Ok(0) => {}
+                Ok(count) => tracing::info!("Sent {} win-back emails", count),
+                Err(e) => tracing::error!("Win-back run failed: {}", e),
+            }
+        }
+    });
+}
+
+/// Send one win-back email per lapse to customers whose last paid order is older
+/// than the configured window. Only confirmed subscribers opted into new drops are
+/// emailed, and only when pieces were added since their last order; the selection
+/// favors categories they bought before. Returns the number sent.
+pub async fn run_win_back(state: &AppState) -> AppResult<usize> {
+    let resend = match &state.resend {
+        Some(resend) => resend,
+        None => return Ok(0),
+    };
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let settings = Setting::get_win_back(&conn).await?;
+    if !settings.enabled || settings.inactive_months <= 0 {
+        return Ok(0);
+    }
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    let cutoff_ts = now - settings.inactive_months * 30 * SECONDS_PER_DAY;
+
+    let suppressed = EmailSuppression::emails(&conn).await?;
+    let mut sent_count = 0;
+
+    for customer in WinBackEmail::lapsed_customers(&conn, cutoff_ts).await? {
+        if sent_count >= WIN_BACK_MAX_PER_RUN {
+            break;
+        }
+        if suppressed.contains(&customer.email) {
+            continue;
+        }
+        let subscriber = match NewsletterSubscriber::find_by_email(&conn, &customer.email).await? {
+            Some(subscriber) if subscriber.is_confirmed() && subscriber.wants("new_drops") => subscriber,
+            _ => continue,
+        };
+
+        // New in-stock pieces, the customer's favorite categories first
+        let categories = Order::purchased_categories(&conn, &customer.user_id).await?;
+        let mut new_products: Vec<Product> = Product::list_created_since(&conn, customer.last_order_ts)
+            .await?
+            .into_iter()
+            .filter(|p| p.stock_quantity > 0 && !p.is_subscription())
+            .collect();
+        if new_products.is_empty() {
+            continue;
+        }
+        new_products.sort_by_key(|p| {
+            p.category
+                .as_ref()
+                .and_then(|c| categories.iter().position(|bought| bought == c))
+                .unwrap_or(categories.len())
+        });
+        let product_ids: Vec<String> = new_products.into_iter().take(WIN_BACK_PRODUCTS).map(|p| p.id).collect();
+        let products = featured_products(state, &conn, &product_ids).await?;
+
+        let discount = match settings.discount_percent {
+            Some(percent) => Some(
+                DiscountCode::create(
+                    &conn,
+                    CreateDiscountCode {
+                        code: format!("COMEBACK-{}", &Uuid::new_v4().simple().to_string()[..8]),
+                        percent_off: Some(percent),
+                        amount_off_cents: None,
+                        min_subtotal_cents: 0,
+                        max_redemptions: Some(1),
+                        expires_ts: Some(now + settings.discount_valid_days * SECONDS_PER_DAY),
+                    },
+                )
+                .await?,
+            ),
+            None => None,
+        };
+
+        if let Err(e) = resend.send_win_back_email(&subscriber, &products, discount.as_ref()).await {
+            tracing::error!("Failed to send win-back email to {}: {}", customer.email, e);
+            if let Some(code) = &discount {
+                DiscountCode::set_active(&conn, &code.id, false).await?;
+            }
+            continue;
+        }
+
+        WinBackEmail::record(&conn, &customer, discount.as_ref().map(|c| c.code.as_str()), products.len() as i32).await?;
+        sent_count += 1;
+    }
+
+    Ok(sent_count)
+}
@@ -1,19 +1,27 @@
 mod config;
 mod db;
 mod error;
+mod jobs;
 mod middleware;
 mod models;
 mod routes;
 mod services;
 mod storage;
 
+use std::net::SocketAddr;
 use std::sync::Arc;
 use tokio::net::TcpListener;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 use crate::config::Config;
 use crate::routes::{create_router, AppState};
-use crate::services::{ClerkService, EmailService, JwksVerifier, RateLimiter, ResendService, ShippoService, StripeService};
+use crate::services::auth_provider::AUTH_PROVIDERS;
+use crate::services::mailer::MAIL_PROVIDERS;
+use crate::services::{
+    AuthProvider, ClerkAuthProvider, ClerkService, EmailService, JwksVerifier, JwtAuthProvider, Mailer,
+    MockPaymentProvider, RateLimiter, ResendMailer, ResendService, ShippoService, SmtpMailer, StripeService,
+    TokenService,
+};
 use crate::storage::{LocalStorage, R2Storage, StorageBackend};
 
 #[tokio::main]
@@ -57,13 +65,22 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let db = db::create_database(&config.database_url, config.turso_auth_token.as_deref())
         .await
         .expect("Failed to create database");
+    let db = Arc::new(db);
     eprintln!("Database connected");
 
     tracing::info!("Connected to database");
 
     // Initialize services
-    let clerk = ClerkService::new(&config.clerk_secret_key);
-    let jwks = JwksVerifier::new(&config.clerk_jwks_url);
+    let clerk = ClerkService::new(&config.clerk_secret_key, config.clerk_webhook_secret.clone());
+    let jwks = match config.auth_provider.as_str() {
+        "clerk" => JwksVerifier::new(&config.clerk_jwks_url, None, None),
+        "jwt" => JwksVerifier::new(
+            config.auth_jwks_url.as_deref().expect("AUTH_JWKS_URL must be set when AUTH_PROVIDER=jwt"),
+            config.auth_issuer.clone(),
+            config.auth_audience.clone(),
+        ),
+        other => panic!("AUTH_PROVIDER={} is not one of: {}", other, AUTH_PROVIDERS.join(", ")),
+    };
 
     // Initialize JWKS cache (fetch keys on startup)
     if let Err(e) = jwks.initialize().await {
@@ -72,49 +89,82 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         tracing::info!("JWKS cache initialized");
     }
 
-    let stripe = StripeService::new(&config.stripe_secret_key, &config.stripe_webhook_secret);
+    let auth: Arc<dyn AuthProvider> = if config.auth_provider == "jwt" {
+        Arc::new(JwtAuthProvider::new(jwks))
+    } else {
+        Arc::new(ClerkAuthProvider::new(clerk.clone(), jwks))
+    };
+    tracing::info!("Signing users in with {}", auth.name());
+
+    let stripe = StripeService::new(
+        &config.stripe_secret_key,
+        &config.stripe_webhook_secret,
+        config.stripe_payment_method_types.clone(),
+        config.stripe_bnpl_methods.clone(),
+        config.stripe_manual_capture,
+    );
     let shippo = ShippoService::new(&config.shippo_api_key);
 
-    // Initialize Upstash rate limiter if configured
+    // Use Upstash for rate limits shared across instances; otherwise limit in-process
     let rate_limiter = match &config.upstash_redis_url {
         Some(url) => {
-            match RateLimiter::new(url, config.rate_limit_general) {
+            match RateLimiter::new(url, config.rate_limit_general, config.rate_limit_burst_percent) {
                 Ok(limiter) => {
                     tracing::info!("Upstash Redis rate limiter configured");
-                    Some(limiter)
+                    limiter
                 }
                 Err(e) => {
-                    tracing::error!("Failed to initialize rate limiter: {} - rate limiting disabled", e);
-                    None
+                    tracing::error!("Failed to initialize rate limiter: {} - using in-process limits", e);
+                    RateLimiter::local(config.rate_limit_general, config.rate_limit_burst_percent)
                 }
             }
         }
         None => {
-            tracing::warn!("Upstash Redis not configured - rate limiting disabled");
-            None
+            tracing::warn!("Upstash Redis not configured - using in-process rate limits");
+            RateLimiter::local(config.rate_limit_general, config.rate_limit_burst_percent)
         }
     };
 
-    let email = match EmailService::new(
-        &config.smtp_host,
-        &config.smtp_user,
-        &config.smtp_pass,
-        &config.from_email,
-    ) {
-        Ok(service) => {
-            tracing::info!("Email service initialized");
-            Some(service)
+    // One mailer delivers every email; the services below only render templates
+    let mailer: Option<Arc<dyn Mailer>> = match (config.mail_provider.as_str(), &config.resend_api_key, &config.smtp_pass) {
+        (other, _, _) if !MAIL_PROVIDERS.contains(&other) => {
+            panic!("MAIL_PROVIDER={} is not one of: {}", other, MAIL_PROVIDERS.join(", "))
+        }
+        ("resend", Some(api_key), _) => {
+            tracing::info!("Sending email through the Resend API");
+            Some(Arc::new(ResendMailer::new(api_key, &config.from_email)))
         }
-        Err(e) => {
-            tracing::warn!("Email service not available: {}", e);
+        ("smtp", _, Some(smtp_pass)) => {
+            match SmtpMailer::new(&config.smtp_host, &config.smtp_user, smtp_pass, &config.from_email) {
+                Ok(mailer) => {
+                    tracing::info!("Sending email over SMTP ({})", config.smtp_host);
+                    Some(Arc::new(mailer))
+                }
+                Err(e) => {
+                    tracing::warn!("Email not available: {}", e);
+                    None
+                }
+            }
+        }
+        (provider, _, _) => {
+            tracing::warn!(
+                "Email not available: MAIL_PROVIDER={} needs RESEND_API_KEY (resend) or SMTP_PASS (smtp)",
+                provider
+            );
             None
         }
     };
 
-    // Initialize Resend service for newsletters
-    let resend = config.resend_api_key.as_ref().map(|api_key| {
-        tracing::info!("Resend newsletter service initialized");
-        ResendService::new(api_key, &config.from_email, &config.base_url)
+    let email = mailer.clone().map(|mailer| EmailService::new(mailer, db.clone(), &config.base_url));
+    let resend = mailer.map(|mailer| {
+        ResendService::new(
+            mailer,
+            config.resend_api_key.clone(),
+            &config.base_url,
+            config.resend_audience_id.clone(),
+            config.resend_webhook_secret.clone(),
+            db.clone(),
+        )
     });
 
     // Initialize storage
@@ -139,20 +189,49 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         Arc::new(local)
     };
 
+    // Guest and label links are signed; without a configured secret they only
+    // work on this instance until it restarts
+    let tokens = match &config.access_token_secret {
+        Some(secret) => TokenService::new(secret.as_bytes()),
+        None => {
+            tracing::warn!("ACCESS_TOKEN_SECRET not set - guest order and label links won't survive a restart");
+            let secret = format!("{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple());
+            TokenService::new(secret.as_bytes())
+        }
+    };
+
+    // Local testing mode simulates payments so no Stripe account is needed
+    let mock_payments = if config.testing_mode && !config.deploy_mode.is_cloud() {
+        Some(MockPaymentProvider::new(&config.base_url))
+    } else {
+        None
+    };
+
     // Create app state
     let state = AppState {
-        db: Arc::new(db),
+        db,
         config: config.clone(),
+        auth,
         clerk,
-        jwks,
         stripe,
         shippo,
         email,
         resend,
         storage,
         rate_limiter,
+        tokens,
+        mock_payments,
     };
 
+    // Start background jobs
+    jobs::spawn_retention_job(state.db.clone());
+    jobs::spawn_cart_cleanup_job(state.clone());
+    jobs::spawn_webhook_worker(state.clone());
+    jobs::spawn_authorization_expiry_job(state.clone());
+    jobs::spawn_audience_sync_job(state.clone());
+    jobs::spawn_weekly_digest_job(state.clone());
+    jobs::spawn_win_back_job(state.clone());
+
     // Create router
     let app = create_router(state);
 
@@ -163,7 +242,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tracing::info!("Server listening on {}", addr);
     tracing::info!("Admin panel: {}/gallium/", config.base_url);
 
-    axum::serve(listener, app).await?;
+    // Peer addresses are needed for ADMIN_IP_ALLOWLIST when not behind a proxy
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await?;
 
     Ok(())
 }
@@ -0,0 +1,140 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::{MatchedPath, RawPathParams, Request, State},
+    http::{header, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json, RequestPartsExt,
+};
+use serde_json::{json, Value};
+
+use crate::error::AppError;
+use crate::middleware::AuthUser;
+use crate::models::{AuditLogEntry, NewAuditLogEntry};
+use crate::routes::AppState;
+
+/// Largest JSON body summarized; bigger ones are logged without a summary
+const MAX_AUDITED_BODY: usize = 64 * 1024;
+
+/// Longest summary stored, and longest single value in it
+const MAX_SUMMARY_LEN: usize = 500;
+const MAX_VALUE_LEN: usize = 60;
+
+/// Body fields whose values are never written to the log
+const REDACTED_FIELDS: &[&str] = &["password", "secret", "token", "key"];
+
+/// Record every admin API request that changes something: who, which route and
+/// target, the fields sent, and the response status (403s included)
+pub async fn audit_middleware(State(state): State<AppState>, req: Request<Body>, next: Next) -> Response {
+    if matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS) {
+        return next.run(req).await;
+    }
+
+    let (mut parts, body) = req.into_parts();
+    let method = parts.method.to_string();
+    let route = parts
+        .extensions
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| parts.uri.path().to_string());
+    let user = parts.extensions.get::<AuthUser>().cloned();
+    let target_id = match parts.extract::<RawPathParams>().await {
+        Ok(params) => {
+            let params: Vec<(&str, &str)> = params.iter().collect();
+            params
+                .iter()
+                .find(|(name, _)| *name == "id")
+                .or_else(|| params.first())
+                .map(|(_, value)| value.to_string())
+        }
+        Err(_) => None,
+    };
+
+    let content_type = parts
+        .headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or("")
+        .to_string();
+    let content_length = parts
+        .headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|v| v.parse::<usize>().ok());
+
+    // Buffer small JSON bodies to summarize them, then hand the bytes on unchanged
+    let (body, summary) = match content_length {
+        Some(len) if content_type.starts_with("application/json") && len <= MAX_AUDITED_BODY => {
+            match to_bytes(body, MAX_AUDITED_BODY).await {
+                Ok(bytes) => {
+                    let summary = serde_json::from_slice::<Value>(&bytes).ok().map(|v| summarize(&v));
+                    (Body::from(bytes), summary)
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to read {} {} body: {}", method, route, e);
+                    return (
+                        StatusCode::BAD_REQUEST,
+                        Json(json!({"error": "Invalid request body"})),
+                    )
+                        .into_response();
+                }
+            }
+        }
+        _ if content_type.starts_with("multipart/form-data") => (body, Some("file upload".to_string())),
+        _ => (body, None),
+    };
+
+    let response = next.run(Request::from_parts(parts, body)).await;
+
+    let actor = match &user {
+        Some(user) => user.email.clone(),
+        None => "unauthenticated (testing mode)".to_string(),
+    };
+    let entry = NewAuditLogEntry {
+        user_id: user.as_ref().map(|u| u.id.as_str()).filter(|id| !id.is_empty()),
+        actor: &actor,
+        method: &method,
+        route: &route,
+        target_id: target_id.as_deref(),
+        summary: summary.as_deref(),
+        status: response.status().as_u16(),
+    };
+    let recorded = match state.db.connect() {
+        Ok(conn) => AuditLogEntry::record(&conn, entry).await,
+        Err(e) => Err(AppError::from(e)),
+    };
+    if let Err(e) = recorded {
+        tracing::warn!("Failed to write audit log for {} {} by {}: {}", method, route, actor, e);
+    }
+
+    response
+}
+
+/// Short "field=value, ..." description of a request body, with secrets redacted
+fn summarize(body: &Value) -> String {
+    let summary = match body {
+        Value::Object(fields) => fields
+            .iter()
+            .map(|(name, value)| {
+                let lower = name.to_lowercase();
+                if REDACTED_FIELDS.iter().any(|f| lower.contains(f)) {
+                    format!("{}=[redacted]", name)
+                } else {
+                    format!("{}={}", name, truncate(&value.to_string(), MAX_VALUE_LEN))
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", "),
+        Value::Array(items) => format!("{} items", items.len()),
+        other => truncate(&other.to_string(), MAX_VALUE_LEN),
+    };
+    truncate(&summary, MAX_SUMMARY_LEN)
+}
+
+fn truncate(value: &str, max_chars: usize) -> String {
+    if value.chars().count() <= max_chars {
+        value.to_string()
+    } else {
+        format!("{}...", value.chars().take(max_chars).collect::<String>())
+    }
+}
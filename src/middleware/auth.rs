@@ -1,24 +1,20 @@
 use axum::{
     body::Body,
     extract::{Request, State},
-    http::{header, StatusCode},
+    http::{header, HeaderMap, Method, StatusCode},
     middleware::Next,
     response::{IntoResponse, Response},
     Json,
 };
-use serde::{Deserialize, Serialize};
+use libsql::Connection;
 use serde_json::json;
 
-use crate::models::User;
+use crate::error::{AppError, AppResult};
+use crate::models::api_key::API_KEY_PREFIX;
+use crate::models::impersonation::IMPERSONATION_TOKEN_PREFIX;
+use crate::models::{ApiKey, AuditLogEntry, CreateUser, ImpersonationToken, NewAuditLogEntry, RevokedSession, User};
 use crate::routes::AppState;
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ClerkClaims {
-    pub sub: String,
-    pub exp: usize,
-    pub iat: usize,
-    pub azp: Option<String>,
-}
+use crate::services::auth_provider::UserProfile;
 
 #[derive(Debug, Clone)]
 pub struct AuthUser {
@@ -27,37 +23,128 @@ pub struct AuthUser {
     pub email: String,
     pub name: Option<String>,
     pub is_admin: bool,
+    /// Staff role (see models::user::ROLES); None for customers
+    pub role: Option<String>,
+    /// Set when authenticated with an API key: the permissions it grants, in place of a role
+    pub api_key_scopes: Option<Vec<Permission>>,
+    /// Set when an owner is viewing the storefront as this customer: the owner's user ID
+    pub impersonated_by: Option<String>,
+    /// Provider session the request's token belongs to, when it has one
+    pub session_id: Option<String>,
 }
 
 impl From<User> for AuthUser {
     fn from(user: User) -> Self {
         Self {
+            is_admin: user.role.is_some(),
             id: user.id,
             clerk_id: user.clerk_id,
             email: user.email,
             name: user.name,
-            is_admin: user.is_admin,
+            role: user.role,
+            api_key_scopes: None,
+            impersonated_by: None,
+            session_id: None,
         }
     }
 }
 
-pub async fn auth_middleware(
-    State(state): State<AppState>,
-    mut req: Request<Body>,
-    next: Next,
-) -> Response {
-    tracing::debug!("AUTH MIDDLEWARE CALLED for {}", req.uri());
+impl AuthUser {
+    pub fn can(&self, permission: Permission) -> bool {
+        match &self.api_key_scopes {
+            Some(scopes) => scopes.contains(&permission),
+            None => self.role.as_deref().map(|role| permission.granted_to(role)).unwrap_or(false),
+        }
+    }
+}
 
-    // Try Authorization header first (for API calls from frontend)
-    let auth_header = req
-        .headers()
+/// What a staff role may do in the admin. Each admin route group requires one
+/// permission to read and one to change anything.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    /// List and view products
+    ViewCatalog,
+    /// Create, edit and delete products, prices and stock
+    EditCatalog,
+    /// Orders, labels, manifests and subscription fulfilment
+    Fulfillment,
+    /// Refunds and payment links
+    Refunds,
+    /// Newsletter, discounts and the email log
+    Marketing,
+    /// Revenue dashboards and artist payouts
+    Finance,
+    /// Shop settings and background jobs
+    Settings,
+}
+
+impl Permission {
+    pub const ALL: &'static [Permission] = &[
+        Permission::ViewCatalog,
+        Permission::EditCatalog,
+        Permission::Fulfillment,
+        Permission::Refunds,
+        Permission::Marketing,
+        Permission::Finance,
+        Permission::Settings,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::ViewCatalog => "view_catalog",
+            Permission::EditCatalog => "edit_catalog",
+            Permission::Fulfillment => "fulfillment",
+            Permission::Refunds => "refunds",
+            Permission::Marketing => "marketing",
+            Permission::Finance => "finance",
+            Permission::Settings => "settings",
+        }
+    }
+
+    /// The permission an API key scope names
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|p| p.as_str() == s)
+    }
+
+    pub fn granted_to(&self, role: &str) -> bool {
+        use Permission::*;
+
+        match role {
+            "owner" => true,
+            "fulfillment" => matches!(self, ViewCatalog | Fulfillment),
+            "marketing" => matches!(self, ViewCatalog | Marketing),
+            _ => false,
+        }
+    }
+}
+
+/// Permissions a route group requires: `read` for GET requests, `write` for the rest
+#[derive(Debug, Clone, Copy)]
+pub struct Access {
+    pub read: Permission,
+    pub write: Permission,
+}
+
+impl Access {
+    pub fn new(read: Permission, write: Permission) -> Self {
+        Self { read, write }
+    }
+
+    /// The same permission for reading and writing
+    pub fn only(permission: Permission) -> Self {
+        Self::new(permission, permission)
+    }
+}
+
+/// Clerk session token from the Authorization header (API calls from the
+/// frontend) or the __session cookie (browser navigation)
+pub fn session_token(headers: &HeaderMap) -> Option<&str> {
+    let auth_header = headers
         .get(header::AUTHORIZATION)
         .and_then(|h| h.to_str().ok())
         .and_then(|h| h.strip_prefix("Bearer "));
 
-    // Fall back to __session cookie (for browser navigation)
-    let cookie_token = req
-        .headers()
+    let cookie_token = headers
         .get(header::COOKIE)
         .and_then(|h| h.to_str().ok())
         .and_then(|cookies| {
@@ -72,7 +159,17 @@ pub async fn auth_middleware(
                 })
         });
 
-    let token = match auth_header.or(cookie_token) {
+    auth_header.or(cookie_token)
+}
+
+pub async fn auth_middleware(
+    State(state): State<AppState>,
+    mut req: Request<Body>,
+    next: Next,
+) -> Response {
+    tracing::debug!("AUTH MIDDLEWARE CALLED for {}", req.uri());
+
+    let token = match session_token(req.headers()) {
         Some(t) => t,
         None => {
             return (
@@ -83,8 +180,13 @@ pub async fn auth_middleware(
         }
     };
 
-    // Verify JWT using JWKS
-    let claims = match state.jwks.verify_token(token).await {
+    if token.starts_with(IMPERSONATION_TOKEN_PREFIX) {
+        let token = token.to_string();
+        return impersonation_auth(state, req, next, &token).await;
+    }
+
+    // Verify the session token with the auth provider
+    let claims = match state.auth.verify_token(token).await {
         Ok(c) => c,
         Err(e) => {
             tracing::warn!("JWT verification error: {}", e);
@@ -108,17 +210,205 @@ pub async fn auth_middleware(
         }
     };
 
-    let user = match User::find_by_clerk_id(&conn, &claims.sub).await {
-        Ok(Some(user)) => user,
+    // A signature doesn't say whether the session was revoked since the token was issued
+    if let Some(ref sid) = claims.session_id {
+        match RevokedSession::is_revoked(&conn, sid).await {
+            Ok(false) => {}
+            Ok(true) => {
+                tracing::warn!("Rejected token for revoked session {}", sid);
+                return (
+                    StatusCode::UNAUTHORIZED,
+                    Json(json!({"error": "Session revoked"})),
+                )
+                    .into_response();
+            }
+            Err(e) => {
+                tracing::error!("Failed to check revocation of session {}: {}", sid, e);
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(json!({"error": "Internal server error"})),
+                )
+                    .into_response();
+            }
+        }
+    }
+
+    // First request from a verified account: create the local user, from the
+    // token's own profile when it carries one
+    let user = match User::find_by_clerk_id(&conn, &claims.subject).await {
+        Ok(Some(user)) => Ok(user),
+        Ok(None) => match claims.profile {
+            Some(ref profile) => upsert_profile(&conn, profile).await,
+            None => provision_user(&state, &conn, &claims.subject).await,
+        },
+        Err(e) => Err(e),
+    };
+
+    let user = match user {
+        Ok(user) => user,
+        Err(e) => {
+            tracing::error!("Failed to load user for {} account {}: {}", state.auth.name(), claims.subject, e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": "Internal server error"})),
+            )
+                .into_response();
+        }
+    };
+
+    // Account was deleted at the provider (see the Clerk webhook)
+    if !user.is_active() {
+        tracing::warn!("Rejected request from deactivated user {}", user.id);
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({"error": "Account deactivated"})),
+        )
+            .into_response();
+    }
+
+    req.extensions_mut().insert(AuthUser {
+        session_id: claims.session_id,
+        ..AuthUser::from(user)
+    });
+    next.run(req).await
+}
+
+/// Auth with an owner's impersonation token: read-only storefront access as the
+/// customer it was issued for. Every request is written to the audit log under
+/// the owner.
+async fn impersonation_auth(state: AppState, mut req: Request<Body>, next: Next, token: &str) -> Response {
+    if !matches!(*req.method(), Method::GET | Method::HEAD) {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(json!({"error": "Impersonation is read-only"})),
+        )
+            .into_response();
+    }
+
+    let conn = match state.db.connect() {
+        Ok(c) => c,
+        Err(e) => {
+            tracing::error!("Database connection error: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": "Internal server error"})),
+            )
+                .into_response();
+        }
+    };
+
+    let found = async {
+        let grant = match ImpersonationToken::find_valid(&conn, token).await? {
+            Some(grant) => grant,
+            None => return Ok(None),
+        };
+        let customer = User::find_by_id(&conn, &grant.user_id).await?.filter(|u| u.is_active());
+        // The owner may have been demoted or deactivated since issuing the token
+        let owner = User::find_by_id(&conn, &grant.created_by)
+            .await?
+            .filter(|u| u.is_active() && u.role.as_deref() == Some("owner"));
+        Ok::<_, AppError>(customer.zip(owner).map(|(customer, owner)| (grant, customer, owner)))
+    }
+    .await;
+
+    let (grant, customer, owner) = match found {
+        Ok(Some(found)) => found,
+        Ok(None) => {
+            return (
+                StatusCode::UNAUTHORIZED,
+                Json(json!({"error": "Invalid or expired impersonation token"})),
+            )
+                .into_response();
+        }
+        Err(e) => {
+            tracing::error!("Failed to check impersonation token: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": "Internal server error"})),
+            )
+                .into_response();
+        }
+    };
+
+    let method = req.method().to_string();
+    let path = req.uri().path().to_string();
+    let actor = format!("{} as {}", owner.email, customer.email);
+    let customer_id = customer.id.clone();
+
+    req.extensions_mut().insert(AuthUser {
+        impersonated_by: Some(owner.id.clone()),
+        ..AuthUser::from(customer)
+    });
+    let response = next.run(req).await;
+
+    let entry = NewAuditLogEntry {
+        user_id: Some(&owner.id),
+        actor: &actor,
+        method: &method,
+        route: &path,
+        target_id: Some(&customer_id),
+        summary: Some(&grant.reason),
+        status: response.status().as_u16(),
+    };
+    if let Err(e) = AuditLogEntry::record(&conn, entry).await {
+        tracing::warn!("Failed to write audit log for {} {} by {}: {}", method, path, actor, e);
+    }
+
+    response
+}
+
+/// Auth for the admin API: a scoped API key (`Authorization: Bearer ccak_...`), or
+/// otherwise the same Clerk JWT check as `auth_middleware`
+pub async fn admin_auth_middleware(
+    State(state): State<AppState>,
+    mut req: Request<Body>,
+    next: Next,
+) -> Response {
+    let api_key = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .filter(|token| token.starts_with(API_KEY_PREFIX))
+        .map(|token| token.to_string());
+
+    // Impersonation only ever reaches the storefront API
+    if session_token(req.headers()).is_some_and(|t| t.starts_with(IMPERSONATION_TOKEN_PREFIX)) {
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({"error": "Impersonation tokens can't be used in the admin"})),
+        )
+            .into_response();
+    }
+
+    let key = match api_key {
+        Some(key) => key,
+        None => return auth_middleware(State(state), req, next).await,
+    };
+
+    let conn = match state.db.connect() {
+        Ok(c) => c,
+        Err(e) => {
+            tracing::error!("Database connection error: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": "Internal server error"})),
+            )
+                .into_response();
+        }
+    };
+
+    let api_key = match ApiKey::find_active_by_key(&conn, &key).await {
+        Ok(Some(api_key)) => api_key,
         Ok(None) => {
             return (
                 StatusCode::UNAUTHORIZED,
-                Json(json!({"error": "User not found"})),
+                Json(json!({"error": "Invalid API key"})),
             )
                 .into_response();
         }
         Err(e) => {
-            tracing::error!("Database error: {}", e);
+            tracing::error!("Failed to look up API key: {}", e);
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(json!({"error": "Internal server error"})),
@@ -127,10 +417,79 @@ pub async fn auth_middleware(
         }
     };
 
-    req.extensions_mut().insert(AuthUser::from(user));
+    // A key acts for its creator, so it can never do more than they currently can:
+    // it stops working when they're deactivated or lose their staff role, and a
+    // demotion narrows its scopes
+    let creator = match api_key.created_by.as_deref() {
+        Some(creator_id) => User::find_by_id(&conn, creator_id).await,
+        None => Ok(None),
+    };
+    let role = match creator.map(|c| c.filter(User::is_active).and_then(|c| c.role)) {
+        Ok(Some(role)) => role,
+        Ok(None) => {
+            tracing::warn!("Rejected API key {}: its creator no longer has staff access", api_key.id);
+            return (
+                StatusCode::UNAUTHORIZED,
+                Json(json!({"error": "Invalid API key"})),
+            )
+                .into_response();
+        }
+        Err(e) => {
+            tracing::error!("Failed to load creator of API key {}: {}", api_key.id, e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": "Internal server error"})),
+            )
+                .into_response();
+        }
+    };
+
+    if let Err(e) = ApiKey::touch(&conn, &api_key.id).await {
+        tracing::warn!("Failed to record use of API key {}: {}", api_key.id, e);
+    }
+
+    let scopes = api_key
+        .scopes
+        .iter()
+        .filter_map(|s| Permission::parse(s))
+        .filter(|p| p.granted_to(&role))
+        .collect();
+
+    req.extensions_mut().insert(AuthUser {
+        id: api_key.created_by.clone().unwrap_or_default(),
+        clerk_id: String::new(),
+        email: format!("api-key:{}", api_key.key_prefix),
+        name: Some(api_key.name.clone()),
+        is_admin: true,
+        role: None,
+        api_key_scopes: Some(scopes),
+        impersonated_by: None,
+        session_id: None,
+    });
     next.run(req).await
 }
 
+/// Create or refresh the local user for an account from the auth provider's profile
+pub async fn provision_user(state: &AppState, conn: &Connection, provider_id: &str) -> AppResult<User> {
+    let profile = state.auth.fetch_user(provider_id).await?;
+    upsert_profile(conn, &profile).await
+}
+
+async fn upsert_profile(conn: &Connection, profile: &UserProfile) -> AppResult<User> {
+    let user = User::upsert(
+        conn,
+        CreateUser {
+            clerk_id: profile.id.clone(),
+            email: profile.email.clone(),
+            name: profile.name.clone(),
+        },
+    )
+    .await?;
+
+    tracing::debug!("Synced user {} from account {}", user.id, profile.id);
+    Ok(user)
+}
+
 pub async fn require_admin(
     req: Request<Body>,
     next: Next,
@@ -151,3 +510,34 @@ pub async fn require_admin(
             .into_response(),
     }
 }
+
+/// Reject staff whose role lacks the permission a route group requires. Runs after
+/// `auth_middleware` and `require_admin`.
+pub async fn require_permission(
+    State(access): State<Access>,
+    req: Request<Body>,
+    next: Next,
+) -> Response {
+    let permission = if req.method() == Method::GET {
+        access.read
+    } else {
+        access.write
+    };
+
+    match req.extensions().get::<AuthUser>() {
+        Some(user) if user.can(permission) => next.run(req).await,
+        Some(user) => {
+            tracing::warn!("{} ({:?}) denied {:?} for {} {}", user.email, user.role, permission, req.method(), req.uri());
+            (
+                StatusCode::FORBIDDEN,
+                Json(json!({"error": "Your role doesn't allow this"})),
+            )
+                .into_response()
+        }
+        None => (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({"error": "Authentication required"})),
+        )
+            .into_response(),
+    }
+}
@@ -0,0 +1,86 @@
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::routes::AppState;
+
+/// Cookie holding the admin panel's CSRF token. Readable by the page's JS,
+/// which echoes it back in the header below.
+pub const CSRF_COOKIE: &str = "gallium_csrf";
+pub const CSRF_HEADER: &str = "x-csrf-token";
+
+/// Double-submit CSRF protection for the admin panel. Every admin response
+/// sets a random token cookie if the browser doesn't have one yet. A request
+/// that changes something and is authenticated by cookie (no Authorization
+/// header) must send the same token in `X-CSRF-Token`; another site can make
+/// the browser send the cookie but can't read it to fill in the header.
+/// Bearer-token requests (the panel's authFetch, API keys) aren't sent
+/// automatically by the browser, so they need no token.
+pub async fn csrf_middleware(
+    State(state): State<AppState>,
+    req: Request<Body>,
+    next: Next,
+) -> Response {
+    let cookie_token = csrf_cookie(req.headers());
+
+    let changes_state = !matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS);
+    let bearer_auth = req.headers().contains_key(header::AUTHORIZATION);
+    if changes_state && !bearer_auth {
+        let header_token = req
+            .headers()
+            .get(CSRF_HEADER)
+            .and_then(|h| h.to_str().ok());
+        let valid = match (cookie_token.as_deref(), header_token) {
+            (Some(cookie), Some(header)) => !cookie.is_empty() && constant_time_eq(cookie, header),
+            _ => false,
+        };
+        if !valid {
+            tracing::warn!("Rejected {} {} with missing or mismatched CSRF token", req.method(), req.uri().path());
+            return (
+                StatusCode::FORBIDDEN,
+                Json(json!({"error": "Invalid CSRF token, reload the page and try again"})),
+            )
+                .into_response();
+        }
+    }
+
+    let mut response = next.run(req).await;
+
+    if cookie_token.is_none() {
+        let token = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+        let secure = if state.config.base_url.starts_with("https://") { "; Secure" } else { "" };
+        let cookie = format!("{}={}; Path=/gallium; SameSite=Strict{}", CSRF_COOKIE, token, secure);
+        if let Ok(value) = HeaderValue::from_str(&cookie) {
+            response.headers_mut().append(header::SET_COOKIE, value);
+        }
+    }
+
+    response
+}
+
+fn csrf_cookie(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|h| h.to_str().ok())
+        .flat_map(|cookies| cookies.split(';'))
+        .find_map(|cookie| {
+            cookie
+                .trim()
+                .strip_prefix(CSRF_COOKIE)
+                .and_then(|rest| rest.strip_prefix('='))
+                .map(|value| value.to_string())
+        })
+}
+
+/// Compare without returning early, so timing doesn't reveal the token
+pub(crate) fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
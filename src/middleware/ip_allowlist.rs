@@ -0,0 +1,78 @@
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+use std::net::{IpAddr, SocketAddr};
+
+use crate::routes::AppState;
+
+/// Client address as seen by the nearest trusted proxy. Each proxy appends the
+/// address it received the request from to X-Forwarded-For, so with N trusted
+/// proxies the client is the Nth entry from the right; anything to its left
+/// was sent by the client and can be forged. With no trusted proxies the TCP
+/// peer address is used.
+pub fn trusted_client_ip(headers: &HeaderMap, peer: Option<SocketAddr>, trusted_hops: usize) -> Option<IpAddr> {
+    if trusted_hops == 0 {
+        return peer.map(|p| p.ip());
+    }
+
+    let forwarded: Vec<&str> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|h| h.to_str().ok())
+        .flat_map(|h| h.split(','))
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .collect();
+
+    forwarded
+        .len()
+        .checked_sub(trusted_hops)
+        .and_then(|i| forwarded[i].parse().ok())
+}
+
+/// `trusted_client_ip` as a key for counting a client's requests. A request that
+/// didn't pass through the expected proxies is counted by its TCP peer address.
+pub fn client_address(headers: &HeaderMap, peer: Option<SocketAddr>, trusted_hops: usize) -> String {
+    trusted_client_ip(headers, peer, trusted_hops)
+        .or_else(|| peer.map(|p| p.ip()))
+        .map(|ip| ip.to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Only let clients from `ADMIN_IP_ALLOWLIST` reach the admin panel and API.
+/// Does nothing when the allowlist is empty.
+pub async fn admin_ip_allowlist_middleware(
+    State(state): State<AppState>,
+    req: Request<Body>,
+    next: Next,
+) -> Response {
+    let allowlist = &state.config.admin_ip_allowlist;
+    if allowlist.is_empty() {
+        return next.run(req).await;
+    }
+
+    let peer = req.extensions().get::<ConnectInfo<SocketAddr>>().map(|c| c.0);
+    let ip = trusted_client_ip(req.headers(), peer, state.config.trusted_proxy_hops);
+
+    match ip {
+        Some(ip) if allowlist.iter().any(|net| net.contains(ip)) => next.run(req).await,
+        _ => {
+            tracing::warn!(
+                "Blocked admin request to {} from {} (not in ADMIN_IP_ALLOWLIST)",
+                req.uri().path(),
+                ip.map(|ip| ip.to_string()).unwrap_or_else(|| "unknown address".to_string())
+            );
+            (
+                StatusCode::FORBIDDEN,
+                Json(json!({"error": "Admin access is not allowed from this network"})),
+            )
+                .into_response()
+        }
+    }
+}
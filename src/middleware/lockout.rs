@@ -0,0 +1,247 @@
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, Request, State},
+    http::{Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+use std::net::SocketAddr;
+
+use crate::middleware::auth::{session_token, AuthUser};
+use crate::middleware::ip_allowlist::client_address;
+use crate::models::{AdminAuthEvent, NewAdminAuthEvent, User};
+use crate::routes::AppState;
+
+/// Failed admin sign-ins allowed from one address or account per window
+const MAX_FAILED_ATTEMPTS: u32 = 10;
+/// Window the failures are counted over
+const FAILURE_WINDOW_SECS: u64 = 15 * 60;
+/// How long an offender is locked out once it hits the limit
+const LOCKOUT_SECS: u64 = 15 * 60;
+/// How long a session is remembered as signed in, so only its first admin
+/// request is recorded as a sign-in
+const SIGN_IN_DEDUP_SECS: u64 = 24 * 60 * 60;
+
+/// Marks a response as a failed admin authentication, so the address-level
+/// check counts failures found further in (wrong step-up codes, customers
+/// probing the admin)
+#[derive(Clone, Copy)]
+struct AuthFailure;
+
+fn locked_out() -> Response {
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [("retry-after", LOCKOUT_SECS.to_string())],
+        Json(json!({"error": "Too many failed sign-in attempts, try again later"})),
+    )
+        .into_response()
+}
+
+fn request_ip(state: &AppState, req: &Request<Body>) -> String {
+    let peer = req.extensions().get::<ConnectInfo<SocketAddr>>().map(|c| c.0);
+    client_address(req.headers(), peer, state.config.trusted_proxy_hops)
+}
+
+/// Lock out addresses that keep failing admin authentication. Only requests
+/// that present credentials count, so loading the panel signed out doesn't.
+pub async fn admin_ip_lockout_middleware(
+    State(state): State<AppState>,
+    req: Request<Body>,
+    next: Next,
+) -> Response {
+    let ip = request_ip(&state, &req);
+    let key = format!("admin-auth:ip:{}", ip);
+
+    if is_locked(&state, &key).await {
+        return locked_out();
+    }
+
+    let presented_credentials = session_token(req.headers()).is_some();
+    let response = next.run(req).await;
+
+    // Failures found further in were already logged with their account
+    let found_further_in = response.extensions().get::<AuthFailure>().is_some();
+    let rejected_credentials = presented_credentials && response.status() == StatusCode::UNAUTHORIZED;
+    if rejected_credentials {
+        log_event(&state, AdminAuthEvent::FAILURE, None, &ip, Some("invalid credentials")).await;
+    }
+    if found_further_in || rejected_credentials {
+        register_failure(&state, &key, &format!("IP address {}", ip), None, &ip).await;
+    }
+
+    response
+}
+
+/// Lock out signed-in accounts that keep failing admin checks: customers
+/// without staff access, and staff entering wrong step-up codes. Also records
+/// each staff session's first admin request as a sign-in. Runs between
+/// `admin_auth_middleware` and `require_admin`.
+pub async fn admin_user_lockout_middleware(
+    State(state): State<AppState>,
+    req: Request<Body>,
+    next: Next,
+) -> Response {
+    let user = match req.extensions().get::<AuthUser>() {
+        Some(user) if user.api_key_scopes.is_none() => user.clone(),
+        _ => return next.run(req).await,
+    };
+    let key = format!("admin-auth:user:{}", user.id);
+    let ip = request_ip(&state, &req);
+
+    if is_locked(&state, &key).await {
+        return locked_out();
+    }
+
+    let checks_code = is_code_check(&req);
+    let mut response = next.run(req).await;
+
+    if response.status() == StatusCode::FORBIDDEN && (!user.is_admin || checks_code) {
+        let detail = if checks_code { "wrong authenticator code" } else { "not staff" };
+        log_event(&state, AdminAuthEvent::FAILURE, Some(&user), &ip, Some(detail)).await;
+        register_failure(&state, &key, &format!("account {}", user.email), Some(&user), &ip).await;
+        response.extensions_mut().insert(AuthFailure);
+    } else if user.is_admin {
+        record_sign_in(&state, &user, &ip).await;
+    }
+
+    response
+}
+
+/// Log a staff session the first time it reaches the admin
+async fn record_sign_in(state: &AppState, user: &AuthUser, ip: &str) {
+    let session_id = match user.session_id {
+        Some(ref session_id) => session_id,
+        None => return,
+    };
+
+    match state.rate_limiter.record_event(&format!("admin-session:{}", session_id), SIGN_IN_DEDUP_SECS).await {
+        Ok(1) => {}
+        Ok(_) => return,
+        Err(e) => {
+            tracing::error!("Failed to check admin session {}: {}", session_id, e);
+            return;
+        }
+    }
+
+    let conn = match state.db.connect() {
+        Ok(c) => c,
+        Err(e) => {
+            tracing::error!("Database connection error: {}", e);
+            return;
+        }
+    };
+
+    let event = NewAdminAuthEvent {
+        kind: AdminAuthEvent::SIGN_IN,
+        user_id: Some(&user.id),
+        actor: Some(&user.email),
+        ip: Some(ip),
+        session_id: Some(session_id),
+        detail: None,
+    };
+    if let Err(e) = AdminAuthEvent::record(&conn, event).await {
+        tracing::error!("Failed to record admin sign-in for {}: {}", user.email, e);
+    }
+}
+
+/// Write a failure or lockout to the admin activity log
+async fn log_event(state: &AppState, kind: &str, user: Option<&AuthUser>, ip: &str, detail: Option<&str>) {
+    let conn = match state.db.connect() {
+        Ok(c) => c,
+        Err(e) => {
+            tracing::error!("Database connection error: {}", e);
+            return;
+        }
+    };
+
+    let event = NewAdminAuthEvent {
+        kind,
+        user_id: user.map(|u| u.id.as_str()),
+        actor: user.map(|u| u.email.as_str()),
+        ip: Some(ip),
+        session_id: None,
+        detail,
+    };
+    if let Err(e) = AdminAuthEvent::record(&conn, event).await {
+        tracing::error!("Failed to record admin {} from {}: {}", kind, ip, e);
+    }
+}
+
+/// Requests that submit an authenticator code
+fn is_code_check(req: &Request<Body>) -> bool {
+    let path = req.uri().path();
+    req.method() == Method::POST && (path.ends_with("/step-up") || path.ends_with("/step-up/totp/enable"))
+}
+
+async fn is_locked(state: &AppState, key: &str) -> bool {
+    match state.rate_limiter.is_locked(key).await {
+        Ok(locked) => locked,
+        Err(e) => {
+            tracing::error!("Failed to check lockout for {}: {}", key, e);
+            false
+        }
+    }
+}
+
+/// Count a failure, and lock the offender out (alerting owners) when it
+/// reaches the limit
+async fn register_failure(state: &AppState, key: &str, target: &str, user: Option<&AuthUser>, ip: &str) {
+    let attempts = match state.rate_limiter.record_event(key, FAILURE_WINDOW_SECS).await {
+        Ok(attempts) => attempts,
+        Err(e) => {
+            tracing::error!("Failed to record failed admin sign-in for {}: {}", target, e);
+            return;
+        }
+    };
+
+    if attempts != MAX_FAILED_ATTEMPTS {
+        return;
+    }
+
+    if let Err(e) = state.rate_limiter.lock(key, LOCKOUT_SECS).await {
+        tracing::error!("Failed to lock out {}: {}", target, e);
+        return;
+    }
+    tracing::warn!("Locked out {} after {} failed admin sign-ins", target, attempts);
+    log_event(state, AdminAuthEvent::LOCKOUT, user, ip, Some(target)).await;
+
+    let state = state.clone();
+    let target = target.to_string();
+    tokio::spawn(async move {
+        alert_owners(&state, &target, attempts).await;
+    });
+}
+
+async fn alert_owners(state: &AppState, target: &str, attempts: u32) {
+    let email_service = match state.email {
+        Some(ref email_service) => email_service,
+        None => return,
+    };
+
+    let conn = match state.db.connect() {
+        Ok(c) => c,
+        Err(e) => {
+            tracing::error!("Database connection error: {}", e);
+            return;
+        }
+    };
+
+    let owners = match User::list_admins(&conn).await {
+        Ok(admins) => admins.into_iter().filter(|u| u.role.as_deref() == Some("owner")),
+        Err(e) => {
+            tracing::error!("Failed to list owners for lockout alert: {}", e);
+            return;
+        }
+    };
+
+    for owner in owners {
+        if let Err(e) = email_service
+            .send_lockout_alert(&owner.email, target, attempts, LOCKOUT_SECS / 60)
+            .await
+        {
+            tracing::error!("Failed to send lockout alert to {}: {}", owner.email, e);
+        }
+    }
+}
@@ -1,5 +1,12 @@
+pub mod audit;
 pub mod auth;
+pub mod capability;
+pub mod csrf;
+pub mod ip_allowlist;
+pub mod lockout;
 pub mod rate_limit;
+pub mod request_id;
+pub mod step_up;
 
 pub use auth::AuthUser;
 pub use rate_limit::rate_limit_middleware;
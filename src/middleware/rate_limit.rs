@@ -1,58 +1,162 @@
 use axum::{
     body::Body,
-    extract::{Request, State},
-    http::StatusCode,
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
     middleware::Next,
     response::{IntoResponse, Response},
     Json,
 };
 use serde_json::json;
+use std::net::SocketAddr;
 
+use crate::config::Config;
+use crate::middleware::auth::session_token;
+use crate::middleware::ip_allowlist::client_address;
 use crate::routes::AppState;
+use crate::services::rate_limiter::RateLimitDecision;
 
-/// Rate limiting middleware using Upstash Redis
+/// Endpoints with their own, stricter per-minute limit on top of the general one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteClass {
+    /// Sign-in callbacks and user sync
+    Auth,
+    /// Starting a Stripe checkout or payment intent
+    Checkout,
+    /// Email forms anyone can submit: newsletter signup and Notify Me
+    Signup,
+}
+
+impl RouteClass {
+    /// Class of an API path, with or without the /api prefix
+    pub fn for_path(path: &str) -> Option<Self> {
+        let path = path.strip_prefix("/api").unwrap_or(path);
+
+        if path.starts_with("/auth/") {
+            return Some(RouteClass::Auth);
+        }
+        if path == "/checkout" || path.starts_with("/checkout/") {
+            return Some(RouteClass::Checkout);
+        }
+        if path == "/newsletter/subscribe" {
+            return Some(RouteClass::Signup);
+        }
+        // /products/{id}/notify
+        if let Some(id) = path.strip_prefix("/products/").and_then(|p| p.strip_suffix("/notify")) {
+            if !id.is_empty() && !id.contains('/') {
+                return Some(RouteClass::Signup);
+            }
+        }
+        None
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RouteClass::Auth => "auth",
+            RouteClass::Checkout => "checkout",
+            RouteClass::Signup => "signup",
+        }
+    }
+
+    /// Requests per minute allowed for one caller
+    pub fn limit(&self, config: &Config) -> u32 {
+        match self {
+            RouteClass::Auth | RouteClass::Signup => config.rate_limit_auth,
+            RouteClass::Checkout => config.rate_limit_checkout,
+        }
+    }
+}
+
+/// Who a route class limit applies to: the signed-in user when the request
+/// carries a valid session, so shoppers behind one NAT don't share a limit,
+/// otherwise the client IP
+async fn caller_key(state: &AppState, headers: &HeaderMap, ip: &str) -> String {
+    if let Some(token) = session_token(headers) {
+        if let Ok(claims) = state.auth.verify_token(token).await {
+            return format!("user:{}", claims.subject);
+        }
+    }
+    format!("ip:{}", ip)
+}
+
+/// The decision closest to refusing the caller, which their headers describe
+fn tightest(a: RateLimitDecision, b: RateLimitDecision) -> RateLimitDecision {
+    if b.remaining < a.remaining {
+        b
+    } else {
+        a
+    }
+}
+
+/// `RateLimit-Limit`, `RateLimit-Remaining` and `RateLimit-Reset` (IETF
+/// draft), plus `Retry-After` when the request was refused
+fn set_rate_limit_headers(headers: &mut HeaderMap, decision: &RateLimitDecision) {
+    headers.insert("ratelimit-limit", HeaderValue::from(decision.limit));
+    headers.insert("ratelimit-remaining", HeaderValue::from(decision.remaining));
+    headers.insert("ratelimit-reset", HeaderValue::from(decision.reset_secs));
+    if !decision.allowed {
+        headers.insert(header::RETRY_AFTER, HeaderValue::from(decision.retry_after_secs.max(1)));
+    }
+}
+
+fn too_many_requests(decision: &RateLimitDecision) -> Response {
+    let mut response = (
+        StatusCode::TOO_MANY_REQUESTS,
+        Json(json!({
+            "error": "Too many requests",
+            "retry_after": decision.retry_after_secs.max(1)
+        })),
+    )
+        .into_response();
+    set_rate_limit_headers(response.headers_mut(), decision);
+    response
+}
+
+/// Rate limiting middleware using Upstash Redis, or in-process limits when Redis
+/// isn't available. Every request counts toward the general per-IP limit (the
+/// IP is found the same way as for the admin IP allowlist); auth, checkout and
+/// signup routes also count toward their route class limit. Responses carry the
+/// headers of whichever limit the caller is closest to.
 pub async fn rate_limit_middleware(
     State(state): State<AppState>,
     req: Request<Body>,
     next: Next,
 ) -> Response {
-    // Get client IP from X-Forwarded-For header (for proxied requests) or X-Real-IP
-    let ip = req
-        .headers()
-        .get("x-forwarded-for")
-        .and_then(|h| h.to_str().ok())
-        .and_then(|s| s.split(',').next())
-        .map(|s| s.trim().to_string())
-        .or_else(|| {
-            req.headers()
-                .get("x-real-ip")
-                .and_then(|h| h.to_str().ok())
-                .map(|s| s.to_string())
-        })
-        .unwrap_or_else(|| "unknown".to_string());
-
-    // Check rate limit if rate limiter is configured
-    if let Some(ref rate_limiter) = state.rate_limiter {
-        match rate_limiter.check_rate_limit(&ip).await {
-            Ok(allowed) => {
-                if !allowed {
-                    tracing::warn!("Rate limit exceeded for IP: {}", ip);
-                    return (
-                        StatusCode::TOO_MANY_REQUESTS,
-                        Json(json!({
-                            "error": "Too many requests",
-                            "retry_after": 60
-                        })),
-                    )
-                        .into_response();
-                }
+    let peer = req.extensions().get::<ConnectInfo<SocketAddr>>().map(|c| c.0);
+    let ip = client_address(req.headers(), peer, state.config.trusted_proxy_hops);
+    let rate_limiter = &state.rate_limiter;
+
+    let mut reported = match rate_limiter.check_rate_limit(&ip).await {
+        Ok(decision) => {
+            if !decision.allowed {
+                tracing::warn!("Rate limit exceeded for IP: {}", ip);
+                return too_many_requests(&decision);
+            }
+            Some(decision)
+        }
+        Err(e) => {
+            // Log error but allow request through (fail open)
+            tracing::error!("Rate limiter error: {} - allowing request", e);
+            None
+        }
+    };
+
+    if let Some(class) = RouteClass::for_path(req.uri().path()) {
+        let key = caller_key(&state, req.headers(), &ip).await;
+        match rate_limiter.check_scoped_rate_limit(class.as_str(), &key, class.limit(&state.config)).await {
+            Ok(decision) if decision.allowed => {
+                reported = Some(reported.map_or(decision, |general| tightest(general, decision)));
             }
-            Err(e) => {
-                // Log error but allow request through (fail open)
-                tracing::error!("Rate limiter error: {} - allowing request", e);
+            Ok(decision) => {
+                tracing::warn!("{} rate limit exceeded for {}", class.as_str(), key);
+                return too_many_requests(&decision);
             }
+            Err(e) => tracing::error!("Rate limiter error: {} - allowing request", e),
         }
     }
 
-    next.run(req).await
+    let mut response = next.run(req).await;
+    if let Some(ref decision) = reported {
+        set_rate_limit_headers(response.headers_mut(), decision);
+    }
+    response
 }
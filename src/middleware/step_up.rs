@@ -0,0 +1,143 @@
+use axum::{
+    body::Body,
+    extract::{MatchedPath, Request, State},
+    http::{Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+
+use crate::error::AppError;
+use crate::middleware::AuthUser;
+use crate::models::StepUpToken;
+use crate::routes::AppState;
+
+/// Header carrying the token from POST /gallium/api/step-up
+pub const STEP_UP_HEADER: &str = "x-step-up-token";
+
+/// Admin routes (relative to /gallium/api) that need an elevated session:
+/// refunds, product deletion, removing an authenticator, staff role changes,
+/// impersonating a customer, creating or revoking API keys and any settings change
+fn needs_step_up(method: &Method, route: &str) -> bool {
+    if matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS) {
+        return false;
+    }
+    match (method.as_str(), route) {
+        ("POST", "/orders/{id}/refund") => true,
+        ("DELETE", "/products/{id}") => true,
+        ("DELETE", "/step-up/totp") => true,
+        ("PUT", "/users/{id}/role") => true,
+        ("POST", "/users/{id}/impersonate") => true,
+        // A key outlives the session that minted it and is itself exempt from step-up
+        ("POST", "/keys") => true,
+        ("DELETE", "/keys/{id}") => true,
+        _ => route.starts_with("/settings/") || route == "/settings",
+    }
+}
+
+/// Require a valid X-Step-Up-Token on destructive admin routes. Runs after
+/// `admin_auth_middleware`. API keys are exempt: they can't answer a TOTP
+/// prompt, and an owner granted their scopes explicitly.
+pub async fn require_step_up(
+    State(state): State<AppState>,
+    req: Request<Body>,
+    next: Next,
+) -> Response {
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| req.uri().path().to_string());
+    // Matched paths include the /gallium/api prefix
+    let relative = route.find("/api/").map(|i| &route[i + 4..]).unwrap_or(&route);
+    if !needs_step_up(req.method(), relative) {
+        return next.run(req).await;
+    }
+
+    let user = match req.extensions().get::<AuthUser>() {
+        Some(user) if user.api_key_scopes.is_some() => return next.run(req).await,
+        Some(user) => user.clone(),
+        None => return next.run(req).await,
+    };
+
+    let token = req
+        .headers()
+        .get(STEP_UP_HEADER)
+        .and_then(|h| h.to_str().ok())
+        .map(|t| t.to_string());
+
+    if let Some(token) = token {
+        let valid = match state.db.connect() {
+            Ok(conn) => StepUpToken::is_valid(&conn, &token, &user.id).await,
+            Err(e) => Err(AppError::from(e)),
+        };
+        match valid {
+            Ok(true) => return next.run(req).await,
+            Ok(false) => {}
+            Err(e) => {
+                tracing::error!("Failed to check step-up token for {}: {}", user.email, e);
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(json!({"error": "Internal server error"})),
+                )
+                    .into_response();
+            }
+        }
+    }
+
+    (
+        StatusCode::FORBIDDEN,
+        Json(json!({
+            "error": "Confirm it's you to continue",
+            "step_up_required": true
+        })),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::needs_step_up;
+    use axum::http::Method;
+
+    #[test]
+    fn destructive_routes_need_step_up() {
+        assert!(needs_step_up(&Method::POST, "/orders/{id}/refund"));
+        assert!(needs_step_up(&Method::DELETE, "/products/{id}"));
+        assert!(needs_step_up(&Method::DELETE, "/step-up/totp"));
+        assert!(needs_step_up(&Method::PUT, "/users/{id}/role"));
+        assert!(needs_step_up(&Method::POST, "/users/{id}/impersonate"));
+    }
+
+    #[test]
+    fn minting_and_revoking_api_keys_need_step_up() {
+        assert!(needs_step_up(&Method::POST, "/keys"));
+        assert!(needs_step_up(&Method::DELETE, "/keys/{id}"));
+        assert!(!needs_step_up(&Method::GET, "/keys"));
+    }
+
+    #[test]
+    fn settings_changes_need_step_up() {
+        assert!(needs_step_up(&Method::PUT, "/settings"));
+        assert!(needs_step_up(&Method::PUT, "/settings/shipping"));
+        assert!(needs_step_up(&Method::POST, "/settings/shipping/boxes"));
+        assert!(!needs_step_up(&Method::PUT, "/settingsx"));
+    }
+
+    #[test]
+    fn reads_never_need_step_up() {
+        for method in [Method::GET, Method::HEAD, Method::OPTIONS] {
+            assert!(!needs_step_up(&method, "/settings/shipping"));
+            assert!(!needs_step_up(&method, "/orders/{id}/refund"));
+        }
+    }
+
+    #[test]
+    fn other_writes_dont_need_step_up() {
+        assert!(!needs_step_up(&Method::PUT, "/products/{id}"));
+        assert!(!needs_step_up(&Method::POST, "/orders/{id}/label"));
+        assert!(!needs_step_up(&Method::POST, "/step-up"));
+        assert!(!needs_step_up(&Method::DELETE, "/users/{id}/impersonate"));
+    }
+}
@@ -0,0 +1,231 @@
+use libsql::Connection;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::{AppError, AppResult};
+use crate::models::ShippingAddress;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Address {
+    pub id: String,
+    pub user_id: String,
+    pub label: Option<String>,
+    pub name: String,
+    pub street: String,
+    pub city: String,
+    pub state: String,
+    pub zip: String,
+    pub country: String,
+    pub is_default: bool,
+    pub created_ts: i64,
+    pub updated_ts: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SaveAddress {
+    pub label: Option<String>,
+    pub name: String,
+    pub street: String,
+    pub city: String,
+    pub state: String,
+    pub zip: String,
+    pub country: String,
+    #[serde(default)]
+    pub is_default: bool,
+}
+
+impl Address {
+    fn from_row(row: &libsql::Row) -> Result<Self, libsql::Error> {
+        Ok(Self {
+            id: row.get(0)?,
+            user_id: row.get(1)?,
+            label: row.get(2).ok(),
+            name: row.get(3)?,
+            street: row.get(4)?,
+            city: row.get(5)?,
+            state: row.get(6)?,
+            zip: row.get(7)?,
+            country: row.get(8)?,
+            is_default: row.get::<i32>(9).map(|v| v != 0).unwrap_or(false),
+            created_ts: row.get(10)?,
+            updated_ts: row.get(11)?,
+        })
+    }
+
+    pub fn to_shipping_address(&self) -> ShippingAddress {
+        ShippingAddress {
+            name: self.name.clone(),
+            street: self.street.clone(),
+            city: self.city.clone(),
+            state: self.state.clone(),
+            zip: self.zip.clone(),
+            country: self.country.clone(),
+        }
+    }
+
+    pub async fn list_by_user(conn: &Connection, user_id: &str) -> AppResult<Vec<Self>> {
+        let mut rows = conn
+            .query(
+                "SELECT * FROM addresses WHERE user_id = ? ORDER BY is_default DESC, created_ts DESC",
+                [user_id],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        let mut addresses = Vec::new();
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            addresses.push(Self::from_row(&row).map_err(AppError::from)?);
+        }
+        Ok(addresses)
+    }
+
+    /// Find an address only if it belongs to the given user
+    pub async fn find_for_user(conn: &Connection, id: &str, user_id: &str) -> AppResult<Option<Self>> {
+        let mut rows = conn
+            .query(
+                "SELECT * FROM addresses WHERE id = ? AND user_id = ?",
+                [id, user_id],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        if let Some(row) = rows.next().await.map_err(AppError::from)? {
+            Ok(Some(Self::from_row(&row).map_err(AppError::from)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub async fn create(conn: &Connection, user_id: &str, data: SaveAddress) -> AppResult<Self> {
+        let id = Uuid::new_v4().to_string();
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        // A customer's first address is their default
+        let is_default = data.is_default || Self::list_by_user(conn, user_id).await?.is_empty();
+        if is_default {
+            Self::clear_default(conn, user_id).await?;
+        }
+
+        conn.execute(
+            "INSERT INTO addresses (id, user_id, label, name, street, city, state, zip, country, is_default, created_ts, updated_ts) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            libsql::params![
+                id.clone(),
+                user_id.to_string(),
+                data.label,
+                data.name,
+                data.street,
+                data.city,
+                data.state,
+                data.zip,
+                data.country,
+                is_default as i32,
+                now,
+                now
+            ],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Self::find_for_user(conn, &id, user_id)
+            .await?
+            .ok_or_else(|| AppError::Internal("Failed to create address".to_string()))
+    }
+
+    pub async fn update(conn: &Connection, id: &str, user_id: &str, data: SaveAddress) -> AppResult<Self> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        if data.is_default {
+            Self::clear_default(conn, user_id).await?;
+        }
+
+        let updated = conn
+            .execute(
+                r#"
+                UPDATE addresses SET
+                    label = ?, name = ?, street = ?, city = ?, state = ?, zip = ?, country = ?,
+                    is_default = CASE WHEN ? THEN 1 ELSE is_default END,
+                    updated_ts = ?
+                WHERE id = ? AND user_id = ?
+                "#,
+                libsql::params![
+                    data.label,
+                    data.name,
+                    data.street,
+                    data.city,
+                    data.state,
+                    data.zip,
+                    data.country,
+                    data.is_default as i32,
+                    now,
+                    id.to_string(),
+                    user_id.to_string()
+                ],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        if updated == 0 {
+            return Err(AppError::NotFound("Address not found".to_string()));
+        }
+
+        Self::find_for_user(conn, id, user_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Address not found".to_string()))
+    }
+
+    pub async fn delete(conn: &Connection, id: &str, user_id: &str) -> AppResult<bool> {
+        let result = conn
+            .execute("DELETE FROM addresses WHERE id = ? AND user_id = ?", [id, user_id])
+            .await
+            .map_err(AppError::from)?;
+
+        Ok(result > 0)
+    }
+
+    pub async fn delete_all_for_user(conn: &Connection, user_id: &str) -> AppResult<()> {
+        conn.execute("DELETE FROM addresses WHERE user_id = ?", [user_id])
+            .await
+            .map_err(AppError::from)?;
+
+        Ok(())
+    }
+
+    /// Make one of the user's addresses the default. Returns false if it isn't theirs.
+    pub async fn set_default(conn: &Connection, id: &str, user_id: &str) -> AppResult<bool> {
+        if Self::find_for_user(conn, id, user_id).await?.is_none() {
+            return Ok(false);
+        }
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        Self::clear_default(conn, user_id).await?;
+        conn.execute(
+            "UPDATE addresses SET is_default = 1, updated_ts = ? WHERE id = ? AND user_id = ?",
+            libsql::params![now, id.to_string(), user_id.to_string()],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Ok(true)
+    }
+
+    async fn clear_default(conn: &Connection, user_id: &str) -> AppResult<()> {
+        conn.execute(
+            "UPDATE addresses SET is_default = 0 WHERE user_id = ? AND is_default = 1",
+            [user_id],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Ok(())
+    }
+}
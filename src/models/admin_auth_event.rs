@@ -0,0 +1,121 @@
+use libsql::Connection;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::{AppError, AppResult};
+
+/// Events are kept this long
+const RETENTION_SECS: i64 = 90 * 24 * 60 * 60;
+
+/// A staff sign-in (first admin request of a new session), a failed admin
+/// authentication, or a lockout
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdminAuthEvent {
+    pub id: String,
+    /// sign_in, failure or lockout
+    pub kind: String,
+    pub user_id: Option<String>,
+    pub actor: Option<String>,
+    pub ip: Option<String>,
+    pub session_id: Option<String>,
+    pub detail: Option<String>,
+    pub created_ts: i64,
+}
+
+/// What to record for one event
+pub struct NewAdminAuthEvent<'a> {
+    pub kind: &'a str,
+    pub user_id: Option<&'a str>,
+    pub actor: Option<&'a str>,
+    pub ip: Option<&'a str>,
+    pub session_id: Option<&'a str>,
+    pub detail: Option<&'a str>,
+}
+
+impl AdminAuthEvent {
+    pub const SIGN_IN: &'static str = "sign_in";
+    pub const FAILURE: &'static str = "failure";
+    pub const LOCKOUT: &'static str = "lockout";
+
+    fn from_row(row: &libsql::Row) -> Result<Self, libsql::Error> {
+        Ok(Self {
+            id: row.get(0)?,
+            kind: row.get(1)?,
+            user_id: row.get(2).ok(),
+            actor: row.get(3).ok(),
+            ip: row.get(4).ok(),
+            session_id: row.get(5).ok(),
+            detail: row.get(6).ok(),
+            created_ts: row.get(7)?,
+        })
+    }
+
+    /// Record an event. A sign-in for a session that's already recorded is ignored.
+    pub async fn record(conn: &Connection, event: NewAdminAuthEvent<'_>) -> AppResult<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        conn.execute(
+            "INSERT OR IGNORE INTO admin_auth_events (id, kind, user_id, actor, ip, session_id, detail, created_ts)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+            libsql::params![
+                Uuid::new_v4().to_string(),
+                event.kind.to_string(),
+                event.user_id.map(|u| u.to_string()),
+                event.actor.map(|a| a.to_string()),
+                event.ip.map(|i| i.to_string()),
+                event.session_id.map(|s| s.to_string()),
+                event.detail.map(|d| d.to_string()),
+                now
+            ],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        conn.execute("DELETE FROM admin_auth_events WHERE created_ts < ?", [now - RETENTION_SECS])
+            .await
+            .map_err(AppError::from)?;
+
+        Ok(())
+    }
+
+    /// Events of the given kinds since `since_ts`, newest first
+    pub async fn list_since(conn: &Connection, kinds: &[&str], since_ts: i64, limit: i64) -> AppResult<Vec<Self>> {
+        let placeholders = vec!["?"; kinds.len()].join(", ");
+        let query = format!(
+            "SELECT id, kind, user_id, actor, ip, session_id, detail, created_ts FROM admin_auth_events
+             WHERE kind IN ({}) AND created_ts >= ? ORDER BY created_ts DESC LIMIT ?",
+            placeholders
+        );
+        let mut params: Vec<libsql::Value> = kinds.iter().map(|k| k.to_string().into()).collect();
+        params.push(since_ts.into());
+        params.push(limit.into());
+
+        let mut rows = conn.query(&query, params).await.map_err(AppError::from)?;
+
+        let mut events = Vec::new();
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            events.push(Self::from_row(&row).map_err(AppError::from)?);
+        }
+        Ok(events)
+    }
+
+    pub async fn count_since(conn: &Connection, kind: &str, since_ts: i64) -> AppResult<i64> {
+        let mut rows = conn
+            .query(
+                "SELECT COUNT(*) FROM admin_auth_events WHERE kind = ? AND created_ts >= ?",
+                libsql::params![kind, since_ts],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        if let Some(row) = rows.next().await.map_err(AppError::from)? {
+            let count: i64 = row.get(0).map_err(AppError::from)?;
+            Ok(count)
+        } else {
+            Ok(0)
+        }
+    }
+}
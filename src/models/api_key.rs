@@ -0,0 +1,165 @@
+use libsql::Connection;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::error::{AppError, AppResult};
+
+/// Every API key starts with this, so the auth middleware can tell keys from JWTs
+pub const API_KEY_PREFIX: &str = "ccak_";
+
+/// Characters of the key kept in the clear for display
+const DISPLAY_PREFIX_LEN: usize = 12;
+
+/// A scoped key scripts use instead of a Clerk session. The key itself is only
+/// returned once, at creation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub id: String,
+    pub name: String,
+    /// Start of the key, e.g. "ccak_1a2b3c4"
+    pub key_prefix: String,
+    /// Permission names the key grants (see middleware::auth::Permission)
+    pub scopes: Vec<String>,
+    /// Admin who created the key; actions taken with it are attributed to them
+    pub created_by: Option<String>,
+    pub last_used_ts: Option<i64>,
+    pub revoked_ts: Option<i64>,
+    pub created_ts: i64,
+}
+
+impl ApiKey {
+    fn from_row(row: &libsql::Row) -> Result<Self, libsql::Error> {
+        Ok(Self {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            key_prefix: row.get(2)?,
+            // key_hash (column 3) never leaves the database
+            scopes: row
+                .get::<String>(4)
+                .ok()
+                .and_then(|s| serde_json::from_str(&s).ok())
+                .unwrap_or_default(),
+            created_by: row.get(5).ok(),
+            last_used_ts: row.get(6).ok(),
+            revoked_ts: row.get(7).ok(),
+            created_ts: row.get(8)?,
+        })
+    }
+
+    fn now() -> i64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64
+    }
+
+    /// How a key is stored: hex SHA-256 of the full key
+    pub fn hash(key: &str) -> String {
+        hex::encode(Sha256::digest(key.as_bytes()))
+    }
+
+    /// Whether the key still authenticates, i.e. hasn't been revoked
+    pub fn is_active(&self) -> bool {
+        self.revoked_ts.is_none()
+    }
+
+    pub async fn find_by_id(conn: &Connection, id: &str) -> AppResult<Option<Self>> {
+        let mut rows = conn
+            .query("SELECT * FROM api_keys WHERE id = ?", [id])
+            .await
+            .map_err(AppError::from)?;
+
+        match rows.next().await.map_err(AppError::from)? {
+            Some(row) => Ok(Some(Self::from_row(&row).map_err(AppError::from)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// The unrevoked key matching a presented secret
+    pub async fn find_active_by_key(conn: &Connection, key: &str) -> AppResult<Option<Self>> {
+        let mut rows = conn
+            .query("SELECT * FROM api_keys WHERE key_hash = ?", [Self::hash(key)])
+            .await
+            .map_err(AppError::from)?;
+
+        match rows.next().await.map_err(AppError::from)? {
+            Some(row) => Ok(Some(Self::from_row(&row).map_err(AppError::from)?).filter(Self::is_active)),
+            None => Ok(None),
+        }
+    }
+
+    /// All keys, newest first, including revoked ones
+    pub async fn list_all(conn: &Connection) -> AppResult<Vec<Self>> {
+        let mut rows = conn
+            .query("SELECT * FROM api_keys ORDER BY created_ts DESC", ())
+            .await
+            .map_err(AppError::from)?;
+
+        let mut keys = Vec::new();
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            keys.push(Self::from_row(&row).map_err(AppError::from)?);
+        }
+        Ok(keys)
+    }
+
+    /// Create a key. Returns the record and the full secret, which is not stored.
+    pub async fn create(
+        conn: &Connection,
+        name: &str,
+        scopes: &[String],
+        created_by: Option<&str>,
+    ) -> AppResult<(Self, String)> {
+        let id = Uuid::new_v4().to_string();
+        let key = format!(
+            "{}{}{}",
+            API_KEY_PREFIX,
+            Uuid::new_v4().simple(),
+            Uuid::new_v4().simple()
+        );
+        let scopes_json = serde_json::to_string(scopes).map_err(|e| AppError::Internal(e.to_string()))?;
+
+        conn.execute(
+            "INSERT INTO api_keys (id, name, key_prefix, key_hash, scopes, created_by, created_ts) VALUES (?, ?, ?, ?, ?, ?, ?)",
+            libsql::params![
+                id.clone(),
+                name.to_string(),
+                key[..DISPLAY_PREFIX_LEN].to_string(),
+                Self::hash(&key),
+                scopes_json,
+                created_by.map(|u| u.to_string()),
+                Self::now()
+            ],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        let api_key = Self::find_by_id(conn, &id)
+            .await?
+            .ok_or_else(|| AppError::Internal("Failed to create API key".to_string()))?;
+        Ok((api_key, key))
+    }
+
+    pub async fn touch(conn: &Connection, id: &str) -> AppResult<()> {
+        conn.execute(
+            "UPDATE api_keys SET last_used_ts = ? WHERE id = ?",
+            libsql::params![Self::now(), id.to_string()],
+        )
+        .await
+        .map_err(AppError::from)?;
+        Ok(())
+    }
+
+    /// Revoke a key. Returns false if it doesn't exist or was already revoked.
+    pub async fn revoke(conn: &Connection, id: &str) -> AppResult<bool> {
+        let result = conn
+            .execute(
+                "UPDATE api_keys SET revoked_ts = ? WHERE id = ? AND revoked_ts IS NULL",
+                libsql::params![Self::now(), id.to_string()],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        Ok(result > 0)
+    }
+}
@@ -0,0 +1,134 @@
+use libsql::Connection;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::{AppError, AppResult};
+
+/// An artist selling through the shop with their own Stripe connected account
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Artist {
+    pub id: String,
+    pub name: String,
+    pub email: Option<String>,
+    pub stripe_account_id: Option<String>,
+    /// Stripe lets the account accept payments (onboarding finished)
+    pub charges_enabled: bool,
+    pub payouts_enabled: bool,
+    pub created_ts: i64,
+    pub updated_ts: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateArtist {
+    pub name: String,
+    pub email: Option<String>,
+}
+
+impl Artist {
+    fn from_row(row: &libsql::Row) -> Result<Self, libsql::Error> {
+        Ok(Self {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            email: row.get(2).ok(),
+            stripe_account_id: row.get(3).ok(),
+            charges_enabled: row.get::<i32>(4).map(|v| v != 0).unwrap_or(false),
+            payouts_enabled: row.get::<i32>(5).map(|v| v != 0).unwrap_or(false),
+            created_ts: row.get(6)?,
+            updated_ts: row.get(7)?,
+        })
+    }
+
+    pub async fn list_all(conn: &Connection) -> AppResult<Vec<Self>> {
+        let mut rows = conn
+            .query("SELECT * FROM artists ORDER BY name ASC", ())
+            .await
+            .map_err(AppError::from)?;
+
+        let mut artists = Vec::new();
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            artists.push(Self::from_row(&row).map_err(AppError::from)?);
+        }
+        Ok(artists)
+    }
+
+    pub async fn find_by_id(conn: &Connection, id: &str) -> AppResult<Option<Self>> {
+        let mut rows = conn
+            .query("SELECT * FROM artists WHERE id = ?", [id])
+            .await
+            .map_err(AppError::from)?;
+
+        match rows.next().await.map_err(AppError::from)? {
+            Some(row) => Ok(Some(Self::from_row(&row).map_err(AppError::from)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn find_by_stripe_account(conn: &Connection, account_id: &str) -> AppResult<Option<Self>> {
+        let mut rows = conn
+            .query("SELECT * FROM artists WHERE stripe_account_id = ?", [account_id])
+            .await
+            .map_err(AppError::from)?;
+
+        match rows.next().await.map_err(AppError::from)? {
+            Some(row) => Ok(Some(Self::from_row(&row).map_err(AppError::from)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn create(conn: &Connection, data: CreateArtist) -> AppResult<Self> {
+        let id = Uuid::new_v4().to_string();
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        conn.execute(
+            "INSERT INTO artists (id, name, email, created_ts, updated_ts) VALUES (?, ?, ?, ?, ?)",
+            libsql::params![id.clone(), data.name, data.email, now, now],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Self::find_by_id(conn, &id)
+            .await?
+            .ok_or_else(|| AppError::Internal("Failed to create artist".to_string()))
+    }
+
+    pub async fn set_stripe_account(conn: &Connection, id: &str, account_id: &str) -> AppResult<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        conn.execute(
+            "UPDATE artists SET stripe_account_id = ?, updated_ts = ? WHERE id = ?",
+            libsql::params![account_id.to_string(), now, id.to_string()],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Ok(())
+    }
+
+    /// Store what Stripe reports about the connected account's onboarding
+    pub async fn set_account_status(
+        conn: &Connection,
+        id: &str,
+        charges_enabled: bool,
+        payouts_enabled: bool,
+    ) -> AppResult<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        conn.execute(
+            "UPDATE artists SET charges_enabled = ?, payouts_enabled = ?, updated_ts = ? WHERE id = ?",
+            libsql::params![charges_enabled as i32, payouts_enabled as i32, now, id.to_string()],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Ok(())
+    }
+}
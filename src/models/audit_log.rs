@@ -0,0 +1,123 @@
+use libsql::Connection;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::{AppError, AppResult};
+
+/// A change made (or attempted) through the admin API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditLogEntry {
+    pub id: String,
+    pub user_id: Option<String>,
+    /// Email of the staff user, or api-key:<prefix>
+    pub actor: String,
+    pub method: String,
+    /// Route pattern, e.g. /products/{id}
+    pub route: String,
+    pub target_id: Option<String>,
+    /// Fields sent in the request body
+    pub summary: Option<String>,
+    pub status: i32,
+    pub created_ts: i64,
+}
+
+/// What to record for one admin request
+pub struct NewAuditLogEntry<'a> {
+    pub user_id: Option<&'a str>,
+    pub actor: &'a str,
+    pub method: &'a str,
+    pub route: &'a str,
+    pub target_id: Option<&'a str>,
+    pub summary: Option<&'a str>,
+    pub status: u16,
+}
+
+/// Filters for the audit log viewer; every field is optional
+#[derive(Debug, Default)]
+pub struct AuditLogFilter {
+    pub user_id: Option<String>,
+    pub target_id: Option<String>,
+    /// Part of the route pattern, e.g. "refund" or "/settings"
+    pub route: Option<String>,
+    pub since_ts: Option<i64>,
+    pub until_ts: Option<i64>,
+}
+
+impl AuditLogEntry {
+    fn from_row(row: &libsql::Row) -> Result<Self, libsql::Error> {
+        Ok(Self {
+            id: row.get(0)?,
+            user_id: row.get(1).ok(),
+            actor: row.get(2)?,
+            method: row.get(3)?,
+            route: row.get(4)?,
+            target_id: row.get(5).ok(),
+            summary: row.get(6).ok(),
+            status: row.get(7)?,
+            created_ts: row.get(8)?,
+        })
+    }
+
+    pub async fn record(conn: &Connection, entry: NewAuditLogEntry<'_>) -> AppResult<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        conn.execute(
+            "INSERT INTO audit_log (id, user_id, actor, method, route, target_id, summary, status, created_ts) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            libsql::params![
+                Uuid::new_v4().to_string(),
+                entry.user_id.map(|u| u.to_string()),
+                entry.actor.to_string(),
+                entry.method.to_string(),
+                entry.route.to_string(),
+                entry.target_id.map(|t| t.to_string()),
+                entry.summary.map(|s| s.to_string()),
+                entry.status as i32,
+                now
+            ],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Ok(())
+    }
+
+    /// Entries matching the filter, newest first
+    pub async fn list(conn: &Connection, filter: &AuditLogFilter, limit: i64) -> AppResult<Vec<Self>> {
+        let mut query = String::from("SELECT * FROM audit_log WHERE 1 = 1");
+        let mut params: Vec<libsql::Value> = Vec::new();
+
+        if let Some(user_id) = filter.user_id.as_deref().filter(|u| !u.is_empty()) {
+            query.push_str(" AND user_id = ?");
+            params.push(user_id.to_string().into());
+        }
+        if let Some(target_id) = filter.target_id.as_deref().filter(|t| !t.is_empty()) {
+            query.push_str(" AND target_id = ?");
+            params.push(target_id.to_string().into());
+        }
+        if let Some(route) = filter.route.as_deref().map(str::trim).filter(|r| !r.is_empty()) {
+            query.push_str(" AND route LIKE ?");
+            params.push(format!("%{}%", route).into());
+        }
+        if let Some(since_ts) = filter.since_ts {
+            query.push_str(" AND created_ts >= ?");
+            params.push(since_ts.into());
+        }
+        if let Some(until_ts) = filter.until_ts {
+            query.push_str(" AND created_ts < ?");
+            params.push(until_ts.into());
+        }
+        query.push_str(" ORDER BY created_ts DESC LIMIT ?");
+        params.push(limit.into());
+
+        let mut rows = conn.query(&query, params).await.map_err(AppError::from)?;
+
+        let mut entries = Vec::new();
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            entries.push(Self::from_row(&row).map_err(AppError::from)?);
+        }
+        Ok(entries)
+    }
+}
@@ -0,0 +1,162 @@
+use libsql::Connection;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::{AppError, AppResult};
+
+/// A shipping box the shop keeps in stock
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BoxPreset {
+    pub id: String,
+    pub name: String,
+    pub length_cm: f64,
+    pub width_cm: f64,
+    pub height_cm: f64,
+    /// Weight of the empty box and packing material
+    pub empty_weight_grams: i32,
+    pub created_ts: i64,
+    pub updated_ts: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SaveBoxPreset {
+    pub name: String,
+    pub length_cm: f64,
+    pub width_cm: f64,
+    pub height_cm: f64,
+    #[serde(default)]
+    pub empty_weight_grams: i32,
+}
+
+impl BoxPreset {
+    fn from_row(row: &libsql::Row) -> Result<Self, libsql::Error> {
+        Ok(Self {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            length_cm: row.get(2)?,
+            width_cm: row.get(3)?,
+            height_cm: row.get(4)?,
+            empty_weight_grams: row.get(5).unwrap_or(0),
+            created_ts: row.get(6)?,
+            updated_ts: row.get(7)?,
+        })
+    }
+
+    pub fn volume(&self) -> f64 {
+        self.length_cm * self.width_cm * self.height_cm
+    }
+
+    /// Whether contents of these dimensions fit inside, in any orientation
+    pub fn fits(&self, length_cm: f64, width_cm: f64, height_cm: f64) -> bool {
+        let mut inner = [self.length_cm, self.width_cm, self.height_cm];
+        let mut contents = [length_cm, width_cm, height_cm];
+        inner.sort_by(|a, b| a.total_cmp(b));
+        contents.sort_by(|a, b| a.total_cmp(b));
+        inner.iter().zip(contents.iter()).all(|(box_side, side)| side <= box_side)
+    }
+
+    pub async fn find_by_id(conn: &Connection, id: &str) -> AppResult<Option<Self>> {
+        let mut rows = conn
+            .query("SELECT * FROM box_presets WHERE id = ?", [id])
+            .await
+            .map_err(AppError::from)?;
+
+        match rows.next().await.map_err(AppError::from)? {
+            Some(row) => Ok(Some(Self::from_row(&row).map_err(AppError::from)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Every box, smallest first
+    pub async fn list_all(conn: &Connection) -> AppResult<Vec<Self>> {
+        let mut rows = conn
+            .query(
+                "SELECT * FROM box_presets ORDER BY length_cm * width_cm * height_cm ASC, name ASC",
+                (),
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        let mut boxes = Vec::new();
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            boxes.push(Self::from_row(&row).map_err(AppError::from)?);
+        }
+        Ok(boxes)
+    }
+
+    pub async fn create(conn: &Connection, data: SaveBoxPreset) -> AppResult<Self> {
+        let id = Uuid::new_v4().to_string();
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        conn.execute(
+            "INSERT INTO box_presets (id, name, length_cm, width_cm, height_cm, empty_weight_grams, created_ts, updated_ts) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+            libsql::params![
+                id.clone(),
+                data.name.trim().to_string(),
+                data.length_cm,
+                data.width_cm,
+                data.height_cm,
+                data.empty_weight_grams,
+                now,
+                now
+            ],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Self::find_by_id(conn, &id)
+            .await?
+            .ok_or_else(|| AppError::Internal("Failed to create box preset".to_string()))
+    }
+
+    pub async fn update(conn: &Connection, id: &str, data: SaveBoxPreset) -> AppResult<Self> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        let result = conn
+            .execute(
+                "UPDATE box_presets SET name = ?, length_cm = ?, width_cm = ?, height_cm = ?, empty_weight_grams = ?, updated_ts = ? WHERE id = ?",
+                libsql::params![
+                    data.name.trim().to_string(),
+                    data.length_cm,
+                    data.width_cm,
+                    data.height_cm,
+                    data.empty_weight_grams,
+                    now,
+                    id.to_string()
+                ],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        if result == 0 {
+            return Err(AppError::NotFound("Box preset not found".to_string()));
+        }
+
+        Self::find_by_id(conn, id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Box preset not found".to_string()))
+    }
+
+    /// Delete a box; products and styles that preferred it fall back to automatic packing
+    pub async fn delete(conn: &Connection, id: &str) -> AppResult<bool> {
+        conn.execute("UPDATE products SET box_preset_id = NULL WHERE box_preset_id = ?", [id])
+            .await
+            .map_err(AppError::from)?;
+        conn.execute("UPDATE product_styles SET box_preset_id = NULL WHERE box_preset_id = ?", [id])
+            .await
+            .map_err(AppError::from)?;
+
+        let result = conn
+            .execute("DELETE FROM box_presets WHERE id = ?", [id])
+            .await
+            .map_err(AppError::from)?;
+
+        Ok(result > 0)
+    }
+}
@@ -0,0 +1,202 @@
+use libsql::Connection;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::{AppError, AppResult};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscountCode {
+    pub id: String,
+    pub code: String,
+    pub percent_off: Option<i32>,
+    pub amount_off_cents: Option<i32>,
+    pub min_subtotal_cents: i32,
+    pub max_redemptions: Option<i32>,
+    pub times_redeemed: i32,
+    pub expires_ts: Option<i64>,
+    pub is_active: bool,
+    pub created_ts: i64,
+    pub updated_ts: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateDiscountCode {
+    pub code: String,
+    pub percent_off: Option<i32>,
+    pub amount_off_cents: Option<i32>,
+    #[serde(default)]
+    pub min_subtotal_cents: i32,
+    pub max_redemptions: Option<i32>,
+    pub expires_ts: Option<i64>,
+}
+
+impl DiscountCode {
+    fn from_row(row: &libsql::Row) -> Result<Self, libsql::Error> {
+        Ok(Self {
+            id: row.get(0)?,
+            code: row.get(1)?,
+            percent_off: row.get(2).ok(),
+            amount_off_cents: row.get(3).ok(),
+            min_subtotal_cents: row.get(4).unwrap_or(0),
+            max_redemptions: row.get(5).ok(),
+            times_redeemed: row.get(6).unwrap_or(0),
+            expires_ts: row.get(7).ok(),
+            is_active: row.get::<i32>(8).map(|v| v != 0).unwrap_or(false),
+            created_ts: row.get(9)?,
+            updated_ts: row.get(10)?,
+        })
+    }
+
+    /// Codes are stored uppercase so lookups are case-insensitive
+    pub fn normalize(code: &str) -> String {
+        code.trim().to_uppercase()
+    }
+
+    /// Check that the code can be used on a cart with this merchandise subtotal
+    pub fn check_redeemable(&self, subtotal_cents: i32) -> AppResult<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        if !self.is_active {
+            return Err(AppError::BadRequest("This promo code is no longer active".to_string()));
+        }
+        if self.expires_ts.map(|ts| ts <= now).unwrap_or(false) {
+            return Err(AppError::BadRequest("This promo code has expired".to_string()));
+        }
+        if self.max_redemptions.map(|max| self.times_redeemed >= max).unwrap_or(false) {
+            return Err(AppError::BadRequest("This promo code has been fully redeemed".to_string()));
+        }
+        if subtotal_cents < self.min_subtotal_cents {
+            return Err(AppError::BadRequest(format!(
+                "This promo code requires a subtotal of at least ${:.2}",
+                self.min_subtotal_cents as f64 / 100.0
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Discount in cents for a merchandise subtotal, never more than the subtotal
+    pub fn discount_for(&self, subtotal_cents: i32) -> i32 {
+        let discount = match (self.percent_off, self.amount_off_cents) {
+            (Some(percent), _) => (subtotal_cents as i64 * percent as i64 / 100) as i32,
+            (None, Some(amount)) => amount,
+            (None, None) => 0,
+        };
+        discount.clamp(0, subtotal_cents)
+    }
+
+    pub async fn find_by_id(conn: &Connection, id: &str) -> AppResult<Option<Self>> {
+        let mut rows = conn
+            .query("SELECT * FROM discount_codes WHERE id = ?", [id])
+            .await
+            .map_err(AppError::from)?;
+
+        if let Some(row) = rows.next().await.map_err(AppError::from)? {
+            Ok(Some(Self::from_row(&row).map_err(AppError::from)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub async fn find_by_code(conn: &Connection, code: &str) -> AppResult<Option<Self>> {
+        let mut rows = conn
+            .query("SELECT * FROM discount_codes WHERE code = ?", [Self::normalize(code)])
+            .await
+            .map_err(AppError::from)?;
+
+        if let Some(row) = rows.next().await.map_err(AppError::from)? {
+            Ok(Some(Self::from_row(&row).map_err(AppError::from)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub async fn list_all(conn: &Connection) -> AppResult<Vec<Self>> {
+        let mut rows = conn
+            .query("SELECT * FROM discount_codes ORDER BY created_ts DESC", ())
+            .await
+            .map_err(AppError::from)?;
+
+        let mut codes = Vec::new();
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            codes.push(Self::from_row(&row).map_err(AppError::from)?);
+        }
+        Ok(codes)
+    }
+
+    pub async fn create(conn: &Connection, data: CreateDiscountCode) -> AppResult<Self> {
+        let id = Uuid::new_v4().to_string();
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        conn.execute(
+            "INSERT INTO discount_codes (id, code, percent_off, amount_off_cents, min_subtotal_cents, max_redemptions, expires_ts, is_active, created_ts, updated_ts) VALUES (?, ?, ?, ?, ?, ?, ?, 1, ?, ?)",
+            libsql::params![
+                id.clone(),
+                Self::normalize(&data.code),
+                data.percent_off,
+                data.amount_off_cents,
+                data.min_subtotal_cents,
+                data.max_redemptions,
+                data.expires_ts,
+                now,
+                now
+            ],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Self::find_by_id(conn, &id)
+            .await?
+            .ok_or_else(|| AppError::Internal("Failed to create discount code".to_string()))
+    }
+
+    pub async fn set_active(conn: &Connection, id: &str, is_active: bool) -> AppResult<Self> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        conn.execute(
+            "UPDATE discount_codes SET is_active = ?, updated_ts = ? WHERE id = ?",
+            libsql::params![is_active as i32, now, id.to_string()],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Self::find_by_id(conn, id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Discount code not found".to_string()))
+    }
+
+    pub async fn delete(conn: &Connection, id: &str) -> AppResult<bool> {
+        let result = conn
+            .execute("DELETE FROM discount_codes WHERE id = ?", [id])
+            .await
+            .map_err(AppError::from)?;
+
+        Ok(result > 0)
+    }
+
+    /// Count a redemption once the order using the code is paid
+    pub async fn record_redemption(conn: &Connection, code: &str) -> AppResult<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        conn.execute(
+            "UPDATE discount_codes SET times_redeemed = times_redeemed + 1, updated_ts = ? WHERE code = ?",
+            libsql::params![now, Self::normalize(code)],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,181 @@
+use libsql::Connection;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::{AppError, AppResult};
+
+/// Delivery statuses a sent email can move through, in order. A Resend event only
+/// moves an email forward, so a late "delivered" never hides an "opened".
+pub const DELIVERY_STATUSES: &[&str] = &["sent", "delayed", "delivered", "opened", "clicked", "bounced", "complained"];
+
+/// One attempt to send an email, successful or not
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailLog {
+    pub id: String,
+    pub recipient: String,
+    pub email_type: String,
+    pub subject: String,
+    /// "smtp" or "resend"
+    pub provider: String,
+    /// "failed", or "sent" moving on through DELIVERY_STATUSES as Resend reports events
+    pub status: String,
+    pub provider_id: Option<String>,
+    pub error: Option<String>,
+    pub created_ts: i64,
+    /// When the last delivery event arrived
+    pub delivery_ts: Option<i64>,
+}
+
+/// Filters for the admin email log; every field is optional
+#[derive(Debug, Default)]
+pub struct EmailLogFilter {
+    /// Part of the recipient address
+    pub recipient: Option<String>,
+    pub email_type: Option<String>,
+    pub status: Option<String>,
+    pub since_ts: Option<i64>,
+    pub until_ts: Option<i64>,
+}
+
+impl EmailLog {
+    fn from_row(row: &libsql::Row) -> Result<Self, libsql::Error> {
+        Ok(Self {
+            id: row.get(0)?,
+            recipient: row.get(1)?,
+            email_type: row.get(2)?,
+            subject: row.get(3)?,
+            provider: row.get(4)?,
+            status: row.get(5)?,
+            provider_id: row.get(6).ok(),
+            error: row.get(7).ok(),
+            created_ts: row.get(8)?,
+            delivery_ts: row.get(9).ok(),
+        })
+    }
+
+    /// Replace an address in the log, for an account deletion. Returns how many
+    /// entries were changed.
+    pub async fn redact_recipient(conn: &Connection, email: &str, replacement: &str) -> AppResult<u64> {
+        let result = conn
+            .execute(
+                "UPDATE email_log SET recipient = ? WHERE lower(recipient) = ?",
+                [replacement.to_string(), email.to_lowercase()],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        Ok(result)
+    }
+
+    /// Record a send attempt; it failed when there's an `error`
+    pub async fn record(
+        conn: &Connection,
+        recipient: &str,
+        email_type: &str,
+        subject: &str,
+        provider: &str,
+        provider_id: Option<&str>,
+        error: Option<&str>,
+    ) -> AppResult<()> {
+        let id = Uuid::new_v4().to_string();
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        let status = if error.is_some() { "failed" } else { "sent" };
+
+        conn.execute(
+            "INSERT INTO email_log (id, recipient, email_type, subject, provider, status, provider_id, error, created_ts) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            libsql::params![
+                id,
+                recipient.to_string(),
+                email_type.to_string(),
+                subject.to_string(),
+                provider.to_string(),
+                status,
+                provider_id.map(|s| s.to_string()),
+                error.map(|s| s.to_string()),
+                now
+            ],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Ok(())
+    }
+
+    /// Apply a provider delivery event to the email it sent. Statuses only move forward
+    /// through DELIVERY_STATUSES. Returns false if no logged email matches or the status
+    /// was already at or past this one.
+    pub async fn record_delivery(conn: &Connection, provider_id: &str, status: &str) -> AppResult<bool> {
+        let rank = match DELIVERY_STATUSES.iter().position(|s| *s == status) {
+            Some(rank) => rank as i64,
+            None => return Err(AppError::Internal(format!("Unknown delivery status: {}", status))),
+        };
+
+        let current_rank = DELIVERY_STATUSES
+            .iter()
+            .enumerate()
+            .map(|(rank, status)| format!("WHEN '{}' THEN {}", status, rank))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        let updated = conn
+            .execute(
+                &format!(
+                    "UPDATE email_log SET status = ?, delivery_ts = ?
+                     WHERE provider_id = ? AND (CASE status {} ELSE {} END) < ?",
+                    current_rank,
+                    DELIVERY_STATUSES.len()
+                ),
+                libsql::params![status.to_string(), now, provider_id.to_string(), rank],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        Ok(updated > 0)
+    }
+
+    /// Most recent attempts first
+    pub async fn list(conn: &Connection, filter: &EmailLogFilter, limit: i64) -> AppResult<Vec<Self>> {
+        let mut query = String::from("SELECT * FROM email_log WHERE 1 = 1");
+        let mut params: Vec<libsql::Value> = Vec::new();
+
+        if let Some(recipient) = filter.recipient.as_deref().map(str::trim).filter(|r| !r.is_empty()) {
+            query.push_str(" AND recipient LIKE ?");
+            params.push(format!("%{}%", recipient).into());
+        }
+        if let Some(email_type) = filter.email_type.as_deref().filter(|t| !t.is_empty()) {
+            query.push_str(" AND email_type = ?");
+            params.push(email_type.to_string().into());
+        }
+        if let Some(status) = filter.status.as_deref().filter(|s| !s.is_empty()) {
+            query.push_str(" AND status = ?");
+            params.push(status.to_string().into());
+        }
+        if let Some(since_ts) = filter.since_ts {
+            query.push_str(" AND created_ts >= ?");
+            params.push(since_ts.into());
+        }
+        if let Some(until_ts) = filter.until_ts {
+            query.push_str(" AND created_ts < ?");
+            params.push(until_ts.into());
+        }
+        query.push_str(" ORDER BY created_ts DESC LIMIT ?");
+        params.push(limit.into());
+
+        let mut rows = conn.query(&query, params).await.map_err(AppError::from)?;
+
+        let mut entries = Vec::new();
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            entries.push(Self::from_row(&row).map_err(AppError::from)?);
+        }
+        Ok(entries)
+    }
+}
@@ -0,0 +1,106 @@
+use std::collections::HashSet;
+
+use libsql::Connection;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, AppResult};
+
+/// An address batch sends skip because it bounced or marked us as spam
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailSuppression {
+    pub email: String,
+    /// "bounce", "complaint", or "manual" when an admin suppressed it
+    pub reason: String,
+    pub detail: Option<String>,
+    pub created_ts: i64,
+}
+
+impl EmailSuppression {
+    fn from_row(row: &libsql::Row) -> Result<Self, libsql::Error> {
+        Ok(Self {
+            email: row.get(0)?,
+            reason: row.get(1)?,
+            detail: row.get(2).ok(),
+            created_ts: row.get(3)?,
+        })
+    }
+
+    /// Suppress an address; a later complaint replaces an earlier bounce and vice versa
+    pub async fn suppress(conn: &Connection, email: &str, reason: &str, detail: Option<&str>) -> AppResult<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        conn.execute(
+            "INSERT INTO email_suppressions (email, reason, detail, created_ts) VALUES (?, ?, ?, ?)
+             ON CONFLICT(email) DO UPDATE SET reason = excluded.reason, detail = excluded.detail, created_ts = excluded.created_ts",
+            libsql::params![
+                email.trim().to_lowercase(),
+                reason.to_string(),
+                detail.map(|d| d.to_string()),
+                now
+            ],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Ok(())
+    }
+
+    /// Newest first
+    pub async fn list_all(conn: &Connection) -> AppResult<Vec<Self>> {
+        let mut rows = conn
+            .query("SELECT * FROM email_suppressions ORDER BY created_ts DESC", ())
+            .await
+            .map_err(AppError::from)?;
+
+        let mut suppressions = Vec::new();
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            suppressions.push(Self::from_row(&row).map_err(AppError::from)?);
+        }
+        Ok(suppressions)
+    }
+
+    /// Every suppressed address (lowercase), for filtering a batch send
+    pub async fn emails(conn: &Connection) -> AppResult<HashSet<String>> {
+        let mut rows = conn
+            .query("SELECT email FROM email_suppressions", ())
+            .await
+            .map_err(AppError::from)?;
+
+        let mut emails = HashSet::new();
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            emails.insert(row.get::<String>(0).map_err(AppError::from)?);
+        }
+        Ok(emails)
+    }
+
+    pub async fn find(conn: &Connection, email: &str) -> AppResult<Option<Self>> {
+        let mut rows = conn
+            .query(
+                "SELECT * FROM email_suppressions WHERE email = ?",
+                [email.trim().to_lowercase()],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        match rows.next().await.map_err(AppError::from)? {
+            Some(row) => Ok(Some(Self::from_row(&row).map_err(AppError::from)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Lift a suppression, e.g. once a full mailbox has been cleared
+    pub async fn delete(conn: &Connection, email: &str) -> AppResult<bool> {
+        let result = conn
+            .execute(
+                "DELETE FROM email_suppressions WHERE email = ?",
+                [email.trim().to_lowercase()],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        Ok(result > 0)
+    }
+}
@@ -0,0 +1,100 @@
+use libsql::Connection;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::error::{AppError, AppResult};
+
+/// Every impersonation token starts with this
+pub const IMPERSONATION_TOKEN_PREFIX: &str = "ccim_";
+
+/// How long an impersonation token lasts
+pub const IMPERSONATION_TTL_SECS: i64 = 15 * 60;
+
+fn now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
+
+/// Read-only access to the storefront API as a customer, issued by an owner
+/// for support
+#[derive(Debug, Clone, Serialize)]
+pub struct ImpersonationToken {
+    /// Customer being impersonated
+    pub user_id: String,
+    /// Owner who issued it
+    pub created_by: String,
+    pub reason: String,
+    pub expires_ts: i64,
+    pub created_ts: i64,
+}
+
+impl ImpersonationToken {
+    fn from_row(row: &libsql::Row) -> Result<Self, libsql::Error> {
+        Ok(Self {
+            user_id: row.get(0)?,
+            created_by: row.get(1)?,
+            reason: row.get(2)?,
+            expires_ts: row.get(3)?,
+            created_ts: row.get(4)?,
+        })
+    }
+
+    fn hash(token: &str) -> String {
+        hex::encode(Sha256::digest(token.as_bytes()))
+    }
+
+    /// Issue a token to act as `user_id`. Returns the token and when it expires.
+    pub async fn issue(conn: &Connection, user_id: &str, created_by: &str, reason: &str) -> AppResult<(String, i64)> {
+        let token = format!(
+            "{}{}{}",
+            IMPERSONATION_TOKEN_PREFIX,
+            Uuid::new_v4().simple(),
+            Uuid::new_v4().simple()
+        );
+        let now = now();
+        let expires_ts = now + IMPERSONATION_TTL_SECS;
+
+        conn.execute(
+            "INSERT INTO impersonation_tokens (token_hash, user_id, created_by, reason, expires_ts, created_ts) VALUES (?, ?, ?, ?, ?, ?)",
+            libsql::params![Self::hash(&token), user_id, created_by, reason, expires_ts, now],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Ok((token, expires_ts))
+    }
+
+    /// The token's grant, if it's unexpired and not revoked
+    pub async fn find_valid(conn: &Connection, token: &str) -> AppResult<Option<Self>> {
+        let mut rows = conn
+            .query(
+                "SELECT user_id, created_by, reason, expires_ts, created_ts FROM impersonation_tokens
+                 WHERE token_hash = ? AND expires_ts >= ? AND revoked_ts IS NULL",
+                libsql::params![Self::hash(token), now()],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        match rows.next().await.map_err(AppError::from)? {
+            Some(row) => Ok(Some(Self::from_row(&row).map_err(AppError::from)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// End every live impersonation of a customer. Returns how many were ended.
+    pub async fn revoke_for_user(conn: &Connection, user_id: &str) -> AppResult<u64> {
+        let now = now();
+        let result = conn
+            .execute(
+                "UPDATE impersonation_tokens SET revoked_ts = ? WHERE user_id = ? AND revoked_ts IS NULL AND expires_ts >= ?",
+                libsql::params![now, user_id, now],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        Ok(result)
+    }
+}
@@ -1,15 +1,67 @@
+pub mod address;
+pub mod admin_auth_event;
+pub mod api_key;
+pub mod artist;
+pub mod audit_log;
+pub mod box_preset;
+pub mod discount_code;
+pub mod email_log;
+pub mod email_suppression;
+pub mod impersonation;
+pub mod money;
 pub mod newsletter;
+pub mod newsletter_announcement;
+pub mod newsletter_campaign;
+pub mod newsletter_segment;
+pub mod newsletter_tracking;
 pub mod order;
 pub mod product;
 pub mod product_notification;
 pub mod product_style;
+pub mod revoked_session;
 pub mod settings;
+pub mod shipping_rule;
+pub mod step_up;
+pub mod subscription;
 pub mod user;
+pub mod webhook_event;
+pub mod webhook_job;
+pub mod win_back;
+pub mod wishlist;
 
+pub use address::{Address, SaveAddress};
+pub use admin_auth_event::{AdminAuthEvent, NewAdminAuthEvent};
+pub use api_key::ApiKey;
+pub use artist::{Artist, CreateArtist};
+pub use audit_log::{AuditLogEntry, AuditLogFilter, NewAuditLogEntry};
+pub use box_preset::{BoxPreset, SaveBoxPreset};
+pub use discount_code::{CreateDiscountCode, DiscountCode};
+pub use email_log::{EmailLog, EmailLogFilter};
+pub use email_suppression::EmailSuppression;
+pub use impersonation::ImpersonationToken;
+pub use money::Money;
 pub use newsletter::NewsletterSubscriber;
-pub use order::{CreateOrder, CreateOrderItem, Order, OrderItem, OrderStatus, ShippingAddress};
+pub use newsletter_announcement::NewsletterAnnouncement;
+pub use newsletter_campaign::{NewsletterCampaign, SaveNewsletterCampaign};
+pub use newsletter_segment::{NewsletterSegment, SaveNewsletterSegment, SegmentFilters};
+pub use newsletter_tracking::{CampaignStats, NewsletterLink};
+pub use order::{
+    CreateOrder, CreateOrderItem, Order, OrderItemDetail, OrderStatus, ShippingAddress, ShippingRateRecord,
+    LOCAL_DELIVERY_RATE_ID,
+};
 pub use product::{CreateProduct, Product, ProductImage, UpdateProduct};
 pub use product_notification::ProductNotification;
 pub use product_style::ProductStyle;
-pub use settings::{ArtistInfo, Setting, ShopAddress};
+pub use revoked_session::RevokedSession;
+pub use settings::{
+    ArtistInfo, EmailBranding, FallbackRates, LocalDelivery, OrderAlerts, RateFilter, Setting, ShopAddress,
+    SignatureDefaults, WeeklyDigest, WinBack,
+};
+pub use shipping_rule::{SaveShippingRule, ShippingRule, WeightTier};
+pub use step_up::{AdminTotp, StepUpToken};
+pub use subscription::{CreateSubscription, Subscription};
 pub use user::{CreateUser, User};
+pub use webhook_event::WebhookEvent;
+pub use webhook_job::WebhookJob;
+pub use wishlist::WishlistItem;
+pub use win_back::WinBackEmail;
@@ -0,0 +1,82 @@
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+/// An amount in the smallest unit of its currency. Prices are stored and computed
+/// in integer cents; this is what API responses expose instead of float dollars.
+///
+/// Serializes as `{"cents": 1250, "currency": "usd", "formatted": "$12.50"}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Money {
+    pub cents: i64,
+    pub currency: &'static str,
+}
+
+impl Money {
+    /// The shop only sells in US dollars
+    pub const USD: &'static str = "usd";
+
+    pub fn usd(cents: impl Into<i64>) -> Self {
+        Self {
+            cents: cents.into(),
+            currency: Self::USD,
+        }
+    }
+
+    fn symbol(&self) -> &'static str {
+        match self.currency {
+            "usd" => "$",
+            _ => "",
+        }
+    }
+}
+
+impl std::fmt::Display for Money {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sign = if self.cents < 0 { "-" } else { "" };
+        let abs = self.cents.unsigned_abs();
+        write!(f, "{}{}{}.{:02}", sign, self.symbol(), abs / 100, abs % 100)?;
+        if self.symbol().is_empty() {
+            write!(f, " {}", self.currency.to_uppercase())?;
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("Money", 3)?;
+        s.serialize_field("cents", &self.cents)?;
+        s.serialize_field("currency", self.currency)?;
+        s.serialize_field("formatted", &self.to_string())?;
+        s.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Money;
+
+    #[test]
+    fn formats_dollars_and_cents() {
+        assert_eq!(Money::usd(0).to_string(), "$0.00");
+        assert_eq!(Money::usd(5).to_string(), "$0.05");
+        assert_eq!(Money::usd(2400).to_string(), "$24.00");
+        assert_eq!(Money::usd(123_456).to_string(), "$1234.56");
+    }
+
+    #[test]
+    fn negative_amounts_put_the_sign_first() {
+        assert_eq!(Money::usd(-150).to_string(), "-$1.50");
+    }
+
+    #[test]
+    fn unknown_currencies_are_suffixed() {
+        let money = Money { cents: 1999, currency: "eur" };
+        assert_eq!(money.to_string(), "19.99 EUR");
+    }
+
+    #[test]
+    fn serializes_cents_currency_and_formatted() {
+        let json = serde_json::to_value(Money::usd(1050)).unwrap();
+        assert_eq!(json, serde_json::json!({"cents": 1050, "currency": "usd", "formatted": "$10.50"}));
+    }
+}
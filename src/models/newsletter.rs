@@ -3,6 +3,8 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::error::{AppError, AppResult};
+use crate::models::SegmentFilters;
+use crate::services::i18n::DEFAULT_LOCALE;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NewsletterSubscriber {
@@ -10,10 +12,58 @@ pub struct NewsletterSubscriber {
     pub email: String,
     pub subscribed_ts: i64,
     pub unsubscribe_token: String,
+    /// "pending" until the subscriber follows the confirmation link, then "confirmed"
+    pub status: String,
+    /// Token for the confirmation link; cleared once confirmed
+    pub confirm_token: Option<String>,
+    pub confirmed_ts: Option<i64>,
+    /// Categories chosen in the preference center
+    pub wants_new_drops: bool,
+    pub wants_restocks: bool,
+    pub wants_sales: bool,
+    /// Language for emails; None sends English
+    pub locale: Option<String>,
 }
 
+/// Email categories subscribers can opt in and out of
+pub const CATEGORIES: &[&str] = &["new_drops", "restocks", "sales"];
+
+const COLUMNS: &str = "id, email, subscribed_ts, unsubscribe_token, status, confirm_token, confirmed_ts, wants_new_drops, wants_restocks, wants_sales, locale";
+
 impl NewsletterSubscriber {
-    pub async fn subscribe(conn: &Connection, email: &str) -> AppResult<Self> {
+    fn from_row(row: &libsql::Row) -> Result<Self, libsql::Error> {
+        Ok(Self {
+            id: row.get(0)?,
+            email: row.get(1)?,
+            subscribed_ts: row.get(2)?,
+            unsubscribe_token: row.get(3)?,
+            status: row.get(4).unwrap_or_else(|_| "confirmed".to_string()),
+            confirm_token: row.get(5).ok(),
+            confirmed_ts: row.get(6).ok(),
+            wants_new_drops: row.get::<i32>(7).map(|v| v != 0).unwrap_or(true),
+            wants_restocks: row.get::<i32>(8).map(|v| v != 0).unwrap_or(true),
+            wants_sales: row.get::<i32>(9).map(|v| v != 0).unwrap_or(true),
+            locale: row.get(10).ok(),
+        })
+    }
+
+    /// Whether the subscriber wants emails in this category
+    pub fn wants(&self, category: &str) -> bool {
+        match category {
+            "new_drops" => self.wants_new_drops,
+            "restocks" => self.wants_restocks,
+            "sales" => self.wants_sales,
+            _ => true,
+        }
+    }
+
+    /// Locale to render this subscriber's emails in
+    pub fn email_locale(&self) -> &str {
+        self.locale.as_deref().unwrap_or(DEFAULT_LOCALE)
+    }
+
+    /// Add a pending subscriber; they only get campaigns once they confirm
+    pub async fn subscribe(conn: &Connection, email: &str, locale: Option<&str>) -> AppResult<Self> {
         // Check if already subscribed
         if let Some(existing) = Self::find_by_email(conn, email).await? {
             return Ok(existing);
@@ -21,14 +71,22 @@ impl NewsletterSubscriber {
 
         let id = Uuid::new_v4().to_string();
         let unsubscribe_token = Uuid::new_v4().to_string();
+        let confirm_token = Uuid::new_v4().to_string();
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
             .as_secs() as i64;
 
         conn.execute(
-            "INSERT INTO newsletter_subscribers (id, email, subscribed_ts, unsubscribe_token) VALUES (?, ?, ?, ?)",
-            libsql::params![id.clone(), email.to_lowercase(), now, unsubscribe_token.clone()],
+            "INSERT INTO newsletter_subscribers (id, email, subscribed_ts, unsubscribe_token, status, confirm_token, locale) VALUES (?, ?, ?, ?, 'pending', ?, ?)",
+            libsql::params![
+                id.clone(),
+                email.to_lowercase(),
+                now,
+                unsubscribe_token.clone(),
+                confirm_token.clone(),
+                locale.map(|l| l.to_string())
+            ],
         )
         .await
         .map_err(AppError::from)?;
@@ -38,9 +96,105 @@ impl NewsletterSubscriber {
             email: email.to_lowercase(),
             subscribed_ts: now,
             unsubscribe_token,
+            status: "pending".to_string(),
+            confirm_token: Some(confirm_token),
+            confirmed_ts: None,
+            wants_new_drops: true,
+            wants_restocks: true,
+            wants_sales: true,
+            locale: locale.map(|l| l.to_string()),
         })
     }
 
+    pub fn is_confirmed(&self) -> bool {
+        self.status == "confirmed"
+    }
+
+    /// Confirm the subscriber the token was sent to. Returns None for unknown or
+    /// already used tokens.
+    pub async fn confirm_by_token(conn: &Connection, token: &str) -> AppResult<Option<Self>> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        let mut rows = conn
+            .query(
+                &format!(
+                    "SELECT {} FROM newsletter_subscribers WHERE confirm_token = ? AND status = 'pending'",
+                    COLUMNS
+                ),
+                [token],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        let subscriber = match rows.next().await.map_err(AppError::from)? {
+            Some(row) => Self::from_row(&row).map_err(AppError::from)?,
+            None => return Ok(None),
+        };
+
+        conn.execute(
+            "UPDATE newsletter_subscribers SET status = 'confirmed', confirm_token = NULL, confirmed_ts = ? WHERE id = ?",
+            libsql::params![now, subscriber.id.clone()],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Ok(Some(Self {
+            status: "confirmed".to_string(),
+            confirm_token: None,
+            confirmed_ts: Some(now),
+            ..subscriber
+        }))
+    }
+
+    /// The subscriber a preference-center or unsubscribe link belongs to
+    pub async fn find_by_unsubscribe_token(conn: &Connection, token: &str) -> AppResult<Option<Self>> {
+        let mut rows = conn
+            .query(
+                &format!("SELECT {} FROM newsletter_subscribers WHERE unsubscribe_token = ?", COLUMNS),
+                [token],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        match rows.next().await.map_err(AppError::from)? {
+            Some(row) => Ok(Some(Self::from_row(&row).map_err(AppError::from)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Save the categories chosen in the preference center
+    pub async fn update_preferences(
+        conn: &Connection,
+        id: &str,
+        wants_new_drops: bool,
+        wants_restocks: bool,
+        wants_sales: bool,
+    ) -> AppResult<()> {
+        conn.execute(
+            "UPDATE newsletter_subscribers SET wants_new_drops = ?, wants_restocks = ?, wants_sales = ? WHERE id = ?",
+            libsql::params![wants_new_drops as i32, wants_restocks as i32, wants_sales as i32, id.to_string()],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Ok(())
+    }
+
+    /// Language the subscriber's emails are written in
+    pub async fn set_locale(conn: &Connection, id: &str, locale: &str) -> AppResult<()> {
+        conn.execute(
+            "UPDATE newsletter_subscribers SET locale = ? WHERE id = ?",
+            libsql::params![locale.to_string(), id.to_string()],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Ok(())
+    }
+
     pub async fn unsubscribe_by_token(conn: &Connection, token: &str) -> AppResult<bool> {
         let result = conn
             .execute(
@@ -68,28 +222,27 @@ impl NewsletterSubscriber {
     pub async fn find_by_email(conn: &Connection, email: &str) -> AppResult<Option<Self>> {
         let mut rows = conn
             .query(
-                "SELECT id, email, subscribed_ts, unsubscribe_token FROM newsletter_subscribers WHERE email = ?",
+                &format!("SELECT {} FROM newsletter_subscribers WHERE email = ?", COLUMNS),
                 [email.to_lowercase()],
             )
             .await
             .map_err(AppError::from)?;
 
         if let Some(row) = rows.next().await.map_err(AppError::from)? {
-            Ok(Some(Self {
-                id: row.get(0).map_err(AppError::from)?,
-                email: row.get(1).map_err(AppError::from)?,
-                subscribed_ts: row.get(2).map_err(AppError::from)?,
-                unsubscribe_token: row.get(3).map_err(AppError::from)?,
-            }))
+            Ok(Some(Self::from_row(&row).map_err(AppError::from)?))
         } else {
             Ok(None)
         }
     }
 
+    /// Confirmed subscribers, the only ones campaigns go to
     pub async fn get_all(conn: &Connection) -> AppResult<Vec<Self>> {
         let mut rows = conn
             .query(
-                "SELECT id, email, subscribed_ts, unsubscribe_token FROM newsletter_subscribers ORDER BY subscribed_ts DESC",
+                &format!(
+                    "SELECT {} FROM newsletter_subscribers WHERE status = 'confirmed' ORDER BY subscribed_ts DESC",
+                    COLUMNS
+                ),
                 (),
             )
             .await
@@ -97,20 +250,76 @@ impl NewsletterSubscriber {
 
         let mut subscribers = Vec::new();
         while let Some(row) = rows.next().await.map_err(AppError::from)? {
-            subscribers.push(Self {
-                id: row.get(0).map_err(AppError::from)?,
-                email: row.get(1).map_err(AppError::from)?,
-                subscribed_ts: row.get(2).map_err(AppError::from)?,
-                unsubscribe_token: row.get(3).map_err(AppError::from)?,
-            });
+            subscribers.push(Self::from_row(&row).map_err(AppError::from)?);
         }
 
         Ok(subscribers)
     }
 
-    pub async fn count(conn: &Connection) -> AppResult<i64> {
+    /// Confirmed subscribers matching a segment's filters
+    pub async fn get_in_segment(conn: &Connection, filters: &SegmentFilters) -> AppResult<Vec<Self>> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        let day = 24 * 60 * 60;
+
+        let mut query = format!(
+            "SELECT {} FROM newsletter_subscribers s WHERE s.status = 'confirmed'",
+            COLUMNS
+        );
+        let mut params: Vec<libsql::Value> = Vec::new();
+
+        // Paid orders placed by the account with the subscriber's email
+        let paid_orders = "SELECT COUNT(*) FROM orders o JOIN users u ON u.id = o.user_id
+             WHERE lower(u.email) = s.email
+               AND o.status IN ('paid', 'processing', 'shipped', 'out_for_delivery', 'delivered')";
+
+        match filters.customers {
+            Some(true) => query.push_str(&format!(" AND ({}) > 0", paid_orders)),
+            Some(false) => query.push_str(&format!(" AND ({}) = 0", paid_orders)),
+            None => {}
+        }
+        if let Some(min_orders) = filters.min_orders {
+            query.push_str(&format!(" AND ({}) >= ?", paid_orders));
+            params.push(min_orders.into());
+        }
+        if let Some(days) = filters.ordered_within_days {
+            query.push_str(&format!(" AND ({} AND o.created_ts >= ?) > 0", paid_orders));
+            params.push((now - days as i64 * day).into());
+        }
+        if filters.restock_watchers {
+            query.push_str(" AND EXISTS (SELECT 1 FROM product_notifications n WHERE lower(n.email) = s.email AND n.notified = 0");
+            if !filters.watched_product_ids.is_empty() {
+                let placeholders = vec!["?"; filters.watched_product_ids.len()].join(", ");
+                query.push_str(&format!(" AND n.product_id IN ({})", placeholders));
+                params.extend(filters.watched_product_ids.iter().map(|id| id.clone().into()));
+            }
+            query.push(')');
+        }
+        if let Some(days) = filters.subscribed_within_days {
+            query.push_str(" AND s.subscribed_ts >= ?");
+            params.push((now - days as i64 * day).into());
+        }
+        if let Some(days) = filters.engaged_within_days {
+            query.push_str(" AND EXISTS (SELECT 1 FROM newsletter_events e WHERE e.subscriber_id = s.id AND e.created_ts >= ?)");
+            params.push((now - days as i64 * day).into());
+        }
+        query.push_str(" ORDER BY s.subscribed_ts DESC");
+
+        let mut rows = conn.query(&query, params).await.map_err(AppError::from)?;
+
+        let mut subscribers = Vec::new();
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            subscribers.push(Self::from_row(&row).map_err(AppError::from)?);
+        }
+        Ok(subscribers)
+    }
+
+    /// Number of subscribers with the given status
+    pub async fn count(conn: &Connection, status: &str) -> AppResult<i64> {
         let mut rows = conn
-            .query("SELECT COUNT(*) FROM newsletter_subscribers", ())
+            .query("SELECT COUNT(*) FROM newsletter_subscribers WHERE status = ?", [status])
             .await
             .map_err(AppError::from)?;
 
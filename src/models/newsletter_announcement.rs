@@ -0,0 +1,124 @@
+use libsql::Connection;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::{AppError, AppResult};
+
+/// Kinds of product announcement
+pub const NOTIFY_TYPES: &[&str] = &["new", "restock"];
+
+/// A product drop or restock announcement, sent to subscribers by a background job
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewsletterAnnouncement {
+    pub id: String,
+    /// "new" or "restock"
+    pub notify_type: String,
+    pub product_ids: Vec<String>,
+    /// Sent with the single-product template rather than the multi-product one
+    pub single_product: bool,
+    /// Send only to this segment; None sends to every confirmed subscriber
+    pub segment_id: Option<String>,
+    /// queued, sending or sent
+    pub status: String,
+    pub recipient_count: Option<i32>,
+    pub sent_count: Option<i32>,
+    pub created_ts: i64,
+    pub updated_ts: i64,
+}
+
+impl NewsletterAnnouncement {
+    fn from_row(row: &libsql::Row) -> Result<Self, libsql::Error> {
+        Ok(Self {
+            id: row.get(0)?,
+            notify_type: row.get(1)?,
+            product_ids: row
+                .get::<String>(2)
+                .ok()
+                .and_then(|s| serde_json::from_str(&s).ok())
+                .unwrap_or_default(),
+            single_product: row.get::<i32>(3)? != 0,
+            segment_id: row.get(4).ok(),
+            status: row.get(5)?,
+            recipient_count: row.get(6).ok(),
+            sent_count: row.get(7).ok(),
+            created_ts: row.get(8)?,
+            updated_ts: row.get(9)?,
+        })
+    }
+
+    fn now() -> i64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64
+    }
+
+    pub async fn find_by_id(conn: &Connection, id: &str) -> AppResult<Option<Self>> {
+        let mut rows = conn
+            .query("SELECT * FROM newsletter_announcements WHERE id = ?", [id])
+            .await
+            .map_err(AppError::from)?;
+
+        match rows.next().await.map_err(AppError::from)? {
+            Some(row) => Ok(Some(Self::from_row(&row).map_err(AppError::from)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn create(
+        conn: &Connection,
+        notify_type: &str,
+        product_ids: &[String],
+        single_product: bool,
+        segment_id: Option<&str>,
+    ) -> AppResult<Self> {
+        let id = Uuid::new_v4().to_string();
+        let now = Self::now();
+        let product_ids_json = serde_json::to_string(product_ids).map_err(|e| AppError::Internal(e.to_string()))?;
+
+        conn.execute(
+            "INSERT INTO newsletter_announcements (id, notify_type, product_ids, single_product, segment_id, status, created_ts, updated_ts) VALUES (?, ?, ?, ?, ?, 'queued', ?, ?)",
+            libsql::params![
+                id.clone(),
+                notify_type.to_string(),
+                product_ids_json,
+                single_product as i32,
+                segment_id.map(|s| s.to_string()),
+                now,
+                now
+            ],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Self::find_by_id(conn, &id)
+            .await?
+            .ok_or_else(|| AppError::Internal("Failed to create announcement".to_string()))
+    }
+
+    /// Move a queued announcement to sending. Returns false if it was already claimed,
+    /// so a retried job never sends twice.
+    pub async fn claim_for_sending(conn: &Connection, id: &str, recipient_count: i32) -> AppResult<bool> {
+        let result = conn
+            .execute(
+                "UPDATE newsletter_announcements SET status = 'sending', recipient_count = ?, updated_ts = ?
+                 WHERE id = ? AND status = 'queued'",
+                libsql::params![recipient_count, Self::now(), id.to_string()],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        Ok(result > 0)
+    }
+
+    pub async fn mark_sent(conn: &Connection, id: &str, sent_count: i32) -> AppResult<()> {
+        conn.execute(
+            "UPDATE newsletter_announcements SET status = 'sent', sent_count = ?, updated_ts = ? WHERE id = ?",
+            libsql::params![sent_count, Self::now(), id.to_string()],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Ok(())
+    }
+}
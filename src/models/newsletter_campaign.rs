@@ -0,0 +1,239 @@
+use libsql::Connection;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::{AppError, AppResult};
+
+/// Formats a campaign body can be written in
+pub const BODY_FORMATS: &[&str] = &["html", "markdown"];
+
+/// A custom newsletter email composed in the admin
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewsletterCampaign {
+    pub id: String,
+    pub subject: String,
+    pub body: String,
+    /// "html" or "markdown"
+    pub body_format: String,
+    /// Products featured below the body
+    pub product_ids: Vec<String>,
+    /// Send only to this segment; None sends to every confirmed subscriber
+    pub segment_id: Option<String>,
+    /// draft, scheduled, sending, sent or cancelled
+    pub status: String,
+    pub scheduled_ts: Option<i64>,
+    pub sent_ts: Option<i64>,
+    pub recipient_count: Option<i32>,
+    pub sent_count: Option<i32>,
+    pub created_ts: i64,
+    pub updated_ts: i64,
+    /// Preference-center category: only subscribers opted into it receive the campaign
+    pub category: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SaveNewsletterCampaign {
+    pub subject: String,
+    pub body: String,
+    #[serde(default = "default_body_format")]
+    pub body_format: String,
+    #[serde(default)]
+    pub product_ids: Vec<String>,
+    pub segment_id: Option<String>,
+    #[serde(default = "default_category")]
+    pub category: String,
+}
+
+fn default_category() -> String {
+    "sales".to_string()
+}
+
+fn default_body_format() -> String {
+    "html".to_string()
+}
+
+impl NewsletterCampaign {
+    fn from_row(row: &libsql::Row) -> Result<Self, libsql::Error> {
+        Ok(Self {
+            id: row.get(0)?,
+            subject: row.get(1)?,
+            body: row.get(2)?,
+            body_format: row.get(3)?,
+            product_ids: row
+                .get::<String>(4)
+                .ok()
+                .and_then(|s| serde_json::from_str(&s).ok())
+                .unwrap_or_default(),
+            segment_id: row.get(5).ok(),
+            status: row.get(6)?,
+            scheduled_ts: row.get(7).ok(),
+            sent_ts: row.get(8).ok(),
+            recipient_count: row.get(9).ok(),
+            sent_count: row.get(10).ok(),
+            created_ts: row.get(11)?,
+            updated_ts: row.get(12)?,
+            category: row.get(13).unwrap_or_else(|_| default_category()),
+        })
+    }
+
+    fn now() -> i64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64
+    }
+
+    pub async fn find_by_id(conn: &Connection, id: &str) -> AppResult<Option<Self>> {
+        let mut rows = conn
+            .query("SELECT * FROM newsletter_campaigns WHERE id = ?", [id])
+            .await
+            .map_err(AppError::from)?;
+
+        match rows.next().await.map_err(AppError::from)? {
+            Some(row) => Ok(Some(Self::from_row(&row).map_err(AppError::from)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Newest first
+    pub async fn list_all(conn: &Connection) -> AppResult<Vec<Self>> {
+        let mut rows = conn
+            .query("SELECT * FROM newsletter_campaigns ORDER BY created_ts DESC", ())
+            .await
+            .map_err(AppError::from)?;
+
+        let mut campaigns = Vec::new();
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            campaigns.push(Self::from_row(&row).map_err(AppError::from)?);
+        }
+        Ok(campaigns)
+    }
+
+    /// Draft or scheduled campaigns addressed to a segment
+    pub async fn count_unsent_for_segment(conn: &Connection, segment_id: &str) -> AppResult<i64> {
+        let mut rows = conn
+            .query(
+                "SELECT COUNT(*) FROM newsletter_campaigns WHERE segment_id = ? AND status IN ('draft', 'scheduled')",
+                [segment_id],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        match rows.next().await.map_err(AppError::from)? {
+            Some(row) => Ok(row.get(0).map_err(AppError::from)?),
+            None => Ok(0),
+        }
+    }
+
+    /// Save a new draft
+    pub async fn create(conn: &Connection, data: SaveNewsletterCampaign) -> AppResult<Self> {
+        let id = Uuid::new_v4().to_string();
+        let now = Self::now();
+        let product_ids = serde_json::to_string(&data.product_ids).map_err(|e| AppError::Internal(e.to_string()))?;
+
+        conn.execute(
+            "INSERT INTO newsletter_campaigns (id, subject, body, body_format, product_ids, segment_id, category, status, created_ts, updated_ts) VALUES (?, ?, ?, ?, ?, ?, ?, 'draft', ?, ?)",
+            libsql::params![
+                id.clone(),
+                data.subject.trim().to_string(),
+                data.body,
+                data.body_format,
+                product_ids,
+                data.segment_id,
+                data.category,
+                now,
+                now
+            ],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Self::find_by_id(conn, &id)
+            .await?
+            .ok_or_else(|| AppError::Internal("Failed to create campaign".to_string()))
+    }
+
+    /// Replace the content of a draft or scheduled campaign
+    pub async fn update(conn: &Connection, id: &str, data: SaveNewsletterCampaign) -> AppResult<Self> {
+        let product_ids = serde_json::to_string(&data.product_ids).map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let result = conn
+            .execute(
+                "UPDATE newsletter_campaigns SET subject = ?, body = ?, body_format = ?, product_ids = ?, segment_id = ?, category = ?, updated_ts = ?
+                 WHERE id = ? AND status IN ('draft', 'scheduled')",
+                libsql::params![
+                    data.subject.trim().to_string(),
+                    data.body,
+                    data.body_format,
+                    product_ids,
+                    data.segment_id,
+                    data.category,
+                    Self::now(),
+                    id.to_string()
+                ],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        if result == 0 {
+            return Err(AppError::BadRequest("Only draft or scheduled campaigns can be edited".to_string()));
+        }
+
+        Self::find_by_id(conn, id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Campaign not found".to_string()))
+    }
+
+    /// Schedule a draft (or move a scheduled campaign) to send at `scheduled_ts`
+    pub async fn schedule(conn: &Connection, id: &str, scheduled_ts: i64) -> AppResult<bool> {
+        let result = conn
+            .execute(
+                "UPDATE newsletter_campaigns SET status = 'scheduled', scheduled_ts = ?, updated_ts = ?
+                 WHERE id = ? AND status IN ('draft', 'scheduled')",
+                libsql::params![scheduled_ts, Self::now(), id.to_string()],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        Ok(result > 0)
+    }
+
+    pub async fn cancel(conn: &Connection, id: &str) -> AppResult<bool> {
+        let result = conn
+            .execute(
+                "UPDATE newsletter_campaigns SET status = 'cancelled', updated_ts = ? WHERE id = ? AND status IN ('draft', 'scheduled')",
+                libsql::params![Self::now(), id.to_string()],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        Ok(result > 0)
+    }
+
+    /// Move a campaign that is due at `scheduled_ts` to "sending". Returns false if it
+    /// was cancelled, rescheduled or already picked up, so it is only ever sent once.
+    pub async fn claim_for_sending(conn: &Connection, id: &str, scheduled_ts: i64) -> AppResult<bool> {
+        let result = conn
+            .execute(
+                "UPDATE newsletter_campaigns SET status = 'sending', updated_ts = ?
+                 WHERE id = ? AND status = 'scheduled' AND scheduled_ts = ?",
+                libsql::params![Self::now(), id.to_string(), scheduled_ts],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        Ok(result > 0)
+    }
+
+    pub async fn mark_sent(conn: &Connection, id: &str, recipient_count: i32, sent_count: i32) -> AppResult<()> {
+        let now = Self::now();
+        conn.execute(
+            "UPDATE newsletter_campaigns SET status = 'sent', sent_ts = ?, recipient_count = ?, sent_count = ?, updated_ts = ? WHERE id = ?",
+            libsql::params![now, recipient_count, sent_count, now, id.to_string()],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,157 @@
+use libsql::Connection;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::{AppError, AppResult};
+use crate::models::NewsletterSubscriber;
+
+/// Conditions a confirmed subscriber must all meet to be in a segment.
+/// Customers are matched by the email on their account.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SegmentFilters {
+    /// true: only subscribers who have placed a paid order; false: only those who never have
+    #[serde(default)]
+    pub customers: Option<bool>,
+    /// Only subscribers with at least this many paid orders
+    #[serde(default)]
+    pub min_orders: Option<i32>,
+    /// Only subscribers with a paid order in the last N days
+    #[serde(default)]
+    pub ordered_within_days: Option<i32>,
+    /// Only subscribers waiting on a "Notify Me" restock alert
+    #[serde(default)]
+    pub restock_watchers: bool,
+    /// Narrow `restock_watchers` to alerts for these products
+    #[serde(default)]
+    pub watched_product_ids: Vec<String>,
+    /// Only subscribers who signed up in the last N days
+    #[serde(default)]
+    pub subscribed_within_days: Option<i32>,
+    /// Only subscribers who opened or clicked a campaign in the last N days
+    #[serde(default)]
+    pub engaged_within_days: Option<i32>,
+}
+
+/// A saved, named subscriber filter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewsletterSegment {
+    pub id: String,
+    pub name: String,
+    pub filters: SegmentFilters,
+    pub created_ts: i64,
+    pub updated_ts: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SaveNewsletterSegment {
+    pub name: String,
+    #[serde(default)]
+    pub filters: SegmentFilters,
+}
+
+impl NewsletterSegment {
+    fn from_row(row: &libsql::Row) -> Result<Self, libsql::Error> {
+        Ok(Self {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            filters: row
+                .get::<String>(2)
+                .ok()
+                .and_then(|s| serde_json::from_str(&s).ok())
+                .unwrap_or_default(),
+            created_ts: row.get(3)?,
+            updated_ts: row.get(4)?,
+        })
+    }
+
+    pub async fn find_by_id(conn: &Connection, id: &str) -> AppResult<Option<Self>> {
+        let mut rows = conn
+            .query("SELECT * FROM newsletter_segments WHERE id = ?", [id])
+            .await
+            .map_err(AppError::from)?;
+
+        match rows.next().await.map_err(AppError::from)? {
+            Some(row) => Ok(Some(Self::from_row(&row).map_err(AppError::from)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Who a newsletter goes to: the segment's subscribers, or every confirmed subscriber
+    pub async fn recipients(conn: &Connection, segment_id: Option<&str>) -> AppResult<Vec<NewsletterSubscriber>> {
+        match segment_id.filter(|id| !id.is_empty()) {
+            Some(segment_id) => {
+                let segment = Self::find_by_id(conn, segment_id)
+                    .await?
+                    .ok_or_else(|| AppError::NotFound("Segment not found".to_string()))?;
+                NewsletterSubscriber::get_in_segment(conn, &segment.filters).await
+            }
+            None => NewsletterSubscriber::get_all(conn).await,
+        }
+    }
+
+    pub async fn list_all(conn: &Connection) -> AppResult<Vec<Self>> {
+        let mut rows = conn
+            .query("SELECT * FROM newsletter_segments ORDER BY name ASC", ())
+            .await
+            .map_err(AppError::from)?;
+
+        let mut segments = Vec::new();
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            segments.push(Self::from_row(&row).map_err(AppError::from)?);
+        }
+        Ok(segments)
+    }
+
+    pub async fn create(conn: &Connection, data: SaveNewsletterSegment) -> AppResult<Self> {
+        let id = Uuid::new_v4().to_string();
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        let filters = serde_json::to_string(&data.filters).map_err(|e| AppError::Internal(e.to_string()))?;
+
+        conn.execute(
+            "INSERT INTO newsletter_segments (id, name, filters, created_ts, updated_ts) VALUES (?, ?, ?, ?, ?)",
+            libsql::params![id.clone(), data.name.trim().to_string(), filters, now, now],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Self::find_by_id(conn, &id)
+            .await?
+            .ok_or_else(|| AppError::Internal("Failed to create segment".to_string()))
+    }
+
+    pub async fn update(conn: &Connection, id: &str, data: SaveNewsletterSegment) -> AppResult<Self> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        let filters = serde_json::to_string(&data.filters).map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let result = conn
+            .execute(
+                "UPDATE newsletter_segments SET name = ?, filters = ?, updated_ts = ? WHERE id = ?",
+                libsql::params![data.name.trim().to_string(), filters, now, id.to_string()],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        if result == 0 {
+            return Err(AppError::NotFound("Segment not found".to_string()));
+        }
+
+        Self::find_by_id(conn, id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Segment not found".to_string()))
+    }
+
+    pub async fn delete(conn: &Connection, id: &str) -> AppResult<bool> {
+        let result = conn
+            .execute("DELETE FROM newsletter_segments WHERE id = ?", [id])
+            .await
+            .map_err(AppError::from)?;
+
+        Ok(result > 0)
+    }
+}
@@ -0,0 +1,161 @@
+use libsql::Connection;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::{AppError, AppResult};
+
+/// A link in a sent campaign; clicks go through the tracker before redirecting here
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewsletterLink {
+    pub id: String,
+    pub campaign_id: String,
+    pub url: String,
+    pub created_ts: i64,
+}
+
+/// Clicks on one campaign link
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkStats {
+    pub url: String,
+    pub clicks: i64,
+    pub unique_clicks: i64,
+}
+
+/// Opens and clicks for a campaign. Opens are only counted when the subscriber's
+/// mail client loads images, so they undercount.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CampaignStats {
+    pub opens: i64,
+    pub unique_opens: i64,
+    pub clicks: i64,
+    pub unique_clicks: i64,
+    pub links: Vec<LinkStats>,
+}
+
+impl NewsletterLink {
+    fn from_row(row: &libsql::Row) -> Result<Self, libsql::Error> {
+        Ok(Self {
+            id: row.get(0)?,
+            campaign_id: row.get(1)?,
+            url: row.get(2)?,
+            created_ts: row.get(3)?,
+        })
+    }
+
+    pub async fn find_by_id(conn: &Connection, id: &str) -> AppResult<Option<Self>> {
+        let mut rows = conn
+            .query("SELECT * FROM newsletter_links WHERE id = ?", [id])
+            .await
+            .map_err(AppError::from)?;
+
+        match rows.next().await.map_err(AppError::from)? {
+            Some(row) => Ok(Some(Self::from_row(&row).map_err(AppError::from)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// The campaign's tracked link for this URL, created on first use
+    pub async fn get_or_create(conn: &Connection, campaign_id: &str, url: &str) -> AppResult<Self> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        conn.execute(
+            "INSERT OR IGNORE INTO newsletter_links (id, campaign_id, url, created_ts) VALUES (?, ?, ?, ?)",
+            libsql::params![Uuid::new_v4().to_string(), campaign_id.to_string(), url.to_string(), now],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        let mut rows = conn
+            .query(
+                "SELECT * FROM newsletter_links WHERE campaign_id = ? AND url = ?",
+                [campaign_id, url],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        match rows.next().await.map_err(AppError::from)? {
+            Some(row) => Ok(Self::from_row(&row).map_err(AppError::from)?),
+            None => Err(AppError::Internal("Failed to create newsletter link".to_string())),
+        }
+    }
+}
+
+/// Record an open or click
+pub async fn record_event(
+    conn: &Connection,
+    campaign_id: &str,
+    subscriber_id: &str,
+    event_type: &str,
+    link_id: Option<&str>,
+) -> AppResult<()> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+
+    conn.execute(
+        "INSERT INTO newsletter_events (id, campaign_id, subscriber_id, event_type, link_id, created_ts) VALUES (?, ?, ?, ?, ?, ?)",
+        libsql::params![
+            Uuid::new_v4().to_string(),
+            campaign_id.to_string(),
+            subscriber_id.to_string(),
+            event_type.to_string(),
+            link_id.map(|id| id.to_string()),
+            now
+        ],
+    )
+    .await
+    .map_err(AppError::from)?;
+
+    Ok(())
+}
+
+impl CampaignStats {
+    pub async fn for_campaign(conn: &Connection, campaign_id: &str) -> AppResult<Self> {
+        let mut stats = Self::default();
+
+        let mut rows = conn
+            .query(
+                "SELECT event_type, COUNT(*), COUNT(DISTINCT subscriber_id) FROM newsletter_events
+                 WHERE campaign_id = ? GROUP BY event_type",
+                [campaign_id],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            let event_type: String = row.get(0).map_err(AppError::from)?;
+            let total: i64 = row.get(1).map_err(AppError::from)?;
+            let unique: i64 = row.get(2).map_err(AppError::from)?;
+            match event_type.as_str() {
+                "open" => (stats.opens, stats.unique_opens) = (total, unique),
+                "click" => (stats.clicks, stats.unique_clicks) = (total, unique),
+                _ => {}
+            }
+        }
+
+        let mut rows = conn
+            .query(
+                "SELECT l.url, COUNT(e.id), COUNT(DISTINCT e.subscriber_id) FROM newsletter_links l
+                 LEFT JOIN newsletter_events e ON e.link_id = l.id AND e.event_type = 'click'
+                 WHERE l.campaign_id = ?
+                 GROUP BY l.id ORDER BY COUNT(e.id) DESC, l.url ASC",
+                [campaign_id],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            stats.links.push(LinkStats {
+                url: row.get(0).map_err(AppError::from)?,
+                clicks: row.get(1).map_err(AppError::from)?,
+                unique_clicks: row.get(2).map_err(AppError::from)?,
+            });
+        }
+
+        Ok(stats)
+    }
+}
@@ -1,9 +1,13 @@
 use libsql::Connection;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use uuid::Uuid;
 
 use crate::error::{AppError, AppResult};
 
+/// Rate ID of the shop's own local delivery, offered instead of a carrier rate
+pub const LOCAL_DELIVERY_RATE_ID: &str = "local_delivery";
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum OrderStatus {
@@ -11,6 +15,9 @@ pub enum OrderStatus {
     Paid,
     Processing,
     Shipped,
+    /// Local deliveries only: on the way with the shop's own driver
+    #[serde(rename = "out_for_delivery")]
+    OutForDelivery,
     Delivered,
     Cancelled,
     Refunded,
@@ -23,6 +30,7 @@ impl OrderStatus {
             OrderStatus::Paid => "paid",
             OrderStatus::Processing => "processing",
             OrderStatus::Shipped => "shipped",
+            OrderStatus::OutForDelivery => "out_for_delivery",
             OrderStatus::Delivered => "delivered",
             OrderStatus::Cancelled => "cancelled",
             OrderStatus::Refunded => "refunded",
@@ -35,12 +43,41 @@ impl OrderStatus {
             "paid" => Some(OrderStatus::Paid),
             "processing" => Some(OrderStatus::Processing),
             "shipped" => Some(OrderStatus::Shipped),
+            "out_for_delivery" => Some(OrderStatus::OutForDelivery),
             "delivered" => Some(OrderStatus::Delivered),
             "cancelled" => Some(OrderStatus::Cancelled),
             "refunded" => Some(OrderStatus::Refunded),
             _ => None,
         }
     }
+
+    /// Whether an order may move from this status to `next`.
+    /// Staying in the same status is not a transition and is rejected.
+    pub fn can_transition_to(&self, next: OrderStatus) -> bool {
+        use OrderStatus::*;
+
+        matches!(
+            (self, next),
+            (Pending, Paid)
+                | (Pending, Cancelled)
+                | (Paid, Processing)
+                | (Paid, Shipped)
+                | (Paid, OutForDelivery)
+                | (Paid, Cancelled)
+                | (Paid, Refunded)
+                | (Processing, Shipped)
+                | (Processing, OutForDelivery)
+                | (Processing, Cancelled)
+                | (Processing, Refunded)
+                | (Shipped, Delivered)
+                | (Shipped, Cancelled)
+                | (Shipped, Refunded)
+                | (OutForDelivery, Delivered)
+                | (OutForDelivery, Cancelled)
+                | (OutForDelivery, Refunded)
+                | (Delivered, Refunded)
+        )
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -53,6 +90,16 @@ pub struct ShippingAddress {
     pub country: String,
 }
 
+/// Snapshot of a Shippo rate as it was selected, kept for auditing shipping costs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShippingRateRecord {
+    pub rate_id: Option<String>,
+    pub carrier: Option<String>,
+    pub service: Option<String>,
+    pub amount_cents: i32,
+    pub recorded_ts: i64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Order {
     pub id: String,
@@ -73,6 +120,59 @@ pub struct Order {
     pub estimated_delivery_days: Option<i32>,
     // Label from Shippo
     pub label_url: Option<String>,
+    // Gift options
+    pub is_gift: bool,
+    pub gift_message: Option<String>,
+    pub gift_wrap: bool,
+    pub gift_wrap_cents: i32,
+    // Stripe Radar assessment
+    pub risk_level: Option<String>,
+    pub risk_score: Option<i32>,
+    // Carrier ETA from Shippo tracking
+    pub delivery_eta_ts: Option<i64>,
+    // Retention
+    pub archived: bool,
+    pub archived_ts: Option<i64>,
+    // Shipping rate audit trail (JSON ShippingRateRecord)
+    pub checkout_rate: Option<String>,
+    pub label_rate: Option<String>,
+    // Promo code redeemed at checkout
+    pub promo_code: Option<String>,
+    pub discount_cents: i32,
+    // Whether stock is currently held for this order
+    pub stock_reserved: bool,
+    // Checkout session expiry and why the order was cancelled
+    pub checkout_expires_ts: Option<i64>,
+    pub cancel_reason: Option<String>,
+    // Tip
+    pub tip_cents: i32,
+    // Stripe Payment Link sent by an admin
+    pub payment_link_id: Option<String>,
+    pub payment_link_url: Option<String>,
+    // Manual capture: authorized, captured, or voided (None when charged at checkout)
+    pub capture_status: Option<String>,
+    pub authorized_ts: Option<i64>,
+    // How the customer paid (from the Stripe charge)
+    pub payment_method_type: Option<String>,
+    pub card_brand: Option<String>,
+    pub payment_wallet: Option<String>,
+    // Set while a delayed payment method (e.g. bank debit) is waiting for funds
+    pub payment_processing_ts: Option<i64>,
+    // Marketplace: artist the payment was sent to and the shop's fee
+    pub artist_id: Option<String>,
+    pub application_fee_cents: Option<i32>,
+    // Subscription billing cycle this order fulfils
+    pub subscription_id: Option<String>,
+    pub stripe_invoice_id: Option<String>,
+    // Shippo transaction for the purchased label
+    pub shippo_transaction_id: Option<String>,
+    // Shipment insurance: declared value covered and what it added to the label
+    pub insurance_amount_cents: Option<i32>,
+    pub insurance_cost_cents: Option<i32>,
+    // Delivery signature on the label: standard or adult
+    pub signature_confirmation: Option<String>,
+    // Charged a fallback shipping rate while Shippo was down
+    pub needs_rate_review: bool,
 }
 
 impl Order {
@@ -96,6 +196,59 @@ impl Order {
             estimated_delivery_days: row.get(16).ok(),
             // Label URL (column 17 after migration 020)
             label_url: row.get(17).ok(),
+            // Gift options (columns 18-21 after migration 022)
+            is_gift: row.get::<i32>(18).map(|v| v != 0).unwrap_or(false),
+            gift_message: row.get(19).ok(),
+            gift_wrap: row.get::<i32>(20).map(|v| v != 0).unwrap_or(false),
+            gift_wrap_cents: row.get(21).unwrap_or(0),
+            // Radar risk (columns 22-23 after migration 023)
+            risk_level: row.get(22).ok(),
+            risk_score: row.get(23).ok(),
+            // Delivery ETA (column 24 after migration 024)
+            delivery_eta_ts: row.get(24).ok(),
+            // Archival (columns 25-26 after migration 025)
+            archived: row.get::<i32>(25).map(|v| v != 0).unwrap_or(false),
+            archived_ts: row.get(26).ok(),
+            // Shipping rate audit (columns 27-28 after migration 026)
+            checkout_rate: row.get(27).ok(),
+            label_rate: row.get(28).ok(),
+            // Promo code (columns 29-30 after migration 027)
+            promo_code: row.get(29).ok(),
+            discount_cents: row.get(30).unwrap_or(0),
+            // Stock reservation (column 31 after migration 029)
+            stock_reserved: row.get::<i32>(31).map(|v| v != 0).unwrap_or(false),
+            // Checkout expiry (columns 32-33 after migration 030)
+            checkout_expires_ts: row.get(32).ok(),
+            cancel_reason: row.get(33).ok(),
+            // Tip (column 34 after migration 032)
+            tip_cents: row.get(34).unwrap_or(0),
+            // Payment link (columns 35-36 after migration 038)
+            payment_link_id: row.get(35).ok(),
+            payment_link_url: row.get(36).ok(),
+            // Manual capture (columns 37-38 after migration 039)
+            capture_status: row.get(37).ok(),
+            authorized_ts: row.get(38).ok(),
+            // Payment method (columns 39-41 after migration 040)
+            payment_method_type: row.get(39).ok(),
+            card_brand: row.get(40).ok(),
+            payment_wallet: row.get(41).ok(),
+            // Delayed payment (column 42 after migration 041)
+            payment_processing_ts: row.get(42).ok(),
+            // Marketplace (columns 43-44 after migration 042)
+            artist_id: row.get(43).ok(),
+            application_fee_cents: row.get(44).ok(),
+            // Subscription cycle (columns 45-46 after migration 043)
+            subscription_id: row.get(45).ok(),
+            stripe_invoice_id: row.get(46).ok(),
+            // Label transaction (column 47 after migration 045)
+            shippo_transaction_id: row.get(47).ok(),
+            // Insurance (columns 48-49 after migration 046)
+            insurance_amount_cents: row.get(48).ok(),
+            insurance_cost_cents: row.get(49).ok(),
+            // Signature (column 50 after migration 047)
+            signature_confirmation: row.get(50).ok(),
+            // Rate review (column 51 after migration 049)
+            needs_rate_review: row.get::<i32>(51).map(|v| v != 0).unwrap_or(false),
         })
     }
 }
@@ -107,6 +260,8 @@ pub struct OrderItem {
     pub product_id: String,
     pub quantity: i32,
     pub price_cents: i32,
+    pub style_id: Option<String>,
+    pub style_name: Option<String>,
 }
 
 impl OrderItem {
@@ -117,15 +272,34 @@ impl OrderItem {
             product_id: row.get(2)?,
             quantity: row.get(3)?,
             price_cents: row.get(4)?,
+            // Style (columns 5-6 after migration 028)
+            style_id: row.get(5).ok(),
+            style_name: row.get(6).ok(),
         })
     }
 }
 
+/// An order item joined with its product's current name
+#[derive(Debug, Clone, Serialize)]
+pub struct OrderItemDetail {
+    pub order_id: String,
+    pub product_id: String,
+    pub product_name: Option<String>,
+    pub style_name: Option<String>,
+    pub quantity: i32,
+    pub price_cents: i32,
+}
+
+/// Keep IN (...) lists well under SQLite's bound-parameter limit
+pub(crate) const IN_CLAUSE_CHUNK: usize = 500;
+
 #[derive(Debug, Deserialize)]
 pub struct CreateOrderItem {
     pub product_id: String,
     pub quantity: i32,
     pub price_cents: i32,
+    pub style_id: Option<String>,
+    pub style_name: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -140,6 +314,20 @@ pub struct CreateOrder {
     pub shipping_carrier: Option<String>,
     pub shipping_service: Option<String>,
     pub estimated_delivery_days: Option<i32>,
+    // Gift options
+    pub is_gift: bool,
+    pub gift_message: Option<String>,
+    pub gift_wrap: bool,
+    pub gift_wrap_cents: i32,
+    // Rate quoted at checkout
+    pub checkout_rate: Option<ShippingRateRecord>,
+    // Promo code
+    pub promo_code: Option<String>,
+    pub discount_cents: i32,
+    // Tip
+    pub tip_cents: i32,
+    // Take stock now; subscription cycles are already paid for and take it when marked paid
+    pub reserve_stock: bool,
 }
 
 impl Order {
@@ -151,10 +339,41 @@ impl Order {
         serde_json::from_str(&self.shipping_address).ok()
     }
 
+    pub fn get_checkout_rate(&self) -> Option<ShippingRateRecord> {
+        self.checkout_rate.as_deref().and_then(|r| serde_json::from_str(r).ok())
+    }
+
+    pub fn get_label_rate(&self) -> Option<ShippingRateRecord> {
+        self.label_rate.as_deref().and_then(|r| serde_json::from_str(r).ok())
+    }
+
     pub fn get_status(&self) -> Option<OrderStatus> {
         OrderStatus::from_str(&self.status)
     }
 
+    /// Whether the customer picked local delivery at checkout
+    pub fn is_local_delivery(&self) -> bool {
+        self.get_checkout_rate()
+            .and_then(|r| r.rate_id)
+            .map(|id| id == LOCAL_DELIVERY_RATE_ID)
+            .unwrap_or(false)
+    }
+
+    /// Best known delivery estimate: the carrier's tracking ETA when available,
+    /// otherwise the quoted transit days counted from when the order was placed
+    pub fn expected_delivery_ts(&self) -> Option<i64> {
+        self.delivery_eta_ts.or_else(|| {
+            self.estimated_delivery_days
+                .map(|days| self.created_ts + days as i64 * 24 * 60 * 60)
+        })
+    }
+
+    /// Whether Stripe Radar rated the payment risky enough to review before shipping
+    pub fn is_elevated_risk(&self) -> bool {
+        matches!(self.risk_level.as_deref(), Some("elevated") | Some("highest"))
+            || self.risk_score.map(|s| s >= 65).unwrap_or(false)
+    }
+
     pub async fn find_by_id(conn: &Connection, id: &str) -> AppResult<Option<Self>> {
         let mut rows = conn
             .query("SELECT * FROM orders WHERE id = ?", [id])
@@ -191,6 +410,18 @@ impl Order {
         }
     }
 
+    pub async fn find_by_payment_link(conn: &Connection, payment_link_id: &str) -> AppResult<Option<Self>> {
+        let mut rows = conn
+            .query("SELECT * FROM orders WHERE payment_link_id = ?", [payment_link_id])
+            .await
+            .map_err(AppError::from)?;
+
+        match rows.next().await.map_err(AppError::from)? {
+            Some(row) => Ok(Some(Self::from_row(&row).map_err(AppError::from)?)),
+            None => Ok(None),
+        }
+    }
+
     pub async fn set_payment_intent(conn: &Connection, id: &str, payment_intent_id: &str) -> AppResult<()> {
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
@@ -207,207 +438,1367 @@ impl Order {
         Ok(())
     }
 
-    pub async fn list_by_user(conn: &Connection, user_id: &str) -> AppResult<Vec<Self>> {
-        let mut rows = conn
-            .query(
-                "SELECT * FROM orders WHERE user_id = ? ORDER BY created_ts DESC",
-                [user_id],
-            )
-            .await
-            .map_err(AppError::from)?;
-
-        let mut orders = Vec::new();
-        while let Some(row) = rows.next().await.map_err(AppError::from)? {
-            orders.push(Self::from_row(&row).map_err(AppError::from)?);
-        }
-        Ok(orders)
-    }
+    /// Remember the Payment Link sent for an order, replacing any earlier one
+    pub async fn set_payment_link(conn: &Connection, id: &str, link_id: &str, url: &str) -> AppResult<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
 
-    pub async fn list_all(conn: &Connection) -> AppResult<Vec<Self>> {
-        let mut rows = conn
-            .query("SELECT * FROM orders ORDER BY created_ts DESC", ())
-            .await
-            .map_err(AppError::from)?;
+        conn.execute(
+            "UPDATE orders SET payment_link_id = ?, payment_link_url = ?, updated_ts = ? WHERE id = ?",
+            libsql::params![link_id.to_string(), url.to_string(), now, id.to_string()],
+        )
+        .await
+        .map_err(AppError::from)?;
 
-        let mut orders = Vec::new();
-        while let Some(row) = rows.next().await.map_err(AppError::from)? {
-            orders.push(Self::from_row(&row).map_err(AppError::from)?);
-        }
-        Ok(orders)
+        Ok(())
     }
 
-    pub async fn create(conn: &Connection, data: CreateOrder) -> AppResult<Self> {
-        let id = Uuid::new_v4().to_string();
+    /// Record a Stripe promotion code the customer entered on the Checkout page.
+    /// Orders that already carry a local promo code are left alone.
+    pub async fn apply_stripe_promotion(
+        conn: &Connection,
+        id: &str,
+        code: &str,
+        discount_cents: i32,
+    ) -> AppResult<()> {
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
             .as_secs() as i64;
-        let shipping_json = serde_json::to_string(&data.shipping_address)
-            .map_err(|e| AppError::Internal(e.to_string()))?;
 
         conn.execute(
-            "INSERT INTO orders (id, user_id, total_cents, shipping_address, stripe_session_id, created_ts, updated_ts, shipping_cents, shipping_carrier, shipping_service, estimated_delivery_days) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
-            libsql::params![id.clone(), data.user_id.clone(), data.total_cents, shipping_json, data.stripe_session_id.clone(), now, now, data.shipping_cents.unwrap_or(0), data.shipping_carrier, data.shipping_service, data.estimated_delivery_days],
+            "UPDATE orders SET promo_code = ?, discount_cents = ?, total_cents = total_cents - ?, updated_ts = ?
+             WHERE id = ? AND promo_code IS NULL",
+            libsql::params![code.to_string(), discount_cents, discount_cents, now, id.to_string()],
         )
         .await
         .map_err(AppError::from)?;
 
-        for item in data.items {
-            let item_id = Uuid::new_v4().to_string();
-            conn.execute(
-                "INSERT INTO order_items (id, order_id, product_id, quantity, price_cents) VALUES (?, ?, ?, ?, ?)",
-                libsql::params![item_id, id.clone(), item.product_id, item.quantity, item.price_cents],
-            )
-            .await
-            .map_err(AppError::from)?;
-        }
-
-        Self::find_by_id(conn, &id)
-            .await?
-            .ok_or_else(|| AppError::Internal("Failed to create order".to_string()))
+        Ok(())
     }
 
-    pub async fn set_stripe_session(conn: &Connection, id: &str, session_id: &str) -> AppResult<Self> {
+    pub async fn set_risk(
+        conn: &Connection,
+        id: &str,
+        risk_level: Option<&str>,
+        risk_score: Option<i32>,
+    ) -> AppResult<()> {
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
             .as_secs() as i64;
 
         conn.execute(
-            "UPDATE orders SET stripe_session_id = ?, updated_ts = ? WHERE id = ?",
-            libsql::params![session_id.to_string(), now, id.to_string()],
+            "UPDATE orders SET risk_level = ?, risk_score = ?, updated_ts = ? WHERE id = ?",
+            libsql::params![risk_level.map(|s| s.to_string()), risk_score, now, id.to_string()],
         )
         .await
         .map_err(AppError::from)?;
 
-        Self::find_by_id(conn, id)
-            .await?
-            .ok_or_else(|| AppError::NotFound("Order not found".to_string()))
+        Ok(())
     }
 
-    pub async fn update_status(conn: &Connection, id: &str, status: OrderStatus) -> AppResult<Self> {
+    pub async fn set_payment_method(
+        conn: &Connection,
+        id: &str,
+        payment_method_type: Option<&str>,
+        card_brand: Option<&str>,
+        wallet: Option<&str>,
+    ) -> AppResult<()> {
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
             .as_secs() as i64;
 
         conn.execute(
-            "UPDATE orders SET status = ?, updated_ts = ? WHERE id = ?",
-            libsql::params![status.as_str().to_string(), now, id.to_string()],
+            "UPDATE orders SET payment_method_type = ?, card_brand = ?, payment_wallet = ?, updated_ts = ? WHERE id = ?",
+            libsql::params![
+                payment_method_type.map(|s| s.to_string()),
+                card_brand.map(|s| s.to_string()),
+                wallet.map(|s| s.to_string()),
+                now,
+                id.to_string()
+            ],
         )
         .await
         .map_err(AppError::from)?;
 
-        Self::find_by_id(conn, id)
-            .await?
-            .ok_or_else(|| AppError::NotFound("Order not found".to_string()))
+        Ok(())
     }
 
-    pub async fn set_tracking(
-        conn: &Connection,
-        id: &str,
-        tracking_number: &str,
-        shippo_tracker_id: Option<&str>,
-    ) -> AppResult<Self> {
+    pub async fn set_delivery_eta(conn: &Connection, id: &str, eta_ts: i64) -> AppResult<()> {
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
             .as_secs() as i64;
 
         conn.execute(
-            r#"
-            UPDATE orders SET
-                tracking_number = ?,
-                shippo_tracker_id = ?,
-                status = 'shipped',
-                updated_ts = ?
-            WHERE id = ?
-            "#,
-            libsql::params![tracking_number.to_string(), shippo_tracker_id.map(|s| s.to_string()), now, id.to_string()],
+            "UPDATE orders SET delivery_eta_ts = ?, updated_ts = ? WHERE id = ?",
+            libsql::params![eta_ts, now, id.to_string()],
         )
         .await
         .map_err(AppError::from)?;
 
-        Self::find_by_id(conn, id)
-            .await?
-            .ok_or_else(|| AppError::NotFound("Order not found".to_string()))
+        Ok(())
     }
 
-    pub async fn set_label(
-        conn: &Connection,
-        id: &str,
-        tracking_number: &str,
-        label_url: &str,
-        carrier: Option<&str>,
-    ) -> AppResult<Self> {
+    /// Archive settled orders created before `cutoff_ts`, stripping the shipping
+    /// address down to state/country and unlinking the customer account.
+    /// In-flight orders (paid, processing, shipped) are left alone.
+    pub async fn archive_before(conn: &Connection, cutoff_ts: i64) -> AppResult<u64> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        let archived = conn
+            .execute(
+                "UPDATE orders SET
+                    archived = 1,
+                    archived_ts = ?,
+                    user_id = NULL,
+                    shipping_address = CASE WHEN json_valid(shipping_address)
+                        THEN json_set(shipping_address, '$.name', '', '$.street', '', '$.city', '', '$.zip', '')
+                        ELSE '{}' END,
+                    gift_message = NULL,
+                    label_url = NULL,
+                    updated_ts = ?
+                 WHERE created_ts < ?
+                   AND COALESCE(archived, 0) = 0
+                   AND status IN ('pending', 'delivered', 'cancelled', 'refunded')",
+                libsql::params![now, now, cutoff_ts],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        Ok(archived)
+    }
+
+    pub async fn set_label_rate(conn: &Connection, id: &str, rate: &ShippingRateRecord) -> AppResult<()> {
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
             .as_secs() as i64;
+        let rate_json = serde_json::to_string(rate).map_err(|e| AppError::Internal(e.to_string()))?;
 
         conn.execute(
-            r#"
-            UPDATE orders SET
-                tracking_number = ?,
-                label_url = ?,
-                shipping_carrier = COALESCE(?, shipping_carrier),
-                status = 'processing',
-                updated_ts = ?
-            WHERE id = ?
-            "#,
-            libsql::params![tracking_number.to_string(), label_url.to_string(), carrier.map(|s| s.to_string()), now, id.to_string()],
+            "UPDATE orders SET label_rate = ?, updated_ts = ? WHERE id = ?",
+            libsql::params![rate_json, now, id.to_string()],
         )
         .await
         .map_err(AppError::from)?;
 
-        Self::find_by_id(conn, id)
-            .await?
-            .ok_or_else(|| AppError::NotFound("Order not found".to_string()))
+        Ok(())
     }
 
-    pub async fn get_items(conn: &Connection, order_id: &str) -> AppResult<Vec<OrderItem>> {
+    /// Most recent order whose ID starts with `reference` (the short number shown in
+    /// emails, or the full ID) and whose customer has this email
+    pub async fn find_by_reference_and_email(
+        conn: &Connection,
+        reference: &str,
+        email: &str,
+    ) -> AppResult<Option<Self>> {
         let mut rows = conn
-            .query("SELECT * FROM order_items WHERE order_id = ?", [order_id])
+            .query(
+                "SELECT o.* FROM orders o JOIN users u ON u.id = o.user_id
+                 WHERE o.id LIKE ? AND lower(u.email) = lower(?)
+                 ORDER BY o.created_ts DESC LIMIT 1",
+                [format!("{}%", reference), email.to_string()],
+            )
             .await
             .map_err(AppError::from)?;
 
-        let mut items = Vec::new();
+        match rows.next().await.map_err(AppError::from)? {
+            Some(row) => Ok(Some(Self::from_row(&row).map_err(AppError::from)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn list_by_user(conn: &Connection, user_id: &str) -> AppResult<Vec<Self>> {
+        let mut rows = conn
+            .query(
+                "SELECT * FROM orders WHERE user_id = ? ORDER BY created_ts DESC",
+                [user_id],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        let mut orders = Vec::new();
         while let Some(row) = rows.next().await.map_err(AppError::from)? {
-            items.push(OrderItem::from_row(&row).map_err(AppError::from)?);
+            orders.push(Self::from_row(&row).map_err(AppError::from)?);
         }
-        Ok(items)
+        Ok(orders)
     }
 
-    pub async fn count_all(conn: &Connection) -> AppResult<i64> {
+    /// Number of orders the user has placed (checkouts that were never paid don't count)
+    pub async fn count_by_user(conn: &Connection, user_id: &str) -> AppResult<i64> {
         let mut rows = conn
-            .query("SELECT COUNT(*) FROM orders", ())
+            .query(
+                "SELECT COUNT(*) FROM orders WHERE user_id = ? AND status != 'pending'",
+                [user_id],
+            )
             .await
             .map_err(AppError::from)?;
 
-        match rows.next().await.map_err(AppError::from)? {
-            Some(row) => {
-                let count: i32 = row.get(0).map_err(AppError::from)?;
-                Ok(count as i64)
+        if let Some(row) = rows.next().await.map_err(AppError::from)? {
+            let count: i64 = row.get(0).map_err(AppError::from)?;
+            Ok(count)
+        } else {
+            Ok(0)
+        }
+    }
+
+    /// Strip personal details from the user's orders for an account deletion.
+    /// Totals, items, statuses, tracking and payment references stay for the
+    /// books; the address keeps only its state and country, for tax records.
+    pub async fn anonymize_for_user(conn: &Connection, user_id: &str) -> AppResult<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        conn.execute(
+            "UPDATE orders SET
+                shipping_address = CASE WHEN json_valid(shipping_address)
+                     THEN json_set(shipping_address, '$.name', 'Deleted customer', '$.street', '', '$.city', '', '$.zip', '')
+                     ELSE '{}' END,
+                gift_message = NULL, label_url = NULL, updated_ts = ?
+             WHERE user_id = ?",
+            libsql::params![now, user_id],
+        )
+        .await
+        .map_err(AppError::from)?;
+        Ok(())
+    }
+
+    /// Paid order count and total spent in cents per user, for the admin customer list.
+    /// Users without paid orders are missing from the map.
+    pub async fn totals_by_user(conn: &Connection, user_ids: &[String]) -> AppResult<HashMap<String, (i64, i64)>> {
+        let mut totals = HashMap::new();
+
+        for chunk in user_ids.chunks(IN_CLAUSE_CHUNK) {
+            let placeholders: Vec<&str> = chunk.iter().map(|_| "?").collect();
+            let query = format!(
+                "SELECT user_id, COUNT(*), SUM(total_cents) FROM orders
+                 WHERE user_id IN ({}) AND status IN ('paid', 'processing', 'shipped', 'out_for_delivery', 'delivered')
+                 GROUP BY user_id",
+                placeholders.join(", ")
+            );
+
+            let params: Vec<libsql::Value> = chunk.iter().map(|id| id.clone().into()).collect();
+            let mut rows = conn.query(&query, params).await.map_err(AppError::from)?;
+
+            while let Some(row) = rows.next().await.map_err(AppError::from)? {
+                let user_id: String = row.get(0).map_err(AppError::from)?;
+                let count: i64 = row.get(1).map_err(AppError::from)?;
+                let spent: i64 = row.get(2).map_err(AppError::from)?;
+                totals.insert(user_id, (count, spent));
             }
-            None => Ok(0),
         }
+
+        Ok(totals)
     }
 
-    pub async fn total_revenue(conn: &Connection) -> AppResult<i64> {
+    /// Product categories the user has bought from in paid orders, most bought first
+    pub async fn purchased_categories(conn: &Connection, user_id: &str) -> AppResult<Vec<String>> {
+        let mut rows = conn
+            .query(
+                "SELECT p.category FROM order_items oi
+                 JOIN orders o ON o.id = oi.order_id
+                 JOIN products p ON p.id = oi.product_id
+                 WHERE o.user_id = ? AND p.category IS NOT NULL
+                   AND o.status IN ('paid', 'processing', 'shipped', 'out_for_delivery', 'delivered')
+                 GROUP BY p.category
+                 ORDER BY SUM(oi.quantity) DESC",
+                [user_id],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        let mut categories = Vec::new();
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            categories.push(row.get::<String>(0).map_err(AppError::from)?);
+        }
+        Ok(categories)
+    }
+
+    /// Orders with a Shippo label that the carrier hasn't picked up yet
+    pub async fn list_awaiting_pickup(conn: &Connection) -> AppResult<Vec<Self>> {
         let mut rows = conn
             .query(
-                "SELECT SUM(total_cents) FROM orders WHERE status NOT IN ('pending', 'cancelled')",
+                "SELECT * FROM orders WHERE shippo_transaction_id IS NOT NULL AND status = 'processing' ORDER BY created_ts ASC",
                 (),
             )
             .await
             .map_err(AppError::from)?;
 
-        match rows.next().await.map_err(AppError::from)? {
-            Some(row) => {
-                let total: Option<i64> = row.get(0).map_err(AppError::from)?;
-                Ok(total.unwrap_or(0))
+        let mut orders = Vec::new();
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            orders.push(Self::from_row(&row).map_err(AppError::from)?);
+        }
+        Ok(orders)
+    }
+
+    pub async fn list_all(conn: &Connection) -> AppResult<Vec<Self>> {
+        let mut rows = conn
+            .query("SELECT * FROM orders ORDER BY created_ts DESC", ())
+            .await
+            .map_err(AppError::from)?;
+
+        let mut orders = Vec::new();
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            orders.push(Self::from_row(&row).map_err(AppError::from)?);
+        }
+        Ok(orders)
+    }
+
+    pub async fn create(conn: &Connection, data: CreateOrder) -> AppResult<Self> {
+        // Reserve stock and insert the order atomically so concurrent checkouts can't oversell
+        let tx = conn.transaction().await.map_err(AppError::from)?;
+        let id = match Self::insert(&tx, data).await {
+            Ok(id) => {
+                tx.commit().await.map_err(AppError::from)?;
+                id
             }
-            None => Ok(0),
+            Err(e) => {
+                let _ = tx.rollback().await;
+                return Err(e);
+            }
+        };
+
+        Self::find_by_id(conn, &id)
+            .await?
+            .ok_or_else(|| AppError::Internal("Failed to create order".to_string()))
+    }
+
+    /// Reserve stock and insert an order with its items, returning its ID. Run
+    /// inside a transaction.
+    pub async fn insert(conn: &Connection, data: CreateOrder) -> AppResult<String> {
+        let id = Uuid::new_v4().to_string();
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        let shipping_json = serde_json::to_string(&data.shipping_address)
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        let checkout_rate_json = data
+            .checkout_rate
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        if data.reserve_stock {
+            for item in &data.items {
+                Self::reserve_stock(conn, item).await?;
+            }
+        }
+
+        conn.execute(
+            "INSERT INTO orders (id, user_id, total_cents, shipping_address, stripe_session_id, created_ts, updated_ts, shipping_cents, shipping_carrier, shipping_service, estimated_delivery_days, is_gift, gift_message, gift_wrap, gift_wrap_cents, checkout_rate, promo_code, discount_cents, tip_cents, stock_reserved) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            libsql::params![id.clone(), data.user_id.clone(), data.total_cents, shipping_json, data.stripe_session_id.clone(), now, now, data.shipping_cents.unwrap_or(0), data.shipping_carrier, data.shipping_service, data.estimated_delivery_days, data.is_gift as i32, data.gift_message, data.gift_wrap as i32, data.gift_wrap_cents, checkout_rate_json, data.promo_code, data.discount_cents, data.tip_cents, data.reserve_stock as i32],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        for item in data.items {
+            let item_id = Uuid::new_v4().to_string();
+            conn.execute(
+                "INSERT INTO order_items (id, order_id, product_id, quantity, price_cents, style_id, style_name) VALUES (?, ?, ?, ?, ?, ?, ?)",
+                libsql::params![item_id, id.clone(), item.product_id, item.quantity, item.price_cents, item.style_id, item.style_name],
+            )
+            .await
+            .map_err(AppError::from)?;
+        }
+
+        Ok(id)
+    }
+
+    /// Take stock for one order item, failing if the product (or style) doesn't have enough
+    async fn reserve_stock(conn: &Connection, item: &CreateOrderItem) -> AppResult<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        let reserved = conn
+            .execute(
+                "UPDATE products SET stock_quantity = stock_quantity - ?, updated_ts = ? WHERE id = ? AND is_active = 1 AND stock_quantity >= ? AND ? > 0",
+                libsql::params![item.quantity, now, item.product_id.clone(), item.quantity, item.quantity],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        if reserved == 0 {
+            let mut rows = conn
+                .query("SELECT name, stock_quantity FROM products WHERE id = ?", [item.product_id.clone()])
+                .await
+                .map_err(AppError::from)?;
+            let (name, available) = match rows.next().await.map_err(AppError::from)? {
+                Some(row) => (
+                    row.get::<String>(0).map_err(AppError::from)?,
+                    row.get::<i64>(1).map_err(AppError::from)?,
+                ),
+                None => return Err(AppError::NotFound(format!("Product {} not found", item.product_id))),
+            };
+
+            return Err(AppError::InsufficientStock {
+                product_id: item.product_id.clone(),
+                style_id: None,
+                name,
+                available,
+            });
+        }
+
+        if let Some(ref style_id) = item.style_id {
+            let reserved = conn
+                .execute(
+                    "UPDATE product_styles SET stock_quantity = stock_quantity - ? WHERE id = ? AND stock_quantity >= ? AND ? > 0",
+                    libsql::params![item.quantity, style_id.clone(), item.quantity, item.quantity],
+                )
+                .await
+                .map_err(AppError::from)?;
+
+            if reserved == 0 {
+                let mut rows = conn
+                    .query(
+                        "SELECT p.name, ps.name, ps.stock_quantity FROM product_styles ps
+                         JOIN products p ON p.id = ps.product_id
+                         WHERE ps.id = ?",
+                        [style_id.clone()],
+                    )
+                    .await
+                    .map_err(AppError::from)?;
+                let (name, available) = match rows.next().await.map_err(AppError::from)? {
+                    Some(row) => (
+                        format!(
+                            "{} ({})",
+                            row.get::<String>(0).map_err(AppError::from)?,
+                            row.get::<String>(1).map_err(AppError::from)?
+                        ),
+                        row.get::<i64>(2).map_err(AppError::from)?,
+                    ),
+                    None => return Err(AppError::NotFound(format!("Style {} not found", style_id))),
+                };
+
+                return Err(AppError::InsufficientStock {
+                    product_id: item.product_id.clone(),
+                    style_id: Some(style_id.clone()),
+                    name,
+                    available,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Return an order's reserved stock to inventory (e.g. the order was cancelled
+    /// before payment). Does nothing if the stock was already released.
+    pub async fn release_stock(conn: &Connection, id: &str) -> AppResult<bool> {
+        let released = conn
+            .execute(
+                "UPDATE orders SET stock_reserved = 0 WHERE id = ? AND stock_reserved = 1",
+                [id],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        if released == 0 {
+            return Ok(false);
+        }
+
+        Self::restock_items(conn, id).await?;
+        Ok(true)
+    }
+
+    /// Add an order's item quantities back to product and style stock
+    pub async fn restock_items(conn: &Connection, id: &str) -> AppResult<()> {
+        for item in Self::get_items(conn, id).await? {
+            conn.execute(
+                "UPDATE products SET stock_quantity = stock_quantity + ? WHERE id = ?",
+                libsql::params![item.quantity, item.product_id.clone()],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+            if let Some(style_id) = item.style_id {
+                conn.execute(
+                    "UPDATE product_styles SET stock_quantity = stock_quantity + ? WHERE id = ?",
+                    libsql::params![item.quantity, style_id],
+                )
+                .await
+                .map_err(AppError::from)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn set_stripe_session(
+        conn: &Connection,
+        id: &str,
+        session_id: &str,
+        expires_ts: i64,
+    ) -> AppResult<Self> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        conn.execute(
+            "UPDATE orders SET stripe_session_id = ?, checkout_expires_ts = ?, updated_ts = ? WHERE id = ?",
+            libsql::params![session_id.to_string(), expires_ts, now, id.to_string()],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Self::find_by_id(conn, id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Order not found".to_string()))
+    }
+
+    /// Move an order to a new status, rejecting transitions the state machine
+    /// doesn't allow. `force` skips validation (admin override).
+    pub async fn update_status(
+        conn: &Connection,
+        id: &str,
+        status: OrderStatus,
+        force: bool,
+    ) -> AppResult<Self> {
+        let current = Self::find_by_id(conn, id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Order not found".to_string()))?;
+
+        if !force {
+            if status == OrderStatus::OutForDelivery && !current.is_local_delivery() {
+                return Err(AppError::BadRequest(
+                    "Only local delivery orders can be out for delivery".to_string(),
+                ));
+            }
+            if let Some(current_status) = current.get_status() {
+                if !current_status.can_transition_to(status) {
+                    return Err(AppError::BadRequest(format!(
+                        "Cannot change order status from {} to {}",
+                        current_status.as_str(),
+                        status.as_str()
+                    )));
+                }
+            }
+        }
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        // Only write over the status we validated against; a concurrent change
+        // (e.g. a webhook) in between means the transition may no longer hold
+        let updated = conn
+            .execute(
+                "UPDATE orders SET status = ?, updated_ts = ? WHERE id = ? AND status = ?",
+                libsql::params![status.as_str().to_string(), now, id.to_string(), current.status.clone()],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        if updated == 0 {
+            return Err(AppError::BadRequest(
+                "Order status changed concurrently, please retry".to_string(),
+            ));
+        }
+
+        // An unpaid order that's cancelled gives its reserved stock back
+        if status == OrderStatus::Cancelled && current.get_status() == Some(OrderStatus::Pending) {
+            Self::release_stock(conn, id).await?;
+        }
+
+        Self::find_by_id(conn, id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Order not found".to_string()))
+    }
+
+    pub async fn set_tracking(
+        conn: &Connection,
+        id: &str,
+        tracking_number: &str,
+        shippo_tracker_id: Option<&str>,
+    ) -> AppResult<Self> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        conn.execute(
+            r#"
+            UPDATE orders SET
+                tracking_number = ?,
+                shippo_tracker_id = ?,
+                status = 'shipped',
+                updated_ts = ?
+            WHERE id = ?
+            "#,
+            libsql::params![tracking_number.to_string(), shippo_tracker_id.map(|s| s.to_string()), now, id.to_string()],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Self::find_by_id(conn, id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Order not found".to_string()))
+    }
+
+    pub async fn set_label(
+        conn: &Connection,
+        id: &str,
+        tracking_number: &str,
+        label_url: &str,
+        carrier: Option<&str>,
+        transaction_id: Option<&str>,
+    ) -> AppResult<Self> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        conn.execute(
+            r#"
+            UPDATE orders SET
+                tracking_number = ?,
+                label_url = ?,
+                shipping_carrier = COALESCE(?, shipping_carrier),
+                shippo_transaction_id = ?,
+                status = 'processing',
+                updated_ts = ?
+            WHERE id = ?
+            "#,
+            libsql::params![tracking_number.to_string(), label_url.to_string(), carrier.map(|s| s.to_string()), transaction_id.map(|s| s.to_string()), now, id.to_string()],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Self::find_by_id(conn, id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Order not found".to_string()))
+    }
+
+    /// Remember a label Shippo is still generating; `set_label` completes it
+    pub async fn set_pending_label(conn: &Connection, id: &str, transaction_id: &str) -> AppResult<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        conn.execute(
+            "UPDATE orders SET shippo_transaction_id = ?, updated_ts = ? WHERE id = ?",
+            libsql::params![transaction_id.to_string(), now, id.to_string()],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Ok(())
+    }
+
+    /// Drop a pending label that Shippo failed to generate so another can be bought
+    pub async fn clear_pending_label(conn: &Connection, id: &str) -> AppResult<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        conn.execute(
+            r#"
+            UPDATE orders SET
+                shippo_transaction_id = NULL,
+                insurance_amount_cents = NULL,
+                insurance_cost_cents = NULL,
+                signature_confirmation = NULL,
+                updated_ts = ?
+            WHERE id = ? AND label_url IS NULL
+            "#,
+            libsql::params![now, id.to_string()],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Ok(())
+    }
+
+    pub async fn find_by_shippo_transaction(conn: &Connection, transaction_id: &str) -> AppResult<Option<Self>> {
+        let mut rows = conn
+            .query("SELECT * FROM orders WHERE shippo_transaction_id = ?", [transaction_id])
+            .await
+            .map_err(AppError::from)?;
+
+        match rows.next().await.map_err(AppError::from)? {
+            Some(row) => Ok(Some(Self::from_row(&row).map_err(AppError::from)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Record the insurance bought with the label (cost is None when it couldn't be worked out)
+    pub async fn set_insurance(conn: &Connection, id: &str, amount_cents: i32, cost_cents: Option<i32>) -> AppResult<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        conn.execute(
+            "UPDATE orders SET insurance_amount_cents = ?, insurance_cost_cents = ?, updated_ts = ? WHERE id = ?",
+            libsql::params![amount_cents, cost_cents, now, id.to_string()],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Ok(())
+    }
+
+    pub async fn set_signature_confirmation(conn: &Connection, id: &str, signature: &str) -> AppResult<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        conn.execute(
+            "UPDATE orders SET signature_confirmation = ?, updated_ts = ? WHERE id = ?",
+            libsql::params![signature.to_string(), now, id.to_string()],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Ok(())
+    }
+
+    /// Flag or clear an order whose shipping was charged at a fallback rate
+    pub async fn set_rate_review(conn: &Connection, id: &str, needs_review: bool) -> AppResult<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        conn.execute(
+            "UPDATE orders SET needs_rate_review = ?, updated_ts = ? WHERE id = ?",
+            libsql::params![needs_review as i32, now, id.to_string()],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Ok(())
+    }
+
+    /// Forget a voided label and put the order back in line for shipping
+    pub async fn clear_label(conn: &Connection, id: &str) -> AppResult<Self> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        conn.execute(
+            r#"
+            UPDATE orders SET
+                tracking_number = NULL,
+                shippo_tracker_id = NULL,
+                label_url = NULL,
+                label_rate = NULL,
+                shippo_transaction_id = NULL,
+                insurance_amount_cents = NULL,
+                insurance_cost_cents = NULL,
+                signature_confirmation = NULL,
+                status = 'paid',
+                updated_ts = ?
+            WHERE id = ?
+            "#,
+            libsql::params![now, id.to_string()],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Self::find_by_id(conn, id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Order not found".to_string()))
+    }
+
+    pub async fn get_items(conn: &Connection, order_id: &str) -> AppResult<Vec<OrderItem>> {
+        let mut rows = conn
+            .query("SELECT * FROM order_items WHERE order_id = ?", [order_id])
+            .await
+            .map_err(AppError::from)?;
+
+        let mut items = Vec::new();
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            items.push(OrderItem::from_row(&row).map_err(AppError::from)?);
+        }
+        Ok(items)
+    }
+
+    /// Load the items (with product names) for many orders in one query per chunk,
+    /// keyed by order ID
+    pub async fn get_items_for_orders(
+        conn: &Connection,
+        order_ids: &[String],
+    ) -> AppResult<HashMap<String, Vec<OrderItemDetail>>> {
+        let mut items_by_order: HashMap<String, Vec<OrderItemDetail>> = HashMap::new();
+
+        for chunk in order_ids.chunks(IN_CLAUSE_CHUNK) {
+            let placeholders: Vec<&str> = chunk.iter().map(|_| "?").collect();
+            let query = format!(
+                "SELECT oi.order_id, oi.product_id, p.name, oi.style_name, oi.quantity, oi.price_cents
+                 FROM order_items oi
+                 LEFT JOIN products p ON p.id = oi.product_id
+                 WHERE oi.order_id IN ({})",
+                placeholders.join(", ")
+            );
+
+            let params: Vec<libsql::Value> = chunk.iter().map(|id| id.clone().into()).collect();
+            let mut rows = conn.query(&query, params).await.map_err(AppError::from)?;
+
+            while let Some(row) = rows.next().await.map_err(AppError::from)? {
+                let item = OrderItemDetail {
+                    order_id: row.get(0).map_err(AppError::from)?,
+                    product_id: row.get(1).map_err(AppError::from)?,
+                    product_name: row.get(2).ok(),
+                    style_name: row.get(3).ok(),
+                    quantity: row.get(4).map_err(AppError::from)?,
+                    price_cents: row.get(5).map_err(AppError::from)?,
+                };
+                items_by_order.entry(item.order_id.clone()).or_default().push(item);
+            }
+        }
+
+        Ok(items_by_order)
+    }
+
+    /// Cancel a pending order whose Checkout session expired, releasing its stock
+    pub async fn cancel_expired_checkout(conn: &Connection, id: &str) -> AppResult<Self> {
+        Self::cancel_with_reason(conn, id, "checkout_expired").await
+    }
+
+    /// Cancel a pending order whose delayed payment (e.g. bank debit) failed, releasing its stock
+    pub async fn cancel_failed_payment(conn: &Connection, id: &str) -> AppResult<Self> {
+        Self::cancel_with_reason(conn, id, "payment_failed").await
+    }
+
+    /// Cancel a pending order left behind by the cart cleanup job, releasing its stock
+    pub async fn cancel_abandoned(conn: &Connection, id: &str) -> AppResult<Self> {
+        Self::cancel_with_reason(conn, id, "abandoned").await
+    }
+
+    /// Claim a cancelled order's late payment for returning to the customer. Returns
+    /// false if the order isn't cancelled or the payment was already claimed, so a
+    /// redelivered event can't return it twice.
+    pub async fn claim_late_payment(conn: &Connection, id: &str) -> AppResult<bool> {
+        let claimed = conn
+            .execute(
+                "UPDATE orders SET cancel_reason = 'paid_after_cancel'
+                 WHERE id = ? AND status = 'cancelled' AND COALESCE(cancel_reason, '') != 'paid_after_cancel'",
+                [id.to_string()],
+            )
+            .await
+            .map_err(AppError::from)?;
+        Ok(claimed > 0)
+    }
+
+    async fn cancel_with_reason(conn: &Connection, id: &str, reason: &str) -> AppResult<Self> {
+        let mut order = Self::update_status(conn, id, OrderStatus::Cancelled, false).await?;
+
+        conn.execute(
+            "UPDATE orders SET cancel_reason = ? WHERE id = ?",
+            libsql::params![reason.to_string(), id.to_string()],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        order.cancel_reason = Some(reason.to_string());
+        Ok(order)
+    }
+
+    /// Tie an order to the subscription billing cycle (invoice) it fulfils
+    pub async fn set_subscription_cycle(
+        conn: &Connection,
+        id: &str,
+        subscription_id: &str,
+        invoice_id: &str,
+    ) -> AppResult<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        conn.execute(
+            "UPDATE orders SET subscription_id = ?, stripe_invoice_id = ?, updated_ts = ? WHERE id = ?",
+            libsql::params![subscription_id.to_string(), invoice_id.to_string(), now, id.to_string()],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Ok(())
+    }
+
+    pub async fn find_by_invoice(conn: &Connection, invoice_id: &str) -> AppResult<Option<Self>> {
+        let mut rows = conn
+            .query("SELECT * FROM orders WHERE stripe_invoice_id = ?", [invoice_id])
+            .await
+            .map_err(AppError::from)?;
+
+        match rows.next().await.map_err(AppError::from)? {
+            Some(row) => Ok(Some(Self::from_row(&row).map_err(AppError::from)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Record that an order's payment goes to an artist's connected account
+    pub async fn set_artist(conn: &Connection, id: &str, artist_id: &str, application_fee_cents: i32) -> AppResult<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        conn.execute(
+            "UPDATE orders SET artist_id = ?, application_fee_cents = ?, updated_ts = ? WHERE id = ?",
+            libsql::params![artist_id.to_string(), application_fee_cents, now, id.to_string()],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Ok(())
+    }
+
+    /// Per-artist sales for marketplace payout reporting:
+    /// (artist_id, orders, gross cents, shop fee cents). Refunded orders are left out.
+    pub async fn sales_by_artist(conn: &Connection) -> AppResult<Vec<(String, i64, i64, i64)>> {
+        let mut rows = conn
+            .query(
+                "SELECT artist_id, COUNT(*), SUM(total_cents), SUM(COALESCE(application_fee_cents, 0))
+                 FROM orders
+                 WHERE artist_id IS NOT NULL AND status NOT IN ('pending', 'cancelled', 'refunded')
+                 GROUP BY artist_id
+                 ORDER BY 3 DESC",
+                (),
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        let mut sales = Vec::new();
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            let gross: Option<i64> = row.get(2).map_err(AppError::from)?;
+            let fees: Option<i64> = row.get(3).map_err(AppError::from)?;
+            sales.push((
+                row.get(0).map_err(AppError::from)?,
+                row.get(1).map_err(AppError::from)?,
+                gross.unwrap_or(0),
+                fees.unwrap_or(0),
+            ));
+        }
+        Ok(sales)
+    }
+
+    /// Record that Checkout completed with a delayed payment method whose funds haven't cleared.
+    /// The order stays pending, but the cart cleanup job leaves it alone.
+    pub async fn set_payment_processing(conn: &Connection, id: &str) -> AppResult<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        conn.execute(
+            "UPDATE orders SET payment_processing_ts = COALESCE(payment_processing_ts, ?), updated_ts = ? WHERE id = ?",
+            libsql::params![now, now, id.to_string()],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Ok(())
+    }
+
+    /// Record that the payment was only authorized and still needs capturing
+    pub async fn set_authorized(conn: &Connection, id: &str) -> AppResult<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        conn.execute(
+            "UPDATE orders SET capture_status = 'authorized', authorized_ts = ?, updated_ts = ? WHERE id = ?",
+            libsql::params![now, now, id.to_string()],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Ok(())
+    }
+
+    pub async fn set_captured(conn: &Connection, id: &str) -> AppResult<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        conn.execute(
+            "UPDATE orders SET capture_status = 'captured', updated_ts = ? WHERE id = ?",
+            libsql::params![now, id.to_string()],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Ok(())
+    }
+
+    pub fn is_awaiting_capture(&self) -> bool {
+        self.capture_status.as_deref() == Some("authorized")
+    }
+
+    /// Cancel an order whose authorization was released without capture and put
+    /// its stock back. Run inside a transaction.
+    pub async fn void_authorization(conn: &Connection, id: &str, reason: &str) -> AppResult<Self> {
+        let mut order = Self::cancel_with_reason(conn, id, reason).await?;
+
+        // Paid orders keep their reservation flag; older ones took stock at payment
+        if !Self::release_stock(conn, id).await? {
+            Self::restock_items(conn, id).await?;
+        }
+
+        conn.execute(
+            "UPDATE orders SET capture_status = 'voided' WHERE id = ?",
+            [id],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        order.capture_status = Some("voided".to_string());
+        Ok(order)
+    }
+
+    /// Authorized orders not yet captured whose hold was placed before `cutoff_ts`
+    pub async fn list_expired_authorizations(conn: &Connection, cutoff_ts: i64) -> AppResult<Vec<Self>> {
+        let mut rows = conn
+            .query(
+                "SELECT * FROM orders
+                 WHERE capture_status = 'authorized' AND authorized_ts < ?
+                   AND status IN ('paid', 'processing')",
+                [cutoff_ts],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        let mut orders = Vec::new();
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            orders.push(Self::from_row(&row).map_err(AppError::from)?);
+        }
+        Ok(orders)
+    }
+
+    /// Pending orders still holding stock that can no longer be paid: their Checkout
+    /// session has expired, or they never got one and were created before `cutoff_ts`.
+    /// Orders waiting on a delayed payment to clear are skipped. Each comes with the
+    /// PaymentIntent of an order paid without a Checkout session, which nothing else
+    /// expires and so has to be cancelled before the order is.
+    pub async fn list_stale_pending_ids(
+        conn: &Connection,
+        now: i64,
+        cutoff_ts: i64,
+    ) -> AppResult<Vec<(String, Option<String>)>> {
+        let mut rows = conn
+            .query(
+                "SELECT id, CASE WHEN checkout_expires_ts IS NULL THEN stripe_payment_intent_id END FROM orders
+                 WHERE status = 'pending' AND stock_reserved = 1 AND payment_link_id IS NULL
+                   AND payment_processing_ts IS NULL
+                   AND ((checkout_expires_ts IS NOT NULL AND checkout_expires_ts < ?)
+                     OR (checkout_expires_ts IS NULL AND created_ts < ?))",
+                libsql::params![now, cutoff_ts],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        let mut ids = Vec::new();
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            ids.push((row.get(0).map_err(AppError::from)?, row.get(1).ok()));
+        }
+        Ok(ids)
+    }
+
+    /// Orders cancelled without payment because the customer walked away
+    pub async fn count_abandoned(conn: &Connection) -> AppResult<i64> {
+        let mut rows = conn
+            .query(
+                "SELECT COUNT(*) FROM orders WHERE cancel_reason IN ('checkout_expired', 'abandoned')",
+                (),
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        match rows.next().await.map_err(AppError::from)? {
+            Some(row) => Ok(row.get(0).map_err(AppError::from)?),
+            None => Ok(0),
+        }
+    }
+
+    /// Checkout funnel counts: (sessions started, sessions paid, sessions expired)
+    pub async fn checkout_stats(conn: &Connection) -> AppResult<(i64, i64, i64)> {
+        let mut rows = conn
+            .query(
+                "SELECT
+                    COUNT(*),
+                    COALESCE(SUM(CASE WHEN stripe_payment_intent_id IS NOT NULL THEN 1 ELSE 0 END), 0),
+                    COALESCE(SUM(CASE WHEN cancel_reason = 'checkout_expired' THEN 1 ELSE 0 END), 0)
+                 FROM orders WHERE stripe_session_id IS NOT NULL",
+                (),
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        match rows.next().await.map_err(AppError::from)? {
+            Some(row) => Ok((
+                row.get(0).map_err(AppError::from)?,
+                row.get(1).map_err(AppError::from)?,
+                row.get(2).map_err(AppError::from)?,
+            )),
+            None => Ok((0, 0, 0)),
+        }
+    }
+
+    pub async fn count_all(conn: &Connection) -> AppResult<i64> {
+        let mut rows = conn
+            .query("SELECT COUNT(*) FROM orders", ())
+            .await
+            .map_err(AppError::from)?;
+
+        match rows.next().await.map_err(AppError::from)? {
+            Some(row) => {
+                let count: i32 = row.get(0).map_err(AppError::from)?;
+                Ok(count as i64)
+            }
+            None => Ok(0),
+        }
+    }
+
+    /// Tips received on paid orders (not counted in revenue)
+    pub async fn total_tips(conn: &Connection) -> AppResult<i64> {
+        let mut rows = conn
+            .query(
+                "SELECT SUM(tip_cents) FROM orders WHERE status NOT IN ('pending', 'cancelled')",
+                (),
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        match rows.next().await.map_err(AppError::from)? {
+            Some(row) => {
+                let total: Option<i64> = row.get(0).map_err(AppError::from)?;
+                Ok(total.unwrap_or(0))
+            }
+            None => Ok(0),
+        }
+    }
+
+    /// Revenue from paid orders, excluding tips
+    /// Orders and revenue per payment method: (method, orders, revenue_cents).
+    /// Wallets (Apple Pay, Google Pay, Link) are reported separately from plain cards.
+    pub async fn revenue_by_payment_method(conn: &Connection) -> AppResult<Vec<(String, i64, i64)>> {
+        Self::revenue_grouped_by(conn, "COALESCE(payment_wallet, payment_method_type, 'unknown')").await
+    }
+
+    /// Orders and revenue per card brand: (brand, orders, revenue_cents)
+    pub async fn revenue_by_card_brand(conn: &Connection) -> AppResult<Vec<(String, i64, i64)>> {
+        Self::revenue_grouped_by(conn, "card_brand").await
+    }
+
+    async fn revenue_grouped_by(conn: &Connection, group_expr: &str) -> AppResult<Vec<(String, i64, i64)>> {
+        let query = format!(
+            "SELECT {expr}, COUNT(*), SUM(total_cents - COALESCE(tip_cents, 0))
+             FROM orders
+             WHERE status NOT IN ('pending', 'cancelled') AND {expr} IS NOT NULL
+             GROUP BY {expr}
+             ORDER BY 3 DESC",
+            expr = group_expr
+        );
+        let mut rows = conn.query(&query, ()).await.map_err(AppError::from)?;
+
+        let mut groups = Vec::new();
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            let revenue: Option<i64> = row.get(2).map_err(AppError::from)?;
+            groups.push((
+                row.get(0).map_err(AppError::from)?,
+                row.get(1).map_err(AppError::from)?,
+                revenue.unwrap_or(0),
+            ));
+        }
+        Ok(groups)
+    }
+
+    pub async fn total_revenue(conn: &Connection) -> AppResult<i64> {
+        let mut rows = conn
+            .query(
+                "SELECT SUM(total_cents - COALESCE(tip_cents, 0)) FROM orders WHERE status NOT IN ('pending', 'cancelled')",
+                (),
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        match rows.next().await.map_err(AppError::from)? {
+            Some(row) => {
+                let total: Option<i64> = row.get(0).map_err(AppError::from)?;
+                Ok(total.unwrap_or(0))
+            }
+            None => Ok(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OrderStatus::{self, *};
+
+    const ALL: [OrderStatus; 7] = [Pending, Paid, Processing, Shipped, Delivered, Cancelled, Refunded];
+
+    #[test]
+    fn forward_transitions_are_allowed() {
+        assert!(Pending.can_transition_to(Paid));
+        assert!(Paid.can_transition_to(Processing));
+        assert!(Paid.can_transition_to(Shipped));
+        assert!(Processing.can_transition_to(Shipped));
+        assert!(Shipped.can_transition_to(Delivered));
+        assert!(Delivered.can_transition_to(Refunded));
+    }
+
+    #[test]
+    fn unpaid_orders_can_only_be_paid_or_cancelled() {
+        for next in ALL {
+            assert_eq!(Pending.can_transition_to(next), matches!(next, Paid | Cancelled), "pending -> {:?}", next);
+        }
+    }
+
+    #[test]
+    fn final_statuses_go_nowhere() {
+        for next in ALL {
+            assert!(!Cancelled.can_transition_to(next), "cancelled -> {:?}", next);
+            assert!(!Refunded.can_transition_to(next), "refunded -> {:?}", next);
+        }
+    }
+
+    #[test]
+    fn staying_put_is_not_a_transition() {
+        for status in ALL {
+            assert!(!status.can_transition_to(status), "{:?} -> itself", status);
+        }
+    }
+
+    #[test]
+    fn orders_never_move_backwards() {
+        assert!(!Paid.can_transition_to(Pending));
+        assert!(!Shipped.can_transition_to(Processing));
+        assert!(!Delivered.can_transition_to(Shipped));
+        assert!(!Delivered.can_transition_to(Cancelled));
+    }
+
+    #[test]
+    fn status_names_round_trip() {
+        for status in ALL {
+            assert_eq!(OrderStatus::from_str(status.as_str()), Some(status));
+        }
+        assert_eq!(OrderStatus::from_str("lost"), None);
+    }
+
+    mod reservation {
+        use super::super::{CreateOrderItem, Order};
+        use crate::error::AppError;
+        use libsql::{Builder, Connection};
+
+        async fn connect() -> Connection {
+            let db = Builder::new_local(":memory:").build().await.unwrap();
+            let conn = db.connect().unwrap();
+            conn.execute_batch(
+                r#"
+                CREATE TABLE products (id TEXT PRIMARY KEY, name TEXT NOT NULL, stock_quantity INTEGER NOT NULL, is_active INTEGER NOT NULL DEFAULT 1, updated_ts INTEGER);
+                CREATE TABLE product_styles (id TEXT PRIMARY KEY, product_id TEXT NOT NULL, name TEXT NOT NULL, stock_quantity INTEGER NOT NULL);
+                INSERT INTO products (id, name, stock_quantity) VALUES ('mug', 'Mug', 5);
+                INSERT INTO product_styles (id, product_id, name, stock_quantity) VALUES ('blue', 'mug', 'Blue', 2);
+                "#,
+            )
+            .await
+            .unwrap();
+            conn
+        }
+
+        fn item(quantity: i32, style_id: Option<&str>) -> CreateOrderItem {
+            CreateOrderItem {
+                product_id: "mug".to_string(),
+                quantity,
+                price_cents: 2500,
+                style_id: style_id.map(str::to_string),
+                style_name: None,
+            }
+        }
+
+        async fn stock(conn: &Connection, sql: &str) -> i64 {
+            let mut rows = conn.query(sql, ()).await.unwrap();
+            rows.next().await.unwrap().unwrap().get::<i64>(0).unwrap()
+        }
+
+        async fn product_stock(conn: &Connection) -> i64 {
+            stock(conn, "SELECT stock_quantity FROM products WHERE id = 'mug'").await
+        }
+
+        async fn style_stock(conn: &Connection) -> i64 {
+            stock(conn, "SELECT stock_quantity FROM product_styles WHERE id = 'blue'").await
+        }
+
+        #[tokio::test]
+        async fn reserving_takes_stock() {
+            let conn = connect().await;
+            Order::reserve_stock(&conn, &item(3, None)).await.unwrap();
+            assert_eq!(product_stock(&conn).await, 2);
+        }
+
+        #[tokio::test]
+        async fn the_last_unit_can_be_reserved() {
+            let conn = connect().await;
+            Order::reserve_stock(&conn, &item(5, None)).await.unwrap();
+            assert_eq!(product_stock(&conn).await, 0);
+        }
+
+        #[tokio::test]
+        async fn overselling_is_rejected() {
+            let conn = connect().await;
+            let err = Order::reserve_stock(&conn, &item(6, None)).await.unwrap_err();
+            assert!(matches!(err, AppError::InsufficientStock { available: 5, style_id: None, .. }));
+            assert_eq!(product_stock(&conn).await, 5);
+        }
+
+        #[tokio::test]
+        async fn negative_and_zero_quantities_take_nothing() {
+            let conn = connect().await;
+            for quantity in [-3, 0] {
+                assert!(Order::reserve_stock(&conn, &item(quantity, None)).await.is_err());
+                assert!(Order::reserve_stock(&conn, &item(quantity, Some("blue"))).await.is_err());
+            }
+            assert_eq!(product_stock(&conn).await, 5);
+            assert_eq!(style_stock(&conn).await, 2);
+        }
+
+        #[tokio::test]
+        async fn styles_keep_their_own_count() {
+            let conn = connect().await;
+            Order::reserve_stock(&conn, &item(2, Some("blue"))).await.unwrap();
+            assert_eq!(product_stock(&conn).await, 3);
+            assert_eq!(style_stock(&conn).await, 0);
+        }
+
+        #[tokio::test]
+        async fn overselling_a_style_is_rejected() {
+            let conn = connect().await;
+            let err = Order::reserve_stock(&conn, &item(3, Some("blue"))).await.unwrap_err();
+            assert!(matches!(err, AppError::InsufficientStock { available: 2, style_id: Some(_), .. }));
+            assert_eq!(style_stock(&conn).await, 2);
+        }
+
+        #[tokio::test]
+        async fn inactive_products_cant_be_reserved() {
+            let conn = connect().await;
+            conn.execute("UPDATE products SET is_active = 0", ()).await.unwrap();
+            assert!(Order::reserve_stock(&conn, &item(1, None)).await.is_err());
+            assert_eq!(product_stock(&conn).await, 5);
         }
     }
 }
@@ -147,6 +147,14 @@ pub struct Product {
     pub length_cm: Option<f64>,
     pub width_cm: Option<f64>,
     pub height_cm: Option<f64>,
+    pub category: Option<String>,
+    // Marketplace owner (None = sold by the shop itself)
+    pub artist_id: Option<String>,
+    // Subscription products bill every `billing_interval` (month or year); None = one-time
+    pub billing_interval: Option<String>,
+    pub stripe_recurring_price_id: Option<String>,
+    // Box to ship in when the order fits (None = packed automatically)
+    pub box_preset_id: Option<String>,
 }
 
 impl Product {
@@ -172,6 +180,14 @@ impl Product {
             length_cm: row.get(14).ok(),
             width_cm: row.get(15).ok(),
             height_cm: row.get(16).ok(),
+            category: row.get(17).ok(),
+            // Artist (column 18 after migration 042)
+            artist_id: row.get(18).ok(),
+            // Subscription (columns 19-20 after migration 043)
+            billing_interval: row.get(19).ok(),
+            stripe_recurring_price_id: row.get(20).ok(),
+            // Preferred box (column 21 after migration 048)
+            box_preset_id: row.get(21).ok(),
         })
     }
 }
@@ -187,6 +203,10 @@ pub struct CreateProduct {
     pub length_cm: Option<f64>,
     pub width_cm: Option<f64>,
     pub height_cm: Option<f64>,
+    pub category: Option<String>,
+    pub artist_id: Option<String>,
+    pub billing_interval: Option<String>,
+    pub box_preset_id: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -203,9 +223,22 @@ pub struct UpdateProduct {
     pub length_cm: Option<f64>,
     pub width_cm: Option<f64>,
     pub height_cm: Option<f64>,
+    pub category: Option<String>,
+    pub artist_id: Option<String>,
+    pub billing_interval: Option<String>,
+    /// An empty string clears the preferred box
+    pub box_preset_id: Option<String>,
 }
 
+/// Billing intervals a subscription product can use
+pub const BILLING_INTERVALS: &[&str] = &["month", "year"];
+
 impl Product {
+    /// Sold as a recurring subscription rather than through the cart
+    pub fn is_subscription(&self) -> bool {
+        self.billing_interval.is_some()
+    }
+
     pub async fn list_active(conn: &Connection) -> AppResult<Vec<Self>> {
         let mut rows = conn
             .query(
@@ -222,6 +255,23 @@ impl Product {
         Ok(products)
     }
 
+    /// Active products added at or after `since_ts`, newest first
+    pub async fn list_created_since(conn: &Connection, since_ts: i64) -> AppResult<Vec<Self>> {
+        let mut rows = conn
+            .query(
+                "SELECT * FROM products WHERE is_active = 1 AND created_ts >= ? ORDER BY created_ts DESC",
+                [since_ts],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        let mut products = Vec::new();
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            products.push(Self::from_row(&row).map_err(AppError::from)?);
+        }
+        Ok(products)
+    }
+
     pub async fn list_all(conn: &Connection) -> AppResult<Vec<Self>> {
         let mut rows = conn
             .query("SELECT * FROM products ORDER BY created_ts DESC", ())
@@ -247,6 +297,74 @@ impl Product {
         }
     }
 
+    /// Cheapest in-stock active products that could be added to a cart.
+    /// Restricted to `categories` when any are given; `exclude_ids` is the cart itself.
+    pub async fn list_cross_sell(
+        conn: &Connection,
+        categories: &[String],
+        exclude_ids: &[String],
+        max_price_cents: i32,
+        limit: i64,
+    ) -> AppResult<Vec<Self>> {
+        let mut query = String::from(
+            "SELECT * FROM products WHERE is_active = 1 AND stock_quantity > 0 AND price_cents <= ?",
+        );
+        let mut params: Vec<libsql::Value> = vec![max_price_cents.into()];
+
+        if !categories.is_empty() {
+            let placeholders: Vec<&str> = categories.iter().map(|_| "?").collect();
+            query.push_str(&format!(" AND category IN ({})", placeholders.join(", ")));
+            params.extend(categories.iter().map(|c| libsql::Value::from(c.clone())));
+        }
+        if !exclude_ids.is_empty() {
+            let placeholders: Vec<&str> = exclude_ids.iter().map(|_| "?").collect();
+            query.push_str(&format!(" AND id NOT IN ({})", placeholders.join(", ")));
+            params.extend(exclude_ids.iter().map(|id| libsql::Value::from(id.clone())));
+        }
+        query.push_str(" ORDER BY price_cents ASC, created_ts DESC LIMIT ?");
+        params.push(limit.into());
+
+        let mut rows = conn.query(&query, params).await.map_err(AppError::from)?;
+
+        let mut products = Vec::new();
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            products.push(Self::from_row(&row).map_err(AppError::from)?);
+        }
+        Ok(products)
+    }
+
+    /// In-stock one-time products most often bought in the same paid order as any of
+    /// this order's items, excluding the items themselves
+    pub async fn list_bought_with(conn: &Connection, order_id: &str, limit: i64) -> AppResult<Vec<Self>> {
+        let mut rows = conn
+            .query(
+                "SELECT p.* FROM products p
+                 JOIN (
+                     SELECT other.product_id, COUNT(DISTINCT other.order_id) AS together
+                     FROM order_items bought
+                     JOIN order_items other ON other.order_id = bought.order_id AND other.product_id != bought.product_id
+                     JOIN orders o ON o.id = bought.order_id
+                     WHERE bought.product_id IN (SELECT product_id FROM order_items WHERE order_id = ?)
+                       AND bought.order_id != ?
+                       AND other.product_id NOT IN (SELECT product_id FROM order_items WHERE order_id = ?)
+                       AND o.status IN ('paid', 'processing', 'shipped', 'out_for_delivery', 'delivered')
+                     GROUP BY other.product_id
+                 ) c ON c.product_id = p.id
+                 WHERE p.is_active = 1 AND p.stock_quantity > 0 AND p.billing_interval IS NULL
+                 ORDER BY c.together DESC, p.created_ts DESC
+                 LIMIT ?",
+                libsql::params![order_id.to_string(), order_id.to_string(), order_id.to_string(), limit],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        let mut products = Vec::new();
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            products.push(Self::from_row(&row).map_err(AppError::from)?);
+        }
+        Ok(products)
+    }
+
     pub async fn create(conn: &Connection, data: CreateProduct) -> AppResult<Self> {
         let id = Uuid::new_v4().to_string();
         let now = std::time::SystemTime::now()
@@ -255,8 +373,8 @@ impl Product {
             .as_secs() as i64;
 
         conn.execute(
-            "INSERT INTO products (id, name, description, price_cents, stock_quantity, created_ts, updated_ts, weight_grams, length_cm, width_cm, height_cm) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
-            libsql::params![id.clone(), data.name, data.description, data.price_cents, data.stock_quantity.unwrap_or(0), now, now, data.weight_grams, data.length_cm, data.width_cm, data.height_cm],
+            "INSERT INTO products (id, name, description, price_cents, stock_quantity, created_ts, updated_ts, weight_grams, length_cm, width_cm, height_cm, category, artist_id, billing_interval, box_preset_id) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            libsql::params![id.clone(), data.name, data.description, data.price_cents, data.stock_quantity.unwrap_or(0), now, now, data.weight_grams, data.length_cm, data.width_cm, data.height_cm, data.category, data.artist_id, data.billing_interval, data.box_preset_id.filter(|id| !id.is_empty())],
         )
         .await
         .map_err(AppError::from)?;
@@ -283,6 +401,13 @@ impl Product {
         let length_cm = data.length_cm.or(current.length_cm);
         let width_cm = data.width_cm.or(current.width_cm);
         let height_cm = data.height_cm.or(current.height_cm);
+        let category = data.category.or(current.category);
+        let artist_id = data.artist_id.or(current.artist_id);
+        let billing_interval = data.billing_interval.or(current.billing_interval);
+        let box_preset_id = data
+            .box_preset_id
+            .or(current.box_preset_id)
+            .filter(|id| !id.is_empty());
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
@@ -302,10 +427,14 @@ impl Product {
                 weight_grams = ?,
                 length_cm = ?,
                 width_cm = ?,
-                height_cm = ?
+                height_cm = ?,
+                category = ?,
+                artist_id = ?,
+                billing_interval = ?,
+                box_preset_id = ?
             WHERE id = ?
             "#,
-            libsql::params![name, description, price_cents, image_path, stock_quantity, is_active, stripe_price_id, now, weight_grams, length_cm, width_cm, height_cm, id.to_string()],
+            libsql::params![name, description, price_cents, image_path, stock_quantity, is_active, stripe_price_id, now, weight_grams, length_cm, width_cm, height_cm, category, artist_id, billing_interval, box_preset_id, id.to_string()],
         )
         .await
         .map_err(AppError::from)?;
@@ -356,6 +485,23 @@ impl Product {
             .ok_or_else(|| AppError::NotFound("Product not found".to_string()))
     }
 
+    /// Remember the recurring Stripe price subscriptions are billed at
+    pub async fn set_recurring_price(conn: &Connection, id: &str, price_id: &str) -> AppResult<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        conn.execute(
+            "UPDATE products SET stripe_recurring_price_id = ?, updated_ts = ? WHERE id = ?",
+            libsql::params![price_id.to_string(), now, id.to_string()],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Ok(())
+    }
+
     pub async fn delete(conn: &Connection, id: &str) -> AppResult<()> {
         conn.execute("DELETE FROM products WHERE id = ?", [id.to_string()])
             .await
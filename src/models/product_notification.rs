@@ -1,3 +1,5 @@
+use std::collections::HashSet;
+
 use libsql::Connection;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -13,6 +15,8 @@ pub struct ProductNotification {
     pub notified: bool,
     pub created_ts: i64,
     pub notified_ts: Option<i64>,
+    /// Language for the restock email; None sends English
+    pub locale: Option<String>,
 }
 
 impl ProductNotification {
@@ -22,6 +26,7 @@ impl ProductNotification {
         email: &str,
         product_id: &str,
         style_id: Option<&str>,
+        locale: Option<&str>,
     ) -> AppResult<Self> {
         let email = email.to_lowercase();
 
@@ -37,8 +42,8 @@ impl ProductNotification {
             .as_secs() as i64;
 
         conn.execute(
-            "INSERT INTO product_notifications (id, email, product_id, style_id, notified, created_ts) VALUES (?, ?, ?, ?, 0, ?)",
-            libsql::params![id.clone(), email.clone(), product_id, style_id, now],
+            "INSERT INTO product_notifications (id, email, product_id, style_id, notified, created_ts, locale) VALUES (?, ?, ?, ?, 0, ?, ?)",
+            libsql::params![id.clone(), email.clone(), product_id, style_id, now, locale],
         )
         .await
         .map_err(AppError::from)?;
@@ -51,6 +56,7 @@ impl ProductNotification {
             notified: false,
             created_ts: now,
             notified_ts: None,
+            locale: locale.map(|l| l.to_string()),
         })
     }
 
@@ -63,12 +69,12 @@ impl ProductNotification {
     ) -> AppResult<Option<Self>> {
         let query = match style_id {
             Some(_) => {
-                "SELECT id, email, product_id, style_id, notified, created_ts, notified_ts
+                "SELECT id, email, product_id, style_id, notified, created_ts, notified_ts, locale
                  FROM product_notifications
                  WHERE email = ? AND product_id = ? AND style_id = ? AND notified = 0"
             }
             None => {
-                "SELECT id, email, product_id, style_id, notified, created_ts, notified_ts
+                "SELECT id, email, product_id, style_id, notified, created_ts, notified_ts, locale
                  FROM product_notifications
                  WHERE email = ? AND product_id = ? AND style_id IS NULL AND notified = 0"
             }
@@ -95,7 +101,7 @@ impl ProductNotification {
     pub async fn get_pending_for_product(conn: &Connection, product_id: &str) -> AppResult<Vec<Self>> {
         let mut rows = conn
             .query(
-                "SELECT id, email, product_id, style_id, notified, created_ts, notified_ts
+                "SELECT id, email, product_id, style_id, notified, created_ts, notified_ts, locale
                  FROM product_notifications
                  WHERE product_id = ? AND notified = 0
                  ORDER BY created_ts ASC",
@@ -124,7 +130,7 @@ impl ProductNotification {
 
         let placeholders: Vec<&str> = style_ids.iter().map(|_| "?").collect();
         let query = format!(
-            "SELECT id, email, product_id, style_id, notified, created_ts, notified_ts
+            "SELECT id, email, product_id, style_id, notified, created_ts, notified_ts, locale
              FROM product_notifications
              WHERE product_id = ? AND style_id IN ({}) AND notified = 0
              ORDER BY created_ts ASC",
@@ -163,22 +169,24 @@ impl ProductNotification {
         Ok(())
     }
 
-    /// Mark all pending notifications for a product as notified
-    pub async fn mark_all_notified_for_product(conn: &Connection, product_id: &str) -> AppResult<u64> {
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs() as i64;
-
-        let result = conn
-            .execute(
-                "UPDATE product_notifications SET notified = 1, notified_ts = ? WHERE product_id = ? AND notified = 0",
-                libsql::params![now, product_id],
+    /// Addresses (lowercase) alerted about a product since `since_ts`
+    pub async fn emails_notified_since(conn: &Connection, product_id: &str, since_ts: i64) -> AppResult<HashSet<String>> {
+        let mut rows = conn
+            .query(
+                "SELECT DISTINCT email FROM product_notifications
+                 WHERE product_id = ? AND notified = 1 AND notified_ts >= ?",
+                libsql::params![product_id, since_ts],
             )
             .await
             .map_err(AppError::from)?;
 
-        Ok(result)
+        let mut emails = HashSet::new();
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            let email: String = row.get(0).map_err(AppError::from)?;
+            emails.insert(email.to_lowercase());
+        }
+
+        Ok(emails)
     }
 
     /// Count pending notifications for a product
@@ -199,6 +207,35 @@ impl ProductNotification {
         }
     }
 
+    /// Every Notify Me signup made with an email, for a data export
+    pub async fn list_by_email(conn: &Connection, email: &str) -> AppResult<Vec<Self>> {
+        let mut rows = conn
+            .query(
+                "SELECT id, email, product_id, style_id, notified, created_ts, notified_ts, locale
+                 FROM product_notifications WHERE email = ? ORDER BY created_ts DESC",
+                [email.to_lowercase()],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        let mut notifications = Vec::new();
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            notifications.push(Self::from_row(&row)?);
+        }
+        Ok(notifications)
+    }
+
+    pub async fn delete_by_email(conn: &Connection, email: &str) -> AppResult<()> {
+        conn.execute(
+            "DELETE FROM product_notifications WHERE email = ?",
+            [email.to_lowercase()],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Ok(())
+    }
+
     /// Clean up old notified entries (optional maintenance)
     pub async fn cleanup_old_notified(conn: &Connection, older_than_days: i64) -> AppResult<u64> {
         let cutoff = std::time::SystemTime::now()
@@ -227,6 +264,7 @@ impl ProductNotification {
             notified: row.get::<i64>(4).map_err(AppError::from)? != 0,
             created_ts: row.get(5).map_err(AppError::from)?,
             notified_ts: row.get(6).map_err(AppError::from).ok(),
+            locale: row.get(7).map_err(AppError::from).ok(),
         })
     }
 }
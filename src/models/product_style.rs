@@ -13,6 +13,8 @@ pub struct ProductStyle {
     pub image_id: Option<String>,
     pub sort_order: i64,
     pub created_ts: i64,
+    // Box to ship in when the order fits, overriding the product's
+    pub box_preset_id: Option<String>,
     // Populated when fetching with image info
     #[serde(skip_serializing_if = "Option::is_none")]
     pub image_path: Option<String>,
@@ -25,6 +27,7 @@ impl ProductStyle {
         name: &str,
         stock_quantity: i64,
         image_id: Option<&str>,
+        box_preset_id: Option<&str>,
     ) -> AppResult<Self> {
         let id = Uuid::new_v4().to_string();
         let now = std::time::SystemTime::now()
@@ -48,9 +51,9 @@ impl ProductStyle {
         };
 
         conn.execute(
-            "INSERT INTO product_styles (id, product_id, name, stock_quantity, image_id, sort_order, created_ts)
-             VALUES (?, ?, ?, ?, ?, ?, ?)",
-            libsql::params![id.clone(), product_id, name, stock_quantity, image_id, sort_order, now],
+            "INSERT INTO product_styles (id, product_id, name, stock_quantity, image_id, sort_order, created_ts, box_preset_id)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+            libsql::params![id.clone(), product_id, name, stock_quantity, image_id, sort_order, now, box_preset_id],
         )
         .await
         .map_err(AppError::from)?;
@@ -63,6 +66,7 @@ impl ProductStyle {
             image_id: image_id.map(|s| s.to_string()),
             sort_order,
             created_ts: now,
+            box_preset_id: box_preset_id.map(|s| s.to_string()),
             image_path: None,
         })
     }
@@ -70,7 +74,7 @@ impl ProductStyle {
     pub async fn get_by_product(conn: &Connection, product_id: &str) -> AppResult<Vec<Self>> {
         let mut rows = conn
             .query(
-                "SELECT ps.id, ps.product_id, ps.name, ps.stock_quantity, ps.image_id, ps.sort_order, ps.created_ts, pi.image_path
+                "SELECT ps.id, ps.product_id, ps.name, ps.stock_quantity, ps.image_id, ps.sort_order, ps.created_ts, pi.image_path, ps.box_preset_id
                  FROM product_styles ps
                  LEFT JOIN product_images pi ON ps.image_id = pi.id
                  WHERE ps.product_id = ?
@@ -91,7 +95,7 @@ impl ProductStyle {
     pub async fn get_by_id(conn: &Connection, id: &str) -> AppResult<Option<Self>> {
         let mut rows = conn
             .query(
-                "SELECT ps.id, ps.product_id, ps.name, ps.stock_quantity, ps.image_id, ps.sort_order, ps.created_ts, pi.image_path
+                "SELECT ps.id, ps.product_id, ps.name, ps.stock_quantity, ps.image_id, ps.sort_order, ps.created_ts, pi.image_path, ps.box_preset_id
                  FROM product_styles ps
                  LEFT JOIN product_images pi ON ps.image_id = pi.id
                  WHERE ps.id = ?",
@@ -113,10 +117,11 @@ impl ProductStyle {
         name: &str,
         stock_quantity: i64,
         image_id: Option<&str>,
+        box_preset_id: Option<&str>,
     ) -> AppResult<()> {
         conn.execute(
-            "UPDATE product_styles SET name = ?, stock_quantity = ?, image_id = ? WHERE id = ?",
-            libsql::params![name, stock_quantity, image_id, id],
+            "UPDATE product_styles SET name = ?, stock_quantity = ?, image_id = ?, box_preset_id = ? WHERE id = ?",
+            libsql::params![name, stock_quantity, image_id, box_preset_id, id],
         )
         .await
         .map_err(AppError::from)?;
@@ -135,6 +140,28 @@ impl ProductStyle {
         Ok(())
     }
 
+    pub async fn decrement_stock(conn: &Connection, id: &str, quantity: i32) -> AppResult<()> {
+        conn.execute(
+            "UPDATE product_styles SET stock_quantity = stock_quantity - ? WHERE id = ? AND stock_quantity >= ?",
+            libsql::params![quantity, id, quantity],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Ok(())
+    }
+
+    pub async fn increment_stock(conn: &Connection, id: &str, quantity: i32) -> AppResult<()> {
+        conn.execute(
+            "UPDATE product_styles SET stock_quantity = stock_quantity + ? WHERE id = ?",
+            libsql::params![quantity, id],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Ok(())
+    }
+
     pub async fn delete(conn: &Connection, id: &str) -> AppResult<()> {
         conn.execute("DELETE FROM product_styles WHERE id = ?", [id])
             .await
@@ -168,7 +195,7 @@ impl ProductStyle {
 
         let placeholders: Vec<&str> = style_ids.iter().map(|_| "?").collect();
         let query = format!(
-            "SELECT ps.id, ps.product_id, ps.name, ps.stock_quantity, ps.image_id, ps.sort_order, ps.created_ts, pi.image_path
+            "SELECT ps.id, ps.product_id, ps.name, ps.stock_quantity, ps.image_id, ps.sort_order, ps.created_ts, pi.image_path, ps.box_preset_id
              FROM product_styles ps
              LEFT JOIN product_images pi ON ps.image_id = pi.id
              WHERE ps.product_id = ? AND ps.id IN ({}) AND ps.stock_quantity > 0
@@ -201,6 +228,7 @@ impl ProductStyle {
             sort_order: row.get(5).map_err(AppError::from)?,
             created_ts: row.get(6).map_err(AppError::from)?,
             image_path: row.get(7).map_err(AppError::from).ok(),
+            box_preset_id: row.get(8).map_err(AppError::from).ok(),
         })
     }
 }
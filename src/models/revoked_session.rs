@@ -0,0 +1,46 @@
+use libsql::Connection;
+
+use crate::error::{AppError, AppResult};
+
+/// Revoked sessions are kept this long. Clerk session tokens live about a
+/// minute, so a week is far past the last token any of them could have issued.
+const RETENTION_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// Clerk sessions ended early (sign-out everywhere, revoked from the Clerk
+/// dashboard), whose still-unexpired JWTs must no longer be accepted
+pub struct RevokedSession;
+
+impl RevokedSession {
+    pub async fn revoke(conn: &Connection, session_id: &str, clerk_user_id: Option<&str>, reason: &str) -> AppResult<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        conn.execute(
+            "INSERT OR IGNORE INTO revoked_sessions (session_id, clerk_user_id, reason, revoked_ts) VALUES (?, ?, ?, ?)",
+            libsql::params![session_id, clerk_user_id.map(|u| u.to_string()), reason, now],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        // Old entries can't match a live token any more
+        conn.execute(
+            "DELETE FROM revoked_sessions WHERE revoked_ts < ?",
+            [now - RETENTION_SECS],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Ok(())
+    }
+
+    pub async fn is_revoked(conn: &Connection, session_id: &str) -> AppResult<bool> {
+        let mut rows = conn
+            .query("SELECT 1 FROM revoked_sessions WHERE session_id = ?", [session_id])
+            .await
+            .map_err(AppError::from)?;
+
+        Ok(rows.next().await.map_err(AppError::from)?.is_some())
+    }
+}
@@ -2,6 +2,7 @@ use libsql::Connection;
 use serde::{Deserialize, Serialize};
 
 use crate::error::{AppError, AppResult};
+use crate::models::{ShippingAddress, WeightTier};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Setting {
@@ -69,6 +70,199 @@ impl Setting {
     pub async fn get_unit_system(conn: &Connection) -> AppResult<String> {
         Ok(Self::get(conn, "shipping_unit_system").await?.unwrap_or_else(|| "metric".to_string()))
     }
+
+    pub async fn get_gift_wrap_fee_cents(conn: &Connection) -> AppResult<i32> {
+        Ok(Self::get(conn, "gift_wrap_fee_cents")
+            .await?
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(500))
+    }
+
+    /// Merchandise subtotal at or above which shipping is free (None when disabled)
+    pub async fn get_free_shipping_threshold_cents(conn: &Connection) -> AppResult<Option<i32>> {
+        Ok(Self::get(conn, "free_shipping_threshold_cents")
+            .await?
+            .and_then(|v| v.parse().ok())
+            .filter(|cents: &i32| *cents > 0))
+    }
+
+    /// Smallest merchandise subtotal accepted at checkout (None when disabled)
+    pub async fn get_minimum_order_cents(conn: &Connection) -> AppResult<Option<i32>> {
+        Ok(Self::get(conn, "minimum_order_cents")
+            .await?
+            .and_then(|v| v.parse().ok())
+            .filter(|cents: &i32| *cents > 0))
+    }
+
+    pub async fn get_order_retention_years(conn: &Connection) -> AppResult<i64> {
+        Ok(Self::get(conn, "order_retention_years")
+            .await?
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(7))
+    }
+
+    /// Carriers and service levels customers may be offered
+    pub async fn get_rate_filter(conn: &Connection) -> AppResult<RateFilter> {
+        let list = |value: Option<String>| -> Vec<String> {
+            value
+                .unwrap_or_default()
+                .split(',')
+                .map(|v| v.trim().to_lowercase())
+                .filter(|v| !v.is_empty())
+                .collect()
+        };
+
+        Ok(RateFilter {
+            enabled_carriers: list(Self::get(conn, "shipping_enabled_carriers").await?),
+            disabled_services: list(Self::get(conn, "shipping_disabled_services").await?),
+        })
+    }
+
+    pub async fn get_cart_ttl_hours(conn: &Connection) -> AppResult<i64> {
+        Ok(Self::get(conn, "cart_ttl_hours")
+            .await?
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(24))
+    }
+
+    /// Hours after a restock alert during which the same address isn't alerted about
+    /// the same product again (0 disables)
+    pub async fn get_restock_alert_window_hours(conn: &Connection) -> AppResult<i64> {
+        Ok(Self::get(conn, "restock_alert_window_hours")
+            .await?
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(24))
+    }
+
+    /// The shop's own delivery zone and fee
+    pub async fn get_local_delivery(conn: &Connection) -> AppResult<LocalDelivery> {
+        let zip_prefixes = Self::get(conn, "local_delivery_zips")
+            .await?
+            .unwrap_or_default()
+            .split(',')
+            .map(|z| z.trim().to_uppercase())
+            .filter(|z| !z.is_empty())
+            .collect();
+
+        Ok(LocalDelivery {
+            enabled: Self::get(conn, "local_delivery_enabled").await?.as_deref() == Some("true"),
+            zip_prefixes,
+            fee_cents: Self::get(conn, "local_delivery_fee_cents")
+                .await?
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0),
+            estimated_days: Self::get(conn, "local_delivery_days")
+                .await?
+                .and_then(|v| v.parse().ok()),
+        })
+    }
+
+    /// Signature required by default on labels for higher-value orders
+    pub async fn get_signature_defaults(conn: &Connection) -> AppResult<SignatureDefaults> {
+        Ok(SignatureDefaults {
+            threshold_cents: Self::get(conn, "signature_threshold_cents")
+                .await?
+                .and_then(|v| v.parse().ok())
+                .filter(|cents: &i32| *cents > 0),
+            signature: Self::get(conn, "signature_default")
+                .await?
+                .filter(|s| !s.is_empty())
+                .unwrap_or_else(|| "standard".to_string()),
+        })
+    }
+
+    /// Rates offered at checkout when Shippo can't be reached
+    pub async fn get_fallback_rates(conn: &Connection) -> AppResult<FallbackRates> {
+        Ok(FallbackRates {
+            weight_tiers: Self::get(conn, "fallback_rate_tiers")
+                .await?
+                .and_then(|v| serde_json::from_str(&v).ok())
+                .unwrap_or_default(),
+            estimated_days: Self::get(conn, "fallback_rate_days")
+                .await?
+                .and_then(|v| v.parse().ok()),
+        })
+    }
+
+    /// Days between an order being placed and handed to the carrier
+    pub async fn get_handling_days(conn: &Connection) -> AppResult<i64> {
+        Ok(Self::get(conn, "handling_days")
+            .await?
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(2))
+    }
+
+    /// Whether admins are emailed when an order is paid, and whether a pick list is included
+    pub async fn get_order_alerts(conn: &Connection) -> AppResult<OrderAlerts> {
+        Ok(OrderAlerts {
+            enabled: Self::get(conn, "order_alerts_enabled").await?.as_deref() == Some("true"),
+            include_pick_list: Self::get(conn, "order_alerts_pick_list").await?.as_deref() == Some("true"),
+        })
+    }
+
+    /// Archive address blind-copied on every customer order email, if set
+    pub async fn get_order_email_bcc(conn: &Connection) -> AppResult<Option<String>> {
+        Ok(Self::get(conn, "order_email_bcc")
+            .await?
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty()))
+    }
+
+    /// Whether the weekly new-arrivals digest is sent, and on which day
+    pub async fn get_weekly_digest(conn: &Connection) -> AppResult<WeeklyDigest> {
+        Ok(WeeklyDigest {
+            enabled: Self::get(conn, "weekly_digest_enabled").await?.as_deref() == Some("true"),
+            send_day: Self::get(conn, "weekly_digest_day")
+                .await?
+                .filter(|d| DIGEST_DAYS.contains(&d.as_str()))
+                .unwrap_or_else(|| "monday".to_string()),
+        })
+    }
+
+    /// Win-back emails to customers who haven't ordered in a while; off unless enabled
+    pub async fn get_win_back(conn: &Connection) -> AppResult<WinBack> {
+        Ok(WinBack {
+            enabled: Self::get(conn, "win_back_enabled").await?.as_deref() == Some("true"),
+            inactive_months: Self::get(conn, "win_back_inactive_months")
+                .await?
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(6),
+            discount_percent: Self::get(conn, "win_back_discount_percent")
+                .await?
+                .and_then(|v| v.parse().ok()),
+            discount_valid_days: Self::get(conn, "win_back_discount_valid_days")
+                .await?
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(30),
+        })
+    }
+
+    /// Colors, logo, footer and social links for customer and newsletter emails.
+    /// Unset values fall back to the shop's default look.
+    pub async fn get_email_branding(conn: &Connection) -> AppResult<EmailBranding> {
+        let defaults = EmailBranding::default();
+        let color = |value: Option<String>, default: String| value.filter(|c| is_hex_color(c)).unwrap_or(default);
+
+        Ok(EmailBranding {
+            accent_color: color(Self::get(conn, "email_accent_color").await?, defaults.accent_color),
+            text_color: color(Self::get(conn, "email_text_color").await?, defaults.text_color),
+            background_color: color(Self::get(conn, "email_background_color").await?, defaults.background_color),
+            logo_url: Self::get(conn, "email_logo_url").await?.filter(|v| !v.is_empty()),
+            footer_text: Self::get(conn, "email_footer_text").await?.filter(|v| !v.is_empty()),
+            social_links: Self::get(conn, "email_social_links")
+                .await?
+                .and_then(|v| serde_json::from_str(&v).ok())
+                .unwrap_or_default(),
+        })
+    }
+
+    /// File type labels are bought in (see LABEL_FILE_TYPES); PDF unless set
+    pub async fn get_label_file_type(conn: &Connection) -> AppResult<String> {
+        Ok(Self::get(conn, "label_file_type")
+            .await?
+            .filter(|v| !v.is_empty())
+            .unwrap_or_else(|| "PDF".to_string()))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -88,3 +282,181 @@ pub struct ShopAddress {
     pub country: String,
     pub phone: Option<String>,
 }
+
+/// New-order emails to the shop's admins
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OrderAlerts {
+    pub enabled: bool,
+    /// List each item, style and quantity so the order can be pulled from the shelf
+    pub include_pick_list: bool,
+}
+
+/// Days the weekly digest can go out on
+pub const DIGEST_DAYS: &[&str] = &["monday", "tuesday", "wednesday", "thursday", "friday", "saturday", "sunday"];
+
+/// Weekly email to newsletter subscribers listing the past week's new products
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeeklyDigest {
+    pub enabled: bool,
+    /// Lowercase weekday (UTC) the digest is sent on
+    pub send_day: String,
+}
+
+/// Email to customers whose last paid order is older than `inactive_months`,
+/// showing pieces added since they last ordered
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WinBack {
+    pub enabled: bool,
+    pub inactive_months: i64,
+    /// Percent off a single-use code generated per customer; None sends no code
+    pub discount_percent: Option<i32>,
+    /// Days the generated code stays redeemable
+    pub discount_valid_days: i64,
+}
+
+/// A link in the email footer, e.g. to the shop's Instagram
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SocialLink {
+    pub label: String,
+    pub url: String,
+}
+
+/// The look shared by every customer and newsletter email
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailBranding {
+    /// Headings, buttons and links, as #RRGGBB
+    pub accent_color: String,
+    /// Text on accent-colored buttons and product names, as #RRGGBB
+    pub text_color: String,
+    /// Page behind the email card, as #RRGGBB
+    pub background_color: String,
+    /// Shown above the content when set
+    pub logo_url: Option<String>,
+    /// Replaces the translated footer tagline when set
+    pub footer_text: Option<String>,
+    #[serde(default)]
+    pub social_links: Vec<SocialLink>,
+}
+
+impl Default for EmailBranding {
+    fn default() -> Self {
+        Self {
+            accent_color: "#97BAD9".to_string(),
+            text_color: "#18191B".to_string(),
+            background_color: "#F8F8F8".to_string(),
+            logo_url: None,
+            footer_text: None,
+            social_links: Vec::new(),
+        }
+    }
+}
+
+/// Whether a value is a #RRGGBB color, safe to put in an email's CSS
+pub fn is_hex_color(value: &str) -> bool {
+    value.len() == 7 && value.starts_with('#') && value[1..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Delivery by the shop itself to nearby zip codes, offered next to carrier rates
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LocalDelivery {
+    pub enabled: bool,
+    /// Zip codes or prefixes served (e.g. "97201", "972")
+    pub zip_prefixes: Vec<String>,
+    pub fee_cents: i32,
+    pub estimated_days: Option<i32>,
+}
+
+impl LocalDelivery {
+    /// Whether the destination is inside the delivery zone
+    pub fn covers(&self, destination: &ShippingAddress) -> bool {
+        let zip = destination.zip.trim().to_uppercase();
+        self.enabled
+            && !zip.is_empty()
+            && self.zip_prefixes.iter().any(|p| zip.starts_with(p.as_str()))
+    }
+}
+
+/// Orders totalling at least `threshold_cents` get `signature` on their label
+/// unless the admin picks otherwise (no threshold disables the default)
+/// Flat rates by parcel weight, charged when live rates are unavailable so checkout
+/// can still go ahead. No tiers disables the fallback.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FallbackRates {
+    pub weight_tiers: Vec<WeightTier>,
+    pub estimated_days: Option<i32>,
+}
+
+impl FallbackRates {
+    /// Price of the smallest tier the parcel fits in
+    pub fn price_for(&self, weight_grams: i32) -> Option<i32> {
+        self.weight_tiers
+            .iter()
+            .filter(|t| weight_grams <= t.max_grams)
+            .min_by_key(|t| t.max_grams)
+            .map(|t| t.cents)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignatureDefaults {
+    pub threshold_cents: Option<i32>,
+    /// "standard" or "adult"
+    pub signature: String,
+}
+
+impl SignatureDefaults {
+    pub fn for_total(&self, total_cents: i32) -> Option<&str> {
+        match self.threshold_cents {
+            Some(threshold) if total_cents >= threshold => Some(self.signature.as_str()),
+            _ => None,
+        }
+    }
+}
+
+/// Which Shippo rates are shown to customers. Values are Shippo tokens, lowercase.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RateFilter {
+    /// Carriers to offer (e.g. "usps"); empty offers every carrier
+    pub enabled_carriers: Vec<String>,
+    /// Service levels to hide (e.g. "usps_priority_express")
+    pub disabled_services: Vec<String>,
+}
+
+impl RateFilter {
+    pub fn allows(&self, carrier: &str, service: &str) -> bool {
+        (self.enabled_carriers.is_empty() || self.enabled_carriers.iter().any(|c| c == carrier))
+            && !self.disabled_services.iter().any(|s| s == service)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fallback(tiers: &[(i32, i32)]) -> FallbackRates {
+        FallbackRates {
+            weight_tiers: tiers.iter().map(|&(max_grams, cents)| WeightTier { max_grams, cents }).collect(),
+            estimated_days: None,
+        }
+    }
+
+    #[test]
+    fn fallback_charges_the_smallest_tier_the_parcel_fits() {
+        let rates = fallback(&[(5000, 2500), (1000, 900), (2500, 1500)]);
+        assert_eq!(rates.price_for(0), Some(900));
+        assert_eq!(rates.price_for(1000), Some(900));
+        assert_eq!(rates.price_for(1001), Some(1500));
+        assert_eq!(rates.price_for(2500), Some(1500));
+        assert_eq!(rates.price_for(5000), Some(2500));
+    }
+
+    #[test]
+    fn parcels_heavier_than_every_fallback_tier_get_no_rate() {
+        assert_eq!(fallback(&[(1000, 900), (5000, 2500)]).price_for(5001), None);
+    }
+
+    #[test]
+    fn no_tiers_disables_the_fallback() {
+        assert_eq!(fallback(&[]).price_for(1), None);
+    }
+}
@@ -0,0 +1,367 @@
+use libsql::Connection;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::{AppError, AppResult};
+use crate::models::ShippingAddress;
+
+/// Rate types a shipping rule can charge
+pub const RATE_TYPES: &[&str] = &["flat", "weight"];
+
+/// A shop-defined shipping rate for a zone (countries, states, zip prefixes).
+/// Matching rules are offered instead of live Shippo rates.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShippingRule {
+    pub id: String,
+    pub carrier: String,
+    pub service: String,
+    /// ISO country codes; empty matches every country
+    pub countries: Vec<String>,
+    /// State/province codes; empty matches every state
+    pub states: Vec<String>,
+    /// Zip/postal code prefixes; empty matches every zip
+    pub zip_prefixes: Vec<String>,
+    /// "flat" or "weight"
+    pub rate_type: String,
+    pub flat_cents: Option<i32>,
+    pub weight_tiers: Vec<WeightTier>,
+    pub estimated_days: Option<i32>,
+    /// Listing order; also breaks ties between equally priced rates
+    pub sort_order: i32,
+    pub is_active: bool,
+    pub created_ts: i64,
+    pub updated_ts: i64,
+}
+
+/// Price for parcels up to `max_grams`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeightTier {
+    pub max_grams: i32,
+    pub cents: i32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SaveShippingRule {
+    pub carrier: String,
+    pub service: String,
+    #[serde(default)]
+    pub countries: Vec<String>,
+    #[serde(default)]
+    pub states: Vec<String>,
+    #[serde(default)]
+    pub zip_prefixes: Vec<String>,
+    pub rate_type: String,
+    pub flat_cents: Option<i32>,
+    #[serde(default)]
+    pub weight_tiers: Vec<WeightTier>,
+    pub estimated_days: Option<i32>,
+    #[serde(default)]
+    pub sort_order: i32,
+    pub is_active: Option<bool>,
+}
+
+/// Zone values are compared uppercase without surrounding whitespace
+fn normalize_list(values: Vec<String>) -> Vec<String> {
+    values
+        .into_iter()
+        .map(|v| v.trim().to_uppercase())
+        .filter(|v| !v.is_empty())
+        .collect()
+}
+
+impl ShippingRule {
+    fn from_row(row: &libsql::Row) -> Result<Self, libsql::Error> {
+        let list = |idx: i32| -> Vec<String> {
+            row.get::<String>(idx)
+                .ok()
+                .and_then(|s| serde_json::from_str(&s).ok())
+                .unwrap_or_default()
+        };
+
+        Ok(Self {
+            id: row.get(0)?,
+            carrier: row.get(1)?,
+            service: row.get(2)?,
+            countries: list(3),
+            states: list(4),
+            zip_prefixes: list(5),
+            rate_type: row.get(6)?,
+            flat_cents: row.get(7).ok(),
+            weight_tiers: row
+                .get::<String>(8)
+                .ok()
+                .and_then(|s| serde_json::from_str(&s).ok())
+                .unwrap_or_default(),
+            estimated_days: row.get(9).ok(),
+            sort_order: row.get(10).unwrap_or(0),
+            is_active: row.get::<i32>(11).map(|v| v != 0).unwrap_or(false),
+            created_ts: row.get(12)?,
+            updated_ts: row.get(13)?,
+        })
+    }
+
+    /// Whether the destination falls inside this rule's zone
+    pub fn matches(&self, destination: &ShippingAddress) -> bool {
+        let country = destination.country.trim().to_uppercase();
+        let state = destination.state.trim().to_uppercase();
+        let zip = destination.zip.trim().to_uppercase();
+
+        (self.countries.is_empty() || self.countries.contains(&country))
+            && (self.states.is_empty() || self.states.contains(&state))
+            && (self.zip_prefixes.is_empty() || self.zip_prefixes.iter().any(|p| zip.starts_with(p.as_str())))
+    }
+
+    /// Price for a parcel of this weight, or None when it's heavier than every tier
+    pub fn price_for(&self, weight_grams: i32) -> Option<i32> {
+        match self.rate_type.as_str() {
+            "flat" => self.flat_cents,
+            "weight" => self
+                .weight_tiers
+                .iter()
+                .filter(|t| weight_grams <= t.max_grams)
+                .min_by_key(|t| t.max_grams)
+                .map(|t| t.cents),
+            _ => None,
+        }
+    }
+
+    pub async fn find_by_id(conn: &Connection, id: &str) -> AppResult<Option<Self>> {
+        let mut rows = conn
+            .query("SELECT * FROM shipping_rules WHERE id = ?", [id])
+            .await
+            .map_err(AppError::from)?;
+
+        match rows.next().await.map_err(AppError::from)? {
+            Some(row) => Ok(Some(Self::from_row(&row).map_err(AppError::from)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn list_all(conn: &Connection) -> AppResult<Vec<Self>> {
+        let mut rows = conn
+            .query("SELECT * FROM shipping_rules ORDER BY sort_order ASC, created_ts ASC", ())
+            .await
+            .map_err(AppError::from)?;
+
+        let mut rules = Vec::new();
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            rules.push(Self::from_row(&row).map_err(AppError::from)?);
+        }
+        Ok(rules)
+    }
+
+    pub async fn list_active(conn: &Connection) -> AppResult<Vec<Self>> {
+        let mut rows = conn
+            .query(
+                "SELECT * FROM shipping_rules WHERE is_active = 1 ORDER BY sort_order ASC, created_ts ASC",
+                (),
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        let mut rules = Vec::new();
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            rules.push(Self::from_row(&row).map_err(AppError::from)?);
+        }
+        Ok(rules)
+    }
+
+    pub async fn create(conn: &Connection, data: SaveShippingRule) -> AppResult<Self> {
+        let id = Uuid::new_v4().to_string();
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        let to_json = |v: &Vec<String>| serde_json::to_string(v).map_err(|e| AppError::Internal(e.to_string()));
+        let countries = to_json(&normalize_list(data.countries))?;
+        let states = to_json(&normalize_list(data.states))?;
+        let zip_prefixes = to_json(&normalize_list(data.zip_prefixes))?;
+        let weight_tiers = serde_json::to_string(&data.weight_tiers)
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        conn.execute(
+            "INSERT INTO shipping_rules (id, carrier, service, countries, states, zip_prefixes, rate_type, flat_cents, weight_tiers, estimated_days, sort_order, is_active, created_ts, updated_ts) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            libsql::params![
+                id.clone(),
+                data.carrier.trim().to_string(),
+                data.service.trim().to_string(),
+                countries,
+                states,
+                zip_prefixes,
+                data.rate_type,
+                data.flat_cents,
+                weight_tiers,
+                data.estimated_days,
+                data.sort_order,
+                data.is_active.unwrap_or(true) as i32,
+                now,
+                now
+            ],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Self::find_by_id(conn, &id)
+            .await?
+            .ok_or_else(|| AppError::Internal("Failed to create shipping rule".to_string()))
+    }
+
+    /// Replace a rule's zone and rate
+    pub async fn update(conn: &Connection, id: &str, data: SaveShippingRule) -> AppResult<Self> {
+        let current = Self::find_by_id(conn, id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Shipping rule not found".to_string()))?;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        let to_json = |v: &Vec<String>| serde_json::to_string(v).map_err(|e| AppError::Internal(e.to_string()));
+        let countries = to_json(&normalize_list(data.countries))?;
+        let states = to_json(&normalize_list(data.states))?;
+        let zip_prefixes = to_json(&normalize_list(data.zip_prefixes))?;
+        let weight_tiers = serde_json::to_string(&data.weight_tiers)
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        conn.execute(
+            "UPDATE shipping_rules SET carrier = ?, service = ?, countries = ?, states = ?, zip_prefixes = ?, rate_type = ?, flat_cents = ?, weight_tiers = ?, estimated_days = ?, sort_order = ?, is_active = ?, updated_ts = ? WHERE id = ?",
+            libsql::params![
+                data.carrier.trim().to_string(),
+                data.service.trim().to_string(),
+                countries,
+                states,
+                zip_prefixes,
+                data.rate_type,
+                data.flat_cents,
+                weight_tiers,
+                data.estimated_days,
+                data.sort_order,
+                data.is_active.unwrap_or(current.is_active) as i32,
+                now,
+                id.to_string()
+            ],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Self::find_by_id(conn, id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Shipping rule not found".to_string()))
+    }
+
+    pub async fn delete(conn: &Connection, id: &str) -> AppResult<bool> {
+        let result = conn
+            .execute("DELETE FROM shipping_rules WHERE id = ?", [id])
+            .await
+            .map_err(AppError::from)?;
+
+        Ok(result > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(countries: &[&str], states: &[&str], zip_prefixes: &[&str]) -> ShippingRule {
+        let list = |values: &[&str]| values.iter().map(|v| v.to_string()).collect();
+        ShippingRule {
+            id: "rule".to_string(),
+            carrier: "USPS".to_string(),
+            service: "Ground".to_string(),
+            countries: list(countries),
+            states: list(states),
+            zip_prefixes: list(zip_prefixes),
+            rate_type: "flat".to_string(),
+            flat_cents: Some(800),
+            weight_tiers: Vec::new(),
+            estimated_days: None,
+            sort_order: 0,
+            is_active: true,
+            created_ts: 0,
+            updated_ts: 0,
+        }
+    }
+
+    fn tiered(tiers: &[(i32, i32)]) -> ShippingRule {
+        ShippingRule {
+            rate_type: "weight".to_string(),
+            flat_cents: None,
+            weight_tiers: tiers.iter().map(|&(max_grams, cents)| WeightTier { max_grams, cents }).collect(),
+            ..rule(&[], &[], &[])
+        }
+    }
+
+    fn address(country: &str, state: &str, zip: &str) -> ShippingAddress {
+        ShippingAddress {
+            name: "Ada".to_string(),
+            street: "1 Main St".to_string(),
+            city: "Portland".to_string(),
+            state: state.to_string(),
+            zip: zip.to_string(),
+            country: country.to_string(),
+        }
+    }
+
+    #[test]
+    fn empty_zone_matches_everywhere() {
+        assert!(rule(&[], &[], &[]).matches(&address("US", "OR", "97201")));
+        assert!(rule(&[], &[], &[]).matches(&address("DE", "", "10115")));
+    }
+
+    #[test]
+    fn every_zone_list_has_to_match() {
+        let oregon = rule(&["US"], &["OR"], &["972"]);
+        assert!(oregon.matches(&address("US", "OR", "97201")));
+        assert!(!oregon.matches(&address("CA", "OR", "97201")));
+        assert!(!oregon.matches(&address("US", "WA", "97201")));
+        assert!(!oregon.matches(&address("US", "OR", "97001")));
+    }
+
+    #[test]
+    fn destination_is_compared_trimmed_and_uppercase() {
+        let london = rule(&["GB"], &[], &["SW1"]);
+        assert!(london.matches(&address(" gb ", "", " sw1a 1aa")));
+    }
+
+    #[test]
+    fn zip_prefixes_only_match_the_start() {
+        let portland = rule(&[], &[], &["972"]);
+        assert!(portland.matches(&address("US", "OR", "97205")));
+        assert!(!portland.matches(&address("US", "OR", "19720")));
+        assert!(!portland.matches(&address("US", "OR", "97")));
+    }
+
+    #[test]
+    fn flat_rules_ignore_weight() {
+        assert_eq!(rule(&[], &[], &[]).price_for(0), Some(800));
+        assert_eq!(rule(&[], &[], &[]).price_for(50_000), Some(800));
+    }
+
+    #[test]
+    fn weight_rules_charge_the_smallest_tier_that_fits() {
+        // Tiers are stored as entered, not sorted
+        let rule = tiered(&[(2000, 1200), (500, 600), (1000, 900)]);
+        assert_eq!(rule.price_for(1), Some(600));
+        assert_eq!(rule.price_for(500), Some(600));
+        assert_eq!(rule.price_for(501), Some(900));
+        assert_eq!(rule.price_for(1000), Some(900));
+        assert_eq!(rule.price_for(2000), Some(1200));
+    }
+
+    #[test]
+    fn parcels_heavier_than_every_tier_get_no_price() {
+        assert_eq!(tiered(&[(500, 600), (1000, 900)]).price_for(1001), None);
+        assert_eq!(tiered(&[]).price_for(1), None);
+    }
+
+    #[test]
+    fn unknown_rate_types_get_no_price() {
+        let rule = ShippingRule {
+            rate_type: "pallet".to_string(),
+            ..rule(&[], &[], &[])
+        };
+        assert_eq!(rule.price_for(100), None);
+    }
+}
@@ -0,0 +1,152 @@
+use libsql::Connection;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::error::{AppError, AppResult};
+
+/// Every step-up token starts with this
+pub const STEP_UP_TOKEN_PREFIX: &str = "ccsu_";
+
+/// How long an elevated session lasts
+pub const STEP_UP_TTL_SECS: i64 = 5 * 60;
+
+fn now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
+
+/// A staff member's authenticator app secret
+#[derive(Debug, Clone)]
+pub struct AdminTotp {
+    pub user_id: String,
+    pub secret: String,
+    /// None until the first code has been confirmed
+    pub enabled_ts: Option<i64>,
+    /// Last time step accepted; codes at or before it are rejected
+    pub last_step: i64,
+}
+
+impl AdminTotp {
+    fn from_row(row: &libsql::Row) -> Result<Self, libsql::Error> {
+        Ok(Self {
+            user_id: row.get(0)?,
+            secret: row.get(1)?,
+            enabled_ts: row.get(2).ok(),
+            last_step: row.get(3)?,
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled_ts.is_some()
+    }
+
+    pub async fn find_by_user(conn: &Connection, user_id: &str) -> AppResult<Option<Self>> {
+        let mut rows = conn
+            .query("SELECT * FROM admin_totp WHERE user_id = ?", [user_id])
+            .await
+            .map_err(AppError::from)?;
+
+        match rows.next().await.map_err(AppError::from)? {
+            Some(row) => Ok(Some(Self::from_row(&row).map_err(AppError::from)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Store a new, not yet enabled secret, replacing any unconfirmed one
+    pub async fn start_setup(conn: &Connection, user_id: &str, secret: &str) -> AppResult<Self> {
+        conn.execute(
+            "INSERT INTO admin_totp (user_id, secret, enabled_ts, last_step, created_ts) VALUES (?, ?, NULL, 0, ?)
+             ON CONFLICT(user_id) DO UPDATE SET secret = excluded.secret, enabled_ts = NULL, last_step = 0, created_ts = excluded.created_ts",
+            libsql::params![user_id, secret, now()],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Self::find_by_user(conn, user_id)
+            .await?
+            .ok_or_else(|| AppError::Internal("Failed to save authenticator secret".to_string()))
+    }
+
+    /// Record an accepted code's time step, enabling the secret if this was
+    /// the first code. Returns false if the step was already used, so the
+    /// same code can't be replayed.
+    pub async fn accept_step(conn: &Connection, user_id: &str, step: i64) -> AppResult<bool> {
+        let updated = conn
+            .execute(
+                "UPDATE admin_totp SET last_step = ?, enabled_ts = COALESCE(enabled_ts, ?) WHERE user_id = ? AND last_step < ?",
+                libsql::params![step, now(), user_id, step],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        Ok(updated > 0)
+    }
+
+    pub async fn delete(conn: &Connection, user_id: &str) -> AppResult<()> {
+        conn.execute("DELETE FROM admin_totp WHERE user_id = ?", [user_id])
+            .await
+            .map_err(AppError::from)?;
+
+        Ok(())
+    }
+}
+
+/// Elevated-session tokens that unlock refunds, product deletion and settings
+/// changes for a few minutes. Only a hash is stored.
+pub struct StepUpToken;
+
+impl StepUpToken {
+    fn hash(token: &str) -> String {
+        hex::encode(Sha256::digest(token.as_bytes()))
+    }
+
+    /// Issue a token for a user. Returns the token and when it expires.
+    pub async fn issue(conn: &Connection, user_id: &str) -> AppResult<(String, i64)> {
+        let token = format!(
+            "{}{}{}",
+            STEP_UP_TOKEN_PREFIX,
+            Uuid::new_v4().simple(),
+            Uuid::new_v4().simple()
+        );
+        let now = now();
+        let expires_ts = now + STEP_UP_TTL_SECS;
+
+        // Expired tokens are useless; clear them out while we're here
+        conn.execute("DELETE FROM admin_step_up_tokens WHERE expires_ts < ?", [now])
+            .await
+            .map_err(AppError::from)?;
+
+        conn.execute(
+            "INSERT INTO admin_step_up_tokens (token_hash, user_id, expires_ts, created_ts) VALUES (?, ?, ?, ?)",
+            libsql::params![Self::hash(&token), user_id, expires_ts, now],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Ok((token, expires_ts))
+    }
+
+    /// Whether a presented token is unexpired and belongs to the user
+    pub async fn is_valid(conn: &Connection, token: &str, user_id: &str) -> AppResult<bool> {
+        let mut rows = conn
+            .query(
+                "SELECT 1 FROM admin_step_up_tokens WHERE token_hash = ? AND user_id = ? AND expires_ts >= ?",
+                libsql::params![Self::hash(token), user_id, now()],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        Ok(rows.next().await.map_err(AppError::from)?.is_some())
+    }
+
+    /// End every elevated session a user has, e.g. after their authenticator is removed
+    pub async fn revoke_all(conn: &Connection, user_id: &str) -> AppResult<()> {
+        conn.execute("DELETE FROM admin_step_up_tokens WHERE user_id = ?", [user_id])
+            .await
+            .map_err(AppError::from)?;
+
+        Ok(())
+    }
+}
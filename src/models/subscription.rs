@@ -0,0 +1,215 @@
+use libsql::Connection;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::{AppError, AppResult};
+use crate::models::ShippingAddress;
+
+/// A customer's recurring order of a subscription product, mirrored from Stripe
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Subscription {
+    pub id: String,
+    pub user_id: String,
+    pub product_id: String,
+    pub style_id: Option<String>,
+    pub shipping_address: String,
+    pub stripe_subscription_id: Option<String>,
+    /// Stripe subscription status: incomplete, active, past_due, canceled, ...
+    pub status: String,
+    pub current_period_end: Option<i64>,
+    pub cancel_at_period_end: bool,
+    pub created_ts: i64,
+    pub updated_ts: i64,
+}
+
+pub struct CreateSubscription {
+    pub user_id: String,
+    pub product_id: String,
+    pub style_id: Option<String>,
+    pub shipping_address: ShippingAddress,
+}
+
+impl Subscription {
+    fn from_row(row: &libsql::Row) -> Result<Self, libsql::Error> {
+        Ok(Self {
+            id: row.get(0)?,
+            user_id: row.get(1)?,
+            product_id: row.get(2)?,
+            style_id: row.get(3).ok(),
+            shipping_address: row.get(4)?,
+            stripe_subscription_id: row.get(5).ok(),
+            status: row.get(6)?,
+            current_period_end: row.get(7).ok(),
+            cancel_at_period_end: row.get::<i32>(8).map(|v| v != 0).unwrap_or(false),
+            created_ts: row.get(9)?,
+            updated_ts: row.get(10)?,
+        })
+    }
+
+    pub fn get_shipping_address(&self) -> Option<ShippingAddress> {
+        serde_json::from_str(&self.shipping_address).ok()
+    }
+
+    /// Whether Stripe is still billing this subscription
+    pub fn is_live(&self) -> bool {
+        matches!(self.status.as_str(), "active" | "trialing" | "past_due")
+    }
+
+    pub async fn create(conn: &Connection, data: CreateSubscription) -> AppResult<Self> {
+        let id = Uuid::new_v4().to_string();
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        let shipping_json = serde_json::to_string(&data.shipping_address)
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        conn.execute(
+            "INSERT INTO subscriptions (id, user_id, product_id, style_id, shipping_address, created_ts, updated_ts) VALUES (?, ?, ?, ?, ?, ?, ?)",
+            libsql::params![id.clone(), data.user_id, data.product_id, data.style_id, shipping_json, now, now],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Self::find_by_id(conn, &id)
+            .await?
+            .ok_or_else(|| AppError::Internal("Failed to create subscription".to_string()))
+    }
+
+    pub async fn find_by_id(conn: &Connection, id: &str) -> AppResult<Option<Self>> {
+        let mut rows = conn
+            .query("SELECT * FROM subscriptions WHERE id = ?", [id])
+            .await
+            .map_err(AppError::from)?;
+
+        match rows.next().await.map_err(AppError::from)? {
+            Some(row) => Ok(Some(Self::from_row(&row).map_err(AppError::from)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Find a subscription only if it belongs to the given user
+    pub async fn find_for_user(conn: &Connection, id: &str, user_id: &str) -> AppResult<Option<Self>> {
+        let mut rows = conn
+            .query(
+                "SELECT * FROM subscriptions WHERE id = ? AND user_id = ?",
+                [id, user_id],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        match rows.next().await.map_err(AppError::from)? {
+            Some(row) => Ok(Some(Self::from_row(&row).map_err(AppError::from)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn find_by_stripe_id(conn: &Connection, stripe_subscription_id: &str) -> AppResult<Option<Self>> {
+        let mut rows = conn
+            .query(
+                "SELECT * FROM subscriptions WHERE stripe_subscription_id = ?",
+                [stripe_subscription_id],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        match rows.next().await.map_err(AppError::from)? {
+            Some(row) => Ok(Some(Self::from_row(&row).map_err(AppError::from)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn list_by_user(conn: &Connection, user_id: &str) -> AppResult<Vec<Self>> {
+        let mut rows = conn
+            .query(
+                "SELECT * FROM subscriptions WHERE user_id = ? AND stripe_subscription_id IS NOT NULL ORDER BY created_ts DESC",
+                [user_id],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        let mut subscriptions = Vec::new();
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            subscriptions.push(Self::from_row(&row).map_err(AppError::from)?);
+        }
+        Ok(subscriptions)
+    }
+
+    /// Strip the shipping address from the user's subscriptions for an account
+    /// deletion, like `Order::anonymize_for_user`
+    pub async fn anonymize_for_user(conn: &Connection, user_id: &str) -> AppResult<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        conn.execute(
+            "UPDATE subscriptions SET
+                shipping_address = CASE WHEN json_valid(shipping_address)
+                     THEN json_set(shipping_address, '$.name', 'Deleted customer', '$.street', '', '$.city', '', '$.zip', '')
+                     ELSE '{}' END,
+                updated_ts = ?
+             WHERE user_id = ?",
+            libsql::params![now, user_id],
+        )
+        .await
+        .map_err(AppError::from)?;
+        Ok(())
+    }
+
+    pub async fn list_all(conn: &Connection) -> AppResult<Vec<Self>> {
+        let mut rows = conn
+            .query(
+                "SELECT * FROM subscriptions WHERE stripe_subscription_id IS NOT NULL ORDER BY created_ts DESC",
+                (),
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        let mut subscriptions = Vec::new();
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            subscriptions.push(Self::from_row(&row).map_err(AppError::from)?);
+        }
+        Ok(subscriptions)
+    }
+
+    /// Link the local subscription to the one Stripe created at checkout
+    pub async fn set_stripe_subscription(conn: &Connection, id: &str, stripe_subscription_id: &str) -> AppResult<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        conn.execute(
+            "UPDATE subscriptions SET stripe_subscription_id = ?, updated_ts = ? WHERE id = ?",
+            libsql::params![stripe_subscription_id.to_string(), now, id.to_string()],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Ok(())
+    }
+
+    /// Store the latest state Stripe reported for the subscription
+    pub async fn update_status(
+        conn: &Connection,
+        id: &str,
+        status: &str,
+        current_period_end: Option<i64>,
+        cancel_at_period_end: bool,
+    ) -> AppResult<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        conn.execute(
+            "UPDATE subscriptions SET status = ?, current_period_end = COALESCE(?, current_period_end), cancel_at_period_end = ?, updated_ts = ? WHERE id = ?",
+            libsql::params![status.to_string(), current_period_end, cancel_at_period_end as i32, now, id.to_string()],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Ok(())
+    }
+}
@@ -1,8 +1,11 @@
 use libsql::Connection;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use uuid::Uuid;
 
 use crate::error::{AppError, AppResult};
+use crate::models::order::IN_CLAUSE_CHUNK;
+use crate::services::i18n::DEFAULT_LOCALE;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct User {
@@ -13,8 +16,18 @@ pub struct User {
     pub is_admin: bool,
     pub created_ts: i64,
     pub updated_ts: i64,
+    pub stripe_customer_id: Option<String>,
+    /// Language for emails (see services::i18n::LOCALES); None sends English
+    pub locale: Option<String>,
+    /// Staff role (see ROLES); None for customers
+    pub role: Option<String>,
+    /// Set when the Clerk account was deleted; the user can no longer sign in
+    pub deactivated_ts: Option<i64>,
 }
 
+/// Staff roles, from full access to a single area of the admin
+pub const ROLES: &[&str] = &["owner", "fulfillment", "marketing"];
+
 impl User {
     pub fn uuid(&self) -> Option<Uuid> {
         Uuid::parse_str(&self.id).ok()
@@ -29,8 +42,25 @@ impl User {
             is_admin: row.get::<i32>(4)? != 0,
             created_ts: row.get(7)?,
             updated_ts: row.get(8)?,
+            stripe_customer_id: row.get(9).ok(),
+            locale: row.get(10).ok(),
+            // Role (column 11 after migration 061); admins flagged by hand are owners
+            role: row
+                .get::<String>(11)
+                .ok()
+                .or_else(|| (row.get::<i32>(4).unwrap_or(0) != 0).then(|| "owner".to_string())),
+            deactivated_ts: row.get(12).ok(),
         })
     }
+
+    pub fn is_active(&self) -> bool {
+        self.deactivated_ts.is_none()
+    }
+
+    /// Locale to render this user's emails in
+    pub fn email_locale(&self) -> &str {
+        self.locale.as_deref().unwrap_or(DEFAULT_LOCALE)
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -65,6 +95,86 @@ impl User {
         }
     }
 
+    /// Look up many users at once, keyed by user ID
+    pub async fn find_by_ids(conn: &Connection, ids: &[String]) -> AppResult<HashMap<String, Self>> {
+        let mut users = HashMap::new();
+
+        for chunk in ids.chunks(IN_CLAUSE_CHUNK) {
+            let placeholders: Vec<&str> = chunk.iter().map(|_| "?").collect();
+            let query = format!(
+                "SELECT * FROM users WHERE id IN ({})",
+                placeholders.join(", ")
+            );
+
+            let params: Vec<libsql::Value> = chunk.iter().map(|id| id.clone().into()).collect();
+            let mut rows = conn.query(&query, params).await.map_err(AppError::from)?;
+
+            while let Some(row) = rows.next().await.map_err(AppError::from)? {
+                let user = Self::from_row(&row).map_err(AppError::from)?;
+                users.insert(user.id.clone(), user);
+            }
+        }
+
+        Ok(users)
+    }
+
+    /// Users whose email or name contains `search` (all users without one),
+    /// newest first
+    pub async fn search(conn: &Connection, search: Option<&str>, limit: i64, offset: i64) -> AppResult<Vec<Self>> {
+        let pattern = search
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .map(|s| format!("%{}%", s.to_lowercase()));
+
+        let mut rows = conn
+            .query(
+                "SELECT * FROM users
+                 WHERE ?1 IS NULL OR lower(email) LIKE ?1 OR lower(name) LIKE ?1
+                 ORDER BY created_ts DESC LIMIT ?2 OFFSET ?3",
+                libsql::params![pattern, limit, offset],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        let mut users = Vec::new();
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            users.push(Self::from_row(&row).map_err(AppError::from)?);
+        }
+        Ok(users)
+    }
+
+    /// Number of owners who can still sign in
+    pub async fn count_owners(conn: &Connection) -> AppResult<i64> {
+        let mut rows = conn
+            .query(
+                "SELECT COUNT(*) FROM users WHERE role = 'owner' AND deactivated_ts IS NULL",
+                (),
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        if let Some(row) = rows.next().await.map_err(AppError::from)? {
+            let count: i64 = row.get(0).map_err(AppError::from)?;
+            Ok(count)
+        } else {
+            Ok(0)
+        }
+    }
+
+    /// Users with admin access, e.g. to receive shop alerts
+    pub async fn list_admins(conn: &Connection) -> AppResult<Vec<Self>> {
+        let mut rows = conn
+            .query("SELECT * FROM users WHERE is_admin = 1", ())
+            .await
+            .map_err(AppError::from)?;
+
+        let mut users = Vec::new();
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            users.push(Self::from_row(&row).map_err(AppError::from)?);
+        }
+        Ok(users)
+    }
+
     pub async fn create(conn: &Connection, data: CreateUser) -> AppResult<Self> {
         let id = Uuid::new_v4().to_string();
         let now = std::time::SystemTime::now()
@@ -84,6 +194,21 @@ impl User {
             .ok_or_else(|| AppError::Internal("Failed to create user".to_string()))
     }
 
+    pub async fn set_stripe_customer_id(conn: &Connection, id: &str, customer_id: &str) -> AppResult<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        conn.execute(
+            "UPDATE users SET stripe_customer_id = ?, updated_ts = ? WHERE id = ?",
+            libsql::params![customer_id.to_string(), now, id.to_string()],
+        )
+        .await
+        .map_err(AppError::from)?;
+        Ok(())
+    }
+
     pub async fn upsert(conn: &Connection, data: CreateUser) -> AppResult<Self> {
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
@@ -106,15 +231,46 @@ impl User {
         }
     }
 
-    pub async fn set_admin(conn: &Connection, id: &str, is_admin: bool) -> AppResult<Self> {
+    pub async fn set_locale(conn: &Connection, id: &str, locale: &str) -> AppResult<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        conn.execute(
+            "UPDATE users SET locale = ?, updated_ts = ? WHERE id = ?",
+            libsql::params![locale.to_string(), now, id.to_string()],
+        )
+        .await
+        .map_err(AppError::from)?;
+        Ok(())
+    }
+
+    pub async fn set_name(conn: &Connection, id: &str, name: Option<&str>) -> AppResult<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        conn.execute(
+            "UPDATE users SET name = ?, updated_ts = ? WHERE id = ?",
+            libsql::params![name.map(|n| n.to_string()), now, id.to_string()],
+        )
+        .await
+        .map_err(AppError::from)?;
+        Ok(())
+    }
+
+    /// Give the user a staff role, or take staff access away with None
+    pub async fn set_role(conn: &Connection, id: &str, role: Option<&str>) -> AppResult<Self> {
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
             .as_secs() as i64;
 
         conn.execute(
-            "UPDATE users SET is_admin = ?, updated_ts = ? WHERE id = ?",
-            libsql::params![is_admin as i32, now, id.to_string()],
+            "UPDATE users SET role = ?, is_admin = ?, updated_ts = ? WHERE id = ?",
+            libsql::params![role.map(|r| r.to_string()), role.is_some() as i32, now, id.to_string()],
         )
         .await
         .map_err(AppError::from)?;
@@ -123,4 +279,49 @@ impl User {
             .await?
             .ok_or_else(|| AppError::NotFound("User not found".to_string()))
     }
+
+    /// Erase the user's personal details for an account deletion. The row stays
+    /// (orders still point at it) but can't be signed into or matched to a
+    /// Clerk account again.
+    pub async fn anonymize(conn: &Connection, id: &str) -> AppResult<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        conn.execute(
+            "UPDATE users SET clerk_id = ?, email = ?, name = NULL, locale = NULL, role = NULL, is_admin = 0,
+                deactivated_ts = COALESCE(deactivated_ts, ?), updated_ts = ?
+             WHERE id = ?",
+            libsql::params![
+                format!("deleted:{}", id),
+                format!("deleted-{}@deleted.invalid", id),
+                now,
+                now,
+                id.to_string()
+            ],
+        )
+        .await
+        .map_err(AppError::from)?;
+        Ok(())
+    }
+
+    /// Deactivate the user for a deleted Clerk account: they can't sign in and
+    /// lose any staff role. Orders and addresses are kept. Returns None if no
+    /// local user has that Clerk ID.
+    pub async fn deactivate_by_clerk_id(conn: &Connection, clerk_id: &str) -> AppResult<Option<Self>> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        conn.execute(
+            "UPDATE users SET deactivated_ts = COALESCE(deactivated_ts, ?), role = NULL, is_admin = 0, updated_ts = ? WHERE clerk_id = ?",
+            libsql::params![now, now, clerk_id.to_string()],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Self::find_by_clerk_id(conn, clerk_id).await
+    }
 }
@@ -0,0 +1,38 @@
+use libsql::Connection;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, AppResult};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookEvent {
+    pub provider: String,
+    pub event_id: String,
+    pub event_type: String,
+    pub received_ts: i64,
+}
+
+impl WebhookEvent {
+    /// Record an incoming event. Returns false if it was already recorded,
+    /// meaning this delivery is a duplicate and should not be processed again.
+    pub async fn claim(
+        conn: &Connection,
+        provider: &str,
+        event_id: &str,
+        event_type: &str,
+    ) -> AppResult<bool> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        let inserted = conn
+            .execute(
+                "INSERT OR IGNORE INTO webhook_events (provider, event_id, event_type, received_ts) VALUES (?, ?, ?, ?)",
+                libsql::params![provider.to_string(), event_id.to_string(), event_type.to_string(), now],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        Ok(inserted > 0)
+    }
+}
@@ -0,0 +1,216 @@
+use libsql::Connection;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::{AppError, AppResult};
+
+/// Attempts before a job is moved to the dead-letter state
+const MAX_ATTEMPTS: i32 = 8;
+
+/// First retry delay; doubles with each failed attempt
+const BASE_BACKOFF_SECS: i64 = 30;
+
+/// Longest delay between retries (6 hours)
+const MAX_BACKOFF_SECS: i64 = 6 * 60 * 60;
+
+/// A job left in "processing" this long belonged to a worker that died
+const STALE_PROCESSING_SECS: i64 = 10 * 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookJob {
+    pub id: String,
+    pub kind: String,
+    pub payload: String,
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt_ts: i64,
+    pub last_error: Option<String>,
+    pub created_ts: i64,
+    pub updated_ts: i64,
+}
+
+impl WebhookJob {
+    pub const KIND_STRIPE_EVENT: &'static str = "stripe_event";
+    pub const KIND_SHIPPO_EVENT: &'static str = "shippo_event";
+    pub const KIND_EMAIL: &'static str = "email";
+    pub const KIND_NEWSLETTER_CAMPAIGN: &'static str = "newsletter_campaign";
+    pub const KIND_NEWSLETTER_ANNOUNCEMENT: &'static str = "newsletter_announcement";
+
+    fn from_row(row: &libsql::Row) -> Result<Self, libsql::Error> {
+        Ok(Self {
+            id: row.get(0)?,
+            kind: row.get(1)?,
+            payload: row.get(2)?,
+            status: row.get(3)?,
+            attempts: row.get(4)?,
+            next_attempt_ts: row.get(5)?,
+            last_error: row.get(6)?,
+            created_ts: row.get(7)?,
+            updated_ts: row.get(8)?,
+        })
+    }
+
+    fn now() -> i64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64
+    }
+
+    /// Queue a job to run as soon as a worker picks it up
+    pub async fn enqueue(conn: &Connection, kind: &str, payload: &str) -> AppResult<String> {
+        Self::enqueue_at(conn, kind, payload, Self::now()).await
+    }
+
+    /// Queue a job that no worker picks up before `run_at_ts`
+    pub async fn enqueue_at(conn: &Connection, kind: &str, payload: &str, run_at_ts: i64) -> AppResult<String> {
+        let id = Uuid::new_v4().to_string();
+        let now = Self::now();
+
+        conn.execute(
+            "INSERT INTO webhook_jobs (id, kind, payload, status, attempts, next_attempt_ts, created_ts, updated_ts) VALUES (?, ?, ?, 'pending', 0, ?, ?, ?)",
+            libsql::params![id.clone(), kind.to_string(), payload.to_string(), run_at_ts.max(now), now, now],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Ok(id)
+    }
+
+    /// Jobs ready to run: pending and due, or abandoned mid-run by a dead worker
+    pub async fn list_due(conn: &Connection, limit: i64) -> AppResult<Vec<Self>> {
+        let now = Self::now();
+        let mut rows = conn
+            .query(
+                "SELECT * FROM webhook_jobs
+                 WHERE (status = 'pending' AND next_attempt_ts <= ?)
+                    OR (status = 'processing' AND updated_ts < ?)
+                 ORDER BY next_attempt_ts ASC
+                 LIMIT ?",
+                libsql::params![now, now - STALE_PROCESSING_SECS, limit],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        let mut jobs = Vec::new();
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            jobs.push(Self::from_row(&row).map_err(AppError::from)?);
+        }
+        Ok(jobs)
+    }
+
+    pub async fn list_by_status(conn: &Connection, status: &str, limit: i64) -> AppResult<Vec<Self>> {
+        let mut rows = conn
+            .query(
+                "SELECT * FROM webhook_jobs WHERE status = ? ORDER BY updated_ts DESC LIMIT ?",
+                libsql::params![status.to_string(), limit],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        let mut jobs = Vec::new();
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            jobs.push(Self::from_row(&row).map_err(AppError::from)?);
+        }
+        Ok(jobs)
+    }
+
+    /// Mark a job as running. Returns false if another worker got to it first.
+    pub async fn claim(conn: &Connection, id: &str) -> AppResult<bool> {
+        let now = Self::now();
+        let claimed = conn
+            .execute(
+                "UPDATE webhook_jobs SET status = 'processing', updated_ts = ?
+                 WHERE id = ? AND (status = 'pending' OR (status = 'processing' AND updated_ts < ?))",
+                libsql::params![now, id.to_string(), now - STALE_PROCESSING_SECS],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        Ok(claimed > 0)
+    }
+
+    pub async fn complete(conn: &Connection, id: &str) -> AppResult<()> {
+        conn.execute(
+            "UPDATE webhook_jobs SET status = 'done', last_error = NULL, updated_ts = ? WHERE id = ?",
+            libsql::params![Self::now(), id.to_string()],
+        )
+        .await
+        .map_err(AppError::from)?;
+        Ok(())
+    }
+
+    /// Record a failed attempt and schedule a retry with exponential backoff,
+    /// or move the job to the dead-letter state once it runs out of attempts.
+    /// Returns the job's new status.
+    pub async fn fail(conn: &Connection, job: &Self, error: &str) -> AppResult<&'static str> {
+        let now = Self::now();
+        let attempts = job.attempts + 1;
+
+        let (status, next_attempt_ts) = if attempts >= MAX_ATTEMPTS {
+            ("dead", job.next_attempt_ts)
+        } else {
+            let backoff = (BASE_BACKOFF_SECS << (attempts - 1).min(20)).min(MAX_BACKOFF_SECS);
+            ("pending", now + backoff)
+        };
+
+        conn.execute(
+            "UPDATE webhook_jobs SET status = ?, attempts = ?, next_attempt_ts = ?, last_error = ?, updated_ts = ? WHERE id = ?",
+            libsql::params![status.to_string(), attempts, next_attempt_ts, error.to_string(), now, job.id.clone()],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Ok(status)
+    }
+
+    /// Move a job straight to the dead-letter state after a failure retrying can't fix
+    pub async fn fail_permanently(conn: &Connection, job: &Self, error: &str) -> AppResult<()> {
+        conn.execute(
+            "UPDATE webhook_jobs SET status = 'dead', attempts = ?, last_error = ?, updated_ts = ? WHERE id = ?",
+            libsql::params![job.attempts + 1, error.to_string(), Self::now(), job.id.clone()],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Ok(())
+    }
+
+    /// Send a dead job back to the queue for another full set of attempts
+    pub async fn retry(conn: &Connection, id: &str) -> AppResult<bool> {
+        let now = Self::now();
+        let updated = conn
+            .execute(
+                "UPDATE webhook_jobs SET status = 'pending', attempts = 0, next_attempt_ts = ?, updated_ts = ? WHERE id = ? AND status = 'dead'",
+                libsql::params![now, now, id.to_string()],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        Ok(updated > 0)
+    }
+
+    /// Delete finished jobs older than the cutoff
+    pub async fn purge_done_before(conn: &Connection, cutoff_ts: i64) -> AppResult<u64> {
+        conn.execute(
+            "DELETE FROM webhook_jobs WHERE status = 'done' AND updated_ts < ?",
+            [cutoff_ts],
+        )
+        .await
+        .map_err(AppError::from)
+    }
+
+    /// Job counts per status, for the admin queue view
+    pub async fn count_by_status(conn: &Connection) -> AppResult<Vec<(String, i64)>> {
+        let mut rows = conn
+            .query("SELECT status, COUNT(*) FROM webhook_jobs GROUP BY status", ())
+            .await
+            .map_err(AppError::from)?;
+
+        let mut counts = Vec::new();
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            counts.push((row.get(0).map_err(AppError::from)?, row.get(1).map_err(AppError::from)?));
+        }
+        Ok(counts)
+    }
+}
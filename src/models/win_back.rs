@@ -0,0 +1,120 @@
+use libsql::Connection;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::{AppError, AppResult};
+
+/// A win-back email sent to a customer who hadn't ordered in a while
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WinBackEmail {
+    pub id: String,
+    pub user_id: String,
+    pub email: String,
+    /// The customer's most recent paid order when the email went out
+    pub last_order_ts: i64,
+    /// Single-use code generated for the customer, if discounts were on
+    pub discount_code: Option<String>,
+    pub product_count: i32,
+    pub sent_ts: i64,
+}
+
+/// A customer whose last paid order is older than the win-back cutoff and who
+/// hasn't been emailed about this lapse yet
+#[derive(Debug, Clone)]
+pub struct LapsedCustomer {
+    pub user_id: String,
+    /// Lowercase account email
+    pub email: String,
+    pub last_order_ts: i64,
+}
+
+impl WinBackEmail {
+    fn from_row(row: &libsql::Row) -> Result<Self, libsql::Error> {
+        Ok(Self {
+            id: row.get(0)?,
+            user_id: row.get(1)?,
+            email: row.get(2)?,
+            last_order_ts: row.get(3)?,
+            discount_code: row.get(4).ok(),
+            product_count: row.get(5).unwrap_or(0),
+            sent_ts: row.get(6)?,
+        })
+    }
+
+    /// Customers whose most recent paid order was placed before `cutoff_ts`,
+    /// skipping anyone already sent a win-back since that order
+    pub async fn lapsed_customers(conn: &Connection, cutoff_ts: i64) -> AppResult<Vec<LapsedCustomer>> {
+        let mut rows = conn
+            .query(
+                "SELECT c.user_id, c.email, c.last_order_ts FROM (
+                     SELECT u.id AS user_id, lower(u.email) AS email, MAX(o.created_ts) AS last_order_ts
+                     FROM orders o JOIN users u ON u.id = o.user_id
+                     WHERE o.status IN ('paid', 'processing', 'shipped', 'out_for_delivery', 'delivered')
+                     GROUP BY u.id
+                 ) c
+                 WHERE c.last_order_ts < ?
+                   AND NOT EXISTS (
+                       SELECT 1 FROM win_back_emails w
+                       WHERE w.user_id = c.user_id AND w.last_order_ts >= c.last_order_ts
+                   )
+                 ORDER BY c.last_order_ts DESC",
+                [cutoff_ts],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        let mut customers = Vec::new();
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            customers.push(LapsedCustomer {
+                user_id: row.get(0).map_err(AppError::from)?,
+                email: row.get(1).map_err(AppError::from)?,
+                last_order_ts: row.get(2).map_err(AppError::from)?,
+            });
+        }
+        Ok(customers)
+    }
+
+    /// Record a sent win-back so the customer isn't emailed again for the same lapse
+    pub async fn record(
+        conn: &Connection,
+        customer: &LapsedCustomer,
+        discount_code: Option<&str>,
+        product_count: i32,
+    ) -> AppResult<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        conn.execute(
+            "INSERT INTO win_back_emails (id, user_id, email, last_order_ts, discount_code, product_count, sent_ts) VALUES (?, ?, ?, ?, ?, ?, ?)",
+            libsql::params![
+                Uuid::new_v4().to_string(),
+                customer.user_id.clone(),
+                customer.email.clone(),
+                customer.last_order_ts,
+                discount_code.map(|c| c.to_string()),
+                product_count,
+                now
+            ],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Ok(())
+    }
+
+    /// Most recently sent win-back emails, newest first
+    pub async fn list_recent(conn: &Connection, limit: i64) -> AppResult<Vec<Self>> {
+        let mut rows = conn
+            .query("SELECT * FROM win_back_emails ORDER BY sent_ts DESC LIMIT ?", [limit])
+            .await
+            .map_err(AppError::from)?;
+
+        let mut emails = Vec::new();
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            emails.push(Self::from_row(&row).map_err(AppError::from)?);
+        }
+        Ok(emails)
+    }
+}
@@ -0,0 +1,94 @@
+use libsql::Connection;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, AppResult};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WishlistItem {
+    pub user_id: String,
+    pub product_id: String,
+    pub created_ts: i64,
+}
+
+impl WishlistItem {
+    fn from_row(row: &libsql::Row) -> Result<Self, libsql::Error> {
+        Ok(Self {
+            user_id: row.get(0)?,
+            product_id: row.get(1)?,
+            created_ts: row.get(2)?,
+        })
+    }
+
+    /// The user's saved products, newest first
+    pub async fn list_by_user(conn: &Connection, user_id: &str) -> AppResult<Vec<Self>> {
+        let mut rows = conn
+            .query(
+                "SELECT user_id, product_id, created_ts FROM wishlist_items WHERE user_id = ? ORDER BY created_ts DESC",
+                [user_id],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        let mut items = Vec::new();
+        while let Some(row) = rows.next().await.map_err(AppError::from)? {
+            items.push(Self::from_row(&row).map_err(AppError::from)?);
+        }
+        Ok(items)
+    }
+
+    /// Save a product; saving it again keeps the original date
+    pub async fn add(conn: &Connection, user_id: &str, product_id: &str) -> AppResult<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        conn.execute(
+            "INSERT OR IGNORE INTO wishlist_items (user_id, product_id, created_ts) VALUES (?, ?, ?)",
+            libsql::params![user_id, product_id, now],
+        )
+        .await
+        .map_err(AppError::from)?;
+
+        Ok(())
+    }
+
+    pub async fn remove(conn: &Connection, user_id: &str, product_id: &str) -> AppResult<bool> {
+        let result = conn
+            .execute(
+                "DELETE FROM wishlist_items WHERE user_id = ? AND product_id = ?",
+                [user_id, product_id],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        Ok(result > 0)
+    }
+
+    pub async fn clear(conn: &Connection, user_id: &str) -> AppResult<()> {
+        conn.execute("DELETE FROM wishlist_items WHERE user_id = ?", [user_id])
+            .await
+            .map_err(AppError::from)?;
+
+        Ok(())
+    }
+
+    /// Saved products that are still for sale
+    pub async fn count_by_user(conn: &Connection, user_id: &str) -> AppResult<i64> {
+        let mut rows = conn
+            .query(
+                "SELECT COUNT(*) FROM wishlist_items w JOIN products p ON p.id = w.product_id
+                 WHERE w.user_id = ? AND p.is_active = 1",
+                [user_id],
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        if let Some(row) = rows.next().await.map_err(AppError::from)? {
+            let count: i64 = row.get(0).map_err(AppError::from)?;
+            Ok(count)
+        } else {
+            Ok(0)
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use axum::{
+    extract::{Extension, Path, State},
+    routing::{delete, get, post, put},
+    Json, Router,
+};
+
+use crate::error::{AppError, AppResult};
+use crate::middleware::AuthUser;
+use crate::models::{Address, SaveAddress};
+use crate::routes::AppState;
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/addresses", get(list_addresses))
+        .route("/addresses", post(create_address))
+        .route("/addresses/{id}", put(update_address))
+        .route("/addresses/{id}", delete(delete_address))
+}
+
+fn validate_address(payload: &SaveAddress) -> AppResult<()> {
+    let required = [
+        ("name", &payload.name),
+        ("street", &payload.street),
+        ("city", &payload.city),
+        ("state", &payload.state),
+        ("zip", &payload.zip),
+        ("country", &payload.country),
+    ];
+
+    for (field, value) in required {
+        if value.trim().is_empty() {
+            return Err(AppError::BadRequest(format!("Address {} is required", field)));
+        }
+    }
+
+    Ok(())
+}
+
+async fn list_addresses(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+) -> AppResult<Json<Vec<Address>>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let addresses = Address::list_by_user(&conn, &user.id).await?;
+    Ok(Json(addresses))
+}
+
+async fn create_address(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Json(payload): Json<SaveAddress>,
+) -> AppResult<Json<Address>> {
+    validate_address(&payload)?;
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let address = Address::create(&conn, &user.id, payload).await?;
+    Ok(Json(address))
+}
+
+async fn update_address(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<String>,
+    Json(payload): Json<SaveAddress>,
+) -> AppResult<Json<Address>> {
+    validate_address(&payload)?;
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let address = Address::update(&conn, &id, &user.id, payload).await?;
+    Ok(Json(address))
+}
+
+async fn delete_address(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<String>,
+) -> AppResult<Json<serde_json::Value>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+
+    if !Address::delete(&conn, &id, &user.id).await? {
+        return Err(AppError::NotFound("Address not found".to_string()));
+    }
+
+    Ok(Json(serde_json::json!({"success": true})))
+}
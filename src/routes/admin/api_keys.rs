@@ -0,0 +1,91 @@
+use axum::{
+    extract::{Path, State},
+    routing::{delete, get, post},
+    Extension, Json, Router,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, AppResult};
+use crate::middleware::auth::Permission;
+use crate::middleware::AuthUser;
+use crate::models::ApiKey;
+use crate::routes::AppState;
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/keys", get(list_keys))
+        .route("/keys", post(create_key))
+        .route("/keys/{id}", delete(revoke_key))
+}
+
+#[derive(Deserialize)]
+pub struct CreateKeyRequest {
+    pub name: String,
+    /// Permission names, e.g. ["view_catalog", "edit_catalog"]
+    pub scopes: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct CreateKeyResponse {
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+    /// The full key. It is not stored and can't be shown again.
+    pub key: String,
+}
+
+async fn list_keys(State(state): State<AppState>) -> AppResult<Json<Vec<ApiKey>>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let keys = ApiKey::list_all(&conn).await?;
+    Ok(Json(keys))
+}
+
+async fn create_key(
+    State(state): State<AppState>,
+    user: Option<Extension<AuthUser>>,
+    Json(payload): Json<CreateKeyRequest>,
+) -> AppResult<Json<CreateKeyResponse>> {
+    let name = payload.name.trim();
+    if name.is_empty() {
+        return Err(AppError::BadRequest("Name is required".to_string()));
+    }
+    if payload.scopes.is_empty() {
+        return Err(AppError::BadRequest("At least one scope is required".to_string()));
+    }
+
+    let mut scopes: Vec<String> = Vec::new();
+    for scope in &payload.scopes {
+        let permission = Permission::parse(scope.trim()).ok_or_else(|| {
+            let names: Vec<&str> = Permission::ALL.iter().map(|p| p.as_str()).collect();
+            AppError::BadRequest(format!("Unknown scope '{}'. Use: {}", scope, names.join(", ")))
+        })?;
+        // A key can't be given more than its creator has
+        if let Some(Extension(ref user)) = user {
+            if !user.can(permission) {
+                return Err(AppError::Forbidden(format!("You don't have the {} permission", permission.as_str())));
+            }
+        }
+        if !scopes.iter().any(|s| s == permission.as_str()) {
+            scopes.push(permission.as_str().to_string());
+        }
+    }
+
+    let created_by = user.as_ref().map(|Extension(u)| u.id.as_str()).filter(|id| !id.is_empty());
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let (api_key, key) = ApiKey::create(&conn, name, &scopes, created_by).await?;
+    tracing::info!("Created API key {} ({}) with scopes {:?}", api_key.name, api_key.key_prefix, api_key.scopes);
+    Ok(Json(CreateKeyResponse { api_key, key }))
+}
+
+async fn revoke_key(State(state): State<AppState>, Path(id): Path<String>) -> AppResult<Json<ApiKey>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    if !ApiKey::revoke(&conn, &id).await? {
+        return Err(AppError::NotFound("API key not found or already revoked".to_string()));
+    }
+
+    let api_key = ApiKey::find_by_id(&conn, &id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("API key not found".to_string()))?;
+    tracing::info!("Revoked API key {} ({})", api_key.name, api_key.key_prefix);
+    Ok(Json(api_key))
+}
@@ -0,0 +1,127 @@
+use axum::{
+    extract::{Path, State},
+    routing::{get, post},
+    Json, Router,
+};
+use serde::Serialize;
+
+use crate::error::{AppError, AppResult};
+use crate::models::{Artist, CreateArtist};
+use crate::routes::AppState;
+
+#[derive(Serialize)]
+pub struct OnboardingLinkResponse {
+    pub artist_id: String,
+    pub url: String,
+}
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/artists", get(list_artists))
+        .route("/artists", post(create_artist))
+        .route("/artists/{id}/onboarding-link", post(create_onboarding_link))
+        .route("/artists/{id}/refresh", post(refresh_artist))
+}
+
+fn require_connect(state: &AppState) -> AppResult<()> {
+    if !state.config.stripe_connect_enabled {
+        return Err(AppError::BadRequest("Stripe Connect is not enabled".to_string()));
+    }
+    Ok(())
+}
+
+async fn list_artists(State(state): State<AppState>) -> AppResult<Json<Vec<Artist>>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let artists = Artist::list_all(&conn).await?;
+    Ok(Json(artists))
+}
+
+/// Add an artist and open their Stripe connected account
+async fn create_artist(
+    State(state): State<AppState>,
+    Json(payload): Json<CreateArtist>,
+) -> AppResult<Json<Artist>> {
+    require_connect(&state)?;
+
+    if payload.name.trim().is_empty() {
+        return Err(AppError::BadRequest("Name is required".to_string()));
+    }
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let artist = Artist::create(&conn, payload).await?;
+
+    let account_id = state
+        .stripe
+        .create_connected_account(&artist.id, artist.email.as_deref())
+        .await?;
+    Artist::set_stripe_account(&conn, &artist.id, &account_id).await?;
+    tracing::info!("Created artist {} with Stripe account {}", artist.id, account_id);
+
+    let artist = Artist::find_by_id(&conn, &artist.id)
+        .await?
+        .ok_or_else(|| AppError::Internal("Failed to create artist".to_string()))?;
+    Ok(Json(artist))
+}
+
+/// Stripe-hosted onboarding link to send to the artist. Links expire after a few
+/// minutes, so a fresh one is created each time.
+async fn create_onboarding_link(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> AppResult<Json<OnboardingLinkResponse>> {
+    require_connect(&state)?;
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let artist = Artist::find_by_id(&conn, &id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Artist not found".to_string()))?;
+
+    let account_id = match artist.stripe_account_id {
+        Some(account_id) => account_id,
+        None => {
+            let account_id = state
+                .stripe
+                .create_connected_account(&artist.id, artist.email.as_deref())
+                .await?;
+            Artist::set_stripe_account(&conn, &artist.id, &account_id).await?;
+            account_id
+        }
+    };
+
+    // The artist isn't an admin, so both land on the storefront; an expired link
+    // just needs a new one from here
+    let refresh_url = format!("{}/?onboarding=expired", state.config.base_url);
+    let return_url = format!("{}/?onboarding=complete", state.config.base_url);
+    let url = state
+        .stripe
+        .create_onboarding_link(&account_id, &refresh_url, &return_url)
+        .await?;
+
+    Ok(Json(OnboardingLinkResponse { artist_id: artist.id, url }))
+}
+
+/// Pull the connected account's onboarding status from Stripe
+async fn refresh_artist(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> AppResult<Json<Artist>> {
+    require_connect(&state)?;
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let artist = Artist::find_by_id(&conn, &id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Artist not found".to_string()))?;
+
+    let account_id = artist
+        .stripe_account_id
+        .as_deref()
+        .ok_or_else(|| AppError::BadRequest("Artist has no Stripe account yet".to_string()))?;
+
+    let (charges_enabled, payouts_enabled) = state.stripe.get_account_status(account_id).await?;
+    Artist::set_account_status(&conn, &artist.id, charges_enabled, payouts_enabled).await?;
+
+    let artist = Artist::find_by_id(&conn, &id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Artist not found".to_string()))?;
+    Ok(Json(artist))
+}
@@ -0,0 +1,48 @@
+use axum::{
+    extract::{Query, State},
+    routing::get,
+    Json, Router,
+};
+use serde::Deserialize;
+
+use crate::error::{AppError, AppResult};
+use crate::models::{AuditLogEntry, AuditLogFilter};
+use crate::routes::AppState;
+
+/// Most audit entries listed at once
+const MAX_LISTED_ENTRIES: i64 = 200;
+
+pub fn routes() -> Router<AppState> {
+    Router::new().route("/audit-log", get(list_audit_log))
+}
+
+#[derive(Deserialize)]
+pub struct ListAuditLogQuery {
+    pub user_id: Option<String>,
+    /// Product, order or other ID from the request path
+    pub target_id: Option<String>,
+    /// Part of the route pattern, e.g. "refund"
+    pub route: Option<String>,
+    pub since_ts: Option<i64>,
+    pub until_ts: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+/// Recent admin changes, e.g. who refunded an order or changed a price
+async fn list_audit_log(
+    State(state): State<AppState>,
+    Query(query): Query<ListAuditLogQuery>,
+) -> AppResult<Json<Vec<AuditLogEntry>>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let limit = query.limit.unwrap_or(MAX_LISTED_ENTRIES).clamp(1, MAX_LISTED_ENTRIES);
+    let filter = AuditLogFilter {
+        user_id: query.user_id,
+        target_id: query.target_id,
+        route: query.route,
+        since_ts: query.since_ts,
+        until_ts: query.until_ts,
+    };
+    let entries = AuditLogEntry::list(&conn, &filter, limit).await?;
+
+    Ok(Json(entries))
+}
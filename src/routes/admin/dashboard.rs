@@ -1,18 +1,33 @@
-use axum::{extract::State, routing::get, Json, Router};
-use serde::Serialize;
+use axum::{
+    extract::{Query, State},
+    routing::get,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
 
 use crate::error::{AppError, AppResult};
-use crate::models::{Order, Product};
+use crate::models::{AdminAuthEvent, Artist, AuditLogEntry, AuditLogFilter, Money, Order, Product};
 use crate::routes::AppState;
 
 #[derive(Serialize)]
 pub struct DashboardStats {
     pub total_orders: i64,
     pub total_revenue_cents: i64,
-    pub total_revenue: f64,
+    pub total_revenue: Money,
+    pub total_tips_cents: i64,
     pub total_products: i64,
     pub low_stock_products: Vec<LowStockProduct>,
     pub recent_orders: Vec<RecentOrder>,
+    pub shipping_discrepancies: Vec<ShippingDiscrepancy>,
+    /// Label cost minus shipping charged, summed over orders with a purchased label
+    pub shipping_discrepancy_total_cents: i64,
+    pub checkout_sessions: i64,
+    pub checkout_completed: i64,
+    pub checkout_expired: i64,
+    /// Share of Checkout sessions that were paid (0.0 - 1.0)
+    pub checkout_conversion_rate: f64,
+    /// Unpaid orders cancelled because the session expired or the cart went stale
+    pub abandoned_carts: i64,
 }
 
 #[derive(Serialize)]
@@ -26,12 +41,124 @@ pub struct LowStockProduct {
 pub struct RecentOrder {
     pub id: String,
     pub status: String,
-    pub total: f64,
+    pub total: Money,
     pub created_ts: i64,
 }
 
+#[derive(Serialize)]
+pub struct ShippingDiscrepancy {
+    pub order_id: String,
+    pub charged_cents: i32,
+    pub label_cents: i32,
+    /// Positive when the label cost more than the customer paid
+    pub difference_cents: i32,
+    pub checkout_carrier: Option<String>,
+    pub checkout_service: Option<String>,
+    pub label_carrier: Option<String>,
+    pub label_service: Option<String>,
+    pub created_ts: i64,
+}
+
+/// How many discrepancies to list on the dashboard
+const MAX_SHIPPING_DISCREPANCIES: usize = 20;
+
+#[derive(Serialize)]
+pub struct PaymentMethodRevenue {
+    pub key: String,
+    pub orders: i64,
+    pub revenue_cents: i64,
+    pub revenue: Money,
+}
+
+#[derive(Serialize)]
+pub struct PaymentMethodBreakdown {
+    /// card, klarna, apple_pay, ... (wallets split out from cards)
+    pub by_payment_method: Vec<PaymentMethodRevenue>,
+    pub by_card_brand: Vec<PaymentMethodRevenue>,
+}
+
+#[derive(Deserialize)]
+pub struct PayoutsQuery {
+    pub limit: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct PayoutReport {
+    pub id: String,
+    pub amount_cents: i64,
+    pub currency: String,
+    pub status: String,
+    pub arrival_date: i64,
+    pub created: i64,
+    pub transactions: Vec<PayoutLine>,
+    /// Charges and refunds in this payout that don't match any order
+    pub unmatched_count: usize,
+}
+
+#[derive(Serialize)]
+pub struct PayoutLine {
+    pub balance_transaction_id: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub amount_cents: i64,
+    pub fee_cents: i64,
+    pub net_cents: i64,
+    pub payment_intent_id: Option<String>,
+    pub order_id: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ArtistSales {
+    pub artist_id: String,
+    pub name: Option<String>,
+    pub orders: i64,
+    pub gross: Money,
+    /// Shop's application fees on these orders
+    pub fees: Money,
+    /// What Stripe transferred to the artist's account
+    pub net: Money,
+}
+
+#[derive(Deserialize)]
+pub struct ActivityQuery {
+    /// How far back to look, in days (default 7)
+    pub days: Option<i64>,
+}
+
+/// Who got into the admin, who failed to, and what changed
+#[derive(Serialize)]
+pub struct AdminActivity {
+    pub since_ts: i64,
+    pub sign_in_count: i64,
+    pub failed_attempt_count: i64,
+    pub lockout_count: i64,
+    /// Staff sessions, newest first
+    pub sign_ins: Vec<AdminAuthEvent>,
+    /// Failed attempts and lockouts, newest first
+    pub failed_attempts: Vec<AdminAuthEvent>,
+    /// Admin changes from the audit log, newest first, including refused ones
+    pub recent_changes: Vec<AuditLogEntry>,
+}
+
+/// Payouts shown by default, and the most that can be requested
+const DEFAULT_PAYOUT_LIMIT: i64 = 10;
+const MAX_PAYOUT_LIMIT: i64 = 50;
+
+/// Activity window shown by default, and the longest that can be requested
+/// (sign-ins and failures are only kept for 90 days)
+const DEFAULT_ACTIVITY_DAYS: i64 = 7;
+const MAX_ACTIVITY_DAYS: i64 = 90;
+
+/// Most events of each list on the activity overview
+const MAX_ACTIVITY_EVENTS: i64 = 50;
+
 pub fn routes() -> Router<AppState> {
-    Router::new().route("/dashboard", get(get_dashboard))
+    Router::new()
+        .route("/dashboard", get(get_dashboard))
+        .route("/dashboard/payment-methods", get(get_payment_methods))
+        .route("/dashboard/payouts", get(get_payouts))
+        .route("/dashboard/artists", get(get_artist_sales))
+        .route("/dashboard/activity", get(get_activity))
 }
 
 async fn get_dashboard(State(state): State<AppState>) -> AppResult<Json<DashboardStats>> {
@@ -39,6 +166,16 @@ async fn get_dashboard(State(state): State<AppState>) -> AppResult<Json<Dashboar
 
     let total_orders = Order::count_all(&conn).await?;
     let total_revenue_cents = Order::total_revenue(&conn).await?;
+    let total_tips_cents = Order::total_tips(&conn).await?;
+
+    let (checkout_sessions, checkout_completed, checkout_expired) = Order::checkout_stats(&conn).await?;
+    let checkout_conversion_rate = if checkout_sessions > 0 {
+        checkout_completed as f64 / checkout_sessions as f64
+    } else {
+        0.0
+    };
+
+    let abandoned_carts = Order::count_abandoned(&conn).await?;
 
     let products = Product::list_all(&conn).await?;
     let total_products = products.len() as i64;
@@ -54,13 +191,43 @@ async fn get_dashboard(State(state): State<AppState>) -> AppResult<Json<Dashboar
         .collect();
 
     let orders = Order::list_all(&conn).await?;
+
+    // Compare what each order was charged for shipping with what its label cost
+    let mut shipping_discrepancy_total_cents = 0i64;
+    let mut shipping_discrepancies: Vec<ShippingDiscrepancy> = orders
+        .iter()
+        .filter_map(|o| {
+            let label = o.get_label_rate()?;
+            let difference_cents = label.amount_cents - o.shipping_cents;
+            shipping_discrepancy_total_cents += difference_cents as i64;
+            if difference_cents == 0 {
+                return None;
+            }
+
+            let checkout = o.get_checkout_rate();
+            Some(ShippingDiscrepancy {
+                order_id: o.id.clone(),
+                charged_cents: o.shipping_cents,
+                label_cents: label.amount_cents,
+                difference_cents,
+                checkout_carrier: checkout.as_ref().and_then(|r| r.carrier.clone()),
+                checkout_service: checkout.as_ref().and_then(|r| r.service.clone()),
+                label_carrier: label.carrier,
+                label_service: label.service,
+                created_ts: o.created_ts,
+            })
+        })
+        .collect();
+    shipping_discrepancies.sort_by_key(|d| std::cmp::Reverse(d.difference_cents));
+    shipping_discrepancies.truncate(MAX_SHIPPING_DISCREPANCIES);
+
     let recent_orders: Vec<RecentOrder> = orders
         .into_iter()
         .take(10)
         .map(|o| RecentOrder {
             id: o.id.to_string()[..8].to_string(),
             status: o.status,
-            total: o.total_cents as f64 / 100.0,
+            total: Money::usd(o.total_cents),
             created_ts: o.created_ts,
         })
         .collect();
@@ -68,9 +235,158 @@ async fn get_dashboard(State(state): State<AppState>) -> AppResult<Json<Dashboar
     Ok(Json(DashboardStats {
         total_orders,
         total_revenue_cents,
-        total_revenue: total_revenue_cents as f64 / 100.0,
+        total_revenue: Money::usd(total_revenue_cents),
+        total_tips_cents,
         total_products,
         low_stock_products,
         recent_orders,
+        shipping_discrepancies,
+        shipping_discrepancy_total_cents,
+        checkout_sessions,
+        checkout_completed,
+        checkout_expired,
+        checkout_conversion_rate,
+        abandoned_carts,
+    }))
+}
+
+async fn get_payment_methods(State(state): State<AppState>) -> AppResult<Json<PaymentMethodBreakdown>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+
+    let to_revenue = |(key, orders, revenue_cents): (String, i64, i64)| PaymentMethodRevenue {
+        key,
+        orders,
+        revenue_cents,
+        revenue: Money::usd(revenue_cents),
+    };
+
+    let by_payment_method = Order::revenue_by_payment_method(&conn)
+        .await?
+        .into_iter()
+        .map(to_revenue)
+        .collect();
+    let by_card_brand = Order::revenue_by_card_brand(&conn)
+        .await?
+        .into_iter()
+        .map(to_revenue)
+        .collect();
+
+    Ok(Json(PaymentMethodBreakdown {
+        by_payment_method,
+        by_card_brand,
+    }))
+}
+
+/// Recent Stripe payouts broken down into the orders they paid out,
+/// so bank deposits can be tied back to shop sales
+async fn get_payouts(
+    State(state): State<AppState>,
+    Query(query): Query<PayoutsQuery>,
+) -> AppResult<Json<Vec<PayoutReport>>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let limit = query.limit.unwrap_or(DEFAULT_PAYOUT_LIMIT).clamp(1, MAX_PAYOUT_LIMIT);
+
+    let mut reports = Vec::new();
+    for payout in state.stripe.list_payouts(limit).await? {
+        let mut transactions = Vec::new();
+        let mut unmatched_count = 0;
+
+        for txn in state.stripe.list_payout_transactions(&payout.id).await? {
+            // The payout's own entry just balances the others out
+            if txn.kind == "payout" {
+                continue;
+            }
+
+            let order_id = match txn.payment_intent_id {
+                Some(ref pi_id) => Order::find_by_payment_intent(&conn, pi_id).await?.map(|o| o.id),
+                None => None,
+            };
+            if order_id.is_none() && matches!(txn.kind.as_str(), "charge" | "payment" | "refund") {
+                unmatched_count += 1;
+            }
+
+            transactions.push(PayoutLine {
+                balance_transaction_id: txn.id,
+                kind: txn.kind,
+                amount_cents: txn.amount,
+                fee_cents: txn.fee,
+                net_cents: txn.net,
+                payment_intent_id: txn.payment_intent_id,
+                order_id,
+            });
+        }
+
+        reports.push(PayoutReport {
+            id: payout.id,
+            amount_cents: payout.amount,
+            currency: payout.currency,
+            status: payout.status,
+            arrival_date: payout.arrival_date,
+            created: payout.created,
+            transactions,
+            unmatched_count,
+        });
+    }
+
+    Ok(Json(reports))
+}
+
+/// Marketplace sales per artist: what customers paid, the shop's cut, and the artist's share
+async fn get_artist_sales(State(state): State<AppState>) -> AppResult<Json<Vec<ArtistSales>>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let artists = Artist::list_all(&conn).await?;
+
+    let sales = Order::sales_by_artist(&conn)
+        .await?
+        .into_iter()
+        .map(|(artist_id, orders, gross_cents, fee_cents)| ArtistSales {
+            name: artists.iter().find(|a| a.id == artist_id).map(|a| a.name.clone()),
+            artist_id,
+            orders,
+            gross: Money::usd(gross_cents),
+            fees: Money::usd(fee_cents),
+            net: Money::usd(gross_cents - fee_cents),
+        })
+        .collect();
+
+    Ok(Json(sales))
+}
+
+/// Recent admin sign-ins, failed sign-ins and lockouts, and audit log entries,
+/// so an owner can spot anything unusual at a glance
+async fn get_activity(
+    State(state): State<AppState>,
+    Query(query): Query<ActivityQuery>,
+) -> AppResult<Json<AdminActivity>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let days = query.days.unwrap_or(DEFAULT_ACTIVITY_DAYS).clamp(1, MAX_ACTIVITY_DAYS);
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    let since_ts = now - days * 24 * 60 * 60;
+
+    let sign_ins = AdminAuthEvent::list_since(&conn, &[AdminAuthEvent::SIGN_IN], since_ts, MAX_ACTIVITY_EVENTS).await?;
+    let failed_attempts = AdminAuthEvent::list_since(
+        &conn,
+        &[AdminAuthEvent::FAILURE, AdminAuthEvent::LOCKOUT],
+        since_ts,
+        MAX_ACTIVITY_EVENTS,
+    )
+    .await?;
+    let filter = AuditLogFilter {
+        since_ts: Some(since_ts),
+        ..Default::default()
+    };
+    let recent_changes = AuditLogEntry::list(&conn, &filter, MAX_ACTIVITY_EVENTS).await?;
+
+    Ok(Json(AdminActivity {
+        since_ts,
+        sign_in_count: AdminAuthEvent::count_since(&conn, AdminAuthEvent::SIGN_IN, since_ts).await?,
+        failed_attempt_count: AdminAuthEvent::count_since(&conn, AdminAuthEvent::FAILURE, since_ts).await?,
+        lockout_count: AdminAuthEvent::count_since(&conn, AdminAuthEvent::LOCKOUT, since_ts).await?,
+        sign_ins,
+        failed_attempts,
+        recent_changes,
     }))
 }
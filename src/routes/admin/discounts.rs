@@ -0,0 +1,184 @@
+use axum::{
+    extract::{Path, State},
+    routing::{delete, get, post, put},
+    Json, Router,
+};
+use serde::Deserialize;
+
+use crate::error::{AppError, AppResult};
+use crate::models::{CreateDiscountCode, DiscountCode};
+use crate::routes::AppState;
+use crate::services::stripe::{StripeCouponSummary, StripePromotionCodeSummary};
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/discounts", get(list_discounts))
+        .route("/discounts", post(create_discount))
+        .route("/discounts/{id}", put(update_discount))
+        .route("/discounts/{id}", delete(delete_discount))
+        .route("/discounts/stripe/coupons", get(list_stripe_coupons))
+        .route("/discounts/stripe/coupons", post(create_stripe_coupon))
+        .route("/discounts/stripe/promotion-codes", get(list_stripe_promotion_codes))
+        .route("/discounts/stripe/promotion-codes", post(create_stripe_promotion_code))
+}
+
+#[derive(Deserialize)]
+pub struct UpdateDiscountRequest {
+    pub is_active: bool,
+}
+
+async fn list_discounts(State(state): State<AppState>) -> AppResult<Json<Vec<DiscountCode>>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let codes = DiscountCode::list_all(&conn).await?;
+    Ok(Json(codes))
+}
+
+async fn create_discount(
+    State(state): State<AppState>,
+    Json(payload): Json<CreateDiscountCode>,
+) -> AppResult<Json<DiscountCode>> {
+    if DiscountCode::normalize(&payload.code).is_empty() {
+        return Err(AppError::BadRequest("Code is required".to_string()));
+    }
+
+    match (payload.percent_off, payload.amount_off_cents) {
+        (Some(percent), None) if (1..=100).contains(&percent) => {}
+        (None, Some(amount)) if amount > 0 => {}
+        _ => {
+            return Err(AppError::BadRequest(
+                "Set either percent_off (1-100) or a positive amount_off_cents".to_string(),
+            ));
+        }
+    }
+
+    if payload.min_subtotal_cents < 0 {
+        return Err(AppError::BadRequest("Minimum subtotal cannot be negative".to_string()));
+    }
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+
+    if DiscountCode::find_by_code(&conn, &payload.code).await?.is_some() {
+        return Err(AppError::BadRequest("A discount with this code already exists".to_string()));
+    }
+
+    let code = DiscountCode::create(&conn, payload).await?;
+    tracing::info!("Created discount code {}", code.code);
+    Ok(Json(code))
+}
+
+async fn update_discount(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(payload): Json<UpdateDiscountRequest>,
+) -> AppResult<Json<DiscountCode>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let code = DiscountCode::set_active(&conn, &id, payload.is_active).await?;
+    Ok(Json(code))
+}
+
+async fn delete_discount(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> AppResult<Json<serde_json::Value>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+
+    if !DiscountCode::delete(&conn, &id).await? {
+        return Err(AppError::NotFound("Discount code not found".to_string()));
+    }
+
+    Ok(Json(serde_json::json!({"success": true})))
+}
+
+// ============ STRIPE COUPONS & PROMOTION CODES ============
+// Promotion codes are entered by the customer on the Stripe Checkout page
+
+#[derive(Deserialize)]
+pub struct CreateStripeCouponRequest {
+    pub name: String,
+    pub percent_off: Option<f64>,
+    pub amount_off_cents: Option<i64>,
+    pub max_redemptions: Option<i64>,
+    pub redeem_by: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct CreateStripePromotionCodeRequest {
+    pub coupon_id: String,
+    pub code: String,
+    pub max_redemptions: Option<i64>,
+    pub expires_at: Option<i64>,
+    pub minimum_amount_cents: Option<i64>,
+}
+
+async fn list_stripe_coupons(State(state): State<AppState>) -> AppResult<Json<Vec<StripeCouponSummary>>> {
+    let coupons = state.stripe.list_coupons().await?;
+    Ok(Json(coupons))
+}
+
+async fn create_stripe_coupon(
+    State(state): State<AppState>,
+    Json(payload): Json<CreateStripeCouponRequest>,
+) -> AppResult<Json<StripeCouponSummary>> {
+    if payload.name.trim().is_empty() {
+        return Err(AppError::BadRequest("Coupon name is required".to_string()));
+    }
+
+    match (payload.percent_off, payload.amount_off_cents) {
+        (Some(percent), None) if percent > 0.0 && percent <= 100.0 => {}
+        (None, Some(amount)) if amount > 0 => {}
+        _ => {
+            return Err(AppError::BadRequest(
+                "Set either percent_off (0-100) or a positive amount_off_cents".to_string(),
+            ));
+        }
+    }
+
+    let coupon = state
+        .stripe
+        .create_coupon(
+            payload.name.trim(),
+            payload.percent_off,
+            payload.amount_off_cents,
+            payload.max_redemptions,
+            payload.redeem_by,
+        )
+        .await?;
+    tracing::info!("Created Stripe coupon {}", coupon.id);
+    Ok(Json(coupon))
+}
+
+async fn list_stripe_promotion_codes(
+    State(state): State<AppState>,
+) -> AppResult<Json<Vec<StripePromotionCodeSummary>>> {
+    let codes = state.stripe.list_promotion_codes().await?;
+    Ok(Json(codes))
+}
+
+async fn create_stripe_promotion_code(
+    State(state): State<AppState>,
+    Json(payload): Json<CreateStripePromotionCodeRequest>,
+) -> AppResult<Json<StripePromotionCodeSummary>> {
+    let code = DiscountCode::normalize(&payload.code);
+    if code.is_empty() {
+        return Err(AppError::BadRequest("Code is required".to_string()));
+    }
+
+    // Keep Stripe and local codes from shadowing each other
+    let conn = state.db.connect().map_err(AppError::from)?;
+    if DiscountCode::find_by_code(&conn, &code).await?.is_some() {
+        return Err(AppError::BadRequest("A local discount with this code already exists".to_string()));
+    }
+
+    let promotion_code = state
+        .stripe
+        .create_promotion_code(
+            &payload.coupon_id,
+            &code,
+            payload.max_redemptions,
+            payload.expires_at,
+            payload.minimum_amount_cents,
+        )
+        .await?;
+    tracing::info!("Created Stripe promotion code {}", promotion_code.code);
+    Ok(Json(promotion_code))
+}
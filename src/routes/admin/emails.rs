@@ -0,0 +1,76 @@
+use axum::{
+    extract::{Path, Query, State},
+    routing::{delete, get},
+    Json, Router,
+};
+use serde::Deserialize;
+
+use crate::error::{AppError, AppResult};
+use crate::models::email_log::DELIVERY_STATUSES;
+use crate::models::{EmailLog, EmailLogFilter, EmailSuppression};
+use crate::routes::AppState;
+
+/// Most log entries listed at once
+const MAX_LISTED_EMAILS: i64 = 200;
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/emails", get(list_emails))
+        .route("/emails/suppressions", get(list_suppressions))
+        .route("/emails/suppressions/{email}", delete(delete_suppression))
+}
+
+#[derive(Deserialize)]
+pub struct ListEmailsQuery {
+    /// Part of the recipient address
+    pub recipient: Option<String>,
+    pub email_type: Option<String>,
+    /// failed, or a delivery status: sent, delayed, delivered, opened, clicked, bounced, complained
+    pub status: Option<String>,
+    pub since_ts: Option<i64>,
+    pub until_ts: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+/// Recent send attempts, e.g. to check whether a customer's shipping email went out
+async fn list_emails(
+    State(state): State<AppState>,
+    Query(query): Query<ListEmailsQuery>,
+) -> AppResult<Json<Vec<EmailLog>>> {
+    if let Some(status) = query.status.as_deref().filter(|s| !s.is_empty()) {
+        if status != "failed" && !DELIVERY_STATUSES.contains(&status) {
+            return Err(AppError::BadRequest(format!("Invalid email status: {}", status)));
+        }
+    }
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let limit = query.limit.unwrap_or(MAX_LISTED_EMAILS).clamp(1, MAX_LISTED_EMAILS);
+    let filter = EmailLogFilter {
+        recipient: query.recipient,
+        email_type: query.email_type,
+        status: query.status,
+        since_ts: query.since_ts,
+        until_ts: query.until_ts,
+    };
+    let emails = EmailLog::list(&conn, &filter, limit).await?;
+
+    Ok(Json(emails))
+}
+
+/// Addresses batch sends skip after a bounce or spam complaint
+async fn list_suppressions(State(state): State<AppState>) -> AppResult<Json<Vec<EmailSuppression>>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    Ok(Json(EmailSuppression::list_all(&conn).await?))
+}
+
+/// Let batch sends reach an address again, e.g. after a customer fixed their mailbox
+async fn delete_suppression(
+    State(state): State<AppState>,
+    Path(email): Path<String>,
+) -> AppResult<Json<serde_json::Value>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    if !EmailSuppression::delete(&conn, &email).await? {
+        return Err(AppError::NotFound("Suppression not found".to_string()));
+    }
+    Ok(Json(serde_json::json!({"success": true})))
+}
@@ -1,19 +1,36 @@
+pub mod api_keys;
+pub mod artists;
+pub mod audit_log;
 pub mod dashboard;
+pub mod discounts;
+pub mod emails;
 pub mod newsletter;
 pub mod orders;
+pub mod payments;
 pub mod products;
 pub mod settings;
+pub mod shipping;
+pub mod step_up;
+pub mod subscriptions;
+pub mod users;
+pub mod webhook_jobs;
 
 use axum::{
-    extract::Path,
+    extract::{DefaultBodyLimit, Path},
     http::{header, StatusCode},
     middleware,
     response::IntoResponse,
     routing::get,
     Router,
 };
+use tower_http::limit::RequestBodyLimitLayer;
 
-use crate::middleware::auth::{auth_middleware, require_admin};
+use crate::middleware::audit::audit_middleware;
+use crate::middleware::auth::{admin_auth_middleware, require_admin, require_permission, Access, Permission};
+use crate::middleware::csrf::csrf_middleware;
+use crate::middleware::ip_allowlist::admin_ip_allowlist_middleware;
+use crate::middleware::lockout::{admin_ip_lockout_middleware, admin_user_lockout_middleware};
+use crate::middleware::step_up::require_step_up;
 use crate::routes::AppState;
 
 async fn serve_admin_static(path: Option<Path<String>>) -> impl IntoResponse {
@@ -54,6 +71,9 @@ async fn serve_admin_static(path: Option<Path<String>>) -> impl IntoResponse {
     }
 }
 
+/// Largest admin JSON body (batch product edits, campaign HTML)
+const ADMIN_JSON_BODY_LIMIT: usize = 1024 * 1024;
+
 pub fn routes(state: AppState) -> Router<AppState> {
     // Skip auth only in local testing mode (not cloud)
     let skip_auth = state.config.testing_mode && !state.config.deploy_mode.is_cloud();
@@ -65,12 +85,53 @@ pub fn routes(state: AppState) -> Router<AppState> {
         skip_auth
     );
 
-    let api_routes = Router::new()
-        .merge(products::routes())
-        .merge(orders::routes())
-        .merge(dashboard::routes())
-        .merge(settings::routes())
-        .merge(newsletter::routes());
+    // Each route group is limited to the staff roles allowed to use it
+    let guard = |router: Router<AppState>, access: Access| {
+        if skip_auth {
+            router
+        } else {
+            router.route_layer(middleware::from_fn_with_state(access, require_permission))
+        }
+    };
+
+    let json_routes = Router::new()
+        .merge(guard(products::routes(), Access::new(Permission::ViewCatalog, Permission::EditCatalog)))
+        .merge(guard(orders::routes(), Access::only(Permission::Fulfillment)))
+        .merge(guard(orders::payment_routes(), Access::only(Permission::Refunds)))
+        .merge(guard(dashboard::routes(), Access::only(Permission::Finance)))
+        .merge(guard(artists::routes(), Access::only(Permission::Finance)))
+        .merge(guard(discounts::routes(), Access::only(Permission::Marketing)))
+        .merge(guard(emails::routes(), Access::only(Permission::Marketing)))
+        .merge(guard(payments::routes(), Access::only(Permission::Settings)))
+        .merge(guard(settings::routes(), Access::only(Permission::Settings)))
+        .merge(guard(shipping::routes(), Access::only(Permission::Fulfillment)))
+        .merge(guard(newsletter::routes(), Access::only(Permission::Marketing)))
+        .merge(guard(subscriptions::routes(), Access::only(Permission::Fulfillment)))
+        .merge(guard(webhook_jobs::routes(), Access::only(Permission::Settings)))
+        .merge(guard(api_keys::routes(), Access::only(Permission::Settings)))
+        .merge(guard(audit_log::routes(), Access::only(Permission::Settings)))
+        .merge(guard(users::routes(), Access::only(Permission::Settings)))
+        .merge(step_up::routes())
+        .layer(RequestBodyLimitLayer::new(ADMIN_JSON_BODY_LIMIT));
+
+    // Image uploads replace axum's 2MB default with the configured upload limit.
+    // Oversized uploads are refused from Content-Length, or as soon as the stream
+    // passes the limit, before they are buffered.
+    let upload_routes = Router::new()
+        .merge(guard(products::upload_routes(), Access::only(Permission::EditCatalog)))
+        .merge(guard(settings::upload_routes(), Access::only(Permission::Settings)))
+        .layer(DefaultBodyLimit::disable())
+        .layer(RequestBodyLimitLayer::new(state.config.max_upload_bytes));
+
+    let api_routes = json_routes.merge(upload_routes);
+
+    // Refunds, product deletion and settings changes need an elevated session
+    let api_routes = if skip_auth {
+        api_routes
+    } else {
+        api_routes.route_layer(middleware::from_fn_with_state(state.clone(), require_step_up))
+    };
+    let api_routes = api_routes.route_layer(middleware::from_fn_with_state(state.clone(), audit_middleware));
 
     // Serve static files through route handlers (not fallback_service)
     // so middleware applies properly
@@ -82,12 +143,19 @@ pub fn routes(state: AppState) -> Router<AppState> {
         .nest("/api", api_routes)
         .merge(static_routes);
 
-    if skip_auth {
+    let router = if skip_auth {
         base_router
     } else {
-        // Apply auth to entire admin router (API + static files)
+        // Apply auth to entire admin router (API + static files); CSRF check runs first.
+        // Repeated auth failures lock out the address, and the account once known.
         base_router
             .layer(middleware::from_fn(require_admin))
-            .layer(middleware::from_fn_with_state(state, auth_middleware))
-    }
+            .layer(middleware::from_fn_with_state(state.clone(), admin_user_lockout_middleware))
+            .layer(middleware::from_fn_with_state(state.clone(), admin_auth_middleware))
+            .layer(middleware::from_fn_with_state(state.clone(), csrf_middleware))
+            .layer(middleware::from_fn_with_state(state.clone(), admin_ip_lockout_middleware))
+    };
+
+    // Network allowlist is checked before anything else, even in testing mode
+    router.layer(middleware::from_fn_with_state(state, admin_ip_allowlist_middleware))
 }
@@ -1,145 +1,294 @@
 use axum::{
-    extract::{Path, State},
-    routing::{get, post},
+    extract::{Path, Query, State},
+    routing::{delete, get, post, put},
     Json, Router,
 };
+use libsql::Connection;
 use serde::{Deserialize, Serialize};
 
 use crate::error::{AppError, AppResult};
-use crate::models::{NewsletterSubscriber, Product, ProductImage};
+use crate::jobs::announcements::queue_announcement;
+use crate::jobs::audience::{run_audience_sync, AudienceSyncSummary};
+use crate::jobs::campaigns::{campaign_body_html, featured_products, schedule_campaign};
+use crate::models::newsletter::CATEGORIES;
+use crate::models::newsletter_announcement::NOTIFY_TYPES;
+use crate::models::newsletter_campaign::BODY_FORMATS;
+use crate::models::{
+    CampaignStats, NewsletterAnnouncement, NewsletterCampaign, NewsletterSegment, NewsletterSubscriber, Product,
+    SaveNewsletterCampaign, SaveNewsletterSegment,
+};
+use crate::services::email::html_to_text;
+use crate::services::i18n::{normalize_locale, DEFAULT_LOCALE};
 use crate::routes::AppState;
 
 pub fn routes() -> Router<AppState> {
     Router::new()
         .route("/newsletter/subscribers", get(get_subscriber_count))
+        .route("/newsletter/audience/sync", post(sync_audience))
         .route("/newsletter/notify/new/{product_id}", post(notify_new_product))
         .route("/newsletter/notify/restock/{product_id}", post(notify_back_in_stock))
         .route("/newsletter/notify-batch/{notify_type}", post(notify_batch))
+        .route("/newsletter/announcements/{id}", get(get_announcement))
+        .route("/newsletter/test-send", post(test_send))
+        .route("/newsletter/segments", get(list_segments))
+        .route("/newsletter/segments", post(create_segment))
+        .route("/newsletter/segments/{id}", put(update_segment))
+        .route("/newsletter/segments/{id}", delete(delete_segment))
+        .route("/newsletter/campaigns", get(list_campaigns))
+        .route("/newsletter/campaigns", post(create_campaign))
+        .route("/newsletter/campaigns/preview", post(preview_campaign))
+        .route("/newsletter/campaigns/{id}", get(get_campaign))
+        .route("/newsletter/campaigns/{id}", put(update_campaign))
+        .route("/newsletter/campaigns/{id}/schedule", post(schedule_campaign_send))
+        .route("/newsletter/campaigns/{id}/cancel", post(cancel_campaign))
+}
+
+#[derive(Deserialize)]
+pub struct CreateCampaignRequest {
+    #[serde(flatten)]
+    pub campaign: SaveNewsletterCampaign,
+    /// Schedule the send right away; omit to save a draft
+    pub scheduled_ts: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct ScheduleCampaignRequest {
+    /// When to send; omit to send as soon as possible
+    pub scheduled_ts: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct CampaignResponse {
+    #[serde(flatten)]
+    pub campaign: NewsletterCampaign,
+    pub stats: CampaignStats,
+}
+
+#[derive(Serialize)]
+pub struct CampaignPreviewResponse {
+    pub subject: String,
+    pub html: String,
+    /// The plain-text alternative sent alongside the HTML
+    pub text: String,
+    /// Confirmed subscribers the campaign would go to right now
+    pub recipient_count: usize,
 }
 
 #[derive(Deserialize)]
 pub struct BatchNotifyRequest {
     pub product_ids: Vec<String>,
+    /// Send only to this segment instead of every subscriber
+    pub segment_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct TestSendRequest {
+    /// Where to send the proof
+    pub to: String,
+    /// "campaign", "new" or "restock"
+    pub kind: String,
+    /// Campaign to proof, for kind "campaign"
+    pub campaign_id: Option<String>,
+    /// Products to announce, for kind "new" or "restock"
+    #[serde(default)]
+    pub product_ids: Vec<String>,
+    /// Language to render in; defaults to English
+    pub locale: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct NotifyQuery {
+    /// Send only to this segment instead of every subscriber
+    pub segment_id: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct SegmentResponse {
+    #[serde(flatten)]
+    pub segment: NewsletterSegment,
+    /// Confirmed subscribers currently matching the filters
+    pub subscriber_count: usize,
+}
+
+async fn to_segment_response(conn: &Connection, segment: NewsletterSegment) -> AppResult<SegmentResponse> {
+    let subscriber_count = NewsletterSubscriber::get_in_segment(conn, &segment.filters).await?.len();
+    Ok(SegmentResponse { segment, subscriber_count })
+}
+
+fn validate_segment(data: &SaveNewsletterSegment) -> AppResult<()> {
+    if data.name.trim().is_empty() {
+        return Err(AppError::BadRequest("Segment name is required".to_string()));
+    }
+    let filters = &data.filters;
+    for days in [
+        filters.ordered_within_days,
+        filters.subscribed_within_days,
+        filters.engaged_within_days,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        if days <= 0 {
+            return Err(AppError::BadRequest("Day ranges must be positive".to_string()));
+        }
+    }
+    if filters.min_orders.is_some_and(|n| n <= 0) {
+        return Err(AppError::BadRequest("Minimum orders must be positive".to_string()));
+    }
+    Ok(())
+}
+
+async fn list_segments(State(state): State<AppState>) -> AppResult<Json<Vec<SegmentResponse>>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+
+    let mut segments = Vec::new();
+    for segment in NewsletterSegment::list_all(&conn).await? {
+        segments.push(to_segment_response(&conn, segment).await?);
+    }
+    Ok(Json(segments))
+}
+
+async fn create_segment(
+    State(state): State<AppState>,
+    Json(payload): Json<SaveNewsletterSegment>,
+) -> AppResult<Json<SegmentResponse>> {
+    validate_segment(&payload)?;
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let segment = NewsletterSegment::create(&conn, payload).await?;
+    Ok(Json(to_segment_response(&conn, segment).await?))
+}
+
+async fn update_segment(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(payload): Json<SaveNewsletterSegment>,
+) -> AppResult<Json<SegmentResponse>> {
+    validate_segment(&payload)?;
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let segment = NewsletterSegment::update(&conn, &id, payload).await?;
+    Ok(Json(to_segment_response(&conn, segment).await?))
+}
+
+async fn delete_segment(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> AppResult<Json<serde_json::Value>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+
+    if NewsletterCampaign::count_unsent_for_segment(&conn, &id).await? > 0 {
+        return Err(AppError::BadRequest(
+            "This segment is used by a draft or scheduled campaign".to_string(),
+        ));
+    }
+
+    if !NewsletterSegment::delete(&conn, &id).await? {
+        return Err(AppError::NotFound("Segment not found".to_string()));
+    }
+
+    Ok(Json(serde_json::json!({"success": true})))
 }
 
 #[derive(Serialize)]
 pub struct SubscriberCountResponse {
+    /// Confirmed subscribers
     pub count: i64,
+    /// Signed up but haven't confirmed yet
+    pub pending_count: i64,
 }
 
 async fn get_subscriber_count(
     State(state): State<AppState>,
 ) -> AppResult<Json<SubscriberCountResponse>> {
     let conn = state.db.connect().map_err(AppError::from)?;
-    let count = NewsletterSubscriber::count(&conn).await?;
-    Ok(Json(SubscriberCountResponse { count }))
+    let count = NewsletterSubscriber::count(&conn, "confirmed").await?;
+    let pending_count = NewsletterSubscriber::count(&conn, "pending").await?;
+    Ok(Json(SubscriberCountResponse { count, pending_count }))
+}
+
+/// Reconcile the Resend Audience with the subscriber list now instead of waiting for the hourly job
+async fn sync_audience(State(state): State<AppState>) -> AppResult<Json<AudienceSyncSummary>> {
+    match &state.resend {
+        Some(resend) if resend.has_audience() => {}
+        _ => {
+            return Err(AppError::BadRequest(
+                "Resend audience not configured. Set RESEND_API_KEY and RESEND_AUDIENCE_ID.".to_string(),
+            ))
+        }
+    }
+
+    Ok(Json(run_audience_sync(&state).await?))
 }
 
 #[derive(Serialize)]
 pub struct NotifyResponse {
     pub success: bool,
-    pub sent_count: usize,
+    /// Announcement to poll for progress; None when there was nobody to send to
+    pub job_id: Option<String>,
     pub total_subscribers: usize,
 }
 
-async fn notify_new_product(
-    State(state): State<AppState>,
-    Path(product_id): Path<String>,
+/// Queue an announcement of existing products for the background sender.
+/// Returns as soon as it's queued; the send itself can take minutes.
+async fn queue_product_announcement(
+    state: &AppState,
+    notify_type: &str,
+    product_ids: Vec<String>,
+    single_product: bool,
+    segment_id: Option<&str>,
 ) -> AppResult<Json<NotifyResponse>> {
     let conn = state.db.connect().map_err(AppError::from)?;
 
-    // Get the product
-    let product = Product::find_by_id(&conn, &product_id)
-        .await?
-        .ok_or_else(|| AppError::NotFound("Product not found".to_string()))?;
-
-    // Get all subscribers
-    let subscribers = NewsletterSubscriber::get_all(&conn).await?;
-    let total_subscribers = subscribers.len();
-
+    // Get the subscribers in the chosen segment (all subscribers by default)
+    let total_subscribers = NewsletterSegment::recipients(&conn, segment_id).await?.len();
     if total_subscribers == 0 {
         return Ok(Json(NotifyResponse {
             success: true,
-            sent_count: 0,
+            job_id: None,
             total_subscribers: 0,
         }));
     }
 
-    // Check if Resend is configured
-    let resend = state.resend.as_ref().ok_or_else(|| {
-        AppError::Internal("Newsletter service not configured. Set RESEND_API_KEY.".to_string())
-    })?;
-
-    // Get product's first image URL if available
-    let images = ProductImage::list_by_product(&conn, &product_id).await?;
-    let first_image_url = images.first().map(|img| state.storage.public_url(&img.image_path));
-
-    // Prepare subscriber list
-    let subscriber_list: Vec<(String, String)> = subscribers
-        .into_iter()
-        .map(|s| (s.email, s.unsubscribe_token))
-        .collect();
+    if state.resend.is_none() {
+        return Err(AppError::Internal(
+            "Email not configured. Set RESEND_API_KEY or SMTP_PASS.".to_string(),
+        ));
+    }
 
-    // Send notifications
-    let sent_count = resend
-        .send_batch_new_product_notification(&subscriber_list, &product, first_image_url.as_deref())
-        .await?;
+    let announcement =
+        NewsletterAnnouncement::create(&conn, notify_type, &product_ids, single_product, segment_id).await?;
+    queue_announcement(&conn, &announcement.id).await?;
 
     Ok(Json(NotifyResponse {
         success: true,
-        sent_count,
+        job_id: Some(announcement.id),
         total_subscribers,
     }))
 }
 
-async fn notify_back_in_stock(
+async fn notify_new_product(
     State(state): State<AppState>,
     Path(product_id): Path<String>,
+    Query(query): Query<NotifyQuery>,
 ) -> AppResult<Json<NotifyResponse>> {
     let conn = state.db.connect().map_err(AppError::from)?;
-
-    // Get the product
-    let product = Product::find_by_id(&conn, &product_id)
+    Product::find_by_id(&conn, &product_id)
         .await?
         .ok_or_else(|| AppError::NotFound("Product not found".to_string()))?;
 
-    // Get all subscribers
-    let subscribers = NewsletterSubscriber::get_all(&conn).await?;
-    let total_subscribers = subscribers.len();
-
-    if total_subscribers == 0 {
-        return Ok(Json(NotifyResponse {
-            success: true,
-            sent_count: 0,
-            total_subscribers: 0,
-        }));
-    }
-
-    // Check if Resend is configured
-    let resend = state.resend.as_ref().ok_or_else(|| {
-        AppError::Internal("Newsletter service not configured. Set RESEND_API_KEY.".to_string())
-    })?;
-
-    // Get product's first image URL if available
-    let images = ProductImage::list_by_product(&conn, &product_id).await?;
-    let first_image_url = images.first().map(|img| state.storage.public_url(&img.image_path));
-
-    // Prepare subscriber list
-    let subscriber_list: Vec<(String, String)> = subscribers
-        .into_iter()
-        .map(|s| (s.email, s.unsubscribe_token))
-        .collect();
+    queue_product_announcement(&state, "new", vec![product_id], true, query.segment_id.as_deref()).await
+}
 
-    // Send back in stock notifications
-    let sent_count = resend
-        .send_batch_back_in_stock_notification(&subscriber_list, &product, first_image_url.as_deref())
-        .await?;
+async fn notify_back_in_stock(
+    State(state): State<AppState>,
+    Path(product_id): Path<String>,
+    Query(query): Query<NotifyQuery>,
+) -> AppResult<Json<NotifyResponse>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    Product::find_by_id(&conn, &product_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Product not found".to_string()))?;
 
-    Ok(Json(NotifyResponse {
-        success: true,
-        sent_count,
-        total_subscribers,
-    }))
+    queue_product_announcement(&state, "restock", vec![product_id], true, query.segment_id.as_deref()).await
 }
 
 async fn notify_batch(
@@ -147,63 +296,270 @@ async fn notify_batch(
     Path(notify_type): Path<String>,
     Json(payload): Json<BatchNotifyRequest>,
 ) -> AppResult<Json<NotifyResponse>> {
-    let conn = state.db.connect().map_err(AppError::from)?;
+    if !NOTIFY_TYPES.contains(&notify_type.as_str()) {
+        return Err(AppError::BadRequest("Invalid notify type".to_string()));
+    }
 
     if payload.product_ids.is_empty() {
         return Ok(Json(NotifyResponse {
             success: true,
-            sent_count: 0,
+            job_id: None,
             total_subscribers: 0,
         }));
     }
 
-    // Get all products with their images
-    let mut products_with_images: Vec<(Product, Option<String>)> = Vec::new();
-    for product_id in &payload.product_ids {
-        if let Some(product) = Product::find_by_id(&conn, product_id).await? {
-            let images = ProductImage::list_by_product(&conn, product_id).await?;
-            let first_image_url = images.first().map(|img| state.storage.public_url(&img.image_path));
-            products_with_images.push((product, first_image_url));
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let mut product_ids = Vec::new();
+    for product_id in payload.product_ids {
+        if Product::find_by_id(&conn, &product_id).await?.is_some() {
+            product_ids.push(product_id);
         }
     }
 
-    if products_with_images.is_empty() {
+    if product_ids.is_empty() {
         return Err(AppError::NotFound("No valid products found".to_string()));
     }
 
-    // Get all subscribers
-    let subscribers = NewsletterSubscriber::get_all(&conn).await?;
-    let total_subscribers = subscribers.len();
+    queue_product_announcement(&state, &notify_type, product_ids, false, payload.segment_id.as_deref()).await
+}
 
-    if total_subscribers == 0 {
-        return Ok(Json(NotifyResponse {
-            success: true,
-            sent_count: 0,
-            total_subscribers: 0,
-        }));
+/// Send one proof of a campaign or product notification to an admin-supplied
+/// address, without touching the subscriber list
+async fn test_send(
+    State(state): State<AppState>,
+    Json(payload): Json<TestSendRequest>,
+) -> AppResult<Json<serde_json::Value>> {
+    let to = payload.to.trim();
+    if !to.contains('@') || to.len() < 5 {
+        return Err(AppError::BadRequest("Invalid email address".to_string()));
     }
 
-    // Check if Resend is configured
     let resend = state.resend.as_ref().ok_or_else(|| {
-        AppError::Internal("Newsletter service not configured. Set RESEND_API_KEY.".to_string())
+        AppError::Internal("Email not configured. Set RESEND_API_KEY or SMTP_PASS.".to_string())
     })?;
 
-    // Prepare subscriber list
-    let subscriber_list: Vec<(String, String)> = subscribers
-        .into_iter()
-        .map(|s| (s.email, s.unsubscribe_token))
-        .collect();
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let locale = payload
+        .locale
+        .as_deref()
+        .and_then(normalize_locale)
+        .unwrap_or(DEFAULT_LOCALE);
+
+    match payload.kind.as_str() {
+        "campaign" => {
+            let campaign_id = payload
+                .campaign_id
+                .as_deref()
+                .ok_or_else(|| AppError::BadRequest("campaign_id is required".to_string()))?;
+            let campaign = NewsletterCampaign::find_by_id(&conn, campaign_id)
+                .await?
+                .ok_or_else(|| AppError::NotFound("Campaign not found".to_string()))?;
+
+            let products = featured_products(&state, &conn, &campaign.product_ids).await?;
+            let body_html = campaign_body_html(&campaign.body, &campaign.body_format);
+            resend
+                .send_test_campaign(to, &campaign.subject, &body_html, &products, locale)
+                .await?;
+        }
+        notify_type if NOTIFY_TYPES.contains(&notify_type) => {
+            let products = featured_products(&state, &conn, &payload.product_ids).await?;
+            if products.is_empty() {
+                return Err(AppError::NotFound("No valid products found".to_string()));
+            }
+            resend
+                .send_test_product_notification(to, notify_type, &products, locale)
+                .await?;
+        }
+        _ => {
+            return Err(AppError::BadRequest(
+                "Kind must be one of: campaign, new, restock".to_string(),
+            ))
+        }
+    }
 
-    // Send batch notification based on type
-    let sent_count = match notify_type.as_str() {
-        "new" => resend.send_batch_multi_product_new(&subscriber_list, &products_with_images).await?,
-        "restock" => resend.send_batch_multi_product_restock(&subscriber_list, &products_with_images).await?,
-        _ => return Err(AppError::BadRequest("Invalid notify type".to_string())),
-    };
+    Ok(Json(serde_json::json!({"success": true})))
+}
 
-    Ok(Json(NotifyResponse {
-        success: true,
-        sent_count,
-        total_subscribers,
+async fn get_announcement(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> AppResult<Json<NewsletterAnnouncement>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let announcement = NewsletterAnnouncement::find_by_id(&conn, &id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Announcement not found".to_string()))?;
+    Ok(Json(announcement))
+}
+
+async fn validate_campaign(conn: &Connection, data: &SaveNewsletterCampaign) -> AppResult<()> {
+    if data.subject.trim().is_empty() {
+        return Err(AppError::BadRequest("Subject is required".to_string()));
+    }
+    if data.body.trim().is_empty() {
+        return Err(AppError::BadRequest("Body is required".to_string()));
+    }
+    if !CATEGORIES.contains(&data.category.as_str()) {
+        return Err(AppError::BadRequest(format!(
+            "Category must be one of: {}",
+            CATEGORIES.join(", ")
+        )));
+    }
+    if !BODY_FORMATS.contains(&data.body_format.as_str()) {
+        return Err(AppError::BadRequest(format!(
+            "Body format must be one of: {}",
+            BODY_FORMATS.join(", ")
+        )));
+    }
+    if let Some(segment_id) = data.segment_id.as_deref().filter(|id| !id.is_empty()) {
+        NewsletterSegment::find_by_id(conn, segment_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Segment not found".to_string()))?;
+    }
+    for product_id in &data.product_ids {
+        Product::find_by_id(conn, product_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Product {} not found", product_id)))?;
+    }
+    Ok(())
+}
+
+fn now_ts() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
+
+async fn to_campaign_response(conn: &Connection, campaign: NewsletterCampaign) -> AppResult<CampaignResponse> {
+    let stats = CampaignStats::for_campaign(conn, &campaign.id).await?;
+    Ok(CampaignResponse { campaign, stats })
+}
+
+async fn list_campaigns(State(state): State<AppState>) -> AppResult<Json<Vec<CampaignResponse>>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+
+    let mut campaigns = Vec::new();
+    for campaign in NewsletterCampaign::list_all(&conn).await? {
+        campaigns.push(to_campaign_response(&conn, campaign).await?);
+    }
+    Ok(Json(campaigns))
+}
+
+async fn get_campaign(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> AppResult<Json<CampaignResponse>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let campaign = NewsletterCampaign::find_by_id(&conn, &id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Campaign not found".to_string()))?;
+    Ok(Json(to_campaign_response(&conn, campaign).await?))
+}
+
+/// Save a composed campaign as a draft, or schedule it when `scheduled_ts` is given
+async fn create_campaign(
+    State(state): State<AppState>,
+    Json(payload): Json<CreateCampaignRequest>,
+) -> AppResult<Json<CampaignResponse>> {
+    if payload.scheduled_ts.is_some() && state.resend.is_none() {
+        return Err(AppError::BadRequest(
+            "Email not configured. Set RESEND_API_KEY or SMTP_PASS.".to_string(),
+        ));
+    }
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+    validate_campaign(&conn, &payload.campaign).await?;
+
+    let campaign = NewsletterCampaign::create(&conn, payload.campaign).await?;
+    if let Some(scheduled_ts) = payload.scheduled_ts {
+        schedule_campaign(&conn, &campaign.id, scheduled_ts.max(now_ts())).await?;
+    }
+
+    let campaign = NewsletterCampaign::find_by_id(&conn, &campaign.id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Campaign not found".to_string()))?;
+    Ok(Json(to_campaign_response(&conn, campaign).await?))
+}
+
+async fn update_campaign(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(payload): Json<SaveNewsletterCampaign>,
+) -> AppResult<Json<CampaignResponse>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    validate_campaign(&conn, &payload).await?;
+    let campaign = NewsletterCampaign::update(&conn, &id, payload).await?;
+    Ok(Json(to_campaign_response(&conn, campaign).await?))
+}
+
+/// Render a composed campaign exactly as a subscriber would receive it
+async fn preview_campaign(
+    State(state): State<AppState>,
+    Json(payload): Json<SaveNewsletterCampaign>,
+) -> AppResult<Json<CampaignPreviewResponse>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    validate_campaign(&conn, &payload).await?;
+
+    let resend = state.resend.as_ref().ok_or_else(|| {
+        AppError::Internal("Email not configured. Set RESEND_API_KEY or SMTP_PASS.".to_string())
+    })?;
+
+    let products = featured_products(&state, &conn, &payload.product_ids).await?;
+    let body_html = campaign_body_html(&payload.body, &payload.body_format);
+    let branding = resend.branding().await;
+    let html = resend.render_campaign(&body_html, &products, "preview", &branding, DEFAULT_LOCALE);
+    let recipient_count = NewsletterSegment::recipients(&conn, payload.segment_id.as_deref())
+        .await?
+        .iter()
+        .filter(|s| s.wants(&payload.category))
+        .count();
+
+    Ok(Json(CampaignPreviewResponse {
+        subject: payload.subject.trim().to_string(),
+        text: html_to_text(&html),
+        html,
+        recipient_count,
     }))
 }
+
+/// Schedule a draft, move a scheduled send, or send as soon as possible
+async fn schedule_campaign_send(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(payload): Json<ScheduleCampaignRequest>,
+) -> AppResult<Json<CampaignResponse>> {
+    if state.resend.is_none() {
+        return Err(AppError::BadRequest(
+            "Email not configured. Set RESEND_API_KEY or SMTP_PASS.".to_string(),
+        ));
+    }
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+    NewsletterCampaign::find_by_id(&conn, &id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Campaign not found".to_string()))?;
+
+    let scheduled_ts = payload.scheduled_ts.unwrap_or(0).max(now_ts());
+    schedule_campaign(&conn, &id, scheduled_ts).await?;
+
+    let campaign = NewsletterCampaign::find_by_id(&conn, &id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Campaign not found".to_string()))?;
+    Ok(Json(to_campaign_response(&conn, campaign).await?))
+}
+
+async fn cancel_campaign(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> AppResult<Json<CampaignResponse>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+
+    if !NewsletterCampaign::cancel(&conn, &id).await? {
+        return Err(AppError::BadRequest("Only draft or scheduled campaigns can be cancelled".to_string()));
+    }
+
+    let campaign = NewsletterCampaign::find_by_id(&conn, &id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Campaign not found".to_string()))?;
+    Ok(Json(to_campaign_response(&conn, campaign).await?))
+}
@@ -1,5 +1,6 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
+    response::Html,
     routing::{get, post, put},
     Json, Router,
 };
@@ -7,9 +8,16 @@ use libsql::Connection;
 use serde::{Deserialize, Serialize};
 
 use crate::error::{AppError, AppResult};
-use crate::models::{Order, OrderStatus, Product, Setting, ShippingAddress, User};
+use crate::models::{
+    BoxPreset, Money, Order, OrderItemDetail, OrderStatus, Product, ProductStyle, RateFilter, Setting, ShippingAddress,
+    ShippingRateRecord, User,
+};
+use crate::routes::shipping::ParcelSize;
+use crate::routes::webhooks::{queue_stripe_event, void_authorized_order};
 use crate::routes::AppState;
-use crate::services::shippo::{ShippoAddress, ShippoParcel};
+use crate::services::i18n::DEFAULT_LOCALE;
+use crate::services::shippo::{LabelExtras, ShippoAddress, ShippoParcel, SIGNATURE_TYPES};
+use crate::services::token::Capability;
 
 #[derive(Serialize)]
 pub struct AdminOrderResponse {
@@ -17,31 +25,114 @@ pub struct AdminOrderResponse {
     pub user: Option<OrderUserInfo>,
     pub status: String,
     pub total_cents: i32,
-    pub total: f64,
+    pub total: Money,
     pub shipping_address: Option<ShippingAddress>,
     pub tracking_number: Option<String>,
     pub shippo_tracker_id: Option<String>,
     pub stripe_payment_intent_id: Option<String>,
     pub label_url: Option<String>,
+    /// An async label purchase is waiting on Shippo's transaction webhook
+    pub label_pending: bool,
+    /// Shipping was charged at a fallback rate and should be checked against real postage
+    pub needs_rate_review: bool,
     pub shipping_carrier: Option<String>,
     pub shipping_service: Option<String>,
     pub shipping_cents: i32,
+    pub is_gift: bool,
+    pub gift_message: Option<String>,
+    pub gift_wrap: bool,
+    pub gift_wrap_cents: i32,
+    pub promo_code: Option<String>,
+    pub discount_cents: i32,
+    pub tip_cents: i32,
+    pub risk_level: Option<String>,
+    pub risk_score: Option<i32>,
+    pub needs_review: bool,
+    pub archived: bool,
+    pub checkout_rate: Option<ShippingRateRecord>,
+    pub label_rate: Option<ShippingRateRecord>,
+    /// Delivered by the shop itself; moves through out_for_delivery instead of shipped
+    pub local_delivery: bool,
+    pub insurance_amount_cents: Option<i32>,
+    pub insurance_cost_cents: Option<i32>,
+    pub signature_confirmation: Option<String>,
+    pub payment_link_url: Option<String>,
+    /// "authorized" until the payment is captured at label purchase (or out for delivery)
+    pub capture_status: Option<String>,
     pub items: Vec<AdminOrderItemResponse>,
     pub created_ts: i64,
     pub updated_ts: i64,
 }
 
+impl AdminOrderResponse {
+    fn from_order(
+        order: Order,
+        user: Option<OrderUserInfo>,
+        items: Vec<AdminOrderItemResponse>,
+    ) -> Self {
+        let shipping_address = order.get_shipping_address();
+        let needs_review = order.is_elevated_risk();
+        let checkout_rate = order.get_checkout_rate();
+        let label_rate = order.get_label_rate();
+        let local_delivery = order.is_local_delivery();
+        let label_pending = order.label_url.is_none() && order.shippo_transaction_id.is_some();
+
+        Self {
+            id: order.id,
+            user,
+            status: order.status,
+            total_cents: order.total_cents,
+            total: Money::usd(order.total_cents),
+            shipping_address,
+            tracking_number: order.tracking_number,
+            shippo_tracker_id: order.shippo_tracker_id,
+            stripe_payment_intent_id: order.stripe_payment_intent_id,
+            label_url: order.label_url,
+            label_pending,
+            needs_rate_review: order.needs_rate_review,
+            shipping_carrier: order.shipping_carrier,
+            shipping_service: order.shipping_service,
+            shipping_cents: order.shipping_cents,
+            is_gift: order.is_gift,
+            gift_message: order.gift_message,
+            gift_wrap: order.gift_wrap,
+            gift_wrap_cents: order.gift_wrap_cents,
+            promo_code: order.promo_code,
+            discount_cents: order.discount_cents,
+            tip_cents: order.tip_cents,
+            risk_level: order.risk_level,
+            risk_score: order.risk_score,
+            needs_review,
+            archived: order.archived,
+            checkout_rate,
+            label_rate,
+            local_delivery,
+            insurance_amount_cents: order.insurance_amount_cents,
+            insurance_cost_cents: order.insurance_cost_cents,
+            signature_confirmation: order.signature_confirmation,
+            payment_link_url: order.payment_link_url,
+            capture_status: order.capture_status,
+            items,
+            created_ts: order.created_ts,
+            updated_ts: order.updated_ts,
+        }
+    }
+}
+
 #[derive(Serialize)]
 pub struct OrderUserInfo {
     pub id: String,
     pub email: String,
     pub name: Option<String>,
+    /// Language the customer's emails are sent in
+    pub locale: Option<String>,
 }
 
 #[derive(Serialize)]
 pub struct AdminOrderItemResponse {
     pub product_id: String,
     pub product_name: String,
+    pub style_name: Option<String>,
     pub quantity: i32,
     pub price_cents: i32,
 }
@@ -49,8 +140,38 @@ pub struct AdminOrderItemResponse {
 #[derive(Deserialize)]
 pub struct UpdateStatusRequest {
     pub status: String,
+    /// Skip transition validation (e.g. to correct a mistaken status)
+    #[serde(default)]
+    pub force: bool,
 }
 
+#[derive(Deserialize)]
+pub struct BatchStatusRequest {
+    pub order_ids: Vec<String>,
+    pub status: String,
+    #[serde(default)]
+    pub force: bool,
+}
+
+#[derive(Serialize)]
+pub struct BatchStatusResult {
+    pub order_id: String,
+    pub success: bool,
+    pub previous_status: Option<String>,
+    pub status: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct BatchStatusResponse {
+    pub updated: usize,
+    pub failed: usize,
+    pub results: Vec<BatchStatusResult>,
+}
+
+/// Upper bound on orders per batch status request
+const MAX_BATCH_STATUS_ORDERS: usize = 500;
+
 #[derive(Deserialize)]
 pub struct AddTrackingRequest {
     pub tracking_number: String,
@@ -81,65 +202,103 @@ pub struct ShippingRateOption {
 #[derive(Deserialize)]
 pub struct PurchaseLabelRequest {
     pub rate_id: String,
+    /// Insure the shipment for this declared value (up to the order total)
+    pub insurance_amount_cents: Option<i32>,
+    /// "standard", "adult" or "none"; left out, the shop's default for the order value applies
+    pub signature_confirmation: Option<String>,
+    /// Don't wait for Shippo to generate the label; it's attached when the transaction webhook arrives
+    #[serde(default)]
+    pub async_purchase: bool,
 }
 
 #[derive(Serialize)]
 pub struct PurchaseLabelResponse {
-    pub tracking_number: String,
-    pub label_url: String,
+    /// "purchased", or "pending" while Shippo generates an async label
+    pub status: String,
+    pub tracking_number: Option<String>,
+    pub label_url: Option<String>,
     pub carrier: Option<String>,
 }
 
+#[derive(Serialize)]
+pub struct LabelLinkResponse {
+    /// Opens the label without signing in, e.g. on a shipping station
+    pub url: String,
+    pub expires_ts: i64,
+}
+
+/// How long a shareable label link works
+const LABEL_LINK_TTL_SECS: i64 = 24 * 60 * 60;
+
 pub fn routes() -> Router<AppState> {
     Router::new()
         .route("/orders", get(list_orders))
+        .route("/orders/batch-status", put(batch_update_status))
         .route("/orders/{id}", get(get_order))
         .route("/orders/{id}/status", put(update_status))
         .route("/orders/{id}/tracking", post(add_tracking))
-        .route("/orders/{id}/refund", post(refund_order))
         .route("/orders/{id}/shipping-rates", get(get_shipping_rates))
         .route("/orders/{id}/buy-label", post(buy_label))
+        .route("/orders/{id}/void-label", post(void_label))
+        .route("/orders/{id}/label-link", post(label_link))
+        .route("/orders/{id}/rate-reviewed", post(mark_rate_reviewed))
+        .route("/orders/{id}/packing-slip", get(packing_slip))
+}
+
+/// Routes that move money, kept apart so fulfillment staff can't reach them
+pub fn payment_routes() -> Router<AppState> {
+    Router::new()
+        .route("/orders/{id}/refund", post(refund_order))
+        .route("/orders/{id}/payment-link", post(send_payment_link))
 }
 
-async fn list_orders(State(state): State<AppState>) -> AppResult<Json<Vec<AdminOrderResponse>>> {
+#[derive(Deserialize)]
+pub struct ListOrdersQuery {
+    #[serde(default)]
+    pub include_archived: bool,
+}
+
+async fn list_orders(
+    State(state): State<AppState>,
+    Query(query): Query<ListOrdersQuery>,
+) -> AppResult<Json<Vec<AdminOrderResponse>>> {
     let conn = state.db.connect().map_err(AppError::from)?;
-    let orders = Order::list_all(&conn).await?;
+    let orders: Vec<Order> = Order::list_all(&conn)
+        .await?
+        .into_iter()
+        .filter(|o| query.include_archived || !o.archived)
+        .collect();
 
-    let mut responses = Vec::new();
-    for order in orders {
-        let user_info = if let Some(ref user_id) = order.user_id {
-            User::find_by_id(&conn, user_id)
-                .await?
-                .map(|u| OrderUserInfo {
-                    id: u.id,
-                    email: u.email,
-                    name: u.name,
-                })
-        } else {
-            None
-        };
+    // Batch-load items and customers so the list is a fixed number of queries
+    let order_ids: Vec<String> = orders.iter().map(|o| o.id.clone()).collect();
+    let mut user_ids: Vec<String> = orders.iter().filter_map(|o| o.user_id.clone()).collect();
+    user_ids.sort();
+    user_ids.dedup();
 
-        let items = build_order_items(&conn, &order.id).await?;
+    let mut items_by_order = Order::get_items_for_orders(&conn, &order_ids).await?;
+    let users = User::find_by_ids(&conn, &user_ids).await?;
 
-        responses.push(AdminOrderResponse {
-            id: order.id.clone(),
-            user: user_info,
-            status: order.status.clone(),
-            total_cents: order.total_cents,
-            total: order.total_cents as f64 / 100.0,
-            shipping_address: order.get_shipping_address(),
-            tracking_number: order.tracking_number.clone(),
-            shippo_tracker_id: order.shippo_tracker_id.clone(),
-            stripe_payment_intent_id: order.stripe_payment_intent_id.clone(),
-            label_url: order.label_url.clone(),
-            shipping_carrier: order.shipping_carrier.clone(),
-            shipping_service: order.shipping_service.clone(),
-            shipping_cents: order.shipping_cents,
-            items,
-            created_ts: order.created_ts,
-            updated_ts: order.updated_ts,
-        });
-    }
+    let responses = orders
+        .into_iter()
+        .map(|order| {
+            let user_info = order
+                .user_id
+                .as_ref()
+                .and_then(|id| users.get(id))
+                .map(|u| OrderUserInfo {
+                    id: u.id.clone(),
+                    email: u.email.clone(),
+                    name: u.name.clone(),
+                    locale: u.locale.clone(),
+                });
+            let items = items_by_order
+                .remove(&order.id)
+                .map(to_item_responses)
+                .unwrap_or_default();
+
+            AdminOrderResponse::from_order(order, user_info, items)
+        })
+        .collect();
 
     Ok(Json(responses))
 }
@@ -153,38 +312,10 @@ async fn get_order(
         .await?
         .ok_or_else(|| AppError::NotFound("Order not found".to_string()))?;
 
-    let user_info = if let Some(ref user_id) = order.user_id {
-        User::find_by_id(&conn, user_id)
-            .await?
-            .map(|u| OrderUserInfo {
-                id: u.id,
-                email: u.email,
-                name: u.name,
-            })
-    } else {
-        None
-    };
-
+    let user_info = load_user_info(&conn, order.user_id.as_deref()).await?;
     let items = build_order_items(&conn, &order.id).await?;
 
-    Ok(Json(AdminOrderResponse {
-        id: order.id.clone(),
-        user: user_info,
-        status: order.status.clone(),
-        total_cents: order.total_cents,
-        total: order.total_cents as f64 / 100.0,
-        shipping_address: order.get_shipping_address(),
-        tracking_number: order.tracking_number.clone(),
-        shippo_tracker_id: order.shippo_tracker_id.clone(),
-        stripe_payment_intent_id: order.stripe_payment_intent_id.clone(),
-        label_url: order.label_url.clone(),
-        shipping_carrier: order.shipping_carrier.clone(),
-        shipping_service: order.shipping_service.clone(),
-        shipping_cents: order.shipping_cents,
-        items,
-        created_ts: order.created_ts,
-        updated_ts: order.updated_ts,
-    }))
+    Ok(Json(AdminOrderResponse::from_order(order, user_info, items)))
 }
 
 async fn update_status(
@@ -197,42 +328,122 @@ async fn update_status(
     let status = OrderStatus::from_str(&payload.status)
         .ok_or_else(|| AppError::BadRequest("Invalid status".to_string()))?;
 
-    let order = Order::update_status(&conn, &id, status).await?;
+    if payload.force {
+        tracing::warn!("Forcing order {} to status {}", id, status.as_str());
+    }
 
-    let user_info = if let Some(ref user_id) = order.user_id {
-        User::find_by_id(&conn, user_id)
-            .await?
-            .map(|u| OrderUserInfo {
-                id: u.id,
-                email: u.email,
-                name: u.name,
-            })
-    } else {
-        None
-    };
+    capture_for_delivery(&state, &conn, &id, status).await?;
+    let order = Order::update_status(&conn, &id, status, payload.force).await?;
 
+    let user_info = load_user_info(&conn, order.user_id.as_deref()).await?;
     let items = build_order_items(&conn, &order.id).await?;
 
-    Ok(Json(AdminOrderResponse {
-        id: order.id.clone(),
-        user: user_info,
-        status: order.status.clone(),
-        total_cents: order.total_cents,
-        total: order.total_cents as f64 / 100.0,
-        shipping_address: order.get_shipping_address(),
-        tracking_number: order.tracking_number.clone(),
-        shippo_tracker_id: order.shippo_tracker_id.clone(),
-        stripe_payment_intent_id: order.stripe_payment_intent_id.clone(),
-        label_url: order.label_url.clone(),
-        shipping_carrier: order.shipping_carrier.clone(),
-        shipping_service: order.shipping_service.clone(),
-        shipping_cents: order.shipping_cents,
-        items,
-        created_ts: order.created_ts,
-        updated_ts: order.updated_ts,
+    Ok(Json(AdminOrderResponse::from_order(order, user_info, items)))
+}
+
+async fn batch_update_status(
+    State(state): State<AppState>,
+    Json(payload): Json<BatchStatusRequest>,
+) -> AppResult<Json<BatchStatusResponse>> {
+    let status = OrderStatus::from_str(&payload.status)
+        .ok_or_else(|| AppError::BadRequest("Invalid status".to_string()))?;
+
+    if payload.order_ids.is_empty() {
+        return Err(AppError::BadRequest("No orders selected".to_string()));
+    }
+    if payload.order_ids.len() > MAX_BATCH_STATUS_ORDERS {
+        return Err(AppError::BadRequest(format!(
+            "At most {} orders can be updated at once",
+            MAX_BATCH_STATUS_ORDERS
+        )));
+    }
+
+    if payload.force {
+        tracing::warn!(
+            "Forcing {} orders to status {}",
+            payload.order_ids.len(),
+            status.as_str()
+        );
+    }
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let mut results = Vec::with_capacity(payload.order_ids.len());
+
+    for order_id in payload.order_ids {
+        let previous_status = Order::find_by_id(&conn, &order_id).await?.map(|o| o.status);
+
+        // Each order is validated on its own so one bad order doesn't block the rest
+        let updated = match capture_for_delivery(&state, &conn, &order_id, status).await {
+            Ok(()) => Order::update_status(&conn, &order_id, status, payload.force).await,
+            Err(e) => Err(e),
+        };
+        let result = match updated {
+            Ok(order) => BatchStatusResult {
+                order_id,
+                success: true,
+                previous_status,
+                status: Some(order.status),
+                error: None,
+            },
+            Err(AppError::NotFound(msg)) | Err(AppError::BadRequest(msg)) => BatchStatusResult {
+                order_id,
+                success: false,
+                status: previous_status.clone(),
+                previous_status,
+                error: Some(msg),
+            },
+            Err(e) => return Err(e),
+        };
+        results.push(result);
+    }
+
+    let updated = results.iter().filter(|r| r.success).count();
+    tracing::info!(
+        "Batch status update to {}: {} updated, {} failed",
+        status.as_str(),
+        updated,
+        results.len() - updated
+    );
+
+    Ok(Json(BatchStatusResponse {
+        updated,
+        failed: results.len() - updated,
+        results,
     }))
 }
 
+/// Local deliveries never get a label, so an authorized payment is captured
+/// when the order goes out for delivery instead
+async fn capture_for_delivery(
+    state: &AppState,
+    conn: &Connection,
+    order_id: &str,
+    status: OrderStatus,
+) -> AppResult<()> {
+    if status != OrderStatus::OutForDelivery {
+        return Ok(());
+    }
+
+    let order = match Order::find_by_id(conn, order_id).await? {
+        Some(order) => order,
+        None => return Ok(()),
+    };
+    let ready = matches!(order.get_status(), Some(OrderStatus::Paid) | Some(OrderStatus::Processing));
+    if !ready || !order.is_local_delivery() || !order.is_awaiting_capture() {
+        return Ok(());
+    }
+
+    let payment_intent_id = order
+        .stripe_payment_intent_id
+        .as_deref()
+        .ok_or_else(|| AppError::BadRequest("Order has no payment intent to capture".to_string()))?;
+    state.stripe.capture_payment_intent(payment_intent_id).await?;
+    Order::set_captured(conn, order_id).await?;
+    tracing::info!("Captured payment {} for local delivery order {}", payment_intent_id, order_id);
+
+    Ok(())
+}
+
 async fn add_tracking(
     State(state): State<AppState>,
     Path(id): Path<String>,
@@ -240,11 +451,20 @@ async fn add_tracking(
 ) -> AppResult<Json<AdminOrderResponse>> {
     let conn = state.db.connect().map_err(AppError::from)?;
 
-    // Verify order exists
-    Order::find_by_id(&conn, &id)
+    // Verify order exists and can be marked shipped
+    let order = Order::find_by_id(&conn, &id)
         .await?
         .ok_or_else(|| AppError::NotFound("Order not found".to_string()))?;
 
+    if let Some(status) = order.get_status() {
+        if status != OrderStatus::Shipped && !status.can_transition_to(OrderStatus::Shipped) {
+            return Err(AppError::BadRequest(format!(
+                "Cannot ship an order with status {}",
+                status.as_str()
+            )));
+        }
+    }
+
     // Register tracking with Shippo
     let carrier = payload.carrier.as_deref().unwrap_or("usps");
     let tracking = state
@@ -262,43 +482,93 @@ async fn add_tracking(
             if let Ok(Some(user)) = User::find_by_id(&conn, user_id).await {
                 let name = user.name.as_deref().unwrap_or("Customer");
                 let _ = email_service
-                    .send_order_shipped(&user.email, &order, name, &payload.tracking_number)
+                    .send_order_shipped(&user.email, &order, name, &payload.tracking_number, user.email_locale())
                     .await;
             }
         }
     }
 
-    let user_info = if let Some(ref user_id) = order.user_id {
-        User::find_by_id(&conn, user_id)
-            .await?
-            .map(|u| OrderUserInfo {
-                id: u.id,
-                email: u.email,
-                name: u.name,
-            })
-    } else {
-        None
+    let user_info = load_user_info(&conn, order.user_id.as_deref()).await?;
+    let items = build_order_items(&conn, &order.id).await?;
+
+    Ok(Json(AdminOrderResponse::from_order(order, user_info, items)))
+}
+
+#[derive(Deserialize, Default)]
+pub struct PaymentLinkRequest {
+    /// Send to this address instead of the account email
+    pub email: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct PaymentLinkResponse {
+    pub order_id: String,
+    pub payment_link_url: String,
+    pub email_sent: bool,
+}
+
+/// Create a Stripe Payment Link for an unpaid order and email it to the customer.
+/// The order is marked paid by the checkout.session.completed webhook.
+async fn send_payment_link(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    payload: Option<Json<PaymentLinkRequest>>,
+) -> AppResult<Json<PaymentLinkResponse>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let payload = payload.map(|Json(p)| p).unwrap_or_default();
+
+    let order = Order::find_by_id(&conn, &id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Order not found".to_string()))?;
+
+    if order.get_status() != Some(OrderStatus::Pending) {
+        return Err(AppError::BadRequest(format!(
+            "Cannot request payment for an order with status {}",
+            order.status
+        )));
+    }
+    if order.total_cents <= 0 {
+        return Err(AppError::BadRequest("Order total must be greater than zero".to_string()));
+    }
+
+    let user = load_user_info(&conn, order.user_id.as_deref()).await?;
+    let to_email = match payload.email.or_else(|| user.as_ref().map(|u| u.email.clone())) {
+        Some(email) => email,
+        None => return Err(AppError::BadRequest("Order has no customer email".to_string())),
     };
 
-    let items = build_order_items(&conn, &order.id).await?;
+    let link = state
+        .stripe
+        .create_payment_link(
+            &order.id,
+            &format!("Caterpillar Clay Order #{}", &order.id[..8]),
+            order.total_cents as i64,
+        )
+        .await?;
+
+    // Only the newest link should be payable
+    if let Some(ref old_link_id) = order.payment_link_id {
+        if let Err(e) = state.stripe.deactivate_payment_link(old_link_id).await {
+            tracing::warn!("Failed to deactivate old payment link {}: {}", old_link_id, e);
+        }
+    }
+
+    Order::set_payment_link(&conn, &order.id, &link.id, &link.url).await?;
 
-    Ok(Json(AdminOrderResponse {
-        id: order.id.clone(),
-        user: user_info,
-        status: order.status.clone(),
-        total_cents: order.total_cents,
-        total: order.total_cents as f64 / 100.0,
-        shipping_address: order.get_shipping_address(),
-        tracking_number: order.tracking_number.clone(),
-        shippo_tracker_id: order.shippo_tracker_id.clone(),
-        stripe_payment_intent_id: order.stripe_payment_intent_id.clone(),
-        label_url: order.label_url.clone(),
-        shipping_carrier: order.shipping_carrier.clone(),
-        shipping_service: order.shipping_service.clone(),
-        shipping_cents: order.shipping_cents,
-        items,
-        created_ts: order.created_ts,
-        updated_ts: order.updated_ts,
+    let mut email_sent = false;
+    if let Some(ref email_service) = state.email {
+        let name = user.as_ref().and_then(|u| u.name.clone()).unwrap_or_else(|| "Customer".to_string());
+        let locale = user.as_ref().and_then(|u| u.locale.as_deref()).unwrap_or(DEFAULT_LOCALE);
+        match email_service.send_payment_link(&to_email, &order, &name, &link.url, locale).await {
+            Ok(()) => email_sent = true,
+            Err(e) => tracing::error!("Failed to email payment link for order {}: {}", order.id, e),
+        }
+    }
+
+    Ok(Json(PaymentLinkResponse {
+        order_id: order.id,
+        payment_link_url: link.url,
+        email_sent,
     }))
 }
 
@@ -321,7 +591,11 @@ async fn refund_order(
     // Check if order is in a refundable state
     let status = OrderStatus::from_str(&order.status);
     match status {
-        Some(OrderStatus::Paid) | Some(OrderStatus::Processing) | Some(OrderStatus::Shipped) | Some(OrderStatus::Delivered) => {
+        Some(OrderStatus::Paid)
+        | Some(OrderStatus::Processing)
+        | Some(OrderStatus::Shipped)
+        | Some(OrderStatus::OutForDelivery)
+        | Some(OrderStatus::Delivered) => {
             // These statuses are refundable
         }
         Some(OrderStatus::Refunded) => {
@@ -338,11 +612,37 @@ async fn refund_order(
         }
     }
 
+    // Nothing was charged yet, so release the hold instead of refunding
+    if order.is_awaiting_capture() {
+        state.stripe.cancel_payment_intent(payment_intent_id).await?;
+        void_authorized_order(&conn, &order.id, "authorization_released").await?;
+
+        return Ok(Json(RefundResponse {
+            refund_id: payment_intent_id.clone(),
+            status: "canceled".to_string(),
+            amount_cents: order.total_cents as i64,
+        }));
+    }
+
+    // Simulated refunds send their webhook straight to the worker queue
+    if let Some(ref mock) = state.mock_payments {
+        let refund = mock.create_refund(order.total_cents as i64);
+        let event = mock.refund_succeeded_event(&refund, payment_intent_id);
+        queue_stripe_event(&conn, &event.id, &event.event_type, &event.payload).await?;
+
+        return Ok(Json(RefundResponse {
+            refund_id: refund.id,
+            status: refund.status,
+            amount_cents: refund.amount,
+        }));
+    }
+
     // Create refund via Stripe (full refund)
     let refund = state.stripe.create_refund(
         payment_intent_id,
         None, // Full refund
         payload.reason.as_deref(),
+        order.artist_id.is_some(), // Marketplace orders take the money back from the artist
     ).await?;
 
     tracing::info!("Created refund {} for order {} (amount: {} cents)", refund.id, id, refund.amount);
@@ -356,28 +656,47 @@ async fn refund_order(
     }))
 }
 
+async fn load_user_info(conn: &Connection, user_id: Option<&str>) -> AppResult<Option<OrderUserInfo>> {
+    let user_id = match user_id {
+        Some(id) => id,
+        None => return Ok(None),
+    };
+
+    Ok(User::find_by_id(conn, user_id)
+        .await?
+        .map(|u| OrderUserInfo {
+            id: u.id,
+            email: u.email,
+            name: u.name,
+            locale: u.locale,
+        }))
+}
+
 async fn build_order_items(
     conn: &Connection,
     order_id: &str,
 ) -> AppResult<Vec<AdminOrderItemResponse>> {
-    let items = Order::get_items(conn, order_id).await?;
-    let mut responses = Vec::new();
+    let items = Order::get_items_for_orders(conn, &[order_id.to_string()])
+        .await?
+        .remove(order_id)
+        .unwrap_or_default();
 
-    for item in items {
-        let product_name = match Product::find_by_id(conn, &item.product_id).await? {
-            Some(p) => p.name,
-            None => "Unknown Product".to_string(),
-        };
+    Ok(to_item_responses(items))
+}
 
-        responses.push(AdminOrderItemResponse {
+fn to_item_responses(items: Vec<OrderItemDetail>) -> Vec<AdminOrderItemResponse> {
+    items
+        .into_iter()
+        .map(|item| AdminOrderItemResponse {
             product_id: item.product_id,
-            product_name,
+            product_name: item
+                .product_name
+                .unwrap_or_else(|| "Unknown Product".to_string()),
+            style_name: item.style_name,
             quantity: item.quantity,
             price_cents: item.price_cents,
-        });
-    }
-
-    Ok(responses)
+        })
+        .collect()
 }
 
 async fn get_shipping_rates(
@@ -407,40 +726,33 @@ async fn get_shipping_rates(
         ("in", "oz")
     };
 
-    // Calculate parcel dimensions from order items
+    // Calculate parcel dimensions from order items, packed into the box they'll ship in
     let items = Order::get_items(&conn, &id).await?;
-    let mut total_weight = 0.0f64;
-    let mut max_length = 0.0f64;
-    let mut max_width = 0.0f64;
-    let mut total_height = 0.0f64;
+    let mut parcel = ParcelSize::default();
 
     for item in &items {
         let product = Product::find_by_id(&conn, &item.product_id)
             .await?
             .ok_or_else(|| AppError::NotFound(format!("Product {} not found", item.product_id)))?;
+        let style = match &item.style_id {
+            Some(style_id) => ProductStyle::get_by_id(&conn, style_id).await?,
+            None => None,
+        };
 
-        // Use product dimensions or defaults
-        let weight = product.weight_grams.unwrap_or(500) as f64;
-        let length = product.length_cm.unwrap_or(15.0);
-        let width = product.width_cm.unwrap_or(15.0);
-        let height = product.height_cm.unwrap_or(10.0);
-
-        total_weight += weight * item.quantity as f64;
-        max_length = max_length.max(length);
-        max_width = max_width.max(width);
-        total_height += height * item.quantity as f64;
+        parcel.add(&product, style.as_ref(), item.quantity);
     }
+    parcel.pack(&BoxPreset::list_all(&conn).await?);
 
     // Convert units if US system
     let (final_weight, final_length, final_width, final_height) = if unit_system == "us" {
         (
-            total_weight * 0.035274,
-            max_length * 0.393701,
-            max_width * 0.393701,
-            total_height * 0.393701,
+            parcel.weight_grams * 0.035274,
+            parcel.length_cm * 0.393701,
+            parcel.width_cm * 0.393701,
+            parcel.height_cm * 0.393701,
         )
     } else {
-        (total_weight, max_length, max_width, total_height)
+        (parcel.weight_grams, parcel.length_cm, parcel.width_cm, parcel.height_cm)
     };
 
     // Build addresses for Shippo
@@ -475,8 +787,11 @@ async fn get_shipping_rates(
         mass_unit: mass_unit.to_string(),
     };
 
-    // Get rates from Shippo
-    let shippo_rates = state.shippo.get_rates(from_address, to_address, vec![parcel]).await?;
+    // Get rates from Shippo; labels can be bought with any carrier, not just those offered at checkout
+    let shippo_rates = state
+        .shippo
+        .get_rates(from_address, to_address, vec![parcel], &RateFilter::default())
+        .await?;
 
     // Convert to response format
     let rates: Vec<ShippingRateOption> = shippo_rates
@@ -513,7 +828,7 @@ async fn buy_label(
         Some(OrderStatus::Paid) | Some(OrderStatus::Processing) => {
             // OK to buy label
         }
-        Some(OrderStatus::Shipped) | Some(OrderStatus::Delivered) => {
+        Some(OrderStatus::Shipped) | Some(OrderStatus::OutForDelivery) | Some(OrderStatus::Delivered) => {
             return Err(AppError::BadRequest("Order already shipped".to_string()));
         }
         _ => {
@@ -524,26 +839,393 @@ async fn buy_label(
     if order.label_url.is_some() {
         return Err(AppError::BadRequest("Label already purchased for this order".to_string()));
     }
+    if order.shippo_transaction_id.is_some() {
+        return Err(AppError::BadRequest("A label is already being generated for this order".to_string()));
+    }
+
+    let insurance_amount_cents = payload.insurance_amount_cents.filter(|cents| *cents != 0);
+    if let Some(cents) = insurance_amount_cents {
+        if cents < 0 || cents > order.total_cents {
+            return Err(AppError::BadRequest(format!(
+                "Insurance must be between $0 and the order total ({})",
+                Money::usd(order.total_cents)
+            )));
+        }
+    }
+
+    // No choice falls back to the shop's default for orders of this value; "none" opts out
+    let signature_confirmation = match payload.signature_confirmation.as_deref().map(str::trim) {
+        Some("none") | Some("") => None,
+        Some(signature) if SIGNATURE_TYPES.contains(&signature) => Some(signature.to_string()),
+        Some(signature) => {
+            return Err(AppError::BadRequest(format!("Unknown signature option: {}", signature)));
+        }
+        None => Setting::get_signature_defaults(&conn)
+            .await?
+            .for_total(order.total_cents)
+            .map(str::to_string),
+    };
+
+    // The uninsured price, to work out what the coverage adds
+    let base_rate_cents = match insurance_amount_cents {
+        Some(_) => {
+            let rate = state.shippo.get_rate(&payload.rate_id).await?;
+            let amount: f64 = rate.amount.parse().unwrap_or(0.0);
+            Some((amount * 100.0).round() as i32)
+        }
+        None => None,
+    };
+
+    // Collect an authorized payment before spending money on postage
+    if order.is_awaiting_capture() {
+        let payment_intent_id = order.stripe_payment_intent_id.as_deref().ok_or_else(|| {
+            AppError::BadRequest("Order has no payment intent to capture".to_string())
+        })?;
+        state.stripe.capture_payment_intent(payment_intent_id).await?;
+        Order::set_captured(&conn, &id).await?;
+        tracing::info!("Captured payment {} for order {}", payment_intent_id, id);
+    }
 
     // Purchase the label from Shippo
-    let transaction = state.shippo.purchase_label(&payload.rate_id).await?;
+    let extras = LabelExtras {
+        insurance_amount_cents: insurance_amount_cents.map(|c| c as i64),
+        signature_confirmation: signature_confirmation.clone(),
+    };
+    let label_file_type = Setting::get_label_file_type(&conn).await?;
+    let transaction = state
+        .shippo
+        .purchase_label(&payload.rate_id, &extras, &label_file_type, payload.async_purchase)
+        .await?;
+
+    if let Some(ref signature) = signature_confirmation {
+        Order::set_signature_confirmation(&conn, &id, signature).await?;
+    }
+
+    // Shippo is still generating the label; the transaction webhook completes the order
+    if transaction.status != "SUCCESS" {
+        Order::set_pending_label(&conn, &id, &transaction.object_id).await?;
+        if let Some(amount_cents) = insurance_amount_cents {
+            Order::set_insurance(&conn, &id, amount_cents, None).await?;
+        }
+        tracing::info!(
+            "Label for order {} queued as transaction {} ({})",
+            id,
+            transaction.object_id,
+            transaction.status
+        );
+
+        return Ok(Json(PurchaseLabelResponse {
+            status: "pending".to_string(),
+            tracking_number: None,
+            label_url: None,
+            carrier: None,
+        }));
+    }
 
     let tracking_number = transaction.tracking_number
         .ok_or_else(|| AppError::ExternalService("No tracking number in response".to_string()))?;
     let label_url = transaction.label_url
         .ok_or_else(|| AppError::ExternalService("No label URL in response".to_string()))?;
 
-    // Update order with label info
-    Order::set_label(&conn, &id, &tracking_number, &label_url, None).await?;
+    let rate_id = transaction.rate.clone().unwrap_or_else(|| payload.rate_id.clone());
+    let label_rate = complete_label_purchase(
+        &state,
+        &conn,
+        &id,
+        &tracking_number,
+        &label_url,
+        &transaction.object_id,
+        Some(&rate_id),
+    )
+    .await?;
+
+    if let Some(amount_cents) = insurance_amount_cents {
+        // A signature also raises the price, so the difference isn't just insurance then
+        let cost_cents = match (label_rate, base_rate_cents) {
+            (Some(record), Some(base)) if signature_confirmation.is_none() => {
+                Some((record.amount_cents - base).max(0))
+            }
+            _ => None,
+        };
+        Order::set_insurance(&conn, &id, amount_cents, cost_cents).await?;
+    }
+
+    Ok(Json(PurchaseLabelResponse {
+        status: "purchased".to_string(),
+        tracking_number: Some(tracking_number),
+        label_url: Some(label_url),
+        carrier: None,
+    }))
+}
+
+/// Save a generated label on its order, record what it cost for the shipping
+/// audit and register its tracking. Used for both synchronous purchases and
+/// labels completed by the transaction webhook. Returns the label's rate when
+/// Shippo could be asked for it.
+pub(crate) async fn complete_label_purchase(
+    state: &AppState,
+    conn: &Connection,
+    order_id: &str,
+    tracking_number: &str,
+    label_url: &str,
+    transaction_id: &str,
+    rate_id: Option<&str>,
+) -> AppResult<Option<ShippingRateRecord>> {
+    Order::set_label(conn, order_id, tracking_number, label_url, None, Some(transaction_id)).await?;
+
+    let label_rate = match rate_id {
+        Some(rate_id) => match state.shippo.get_rate(rate_id).await {
+            Ok(rate) => {
+                let amount: f64 = rate.amount.parse().unwrap_or(0.0);
+                let record = ShippingRateRecord {
+                    rate_id: Some(rate.object_id),
+                    carrier: Some(rate.provider),
+                    service: Some(rate.servicelevel.name),
+                    amount_cents: (amount * 100.0).round() as i32,
+                    recorded_ts: std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .unwrap()
+                        .as_secs() as i64,
+                };
+                Order::set_label_rate(conn, order_id, &record).await?;
+                Some(record)
+            }
+            Err(e) => {
+                tracing::warn!("Failed to fetch label rate {} for order {}: {}", rate_id, order_id, e);
+                None
+            }
+        },
+        None => None,
+    };
 
     // Register tracking with Shippo for webhook updates
-    let _ = state.shippo.register_tracking(&tracking_number, "usps").await;
+    let _ = state.shippo.register_tracking(tracking_number, "usps").await;
 
-    tracing::info!("Purchased label for order {}: tracking={}", id, tracking_number);
+    tracing::info!("Purchased label for order {}: tracking={}", order_id, tracking_number);
 
-    Ok(Json(PurchaseLabelResponse {
-        tracking_number,
-        label_url,
-        carrier: None,
+    Ok(label_rate)
+}
+
+/// Void an unused label through Shippo's refund API and put the order back to paid
+/// so a new label can be bought. Shippo credits the postage once the carrier approves.
+async fn void_label(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> AppResult<Json<AdminOrderResponse>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+
+    let order = Order::find_by_id(&conn, &id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Order not found".to_string()))?;
+
+    if order.label_url.is_none() {
+        return Err(AppError::BadRequest("Order has no label to void".to_string()));
+    }
+
+    // A label that's been scanned by the carrier can't be refunded
+    if order.get_status() != Some(OrderStatus::Processing) {
+        return Err(AppError::BadRequest("Only labels for unshipped orders can be voided".to_string()));
+    }
+
+    let transaction_id = order.shippo_transaction_id.as_deref().ok_or_else(|| {
+        AppError::BadRequest("This label predates voiding support; refund it from the Shippo dashboard".to_string())
+    })?;
+
+    let refund = state.shippo.refund_label(transaction_id).await?;
+    tracing::info!(
+        "Voided label for order {} (tracking={:?}, refund {} {})",
+        id,
+        order.tracking_number,
+        refund.object_id,
+        refund.status
+    );
+
+    let order = Order::clear_label(&conn, &id).await?;
+
+    let user_info = load_user_info(&conn, order.user_id.as_deref()).await?;
+    let items = build_order_items(&conn, &order.id).await?;
+
+    Ok(Json(AdminOrderResponse::from_order(order, user_info, items)))
+}
+
+/// A signed link to the order's label that works for a day without signing in,
+/// for printing from a device that isn't logged in to the admin
+async fn label_link(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> AppResult<Json<LabelLinkResponse>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+
+    let order = Order::find_by_id(&conn, &id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Order not found".to_string()))?;
+
+    if order.label_url.is_none() {
+        return Err(AppError::BadRequest("Order has no label".to_string()));
+    }
+
+    let (token, expires_ts) = state.tokens.issue(Capability::DownloadLabel, &order.id, LABEL_LINK_TTL_SECS);
+
+    Ok(Json(LabelLinkResponse {
+        url: format!("{}/api/orders/{}/label?token={}", state.config.base_url, order.id, token),
+        expires_ts,
     }))
 }
+
+/// Clear the fallback-rate flag once the shipping charge has been checked
+async fn mark_rate_reviewed(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> AppResult<Json<AdminOrderResponse>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+
+    Order::find_by_id(&conn, &id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Order not found".to_string()))?;
+
+    Order::set_rate_review(&conn, &id, false).await?;
+
+    let order = Order::find_by_id(&conn, &id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Order not found".to_string()))?;
+    let user_info = load_user_info(&conn, order.user_id.as_deref()).await?;
+    let items = build_order_items(&conn, &order.id).await?;
+
+    Ok(Json(AdminOrderResponse::from_order(order, user_info, items)))
+}
+
+/// Escape user-provided text for inclusion in HTML
+fn escape_html(input: &str) -> String {
+    input
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+async fn packing_slip(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> AppResult<Html<String>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+
+    let order = Order::find_by_id(&conn, &id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Order not found".to_string()))?;
+
+    let items = build_order_items(&conn, &order.id).await?;
+
+    // Gift orders never show prices - the slip goes in the box to the recipient
+    let show_prices = !order.is_gift;
+
+    let items_html: String = items
+        .iter()
+        .map(|item| {
+            let name = match &item.style_name {
+                Some(style) => format!("{} ({})", escape_html(&item.product_name), escape_html(style)),
+                None => escape_html(&item.product_name),
+            };
+            if show_prices {
+                format!(
+                    "<tr><td>{}</td><td>{}</td><td>{}</td></tr>",
+                    name,
+                    item.quantity,
+                    Money::usd(item.price_cents * item.quantity)
+                )
+            } else {
+                format!(
+                    "<tr><td>{}</td><td>{}</td></tr>",
+                    name,
+                    item.quantity
+                )
+            }
+        })
+        .collect();
+
+    let price_header = if show_prices { "<th>Price</th>" } else { "" };
+    let total_html = if show_prices {
+        format!(r#"<p class="total">Total: {}</p>"#, Money::usd(order.total_cents))
+    } else {
+        String::new()
+    };
+
+    let address_html = match order.get_shipping_address() {
+        Some(addr) => format!(
+            "{}<br>{}<br>{}, {} {}<br>{}",
+            escape_html(&addr.name),
+            escape_html(&addr.street),
+            escape_html(&addr.city),
+            escape_html(&addr.state),
+            escape_html(&addr.zip),
+            escape_html(&addr.country)
+        ),
+        None => String::new(),
+    };
+
+    let gift_html = match &order.gift_message {
+        Some(message) if order.is_gift => format!(
+            r#"<div class="gift"><p class="gift-label">A gift for you</p><p>{}</p></div>"#,
+            escape_html(message).replace('\n', "<br>")
+        ),
+        Some(message) => format!(
+            r#"<div class="gift"><p>{}</p></div>"#,
+            escape_html(message).replace('\n', "<br>")
+        ),
+        None => String::new(),
+    };
+
+    let gift_wrap_note = if order.gift_wrap {
+        r#"<p class="note">GIFT WRAP REQUESTED</p>"#
+    } else {
+        ""
+    };
+
+    let html = format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+    <meta charset="UTF-8">
+    <title>Packing Slip - #{}</title>
+    <style>
+        body {{ font-family: 'Courier New', monospace; padding: 32px; color: #18191B; }}
+        h1 {{ font-size: 18px; margin-bottom: 4px; }}
+        .order-id {{ color: #666; font-size: 12px; margin-bottom: 24px; }}
+        .address {{ font-size: 14px; line-height: 1.6; margin-bottom: 24px; }}
+        table {{ width: 100%; border-collapse: collapse; font-size: 14px; }}
+        th, td {{ text-align: left; padding: 8px; border-bottom: 1px solid #E0E0E0; }}
+        .total {{ font-size: 16px; margin-top: 16px; text-align: right; }}
+        .gift {{ border: 2px dashed #97BAD9; padding: 16px; margin-top: 24px; font-size: 14px; line-height: 1.6; }}
+        .gift-label {{ font-weight: bold; margin-top: 0; }}
+        .note {{ font-weight: bold; margin-top: 16px; }}
+        .footer {{ margin-top: 32px; font-size: 10px; color: #888; }}
+    </style>
+</head>
+<body>
+    <h1>Caterpillar Clay</h1>
+    <p class="order-id">Order #{}</p>
+    <div class="address">{}</div>
+    <table>
+        <tr><th>Item</th><th>Qty</th>{}</tr>
+        {}
+    </table>
+    {}
+    {}
+    {}
+    <div class="footer">
+        <p>Caterpillar Clay - Handmade Pottery</p>
+    </div>
+</body>
+</html>"#,
+        &order.id[..8],
+        &order.id[..8],
+        address_html,
+        price_header,
+        items_html,
+        total_html,
+        gift_wrap_note,
+        gift_html
+    );
+
+    Ok(Html(html))
+}
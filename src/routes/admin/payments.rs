@@ -0,0 +1,68 @@
+use axum::{
+    extract::State,
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, AppResult};
+use crate::routes::AppState;
+use crate::services::stripe::PaymentMethodDomain;
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/payments/domains", get(list_domains))
+        .route("/payments/domains", post(register_domain))
+}
+
+#[derive(Deserialize)]
+pub struct RegisterDomainRequest {
+    /// Defaults to STRIPE_WALLET_DOMAIN, then the host of BASE_URL
+    pub domain: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct PaymentDomainsResponse {
+    pub payment_method_types: Vec<String>,
+    pub domains: Vec<PaymentMethodDomain>,
+}
+
+async fn list_domains(State(state): State<AppState>) -> AppResult<Json<PaymentDomainsResponse>> {
+    let domains = state.stripe.list_payment_method_domains().await?;
+    Ok(Json(PaymentDomainsResponse {
+        payment_method_types: state.config.stripe_payment_method_types.clone(),
+        domains,
+    }))
+}
+
+/// Register the storefront domain with Stripe so Apple Pay and Google Pay buttons appear
+async fn register_domain(
+    State(state): State<AppState>,
+    Json(payload): Json<RegisterDomainRequest>,
+) -> AppResult<Json<PaymentMethodDomain>> {
+    let domain = payload
+        .domain
+        .or_else(|| state.config.stripe_wallet_domain.clone())
+        .unwrap_or_else(|| host_of(&state.config.base_url).to_string());
+    let domain = domain.trim().to_lowercase();
+
+    if domain.is_empty() || domain == "localhost" || domain.contains('/') || domain.contains(':') {
+        return Err(AppError::BadRequest(format!(
+            "'{}' is not a public domain that can be registered for wallet payments",
+            domain
+        )));
+    }
+
+    let registered = state.stripe.register_payment_method_domain(&domain).await?;
+    tracing::info!("Registered payment method domain {} ({})", registered.domain_name, registered.id);
+    Ok(Json(registered))
+}
+
+/// Host part of a URL like "https://shop.example.com:443/path"
+fn host_of(url: &str) -> &str {
+    let without_scheme = url.split("://").nth(1).unwrap_or(url);
+    without_scheme
+        .split(['/', ':'])
+        .next()
+        .unwrap_or(without_scheme)
+}
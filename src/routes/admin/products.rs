@@ -1,3 +1,5 @@
+use std::collections::HashSet;
+
 use axum::{
     extract::{Multipart, Path, State},
     routing::{delete, get, post, put},
@@ -6,8 +8,13 @@ use axum::{
 use serde::{Deserialize, Serialize};
 
 use crate::error::{AppError, AppResult};
-use crate::models::{CreateProduct, Product, ProductImage, ProductNotification, ProductStyle, UpdateProduct};
+use crate::models::{
+    Artist, BoxPreset, CreateProduct, EmailSuppression, Money, Product, ProductImage, ProductNotification, ProductStyle,
+    Setting, UpdateProduct,
+};
+use crate::models::product::BILLING_INTERVALS;
 use crate::routes::AppState;
+use crate::services::i18n::DEFAULT_LOCALE;
 use crate::services::image::process_image;
 
 /// Sanitize a style name for use in folder paths
@@ -40,6 +47,7 @@ pub struct AdminStyleResponse {
     pub stock_quantity: i64,
     pub image_id: Option<String>,
     pub sort_order: i64,
+    pub box_preset_id: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -48,7 +56,7 @@ pub struct AdminProductResponse {
     pub name: String,
     pub description: Option<String>,
     pub price_cents: i32,
-    pub price: f64,
+    pub price: Money,
     pub images: Vec<ImageResponse>,
     pub styles: Vec<AdminStyleResponse>,
     pub stock_quantity: i32,
@@ -62,6 +70,10 @@ pub struct AdminProductResponse {
     pub length_cm: Option<f64>,
     pub width_cm: Option<f64>,
     pub height_cm: Option<f64>,
+    pub category: Option<String>,
+    pub artist_id: Option<String>,
+    pub billing_interval: Option<String>,
+    pub box_preset_id: Option<String>,
 }
 
 impl AdminProductResponse {
@@ -96,6 +108,7 @@ impl AdminProductResponse {
                 stock_quantity: style.stock_quantity,
                 image_id: style.image_id,
                 sort_order: style.sort_order,
+                box_preset_id: style.box_preset_id,
             })
             .collect();
 
@@ -104,7 +117,7 @@ impl AdminProductResponse {
             name: product.name,
             description: product.description,
             price_cents: product.price_cents,
-            price: product.price_cents as f64 / 100.0,
+            price: Money::usd(product.price_cents),
             images: image_responses,
             styles: style_responses,
             stock_quantity: product.stock_quantity,
@@ -118,6 +131,10 @@ impl AdminProductResponse {
             length_cm: product.length_cm,
             width_cm: product.width_cm,
             height_cm: product.height_cm,
+            category: product.category,
+            artist_id: product.artist_id,
+            billing_interval: product.billing_interval,
+            box_preset_id: product.box_preset_id,
         }
     }
 }
@@ -132,6 +149,7 @@ pub struct CreateStyleRequest {
     pub name: String,
     pub stock_quantity: i64,
     pub image_id: Option<String>,
+    pub box_preset_id: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -139,6 +157,7 @@ pub struct UpdateStyleRequest {
     pub name: String,
     pub stock_quantity: i64,
     pub image_id: Option<String>,
+    pub box_preset_id: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -179,10 +198,10 @@ pub fn routes() -> Router<AppState> {
         .route("/products/{id}", get(get_product))
         .route("/products/{id}", put(update_product))
         .route("/products/{id}", delete(delete_product))
-        .route("/products/{id}/images", post(upload_image))
         .route("/products/{id}/images/reorder", put(reorder_images))
         .route("/products/{id}/images/{image_id}", delete(delete_image))
         .route("/products/{id}/sync-stripe", post(sync_to_stripe))
+        .route("/products/sync-stripe-all", post(sync_all_to_stripe))
         // Style routes
         .route("/products/{id}/styles", post(create_style))
         .route("/products/{id}/styles/reorder", put(reorder_styles))
@@ -190,6 +209,136 @@ pub fn routes() -> Router<AppState> {
         .route("/products/{id}/styles/{style_id}", delete(delete_style))
 }
 
+/// Image uploads, kept apart so they get the larger upload body limit
+pub fn upload_routes() -> Router<AppState> {
+    Router::new().route("/products/{id}/images", post(upload_image))
+}
+
+fn validate_billing_interval(interval: Option<&str>) -> AppResult<()> {
+    match interval {
+        Some(interval) if !BILLING_INTERVALS.contains(&interval) => Err(AppError::BadRequest(format!(
+            "Billing interval must be one of: {}",
+            BILLING_INTERVALS.join(", ")
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// A preferred box must be one of the shop's presets; empty means none
+async fn validate_box_preset(conn: &libsql::Connection, box_preset_id: Option<&str>) -> AppResult<()> {
+    match box_preset_id {
+        Some(id) if !id.is_empty() => {
+            BoxPreset::find_by_id(conn, id)
+                .await?
+                .ok_or_else(|| AppError::NotFound("Box preset not found".to_string()))?;
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Send Notify Me restock alerts that are now due: whole-product subscribers when
+/// `product_restocked`, and style subscribers whose style has stock again. Only the
+/// alerts that went out (or were skipped as suppressed or recently alerted) are marked
+/// notified, so the rest keep waiting. An address gets at most one alert per product
+/// within the restock alert window, however often the stock flaps between 0 and 1.
+/// Returns the number of emails sent.
+async fn send_restock_alerts(
+    state: &AppState,
+    conn: &libsql::Connection,
+    product: &Product,
+    product_restocked: bool,
+) -> AppResult<usize> {
+    let resend = match state.resend {
+        Some(ref resend) => resend,
+        None => return Ok(0),
+    };
+
+    let pending = ProductNotification::get_pending_for_product(conn, &product.id).await?;
+    let mut style_ids: Vec<String> = pending.iter().filter_map(|n| n.style_id.clone()).collect();
+    style_ids.sort();
+    style_ids.dedup();
+
+    let mut due: Vec<(ProductNotification, Option<ProductStyle>)> = Vec::new();
+    if product_restocked {
+        due.extend(pending.iter().filter(|n| n.style_id.is_none()).map(|n| (n.clone(), None)));
+    }
+    for style in ProductStyle::get_restocked_styles(conn, &product.id, &style_ids).await? {
+        for notification in ProductNotification::get_pending_for_styles(conn, &product.id, std::slice::from_ref(&style.id)).await? {
+            due.push((notification, Some(style.clone())));
+        }
+    }
+
+    if due.is_empty() {
+        return Ok(0);
+    }
+
+    let images = ProductImage::list_by_product(conn, &product.id).await?;
+    let public_url = |path: &str| {
+        if path.starts_with("http") {
+            path.to_string()
+        } else {
+            state.storage.public_url(path)
+        }
+    };
+    let product_image_url = images.first().map(|img| public_url(&img.image_path));
+    let suppressed = EmailSuppression::emails(conn).await?;
+
+    let window_hours = Setting::get_restock_alert_window_hours(conn).await?;
+    let mut recently_alerted = if window_hours > 0 {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        ProductNotification::emails_notified_since(conn, &product.id, now - window_hours * 60 * 60).await?
+    } else {
+        HashSet::new()
+    };
+
+    let mut sent_count = 0;
+    for (notification, style) in &due {
+        let email = notification.email.to_lowercase();
+        if recently_alerted.contains(&email) {
+            tracing::info!("Skipping restock alert to {}: already alerted about {} recently", email, product.name);
+        } else if !suppressed.contains(&email) {
+            let locale = notification.locale.as_deref().unwrap_or(DEFAULT_LOCALE);
+            let result = match style {
+                Some(style) => {
+                    let image_url = style.image_path.as_deref().map(public_url).or_else(|| product_image_url.clone());
+                    resend
+                        .send_product_restock_alert_with_styles(
+                            &notification.email,
+                            product,
+                            image_url.as_deref(),
+                            std::slice::from_ref(&style.name),
+                            locale,
+                        )
+                        .await
+                }
+                None => {
+                    resend
+                        .send_product_restock_alert(&notification.email, product, product_image_url.as_deref(), locale)
+                        .await
+                }
+            };
+
+            if let Err(e) = result {
+                tracing::error!("Failed to send restock alert to {}: {}", notification.email, e);
+                continue;
+            }
+            sent_count += 1;
+            if window_hours > 0 {
+                recently_alerted.insert(email);
+            }
+        }
+
+        ProductNotification::mark_notified(conn, &notification.id).await?;
+    }
+
+    tracing::info!("Sent {} restock notifications for product {}", sent_count, product.name);
+    Ok(sent_count)
+}
+
 async fn list_products(State(state): State<AppState>) -> AppResult<Json<Vec<AdminProductResponse>>> {
     let conn = state.db.connect().map_err(AppError::from)?;
     let products = Product::list_all(&conn).await?;
@@ -215,7 +364,7 @@ async fn batch_update_products(
     let mut emails_sent = 0;
 
     // Collect products that need restock notifications
-    let mut restocked_products: Vec<(Product, Option<String>)> = Vec::new();
+    let mut restocked_products: Vec<Product> = Vec::new();
 
     for update in &payload.updates {
         tracing::info!("Updating product: {} ({})", update.name, update.id);
@@ -238,6 +387,10 @@ async fn batch_update_products(
             length_cm: None,
             width_cm: None,
             height_cm: None,
+            category: None,
+            artist_id: None,
+            billing_interval: None,
+            box_preset_id: None,
         };
 
         let mut product = match Product::update(&conn, &update.id, update_data).await {
@@ -293,41 +446,13 @@ async fn batch_update_products(
 
         // Check if this is a restock (was out of stock, now has stock)
         if payload.send_emails && update.was_out_of_stock && update.stock_quantity > 0 {
-            // Get first image for email
-            let images = ProductImage::list_by_product(&conn, &product.id).await?;
-            let image_url = images.first().map(|img| {
-                if img.image_path.starts_with("http") {
-                    img.image_path.clone()
-                } else {
-                    state.storage.public_url(&img.image_path)
-                }
-            });
-            restocked_products.push((product, image_url));
+            restocked_products.push(product);
         }
     }
 
     // Send restock notifications
-    if payload.send_emails && !restocked_products.is_empty() {
-        if let Some(ref resend) = state.resend {
-            for (product, image_url) in &restocked_products {
-                // Get all pending notifications for this product
-                let notifications = ProductNotification::get_pending_for_product(&conn, &product.id).await?;
-
-                for notification in &notifications {
-                    if let Err(e) = resend
-                        .send_product_restock_alert(&notification.email, product, image_url.as_deref())
-                        .await
-                    {
-                        tracing::error!("Failed to send restock alert to {}: {}", notification.email, e);
-                    } else {
-                        emails_sent += 1;
-                    }
-                }
-
-                // Mark all as notified
-                ProductNotification::mark_all_notified_for_product(&conn, &product.id).await?;
-            }
-        }
+    for product in &restocked_products {
+        emails_sent += send_restock_alerts(&state, &conn, product, true).await?;
     }
 
     Ok(Json(BatchUpdateResponse {
@@ -358,6 +483,14 @@ async fn create_product(
 ) -> AppResult<Json<AdminProductResponse>> {
     let conn = state.db.connect().map_err(AppError::from)?;
 
+    if let Some(ref artist_id) = payload.artist_id {
+        Artist::find_by_id(&conn, artist_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Artist not found".to_string()))?;
+    }
+    validate_billing_interval(payload.billing_interval.as_deref())?;
+    validate_box_preset(&conn, payload.box_preset_id.as_deref()).await?;
+
     // Extract values for Stripe sync before moving payload
     let name = payload.name.clone();
     let description = payload.description.clone();
@@ -368,7 +501,7 @@ async fn create_product(
     // Sync to Stripe
     match state
         .stripe
-        .create_product(&name, description.as_deref(), price_cents as i64, vec![])
+        .create_product(&product.id, &name, description.as_deref(), price_cents as i64, vec![])
         .await
     {
         Ok((stripe_product_id, stripe_price_id)) => {
@@ -394,6 +527,14 @@ async fn update_product(
         .await?
         .ok_or_else(|| AppError::NotFound("Product not found".to_string()))?;
 
+    if let Some(ref artist_id) = payload.artist_id {
+        Artist::find_by_id(&conn, artist_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Artist not found".to_string()))?;
+    }
+    validate_billing_interval(payload.billing_interval.as_deref())?;
+    validate_box_preset(&conn, payload.box_preset_id.as_deref()).await?;
+
     // Check if this is a restock (was 0, now > 0)
     let was_out_of_stock = current.stock_quantity == 0;
     let new_stock = payload.stock_quantity;
@@ -406,38 +547,7 @@ async fn update_product(
 
     // Send restock notifications if product was restocked
     if was_out_of_stock && new_stock.map(|s| s > 0).unwrap_or(false) {
-        let notifications = ProductNotification::get_pending_for_product(&conn, &id).await?;
-
-        if !notifications.is_empty() {
-            // Get first image for email
-            let images = ProductImage::list_by_product(&conn, &id).await?;
-            let image_url = images.first().map(|img| {
-                if img.image_path.starts_with("http") {
-                    img.image_path.clone()
-                } else {
-                    state.storage.public_url(&img.image_path)
-                }
-            });
-
-            // Send notifications
-            if let Some(ref resend) = state.resend {
-                let mut sent_count = 0;
-                for notification in &notifications {
-                    if let Err(e) = resend
-                        .send_product_restock_alert(&notification.email, &product, image_url.as_deref())
-                        .await
-                    {
-                        tracing::error!("Failed to send restock alert to {}: {}", notification.email, e);
-                    } else {
-                        sent_count += 1;
-                    }
-                }
-                tracing::info!("Sent {} restock notifications for product {}", sent_count, product.name);
-            }
-
-            // Mark all as notified
-            ProductNotification::mark_all_notified_for_product(&conn, &id).await?;
-        }
+        send_restock_alerts(&state, &conn, &product, true).await?;
     }
 
     // Get images for Stripe sync (Stripe allows max 8 images)
@@ -489,6 +599,7 @@ async fn update_product(
         match state
             .stripe
             .create_product(
+                &product.id,
                 &product.name,
                 product.description.as_deref(),
                 product.price_cents as i64,
@@ -781,6 +892,186 @@ async fn sync_to_stripe(
     }))
 }
 
+#[derive(Serialize)]
+pub struct ProductSyncResult {
+    /// None for Stripe products with no matching local product
+    pub product_id: Option<String>,
+    pub name: Option<String>,
+    pub stripe_product_id: Option<String>,
+    /// created, updated, repriced, archived, or skipped
+    pub action: String,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct SyncAllResponse {
+    pub success: bool,
+    pub synced_count: usize,
+    pub failed_count: usize,
+    pub results: Vec<ProductSyncResult>,
+}
+
+/// Reconcile the whole catalog with Stripe, reporting what happened to each product
+async fn sync_all_to_stripe(State(state): State<AppState>) -> AppResult<Json<SyncAllResponse>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let products = Product::list_all(&conn).await?;
+    let mut results = Vec::new();
+
+    for product in &products {
+        let mut result = ProductSyncResult {
+            product_id: Some(product.id.clone()),
+            name: Some(product.name.clone()),
+            stripe_product_id: product.stripe_product_id.clone(),
+            action: "skipped".to_string(),
+            success: true,
+            error: None,
+        };
+
+        let outcome = sync_product_to_stripe(&state, &conn, product, &mut result).await;
+        if let Err(e) = outcome {
+            tracing::warn!("Stripe sync failed for product {}: {}", product.id, e);
+            result.success = false;
+            result.error = Some(e.to_string());
+        }
+        results.push(result);
+    }
+
+    // Archive Stripe products we created for products that no longer exist locally
+    match state.stripe.list_active_products().await {
+        Ok(stripe_products) => {
+            for (stripe_product_id, local_id) in stripe_products {
+                let local_id = match local_id {
+                    Some(id) => id,
+                    None => continue,
+                };
+                let linked = products
+                    .iter()
+                    .any(|p| p.stripe_product_id.as_deref() == Some(stripe_product_id.as_str()));
+                if linked || products.iter().any(|p| p.id == local_id) {
+                    continue;
+                }
+
+                let error = state.stripe.archive_product(&stripe_product_id).await.err();
+                results.push(ProductSyncResult {
+                    product_id: None,
+                    name: None,
+                    stripe_product_id: Some(stripe_product_id),
+                    action: "archived".to_string(),
+                    success: error.is_none(),
+                    error: error.map(|e| e.to_string()),
+                });
+            }
+        }
+        Err(e) => {
+            tracing::warn!("Failed to list Stripe products for orphan cleanup: {}", e);
+            results.push(ProductSyncResult {
+                product_id: None,
+                name: None,
+                stripe_product_id: None,
+                action: "archived".to_string(),
+                success: false,
+                error: Some(e.to_string()),
+            });
+        }
+    }
+
+    let failed_count = results.iter().filter(|r| !r.success).count();
+    let synced_count = results.iter().filter(|r| r.success && r.action != "skipped").count();
+
+    Ok(Json(SyncAllResponse {
+        success: failed_count == 0,
+        synced_count,
+        failed_count,
+        results,
+    }))
+}
+
+/// Bring one product's Stripe product and price in line with the local row
+async fn sync_product_to_stripe(
+    state: &AppState,
+    conn: &libsql::Connection,
+    product: &Product,
+    result: &mut ProductSyncResult,
+) -> AppResult<()> {
+    let stripe_product_id = match &product.stripe_product_id {
+        Some(id) => id.clone(),
+        None => {
+            if !product.is_active {
+                return Ok(());
+            }
+
+            let images = ProductImage::list_by_product(conn, &product.id).await?;
+            let image_urls: Vec<String> = images
+                .iter()
+                .take(8)
+                .map(|img| state.storage.public_url(&img.image_path))
+                .collect();
+
+            let (stripe_product_id, stripe_price_id) = state
+                .stripe
+                .create_product(
+                    &product.id,
+                    &product.name,
+                    product.description.as_deref(),
+                    product.price_cents as i64,
+                    image_urls,
+                )
+                .await?;
+            Product::set_stripe_ids(conn, &product.id, &stripe_product_id, &stripe_price_id).await?;
+
+            result.stripe_product_id = Some(stripe_product_id);
+            result.action = "created".to_string();
+            return Ok(());
+        }
+    };
+
+    if !product.is_active {
+        state.stripe.archive_product(&stripe_product_id).await?;
+        result.action = "archived".to_string();
+        return Ok(());
+    }
+
+    let images = ProductImage::list_by_product(conn, &product.id).await?;
+    let image_urls: Vec<String> = images
+        .iter()
+        .take(8)
+        .map(|img| state.storage.public_url(&img.image_path))
+        .collect();
+
+    state
+        .stripe
+        .reconcile_product(
+            &stripe_product_id,
+            &product.id,
+            &product.name,
+            product.description.as_deref(),
+            image_urls,
+        )
+        .await?;
+    result.action = "updated".to_string();
+
+    // Replace the price if it is missing, archived, or no longer matches
+    let price_current = match &product.stripe_price_id {
+        Some(price_id) => {
+            let (unit_amount, active) = state.stripe.get_price(price_id).await?;
+            active && unit_amount == Some(product.price_cents as i64)
+        }
+        None => false,
+    };
+
+    if !price_current {
+        let new_price_id = state
+            .stripe
+            .update_price(&stripe_product_id, product.price_cents as i64, product.stripe_price_id.as_deref())
+            .await?;
+        Product::set_stripe_ids(conn, &product.id, &stripe_product_id, &new_price_id).await?;
+        result.action = "repriced".to_string();
+    }
+
+    Ok(())
+}
+
 // Style CRUD handlers
 
 async fn create_style(
@@ -810,6 +1101,8 @@ async fn create_style(
         }
     }
 
+    validate_box_preset(&conn, payload.box_preset_id.as_deref()).await?;
+
     // Create the style
     ProductStyle::create(
         &conn,
@@ -817,6 +1110,7 @@ async fn create_style(
         &payload.name,
         payload.stock_quantity,
         payload.image_id.as_deref(),
+        payload.box_preset_id.as_deref().filter(|id| !id.is_empty()),
     )
     .await?;
 
@@ -878,6 +1172,8 @@ async fn update_style(
         }
     }
 
+    validate_box_preset(&conn, payload.box_preset_id.as_deref()).await?;
+
     // Update the style
     ProductStyle::update(
         &conn,
@@ -885,9 +1181,15 @@ async fn update_style(
         &payload.name,
         payload.stock_quantity,
         payload.image_id.as_deref(),
+        payload.box_preset_id.as_deref().filter(|id| !id.is_empty()),
     )
     .await?;
 
+    // Subscribers waiting on this style hear about it once it's back in stock
+    if style.stock_quantity == 0 && payload.stock_quantity > 0 {
+        send_restock_alerts(&state, &conn, &product, false).await?;
+    }
+
     let images = ProductImage::list_by_product(&conn, &product_id).await?;
     let styles = ProductStyle::get_by_product(&conn, &product_id).await?;
 
@@ -1,24 +1,84 @@
 use axum::{
-    extract::{Multipart, State},
-    routing::{get, put},
+    extract::{Multipart, Path, State},
+    routing::{delete, get, post, put},
     Json, Router,
 };
 use serde::{Deserialize, Serialize};
 
 use crate::error::{AppError, AppResult};
-use crate::models::{ArtistInfo, Setting, ShopAddress};
+use crate::jobs::cart_cleanup::run_cart_cleanup;
+use crate::jobs::retention::run_retention;
+use crate::jobs::win_back::run_win_back;
+use crate::models::settings::{is_hex_color, DIGEST_DAYS};
+use crate::models::shipping_rule::RATE_TYPES;
+use crate::models::{
+    ArtistInfo, BoxPreset, EmailBranding, FallbackRates, LocalDelivery, OrderAlerts, RateFilter, SaveBoxPreset, SaveShippingRule, Setting,
+    ShippingRule, ShopAddress, SignatureDefaults, WeeklyDigest, WinBack, WinBackEmail,
+};
 use crate::routes::AppState;
+use crate::services::shippo::{LABEL_FILE_TYPES, SIGNATURE_TYPES};
 
 pub fn routes() -> Router<AppState> {
     Router::new()
         .route("/settings/artist", get(get_artist_info))
         .route("/settings/artist", put(update_artist_info))
-        .route("/settings/artist/image", put(upload_artist_image))
         .route("/settings/favicon", get(get_favicon))
-        .route("/settings/favicon", put(upload_favicon))
         .route("/settings/shipping", get(get_shipping_settings))
         .route("/settings/shipping/address", put(update_shop_address))
         .route("/settings/shipping/units", put(update_unit_system))
+        .route("/settings/shipping/carriers", get(get_rate_filter))
+        .route("/settings/shipping/carriers", put(update_rate_filter))
+        .route("/settings/shipping/local-delivery", get(get_local_delivery))
+        .route("/settings/shipping/local-delivery", put(update_local_delivery))
+        .route("/settings/shipping/signature", get(get_signature_defaults))
+        .route("/settings/shipping/signature", put(update_signature_defaults))
+        .route("/settings/shipping/label-format", get(get_label_format))
+        .route("/settings/shipping/label-format", put(update_label_format))
+        .route("/settings/shipping/fallback", get(get_fallback_rates))
+        .route("/settings/shipping/fallback", put(update_fallback_rates))
+        .route("/settings/shipping/rules", get(list_shipping_rules))
+        .route("/settings/shipping/rules", post(create_shipping_rule))
+        .route("/settings/shipping/rules/{id}", put(update_shipping_rule))
+        .route("/settings/shipping/rules/{id}", delete(delete_shipping_rule))
+        .route("/settings/shipping/boxes", get(list_box_presets))
+        .route("/settings/shipping/boxes", post(create_box_preset))
+        .route("/settings/shipping/boxes/{id}", put(update_box_preset))
+        .route("/settings/shipping/boxes/{id}", delete(delete_box_preset))
+        .route("/settings/gift-wrap", get(get_gift_wrap_settings))
+        .route("/settings/gift-wrap", put(update_gift_wrap_settings))
+        .route("/settings/free-shipping", get(get_free_shipping_settings))
+        .route("/settings/free-shipping", put(update_free_shipping_settings))
+        .route("/settings/minimum-order", get(get_minimum_order_settings))
+        .route("/settings/minimum-order", put(update_minimum_order_settings))
+        .route("/settings/retention", get(get_retention_settings))
+        .route("/settings/retention", put(update_retention_settings))
+        .route("/settings/retention/run", post(run_retention_now))
+        .route("/settings/cart-expiry", get(get_cart_expiry_settings))
+        .route("/settings/cart-expiry", put(update_cart_expiry_settings))
+        .route("/settings/cart-expiry/run", post(run_cart_cleanup_now))
+        .route("/settings/restock-alerts", get(get_restock_alert_settings))
+        .route("/settings/restock-alerts", put(update_restock_alert_settings))
+        .route("/settings/handling-time", get(get_handling_time_settings))
+        .route("/settings/handling-time", put(update_handling_time_settings))
+        .route("/settings/order-alerts", get(get_order_alerts))
+        .route("/settings/order-alerts", put(update_order_alerts))
+        .route("/settings/order-bcc", get(get_order_email_bcc))
+        .route("/settings/order-bcc", put(update_order_email_bcc))
+        .route("/settings/weekly-digest", get(get_weekly_digest))
+        .route("/settings/weekly-digest", put(update_weekly_digest))
+        .route("/settings/branding", get(get_email_branding))
+        .route("/settings/branding", put(update_email_branding))
+        .route("/settings/win-back", get(get_win_back))
+        .route("/settings/win-back", put(update_win_back))
+        .route("/settings/win-back/run", post(run_win_back_now))
+        .route("/settings/win-back/sent", get(list_win_back_emails))
+}
+
+/// Image uploads, kept apart so they get the larger upload body limit
+pub fn upload_routes() -> Router<AppState> {
+    Router::new()
+        .route("/settings/artist/image", put(upload_artist_image))
+        .route("/settings/favicon", put(upload_favicon))
 }
 
 async fn get_artist_info(State(state): State<AppState>) -> AppResult<Json<ArtistInfo>> {
@@ -165,3 +225,688 @@ async fn update_unit_system(
     Setting::set(&conn, "shipping_unit_system", &payload.unit_system).await?;
     Ok(Json(serde_json::json!({"success": true})))
 }
+
+async fn get_rate_filter(State(state): State<AppState>) -> AppResult<Json<RateFilter>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let filter = Setting::get_rate_filter(&conn).await?;
+    Ok(Json(filter))
+}
+
+/// Limit the carriers and service levels offered at checkout (e.g. USPS only, no overnight)
+async fn update_rate_filter(
+    State(state): State<AppState>,
+    Json(payload): Json<RateFilter>,
+) -> AppResult<Json<RateFilter>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+
+    Setting::set(&conn, "shipping_enabled_carriers", &payload.enabled_carriers.join(",")).await?;
+    Setting::set(&conn, "shipping_disabled_services", &payload.disabled_services.join(",")).await?;
+
+    let filter = Setting::get_rate_filter(&conn).await?;
+    Ok(Json(filter))
+}
+
+async fn get_local_delivery(State(state): State<AppState>) -> AppResult<Json<LocalDelivery>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let local_delivery = Setting::get_local_delivery(&conn).await?;
+    Ok(Json(local_delivery))
+}
+
+/// Configure the zip codes the shop delivers to itself and what it charges
+async fn update_local_delivery(
+    State(state): State<AppState>,
+    Json(payload): Json<LocalDelivery>,
+) -> AppResult<Json<LocalDelivery>> {
+    if payload.fee_cents < 0 {
+        return Err(AppError::BadRequest("Delivery fee cannot be negative".to_string()));
+    }
+    if payload.estimated_days.map(|d| d < 0).unwrap_or(false) {
+        return Err(AppError::BadRequest("Estimated days cannot be negative".to_string()));
+    }
+    if payload.enabled && payload.zip_prefixes.iter().all(|z| z.trim().is_empty()) {
+        return Err(AppError::BadRequest("Add at least one zip code to enable local delivery".to_string()));
+    }
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+
+    Setting::set(&conn, "local_delivery_enabled", if payload.enabled { "true" } else { "false" }).await?;
+    Setting::set(&conn, "local_delivery_zips", &payload.zip_prefixes.join(",")).await?;
+    Setting::set(&conn, "local_delivery_fee_cents", &payload.fee_cents.to_string()).await?;
+    Setting::set(
+        &conn,
+        "local_delivery_days",
+        &payload.estimated_days.map(|d| d.to_string()).unwrap_or_default(),
+    )
+    .await?;
+
+    let local_delivery = Setting::get_local_delivery(&conn).await?;
+    Ok(Json(local_delivery))
+}
+
+async fn get_signature_defaults(State(state): State<AppState>) -> AppResult<Json<SignatureDefaults>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let defaults = Setting::get_signature_defaults(&conn).await?;
+    Ok(Json(defaults))
+}
+
+/// Require a delivery signature by default on labels for orders at or above a value
+async fn update_signature_defaults(
+    State(state): State<AppState>,
+    Json(payload): Json<SignatureDefaults>,
+) -> AppResult<Json<SignatureDefaults>> {
+    if !SIGNATURE_TYPES.contains(&payload.signature.as_str()) {
+        return Err(AppError::BadRequest(format!(
+            "Signature must be one of: {}",
+            SIGNATURE_TYPES.join(", ")
+        )));
+    }
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+
+    let threshold = payload
+        .threshold_cents
+        .filter(|cents| *cents > 0)
+        .map(|cents| cents.to_string())
+        .unwrap_or_default();
+    Setting::set(&conn, "signature_threshold_cents", &threshold).await?;
+    Setting::set(&conn, "signature_default", &payload.signature).await?;
+
+    let defaults = Setting::get_signature_defaults(&conn).await?;
+    Ok(Json(defaults))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct LabelFormatSettings {
+    /// One of LABEL_FILE_TYPES
+    pub label_file_type: String,
+}
+
+async fn get_label_format(State(state): State<AppState>) -> AppResult<Json<LabelFormatSettings>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let label_file_type = Setting::get_label_file_type(&conn).await?;
+    Ok(Json(LabelFormatSettings { label_file_type }))
+}
+
+/// Format new labels are bought in, e.g. ZPL for a thermal printer
+async fn update_label_format(
+    State(state): State<AppState>,
+    Json(payload): Json<LabelFormatSettings>,
+) -> AppResult<Json<LabelFormatSettings>> {
+    if !LABEL_FILE_TYPES.contains(&payload.label_file_type.as_str()) {
+        return Err(AppError::BadRequest(format!(
+            "Label format must be one of: {}",
+            LABEL_FILE_TYPES.join(", ")
+        )));
+    }
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+    Setting::set(&conn, "label_file_type", &payload.label_file_type).await?;
+    Ok(Json(payload))
+}
+
+async fn get_fallback_rates(State(state): State<AppState>) -> AppResult<Json<FallbackRates>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let fallback = Setting::get_fallback_rates(&conn).await?;
+    Ok(Json(fallback))
+}
+
+/// Weight-tiered flat rates offered at checkout when Shippo can't be reached
+async fn update_fallback_rates(
+    State(state): State<AppState>,
+    Json(payload): Json<FallbackRates>,
+) -> AppResult<Json<FallbackRates>> {
+    if payload.weight_tiers.iter().any(|t| t.max_grams <= 0 || t.cents < 0) {
+        return Err(AppError::BadRequest(
+            "Weight tiers need a positive max_grams and a price of 0 or more".to_string(),
+        ));
+    }
+    if payload.estimated_days.map(|d| d < 0).unwrap_or(false) {
+        return Err(AppError::BadRequest("Estimated days cannot be negative".to_string()));
+    }
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+
+    let tiers = serde_json::to_string(&payload.weight_tiers).map_err(|e| AppError::Internal(e.to_string()))?;
+    Setting::set(&conn, "fallback_rate_tiers", &tiers).await?;
+    Setting::set(
+        &conn,
+        "fallback_rate_days",
+        &payload.estimated_days.map(|d| d.to_string()).unwrap_or_default(),
+    )
+    .await?;
+
+    let fallback = Setting::get_fallback_rates(&conn).await?;
+    Ok(Json(fallback))
+}
+
+// ============ SHIPPING RULES ============
+// Shop-defined zone rates, offered instead of Shippo rates when one matches
+
+fn validate_shipping_rule(payload: &SaveShippingRule) -> AppResult<()> {
+    if payload.carrier.trim().is_empty() || payload.service.trim().is_empty() {
+        return Err(AppError::BadRequest("Carrier and service are required".to_string()));
+    }
+
+    if !RATE_TYPES.contains(&payload.rate_type.as_str()) {
+        return Err(AppError::BadRequest(format!(
+            "Rate type must be one of: {}",
+            RATE_TYPES.join(", ")
+        )));
+    }
+
+    match payload.rate_type.as_str() {
+        "flat" => match payload.flat_cents {
+            Some(cents) if cents >= 0 => {}
+            _ => return Err(AppError::BadRequest("Flat rates need a flat_cents of 0 or more".to_string())),
+        },
+        _ => {
+            if payload.weight_tiers.is_empty() {
+                return Err(AppError::BadRequest("Weight rates need at least one tier".to_string()));
+            }
+            if payload.weight_tiers.iter().any(|t| t.max_grams <= 0 || t.cents < 0) {
+                return Err(AppError::BadRequest(
+                    "Weight tiers need a positive max_grams and a price of 0 or more".to_string(),
+                ));
+            }
+        }
+    }
+
+    if payload.estimated_days.map(|d| d < 0).unwrap_or(false) {
+        return Err(AppError::BadRequest("Estimated days cannot be negative".to_string()));
+    }
+
+    Ok(())
+}
+
+async fn list_shipping_rules(State(state): State<AppState>) -> AppResult<Json<Vec<ShippingRule>>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let rules = ShippingRule::list_all(&conn).await?;
+    Ok(Json(rules))
+}
+
+async fn create_shipping_rule(
+    State(state): State<AppState>,
+    Json(payload): Json<SaveShippingRule>,
+) -> AppResult<Json<ShippingRule>> {
+    validate_shipping_rule(&payload)?;
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let rule = ShippingRule::create(&conn, payload).await?;
+    tracing::info!("Created shipping rule {} ({} {})", rule.id, rule.carrier, rule.service);
+    Ok(Json(rule))
+}
+
+async fn update_shipping_rule(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(payload): Json<SaveShippingRule>,
+) -> AppResult<Json<ShippingRule>> {
+    validate_shipping_rule(&payload)?;
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let rule = ShippingRule::update(&conn, &id, payload).await?;
+    Ok(Json(rule))
+}
+
+async fn delete_shipping_rule(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> AppResult<Json<serde_json::Value>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+
+    if !ShippingRule::delete(&conn, &id).await? {
+        return Err(AppError::NotFound("Shipping rule not found".to_string()));
+    }
+
+    Ok(Json(serde_json::json!({"success": true})))
+}
+
+// ============ BOX PRESETS ============
+
+fn validate_box_preset(payload: &SaveBoxPreset) -> AppResult<()> {
+    if payload.name.trim().is_empty() {
+        return Err(AppError::BadRequest("Box name is required".to_string()));
+    }
+    if payload.length_cm <= 0.0 || payload.width_cm <= 0.0 || payload.height_cm <= 0.0 {
+        return Err(AppError::BadRequest("Box dimensions must be positive".to_string()));
+    }
+    if payload.empty_weight_grams < 0 {
+        return Err(AppError::BadRequest("Empty box weight cannot be negative".to_string()));
+    }
+
+    Ok(())
+}
+
+async fn list_box_presets(State(state): State<AppState>) -> AppResult<Json<Vec<BoxPreset>>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let boxes = BoxPreset::list_all(&conn).await?;
+    Ok(Json(boxes))
+}
+
+async fn create_box_preset(
+    State(state): State<AppState>,
+    Json(payload): Json<SaveBoxPreset>,
+) -> AppResult<Json<BoxPreset>> {
+    validate_box_preset(&payload)?;
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let preset = BoxPreset::create(&conn, payload).await?;
+    tracing::info!("Created box preset {} ({})", preset.id, preset.name);
+    Ok(Json(preset))
+}
+
+async fn update_box_preset(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(payload): Json<SaveBoxPreset>,
+) -> AppResult<Json<BoxPreset>> {
+    validate_box_preset(&payload)?;
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let preset = BoxPreset::update(&conn, &id, payload).await?;
+    Ok(Json(preset))
+}
+
+async fn delete_box_preset(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> AppResult<Json<serde_json::Value>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+
+    if !BoxPreset::delete(&conn, &id).await? {
+        return Err(AppError::NotFound("Box preset not found".to_string()));
+    }
+
+    Ok(Json(serde_json::json!({"success": true})))
+}
+
+// ============ GIFT WRAP SETTINGS ============
+
+#[derive(Serialize, Deserialize)]
+pub struct GiftWrapSettings {
+    pub fee_cents: i32,
+}
+
+async fn get_gift_wrap_settings(State(state): State<AppState>) -> AppResult<Json<GiftWrapSettings>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let fee_cents = Setting::get_gift_wrap_fee_cents(&conn).await?;
+    Ok(Json(GiftWrapSettings { fee_cents }))
+}
+
+async fn update_gift_wrap_settings(
+    State(state): State<AppState>,
+    Json(payload): Json<GiftWrapSettings>,
+) -> AppResult<Json<GiftWrapSettings>> {
+    if payload.fee_cents < 0 {
+        return Err(AppError::BadRequest("Gift wrap fee cannot be negative".to_string()));
+    }
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+    Setting::set(&conn, "gift_wrap_fee_cents", &payload.fee_cents.to_string()).await?;
+    Ok(Json(payload))
+}
+
+// ============ FREE SHIPPING SETTINGS ============
+
+#[derive(Serialize, Deserialize)]
+pub struct FreeShippingSettings {
+    /// Subtotal that qualifies for free shipping (None disables it)
+    pub threshold_cents: Option<i32>,
+}
+
+async fn get_free_shipping_settings(State(state): State<AppState>) -> AppResult<Json<FreeShippingSettings>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let threshold_cents = Setting::get_free_shipping_threshold_cents(&conn).await?;
+    Ok(Json(FreeShippingSettings { threshold_cents }))
+}
+
+async fn update_free_shipping_settings(
+    State(state): State<AppState>,
+    Json(payload): Json<FreeShippingSettings>,
+) -> AppResult<Json<FreeShippingSettings>> {
+    if payload.threshold_cents.map(|c| c < 0).unwrap_or(false) {
+        return Err(AppError::BadRequest("Free shipping threshold cannot be negative".to_string()));
+    }
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let value = payload.threshold_cents.unwrap_or(0).to_string();
+    Setting::set(&conn, "free_shipping_threshold_cents", &value).await?;
+
+    let threshold_cents = Setting::get_free_shipping_threshold_cents(&conn).await?;
+    Ok(Json(FreeShippingSettings { threshold_cents }))
+}
+
+// ============ MINIMUM ORDER SETTINGS ============
+
+#[derive(Serialize, Deserialize)]
+pub struct MinimumOrderSettings {
+    /// Smallest merchandise subtotal accepted at checkout (None disables it)
+    pub minimum_cents: Option<i32>,
+}
+
+async fn get_minimum_order_settings(State(state): State<AppState>) -> AppResult<Json<MinimumOrderSettings>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let minimum_cents = Setting::get_minimum_order_cents(&conn).await?;
+    Ok(Json(MinimumOrderSettings { minimum_cents }))
+}
+
+async fn update_minimum_order_settings(
+    State(state): State<AppState>,
+    Json(payload): Json<MinimumOrderSettings>,
+) -> AppResult<Json<MinimumOrderSettings>> {
+    if payload.minimum_cents.map(|c| c < 0).unwrap_or(false) {
+        return Err(AppError::BadRequest("Minimum order cannot be negative".to_string()));
+    }
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let value = payload.minimum_cents.unwrap_or(0).to_string();
+    Setting::set(&conn, "minimum_order_cents", &value).await?;
+
+    let minimum_cents = Setting::get_minimum_order_cents(&conn).await?;
+    Ok(Json(MinimumOrderSettings { minimum_cents }))
+}
+
+// ============ RETENTION SETTINGS ============
+
+#[derive(Serialize, Deserialize)]
+pub struct RetentionSettings {
+    /// Orders older than this are archived and anonymized (0 disables)
+    pub order_retention_years: i64,
+}
+
+async fn get_retention_settings(State(state): State<AppState>) -> AppResult<Json<RetentionSettings>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let order_retention_years = Setting::get_order_retention_years(&conn).await?;
+    Ok(Json(RetentionSettings { order_retention_years }))
+}
+
+async fn update_retention_settings(
+    State(state): State<AppState>,
+    Json(payload): Json<RetentionSettings>,
+) -> AppResult<Json<RetentionSettings>> {
+    if payload.order_retention_years < 0 {
+        return Err(AppError::BadRequest("Retention period cannot be negative".to_string()));
+    }
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+    Setting::set(&conn, "order_retention_years", &payload.order_retention_years.to_string()).await?;
+    Ok(Json(payload))
+}
+
+async fn run_retention_now(State(state): State<AppState>) -> AppResult<Json<serde_json::Value>> {
+    let archived = run_retention(&state.db).await?;
+    Ok(Json(serde_json::json!({"archived": archived})))
+}
+
+// ============ CART EXPIRY SETTINGS ============
+
+#[derive(Serialize, Deserialize)]
+pub struct CartExpirySettings {
+    /// Unpaid orders holding stock are cancelled after this many hours (0 disables)
+    pub cart_ttl_hours: i64,
+}
+
+async fn get_cart_expiry_settings(State(state): State<AppState>) -> AppResult<Json<CartExpirySettings>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let cart_ttl_hours = Setting::get_cart_ttl_hours(&conn).await?;
+    Ok(Json(CartExpirySettings { cart_ttl_hours }))
+}
+
+async fn update_cart_expiry_settings(
+    State(state): State<AppState>,
+    Json(payload): Json<CartExpirySettings>,
+) -> AppResult<Json<CartExpirySettings>> {
+    if payload.cart_ttl_hours < 0 {
+        return Err(AppError::BadRequest("Cart expiry cannot be negative".to_string()));
+    }
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+    Setting::set(&conn, "cart_ttl_hours", &payload.cart_ttl_hours.to_string()).await?;
+    Ok(Json(payload))
+}
+
+async fn run_cart_cleanup_now(State(state): State<AppState>) -> AppResult<Json<serde_json::Value>> {
+    let cancelled = run_cart_cleanup(&state).await?;
+    Ok(Json(serde_json::json!({"cancelled": cancelled})))
+}
+
+// ============ RESTOCK ALERT SETTINGS ============
+
+#[derive(Serialize, Deserialize)]
+pub struct RestockAlertSettings {
+    /// An address gets at most one restock alert per product in this many hours (0 disables)
+    pub restock_alert_window_hours: i64,
+}
+
+async fn get_restock_alert_settings(State(state): State<AppState>) -> AppResult<Json<RestockAlertSettings>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let restock_alert_window_hours = Setting::get_restock_alert_window_hours(&conn).await?;
+    Ok(Json(RestockAlertSettings { restock_alert_window_hours }))
+}
+
+async fn update_restock_alert_settings(
+    State(state): State<AppState>,
+    Json(payload): Json<RestockAlertSettings>,
+) -> AppResult<Json<RestockAlertSettings>> {
+    if payload.restock_alert_window_hours < 0 {
+        return Err(AppError::BadRequest("Restock alert window cannot be negative".to_string()));
+    }
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+    Setting::set(&conn, "restock_alert_window_hours", &payload.restock_alert_window_hours.to_string()).await?;
+    Ok(Json(payload))
+}
+
+// ============ HANDLING TIME SETTINGS ============
+
+#[derive(Serialize, Deserialize)]
+pub struct HandlingTimeSettings {
+    /// Days added before carrier transit time in delivery estimates
+    pub handling_days: i64,
+}
+
+async fn get_handling_time_settings(State(state): State<AppState>) -> AppResult<Json<HandlingTimeSettings>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let handling_days = Setting::get_handling_days(&conn).await?;
+    Ok(Json(HandlingTimeSettings { handling_days }))
+}
+
+async fn update_handling_time_settings(
+    State(state): State<AppState>,
+    Json(payload): Json<HandlingTimeSettings>,
+) -> AppResult<Json<HandlingTimeSettings>> {
+    if payload.handling_days < 0 {
+        return Err(AppError::BadRequest("Handling time cannot be negative".to_string()));
+    }
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+    Setting::set(&conn, "handling_days", &payload.handling_days.to_string()).await?;
+    Ok(Json(payload))
+}
+
+// ============ ORDER ALERT SETTINGS ============
+
+async fn get_order_alerts(State(state): State<AppState>) -> AppResult<Json<OrderAlerts>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let alerts = Setting::get_order_alerts(&conn).await?;
+    Ok(Json(alerts))
+}
+
+async fn update_order_alerts(
+    State(state): State<AppState>,
+    Json(payload): Json<OrderAlerts>,
+) -> AppResult<Json<OrderAlerts>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    Setting::set(&conn, "order_alerts_enabled", &payload.enabled.to_string()).await?;
+    Setting::set(&conn, "order_alerts_pick_list", &payload.include_pick_list.to_string()).await?;
+    Ok(Json(payload))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct OrderEmailBcc {
+    /// Copied on order confirmation, shipping, delivery, refund and payment emails; None turns it off
+    pub email: Option<String>,
+}
+
+async fn get_order_email_bcc(State(state): State<AppState>) -> AppResult<Json<OrderEmailBcc>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let email = Setting::get_order_email_bcc(&conn).await?;
+    Ok(Json(OrderEmailBcc { email }))
+}
+
+async fn update_order_email_bcc(
+    State(state): State<AppState>,
+    Json(payload): Json<OrderEmailBcc>,
+) -> AppResult<Json<OrderEmailBcc>> {
+    let email = payload.email.map(|e| e.trim().to_string()).filter(|e| !e.is_empty());
+    if let Some(ref email) = email {
+        if !email.contains('@') || email.len() < 5 {
+            return Err(AppError::BadRequest("Invalid BCC email address".to_string()));
+        }
+    }
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+    Setting::set(&conn, "order_email_bcc", email.as_deref().unwrap_or("")).await?;
+    Ok(Json(OrderEmailBcc { email }))
+}
+
+// ============ WEEKLY DIGEST SETTINGS ============
+
+async fn get_weekly_digest(State(state): State<AppState>) -> AppResult<Json<WeeklyDigest>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let digest = Setting::get_weekly_digest(&conn).await?;
+    Ok(Json(digest))
+}
+
+async fn update_weekly_digest(
+    State(state): State<AppState>,
+    Json(payload): Json<WeeklyDigest>,
+) -> AppResult<Json<WeeklyDigest>> {
+    let send_day = payload.send_day.trim().to_lowercase();
+    if !DIGEST_DAYS.contains(&send_day.as_str()) {
+        return Err(AppError::BadRequest(format!(
+            "Send day must be one of: {}",
+            DIGEST_DAYS.join(", ")
+        )));
+    }
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+    Setting::set(&conn, "weekly_digest_enabled", &payload.enabled.to_string()).await?;
+    Setting::set(&conn, "weekly_digest_day", &send_day).await?;
+    Ok(Json(WeeklyDigest {
+        enabled: payload.enabled,
+        send_day,
+    }))
+}
+
+// ============ WIN-BACK SETTINGS ============
+
+/// Most recent win-back sends listed in the admin
+const WIN_BACK_LIST_LIMIT: i64 = 100;
+
+async fn get_win_back(State(state): State<AppState>) -> AppResult<Json<WinBack>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let win_back = Setting::get_win_back(&conn).await?;
+    Ok(Json(win_back))
+}
+
+async fn update_win_back(
+    State(state): State<AppState>,
+    Json(payload): Json<WinBack>,
+) -> AppResult<Json<WinBack>> {
+    if payload.inactive_months < 1 {
+        return Err(AppError::BadRequest("Inactive months must be at least 1".to_string()));
+    }
+    if let Some(percent) = payload.discount_percent {
+        if !(1..=100).contains(&percent) {
+            return Err(AppError::BadRequest("Discount percent must be between 1 and 100".to_string()));
+        }
+    }
+    if payload.discount_valid_days < 1 {
+        return Err(AppError::BadRequest("Discount must be valid for at least 1 day".to_string()));
+    }
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+    Setting::set(&conn, "win_back_enabled", &payload.enabled.to_string()).await?;
+    Setting::set(&conn, "win_back_inactive_months", &payload.inactive_months.to_string()).await?;
+    Setting::set(
+        &conn,
+        "win_back_discount_percent",
+        &payload.discount_percent.map(|p| p.to_string()).unwrap_or_default(),
+    )
+    .await?;
+    Setting::set(&conn, "win_back_discount_valid_days", &payload.discount_valid_days.to_string()).await?;
+    Ok(Json(payload))
+}
+
+async fn run_win_back_now(State(state): State<AppState>) -> AppResult<Json<serde_json::Value>> {
+    let sent = run_win_back(&state).await?;
+    Ok(Json(serde_json::json!({"sent": sent})))
+}
+
+async fn list_win_back_emails(State(state): State<AppState>) -> AppResult<Json<Vec<WinBackEmail>>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let emails = WinBackEmail::list_recent(&conn, WIN_BACK_LIST_LIMIT).await?;
+    Ok(Json(emails))
+}
+
+// ============ EMAIL BRANDING SETTINGS ============
+
+/// Most social links shown in an email footer
+const MAX_SOCIAL_LINKS: usize = 8;
+
+fn is_web_url(url: &str) -> bool {
+    url.starts_with("https://") || url.starts_with("http://")
+}
+
+async fn get_email_branding(State(state): State<AppState>) -> AppResult<Json<EmailBranding>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let branding = Setting::get_email_branding(&conn).await?;
+    Ok(Json(branding))
+}
+
+async fn update_email_branding(
+    State(state): State<AppState>,
+    Json(mut payload): Json<EmailBranding>,
+) -> AppResult<Json<EmailBranding>> {
+    for color in [&payload.accent_color, &payload.text_color, &payload.background_color] {
+        if !is_hex_color(color) {
+            return Err(AppError::BadRequest(format!("Invalid color {}; use #RRGGBB", color)));
+        }
+    }
+
+    payload.logo_url = payload.logo_url.map(|u| u.trim().to_string()).filter(|u| !u.is_empty());
+    if payload.logo_url.as_deref().is_some_and(|u| !is_web_url(u)) {
+        return Err(AppError::BadRequest("Logo URL must start with http:// or https://".to_string()));
+    }
+
+    payload.footer_text = payload.footer_text.map(|t| t.trim().to_string()).filter(|t| !t.is_empty());
+
+    if payload.social_links.len() > MAX_SOCIAL_LINKS {
+        return Err(AppError::BadRequest(format!("At most {} social links", MAX_SOCIAL_LINKS)));
+    }
+    for link in &mut payload.social_links {
+        link.label = link.label.trim().to_string();
+        link.url = link.url.trim().to_string();
+        if link.label.is_empty() {
+            return Err(AppError::BadRequest("Social links need a label".to_string()));
+        }
+        if !is_web_url(&link.url) {
+            return Err(AppError::BadRequest(format!(
+                "Social link URL for {} must start with http:// or https://",
+                link.label
+            )));
+        }
+    }
+
+    let social_links = serde_json::to_string(&payload.social_links).map_err(|e| AppError::Internal(e.to_string()))?;
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+    Setting::set(&conn, "email_accent_color", &payload.accent_color).await?;
+    Setting::set(&conn, "email_text_color", &payload.text_color).await?;
+    Setting::set(&conn, "email_background_color", &payload.background_color).await?;
+    Setting::set(&conn, "email_logo_url", payload.logo_url.as_deref().unwrap_or("")).await?;
+    Setting::set(&conn, "email_footer_text", payload.footer_text.as_deref().unwrap_or("")).await?;
+    Setting::set(&conn, "email_social_links", &social_links).await?;
+    Ok(Json(payload))
+}
@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+
+use axum::{extract::State, routing::post, Json, Router};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, AppResult};
+use crate::models::{Order, Setting};
+use crate::routes::AppState;
+use crate::services::shippo::ShippoAddress;
+
+#[derive(Deserialize)]
+pub struct CreateManifestsRequest {
+    /// YYYY-MM-DD (UTC) whose labels to include; defaults to today
+    pub date: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ManifestResult {
+    pub carrier: String,
+    pub carrier_account: String,
+    pub manifest_id: String,
+    pub status: String,
+    /// Scan form PDFs to print for the pickup
+    pub documents: Vec<String>,
+    pub order_ids: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct CreateManifestsResponse {
+    pub date: String,
+    pub manifests: Vec<ManifestResult>,
+    /// Labels whose carrier account couldn't be looked up
+    pub skipped_order_ids: Vec<String>,
+}
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/shipping/manifests", post(create_manifests))
+}
+
+/// End-of-day scan forms: one Shippo manifest per carrier account covering every
+/// label bought that day that hasn't been picked up yet
+async fn create_manifests(
+    State(state): State<AppState>,
+    Json(payload): Json<CreateManifestsRequest>,
+) -> AppResult<Json<CreateManifestsResponse>> {
+    let date = match payload.date.as_deref().map(str::trim).filter(|d| !d.is_empty()) {
+        Some(date) => chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map_err(|_| AppError::BadRequest("Date must be YYYY-MM-DD".to_string()))?,
+        None => chrono::Utc::now().date_naive(),
+    };
+    let day_start = date
+        .and_hms_opt(0, 0, 0)
+        .map(|dt| dt.and_utc().timestamp())
+        .ok_or_else(|| AppError::BadRequest("Invalid date".to_string()))?;
+    let day_end = day_start + 24 * 60 * 60;
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+
+    let shop_address = Setting::get_shop_address(&conn)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("Shop address not configured".to_string()))?;
+    let from_address = ShippoAddress {
+        name: shop_address.name,
+        street1: shop_address.street1,
+        street2: shop_address.street2,
+        city: shop_address.city,
+        state: shop_address.state,
+        zip: shop_address.zip,
+        country: shop_address.country,
+        phone: shop_address.phone,
+    };
+
+    // Group the day's labels by the carrier account they were bought on
+    let mut by_account: HashMap<String, (String, Vec<(String, String)>)> = HashMap::new();
+    let mut skipped_order_ids = Vec::new();
+
+    for order in Order::list_awaiting_pickup(&conn).await? {
+        let label = match order.get_label_rate() {
+            Some(label) if (day_start..day_end).contains(&label.recorded_ts) => label,
+            _ => continue,
+        };
+        let transaction_id = match order.shippo_transaction_id.clone() {
+            Some(id) => id,
+            None => continue,
+        };
+
+        let rate = match label.rate_id.as_deref() {
+            Some(rate_id) => state.shippo.get_rate(rate_id).await.ok(),
+            None => None,
+        };
+        match rate.and_then(|r| r.carrier_account.map(|account| (account, r.provider))) {
+            Some((account, carrier)) => {
+                by_account
+                    .entry(account)
+                    .or_insert_with(|| (carrier, Vec::new()))
+                    .1
+                    .push((order.id, transaction_id));
+            }
+            None => {
+                tracing::warn!("No carrier account for the label on order {}", order.id);
+                skipped_order_ids.push(order.id);
+            }
+        }
+    }
+
+    if by_account.is_empty() {
+        return Err(AppError::BadRequest(format!("No labels awaiting pickup were bought on {}", date)));
+    }
+
+    let shipment_date = format!("{}T00:00:00Z", date.format("%Y-%m-%d"));
+    let mut manifests = Vec::with_capacity(by_account.len());
+
+    for (carrier_account, (carrier, labels)) in by_account {
+        let (order_ids, transaction_ids): (Vec<String>, Vec<String>) = labels.into_iter().unzip();
+        let manifest = state
+            .shippo
+            .create_manifest(&carrier_account, &shipment_date, from_address.clone(), transaction_ids)
+            .await?;
+
+        tracing::info!(
+            "Created {} manifest {} for {} labels ({})",
+            carrier,
+            manifest.object_id,
+            order_ids.len(),
+            manifest.status
+        );
+
+        manifests.push(ManifestResult {
+            carrier,
+            carrier_account,
+            manifest_id: manifest.object_id,
+            status: manifest.status,
+            documents: manifest.documents,
+            order_ids,
+        });
+    }
+
+    Ok(Json(CreateManifestsResponse {
+        date: date.format("%Y-%m-%d").to_string(),
+        manifests,
+        skipped_order_ids,
+    }))
+}
@@ -0,0 +1,223 @@
+use axum::{
+    extract::State,
+    http::HeaderMap,
+    routing::{delete, get, post},
+    Extension, Json, Router,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, AppResult};
+use crate::middleware::auth::session_token;
+use crate::middleware::AuthUser;
+use crate::models::{AdminTotp, StepUpToken};
+use crate::routes::AppState;
+use crate::services::totp;
+
+/// A sign-in at most this old counts as re-authentication when the user has
+/// no authenticator app set up
+const MAX_REAUTH_AGE_MINUTES: i64 = 10;
+
+/// Issuer shown in authenticator apps
+const TOTP_ISSUER: &str = "Caterpillar Clay Admin";
+
+/// Wrong authenticator codes allowed per account before code checks pause
+const MAX_CODE_FAILURES: u32 = 5;
+
+/// Window wrong codes are counted over, and how long checks pause once it's used up
+const CODE_FAILURE_WINDOW_SECS: u64 = 5 * 60;
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/step-up", get(get_status))
+        .route("/step-up", post(step_up))
+        .route("/step-up/totp/setup", post(setup_totp))
+        .route("/step-up/totp/enable", post(enable_totp))
+        .route("/step-up/totp", delete(remove_totp))
+}
+
+#[derive(Serialize)]
+pub struct StepUpStatus {
+    pub totp_enabled: bool,
+}
+
+#[derive(Deserialize)]
+pub struct CodeRequest {
+    /// Six-digit code from the authenticator app
+    pub code: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct StepUpResponse {
+    /// Send as X-Step-Up-Token on refunds, product deletion and settings changes
+    pub token: String,
+    pub expires_ts: i64,
+}
+
+#[derive(Serialize)]
+pub struct TotpSetupResponse {
+    /// Base32 secret for manual entry
+    pub secret: String,
+    /// otpauth:// URL for a QR code
+    pub otpauth_url: String,
+}
+
+/// The signed-in staff member. Step-up is per person, so API keys and
+/// disabled admin auth can't use it.
+fn staff_user(user: Option<Extension<AuthUser>>) -> AppResult<AuthUser> {
+    match user {
+        Some(Extension(user)) if user.api_key_scopes.is_none() && !user.id.is_empty() => Ok(user),
+        Some(_) => Err(AppError::Forbidden("API keys can't confirm actions".to_string())),
+        None => Err(AppError::BadRequest("Admin auth is disabled".to_string())),
+    }
+}
+
+fn now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
+
+/// Check a code against the user's authenticator secret and use it up. A few
+/// wrong codes in a row pause checking, so codes can't be guessed.
+async fn check_code(
+    state: &AppState,
+    conn: &libsql::Connection,
+    totp: &AdminTotp,
+    code: Option<&str>,
+) -> AppResult<()> {
+    let code = code
+        .filter(|c| !c.trim().is_empty())
+        .ok_or_else(|| AppError::BadRequest("Enter the code from your authenticator app".to_string()))?;
+
+    let key = format!("step-up-code:{}", totp.user_id);
+    let limiter_error = |e| AppError::Internal(format!("Failed to check authenticator code attempts: {}", e));
+    if state.rate_limiter.is_locked(&key).await.map_err(limiter_error)? {
+        return Err(AppError::TooManyRequests(
+            "Too many wrong codes, wait a few minutes and try again".to_string(),
+        ));
+    }
+
+    let step = match totp::verify(&totp.secret, code, now()) {
+        Some(step) => step,
+        None => {
+            let failures = state.rate_limiter.record_event(&key, CODE_FAILURE_WINDOW_SECS).await.map_err(limiter_error)?;
+            if failures >= MAX_CODE_FAILURES {
+                state.rate_limiter.lock(&key, CODE_FAILURE_WINDOW_SECS).await.map_err(limiter_error)?;
+                tracing::warn!("Paused authenticator code checks for {} after {} wrong codes", totp.user_id, failures);
+            }
+            return Err(AppError::Forbidden("Invalid code".to_string()));
+        }
+    };
+    if step <= totp.last_step || !AdminTotp::accept_step(conn, &totp.user_id, step).await? {
+        return Err(AppError::Forbidden("That code was already used, wait for the next one".to_string()));
+    }
+    Ok(())
+}
+
+async fn get_status(
+    State(state): State<AppState>,
+    user: Option<Extension<AuthUser>>,
+) -> AppResult<Json<StepUpStatus>> {
+    let user = staff_user(user)?;
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let totp = AdminTotp::find_by_user(&conn, &user.id).await?;
+
+    Ok(Json(StepUpStatus {
+        totp_enabled: totp.is_some_and(|t| t.is_enabled()),
+    }))
+}
+
+/// Start an elevated session. With an authenticator app set up this takes a
+/// code; otherwise the Clerk session must come from a sign-in within the last
+/// few minutes (Clerk's factor verification age).
+async fn step_up(
+    State(state): State<AppState>,
+    user: Option<Extension<AuthUser>>,
+    headers: HeaderMap,
+    Json(payload): Json<CodeRequest>,
+) -> AppResult<Json<StepUpResponse>> {
+    let user = staff_user(user)?;
+    let conn = state.db.connect().map_err(AppError::from)?;
+
+    match AdminTotp::find_by_user(&conn, &user.id).await?.filter(|t| t.is_enabled()) {
+        Some(totp) => check_code(&state, &conn, &totp, payload.code.as_deref()).await?,
+        None => {
+            let token = session_token(&headers)
+                .ok_or_else(|| AppError::Unauthorized("Missing authorization".to_string()))?;
+            let claims = state.auth.verify_token(token).await?;
+            match claims.first_factor_age_mins {
+                Some(age) if (0..=MAX_REAUTH_AGE_MINUTES).contains(&age) => {}
+                _ => {
+                    return Err(AppError::Forbidden(
+                        "Sign in again, or set up an authenticator app, to confirm this action".to_string(),
+                    ))
+                }
+            }
+        }
+    }
+
+    let (token, expires_ts) = StepUpToken::issue(&conn, &user.id).await?;
+    tracing::info!("Elevated admin session started for {}", user.email);
+    Ok(Json(StepUpResponse { token, expires_ts }))
+}
+
+/// Generate a new authenticator secret. It only takes effect once a code from
+/// it is confirmed with /step-up/totp/enable.
+async fn setup_totp(
+    State(state): State<AppState>,
+    user: Option<Extension<AuthUser>>,
+) -> AppResult<Json<TotpSetupResponse>> {
+    let user = staff_user(user)?;
+    let conn = state.db.connect().map_err(AppError::from)?;
+
+    if AdminTotp::find_by_user(&conn, &user.id).await?.is_some_and(|t| t.is_enabled()) {
+        return Err(AppError::BadRequest(
+            "An authenticator app is already set up; remove it first".to_string(),
+        ));
+    }
+
+    let secret = totp::generate_secret();
+    AdminTotp::start_setup(&conn, &user.id, &secret).await?;
+
+    Ok(Json(TotpSetupResponse {
+        otpauth_url: totp::otpauth_url(&secret, &user.email, TOTP_ISSUER),
+        secret,
+    }))
+}
+
+async fn enable_totp(
+    State(state): State<AppState>,
+    user: Option<Extension<AuthUser>>,
+    Json(payload): Json<CodeRequest>,
+) -> AppResult<Json<StepUpStatus>> {
+    let user = staff_user(user)?;
+    let conn = state.db.connect().map_err(AppError::from)?;
+
+    let totp = AdminTotp::find_by_user(&conn, &user.id)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("Start authenticator setup first".to_string()))?;
+    if totp.is_enabled() {
+        return Err(AppError::BadRequest("Authenticator app is already set up".to_string()));
+    }
+    check_code(&state, &conn, &totp, payload.code.as_deref()).await?;
+
+    tracing::info!("Authenticator app enabled for {}", user.email);
+    Ok(Json(StepUpStatus { totp_enabled: true }))
+}
+
+/// Remove the authenticator app. Needs an elevated session like other
+/// destructive actions, and ends every elevated session the user has.
+async fn remove_totp(
+    State(state): State<AppState>,
+    user: Option<Extension<AuthUser>>,
+) -> AppResult<Json<StepUpStatus>> {
+    let user = staff_user(user)?;
+    let conn = state.db.connect().map_err(AppError::from)?;
+
+    AdminTotp::delete(&conn, &user.id).await?;
+    StepUpToken::revoke_all(&conn, &user.id).await?;
+
+    tracing::info!("Authenticator app removed for {}", user.email);
+    Ok(Json(StepUpStatus { totp_enabled: false }))
+}
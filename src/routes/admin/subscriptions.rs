@@ -0,0 +1,15 @@
+use axum::{extract::State, routing::get, Json, Router};
+
+use crate::error::{AppError, AppResult};
+use crate::models::Subscription;
+use crate::routes::AppState;
+
+pub fn routes() -> Router<AppState> {
+    Router::new().route("/subscriptions", get(list_subscriptions))
+}
+
+async fn list_subscriptions(State(state): State<AppState>) -> AppResult<Json<Vec<Subscription>>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let subscriptions = Subscription::list_all(&conn).await?;
+    Ok(Json(subscriptions))
+}
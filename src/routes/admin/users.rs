@@ -0,0 +1,258 @@
+use axum::{
+    extract::{Path, Query, State},
+    routing::{delete, get, post, put},
+    Extension, Json, Router,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, AppResult};
+use crate::middleware::AuthUser;
+use crate::models::user::ROLES;
+use crate::models::{EmailSuppression, ImpersonationToken, Order, User};
+use crate::routes::AppState;
+
+/// Most users listed at once
+const MAX_LISTED_USERS: i64 = 200;
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/users", get(list_users))
+        .route("/users/{id}", get(get_user))
+        .route("/users/{id}/orders", get(list_user_orders))
+        .route("/users/{id}/role", put(set_role))
+        .route("/users/{id}/email-suppression", put(suppress_email))
+        .route("/users/{id}/email-suppression", delete(unsuppress_email))
+        .route("/users/{id}/impersonate", post(impersonate))
+        .route("/users/{id}/impersonate", delete(end_impersonation))
+}
+
+#[derive(Deserialize)]
+pub struct ListUsersQuery {
+    /// Part of the email or name
+    pub q: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct UserListEntry {
+    #[serde(flatten)]
+    pub user: User,
+    /// Paid orders
+    pub order_count: i64,
+    pub total_spent_cents: i64,
+    /// Batch and marketing emails skip this address
+    pub email_suppressed: bool,
+}
+
+#[derive(Serialize)]
+pub struct UserDetail {
+    #[serde(flatten)]
+    pub user: User,
+    pub order_count: i64,
+    pub total_spent_cents: i64,
+    pub suppression: Option<EmailSuppression>,
+}
+
+#[derive(Deserialize)]
+pub struct SetRoleRequest {
+    /// One of ROLES, or null to take staff access away
+    pub role: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct SuppressRequest {
+    /// Why, e.g. "asked not to be emailed"
+    pub detail: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ImpersonateRequest {
+    /// Why, e.g. the support ticket or order being looked into
+    pub reason: String,
+}
+
+#[derive(Serialize)]
+pub struct ImpersonateResponse {
+    /// Send as `Authorization: Bearer ...` to the storefront API (GET only)
+    pub token: String,
+    pub user_id: String,
+    pub expires_ts: i64,
+}
+
+async fn find_user(conn: &libsql::Connection, id: &str) -> AppResult<User> {
+    User::find_by_id(conn, id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))
+}
+
+/// Customers and staff, newest first, with what they've spent
+async fn list_users(
+    State(state): State<AppState>,
+    Query(query): Query<ListUsersQuery>,
+) -> AppResult<Json<Vec<UserListEntry>>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let limit = query.limit.unwrap_or(50).clamp(1, MAX_LISTED_USERS);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    let users = User::search(&conn, query.q.as_deref(), limit, offset).await?;
+    let ids: Vec<String> = users.iter().map(|u| u.id.clone()).collect();
+    let totals = Order::totals_by_user(&conn, &ids).await?;
+    let suppressed = EmailSuppression::emails(&conn).await?;
+
+    let entries = users
+        .into_iter()
+        .map(|user| {
+            let (order_count, total_spent_cents) = totals.get(&user.id).copied().unwrap_or((0, 0));
+            UserListEntry {
+                email_suppressed: suppressed.contains(&user.email.to_lowercase()),
+                order_count,
+                total_spent_cents,
+                user,
+            }
+        })
+        .collect();
+
+    Ok(Json(entries))
+}
+
+async fn get_user(State(state): State<AppState>, Path(id): Path<String>) -> AppResult<Json<UserDetail>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let user = find_user(&conn, &id).await?;
+
+    let totals = Order::totals_by_user(&conn, std::slice::from_ref(&user.id)).await?;
+    let (order_count, total_spent_cents) = totals.get(&user.id).copied().unwrap_or((0, 0));
+    let suppression = EmailSuppression::find(&conn, &user.email).await?;
+
+    Ok(Json(UserDetail {
+        user,
+        order_count,
+        total_spent_cents,
+        suppression,
+    }))
+}
+
+/// Every order the user placed, newest first
+async fn list_user_orders(State(state): State<AppState>, Path(id): Path<String>) -> AppResult<Json<Vec<Order>>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let user = find_user(&conn, &id).await?;
+    Ok(Json(Order::list_by_user(&conn, &user.id).await?))
+}
+
+/// Make a user staff with a role, change their role, or take staff access away.
+/// Needs a signed-in owner: API keys can't hand out access.
+async fn set_role(
+    State(state): State<AppState>,
+    admin: Option<Extension<AuthUser>>,
+    Path(id): Path<String>,
+    Json(payload): Json<SetRoleRequest>,
+) -> AppResult<Json<User>> {
+    if let Some(Extension(ref admin)) = admin {
+        if admin.api_key_scopes.is_some() {
+            return Err(AppError::Forbidden("API keys can't change staff roles".to_string()));
+        }
+        if admin.id == id {
+            return Err(AppError::BadRequest("You can't change your own role".to_string()));
+        }
+    }
+
+    let role = payload.role.as_deref().map(|r| r.trim().to_lowercase());
+    if let Some(ref role) = role {
+        if !ROLES.contains(&role.as_str()) {
+            return Err(AppError::BadRequest(format!("Unknown role '{}'. Use: {}", role, ROLES.join(", "))));
+        }
+    }
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let user = find_user(&conn, &id).await?;
+    if role.is_some() && !user.is_active() {
+        return Err(AppError::BadRequest("Deactivated users can't be given a role".to_string()));
+    }
+    if user.role.as_deref() == Some("owner") && role.as_deref() != Some("owner") && User::count_owners(&conn).await? <= 1 {
+        return Err(AppError::BadRequest("The shop needs at least one owner".to_string()));
+    }
+
+    let updated = User::set_role(&conn, &user.id, role.as_deref()).await?;
+    tracing::info!(
+        "Staff role of {} changed from {:?} to {:?}",
+        updated.email,
+        user.role,
+        updated.role
+    );
+    Ok(Json(updated))
+}
+
+/// Stop batch and marketing emails to the user (newsletters, announcements,
+/// win-back). Order emails still go out.
+async fn suppress_email(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    payload: Option<Json<SuppressRequest>>,
+) -> AppResult<Json<EmailSuppression>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let user = find_user(&conn, &id).await?;
+
+    let detail = payload.and_then(|Json(p)| p.detail).filter(|d| !d.trim().is_empty());
+    EmailSuppression::suppress(&conn, &user.email, "manual", detail.as_deref()).await?;
+
+    EmailSuppression::find(&conn, &user.email)
+        .await?
+        .map(Json)
+        .ok_or_else(|| AppError::Internal("Failed to suppress email".to_string()))
+}
+
+async fn unsuppress_email(State(state): State<AppState>, Path(id): Path<String>) -> AppResult<Json<serde_json::Value>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let user = find_user(&conn, &id).await?;
+
+    if !EmailSuppression::delete(&conn, &user.email).await? {
+        return Err(AppError::NotFound("Suppression not found".to_string()));
+    }
+    Ok(Json(serde_json::json!({"success": true})))
+}
+
+/// Let an owner see the storefront API exactly as a customer does, e.g. to
+/// debug an order. The token is read-only, lasts 15 minutes, and every
+/// request made with it is written to the audit log.
+async fn impersonate(
+    State(state): State<AppState>,
+    admin: Option<Extension<AuthUser>>,
+    Path(id): Path<String>,
+    Json(payload): Json<ImpersonateRequest>,
+) -> AppResult<Json<ImpersonateResponse>> {
+    let owner = match admin {
+        Some(Extension(admin)) if admin.api_key_scopes.is_none() && admin.role.as_deref() == Some("owner") => admin,
+        Some(_) => return Err(AppError::Forbidden("Only signed-in owners can impersonate customers".to_string())),
+        None => return Err(AppError::BadRequest("Admin auth is disabled".to_string())),
+    };
+
+    let reason = payload.reason.trim();
+    if reason.is_empty() {
+        return Err(AppError::BadRequest("A reason is required".to_string()));
+    }
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let user = find_user(&conn, &id).await?;
+    if user.is_admin {
+        return Err(AppError::BadRequest("Staff accounts can't be impersonated".to_string()));
+    }
+    if !user.is_active() {
+        return Err(AppError::BadRequest("Deactivated users can't be impersonated".to_string()));
+    }
+
+    let (token, expires_ts) = ImpersonationToken::issue(&conn, &user.id, &owner.id, reason).await?;
+    tracing::warn!("{} started impersonating {} ({})", owner.email, user.email, reason);
+    Ok(Json(ImpersonateResponse {
+        token,
+        user_id: user.id,
+        expires_ts,
+    }))
+}
+
+/// End every live impersonation of the customer early
+async fn end_impersonation(State(state): State<AppState>, Path(id): Path<String>) -> AppResult<Json<serde_json::Value>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let user = find_user(&conn, &id).await?;
+    let revoked = ImpersonationToken::revoke_for_user(&conn, &user.id).await?;
+    Ok(Json(serde_json::json!({"success": true, "revoked": revoked})))
+}
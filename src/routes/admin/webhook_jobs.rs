@@ -0,0 +1,63 @@
+use axum::{
+    extract::{Path, Query, State},
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::error::{AppError, AppResult};
+use crate::models::WebhookJob;
+use crate::routes::AppState;
+
+/// Most jobs listed at once
+const MAX_LISTED_JOBS: i64 = 100;
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/webhook-jobs", get(list_jobs))
+        .route("/webhook-jobs/{id}/retry", post(retry_job))
+}
+
+#[derive(Deserialize)]
+pub struct ListJobsQuery {
+    /// pending, processing, done or dead (default)
+    pub status: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct WebhookJobsResponse {
+    pub counts: HashMap<String, i64>,
+    pub jobs: Vec<WebhookJob>,
+}
+
+async fn list_jobs(
+    State(state): State<AppState>,
+    Query(query): Query<ListJobsQuery>,
+) -> AppResult<Json<WebhookJobsResponse>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+
+    let status = query.status.as_deref().unwrap_or("dead");
+    if !matches!(status, "pending" | "processing" | "done" | "dead") {
+        return Err(AppError::BadRequest(format!("Invalid job status: {}", status)));
+    }
+
+    let counts = WebhookJob::count_by_status(&conn).await?.into_iter().collect();
+    let jobs = WebhookJob::list_by_status(&conn, status, MAX_LISTED_JOBS).await?;
+
+    Ok(Json(WebhookJobsResponse { counts, jobs }))
+}
+
+/// Put a dead-lettered job back in the queue
+async fn retry_job(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> AppResult<Json<serde_json::Value>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+
+    if !WebhookJob::retry(&conn, &id).await? {
+        return Err(AppError::NotFound("Dead webhook job not found".to_string()));
+    }
+
+    Ok(Json(serde_json::json!({"requeued": true})))
+}
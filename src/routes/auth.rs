@@ -1,5 +1,6 @@
 use axum::{
     extract::{Query, State},
+    http::HeaderMap,
     response::{IntoResponse, Redirect},
     routing::{get, post},
     Json, Router,
@@ -7,9 +8,10 @@ use axum::{
 use serde::Deserialize;
 
 use crate::error::{AppError, AppResult};
-use crate::models::{CreateUser, User};
+use crate::middleware::auth::provision_user;
+use crate::models::User;
 use crate::routes::AppState;
-use crate::services::clerk::ClerkService;
+use crate::services::i18n::{locale_from_headers, normalize_locale};
 
 #[derive(Deserialize)]
 pub struct AuthCallback {
@@ -29,21 +31,9 @@ async fn auth_callback(
 ) -> impl IntoResponse {
     if let Some(user_id) = params.user_id {
         // Sync user from Clerk
-        if let Ok(clerk_user) = state.clerk.get_user(&user_id).await {
-            let email = ClerkService::get_primary_email(&clerk_user)
-                .unwrap_or_else(|| "unknown@example.com".to_string());
-            let name = ClerkService::get_full_name(&clerk_user);
-
-            if let Ok(conn) = state.db.connect() {
-                let _ = User::upsert(
-                    &conn,
-                    CreateUser {
-                        clerk_id: clerk_user.id,
-                        email,
-                        name,
-                    },
-                )
-                .await;
+        if let Ok(conn) = state.db.connect() {
+            if let Err(e) = provision_user(&state, &conn, &user_id).await {
+                tracing::warn!("Failed to sync user {} on auth callback: {}", user_id, e);
             }
         }
     }
@@ -54,28 +44,32 @@ async fn auth_callback(
 #[derive(Deserialize)]
 pub struct SyncUserRequest {
     pub clerk_id: String,
+    /// Email language; the Accept-Language header is used when missing
+    pub locale: Option<String>,
 }
 
 async fn sync_user(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(payload): Json<SyncUserRequest>,
 ) -> AppResult<Json<User>> {
-    let clerk_user = state.clerk.get_user(&payload.clerk_id).await?;
-
-    let email = ClerkService::get_primary_email(&clerk_user)
-        .unwrap_or_else(|| "unknown@example.com".to_string());
-    let name = ClerkService::get_full_name(&clerk_user);
-
     let conn = state.db.connect().map_err(AppError::from)?;
-    let user = User::upsert(
-        &conn,
-        CreateUser {
-            clerk_id: clerk_user.id,
-            email,
-            name,
-        },
-    )
-    .await?;
+    let user = provision_user(&state, &conn, &payload.clerk_id).await?;
+
+    // An explicit choice always wins; the browser language only fills in a missing one
+    let requested = payload.locale.as_deref().and_then(normalize_locale);
+    let locale = match (requested, &user.locale) {
+        (Some(locale), _) => Some(locale),
+        (None, None) => locale_from_headers(&headers),
+        (None, Some(_)) => None,
+    };
+    let user = match locale {
+        Some(locale) if user.locale.as_deref() != Some(locale) => {
+            User::set_locale(&conn, &user.id, locale).await?;
+            User { locale: Some(locale.to_string()), ..user }
+        }
+        _ => user,
+    };
 
     Ok(Json(user))
 }
@@ -3,40 +3,81 @@ use axum::{
     routing::post,
     Json, Router,
 };
+use libsql::Connection;
 use serde::{Deserialize, Serialize};
 
 use crate::error::{AppError, AppResult};
 use crate::middleware::AuthUser;
-use crate::models::{CreateOrder, CreateOrderItem, Order, Product, ProductImage, ShippingAddress};
+use crate::models::{
+    Address, Artist, BoxPreset, CreateOrder, CreateOrderItem, DiscountCode, Money, Order, OrderStatus, Product,
+    ProductImage, ProductStyle, Setting, ShippingAddress, ShippingRateRecord, ShippingRule, User,
+    LOCAL_DELIVERY_RATE_ID,
+};
+use crate::routes::shipping::{ParcelSize, FALLBACK_RATE_ID, FREE_SHIPPING_RATE_ID, RULE_RATE_PREFIX};
 use crate::routes::AppState;
-use crate::services::stripe::CheckoutItem;
+use crate::services::stripe::{CheckoutItem, CheckoutSessionParams, CheckoutSessionResult, DestinationCharge};
 
 #[derive(Deserialize)]
 pub struct CartItem {
     pub product_id: String,
     pub quantity: i32,
+    pub style_id: Option<String>,
 }
 
 #[derive(Deserialize)]
 pub struct CheckoutRequest {
     pub items: Vec<CartItem>,
-    pub shipping_address: ShippingAddress,
+    /// Either a new address or the ID of one of the customer's saved addresses
+    pub shipping_address: Option<ShippingAddress>,
+    pub address_id: Option<String>,
     // Shipping selection
     pub shipping_rate_id: Option<String>,
     pub shipping_cents: Option<i32>,
     pub shipping_carrier: Option<String>,
     pub shipping_service: Option<String>,
     pub estimated_delivery_days: Option<i32>,
+    // Gift options
+    #[serde(default)]
+    pub is_gift: bool,
+    pub gift_message: Option<String>,
+    #[serde(default)]
+    pub gift_wrap: bool,
+    // Discount code
+    pub promo_code: Option<String>,
+    // Optional "support the artist" tip
+    pub tip_cents: Option<i32>,
+    /// Payment method the customer picked, e.g. "klarna"; validated before the session opens
+    pub payment_method: Option<String>,
 }
 
+/// Longest gift message that fits on the packing slip
+const MAX_GIFT_MESSAGE_LEN: usize = 500;
+
+/// Largest tip accepted at checkout ($500)
+const MAX_TIP_CENTS: i32 = 50_000;
+
+/// Most units of a single line item one checkout can buy
+const MAX_ITEM_QUANTITY: i32 = 100;
+
 #[derive(Serialize)]
 pub struct CheckoutResponse {
     pub checkout_url: String,
     pub order_id: String,
 }
 
+#[derive(Serialize)]
+pub struct PaymentIntentResponse {
+    pub client_secret: String,
+    pub payment_intent_id: String,
+    pub order_id: String,
+    pub amount_cents: i32,
+    pub amount: Money,
+}
+
 pub fn routes() -> Router<AppState> {
-    Router::new().route("/checkout", post(create_checkout))
+    Router::new()
+        .route("/checkout", post(create_checkout))
+        .route("/checkout/payment-intent", post(create_payment_intent))
 }
 
 async fn create_checkout(
@@ -44,18 +85,153 @@ async fn create_checkout(
     Extension(user): Extension<AuthUser>,
     Json(payload): Json<CheckoutRequest>,
 ) -> AppResult<Json<CheckoutResponse>> {
+    let checkout = start_checkout(&state, &user, payload).await?;
+    Ok(Json(checkout))
+}
+
+async fn create_payment_intent(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Json(payload): Json<CheckoutRequest>,
+) -> AppResult<Json<PaymentIntentResponse>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let PendingCheckout { order, destination, .. } = create_pending_order(&state, &conn, &user, payload).await?;
+
+    // Stripe needs something to charge, so an order that comes to $0 or less is refused
+    if order.total_cents <= 0 {
+        if let Err(e) = Order::update_status(&conn, &order.id, OrderStatus::Cancelled, false).await {
+            tracing::error!("Failed to cancel order {} with nothing to pay: {}", order.id, e);
+        }
+        return Err(AppError::BadRequest("Order total must be more than $0".to_string()));
+    }
+
+    let customer_id = stripe_customer_for(&state, &conn, &user).await;
+
+    // The order total already has the promo discount applied, so no coupon is needed
+    let created = match state.mock_payments {
+        Some(ref mock) => Ok(mock.create_payment_intent()),
+        None => {
+            state
+                .stripe
+                .create_payment_intent(
+                    order.total_cents as i64,
+                    Some(&user.email),
+                    customer_id.as_deref(),
+                    &order.id,
+                    destination.as_ref(),
+                )
+                .await
+        }
+    };
+
+    let payment_intent = match created {
+        Ok(payment_intent) => payment_intent,
+        Err(e) => {
+            if let Err(cancel_err) = Order::update_status(&conn, &order.id, OrderStatus::Cancelled, false).await {
+                tracing::error!("Failed to cancel order {} after payment intent error: {}", order.id, cancel_err);
+            }
+            return Err(e);
+        }
+    };
+
+    Order::set_payment_intent(&conn, &order.id, &payment_intent.id).await?;
+
+    Ok(Json(PaymentIntentResponse {
+        client_secret: payment_intent.client_secret,
+        payment_intent_id: payment_intent.id,
+        order_id: order.id,
+        amount_cents: order.total_cents,
+        amount: Money::usd(order.total_cents),
+    }))
+}
+
+/// Validate the cart, create a pending order, and open a Stripe Checkout session for it
+pub async fn start_checkout(
+    state: &AppState,
+    user: &AuthUser,
+    payload: CheckoutRequest,
+) -> AppResult<CheckoutResponse> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let payment_method = payload.payment_method.clone();
+    let PendingCheckout { order, checkout_items, promo, destination } =
+        create_pending_order(state, &conn, user, payload).await?;
+
+    let customer_id = stripe_customer_for(state, &conn, user).await;
+
+    // Create Stripe checkout session
+    let session = StripeSession {
+        customer_id: customer_id.as_deref(),
+        order: &order,
+        checkout_items,
+        promo: promo.as_ref(),
+        payment_method: payment_method.as_deref(),
+        destination: destination.as_ref(),
+    };
+    let checkout = match open_stripe_session(state, user, session).await {
+        Ok(checkout) => checkout,
+        Err(e) => {
+            // Don't hold stock for an order the customer can never pay for
+            if let Err(cancel_err) = Order::update_status(&conn, &order.id, OrderStatus::Cancelled, false).await {
+                tracing::error!("Failed to cancel order {} after checkout error: {}", order.id, cancel_err);
+            }
+            return Err(e);
+        }
+    };
+
+    // Update order with Stripe session ID
+    Order::set_stripe_session(&conn, &order.id, &checkout.id, checkout.expires_at).await?;
+
+    Ok(CheckoutResponse {
+        checkout_url: checkout.url,
+        order_id: order.id,
+    })
+}
+
+/// A pending order with its stock reserved, ready to be paid for
+struct PendingCheckout {
+    order: Order,
+    checkout_items: Vec<CheckoutItem>,
+    promo: Option<(String, i32)>,
+    /// Set when the cart belongs to a marketplace artist
+    destination: Option<DestinationCharge>,
+}
+
+/// Validate the cart and create a pending order, reserving its stock
+async fn create_pending_order(
+    state: &AppState,
+    conn: &Connection,
+    user: &AuthUser,
+    payload: CheckoutRequest,
+) -> AppResult<PendingCheckout> {
     if payload.items.is_empty() {
         return Err(AppError::BadRequest("Cart is empty".to_string()));
     }
 
-    let conn = state.db.connect().map_err(AppError::from)?;
+    let shipping_address = match (payload.shipping_address, &payload.address_id) {
+        (Some(address), _) => address,
+        (None, Some(address_id)) => Address::find_for_user(conn, address_id, &user.id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Address not found".to_string()))?
+            .to_shipping_address(),
+        (None, None) => return Err(AppError::BadRequest("Shipping address is required".to_string())),
+    };
 
     // Calculate total and validate products
     let mut total_cents = 0i32;
+    let mut parcel = ParcelSize::default();
     let mut order_items: Vec<CreateOrderItem> = Vec::new();
+    // Seller of the cart in marketplace mode (inner None = the shop itself)
+    let mut seller: Option<Option<String>> = None;
 
     for item in &payload.items {
-        let product = Product::find_by_id(&conn, &item.product_id)
+        if !(1..=MAX_ITEM_QUANTITY).contains(&item.quantity) {
+            return Err(AppError::BadRequest(format!(
+                "Quantity must be between 1 and {}",
+                MAX_ITEM_QUANTITY
+            )));
+        }
+
+        let product = Product::find_by_id(conn, &item.product_id)
             .await?
             .ok_or_else(|| AppError::NotFound(format!("Product {} not found", item.product_id)))?;
 
@@ -66,36 +242,209 @@ async fn create_checkout(
             )));
         }
 
-        if product.stock_quantity < item.quantity {
+        if product.is_subscription() {
             return Err(AppError::BadRequest(format!(
-                "Insufficient stock for {}",
+                "{} is a subscription and can't be bought through the cart",
                 product.name
             )));
         }
 
+        // Early check for a friendly error; the reservation in Order::create is authoritative
+        if product.stock_quantity < item.quantity {
+            return Err(AppError::InsufficientStock {
+                product_id: product.id,
+                style_id: None,
+                name: product.name,
+                available: product.stock_quantity as i64,
+            });
+        }
+
+        // Styles keep their own stock counts
+        let style = match &item.style_id {
+            Some(style_id) => {
+                let style = ProductStyle::get_by_id(conn, style_id)
+                    .await?
+                    .filter(|s| s.product_id == product.id)
+                    .ok_or_else(|| AppError::NotFound(format!("Style {} not found", style_id)))?;
+
+                if style.stock_quantity < item.quantity as i64 {
+                    return Err(AppError::InsufficientStock {
+                        product_id: product.id,
+                        style_id: Some(style.id),
+                        name: format!("{} ({})", product.name, style.name),
+                        available: style.stock_quantity,
+                    });
+                }
+
+                Some(style)
+            }
+            None => None,
+        };
+
+        // A marketplace payment goes to a single artist, so sellers can't be mixed
+        if state.config.stripe_connect_enabled {
+            match &seller {
+                None => seller = Some(product.artist_id.clone()),
+                Some(artist_id) if *artist_id != product.artist_id => {
+                    return Err(AppError::BadRequest(
+                        "Items from different artists must be checked out separately".to_string(),
+                    ));
+                }
+                Some(_) => {}
+            }
+        }
+
         let item_total = product.price_cents * item.quantity;
         total_cents += item_total;
+        parcel.add(&product, style.as_ref(), item.quantity);
 
         order_items.push(CreateOrderItem {
             product_id: product.id,
             quantity: item.quantity,
             price_cents: product.price_cents,
+            style_id: style.as_ref().map(|s| s.id.clone()),
+            style_name: style.map(|s| s.name),
         });
     }
 
+    let subtotal_cents = total_cents;
+
+    // Weighed the same way as the quoted rates, box included
+    parcel.pack(&BoxPreset::list_all(conn).await?);
+    let weight_grams = parcel.weight_grams.round() as i32;
+
+    let artist = match seller.flatten() {
+        Some(artist_id) => match Artist::find_by_id(conn, &artist_id).await? {
+            Some(Artist { id, stripe_account_id: Some(account_id), charges_enabled: true, .. }) => {
+                Some((id, account_id))
+            }
+            _ => {
+                return Err(AppError::BadRequest(
+                    "This artist isn't accepting orders yet".to_string(),
+                ));
+            }
+        },
+        None => None,
+    };
+
+    if let Some(minimum_cents) = Setting::get_minimum_order_cents(conn).await? {
+        if subtotal_cents < minimum_cents {
+            return Err(AppError::BelowMinimumOrder { minimum_cents, subtotal_cents });
+        }
+    }
+
+    // Apply promo code to the merchandise subtotal
+    let promo = match payload.promo_code.as_deref().map(str::trim).filter(|c| !c.is_empty()) {
+        Some(code) => {
+            let discount = DiscountCode::find_by_code(conn, code)
+                .await?
+                .ok_or_else(|| AppError::BadRequest("Invalid promo code".to_string()))?;
+            discount.check_redeemable(subtotal_cents)?;
+            let discount_cents = discount.discount_for(subtotal_cents);
+            Some((discount.code, discount_cents))
+        }
+        None => None,
+    };
+    let discount_cents = promo.as_ref().map(|(_, cents)| *cents).unwrap_or(0);
+    total_cents -= discount_cents;
+
+    // Carts over the free shipping threshold never pay for shipping
+    let free_shipping = match Setting::get_free_shipping_threshold_cents(conn).await? {
+        Some(threshold) => subtotal_cents >= threshold,
+        None => false,
+    };
+    if payload.shipping_rate_id.as_deref() == Some(FREE_SHIPPING_RATE_ID) && !free_shipping {
+        return Err(AppError::BadRequest("Cart does not qualify for free shipping".to_string()));
+    }
+
+    // Rates from the shop's shipping rules are priced here rather than taken from the client
+    let rule_id = payload
+        .shipping_rate_id
+        .as_deref()
+        .and_then(|id| id.strip_prefix(RULE_RATE_PREFIX));
+    let quoted_cents = match rule_id {
+        Some(rule_id) => ShippingRule::find_by_id(conn, rule_id)
+            .await?
+            .filter(|rule| rule.is_active && rule.matches(&shipping_address))
+            .and_then(|rule| rule.price_for(weight_grams))
+            .ok_or_else(|| AppError::BadRequest("The selected shipping rate is no longer available".to_string()))?,
+        None if payload.shipping_rate_id.as_deref() == Some(LOCAL_DELIVERY_RATE_ID) => {
+            let local_delivery = Setting::get_local_delivery(conn).await?;
+            if !local_delivery.covers(&shipping_address) {
+                return Err(AppError::BadRequest(
+                    "Local delivery isn't available for this address".to_string(),
+                ));
+            }
+            local_delivery.fee_cents
+        }
+        None if payload.shipping_rate_id.as_deref() == Some(FALLBACK_RATE_ID) => Setting::get_fallback_rates(conn)
+            .await?
+            .price_for(weight_grams)
+            .ok_or_else(|| AppError::BadRequest("The selected shipping rate is no longer available".to_string()))?,
+        None if payload.shipping_rate_id.as_deref() == Some(FREE_SHIPPING_RATE_ID) => 0,
+        // Carrier rates are priced from Shippo's own copy, never the client's
+        None => match payload.shipping_rate_id.as_deref() {
+            Some(rate_id) => {
+                let rate = state.shippo.get_rate(rate_id).await.map_err(|e| {
+                    tracing::warn!("Failed to look up shipping rate {}: {}", rate_id, e);
+                    AppError::BadRequest("The selected shipping rate is no longer available".to_string())
+                })?;
+                let amount: f64 = rate.amount.parse().map_err(|_| {
+                    AppError::BadRequest("The selected shipping rate is no longer available".to_string())
+                })?;
+                (amount * 100.0).round() as i32
+            }
+            None if free_shipping => 0,
+            None => return Err(AppError::BadRequest("Choose a shipping rate".to_string())),
+        },
+    };
+    if quoted_cents < 0 || payload.shipping_cents.is_some_and(|cents| cents < 0) {
+        return Err(AppError::BadRequest("Shipping can't be negative".to_string()));
+    }
+
     // Add shipping cost to total
-    let shipping_cents = payload.shipping_cents.unwrap_or(0);
+    let shipping_cents = if free_shipping { 0 } else { quoted_cents };
     total_cents += shipping_cents;
 
+    // Gift options
+    let gift_message = payload
+        .gift_message
+        .as_deref()
+        .map(str::trim)
+        .filter(|m| !m.is_empty())
+        .map(|m| m.to_string());
+    if gift_message.as_ref().map(|m| m.chars().count() > MAX_GIFT_MESSAGE_LEN).unwrap_or(false) {
+        return Err(AppError::BadRequest(format!(
+            "Gift message must be {} characters or fewer",
+            MAX_GIFT_MESSAGE_LEN
+        )));
+    }
+
+    let gift_wrap_cents = if payload.gift_wrap {
+        Setting::get_gift_wrap_fee_cents(conn).await?
+    } else {
+        0
+    };
+    total_cents += gift_wrap_cents;
+
+    let tip_cents = payload.tip_cents.unwrap_or(0);
+    if !(0..=MAX_TIP_CENTS).contains(&tip_cents) {
+        return Err(AppError::BadRequest(format!(
+            "Tip must be between $0 and ${}",
+            MAX_TIP_CENTS / 100
+        )));
+    }
+    total_cents += tip_cents;
+
     // Build checkout items with product details
     let mut checkout_items: Vec<CheckoutItem> = Vec::new();
-    for item in &payload.items {
-        let product = Product::find_by_id(&conn, &item.product_id)
+    for (item, order_item) in payload.items.iter().zip(&order_items) {
+        let product = Product::find_by_id(conn, &item.product_id)
             .await?
             .ok_or_else(|| AppError::NotFound(format!("Product {} not found", item.product_id)))?;
 
         // Get product images for checkout display
-        let images = ProductImage::list_by_product(&conn, &item.product_id).await?;
+        let images = ProductImage::list_by_product(conn, &item.product_id).await?;
         let image_urls: Vec<String> = images
             .iter()
             .take(1) // Stripe checkout shows one image per line item
@@ -108,8 +457,13 @@ async fn create_checkout(
             })
             .collect();
 
+        let name = match &order_item.style_name {
+            Some(style_name) => format!("{} - {}", product.name, style_name),
+            None => product.name.clone(),
+        };
+
         checkout_items.push(CheckoutItem {
-            name: product.name.clone(),
+            name,
             description: product.description.clone(),
             images: if image_urls.is_empty() { None } else { Some(image_urls) },
             price_cents: product.price_cents as i64,
@@ -136,13 +490,51 @@ async fn create_checkout(
         });
     }
 
+    // Add gift wrap as a line item if selected
+    if gift_wrap_cents > 0 {
+        checkout_items.push(CheckoutItem {
+            name: "Gift Wrap".to_string(),
+            description: None,
+            images: None,
+            price_cents: gift_wrap_cents as i64,
+            quantity: 1,
+        });
+    }
+
+    // Add the tip as its own line item
+    if tip_cents > 0 {
+        checkout_items.push(CheckoutItem {
+            name: "Support the Artist".to_string(),
+            description: Some("Thank you!".to_string()),
+            images: None,
+            price_cents: tip_cents as i64,
+            quantity: 1,
+        });
+    }
+
+    // Record the rate the customer picked so it can be compared with the label later
+    let checkout_rate = if payload.shipping_rate_id.is_some() || shipping_cents > 0 {
+        Some(ShippingRateRecord {
+            rate_id: payload.shipping_rate_id.clone(),
+            carrier: payload.shipping_carrier.clone(),
+            service: payload.shipping_service.clone(),
+            amount_cents: shipping_cents,
+            recorded_ts: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs() as i64,
+        })
+    } else {
+        None
+    };
+
     // Create order in pending state (without session ID initially)
     let order = Order::create(
-        &conn,
+        conn,
         CreateOrder {
             user_id: Some(user.id.clone()),
             total_cents,
-            shipping_address: payload.shipping_address,
+            shipping_address,
             stripe_session_id: None,
             items: order_items,
             // Shipping details
@@ -150,30 +542,141 @@ async fn create_checkout(
             shipping_carrier: payload.shipping_carrier,
             shipping_service: payload.shipping_service,
             estimated_delivery_days: payload.estimated_delivery_days,
+            // Gift options
+            is_gift: payload.is_gift,
+            gift_message,
+            gift_wrap: payload.gift_wrap,
+            gift_wrap_cents,
+            checkout_rate,
+            // Promo code
+            promo_code: promo.as_ref().map(|(code, _)| code.clone()),
+            discount_cents,
+            tip_cents,
+            reserve_stock: true,
         },
     )
     .await?;
 
-    // Create Stripe checkout session
+    // The fallback rate is a guess; the real postage gets checked before the label is bought
+    if payload.shipping_rate_id.as_deref() == Some(FALLBACK_RATE_ID) {
+        Order::set_rate_review(conn, &order.id, true).await?;
+    }
+
+    // The shop keeps its percentage of the merchandise plus shipping and gift wrap
+    // (it buys the labels and wraps); the tip goes to the artist in full
+    let destination = match artist {
+        Some((artist_id, account_id)) => {
+            let merchandise_cents = (subtotal_cents - discount_cents) as i64;
+            let fee_cents = merchandise_cents * state.config.stripe_application_fee_percent / 100
+                + shipping_cents as i64
+                + gift_wrap_cents as i64;
+            Order::set_artist(conn, &order.id, &artist_id, fee_cents as i32).await?;
+            Some(DestinationCharge {
+                account_id,
+                application_fee_cents: fee_cents,
+            })
+        }
+        None => None,
+    };
+
+    Ok(PendingCheckout { order, checkout_items, promo, destination })
+}
+
+/// The user's Stripe Customer ID, creating the customer on their first checkout.
+/// Failures are logged and checkout falls back to the email-only flow.
+pub async fn stripe_customer_for(state: &AppState, conn: &Connection, user: &AuthUser) -> Option<String> {
+    if state.mock_payments.is_some() {
+        return None;
+    }
+
+    match User::find_by_id(conn, &user.id).await {
+        Ok(Some(User { stripe_customer_id: Some(customer_id), .. })) => return Some(customer_id),
+        Ok(_) => {}
+        Err(e) => {
+            tracing::error!("Failed to load user {} for Stripe customer: {}", user.id, e);
+            return None;
+        }
+    }
+
+    let customer_id = match state
+        .stripe
+        .create_customer(&user.email, user.name.as_deref(), &user.id)
+        .await
+    {
+        Ok(id) => id,
+        Err(e) => {
+            tracing::error!("Failed to create Stripe customer for user {}: {}", user.id, e);
+            return None;
+        }
+    };
+
+    if let Err(e) = User::set_stripe_customer_id(conn, &user.id, &customer_id).await {
+        tracing::error!("Failed to store Stripe customer for user {}: {}", user.id, e);
+    }
+    Some(customer_id)
+}
+
+/// What a freshly created order's Checkout session is opened with
+struct StripeSession<'a> {
+    customer_id: Option<&'a str>,
+    order: &'a Order,
+    checkout_items: Vec<CheckoutItem>,
+    promo: Option<&'a (String, i32)>,
+    /// Payment method the customer picked, if any
+    payment_method: Option<&'a str>,
+    destination: Option<&'a DestinationCharge>,
+}
+
+/// Create the Stripe Checkout session for a freshly created order
+async fn open_stripe_session(
+    state: &AppState,
+    user: &AuthUser,
+    session: StripeSession<'_>,
+) -> AppResult<CheckoutSessionResult> {
+    let StripeSession { customer_id, order, checkout_items, promo, payment_method, destination } = session;
     let success_url = format!("{}/orders/{}?success=true", state.config.base_url, order.id);
     let cancel_url = format!("{}/cart?cancelled=true", state.config.base_url);
 
-    let checkout = state
-        .stripe
-        .create_checkout_session(
-            checkout_items,
-            &success_url,
-            &cancel_url,
-            Some(&user.email),
-            &order.id,
-        )
-        .await?;
+    // Buy-now-pay-later is only offered within Stripe's amount and country limits;
+    // a customer who picked one for an order that doesn't qualify is told why
+    let amount_cents = order.total_cents as i64;
+    let country = order.get_shipping_address().map(|a| a.country);
+    let bnpl_methods = match payment_method {
+        Some(method) if method != "card" => {
+            state
+                .stripe
+                .check_bnpl_eligibility(method, amount_cents, country.as_deref())
+                .map_err(AppError::BadRequest)?;
+            vec![method.to_string()]
+        }
+        _ => state.stripe.eligible_bnpl_methods(amount_cents, country.as_deref()),
+    };
 
-    // Update order with Stripe session ID
-    Order::set_stripe_session(&conn, &order.id, &checkout.id).await?;
+    // Simulated sessions already carry the discounted total
+    if let Some(ref mock) = state.mock_payments {
+        return Ok(mock.create_checkout_session());
+    }
 
-    Ok(Json(CheckoutResponse {
-        checkout_url: checkout.url,
-        order_id: order.id,
-    }))
+    // Stripe applies the discount through a one-off coupon for the exact amount
+    let coupon_id = match promo {
+        Some((code, cents)) if *cents > 0 => {
+            Some(state.stripe.create_discount_coupon(code, *cents as i64).await?)
+        }
+        _ => None,
+    };
+
+    state
+        .stripe
+        .create_checkout_session(CheckoutSessionParams {
+            items: checkout_items,
+            success_url: &success_url,
+            cancel_url: &cancel_url,
+            customer_email: Some(&user.email),
+            customer_id,
+            order_id: &order.id,
+            coupon_id: coupon_id.as_deref(),
+            bnpl_methods: &bnpl_methods,
+            destination,
+        })
+        .await
 }
@@ -0,0 +1,629 @@
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::{header, HeaderMap},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post, put},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, AppResult};
+use crate::middleware::AuthUser;
+use crate::models::{
+    Address, EmailLog, NewsletterSubscriber, Order, OrderItemDetail, Product, ProductNotification, ShippingAddress,
+    Subscription, User, WishlistItem,
+};
+use crate::routes::AppState;
+use crate::services::i18n::locale_from_headers;
+
+/// Orders in these statuses still need the customer's address, so the account
+/// can't be deleted until they're delivered, cancelled or refunded
+const OPEN_ORDER_STATUSES: &[&str] = &["paid", "processing", "shipped", "out_for_delivery"];
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/me", get(get_profile))
+        .route("/me", put(update_profile))
+        .route("/me/summary", get(get_summary))
+        .route("/me/export", post(export_data))
+        .route("/me/delete", post(delete_account))
+        .route("/me/wishlist", get(list_wishlist))
+        .route("/me/wishlist/{product_id}", put(add_to_wishlist))
+        .route("/me/wishlist/{product_id}", delete(remove_from_wishlist))
+}
+
+/// The signed-in customer's account details
+#[derive(Serialize)]
+pub struct Profile {
+    pub id: String,
+    /// Sign-in email, managed by Clerk
+    pub email: String,
+    pub name: Option<String>,
+    pub locale: Option<String>,
+    pub default_address: Option<Address>,
+    /// Confirmed newsletter subscriber
+    pub marketing_opt_in: bool,
+    /// Subscribed but hasn't clicked the confirmation link yet
+    pub marketing_opt_in_pending: bool,
+    pub created_ts: i64,
+    /// Set when an owner is viewing the account with an impersonation token: the
+    /// owner's user ID, so the storefront can show that it isn't the customer
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub impersonated_by: Option<String>,
+}
+
+/// Fields to change; missing fields are left alone. The email is the Clerk
+/// sign-in address and is changed through Clerk's account screens.
+#[derive(Deserialize)]
+pub struct UpdateProfile {
+    pub name: Option<String>,
+    pub default_address_id: Option<String>,
+    pub marketing_opt_in: Option<bool>,
+}
+
+#[derive(Serialize)]
+pub struct AccountSummary {
+    pub order_count: i64,
+    pub wishlist_count: i64,
+}
+
+async fn load_profile(conn: &libsql::Connection, user_id: &str) -> AppResult<Profile> {
+    let user = User::find_by_id(conn, user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    let default_address = Address::list_by_user(conn, &user.id)
+        .await?
+        .into_iter()
+        .find(|a| a.is_default);
+    let subscriber = NewsletterSubscriber::find_by_email(conn, &user.email).await?;
+
+    Ok(Profile {
+        marketing_opt_in: subscriber.as_ref().is_some_and(|s| s.is_confirmed()),
+        marketing_opt_in_pending: subscriber.as_ref().is_some_and(|s| !s.is_confirmed()),
+        id: user.id,
+        email: user.email,
+        name: user.name,
+        locale: user.locale,
+        default_address,
+        created_ts: user.created_ts,
+        impersonated_by: None,
+    })
+}
+
+async fn get_profile(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+) -> AppResult<Json<Profile>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let profile = load_profile(&conn, &user.id).await?;
+    Ok(Json(Profile {
+        impersonated_by: user.impersonated_by,
+        ..profile
+    }))
+}
+
+async fn update_profile(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    headers: HeaderMap,
+    Json(payload): Json<UpdateProfile>,
+) -> AppResult<Json<Profile>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+
+    if let Some(address_id) = &payload.default_address_id {
+        if !Address::set_default(&conn, address_id, &user.id).await? {
+            return Err(AppError::NotFound("Address not found".to_string()));
+        }
+    }
+
+    if let Some(name) = &payload.name {
+        let name = name.trim();
+        if name.len() > 200 {
+            return Err(AppError::BadRequest("Name is too long".to_string()));
+        }
+
+        // The auth provider is the source of the name on every sign-in, so change it there first
+        let (first_name, last_name) = name.split_once(' ').unwrap_or((name, ""));
+        state.auth.update_name(&user.clerk_id, first_name, last_name.trim()).await?;
+        User::set_name(&conn, &user.id, Some(name).filter(|n| !n.is_empty())).await?;
+    }
+
+    match payload.marketing_opt_in {
+        Some(true) => {
+            let account_locale = User::find_by_id(&conn, &user.id).await?.and_then(|u| u.locale);
+            let locale = account_locale.as_deref().or_else(|| locale_from_headers(&headers));
+            let subscriber = NewsletterSubscriber::subscribe(&conn, &user.email, locale).await?;
+
+            // Same double opt-in as the signup form
+            if let (false, Some(resend), Some(confirm_token)) =
+                (subscriber.is_confirmed(), &state.resend, &subscriber.confirm_token)
+            {
+                if let Err(e) = resend
+                    .send_confirmation_email(&subscriber.email, confirm_token, subscriber.email_locale())
+                    .await
+                {
+                    tracing::error!("Failed to send newsletter confirmation email: {}", e);
+                }
+            }
+        }
+        Some(false) => {
+            NewsletterSubscriber::unsubscribe_by_email(&conn, &user.email).await?;
+        }
+        None => {}
+    }
+
+    Ok(Json(load_profile(&conn, &user.id).await?))
+}
+
+/// Counts for the account page
+async fn get_summary(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+) -> AppResult<Json<AccountSummary>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+
+    Ok(Json(AccountSummary {
+        order_count: Order::count_by_user(&conn, &user.id).await?,
+        wishlist_count: WishlistItem::count_by_user(&conn, &user.id).await?,
+    }))
+}
+
+/// Saved products that still exist, newest first
+async fn list_wishlist(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+) -> AppResult<Json<Vec<Product>>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+
+    let mut products = Vec::new();
+    for item in WishlistItem::list_by_user(&conn, &user.id).await? {
+        if let Some(product) = Product::find_by_id(&conn, &item.product_id).await? {
+            if product.is_active {
+                products.push(product);
+            }
+        }
+    }
+    Ok(Json(products))
+}
+
+async fn add_to_wishlist(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path(product_id): Path<String>,
+) -> AppResult<Json<serde_json::Value>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+
+    match Product::find_by_id(&conn, &product_id).await? {
+        Some(product) if product.is_active => {}
+        _ => return Err(AppError::NotFound("Product not found".to_string())),
+    }
+
+    WishlistItem::add(&conn, &user.id, &product_id).await?;
+    Ok(Json(serde_json::json!({"success": true})))
+}
+
+async fn remove_from_wishlist(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path(product_id): Path<String>,
+) -> AppResult<Json<serde_json::Value>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    WishlistItem::remove(&conn, &user.id, &product_id).await?;
+    Ok(Json(serde_json::json!({"success": true})))
+}
+
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    /// "json" (default) or "csv"
+    pub format: Option<String>,
+}
+
+/// Everything the shop stores about a customer
+#[derive(Serialize)]
+pub struct DataExport {
+    pub exported_ts: i64,
+    pub profile: Profile,
+    pub addresses: Vec<Address>,
+    pub orders: Vec<ExportedOrder>,
+    pub subscriptions: Vec<ExportedSubscription>,
+    pub newsletter: Option<ExportedNewsletter>,
+    pub restock_alerts: Vec<ExportedRestockAlert>,
+    pub wishlist: Vec<WishlistItem>,
+}
+
+#[derive(Serialize)]
+pub struct ExportedOrder {
+    pub id: String,
+    pub status: String,
+    pub created_ts: i64,
+    pub total_cents: i32,
+    pub shipping_cents: i32,
+    pub discount_cents: i32,
+    pub tip_cents: i32,
+    pub gift_wrap_cents: i32,
+    pub promo_code: Option<String>,
+    pub shipping_address: Option<ShippingAddress>,
+    pub shipping_carrier: Option<String>,
+    pub shipping_service: Option<String>,
+    pub tracking_number: Option<String>,
+    pub is_gift: bool,
+    pub gift_message: Option<String>,
+    pub payment_method_type: Option<String>,
+    pub card_brand: Option<String>,
+    pub items: Vec<OrderItemDetail>,
+}
+
+#[derive(Serialize)]
+pub struct ExportedSubscription {
+    pub id: String,
+    pub product_id: String,
+    pub status: String,
+    pub shipping_address: Option<ShippingAddress>,
+    pub current_period_end: Option<i64>,
+    pub cancel_at_period_end: bool,
+    pub created_ts: i64,
+}
+
+#[derive(Serialize)]
+pub struct ExportedNewsletter {
+    pub email: String,
+    pub status: String,
+    pub subscribed_ts: i64,
+    pub confirmed_ts: Option<i64>,
+    pub wants_new_drops: bool,
+    pub wants_restocks: bool,
+    pub wants_sales: bool,
+}
+
+#[derive(Serialize)]
+pub struct ExportedRestockAlert {
+    pub product_id: String,
+    pub style_id: Option<String>,
+    pub notified: bool,
+    pub created_ts: i64,
+}
+
+fn now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
+
+async fn collect_export(conn: &libsql::Connection, user_id: &str) -> AppResult<DataExport> {
+    let profile = load_profile(conn, user_id).await?;
+
+    let orders = Order::list_by_user(conn, user_id).await?;
+    let order_ids: Vec<String> = orders.iter().map(|o| o.id.clone()).collect();
+    let mut items = Order::get_items_for_orders(conn, &order_ids).await?;
+    let orders = orders
+        .into_iter()
+        .map(|order| ExportedOrder {
+            items: items.remove(&order.id).unwrap_or_default(),
+            shipping_address: order.get_shipping_address(),
+            id: order.id,
+            status: order.status,
+            created_ts: order.created_ts,
+            total_cents: order.total_cents,
+            shipping_cents: order.shipping_cents,
+            discount_cents: order.discount_cents,
+            tip_cents: order.tip_cents,
+            gift_wrap_cents: order.gift_wrap_cents,
+            promo_code: order.promo_code,
+            shipping_carrier: order.shipping_carrier,
+            shipping_service: order.shipping_service,
+            tracking_number: order.tracking_number,
+            is_gift: order.is_gift,
+            gift_message: order.gift_message,
+            payment_method_type: order.payment_method_type,
+            card_brand: order.card_brand,
+        })
+        .collect();
+
+    let subscriptions = Subscription::list_by_user(conn, user_id)
+        .await?
+        .into_iter()
+        .map(|s| ExportedSubscription {
+            shipping_address: s.get_shipping_address(),
+            id: s.id,
+            product_id: s.product_id,
+            status: s.status,
+            current_period_end: s.current_period_end,
+            cancel_at_period_end: s.cancel_at_period_end,
+            created_ts: s.created_ts,
+        })
+        .collect();
+
+    let newsletter = NewsletterSubscriber::find_by_email(conn, &profile.email)
+        .await?
+        .map(|s| ExportedNewsletter {
+            email: s.email,
+            status: s.status,
+            subscribed_ts: s.subscribed_ts,
+            confirmed_ts: s.confirmed_ts,
+            wants_new_drops: s.wants_new_drops,
+            wants_restocks: s.wants_restocks,
+            wants_sales: s.wants_sales,
+        });
+
+    let restock_alerts = ProductNotification::list_by_email(conn, &profile.email)
+        .await?
+        .into_iter()
+        .map(|n| ExportedRestockAlert {
+            product_id: n.product_id,
+            style_id: n.style_id,
+            notified: n.notified,
+            created_ts: n.created_ts,
+        })
+        .collect();
+
+    Ok(DataExport {
+        exported_ts: now(),
+        addresses: Address::list_by_user(conn, user_id).await?,
+        wishlist: WishlistItem::list_by_user(conn, user_id).await?,
+        profile,
+        orders,
+        subscriptions,
+        newsletter,
+        restock_alerts,
+    })
+}
+
+/// Quote a CSV field when it contains a separator, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn csv_row(fields: &[String]) -> String {
+    let mut row = fields.iter().map(|f| csv_field(f)).collect::<Vec<_>>().join(",");
+    row.push_str("\r\n");
+    row
+}
+
+fn opt<T: ToString>(value: &Option<T>) -> String {
+    value.as_ref().map(|v| v.to_string()).unwrap_or_default()
+}
+
+fn address_fields(address: Option<&ShippingAddress>) -> Vec<String> {
+    match address {
+        Some(a) => vec![
+            a.name.clone(),
+            a.street.clone(),
+            a.city.clone(),
+            a.state.clone(),
+            a.zip.clone(),
+            a.country.clone(),
+        ],
+        None => vec![String::new(); 6],
+    }
+}
+
+/// The export as one CSV file: a section per kind of record, each starting
+/// with a `# name` line and its own header row
+fn export_csv(export: &DataExport) -> String {
+    let address_header = ["name", "street", "city", "state", "zip", "country"];
+    let header_row = |fields: &[&str]| csv_row(&fields.iter().map(|f| f.to_string()).collect::<Vec<_>>());
+    let mut out = String::new();
+
+    out.push_str("# profile\r\n");
+    out.push_str(&header_row(&["id", "email", "name", "marketing_opt_in", "created_ts"]));
+    out.push_str(&csv_row(&[
+        export.profile.id.clone(),
+        export.profile.email.clone(),
+        opt(&export.profile.name),
+        export.profile.marketing_opt_in.to_string(),
+        export.profile.created_ts.to_string(),
+    ]));
+
+    out.push_str("\r\n# addresses\r\n");
+    out.push_str(&header_row(&[&["id", "label"][..], &address_header[..], &["is_default"][..]].concat()));
+    for a in &export.addresses {
+        let mut row = vec![a.id.clone(), opt(&a.label)];
+        row.extend(address_fields(Some(&a.to_shipping_address())));
+        row.push(a.is_default.to_string());
+        out.push_str(&csv_row(&row));
+    }
+
+    out.push_str("\r\n# orders\r\n");
+    out.push_str(&header_row(
+        &[
+            &["id", "status", "created_ts", "total_cents", "shipping_cents", "discount_cents", "tip_cents", "promo_code"][..],
+            &address_header[..],
+            &["shipping_carrier", "shipping_service", "tracking_number", "gift_message", "payment_method", "card_brand"][..],
+        ]
+        .concat(),
+    ));
+    for o in &export.orders {
+        let mut row = vec![
+            o.id.clone(),
+            o.status.clone(),
+            o.created_ts.to_string(),
+            o.total_cents.to_string(),
+            o.shipping_cents.to_string(),
+            o.discount_cents.to_string(),
+            o.tip_cents.to_string(),
+            opt(&o.promo_code),
+        ];
+        row.extend(address_fields(o.shipping_address.as_ref()));
+        row.extend([
+            opt(&o.shipping_carrier),
+            opt(&o.shipping_service),
+            opt(&o.tracking_number),
+            opt(&o.gift_message),
+            opt(&o.payment_method_type),
+            opt(&o.card_brand),
+        ]);
+        out.push_str(&csv_row(&row));
+    }
+
+    out.push_str("\r\n# order_items\r\n");
+    out.push_str(&header_row(&["order_id", "product_id", "product_name", "style_name", "quantity", "price_cents"]));
+    for item in export.orders.iter().flat_map(|o| &o.items) {
+        out.push_str(&csv_row(&[
+            item.order_id.clone(),
+            item.product_id.clone(),
+            opt(&item.product_name),
+            opt(&item.style_name),
+            item.quantity.to_string(),
+            item.price_cents.to_string(),
+        ]));
+    }
+
+    out.push_str("\r\n# subscriptions\r\n");
+    out.push_str(&header_row(
+        &[&["id", "product_id", "status", "created_ts", "current_period_end"][..], &address_header[..]].concat(),
+    ));
+    for s in &export.subscriptions {
+        let mut row = vec![
+            s.id.clone(),
+            s.product_id.clone(),
+            s.status.clone(),
+            s.created_ts.to_string(),
+            opt(&s.current_period_end),
+        ];
+        row.extend(address_fields(s.shipping_address.as_ref()));
+        out.push_str(&csv_row(&row));
+    }
+
+    out.push_str("\r\n# newsletter\r\n");
+    out.push_str(&header_row(&["email", "status", "subscribed_ts", "confirmed_ts", "new_drops", "restocks", "sales"]));
+    if let Some(n) = &export.newsletter {
+        out.push_str(&csv_row(&[
+            n.email.clone(),
+            n.status.clone(),
+            n.subscribed_ts.to_string(),
+            opt(&n.confirmed_ts),
+            n.wants_new_drops.to_string(),
+            n.wants_restocks.to_string(),
+            n.wants_sales.to_string(),
+        ]));
+    }
+
+    out.push_str("\r\n# restock_alerts\r\n");
+    out.push_str(&header_row(&["product_id", "style_id", "notified", "created_ts"]));
+    for n in &export.restock_alerts {
+        out.push_str(&csv_row(&[
+            n.product_id.clone(),
+            opt(&n.style_id),
+            n.notified.to_string(),
+            n.created_ts.to_string(),
+        ]));
+    }
+
+    out.push_str("\r\n# wishlist\r\n");
+    out.push_str(&header_row(&["product_id", "created_ts"]));
+    for w in &export.wishlist {
+        out.push_str(&csv_row(&[w.product_id.clone(), w.created_ts.to_string()]));
+    }
+
+    out
+}
+
+/// Download everything stored about the signed-in customer
+async fn export_data(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Query(query): Query<ExportQuery>,
+) -> AppResult<Response> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let export = collect_export(&conn, &user.id).await?;
+
+    let (content_type, extension, body) = match query.format.as_deref().unwrap_or("json") {
+        "json" => (
+            "application/json",
+            "json",
+            serde_json::to_string_pretty(&export).map_err(|e| AppError::Internal(e.to_string()))?,
+        ),
+        "csv" => ("text/csv; charset=utf-8", "csv", export_csv(&export)),
+        other => return Err(AppError::BadRequest(format!("Unknown export format: {}", other))),
+    };
+
+    tracing::info!("User {} exported their data as {}", user.id, extension);
+    let disposition = format!("attachment; filename=\"caterpillar-clay-data-{}.{}\"", export.exported_ts, extension);
+    Ok((
+        [(header::CONTENT_TYPE, content_type.to_string()), (header::CONTENT_DISPOSITION, disposition)],
+        body,
+    )
+        .into_response())
+}
+
+#[derive(Deserialize)]
+pub struct DeleteAccountRequest {
+    /// Must match the account email, so a stray request can't delete it
+    pub confirm_email: String,
+}
+
+/// Erase the signed-in customer's personal data and delete their Clerk
+/// account. Orders are kept with their totals and items (the shop needs them
+/// for its books), but lose the name, street address and gift messages.
+async fn delete_account(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Json(payload): Json<DeleteAccountRequest>,
+) -> AppResult<Json<serde_json::Value>> {
+    if !payload.confirm_email.trim().eq_ignore_ascii_case(&user.email) {
+        return Err(AppError::BadRequest("Type your account email to confirm".to_string()));
+    }
+    if user.is_admin {
+        return Err(AppError::BadRequest(
+            "Staff accounts can't be deleted here; ask the owner to remove your role first".to_string(),
+        ));
+    }
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+
+    let orders = Order::list_by_user(&conn, &user.id).await?;
+    if orders.iter().any(|o| OPEN_ORDER_STATUSES.contains(&o.status.as_str())) {
+        return Err(AppError::BadRequest(
+            "You have orders on the way; delete your account once they've arrived".to_string(),
+        ));
+    }
+    let subscriptions = Subscription::list_by_user(&conn, &user.id).await?;
+    if subscriptions.iter().any(|s| s.is_live()) {
+        return Err(AppError::BadRequest(
+            "Cancel your subscriptions, and let the last one end, before deleting your account".to_string(),
+        ));
+    }
+
+    let tx = conn.transaction().await.map_err(AppError::from)?;
+    let result = async {
+        Order::anonymize_for_user(&tx, &user.id).await?;
+        Subscription::anonymize_for_user(&tx, &user.id).await?;
+        Address::delete_all_for_user(&tx, &user.id).await?;
+        WishlistItem::clear(&tx, &user.id).await?;
+        NewsletterSubscriber::unsubscribe_by_email(&tx, &user.email).await?;
+        ProductNotification::delete_by_email(&tx, &user.email).await?;
+        EmailLog::redact_recipient(&tx, &user.email, &format!("deleted-{}@deleted.invalid", user.id)).await?;
+        User::anonymize(&tx, &user.id).await?;
+        Ok::<(), AppError>(())
+    }
+    .await;
+
+    match result {
+        Ok(()) => tx.commit().await.map_err(AppError::from)?,
+        Err(e) => {
+            let _ = tx.rollback().await;
+            return Err(e);
+        }
+    }
+
+    // The local data is already gone; a leftover provider account could only
+    // sign in to a fresh, empty one
+    if let Err(e) = state.auth.delete_user(&user.clerk_id).await {
+        tracing::error!(
+            "Deleted user {} locally but not their {} account {}: {}",
+            user.id,
+            state.auth.name(),
+            user.clerk_id,
+            e
+        );
+    }
+
+    tracing::info!("User {} deleted their account", user.id);
+    Ok(Json(serde_json::json!({"success": true})))
+}
@@ -1,54 +1,73 @@
+pub mod addresses;
 pub mod admin;
 pub mod auth;
 pub mod cart;
+pub mod me;
 pub mod newsletter;
 pub mod orders;
 pub mod products;
 pub mod settings;
 pub mod shipping;
+pub mod subscriptions;
+pub mod testing;
 pub mod webhooks;
 
 use axum::{middleware, response::Redirect, routing::get, Router};
 use libsql::Database;
 use std::sync::Arc;
 use tower_http::cors::CorsLayer;
+use tower_http::limit::RequestBodyLimitLayer;
 use tower_http::services::{ServeDir, ServeFile};
 use tower_http::trace::TraceLayer;
 
 use crate::config::Config;
 use crate::middleware::auth::auth_middleware;
 use crate::middleware::rate_limit::rate_limit_middleware;
-use crate::services::{ClerkService, EmailService, JwksVerifier, RateLimiter, ResendService, ShippoService, StripeService};
+use crate::middleware::request_id::{request_id_middleware, REQUEST_ID_HEADER};
+use crate::services::{
+    AuthProvider, ClerkService, EmailService, MockPaymentProvider, RateLimiter, ResendService, ShippoService,
+    StripeService, TokenService,
+};
 use crate::storage::StorageBackend;
 
 #[derive(Clone)]
 pub struct AppState {
     pub db: Arc<Database>,
     pub config: Config,
+    /// Verifies session tokens and supplies user profiles (Clerk or a self-hosted issuer)
+    pub auth: Arc<dyn AuthProvider>,
+    /// Clerk's webhooks and API, for the Clerk user sync
     pub clerk: ClerkService,
-    pub jwks: JwksVerifier,
     pub stripe: StripeService,
     pub shippo: ShippoService,
     pub email: Option<EmailService>,
     pub resend: Option<ResendService>,
     pub storage: Arc<dyn StorageBackend>,
-    pub rate_limiter: Option<RateLimiter>,
+    pub rate_limiter: RateLimiter,
+    /// Signs capability tokens for guest order views and label downloads
+    pub tokens: TokenService,
+    /// Simulated payments for local testing mode (replaces Stripe checkout and refunds)
+    pub mock_payments: Option<MockPaymentProvider>,
 }
 
+/// Largest webhook payload accepted (Stripe events with many line items)
+const WEBHOOK_BODY_LIMIT: usize = 1024 * 1024;
+
 pub fn create_router(state: AppState) -> Router {
     // Log rate limiting status
-    if state.rate_limiter.is_some() {
-        tracing::info!(
-            "Distributed rate limiting enabled (Upstash): {} requests/minute",
-            state.config.rate_limit_general
-        );
-    } else {
-        tracing::warn!("Rate limiting disabled (no Upstash Redis configured)");
-    }
+    tracing::info!(
+        "{} rate limiting enabled: {} requests/minute, auth/signup {}, checkout {}, burst {}%",
+        if state.rate_limiter.is_distributed() { "Distributed (Upstash)" } else { "In-process" },
+        state.config.rate_limit_general,
+        state.config.rate_limit_auth,
+        state.config.rate_limit_checkout,
+        state.config.rate_limit_burst_percent
+    );
 
     // Webhook routes (exempt from rate limiting)
     let webhook_routes = Router::new()
-        .merge(webhooks::routes());
+        .merge(webhooks::routes())
+        .layer(RequestBodyLimitLayer::new(WEBHOOK_BODY_LIMIT));
 
     // Public routes
     let public_routes = Router::new()
@@ -56,11 +75,23 @@ pub fn create_router(state: AppState) -> Router {
         .merge(auth::routes())
         .merge(settings::routes())
         .merge(newsletter::routes())
-        .merge(shipping::routes());
+        .merge(shipping::routes())
+        .merge(orders::public_routes());
+
+    // Simulated checkout completion only exists when payments are mocked
+    let public_routes = if state.mock_payments.is_some() {
+        tracing::warn!("TESTING MODE (local) - Stripe payments are simulated!");
+        public_routes.merge(testing::routes())
+    } else {
+        public_routes
+    };
 
     let protected_routes = Router::new()
         .merge(orders::routes())
         .merge(cart::routes())
+        .merge(addresses::routes())
+        .merge(me::routes())
+        .merge(subscriptions::routes())
         .layer(middleware::from_fn_with_state(state.clone(), auth_middleware));
 
     // Admin auth is checked directly in handler (bypasses middleware issues with nested routers)
@@ -77,6 +108,7 @@ pub fn create_router(state: AppState) -> Router {
     let rate_limited_api = Router::new()
         .merge(public_routes)
         .merge(protected_routes)
+        .layer(RequestBodyLimitLayer::new(state.config.max_json_body_bytes))
         .layer(middleware::from_fn_with_state(state.clone(), rate_limit_middleware));
 
     // Admin files are in admin_static/ (not static/) so fallback won't serve them
@@ -90,7 +122,16 @@ pub fn create_router(state: AppState) -> Router {
         .fallback_service(
             ServeDir::new("static").fallback(ServeFile::new("static/index.html"))
         )
-        .layer(TraceLayer::new_for_http())
+        // Every log line inside a request carries its ID, e.g. Stripe and Shippo errors
+        .layer(TraceLayer::new_for_http().make_span_with(|req: &axum::http::Request<axum::body::Body>| {
+            let request_id = req
+                .headers()
+                .get(REQUEST_ID_HEADER)
+                .and_then(|h| h.to_str().ok())
+                .unwrap_or("-");
+            tracing::info_span!("request", method = %req.method(), uri = %req.uri(), request_id = %request_id)
+        }))
+        .layer(middleware::from_fn(request_id_middleware))
         .layer(CorsLayer::permissive())
         .with_state(state)
 }
@@ -1,24 +1,234 @@
 use axum::{
-    extract::{Query, State},
-    response::{Html, Redirect},
+    extract::{Path, Query, State},
+    http::{header, HeaderMap},
+    response::{Html, IntoResponse, Redirect},
     routing::{get, post},
-    Json, Router,
+    Form, Json, Router,
 };
 use serde::{Deserialize, Serialize};
 
 use crate::error::{AppError, AppResult};
-use crate::models::NewsletterSubscriber;
+use crate::models::newsletter_tracking::record_event;
+use crate::models::{NewsletterLink, NewsletterSubscriber};
 use crate::routes::AppState;
+use crate::services::i18n::{locale_from_headers, normalize_locale, LOCALES};
+
+/// 1x1 transparent GIF served as the open-tracking pixel
+const TRACKING_PIXEL: &[u8] = &[
+    0x47, 0x49, 0x46, 0x38, 0x39, 0x61, 0x01, 0x00, 0x01, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0xff, 0xff, 0xff, 0x21, 0xf9, 0x04, 0x01, 0x00, 0x00, 0x00, 0x00, 0x2c, 0x00, 0x00, 0x00, 0x00,
+    0x01, 0x00, 0x01, 0x00, 0x00, 0x02, 0x02, 0x44, 0x01, 0x00, 0x3b,
+];
 
 pub fn routes() -> Router<AppState> {
     Router::new()
         .route("/newsletter/subscribe", post(subscribe))
+        .route("/newsletter/confirm", get(confirm))
+        .route("/newsletter/preferences", get(preferences))
+        .route("/newsletter/preferences", post(save_preferences))
         .route("/newsletter/unsubscribe", get(unsubscribe))
+        .route("/newsletter/unsubscribe", post(one_click_unsubscribe))
+        .route("/newsletter/track/open/{campaign_id}", get(track_open))
+        .route("/newsletter/track/click/{link_id}", get(track_click))
+}
+
+#[derive(Deserialize)]
+pub struct PreferencesQuery {
+    pub token: String,
+}
+
+/// Preference-center form; unchecked boxes are simply missing
+#[derive(Deserialize)]
+pub struct PreferencesForm {
+    pub token: String,
+    pub new_drops: Option<String>,
+    pub restocks: Option<String>,
+    pub sales: Option<String>,
+    pub locale: Option<String>,
+}
+
+/// The preference center page for a subscriber, or a notice when the link is invalid
+fn preferences_page(subscriber: Option<&NewsletterSubscriber>, notice: Option<&str>) -> String {
+    let content = match subscriber {
+        Some(subscriber) => {
+            let checkbox = |name: &str, label: &str, checked: bool| {
+                format!(
+                    r#"<label><input type="checkbox" name="{}"{}> {}</label>"#,
+                    name,
+                    if checked { " checked" } else { "" },
+                    label
+                )
+            };
+            let current_locale = subscriber.email_locale();
+            let locale_options: String = LOCALES
+                .iter()
+                .map(|locale| {
+                    format!(
+                        r#"<option value="{}"{}>{}</option>"#,
+                        locale,
+                        if *locale == current_locale { " selected" } else { "" },
+                        match *locale {
+                            "es" => "Español",
+                            _ => "English",
+                        }
+                    )
+                })
+                .collect();
+            format!(
+                r#"<h1>Email Preferences</h1>
+        {}
+        <p>Choose what {} hears about from us:</p>
+        <form method="post" action="/api/newsletter/preferences">
+            <input type="hidden" name="token" value="{}">
+            {}
+            {}
+            {}
+            <label>Language <select name="locale">{}</select></label>
+            <button type="submit">Save Preferences</button>
+        </form>
+        <p class="small"><a class="link" href="/api/newsletter/unsubscribe?token={}">Unsubscribe from everything</a></p>"#,
+                notice.map(|n| format!(r#"<p class="notice">{}</p>"#, n)).unwrap_or_default(),
+                subscriber.email.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;"),
+                subscriber.unsubscribe_token,
+                checkbox("new_drops", "New drops", subscriber.wants_new_drops),
+                checkbox("restocks", "Restocks", subscriber.wants_restocks),
+                checkbox("sales", "Sales &amp; announcements", subscriber.wants_sales),
+                locale_options,
+                subscriber.unsubscribe_token
+            )
+        }
+        None => r#"<h1>Link Not Valid</h1>
+        <p>This email is not subscribed to our newsletter.</p>
+        <a class="button" href="/">Back to Shop</a>"#
+            .to_string(),
+    };
+
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>Email Preferences - Caterpillar Clay</title>
+    <link href="https://fonts.googleapis.com/css2?family=Press+Start+2P&display=swap" rel="stylesheet">
+    <style>
+        body {{ font-family: 'Press Start 2P', cursive; background: #F8F8F8; display: flex; align-items: center; justify-content: center; min-height: 100vh; margin: 0; }}
+        .container {{ background: white; padding: 40px; border: 2px solid #E0E0E0; border-radius: 12px; text-align: center; max-width: 400px; }}
+        h1 {{ color: #97BAD9; font-size: 14px; margin-bottom: 20px; }}
+        p {{ font-size: 10px; color: #666; line-height: 2; margin-bottom: 20px; }}
+        label {{ display: block; font-size: 10px; color: #18191B; text-align: left; margin-bottom: 16px; }}
+        select {{ font-family: inherit; font-size: 10px; margin-left: 8px; }}
+        button, .button {{ display: inline-block; background: #97BAD9; color: #18191B; padding: 14px 24px; border: none; text-decoration: none; font-size: 10px; border-radius: 8px; font-family: inherit; cursor: pointer; }}
+        .notice {{ color: #22c55e; }}
+        .small {{ margin-top: 24px; font-size: 8px; }}
+        .link {{ color: #97BAD9; }}
+    </style>
+</head>
+<body>
+    <div class="container">
+        {}
+    </div>
+</body>
+</html>"#,
+        content
+    )
+}
+
+async fn preferences(
+    State(state): State<AppState>,
+    Query(query): Query<PreferencesQuery>,
+) -> AppResult<Html<String>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let subscriber = NewsletterSubscriber::find_by_unsubscribe_token(&conn, &query.token).await?;
+    Ok(Html(preferences_page(subscriber.as_ref(), None)))
+}
+
+async fn save_preferences(
+    State(state): State<AppState>,
+    Form(form): Form<PreferencesForm>,
+) -> AppResult<Html<String>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+
+    let subscriber = match NewsletterSubscriber::find_by_unsubscribe_token(&conn, &form.token).await? {
+        Some(subscriber) => subscriber,
+        None => return Ok(Html(preferences_page(None, None))),
+    };
+
+    NewsletterSubscriber::update_preferences(
+        &conn,
+        &subscriber.id,
+        form.new_drops.is_some(),
+        form.restocks.is_some(),
+        form.sales.is_some(),
+    )
+    .await?;
+
+    if let Some(locale) = form.locale.as_deref().and_then(normalize_locale) {
+        NewsletterSubscriber::set_locale(&conn, &subscriber.id, locale).await?;
+    }
+
+    let subscriber = NewsletterSubscriber::find_by_unsubscribe_token(&conn, &form.token).await?;
+    Ok(Html(preferences_page(subscriber.as_ref(), Some("Preferences saved!"))))
+}
+
+#[derive(Deserialize)]
+pub struct TrackQuery {
+    /// Subscriber ID
+    pub s: Option<String>,
+}
+
+/// Open-tracking pixel. Always serves the image; failing to record never breaks the email.
+async fn track_open(
+    State(state): State<AppState>,
+    Path(campaign_id): Path<String>,
+    Query(query): Query<TrackQuery>,
+) -> impl IntoResponse {
+    if let Some(subscriber_id) = query.s.as_deref().filter(|s| !s.is_empty()) {
+        let recorded = match state.db.connect() {
+            Ok(conn) => record_event(&conn, &campaign_id, subscriber_id, "open", None).await,
+            Err(e) => Err(AppError::from(e)),
+        };
+        if let Err(e) = recorded {
+            tracing::warn!("Failed to record open for campaign {}: {}", campaign_id, e);
+        }
+    }
+
+    (
+        [
+            (header::CONTENT_TYPE, "image/gif"),
+            (header::CACHE_CONTROL, "no-store, no-cache, must-revalidate"),
+        ],
+        TRACKING_PIXEL,
+    )
+}
+
+/// Tracked campaign link: record the click, then send the subscriber on to the real URL
+async fn track_click(
+    State(state): State<AppState>,
+    Path(link_id): Path<String>,
+    Query(query): Query<TrackQuery>,
+) -> AppResult<Redirect> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+
+    let link = match NewsletterLink::find_by_id(&conn, &link_id).await? {
+        Some(link) => link,
+        None => return Ok(Redirect::to("/")),
+    };
+
+    if let Some(subscriber_id) = query.s.as_deref().filter(|s| !s.is_empty()) {
+        if let Err(e) = record_event(&conn, &link.campaign_id, subscriber_id, "click", Some(&link.id)).await {
+            tracing::warn!("Failed to record click on link {}: {}", link.id, e);
+        }
+    }
+
+    Ok(Redirect::to(&link.url))
 }
 
 #[derive(Deserialize)]
 pub struct SubscribeRequest {
     pub email: String,
+    /// Email language; the Accept-Language header is used when missing
+    pub locale: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -29,6 +239,7 @@ pub struct SubscribeResponse {
 
 async fn subscribe(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(payload): Json<SubscribeRequest>,
 ) -> AppResult<Json<SubscribeResponse>> {
     // Basic email validation
@@ -40,32 +251,128 @@ async fn subscribe(
     }
 
     let conn = state.db.connect().map_err(AppError::from)?;
-    let subscriber = NewsletterSubscriber::subscribe(&conn, &payload.email).await?;
+    let locale = payload
+        .locale
+        .as_deref()
+        .and_then(normalize_locale)
+        .or_else(|| locale_from_headers(&headers));
+    let subscriber = NewsletterSubscriber::subscribe(&conn, &payload.email, locale).await?;
+
+    if subscriber.is_confirmed() {
+        return Ok(Json(SubscribeResponse {
+            success: true,
+            message: "You're already subscribed!".to_string(),
+        }));
+    }
 
-    // Send welcome email if Resend is configured
-    if let Some(resend) = &state.resend {
-        if let Err(e) = resend.send_welcome_email(&subscriber.email, &subscriber.unsubscribe_token).await {
-            tracing::error!("Failed to send welcome email: {}", e);
+    // Send (or resend) the confirmation email if Resend is configured
+    if let (Some(resend), Some(confirm_token)) = (&state.resend, &subscriber.confirm_token) {
+        if let Err(e) = resend.send_confirmation_email(&subscriber.email, confirm_token, subscriber.email_locale()).await {
+            tracing::error!("Failed to send newsletter confirmation email: {}", e);
         }
     }
 
     Ok(Json(SubscribeResponse {
         success: true,
-        message: "Thanks for subscribing! You'll be notified when we add new items.".to_string(),
+        message: "Almost done! Check your inbox and confirm your subscription.".to_string(),
     }))
 }
 
+#[derive(Deserialize)]
+pub struct ConfirmQuery {
+    pub token: String,
+}
+
+/// Confirmation link from the opt-in email; the welcome email follows once confirmed
+async fn confirm(
+    State(state): State<AppState>,
+    Query(query): Query<ConfirmQuery>,
+) -> AppResult<Html<String>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let confirmed = NewsletterSubscriber::confirm_by_token(&conn, &query.token).await?;
+
+    let (title, message) = match &confirmed {
+        Some(subscriber) => {
+            if let Some(resend) = &state.resend {
+                if let Err(e) = resend
+                    .send_welcome_email(&subscriber.email, &subscriber.unsubscribe_token, subscriber.email_locale())
+                    .await
+                {
+                    tracing::error!("Failed to send welcome email: {}", e);
+                }
+                if let Err(e) = resend.add_contact(&subscriber.email).await {
+                    tracing::warn!("Failed to add {} to the Resend audience: {}", subscriber.email, e);
+                }
+            }
+            ("Subscribed", "Thanks for confirming! You'll be notified when we add new items.")
+        }
+        None => ("Link Expired", "This confirmation link has already been used or is no longer valid."),
+    };
+
+    Ok(Html(format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>{} - Caterpillar Clay</title>
+    <link href="https://fonts.googleapis.com/css2?family=Press+Start+2P&display=swap" rel="stylesheet">
+    <style>
+        body {{ font-family: 'Press Start 2P', cursive; background: #F8F8F8; display: flex; align-items: center; justify-content: center; min-height: 100vh; margin: 0; }}
+        .container {{ background: white; padding: 40px; border: 2px solid #E0E0E0; border-radius: 12px; text-align: center; max-width: 400px; }}
+        h1 {{ color: #97BAD9; font-size: 14px; margin-bottom: 20px; }}
+        p {{ font-size: 10px; color: #666; line-height: 2; margin-bottom: 20px; }}
+        a {{ display: inline-block; background: #97BAD9; color: #18191B; padding: 14px 24px; text-decoration: none; font-size: 10px; border-radius: 8px; font-family: inherit; }}
+    </style>
+</head>
+<body>
+    <div class="container">
+        <h1>{}</h1>
+        <p>{}</p>
+        <a href="/">Back to Shop</a>
+    </div>
+</body>
+</html>"#,
+        title, title, message
+    )))
+}
+
 #[derive(Deserialize)]
 pub struct UnsubscribeQuery {
     pub token: String,
 }
 
+/// Remove the subscriber with this token. Returns false if there was none.
+async fn unsubscribe_token(state: &AppState, token: &str) -> AppResult<bool> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let subscriber = NewsletterSubscriber::find_by_unsubscribe_token(&conn, token).await?;
+    let success = NewsletterSubscriber::unsubscribe_by_token(&conn, token).await?;
+
+    if let (true, Some(subscriber), Some(resend)) = (success, &subscriber, &state.resend) {
+        if let Err(e) = resend.remove_contact(&subscriber.email).await {
+            tracing::warn!("Failed to remove {} from the Resend audience: {}", subscriber.email, e);
+        }
+    }
+
+    Ok(success)
+}
+
+/// RFC 8058 one-click unsubscribe: mail clients POST `List-Unsubscribe=One-Click` to the
+/// List-Unsubscribe URL without showing the user a page. An unknown token still gets 200,
+/// since the address is no longer subscribed either way.
+async fn one_click_unsubscribe(
+    State(state): State<AppState>,
+    Query(query): Query<UnsubscribeQuery>,
+) -> AppResult<Json<serde_json::Value>> {
+    unsubscribe_token(&state, &query.token).await?;
+    Ok(Json(serde_json::json!({"success": true})))
+}
+
 async fn unsubscribe(
     State(state): State<AppState>,
     Query(query): Query<UnsubscribeQuery>,
 ) -> AppResult<Html<String>> {
-    let conn = state.db.connect().map_err(AppError::from)?;
-    let success = NewsletterSubscriber::unsubscribe_by_token(&conn, &query.token).await?;
+    let success = unsubscribe_token(&state, &query.token).await?;
 
     let html = if success {
         r#"<!DOCTYPE html>
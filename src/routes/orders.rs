@@ -1,40 +1,160 @@
 use axum::{
-    extract::{Extension, Path, State},
-    routing::get,
+    extract::{ConnectInfo, Extension, Path, Query, State},
+    http::HeaderMap,
+    response::Redirect,
+    routing::{get, post},
     Json, Router,
 };
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
 
 use crate::error::{AppError, AppResult};
+use crate::middleware::capability::{Granted, LabelDownload, OrderView};
+use crate::middleware::ip_allowlist::client_address;
 use crate::middleware::AuthUser;
-use crate::models::{Order, OrderItem, Product, ShippingAddress};
+use crate::models::{Money, Order, OrderItemDetail, Product, ProductStyle, ShippingAddress};
+use crate::routes::cart::{start_checkout, CartItem, CheckoutRequest};
 use crate::routes::AppState;
+use crate::services::shippo::{ShippoService, TrackingLocation};
+use crate::services::token::Capability;
 
 #[derive(Serialize)]
 pub struct OrderResponse {
     pub id: String,
     pub status: String,
     pub total_cents: i32,
-    pub total: f64,
+    pub total: Money,
     pub shipping_address: Option<ShippingAddress>,
     pub tracking_number: Option<String>,
+    pub estimated_delivery_days: Option<i32>,
+    pub expected_delivery_date: Option<String>,
+    pub is_gift: bool,
+    pub gift_message: Option<String>,
+    pub gift_wrap: bool,
+    pub gift_wrap_cents: i32,
+    pub promo_code: Option<String>,
+    pub discount_cents: i32,
+    pub tip_cents: i32,
     pub items: Vec<OrderItemResponse>,
     pub created_ts: i64,
 }
 
+impl OrderResponse {
+    fn from_order(order: Order, items: Vec<OrderItemResponse>) -> Self {
+        let shipping_address = order.get_shipping_address();
+        let expected_delivery_date = order
+            .expected_delivery_ts()
+            .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0))
+            .map(|dt| dt.format("%Y-%m-%d").to_string());
+
+        Self {
+            id: order.id,
+            status: order.status,
+            total_cents: order.total_cents,
+            total: Money::usd(order.total_cents),
+            shipping_address,
+            tracking_number: order.tracking_number,
+            estimated_delivery_days: order.estimated_delivery_days,
+            expected_delivery_date,
+            is_gift: order.is_gift,
+            gift_message: order.gift_message,
+            gift_wrap: order.gift_wrap,
+            gift_wrap_cents: order.gift_wrap_cents,
+            promo_code: order.promo_code,
+            discount_cents: order.discount_cents,
+            tip_cents: order.tip_cents,
+            items,
+            created_ts: order.created_ts,
+        }
+    }
+}
+
 #[derive(Serialize)]
 pub struct OrderItemResponse {
     pub product_id: String,
     pub product_name: String,
+    pub style_name: Option<String>,
     pub quantity: i32,
     pub price_cents: i32,
-    pub price: f64,
+    pub price: Money,
+}
+
+#[derive(Serialize)]
+pub struct TrackingResponse {
+    pub order_id: String,
+    pub tracking_number: String,
+    pub carrier: String,
+    pub status: Option<String>,
+    pub status_details: Option<String>,
+    pub eta: Option<String>,
+    pub events: Vec<TrackingEventResponse>,
+}
+
+#[derive(Serialize)]
+pub struct TrackingEventResponse {
+    pub status: String,
+    pub status_details: Option<String>,
+    pub status_date: Option<String>,
+    pub location: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct TrackQuery {
+    /// Order number from the confirmation email, or the full order ID
+    pub order: String,
+    pub email: String,
+}
+
+#[derive(Serialize)]
+pub struct GuestTrackingResponse {
+    pub order_number: String,
+    pub status: String,
+    pub expected_delivery_date: Option<String>,
+    /// Present once the order has a tracking number
+    pub tracking: Option<TrackingResponse>,
+    pub order_id: String,
+    /// Opens the full order at /api/orders/{order_id}/guest without signing in
+    pub order_token: String,
+}
+
+/// Shortest order reference accepted, matching the number shown in emails
+const MIN_ORDER_REFERENCE_LEN: usize = 8;
+
+/// Lookups per IP per minute; each one is a guess at an order/email pair
+const TRACK_LOOKUPS_PER_MINUTE: u32 = 10;
+
+/// How long a guest can keep viewing an order after looking it up
+const GUEST_ORDER_TOKEN_TTL_SECS: i64 = 7 * 24 * 60 * 60;
+
+#[derive(Serialize)]
+pub struct ReorderResponse {
+    pub checkout_url: String,
+    pub order_id: String,
+    pub skipped: Vec<ReorderSkippedItem>,
+}
+
+#[derive(Serialize)]
+pub struct ReorderSkippedItem {
+    pub product_id: String,
+    pub product_name: String,
+    pub requested_quantity: i32,
+    pub available_quantity: i32,
+    pub reason: String,
 }
 
 pub fn routes() -> Router<AppState> {
     Router::new()
         .route("/orders", get(list_orders))
         .route("/orders/{id}", get(get_order))
+        .route("/orders/{id}/tracking", get(get_order_tracking))
+        .route("/orders/{id}/reorder", post(reorder))
+}
+
+pub fn public_routes() -> Router<AppState> {
+    Router::new()
+        .route("/track", get(track_order))
+        .route("/orders/{id}/guest", get(get_guest_order))
+        .route("/orders/{id}/label", get(download_label))
 }
 
 async fn list_orders(
@@ -44,22 +164,16 @@ async fn list_orders(
     let conn = state.db.connect().map_err(AppError::from)?;
     let orders = Order::list_by_user(&conn, &user.id).await?;
 
-    let mut responses = Vec::new();
-    for order in orders {
-        let items = Order::get_items(&conn, &order.id).await?;
-        let item_responses = build_item_responses(&conn, items).await?;
+    let order_ids: Vec<String> = orders.iter().map(|o| o.id.clone()).collect();
+    let mut items_by_order = Order::get_items_for_orders(&conn, &order_ids).await?;
 
-        responses.push(OrderResponse {
-            id: order.id.clone(),
-            status: order.status.clone(),
-            total_cents: order.total_cents,
-            total: order.total_cents as f64 / 100.0,
-            shipping_address: order.get_shipping_address(),
-            tracking_number: order.tracking_number.clone(),
-            items: item_responses,
-            created_ts: order.created_ts,
-        });
-    }
+    let responses = orders
+        .into_iter()
+        .map(|order| {
+            let items = items_by_order.remove(&order.id).unwrap_or_default();
+            OrderResponse::from_order(order, build_item_responses(items))
+        })
+        .collect();
 
     Ok(Json(responses))
 }
@@ -79,41 +193,327 @@ async fn get_order(
         return Err(AppError::Forbidden("Access denied".to_string()));
     }
 
-    let items = Order::get_items(&conn, &order.id).await?;
-    let item_responses = build_item_responses(&conn, items).await?;
-
-    Ok(Json(OrderResponse {
-        id: order.id.clone(),
-        status: order.status.clone(),
-        total_cents: order.total_cents,
-        total: order.total_cents as f64 / 100.0,
-        shipping_address: order.get_shipping_address(),
-        tracking_number: order.tracking_number.clone(),
-        items: item_responses,
-        created_ts: order.created_ts,
+    let items = Order::get_items_for_orders(&conn, std::slice::from_ref(&order.id))
+        .await?
+        .remove(&order.id)
+        .unwrap_or_default();
+
+    Ok(Json(OrderResponse::from_order(order, build_item_responses(items))))
+}
+
+async fn get_order_tracking(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<String>,
+) -> AppResult<Json<TrackingResponse>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let order = Order::find_by_id(&conn, &id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Order not found".to_string()))?;
+
+    // Verify ownership
+    if order.user_id.as_ref() != Some(&user.id) {
+        return Err(AppError::Forbidden("Access denied".to_string()));
+    }
+
+    let tracking_number = order
+        .tracking_number
+        .clone()
+        .ok_or_else(|| AppError::NotFound("Order has not shipped yet".to_string()))?;
+
+    Ok(Json(fetch_tracking(&state, &order, tracking_number).await?))
+}
+
+/// Order status and tracking for customers checking without signing in. The
+/// email has to belong to the order's customer; a wrong email looks the same
+/// as an unknown order.
+async fn track_order(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Query(query): Query<TrackQuery>,
+) -> AppResult<Json<GuestTrackingResponse>> {
+    let ip = client_address(&headers, Some(peer), state.config.trusted_proxy_hops);
+    match state.rate_limiter.check_scoped_rate_limit("track", &ip, TRACK_LOOKUPS_PER_MINUTE).await {
+        Ok(decision) if decision.allowed => {}
+        Ok(_) => {
+            tracing::warn!("Order tracking lookups rate limited for IP: {}", ip);
+            return Err(AppError::TooManyRequests(
+                "Too many tracking lookups, please try again in a minute".to_string(),
+            ));
+        }
+        Err(e) => tracing::error!("Rate limiter error: {} - allowing request", e),
+    }
+
+    let reference = query.order.trim().trim_start_matches('#').to_lowercase();
+    let email = query.email.trim();
+    let valid_reference = reference.len() >= MIN_ORDER_REFERENCE_LEN
+        && reference.chars().all(|c| c.is_ascii_hexdigit() || c == '-');
+    if !valid_reference || email.is_empty() {
+        return Err(AppError::BadRequest("Enter your order number and email".to_string()));
+    }
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let order = Order::find_by_reference_and_email(&conn, &reference, email)
+        .await?
+        .ok_or_else(|| AppError::NotFound("No order found for that order number and email".to_string()))?;
+
+    let expected_delivery_date = order
+        .expected_delivery_ts()
+        .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0))
+        .map(|dt| dt.format("%Y-%m-%d").to_string());
+
+    // The status is still worth showing when the carrier can't be reached
+    let tracking = match order.tracking_number.clone() {
+        Some(tracking_number) => match fetch_tracking(&state, &order, tracking_number).await {
+            Ok(tracking) => Some(tracking),
+            Err(e) => {
+                tracing::warn!("Failed to fetch tracking for order {}: {}", order.id, e);
+                None
+            }
+        },
+        None => None,
+    };
+
+    let (order_token, _) = state.tokens.issue(Capability::ViewOrder, &order.id, GUEST_ORDER_TOKEN_TTL_SECS);
+
+    Ok(Json(GuestTrackingResponse {
+        order_number: order.id[..MIN_ORDER_REFERENCE_LEN].to_string(),
+        status: order.status,
+        expected_delivery_date,
+        tracking,
+        order_id: order.id,
+        order_token,
     }))
 }
 
-async fn build_item_responses(
-    conn: &libsql::Connection,
-    items: Vec<OrderItem>,
-) -> AppResult<Vec<OrderItemResponse>> {
-    let mut responses = Vec::new();
+/// The full order for a guest holding the token from `/track`
+async fn get_guest_order(
+    State(state): State<AppState>,
+    grant: Granted<OrderView>,
+    Path(id): Path<String>,
+) -> AppResult<Json<OrderResponse>> {
+    if grant.subject != id {
+        return Err(AppError::Forbidden("Access denied".to_string()));
+    }
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let order = Order::find_by_id(&conn, &id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Order not found".to_string()))?;
+
+    let items = Order::get_items_for_orders(&conn, std::slice::from_ref(&order.id))
+        .await?
+        .remove(&order.id)
+        .unwrap_or_default();
+
+    Ok(Json(OrderResponse::from_order(order, build_item_responses(items))))
+}
+
+/// Send the holder of a label link (issued from the admin) to the label PDF
+async fn download_label(
+    State(state): State<AppState>,
+    grant: Granted<LabelDownload>,
+    Path(id): Path<String>,
+) -> AppResult<Redirect> {
+    if grant.subject != id {
+        return Err(AppError::Forbidden("Access denied".to_string()));
+    }
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let label_url = Order::find_by_id(&conn, &id)
+        .await?
+        .and_then(|order| order.label_url)
+        .ok_or_else(|| AppError::NotFound("Label not found".to_string()))?;
+
+    Ok(Redirect::temporary(&label_url))
+}
+
+/// The carrier's tracking history for a shipped order, newest event first
+async fn fetch_tracking(state: &AppState, order: &Order, tracking_number: String) -> AppResult<TrackingResponse> {
+    let carrier = ShippoService::carrier_token(order.shipping_carrier.as_deref().unwrap_or("usps"));
+
+    let tracking = state.shippo.get_tracking_cached(&carrier, &tracking_number).await?;
+
+    // Shippo returns history oldest-first; show newest first for the timeline
+    let events: Vec<TrackingEventResponse> = tracking
+        .tracking_history
+        .into_iter()
+        .rev()
+        .map(|event| TrackingEventResponse {
+            status: event.status,
+            status_details: event.status_details,
+            status_date: event.status_date,
+            location: event.location.as_ref().and_then(format_location),
+        })
+        .collect();
+
+    Ok(TrackingResponse {
+        order_id: order.id.clone(),
+        tracking_number,
+        carrier,
+        status: tracking.tracking_status.as_ref().map(|s| s.status.clone()),
+        status_details: tracking.tracking_status.and_then(|s| s.status_details),
+        eta: tracking.eta,
+        events,
+    })
+}
+
+async fn reorder(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<String>,
+) -> AppResult<Json<ReorderResponse>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let order = Order::find_by_id(&conn, &id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Order not found".to_string()))?;
+
+    // Verify ownership
+    if order.user_id.as_ref() != Some(&user.id) {
+        return Err(AppError::Forbidden("Access denied".to_string()));
+    }
+
+    let shipping_address = order
+        .get_shipping_address()
+        .ok_or_else(|| AppError::BadRequest("Order has no shipping address".to_string()))?;
+
+    // Keep what's still available, trimming quantities to current stock
+    let mut cart_items = Vec::new();
+    let mut skipped = Vec::new();
 
-    for item in items {
-        let product_name = match Product::find_by_id(conn, &item.product_id).await? {
-            Some(p) => p.name,
-            None => "Unknown Product".to_string(),
+    for item in Order::get_items(&conn, &order.id).await? {
+        let product = match Product::find_by_id(&conn, &item.product_id).await? {
+            Some(p) => p,
+            None => {
+                skipped.push(ReorderSkippedItem {
+                    product_id: item.product_id,
+                    product_name: "Unknown Product".to_string(),
+                    requested_quantity: item.quantity,
+                    available_quantity: 0,
+                    reason: "No longer sold".to_string(),
+                });
+                continue;
+            }
         };
 
-        responses.push(OrderItemResponse {
-            product_id: item.product_id,
-            product_name,
-            quantity: item.quantity,
-            price_cents: item.price_cents,
-            price: item.price_cents as f64 / 100.0,
+        if !product.is_active || product.stock_quantity <= 0 {
+            skipped.push(ReorderSkippedItem {
+                product_id: product.id,
+                product_name: product.name,
+                requested_quantity: item.quantity,
+                available_quantity: 0,
+                reason: if product.is_active { "Out of stock" } else { "No longer available" }.to_string(),
+            });
+            continue;
+        }
+
+        // A style that's gone or sold out makes the item unavailable
+        let mut available = product.stock_quantity;
+        if let Some(ref style_id) = item.style_id {
+            match ProductStyle::get_by_id(&conn, style_id).await? {
+                Some(style) if style.stock_quantity > 0 => {
+                    available = available.min(style.stock_quantity as i32);
+                }
+                _ => {
+                    skipped.push(ReorderSkippedItem {
+                        product_id: product.id,
+                        product_name: product.name,
+                        requested_quantity: item.quantity,
+                        available_quantity: 0,
+                        reason: format!(
+                            "{} is out of stock",
+                            item.style_name.as_deref().unwrap_or("Selected style")
+                        ),
+                    });
+                    continue;
+                }
+            }
+        }
+
+        let quantity = item.quantity.min(available);
+        if quantity < item.quantity {
+            skipped.push(ReorderSkippedItem {
+                product_id: product.id.clone(),
+                product_name: product.name.clone(),
+                requested_quantity: item.quantity,
+                available_quantity: quantity,
+                reason: format!("Only {} left in stock", quantity),
+            });
+        }
+
+        cart_items.push(CartItem {
+            product_id: product.id,
+            quantity,
+            style_id: item.style_id,
         });
     }
 
-    Ok(responses)
+    if cart_items.is_empty() {
+        return Err(AppError::BadRequest(
+            "None of the items from this order are currently available".to_string(),
+        ));
+    }
+
+    // Reuse the previous shipping selection; the customer can still change
+    // the address on the Stripe Checkout page
+    let checkout = start_checkout(
+        &state,
+        &user,
+        CheckoutRequest {
+            items: cart_items,
+            shipping_address: Some(shipping_address),
+            address_id: None,
+            shipping_rate_id: None,
+            shipping_cents: Some(order.shipping_cents),
+            shipping_carrier: order.shipping_carrier,
+            shipping_service: order.shipping_service,
+            estimated_delivery_days: order.estimated_delivery_days,
+            is_gift: false,
+            gift_message: None,
+            gift_wrap: false,
+            promo_code: None,
+            tip_cents: None,
+            payment_method: None,
+        },
+    )
+    .await?;
+
+    tracing::info!("Reorder of {} created order {}", id, checkout.order_id);
+
+    Ok(Json(ReorderResponse {
+        checkout_url: checkout.checkout_url,
+        order_id: checkout.order_id,
+        skipped,
+    }))
+}
+
+fn format_location(location: &TrackingLocation) -> Option<String> {
+    let parts: Vec<&str> = [&location.city, &location.state, &location.country]
+        .iter()
+        .filter_map(|p| p.as_deref())
+        .filter(|p| !p.is_empty())
+        .collect();
+
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join(", "))
+    }
+}
+
+fn build_item_responses(items: Vec<OrderItemDetail>) -> Vec<OrderItemResponse> {
+    items
+        .into_iter()
+        .map(|item| OrderItemResponse {
+            product_id: item.product_id,
+            product_name: item
+                .product_name
+                .unwrap_or_else(|| "Unknown Product".to_string()),
+            style_name: item.style_name,
+            quantity: item.quantity,
+            price_cents: item.price_cents,
+            price: Money::usd(item.price_cents),
+        })
+        .collect()
 }
@@ -1,13 +1,16 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
+    http::HeaderMap,
     routing::{get, post},
     Json, Router,
 };
 use serde::{Deserialize, Serialize};
 
 use crate::error::{AppError, AppResult};
-use crate::models::{Product, ProductImage, ProductNotification, ProductStyle};
+use crate::models::{BoxPreset, Money, Product, ProductImage, ProductNotification, ProductStyle, Setting, ShippingAddress};
+use crate::routes::shipping::{rule_rates, shippo_rates, ParcelSize};
 use crate::routes::AppState;
+use crate::services::i18n::{locale_from_headers, normalize_locale};
 
 #[derive(Serialize)]
 pub struct StyleResponse {
@@ -24,11 +27,13 @@ pub struct ProductResponse {
     pub name: String,
     pub description: Option<String>,
     pub price_cents: i32,
-    pub price: f64,
+    pub price: Money,
     pub images: Vec<String>,
     pub image_ids: Vec<String>,
     pub stock_quantity: i32,
     pub styles: Vec<StyleResponse>,
+    /// Set for subscription products (month or year)
+    pub billing_interval: Option<String>,
 }
 
 impl ProductResponse {
@@ -72,11 +77,12 @@ impl ProductResponse {
             name: product.name,
             description: product.description,
             price_cents: product.price_cents,
-            price: product.price_cents as f64 / 100.0,
+            price: Money::usd(product.price_cents),
             images: image_urls,
             image_ids,
             stock_quantity: product.stock_quantity,
             styles: style_responses,
+            billing_interval: product.billing_interval,
         }
     }
 }
@@ -86,6 +92,7 @@ pub fn public_routes() -> Router<AppState> {
         .route("/products", get(list_products))
         .route("/products/{id}", get(get_product))
         .route("/products/{id}/notify", post(subscribe_notification))
+        .route("/products/{id}/delivery-estimate", get(get_delivery_estimate))
 }
 
 async fn list_products(State(state): State<AppState>) -> AppResult<Json<Vec<ProductResponse>>> {
@@ -121,11 +128,112 @@ async fn get_product(
     Ok(Json(ProductResponse::from_product(product, images, styles, &state)))
 }
 
+#[derive(Deserialize)]
+pub struct DeliveryEstimateQuery {
+    pub zip: String,
+    pub country: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct DeliveryEstimateResponse {
+    pub zip: String,
+    pub handling_days: i64,
+    /// Transit days of the fastest and the cheapest offered rate
+    pub min_transit_days: i32,
+    pub max_transit_days: i32,
+    /// YYYY-MM-DD, counted from today
+    pub earliest_date: String,
+    pub latest_date: String,
+}
+
+/// Rough delivery window for one unit of a product shipped to a zip code, for
+/// "arrives by" hints on product pages. Live rates are cached, so repeated
+/// lookups don't hit Shippo.
+async fn get_delivery_estimate(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<DeliveryEstimateQuery>,
+) -> AppResult<Json<DeliveryEstimateResponse>> {
+    let zip = query.zip.trim().to_uppercase();
+    if zip.is_empty() {
+        return Err(AppError::BadRequest("Zip code is required".to_string()));
+    }
+    let country = query
+        .country
+        .map(|c| c.trim().to_uppercase())
+        .filter(|c| !c.is_empty())
+        .unwrap_or_else(|| "US".to_string());
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+    let product = Product::find_by_id(&conn, &id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Product not found".to_string()))?;
+
+    if !product.is_active {
+        return Err(AppError::NotFound("Product not found".to_string()));
+    }
+
+    let mut parcel = ParcelSize::default();
+    parcel.add(&product, None, 1);
+    parcel.pack(&BoxPreset::list_all(&conn).await?);
+
+    // Only the zip is known, which is enough for the shop's zone rules and for carrier quotes
+    let destination = ShippingAddress {
+        name: "Customer".to_string(),
+        street: String::new(),
+        city: String::new(),
+        state: String::new(),
+        zip: zip.clone(),
+        country: country.clone(),
+    };
+
+    // Same precedence as checkout: the shop's rules first, then live rates
+    let mut rates = rule_rates(&conn, &destination, parcel.weight_grams.round() as i32).await?;
+    if rates.is_empty() {
+        let cache_key = format!("{}:{}:{}", product.id, country, zip);
+        rates = shippo_rates(&state, &conn, &destination, &parcel, Some(&cache_key)).await?;
+    }
+
+    // Rates are sorted by price; the cheapest one bounds the window from above
+    let min_transit_days = rates.iter().filter_map(|r| r.estimated_days).min();
+    let cheapest_days = rates.iter().find_map(|r| r.estimated_days);
+    let (min_transit_days, max_transit_days) = match (min_transit_days, cheapest_days) {
+        (Some(min), Some(cheapest)) => (min, cheapest.max(min)),
+        _ => {
+            return Err(AppError::NotFound(
+                "No delivery estimate available for this zip code".to_string(),
+            ))
+        }
+    };
+
+    let handling_days = Setting::get_handling_days(&conn).await?;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    let date_after = |days: i64| {
+        chrono::DateTime::from_timestamp(now + days * 86400, 0)
+            .map(|dt| dt.format("%Y-%m-%d").to_string())
+            .unwrap_or_default()
+    };
+
+    Ok(Json(DeliveryEstimateResponse {
+        zip,
+        handling_days,
+        min_transit_days,
+        max_transit_days,
+        earliest_date: date_after(handling_days + min_transit_days as i64),
+        latest_date: date_after(handling_days + max_transit_days as i64),
+    }))
+}
+
 #[derive(Deserialize)]
 pub struct NotifyRequest {
     pub email: String,
     #[serde(default)]
     pub style_ids: Vec<String>,
+    /// Email language; the Accept-Language header is used when missing
+    pub locale: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -139,9 +247,15 @@ pub struct NotifyResponse {
 async fn subscribe_notification(
     State(state): State<AppState>,
     Path(product_id): Path<String>,
+    headers: HeaderMap,
     Json(payload): Json<NotifyRequest>,
 ) -> AppResult<Json<NotifyResponse>> {
     let conn = state.db.connect().map_err(AppError::from)?;
+    let locale = payload
+        .locale
+        .as_deref()
+        .and_then(normalize_locale)
+        .or_else(|| locale_from_headers(&headers));
 
     // Verify product exists
     let _product = Product::find_by_id(&conn, &product_id)
@@ -152,14 +266,14 @@ async fn subscribe_notification(
 
     if payload.style_ids.is_empty() {
         // Subscribe to the whole product (legacy behavior for products without styles)
-        ProductNotification::subscribe(&conn, &payload.email, &product_id, None).await?;
+        ProductNotification::subscribe(&conn, &payload.email, &product_id, None, locale).await?;
     } else {
         // Subscribe to specific styles
         for style_id in &payload.style_ids {
             // Verify style exists and belongs to this product
             if let Some(style) = ProductStyle::get_by_id(&conn, style_id).await? {
                 if style.product_id == product_id && style.stock_quantity == 0 {
-                    ProductNotification::subscribe(&conn, &payload.email, &product_id, Some(style_id))
+                    ProductNotification::subscribe(&conn, &payload.email, &product_id, Some(style_id), locale)
                         .await?;
                     subscribed_styles.push(style.name);
                 }
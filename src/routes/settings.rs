@@ -20,10 +20,21 @@ async fn get_artist_info(State(state): State<AppState>) -> AppResult<Json<Artist
 #[derive(Serialize)]
 pub struct SiteSettings {
     pub favicon: Option<String>,
+    pub gift_wrap_fee_cents: i32,
+    pub free_shipping_threshold_cents: Option<i32>,
+    pub minimum_order_cents: Option<i32>,
 }
 
 async fn get_site_settings(State(state): State<AppState>) -> AppResult<Json<SiteSettings>> {
     let conn = state.db.connect().map_err(AppError::from)?;
     let favicon = Setting::get(&conn, "site_favicon").await?;
-    Ok(Json(SiteSettings { favicon }))
+    let gift_wrap_fee_cents = Setting::get_gift_wrap_fee_cents(&conn).await?;
+    let free_shipping_threshold_cents = Setting::get_free_shipping_threshold_cents(&conn).await?;
+    let minimum_order_cents = Setting::get_minimum_order_cents(&conn).await?;
+    Ok(Json(SiteSettings {
+        favicon,
+        gift_wrap_fee_cents,
+        free_shipping_threshold_cents,
+        minimum_order_cents,
+    }))
 }
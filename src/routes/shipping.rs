@@ -3,17 +3,37 @@ use axum::{
     routing::post,
     Json, Router,
 };
+use libsql::Connection;
 use serde::{Deserialize, Serialize};
 
 use crate::error::{AppError, AppResult};
-use crate::models::{Product, Setting, ShippingAddress};
+use crate::models::{BoxPreset, Money, Product, ProductImage, ProductStyle, Setting, ShippingAddress, ShippingRule, LOCAL_DELIVERY_RATE_ID};
 use crate::routes::AppState;
 use crate::services::shippo::{ShippoAddress, ShippoParcel};
 
+/// Rate ID of the synthetic rate offered when the cart qualifies for free shipping
+pub const FREE_SHIPPING_RATE_ID: &str = "free_shipping";
+
+/// Prefix of rate IDs that come from the shop's own shipping rules
+pub const RULE_RATE_PREFIX: &str = "rule_";
+
+/// Rate ID of the flat fallback rate offered while Shippo is unreachable
+pub const FALLBACK_RATE_ID: &str = "fallback";
+
+/// Weight assumed for products without one
+pub const DEFAULT_WEIGHT_GRAMS: i32 = 500;
+
+/// Most expensive product offered as a pre-payment add-on
+const CROSS_SELL_MAX_PRICE_CENTS: i32 = 2500;
+/// How many add-on suggestions to return with the rates
+const CROSS_SELL_LIMIT: i64 = 3;
+
 #[derive(Deserialize)]
 pub struct ShippingRateItem {
     pub product_id: String,
     pub quantity: i32,
+    #[serde(default)]
+    pub style_id: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -32,9 +52,78 @@ pub struct ShippingRateOption {
     pub duration_terms: Option<String>,
 }
 
+#[derive(Serialize)]
+pub struct CrossSellSuggestion {
+    pub product_id: String,
+    pub name: String,
+    pub price_cents: i32,
+    pub price: Money,
+    pub image_url: Option<String>,
+}
+
+/// One parcel holding every item: longest and widest item side by side,
+/// heights stacked, until `pack` puts them in a real box
+#[derive(Default)]
+pub struct ParcelSize {
+    pub weight_grams: f64,
+    pub length_cm: f64,
+    pub width_cm: f64,
+    pub height_cm: f64,
+    /// Boxes the items' products or styles prefer to ship in
+    pub preferred_box_ids: Vec<String>,
+}
+
+impl ParcelSize {
+    /// Add a product, using defaults (500g, 15x15x10cm) for missing dimensions.
+    /// A style's preferred box wins over its product's.
+    pub fn add(&mut self, product: &Product, style: Option<&ProductStyle>, quantity: i32) {
+        let weight = product.weight_grams.unwrap_or(DEFAULT_WEIGHT_GRAMS) as f64;
+        let length = product.length_cm.unwrap_or(15.0);
+        let width = product.width_cm.unwrap_or(15.0);
+        let height = product.height_cm.unwrap_or(10.0);
+
+        self.weight_grams += weight * quantity as f64;
+        self.length_cm = self.length_cm.max(length);
+        self.width_cm = self.width_cm.max(width);
+        self.height_cm += height * quantity as f64;
+
+        let preferred = style
+            .and_then(|s| s.box_preset_id.clone())
+            .or_else(|| product.box_preset_id.clone());
+        if let Some(box_id) = preferred {
+            if !self.preferred_box_ids.contains(&box_id) {
+                self.preferred_box_ids.push(box_id);
+            }
+        }
+    }
+
+    /// Quote the box the items will actually ship in: the smallest preferred box
+    /// they fit in, otherwise the smallest preset that fits, plus its empty weight.
+    /// When no preset fits the item stack is quoted as is.
+    pub fn pack(&mut self, boxes: &[BoxPreset]) {
+        let fits = |b: &&BoxPreset| b.fits(self.length_cm, self.width_cm, self.height_cm);
+        let smallest = |a: &&BoxPreset, b: &&BoxPreset| a.volume().total_cmp(&b.volume());
+
+        let chosen = boxes
+            .iter()
+            .filter(|b| self.preferred_box_ids.contains(&b.id))
+            .filter(fits)
+            .min_by(smallest)
+            .or_else(|| boxes.iter().filter(fits).min_by(smallest));
+
+        if let Some(chosen) = chosen {
+            self.length_cm = chosen.length_cm;
+            self.width_cm = chosen.width_cm;
+            self.height_cm = chosen.height_cm;
+            self.weight_grams += chosen.empty_weight_grams as f64;
+        }
+    }
+}
+
 #[derive(Serialize)]
 pub struct GetShippingRatesResponse {
     pub rates: Vec<ShippingRateOption>,
+    pub suggestions: Vec<CrossSellSuggestion>,
 }
 
 pub fn routes() -> Router<AppState> {
@@ -48,53 +137,191 @@ async fn get_shipping_rates(
 ) -> AppResult<Json<GetShippingRatesResponse>> {
     let conn = state.db.connect().map_err(AppError::from)?;
 
+    // Calculate total parcel dimensions from cart items
+    let mut parcel = ParcelSize::default();
+    let mut subtotal_cents = 0i32;
+    let mut categories: Vec<String> = Vec::new();
+
+    for item in &payload.items {
+        let product = Product::find_by_id(&conn, &item.product_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Product {} not found", item.product_id)))?;
+
+        subtotal_cents += product.price_cents * item.quantity;
+        if let Some(category) = &product.category {
+            if !categories.contains(category) {
+                categories.push(category.clone());
+            }
+        }
+
+        let style = match &item.style_id {
+            Some(style_id) => ProductStyle::get_by_id(&conn, style_id)
+                .await?
+                .filter(|s| s.product_id == product.id),
+            None => None,
+        };
+        parcel.add(&product, style.as_ref(), item.quantity);
+    }
+    parcel.pack(&BoxPreset::list_all(&conn).await?);
+
+    // The shop's own rules take precedence; Shippo is only asked when none apply
+    let mut rates = rule_rates(&conn, &payload.destination, parcel.weight_grams.round() as i32).await?;
+    if rates.is_empty() {
+        rates = match shippo_rates(&state, &conn, &payload.destination, &parcel, None).await {
+            Ok(rates) => rates,
+            // Checkout shouldn't dead-end while Shippo is down; those orders get flagged for review
+            Err(AppError::ExternalService(e)) => {
+                match fallback_rate(&conn, parcel.weight_grams.round() as i32).await? {
+                    Some(rate) => {
+                        tracing::warn!("Shippo rates unavailable, offering fallback rate: {}", e);
+                        vec![rate]
+                    }
+                    None => return Err(AppError::ExternalService(e)),
+                }
+            }
+            Err(e) => return Err(e),
+        };
+    }
+
+    // Offer free shipping on top of the carrier rates once the cart qualifies.
+    // Rates are sorted by price, so the first one is what the shop will ship with.
+    if let Some(threshold) = Setting::get_free_shipping_threshold_cents(&conn).await? {
+        if subtotal_cents >= threshold {
+            let cheapest = rates.first();
+            let free_rate = ShippingRateOption {
+                rate_id: FREE_SHIPPING_RATE_ID.to_string(),
+                carrier: cheapest.map(|r| r.carrier.clone()).unwrap_or_else(|| "Standard".to_string()),
+                service: "Free Shipping".to_string(),
+                price_cents: 0,
+                estimated_days: cheapest.and_then(|r| r.estimated_days),
+                duration_terms: cheapest.and_then(|r| r.duration_terms.clone()),
+            };
+            rates.insert(0, free_rate);
+        }
+    }
+
+    // Local delivery is offered next to the carrier rates, never instead of them
+    let local_delivery = Setting::get_local_delivery(&conn).await?;
+    if local_delivery.covers(&payload.destination) {
+        rates.push(ShippingRateOption {
+            rate_id: LOCAL_DELIVERY_RATE_ID.to_string(),
+            carrier: "Local Delivery".to_string(),
+            service: "Delivered by the shop".to_string(),
+            price_cents: local_delivery.fee_cents,
+            estimated_days: local_delivery.estimated_days,
+            duration_terms: None,
+        });
+    }
+
+    // Small add-ons from the same categories as the cart, offered before payment
+    let cart_ids: Vec<String> = payload.items.iter().map(|i| i.product_id.clone()).collect();
+    let candidates = Product::list_cross_sell(
+        &conn,
+        &categories,
+        &cart_ids,
+        CROSS_SELL_MAX_PRICE_CENTS,
+        CROSS_SELL_LIMIT,
+    )
+    .await?;
+
+    let mut suggestions = Vec::with_capacity(candidates.len());
+    for product in candidates {
+        let images = ProductImage::list_by_product(&conn, &product.id).await?;
+        let image_url = images.first().map(|img| {
+            if img.image_path.starts_with("http") {
+                img.image_path.clone()
+            } else {
+                state.storage.public_url(&img.image_path)
+            }
+        });
+        suggestions.push(CrossSellSuggestion {
+            product_id: product.id,
+            name: product.name,
+            price_cents: product.price_cents,
+            price: Money::usd(product.price_cents),
+            image_url,
+        });
+    }
+
+    Ok(Json(GetShippingRatesResponse { rates, suggestions }))
+}
+
+/// Rates from the active shipping rules whose zone covers the destination.
+/// Weight-tiered rules are skipped when the parcel is heavier than every tier.
+pub(crate) async fn rule_rates(
+    conn: &Connection,
+    destination: &ShippingAddress,
+    weight_grams: i32,
+) -> AppResult<Vec<ShippingRateOption>> {
+    let mut rates: Vec<ShippingRateOption> = ShippingRule::list_active(conn)
+        .await?
+        .into_iter()
+        .filter(|rule| rule.matches(destination))
+        .filter_map(|rule| {
+            let price_cents = rule.price_for(weight_grams)?;
+            Some(ShippingRateOption {
+                rate_id: format!("{}{}", RULE_RATE_PREFIX, rule.id),
+                carrier: rule.carrier,
+                service: rule.service,
+                price_cents,
+                estimated_days: rule.estimated_days,
+                duration_terms: None,
+            })
+        })
+        .collect();
+
+    // Cheapest first, like Shippo's rates
+    rates.sort_by_key(|r| r.price_cents);
+    Ok(rates)
+}
+
+/// The fallback tier rate for this weight, if the shop has set one up
+pub(crate) async fn fallback_rate(conn: &Connection, weight_grams: i32) -> AppResult<Option<ShippingRateOption>> {
+    let fallback = Setting::get_fallback_rates(conn).await?;
+
+    Ok(fallback.price_for(weight_grams).map(|price_cents| ShippingRateOption {
+        rate_id: FALLBACK_RATE_ID.to_string(),
+        carrier: "Standard".to_string(),
+        service: "Standard Shipping".to_string(),
+        price_cents,
+        estimated_days: fallback.estimated_days,
+        duration_terms: None,
+    }))
+}
+
+/// Live carrier rates from Shippo for one parcel holding the whole cart.
+/// With a `cache_key`, recently quoted rates are reused; those are only good
+/// for estimates since their rate IDs may have expired.
+pub(crate) async fn shippo_rates(
+    state: &AppState,
+    conn: &Connection,
+    destination: &ShippingAddress,
+    parcel: &ParcelSize,
+    cache_key: Option<&str>,
+) -> AppResult<Vec<ShippingRateOption>> {
     // Get shop address
-    let shop_address = Setting::get_shop_address(&conn)
+    let shop_address = Setting::get_shop_address(conn)
         .await?
         .ok_or_else(|| AppError::BadRequest("Shop address not configured. Please set up shipping origin in admin panel.".to_string()))?;
 
     // Get unit system preference
-    let unit_system = Setting::get_unit_system(&conn).await?;
+    let unit_system = Setting::get_unit_system(conn).await?;
     let (distance_unit, mass_unit) = if unit_system == "metric" {
         ("cm", "g")
     } else {
         ("in", "oz")
     };
 
-    // Calculate total parcel dimensions from cart items
-    let mut total_weight = 0.0f64;
-    let mut max_length = 0.0f64;
-    let mut max_width = 0.0f64;
-    let mut total_height = 0.0f64;
-
-    for item in &payload.items {
-        let product = Product::find_by_id(&conn, &item.product_id)
-            .await?
-            .ok_or_else(|| AppError::NotFound(format!("Product {} not found", item.product_id)))?;
-
-        // Use product dimensions or defaults (500g, 15x15x10cm)
-        let weight = product.weight_grams.unwrap_or(500) as f64;
-        let length = product.length_cm.unwrap_or(15.0);
-        let width = product.width_cm.unwrap_or(15.0);
-        let height = product.height_cm.unwrap_or(10.0);
-
-        // Accumulate for parcel calculation
-        total_weight += weight * item.quantity as f64;
-        max_length = max_length.max(length);
-        max_width = max_width.max(width);
-        total_height += height * item.quantity as f64;
-    }
-
     // Convert units if US system
     let (final_weight, final_length, final_width, final_height) = if unit_system == "us" {
         (
-            total_weight * 0.035274,  // grams to oz
-            max_length * 0.393701,     // cm to inches
-            max_width * 0.393701,
-            total_height * 0.393701,
+            parcel.weight_grams * 0.035274,  // grams to oz
+            parcel.length_cm * 0.393701,     // cm to inches
+            parcel.width_cm * 0.393701,
+            parcel.height_cm * 0.393701,
         )
     } else {
-        (total_weight, max_length, max_width, total_height)
+        (parcel.weight_grams, parcel.length_cm, parcel.width_cm, parcel.height_cm)
     };
 
     // Build addresses for Shippo
@@ -110,17 +337,17 @@ async fn get_shipping_rates(
     };
 
     let to_address = ShippoAddress {
-        name: payload.destination.name.clone(),
-        street1: payload.destination.street.clone(),
+        name: destination.name.clone(),
+        street1: destination.street.clone(),
         street2: None,
-        city: payload.destination.city.clone(),
-        state: payload.destination.state.clone(),
-        zip: payload.destination.zip.clone(),
-        country: payload.destination.country.clone(),
+        city: destination.city.clone(),
+        state: destination.state.clone(),
+        zip: destination.zip.clone(),
+        country: destination.country.clone(),
         phone: None,
     };
 
-    let parcel = ShippoParcel {
+    let shippo_parcel = ShippoParcel {
         length: final_length,
         width: final_width,
         height: final_height,
@@ -129,11 +356,25 @@ async fn get_shipping_rates(
         mass_unit: mass_unit.to_string(),
     };
 
-    // Get rates from Shippo
-    let shippo_rates = state.shippo.get_rates(from_address, to_address, vec![parcel]).await?;
+    // Get rates from Shippo, limited to the carriers and services the shop offers
+    let filter = Setting::get_rate_filter(conn).await?;
+    let shippo_rates = match cache_key {
+        Some(key) => {
+            state
+                .shippo
+                .get_rates_cached(key, from_address, to_address, vec![shippo_parcel], &filter)
+                .await?
+        }
+        None => {
+            state
+                .shippo
+                .get_rates(from_address, to_address, vec![shippo_parcel], &filter)
+                .await?
+        }
+    };
 
     // Convert to response format
-    let rates: Vec<ShippingRateOption> = shippo_rates
+    let rates = shippo_rates
         .into_iter()
         .map(|r| {
             let amount: f64 = r.amount.parse().unwrap_or(0.0);
@@ -148,5 +389,329 @@ async fn get_shipping_rates(
         })
         .collect();
 
-    Ok(Json(GetShippingRatesResponse { rates }))
+    Ok(rates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libsql::Builder;
+
+    fn address(country: &str, state: &str, zip: &str) -> ShippingAddress {
+        ShippingAddress {
+            name: "Ada".to_string(),
+            street: "1 Main St".to_string(),
+            city: "Portland".to_string(),
+            state: state.to_string(),
+            zip: zip.to_string(),
+            country: country.to_string(),
+        }
+    }
+
+    mod rules {
+        use super::*;
+
+        /// id, countries, rate_type, flat_cents, weight_tiers, sort_order, is_active
+        type RuleRow<'a> = (&'a str, &'a str, &'a str, Option<i32>, &'a str, i32, i32);
+
+        async fn connect(rules: &[RuleRow<'_>]) -> Connection {
+            let db = Builder::new_local(":memory:").build().await.unwrap();
+            let conn = db.connect().unwrap();
+            conn.execute_batch(include_str!("../../migrations/044_shipping_rules.sql"))
+                .await
+                .unwrap();
+            for (created_ts, (id, countries, rate_type, flat_cents, tiers, sort_order, is_active)) in rules.iter().enumerate() {
+                conn.execute(
+                    "INSERT INTO shipping_rules (id, carrier, service, countries, rate_type, flat_cents, weight_tiers, sort_order, is_active, created_ts, updated_ts) VALUES (?, 'USPS', ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                    libsql::params![
+                        *id,
+                        *id,
+                        *countries,
+                        *rate_type,
+                        *flat_cents,
+                        *tiers,
+                        *sort_order,
+                        *is_active,
+                        created_ts as i64,
+                        created_ts as i64
+                    ],
+                )
+                .await
+                .unwrap();
+            }
+            conn
+        }
+
+        fn ids(rates: &[ShippingRateOption]) -> Vec<&str> {
+            rates
+                .iter()
+                .map(|r| r.rate_id.strip_prefix(RULE_RATE_PREFIX).unwrap())
+                .collect()
+        }
+
+        #[tokio::test]
+        async fn only_active_rules_covering_the_destination_are_offered() {
+            let conn = connect(&[
+                ("domestic", r#"["US"]"#, "flat", Some(500), "[]", 0, 1),
+                ("canada", r#"["CA"]"#, "flat", Some(900), "[]", 0, 1),
+                ("paused", r#"["US"]"#, "flat", Some(300), "[]", 0, 0),
+            ])
+            .await;
+
+            let rates = rule_rates(&conn, &address("US", "OR", "97201"), 500).await.unwrap();
+            assert_eq!(ids(&rates), ["domestic"]);
+            assert_eq!(rates[0].price_cents, 500);
+        }
+
+        #[tokio::test]
+        async fn cheapest_rule_comes_first_and_ties_keep_sort_order() {
+            let conn = connect(&[
+                ("express", "[]", "flat", Some(1500), "[]", 0, 1),
+                ("second", "[]", "flat", Some(700), "[]", 2, 1),
+                ("first", "[]", "flat", Some(700), "[]", 1, 1),
+            ])
+            .await;
+
+            let rates = rule_rates(&conn, &address("US", "OR", "97201"), 500).await.unwrap();
+            assert_eq!(ids(&rates), ["first", "second", "express"]);
+        }
+
+        #[tokio::test]
+        async fn weight_rules_drop_out_past_their_last_tier() {
+            let tiers = r#"[{"max_grams":1000,"cents":600},{"max_grams":2000,"cents":1100}]"#;
+            let conn = connect(&[
+                ("tiered", "[]", "weight", None, tiers, 0, 1),
+                ("flat", "[]", "flat", Some(1400), "[]", 1, 1),
+            ])
+            .await;
+            let destination = address("US", "OR", "97201");
+
+            let at_limit = rule_rates(&conn, &destination, 2000).await.unwrap();
+            assert_eq!(ids(&at_limit), ["tiered", "flat"]);
+            assert_eq!(at_limit[0].price_cents, 1100);
+
+            let over_limit = rule_rates(&conn, &destination, 2001).await.unwrap();
+            assert_eq!(ids(&over_limit), ["flat"]);
+        }
+
+        #[tokio::test]
+        async fn no_matching_rule_leaves_pricing_to_the_carriers() {
+            let conn = connect(&[("canada", r#"["CA"]"#, "flat", Some(900), "[]", 0, 1)]).await;
+            assert!(rule_rates(&conn, &address("US", "OR", "97201"), 500).await.unwrap().is_empty());
+        }
+    }
+
+    mod packing {
+        use super::*;
+
+        fn product(dimensions: Option<(f64, f64, f64)>, box_preset_id: Option<&str>) -> Product {
+            Product {
+                id: "mug".to_string(),
+                name: "Mug".to_string(),
+                description: None,
+                price_cents: 2500,
+                image_path: None,
+                stock_quantity: 10,
+                is_active: true,
+                stripe_price_id: None,
+                stripe_product_id: None,
+                created_ts: 0,
+                updated_ts: 0,
+                weight_grams: dimensions.map(|_| 400),
+                length_cm: dimensions.map(|d| d.0),
+                width_cm: dimensions.map(|d| d.1),
+                height_cm: dimensions.map(|d| d.2),
+                category: None,
+                artist_id: None,
+                billing_interval: None,
+                stripe_recurring_price_id: None,
+                box_preset_id: box_preset_id.map(str::to_string),
+            }
+        }
+
+        fn style(box_preset_id: Option<&str>) -> ProductStyle {
+            ProductStyle {
+                id: "blue".to_string(),
+                product_id: "mug".to_string(),
+                name: "Blue".to_string(),
+                stock_quantity: 10,
+                image_id: None,
+                sort_order: 0,
+                created_ts: 0,
+                box_preset_id: box_preset_id.map(str::to_string),
+                image_path: None,
+            }
+        }
+
+        fn preset(id: &str, length_cm: f64, width_cm: f64, height_cm: f64, empty_weight_grams: i32) -> BoxPreset {
+            BoxPreset {
+                id: id.to_string(),
+                name: id.to_string(),
+                length_cm,
+                width_cm,
+                height_cm,
+                empty_weight_grams,
+                created_ts: 0,
+                updated_ts: 0,
+            }
+        }
+
+        fn boxes() -> Vec<BoxPreset> {
+            vec![
+                preset("large", 40.0, 30.0, 30.0, 300),
+                preset("small", 20.0, 20.0, 10.0, 100),
+                preset("medium", 30.0, 20.0, 20.0, 200),
+            ]
+        }
+
+        fn dimensions(parcel: &ParcelSize) -> (f64, f64, f64) {
+            (parcel.length_cm, parcel.width_cm, parcel.height_cm)
+        }
+
+        #[test]
+        fn items_sit_side_by_side_with_heights_stacked() {
+            let mut parcel = ParcelSize::default();
+            parcel.add(&product(Some((12.0, 8.0, 5.0)), None), None, 2);
+            parcel.add(&product(Some((10.0, 9.0, 4.0)), None), None, 1);
+
+            assert_eq!(dimensions(&parcel), (12.0, 9.0, 14.0));
+            assert_eq!(parcel.weight_grams, 1200.0);
+        }
+
+        #[test]
+        fn missing_dimensions_use_the_defaults() {
+            let mut parcel = ParcelSize::default();
+            parcel.add(&product(None, None), None, 1);
+
+            assert_eq!(dimensions(&parcel), (15.0, 15.0, 10.0));
+            assert_eq!(parcel.weight_grams, DEFAULT_WEIGHT_GRAMS as f64);
+        }
+
+        #[test]
+        fn smallest_box_that_fits_is_chosen() {
+            let mut parcel = ParcelSize::default();
+            parcel.add(&product(Some((18.0, 15.0, 6.0)), None), None, 1);
+            parcel.pack(&boxes());
+
+            assert_eq!(dimensions(&parcel), (20.0, 20.0, 10.0));
+            assert_eq!(parcel.weight_grams, 500.0);
+        }
+
+        #[test]
+        fn contents_exactly_the_size_of_a_box_fit_it() {
+            let mut parcel = ParcelSize::default();
+            parcel.add(&product(Some((20.0, 20.0, 10.0)), None), None, 1);
+            parcel.pack(&boxes());
+            assert_eq!(dimensions(&parcel), (20.0, 20.0, 10.0));
+
+            let mut parcel = ParcelSize::default();
+            parcel.add(&product(Some((20.0, 20.0, 10.5)), None), None, 1);
+            parcel.pack(&boxes());
+            assert_eq!(dimensions(&parcel), (30.0, 20.0, 20.0));
+        }
+
+        #[test]
+        fn contents_can_be_turned_to_fit() {
+            // 10cm tall but 20cm long only fits the small box on its side
+            let mut parcel = ParcelSize::default();
+            parcel.add(&product(Some((10.0, 20.0, 20.0)), None), None, 1);
+            parcel.pack(&boxes());
+            assert_eq!(dimensions(&parcel), (20.0, 20.0, 10.0));
+        }
+
+        #[test]
+        fn preferred_box_wins_over_a_smaller_one() {
+            let mut parcel = ParcelSize::default();
+            parcel.add(&product(Some((18.0, 15.0, 6.0)), Some("medium")), None, 1);
+            parcel.pack(&boxes());
+            assert_eq!(dimensions(&parcel), (30.0, 20.0, 20.0));
+        }
+
+        #[test]
+        fn style_preference_overrides_the_product() {
+            let mut parcel = ParcelSize::default();
+            parcel.add(&product(Some((18.0, 15.0, 6.0)), Some("medium")), Some(&style(Some("large"))), 1);
+            parcel.pack(&boxes());
+
+            assert_eq!(parcel.preferred_box_ids, ["large"]);
+            assert_eq!(dimensions(&parcel), (40.0, 30.0, 30.0));
+        }
+
+        #[test]
+        fn preferred_box_that_is_too_small_is_passed_over() {
+            let mut parcel = ParcelSize::default();
+            parcel.add(&product(Some((18.0, 15.0, 6.0)), Some("small")), None, 3);
+            parcel.pack(&boxes());
+            assert_eq!(dimensions(&parcel), (30.0, 20.0, 20.0));
+        }
+
+        #[test]
+        fn contents_too_big_for_every_box_are_quoted_as_is() {
+            let mut parcel = ParcelSize::default();
+            parcel.add(&product(Some((50.0, 30.0, 30.0)), None), None, 1);
+            parcel.pack(&boxes());
+
+            assert_eq!(dimensions(&parcel), (50.0, 30.0, 30.0));
+            assert_eq!(parcel.weight_grams, 400.0);
+        }
+
+        #[test]
+        fn no_presets_leave_the_parcel_alone() {
+            let mut parcel = ParcelSize::default();
+            parcel.add(&product(Some((18.0, 15.0, 6.0)), None), None, 1);
+            parcel.pack(&[]);
+            assert_eq!(dimensions(&parcel), (18.0, 15.0, 6.0));
+        }
+    }
+
+    mod fallback {
+        use super::*;
+
+        async fn connect(settings: &[(&str, &str)]) -> Connection {
+            let db = Builder::new_local(":memory:").build().await.unwrap();
+            let conn = db.connect().unwrap();
+            conn.execute_batch(include_str!("../../migrations/010_site_settings.sql"))
+                .await
+                .unwrap();
+            for (key, value) in settings {
+                conn.execute(
+                    "INSERT OR REPLACE INTO site_settings (key, value, updated_ts) VALUES (?, ?, 0)",
+                    libsql::params![*key, *value],
+                )
+                .await
+                .unwrap();
+            }
+            conn
+        }
+
+        const TIERS: &str = r#"[{"max_grams":1000,"cents":900},{"max_grams":5000,"cents":2500}]"#;
+
+        #[tokio::test]
+        async fn fallback_rate_is_priced_by_weight_tier() {
+            let conn = connect(&[("fallback_rate_tiers", TIERS), ("fallback_rate_days", "5")]).await;
+
+            let light = fallback_rate(&conn, 1000).await.unwrap().unwrap();
+            assert_eq!(light.rate_id, FALLBACK_RATE_ID);
+            assert_eq!(light.price_cents, 900);
+            assert_eq!(light.estimated_days, Some(5));
+
+            let heavy = fallback_rate(&conn, 1001).await.unwrap().unwrap();
+            assert_eq!(heavy.price_cents, 2500);
+        }
+
+        #[tokio::test]
+        async fn parcels_past_the_last_tier_get_no_fallback() {
+            let conn = connect(&[("fallback_rate_tiers", TIERS)]).await;
+            assert!(fallback_rate(&conn, 5001).await.unwrap().is_none());
+        }
+
+        #[tokio::test]
+        async fn no_fallback_is_offered_until_tiers_are_set_up() {
+            let conn = connect(&[]).await;
+            assert!(fallback_rate(&conn, 100).await.unwrap().is_none());
+
+            let conn = connect(&[("fallback_rate_tiers", "not json")]).await;
+            assert!(fallback_rate(&conn, 100).await.unwrap().is_none());
+        }
+    }
 }
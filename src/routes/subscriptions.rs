@@ -0,0 +1,222 @@
+use axum::{
+    extract::{Extension, Path, State},
+    routing::{get, post},
+    Json, Router,
+};
+use libsql::Connection;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, AppResult};
+use crate::middleware::AuthUser;
+use crate::models::{Address, CreateSubscription, Money, Product, ProductStyle, ShippingAddress, Subscription};
+use crate::routes::cart::stripe_customer_for;
+use crate::routes::AppState;
+
+#[derive(Deserialize)]
+pub struct SubscribeRequest {
+    pub product_id: String,
+    pub style_id: Option<String>,
+    /// Either a new address or the ID of one of the customer's saved addresses
+    pub shipping_address: Option<ShippingAddress>,
+    pub address_id: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct SubscribeResponse {
+    pub subscription_id: String,
+    pub checkout_url: String,
+}
+
+#[derive(Serialize)]
+pub struct SubscriptionResponse {
+    pub id: String,
+    pub product_id: String,
+    pub product_name: Option<String>,
+    pub style_id: Option<String>,
+    pub status: String,
+    pub price: Option<Money>,
+    pub billing_interval: Option<String>,
+    pub current_period_end: Option<i64>,
+    pub cancel_at_period_end: bool,
+    pub created_ts: i64,
+}
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/subscriptions", get(list_subscriptions))
+        .route("/subscriptions", post(subscribe))
+        .route("/subscriptions/{id}/cancel", post(cancel_subscription))
+}
+
+async fn to_response(conn: &Connection, subscription: Subscription) -> AppResult<SubscriptionResponse> {
+    let product = Product::find_by_id(conn, &subscription.product_id).await?;
+
+    Ok(SubscriptionResponse {
+        id: subscription.id,
+        product_id: subscription.product_id,
+        product_name: product.as_ref().map(|p| p.name.clone()),
+        style_id: subscription.style_id,
+        status: subscription.status,
+        price: product.as_ref().map(|p| Money::usd(p.price_cents)),
+        billing_interval: product.and_then(|p| p.billing_interval),
+        current_period_end: subscription.current_period_end,
+        cancel_at_period_end: subscription.cancel_at_period_end,
+        created_ts: subscription.created_ts,
+    })
+}
+
+/// The product's recurring Stripe price, creating a new one when there is none yet
+/// or the product's price has changed since it was made (Stripe prices are immutable)
+async fn recurring_price_for(state: &AppState, conn: &Connection, product: &Product) -> AppResult<String> {
+    let stripe_product_id = product
+        .stripe_product_id
+        .as_deref()
+        .ok_or_else(|| AppError::BadRequest("This product isn't available for subscription yet".to_string()))?;
+    let interval = product.billing_interval.as_deref().unwrap_or("month");
+
+    if let Some(price_id) = &product.stripe_recurring_price_id {
+        match state.stripe.get_price(price_id).await {
+            Ok((Some(amount), true)) if amount == product.price_cents as i64 => return Ok(price_id.clone()),
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Failed to check recurring price {}: {}", price_id, e),
+        }
+    }
+
+    let price_id = state
+        .stripe
+        .create_recurring_price(stripe_product_id, product.price_cents as i64, interval)
+        .await?;
+    Product::set_recurring_price(conn, &product.id, &price_id).await?;
+    tracing::info!("Created recurring price {} for product {}", price_id, product.id);
+
+    Ok(price_id)
+}
+
+async fn list_subscriptions(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+) -> AppResult<Json<Vec<SubscriptionResponse>>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+
+    let mut subscriptions = Vec::new();
+    for subscription in Subscription::list_by_user(&conn, &user.id).await? {
+        subscriptions.push(to_response(&conn, subscription).await?);
+    }
+    Ok(Json(subscriptions))
+}
+
+/// Start a subscription through Stripe Checkout. The subscription only becomes
+/// active once the checkout completes; each paid invoice then creates an order.
+async fn subscribe(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Json(payload): Json<SubscribeRequest>,
+) -> AppResult<Json<SubscribeResponse>> {
+    if state.mock_payments.is_some() {
+        return Err(AppError::BadRequest(
+            "Subscriptions aren't available in local testing mode".to_string(),
+        ));
+    }
+
+    let conn = state.db.connect().map_err(AppError::from)?;
+
+    let product = Product::find_by_id(&conn, &payload.product_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Product not found".to_string()))?;
+
+    if !product.is_active || !product.is_subscription() {
+        return Err(AppError::BadRequest(format!(
+            "{} is not available as a subscription",
+            product.name
+        )));
+    }
+
+    if let Some(style_id) = &payload.style_id {
+        match ProductStyle::get_by_id(&conn, style_id).await? {
+            Some(style) if style.product_id == product.id => {}
+            _ => return Err(AppError::BadRequest("Invalid style for this product".to_string())),
+        }
+    }
+
+    let shipping_address = match (payload.shipping_address, &payload.address_id) {
+        (Some(address), _) => address,
+        (None, Some(address_id)) => Address::find_for_user(&conn, address_id, &user.id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Address not found".to_string()))?
+            .to_shipping_address(),
+        (None, None) => return Err(AppError::BadRequest("Shipping address is required".to_string())),
+    };
+
+    let price_id = recurring_price_for(&state, &conn, &product).await?;
+
+    let subscription = Subscription::create(
+        &conn,
+        CreateSubscription {
+            user_id: user.id.clone(),
+            product_id: product.id.clone(),
+            style_id: payload.style_id,
+            shipping_address,
+        },
+    )
+    .await?;
+
+    let customer_id = stripe_customer_for(&state, &conn, &user).await;
+    let success_url = format!("{}/account?subscribed=true", state.config.base_url);
+    let cancel_url = format!("{}/products/{}?subscription=cancelled", state.config.base_url, product.id);
+
+    let session = state
+        .stripe
+        .create_subscription_checkout(
+            &price_id,
+            &success_url,
+            &cancel_url,
+            Some(&user.email),
+            customer_id.as_deref(),
+            &subscription.id,
+        )
+        .await?;
+
+    tracing::info!(
+        "Opened subscription checkout {} for subscription {}",
+        session.id,
+        subscription.id
+    );
+
+    Ok(Json(SubscribeResponse {
+        subscription_id: subscription.id,
+        checkout_url: session.url,
+    }))
+}
+
+/// Stop renewing at the end of the current billing period; the period already
+/// paid for still ships
+async fn cancel_subscription(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<String>,
+) -> AppResult<Json<SubscriptionResponse>> {
+    let conn = state.db.connect().map_err(AppError::from)?;
+
+    let subscription = Subscription::find_for_user(&conn, &id, &user.id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Subscription not found".to_string()))?;
+
+    let stripe_subscription_id = match (&subscription.stripe_subscription_id, subscription.is_live()) {
+        (Some(stripe_id), true) => stripe_id.clone(),
+        _ => return Err(AppError::BadRequest("Subscription is not active".to_string())),
+    };
+
+    if !subscription.cancel_at_period_end {
+        state
+            .stripe
+            .cancel_subscription_at_period_end(&stripe_subscription_id)
+            .await?;
+        Subscription::update_status(&conn, &subscription.id, &subscription.status, None, true).await?;
+        tracing::info!("Subscription {} set to cancel at period end", subscription.id);
+    }
+
+    let subscription = Subscription::find_by_id(&conn, &id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Subscription not found".to_string()))?;
+    Ok(Json(to_response(&conn, subscription).await?))
+}
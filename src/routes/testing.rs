@@ -0,0 +1,70 @@
+use axum::{
+    extract::{Path, State},
+    response::Redirect,
+    routing::get,
+    Router,
+};
+
+use crate::error::{AppError, AppResult};
+use crate::models::Order;
+use crate::routes::webhooks::queue_stripe_event;
+use crate::routes::AppState;
+
+/// Routes backing `MockPaymentProvider`; only mounted in local testing mode
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/testing/checkout/{session_id}/complete", get(complete_checkout))
+        .route("/testing/payment-intent/{payment_intent_id}/complete", get(complete_payment_intent))
+}
+
+/// The "pay" button of a simulated Checkout session: queue the
+/// checkout.session.completed event Stripe would send, then return to the shop
+async fn complete_checkout(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+) -> AppResult<Redirect> {
+    let mock = state
+        .mock_payments
+        .as_ref()
+        .ok_or_else(|| AppError::NotFound("Not found".to_string()))?;
+    let conn = state.db.connect().map_err(AppError::from)?;
+
+    let order = Order::find_by_stripe_session(&conn, &session_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Checkout session not found".to_string()))?;
+
+    let event = mock.checkout_completed_event(&session_id, &order.id, order.total_cents as i64);
+    queue_stripe_event(&conn, &event.id, &event.event_type, &event.payload).await?;
+    tracing::info!("Simulated payment for order {} (session {})", order.id, session_id);
+
+    Ok(Redirect::to(&format!(
+        "{}/orders/{}?success=true",
+        state.config.base_url, order.id
+    )))
+}
+
+/// Stands in for confirming a simulated Payment Element intent: queue the
+/// payment_intent.succeeded event Stripe would send, then return to the shop
+async fn complete_payment_intent(
+    State(state): State<AppState>,
+    Path(payment_intent_id): Path<String>,
+) -> AppResult<Redirect> {
+    let mock = state
+        .mock_payments
+        .as_ref()
+        .ok_or_else(|| AppError::NotFound("Not found".to_string()))?;
+    let conn = state.db.connect().map_err(AppError::from)?;
+
+    let order = Order::find_by_payment_intent(&conn, &payment_intent_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Payment intent not found".to_string()))?;
+
+    let event = mock.payment_intent_succeeded_event(&payment_intent_id, &order.id, order.total_cents as i64);
+    queue_stripe_event(&conn, &event.id, &event.event_type, &event.payload).await?;
+    tracing::info!("Simulated payment for order {} (payment intent {})", order.id, payment_intent_id);
+
+    Ok(Redirect::to(&format!(
+        "{}/orders/{}?success=true",
+        state.config.base_url, order.id
+    )))
+}
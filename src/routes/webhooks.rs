@@ -1,22 +1,37 @@
 use axum::{
     body::Bytes,
-    extract::State,
+    extract::{Query, State},
     http::{HeaderMap, StatusCode},
     routing::post,
     Json, Router,
 };
+use libsql::Connection;
+use serde::Deserialize;
 use serde_json::json;
 
-use crate::models::{Order, OrderStatus, Product, User};
+use crate::error::{AppError, AppResult};
+use crate::jobs::webhooks::{enqueue_new_order_alert, enqueue_order_email, enqueue_refund_failed_alert, OrderEmail};
+use crate::middleware::csrf::constant_time_eq;
+use crate::models::{
+    Artist, CreateOrder, CreateOrderItem, CreateUser, DiscountCode, EmailLog, EmailSuppression, Order, OrderStatus, Product,
+    ProductStyle, RevokedSession, Subscription, User, WebhookEvent, WebhookJob,
+};
+use crate::routes::admin::orders::complete_label_purchase;
 use crate::routes::AppState;
+use crate::services::clerk::{ClerkService, ClerkUser};
 use crate::services::shippo::{ShippoService, ShippoWebhookEvent};
+use crate::services::stripe::StripeWebhookEvent;
 
 pub fn routes() -> Router<AppState> {
     Router::new()
         .route("/stripe", post(stripe_webhook))
         .route("/shippo", post(shippo_webhook))
+        .route("/resend", post(resend_webhook))
+        .route("/clerk", post(clerk_webhook))
 }
 
+/// Verify and queue a Stripe event. The side effects run in the webhook worker
+/// (see `process_stripe_event`) so a transient failure is retried, not dropped.
 async fn stripe_webhook(
     State(state): State<AppState>,
     headers: HeaderMap,
@@ -58,7 +73,7 @@ async fn stripe_webhook(
         }
     };
 
-    tracing::info!("Received Stripe webhook: {}", event.event_type);
+    tracing::info!("Received Stripe webhook: {} ({})", event.event_type, event.id);
 
     let conn = match state.db.connect() {
         Ok(c) => c,
@@ -71,64 +86,182 @@ async fn stripe_webhook(
         }
     };
 
+    match queue_stripe_event(&conn, &event.id, &event.event_type, payload).await {
+        Ok(true) => (StatusCode::OK, Json(json!({"received": true}))),
+        Ok(false) => {
+            tracing::info!("Skipping duplicate Stripe event {}", event.id);
+            (StatusCode::OK, Json(json!({"received": true, "duplicate": true})))
+        }
+        Err(e) => {
+            tracing::error!("Failed to queue Stripe event {}: {}", event.id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": "Database error"})),
+            )
+        }
+    }
+}
+
+/// Record a Stripe event and queue it for the webhook worker. Returns false if the
+/// event was already received.
+pub async fn queue_stripe_event(
+    conn: &Connection,
+    event_id: &str,
+    event_type: &str,
+    payload: &str,
+) -> AppResult<bool> {
+    // Stripe delivers at least once - record the event and queue it together,
+    // so a failure here makes Stripe redeliver instead of losing the event
+    let tx = conn.transaction().await.map_err(AppError::from)?;
+    let result = async {
+        if !WebhookEvent::claim(&tx, "stripe", event_id, event_type).await? {
+            return Ok(false);
+        }
+        WebhookJob::enqueue(&tx, WebhookJob::KIND_STRIPE_EVENT, payload).await?;
+        Ok::<bool, AppError>(true)
+    }
+    .await;
+
+    match result {
+        Ok(queued) => {
+            tx.commit().await.map_err(AppError::from)?;
+            Ok(queued)
+        }
+        Err(e) => {
+            let _ = tx.rollback().await;
+            Err(e)
+        }
+    }
+}
+
+/// Apply a queued Stripe event. Errors are returned so the job is retried;
+/// state transitions that were already applied are treated as success.
+pub async fn process_stripe_event(state: &AppState, conn: &Connection, payload: &str) -> AppResult<()> {
+    let event: StripeWebhookEvent = serde_json::from_str(payload)
+        .map_err(|e| AppError::Internal(format!("Invalid queued Stripe event: {}", e)))?;
+
     match event.event_type.as_str() {
-        "checkout.session.completed" => {
-            // Get order_id from metadata
-            let order_id = event.data.object
-                .get("metadata")
-                .and_then(|m| m.get("order_id"))
-                .and_then(|v| v.as_str());
+        "checkout.session.completed" | "checkout.session.async_payment_succeeded" => {
+            // Subscription checkouts have no order; each paid invoice creates one
+            if event.data.object.get("mode").and_then(|v| v.as_str()) == Some("subscription") {
+                return link_subscription_checkout(conn, &event.data.object).await;
+            }
 
             // Get payment_intent_id for linking refunds later
             let payment_intent_id = event.data.object
                 .get("payment_intent")
                 .and_then(|v| v.as_str());
 
-            if let Some(order_id) = order_id {
-                match Order::find_by_id(&conn, order_id).await {
-                    Ok(Some(order)) => {
-                        // Store payment_intent_id for refund tracking
-                        if let Some(pi_id) = payment_intent_id {
-                            if let Err(e) = Order::set_payment_intent(&conn, &order.id, pi_id).await {
-                                tracing::error!("Failed to store payment_intent_id: {}", e);
-                            }
-                        }
+            // A Stripe promotion code entered on the Checkout page shows up as a discount
+            let amount_discount = event.data.object
+                .get("total_details")
+                .and_then(|t| t.get("amount_discount"))
+                .and_then(|v| v.as_i64())
+                .unwrap_or(0);
 
-                        // Update order status to paid
-                        if let Err(e) = Order::update_status(&conn, &order.id, OrderStatus::Paid).await {
-                            tracing::error!("Failed to update order status: {}", e);
-                        }
+            // Delayed methods like bank debits complete the session before the funds clear;
+            // async_payment_succeeded follows once they do
+            let awaiting_funds = event.data.object
+                .get("payment_status")
+                .and_then(|v| v.as_str())
+                == Some("unpaid");
 
-                        // Decrement stock
-                        if let Ok(items) = Order::get_items(&conn, &order.id).await {
-                            for item in items {
-                                let _ = Product::decrement_stock(&conn, &item.product_id, item.quantity).await;
+            match find_session_order(conn, &event.data.object).await? {
+                Some(order) if awaiting_funds => {
+                    if let Some(pi_id) = payment_intent_id {
+                        Order::set_payment_intent(conn, &order.id, pi_id).await?;
+                    }
+                    Order::set_payment_processing(conn, &order.id).await?;
+                    tracing::info!("Order {} waiting for a delayed payment to clear", order.id);
+                }
+                Some(mut order) => {
+                    if amount_discount > 0 && order.promo_code.is_none() {
+                        if let Some(session_id) = event.data.object.get("id").and_then(|v| v.as_str()) {
+                            if let Some((code, cents)) = state.stripe.get_session_promotion_code(session_id).await? {
+                                Order::apply_stripe_promotion(conn, &order.id, &code, cents as i32).await?;
+                                tracing::info!("Order {} used Stripe promotion code {}", order.id, code);
+                                order.promo_code = Some(code);
+                                order.discount_cents = cents as i32;
+                                order.total_cents -= cents as i32;
                             }
                         }
+                    }
+                    mark_order_paid(state, conn, order, payment_intent_id).await?
+                }
+                None => tracing::warn!(
+                    "Order not found for checkout session: {:?}",
+                    event.data.object.get("id").and_then(|v| v.as_str())
+                ),
+            }
+        }
+        "checkout.session.async_payment_failed" => {
+            // The bank debit bounced, so the order was never paid
+            match find_session_order(conn, &event.data.object).await? {
+                Some(order) if order.get_status() == Some(OrderStatus::Pending) => {
+                    Order::cancel_failed_payment(conn, &order.id).await?;
+                    tracing::info!("Order {} cancelled after its delayed payment failed", order.id);
+                }
+                Some(order) => {
+                    tracing::warn!("Delayed payment failed for order {} in status {}", order.id, order.status);
+                }
+                None => tracing::warn!("Order not found for failed checkout payment"),
+            }
+        }
+        "payment_intent.succeeded" | "payment_intent.amount_capturable_updated" => {
+            // Only intents created for the embedded Payment Element carry our order_id;
+            // Checkout sessions are handled by checkout.session.completed.
+            // With manual capture the intent reports amount_capturable_updated instead of succeeded.
+            let order_id = event.data.object
+                .get("metadata")
+                .and_then(|m| m.get("order_id"))
+                .and_then(|v| v.as_str());
 
-                        // Send confirmation email
-                        if let Some(ref email_service) = state.email {
-                            if let Some(ref user_id) = order.user_id {
-                                if let Ok(Some(user)) = User::find_by_id(&conn, user_id).await {
-                                    let name = user.name.as_deref().unwrap_or("Customer");
-                                    let _ = email_service
-                                        .send_order_confirmation(&user.email, &order, name)
-                                        .await;
-                                }
-                            }
-                        }
+            let payment_intent_id = event.data.object
+                .get("id")
+                .and_then(|v| v.as_str());
 
-                        tracing::info!("Order {} marked as paid via Stripe", order.id);
+            if let Some(order_id) = order_id {
+                match Order::find_by_id(conn, order_id).await? {
+                    Some(order) => mark_order_paid(state, conn, order, payment_intent_id).await?,
+                    None => tracing::warn!("Order not found for payment_intent: {}", order_id),
+                }
+            }
+        }
+        "payment_intent.canceled" => {
+            // An authorization released in Stripe (expired hold or cancelled in the dashboard)
+            let payment_intent_id = event.data.object
+                .get("id")
+                .and_then(|v| v.as_str());
+
+            if let Some(pi_id) = payment_intent_id {
+                match Order::find_by_payment_intent(conn, pi_id).await? {
+                    Some(order) if order.is_awaiting_capture() => {
+                        void_authorized_order(conn, &order.id, "authorization_expired").await?;
+                    }
+                    Some(_) => {}
+                    None => tracing::debug!("No order for cancelled payment_intent {}", pi_id),
+                }
+            }
+        }
+        "checkout.session.expired" => {
+            let order_id = event.data.object
+                .get("metadata")
+                .and_then(|m| m.get("order_id"))
+                .and_then(|v| v.as_str());
+
+            if let Some(order_id) = order_id {
+                match Order::find_by_id(conn, order_id).await? {
+                    Some(order) if order.get_status() == Some(OrderStatus::Pending) => {
+                        Order::cancel_expired_checkout(conn, &order.id).await?;
+                        tracing::info!("Order {} cancelled after checkout expired", order.id);
                     }
-                    Ok(None) => {
-                        tracing::warn!("Order not found: {}", order_id);
+                    Some(order) => {
+                        tracing::debug!("Checkout expired for order {} in status {}", order.id, order.status);
                     }
-                    Err(e) => {
-                        tracing::error!("Database error: {}", e);
+                    None => {
+                        tracing::warn!("Order not found for expired session: {}", order_id);
                     }
                 }
-            } else {
-                tracing::warn!("No order_id in checkout session metadata");
             }
         }
         "refund.created" | "refund.updated" => {
@@ -141,7 +274,7 @@ async fn stripe_webhook(
             // Only process succeeded refunds
             if refund_status != "succeeded" {
                 tracing::debug!("Refund not succeeded yet, status: {}", refund_status);
-                return (StatusCode::OK, Json(json!({"received": true})));
+                return Ok(());
             }
 
             // Get payment_intent_id from refund
@@ -149,47 +282,149 @@ async fn stripe_webhook(
                 .get("payment_intent")
                 .and_then(|v| v.as_str());
 
-            if let Some(pi_id) = payment_intent_id {
-                match Order::find_by_payment_intent(&conn, pi_id).await {
-                    Ok(Some(order)) => {
-                        // Update order status to refunded
-                        if let Err(e) = Order::update_status(&conn, &order.id, OrderStatus::Refunded).await {
-                            tracing::error!("Failed to update order status to refunded: {}", e);
-                        } else {
-                            tracing::info!("Order {} marked as refunded", order.id);
-                        }
-
-                        // Restore stock
-                        if let Ok(items) = Order::get_items(&conn, &order.id).await {
-                            for item in items {
-                                if let Err(e) = Product::increment_stock(&conn, &item.product_id, item.quantity).await {
-                                    tracing::error!("Failed to restore stock for product {}: {}", item.product_id, e);
-                                }
-                            }
-                            tracing::info!("Stock restored for order {}", order.id);
-                        }
+            // This refund alone; partial refunds that add up to the full amount
+            // are caught by charge.refunded
+            let amount = event.data.object
+                .get("amount")
+                .and_then(|v| v.as_i64())
+                .unwrap_or(0);
 
-                        // Send refund confirmation email
-                        if let Some(ref email_service) = state.email {
-                            if let Some(ref user_id) = order.user_id {
-                                if let Ok(Some(user)) = User::find_by_id(&conn, user_id).await {
-                                    let name = user.name.as_deref().unwrap_or("Customer");
-                                    let _ = email_service
-                                        .send_refund_confirmation(&user.email, &order, name)
-                                        .await;
-                                }
-                            }
-                        }
+            match payment_intent_id {
+                Some(pi_id) => match Order::find_by_payment_intent(conn, pi_id).await? {
+                    Some(order) if amount >= order.total_cents as i64 => mark_order_refunded(state, conn, order).await?,
+                    Some(order) => {
+                        tracing::info!("Order {} partially refunded ({} cents)", order.id, amount);
                     }
-                    Ok(None) => {
-                        tracing::warn!("Order not found for payment_intent: {}", pi_id);
+                    None => tracing::warn!("Order not found for payment_intent: {}", pi_id),
+                },
+                None => tracing::warn!("No payment_intent in refund event"),
+            }
+        }
+        "charge.refunded" => {
+            // Covers refunds issued outside the admin panel (e.g. from the Stripe dashboard)
+            let fully_refunded = event.data.object
+                .get("refunded")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+
+            let payment_intent_id = event.data.object
+                .get("payment_intent")
+                .and_then(|v| v.as_str());
+
+            match payment_intent_id {
+                Some(pi_id) => match Order::find_by_payment_intent(conn, pi_id).await? {
+                    Some(order) if fully_refunded => mark_order_refunded(state, conn, order).await?,
+                    Some(order) => {
+                        let amount_refunded = event.data.object
+                            .get("amount_refunded")
+                            .and_then(|v| v.as_i64())
+                            .unwrap_or(0);
+                        tracing::info!("Order {} partially refunded ({} cents)", order.id, amount_refunded);
                     }
-                    Err(e) => {
-                        tracing::error!("Database error finding order by payment_intent: {}", e);
+                    None => tracing::warn!("Order not found for refunded charge: {}", pi_id),
+                },
+                None => tracing::warn!("No payment_intent in charge.refunded event"),
+            }
+        }
+        "refund.failed" => {
+            let payment_intent_id = event.data.object
+                .get("payment_intent")
+                .and_then(|v| v.as_str());
+
+            let failure_reason = event.data.object
+                .get("failure_reason")
+                .and_then(|v| v.as_str());
+
+            match payment_intent_id {
+                Some(pi_id) => match Order::find_by_payment_intent(conn, pi_id).await? {
+                    Some(order) => revert_failed_refund(state, conn, order, failure_reason).await?,
+                    None => tracing::warn!("Order not found for failed refund: {}", pi_id),
+                },
+                None => tracing::warn!("No payment_intent in refund.failed event"),
+            }
+        }
+        "account.updated" => {
+            // A marketplace artist finished (or lost) Stripe onboarding
+            let account_id = event.data.object
+                .get("id")
+                .and_then(|v| v.as_str());
+
+            if let Some(account_id) = account_id {
+                match Artist::find_by_stripe_account(conn, account_id).await? {
+                    Some(artist) => {
+                        let flag = |key: &str| {
+                            event.data.object.get(key).and_then(|v| v.as_bool()).unwrap_or(false)
+                        };
+                        Artist::set_account_status(
+                            conn,
+                            &artist.id,
+                            flag("charges_enabled"),
+                            flag("payouts_enabled"),
+                        )
+                        .await?;
+                        tracing::info!("Updated Stripe account status for artist {}", artist.id);
                     }
+                    None => tracing::debug!("No artist for Stripe account {}", account_id),
                 }
-            } else {
-                tracing::warn!("No payment_intent in refund event");
+            }
+        }
+        "customer.subscription.created" | "customer.subscription.updated" | "customer.subscription.deleted" => {
+            let subscription_id = event.data.object
+                .get("metadata")
+                .and_then(|m| m.get("subscription_id"))
+                .and_then(|v| v.as_str());
+            let stripe_subscription_id = event.data.object
+                .get("id")
+                .and_then(|v| v.as_str());
+
+            match find_subscription(conn, subscription_id, stripe_subscription_id).await? {
+                Some(subscription) => {
+                    let status = event.data.object
+                        .get("status")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or(&subscription.status);
+                    let current_period_end = event.data.object
+                        .get("current_period_end")
+                        .and_then(|v| v.as_i64());
+                    let cancel_at_period_end = event.data.object
+                        .get("cancel_at_period_end")
+                        .and_then(|v| v.as_bool())
+                        .unwrap_or(false);
+
+                    Subscription::update_status(conn, &subscription.id, status, current_period_end, cancel_at_period_end)
+                        .await?;
+                    tracing::info!("Subscription {} is now {}", subscription.id, status);
+                }
+                None => tracing::warn!("Subscription not found for Stripe subscription: {:?}", stripe_subscription_id),
+            }
+        }
+        "invoice.paid" => {
+            // One-off invoices (e.g. from the Stripe dashboard) aren't subscription cycles
+            let stripe_subscription_id = event.data.object
+                .get("subscription")
+                .and_then(|v| v.as_str());
+
+            let subscription_id = event.data.object
+                .get("subscription_details")
+                .and_then(|d| d.get("metadata"))
+                .and_then(|m| m.get("subscription_id"))
+                .and_then(|v| v.as_str());
+
+            if let Some(stripe_subscription_id) = stripe_subscription_id {
+                match find_subscription(conn, subscription_id, Some(stripe_subscription_id)).await? {
+                    Some(subscription) => create_cycle_order(state, conn, &subscription, &event.data.object).await?,
+                    None => tracing::warn!("Subscription not found for invoice on {}", stripe_subscription_id),
+                }
+            }
+        }
+        "invoice.payment_failed" => {
+            // Stripe retries the charge; the subscription moves to past_due via customer.subscription.updated
+            let stripe_subscription_id = event.data.object
+                .get("subscription")
+                .and_then(|v| v.as_str());
+
+            if let Some(stripe_subscription_id) = stripe_subscription_id {
+                tracing::warn!("Renewal payment failed for Stripe subscription {}", stripe_subscription_id);
             }
         }
         _ => {
@@ -197,13 +432,484 @@ async fn stripe_webhook(
         }
     }
 
-    (StatusCode::OK, Json(json!({"received": true})))
+    Ok(())
+}
+
+/// Find the order a Checkout session was opened for. Sessions opened from an
+/// admin Payment Link are matched on the link as a fallback.
+async fn find_session_order(conn: &Connection, session: &serde_json::Value) -> AppResult<Option<Order>> {
+    let order_id = session
+        .get("metadata")
+        .and_then(|m| m.get("order_id"))
+        .and_then(|v| v.as_str());
+
+    match order_id {
+        Some(order_id) => Order::find_by_id(conn, order_id).await,
+        None => match session.get("payment_link").and_then(|v| v.as_str()) {
+            Some(link_id) => Order::find_by_payment_link(conn, link_id).await,
+            None => {
+                tracing::warn!("No order_id in checkout session metadata");
+                Ok(None)
+            }
+        },
+    }
+}
+
+/// Link a completed subscription checkout to the Stripe subscription it created
+async fn link_subscription_checkout(conn: &Connection, session: &serde_json::Value) -> AppResult<()> {
+    let subscription_id = session
+        .get("metadata")
+        .and_then(|m| m.get("subscription_id"))
+        .and_then(|v| v.as_str());
+    let stripe_subscription_id = session.get("subscription").and_then(|v| v.as_str());
+
+    match (subscription_id, stripe_subscription_id) {
+        (Some(subscription_id), Some(stripe_subscription_id)) => {
+            Subscription::set_stripe_subscription(conn, subscription_id, stripe_subscription_id).await?;
+            tracing::info!("Subscription {} started as {}", subscription_id, stripe_subscription_id);
+        }
+        _ => tracing::warn!("Subscription checkout without subscription_id metadata"),
+    }
+
+    Ok(())
+}
+
+/// Find the local subscription for a Stripe subscription. Stripe copies our
+/// subscription_id metadata onto it, so events that arrive before the checkout
+/// is linked still match (and link it).
+async fn find_subscription(
+    conn: &Connection,
+    subscription_id: Option<&str>,
+    stripe_subscription_id: Option<&str>,
+) -> AppResult<Option<Subscription>> {
+    if let Some(subscription_id) = subscription_id {
+        if let Some(subscription) = Subscription::find_by_id(conn, subscription_id).await? {
+            if let (None, Some(stripe_id)) = (&subscription.stripe_subscription_id, stripe_subscription_id) {
+                Subscription::set_stripe_subscription(conn, &subscription.id, stripe_id).await?;
+            }
+            return Ok(Some(subscription));
+        }
+    }
+
+    match stripe_subscription_id {
+        Some(stripe_id) => Subscription::find_by_stripe_id(conn, stripe_id).await,
+        None => Ok(None),
+    }
+}
+
+/// Create and pay the order for one billing cycle of a subscription. The invoice
+/// is already paid, so the order goes straight to paid; redelivered invoices are skipped.
+async fn create_cycle_order(
+    state: &AppState,
+    conn: &Connection,
+    subscription: &Subscription,
+    invoice: &serde_json::Value,
+) -> AppResult<()> {
+    let invoice_id = match invoice.get("id").and_then(|v| v.as_str()) {
+        Some(id) => id,
+        None => return Err(AppError::Internal("Invoice event without an id".to_string())),
+    };
+
+    if let Some(order) = Order::find_by_invoice(conn, invoice_id).await? {
+        tracing::debug!("Invoice {} already created order {}", invoice_id, order.id);
+        return Ok(());
+    }
+
+    let shipping_address = subscription
+        .get_shipping_address()
+        .ok_or_else(|| AppError::Internal(format!("Subscription {} has no shipping address", subscription.id)))?;
+    let amount_paid = invoice.get("amount_paid").and_then(|v| v.as_i64()).unwrap_or(0) as i32;
+    let payment_intent_id = invoice.get("payment_intent").and_then(|v| v.as_str());
+
+    let style_name = match &subscription.style_id {
+        Some(style_id) => ProductStyle::get_by_id(conn, style_id).await?.map(|s| s.name),
+        None => None,
+    };
+
+    let authorized_only = is_authorized_only(state, payment_intent_id).await?;
+
+    // Create the order, tie it to the invoice and mark it paid together, so a
+    // failure part way can't leave a pending order that the invoice retry then
+    // skips as already created
+    let tx = conn.transaction().await.map_err(AppError::from)?;
+    let result = async {
+        let order_id = Order::insert(
+            &tx,
+            CreateOrder {
+                user_id: Some(subscription.user_id.clone()),
+                total_cents: amount_paid,
+                shipping_address,
+                stripe_session_id: None,
+                items: vec![CreateOrderItem {
+                    product_id: subscription.product_id.clone(),
+                    quantity: 1,
+                    price_cents: amount_paid,
+                    style_id: subscription.style_id.clone(),
+                    style_name,
+                }],
+                shipping_cents: Some(0),
+                shipping_carrier: None,
+                shipping_service: None,
+                estimated_delivery_days: None,
+                is_gift: false,
+                gift_message: None,
+                gift_wrap: false,
+                gift_wrap_cents: 0,
+                checkout_rate: None,
+                promo_code: None,
+                discount_cents: 0,
+                tip_cents: 0,
+                reserve_stock: false,
+            },
+        )
+        .await?;
+
+        Order::set_subscription_cycle(&tx, &order_id, &subscription.id, invoice_id).await?;
+        if let Some(pi_id) = payment_intent_id {
+            Order::set_payment_intent(&tx, &order_id, pi_id).await?;
+        }
+
+        let order = Order::find_by_id(&tx, &order_id)
+            .await?
+            .ok_or_else(|| AppError::Internal("Failed to create order".to_string()))?;
+        if !record_paid(state, &tx, &order, authorized_only).await? {
+            return Err(AppError::Internal(format!("New order {} couldn't be marked paid", order.id)));
+        }
+        Ok::<Order, AppError>(order)
+    }
+    .await;
+
+    let order = match result {
+        Ok(order) => {
+            tx.commit().await.map_err(AppError::from)?;
+            order
+        }
+        Err(e) => {
+            let _ = tx.rollback().await;
+            return Err(e);
+        }
+    };
+
+    tracing::info!(
+        "Created paid order {} for subscription {} invoice {}",
+        order.id,
+        subscription.id,
+        invoice_id
+    );
+
+    if let Some(pi_id) = payment_intent_id {
+        record_payment_details(state, conn, &order.id, pi_id).await;
+    }
+    Ok(())
+}
+
+/// Record payment on a pending order: store the payment intent, Radar risk and payment method,
+/// then mark it paid together with its one-time side effects (promo redemption,
+/// stock, confirmation email) in a single transaction
+async fn mark_order_paid(
+    state: &AppState,
+    conn: &Connection,
+    order: Order,
+    payment_intent_id: Option<&str>,
+) -> AppResult<()> {
+    // Store payment_intent_id for refund tracking
+    if let Some(pi_id) = payment_intent_id {
+        Order::set_payment_intent(conn, &order.id, pi_id).await?;
+        record_payment_details(state, conn, &order.id, pi_id).await;
+    }
+
+    let authorized_only = is_authorized_only(state, payment_intent_id).await?;
+
+    let tx = conn.transaction().await.map_err(AppError::from)?;
+    match record_paid(state, &tx, &order, authorized_only).await {
+        Ok(true) => {
+            tx.commit().await.map_err(AppError::from)?;
+            tracing::info!("Order {} marked as paid via Stripe", order.id);
+        }
+        Ok(false) => {
+            let _ = tx.rollback().await;
+
+            // Not a repeat: the customer paid for an order that was already cancelled
+            if let Some(pi_id) = payment_intent_id {
+                if Order::claim_late_payment(conn, &order.id).await? {
+                    return_late_payment(state, conn, &order, pi_id, authorized_only).await?;
+                }
+            }
+        }
+        Err(e) => {
+            let _ = tx.rollback().await;
+            return Err(e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Hand back a payment that arrived after its order was cancelled (e.g. its cart
+/// expired while the customer was paying). Admins are alerted if that fails.
+async fn return_late_payment(
+    state: &AppState,
+    conn: &Connection,
+    order: &Order,
+    pi_id: &str,
+    authorized_only: bool,
+) -> AppResult<()> {
+    tracing::warn!("Payment {} arrived after order {} was cancelled, returning it", pi_id, order.id);
+
+    let returned = if state.mock_payments.is_some() {
+        Err(AppError::ExternalService("simulated payments can't be returned".to_string()))
+    } else if authorized_only {
+        state.stripe.cancel_payment_intent(pi_id).await
+    } else {
+        state
+            .stripe
+            .create_refund(pi_id, None, Some("duplicate"), order.artist_id.is_some())
+            .await
+            .map(|refund| tracing::info!("Refunded late payment for order {} ({})", order.id, refund.id))
+    };
+
+    if let Err(e) = returned {
+        tracing::error!("Failed to return late payment for order {}: {}", order.id, e);
+        let reason = format!("Paid after the order was cancelled, and the payment couldn't be returned: {}", e);
+        enqueue_refund_failed_alert(state, conn, order, Some(&reason)).await?;
+    }
+
+    Ok(())
+}
+
+/// Record Radar risk so risky orders can be reviewed before shipping, and how
+/// the customer paid for the payment method breakdown. Failures are only logged.
+async fn record_payment_details(state: &AppState, conn: &Connection, order_id: &str, pi_id: &str) {
+    match state.stripe.get_payment_details(pi_id).await {
+        Ok(Some(details)) => {
+            let score = details.risk_score.map(|s| s as i32);
+            if let Err(e) = Order::set_risk(conn, order_id, details.risk_level.as_deref(), score).await {
+                tracing::error!("Failed to store risk assessment: {}", e);
+            } else if matches!(details.risk_level.as_deref(), Some("elevated") | Some("highest")) {
+                tracing::warn!(
+                    "Order {} flagged by Radar: level={:?}, score={:?}",
+                    order_id, details.risk_level, details.risk_score
+                );
+            }
+
+            if let Err(e) = Order::set_payment_method(
+                conn,
+                order_id,
+                details.payment_method_type.as_deref(),
+                details.card_brand.as_deref(),
+                details.wallet.as_deref(),
+            )
+            .await
+            {
+                tracing::error!("Failed to store payment method: {}", e);
+            }
+        }
+        Ok(None) => {
+            tracing::debug!("No charge yet for payment_intent {}", pi_id);
+        }
+        Err(e) => {
+            tracing::error!("Failed to fetch payment details for order {}: {}", order_id, e);
+        }
+    }
 }
 
+/// With manual capture the card is only authorized until the label is bought
+async fn is_authorized_only(state: &AppState, payment_intent_id: Option<&str>) -> AppResult<bool> {
+    match payment_intent_id {
+        Some(pi_id) if state.stripe.manual_capture() && state.mock_payments.is_none() => {
+            state.stripe.payment_intent_requires_capture(pi_id).await
+        }
+        _ => Ok(false),
+    }
+}
+
+/// Mark an order paid along with its one-time side effects, inside the caller's
+/// transaction. Returns false when the order was no longer pending: either this
+/// payment was already processed, or the order was cancelled before it arrived.
+async fn record_paid(state: &AppState, tx: &Connection, order: &Order, authorized_only: bool) -> AppResult<bool> {
+    // A rejected transition means this payment was already processed or came
+    // too late, so skip stock and email side effects
+    match Order::update_status(tx, &order.id, OrderStatus::Paid, false).await {
+        Ok(_) => {}
+        Err(AppError::BadRequest(e)) => {
+            tracing::warn!("Not marking order {} as paid: {}", order.id, e);
+            return Ok(false);
+        }
+        Err(e) => return Err(e),
+    }
+
+    if authorized_only {
+        Order::set_authorized(tx, &order.id).await?;
+    }
+
+    // Count the promo code redemption now that payment went through
+    if let Some(ref code) = order.promo_code {
+        DiscountCode::record_redemption(tx, code).await?;
+    }
+
+    // Stock is normally reserved at checkout; older orders still take it here
+    if !order.stock_reserved {
+        for item in Order::get_items(tx, &order.id).await? {
+            Product::decrement_stock(tx, &item.product_id, item.quantity).await?;
+            if let Some(ref style_id) = item.style_id {
+                ProductStyle::decrement_stock(tx, style_id, item.quantity).await?;
+            }
+        }
+    }
+
+    enqueue_order_email(state, tx, OrderEmail::Confirmation, order).await?;
+    enqueue_new_order_alert(state, tx, order).await?;
+    Ok(true)
+}
+
+/// Cancel an order whose uncaptured authorization was released and restore its stock
+pub async fn void_authorized_order(conn: &Connection, order_id: &str, reason: &str) -> AppResult<()> {
+    let tx = conn.transaction().await.map_err(AppError::from)?;
+    match Order::void_authorization(&tx, order_id, reason).await {
+        Ok(_) => {
+            tx.commit().await.map_err(AppError::from)?;
+            tracing::info!("Order {} cancelled after its authorization was released", order_id);
+            Ok(())
+        }
+        // Already cancelled or shipped by something else
+        Err(AppError::BadRequest(e)) => {
+            let _ = tx.rollback().await;
+            tracing::warn!("Not voiding order {}: {}", order_id, e);
+            Ok(())
+        }
+        Err(e) => {
+            let _ = tx.rollback().await;
+            Err(e)
+        }
+    }
+}
+
+/// Mark an order refunded, restore its stock and queue the refund email
+async fn mark_order_refunded(state: &AppState, conn: &Connection, order: Order) -> AppResult<()> {
+    let tx = conn.transaction().await.map_err(AppError::from)?;
+    let result = async {
+        // Skip stock/email if the transition is rejected
+        // (e.g. already refunded by an earlier event)
+        match Order::update_status(&tx, &order.id, OrderStatus::Refunded, false).await {
+            Ok(_) => {}
+            Err(AppError::BadRequest(e)) => {
+                tracing::warn!("Not marking order {} as refunded: {}", order.id, e);
+                return Ok(false);
+            }
+            Err(e) => return Err(e),
+        }
+
+        // Restore stock
+        for item in Order::get_items(&tx, &order.id).await? {
+            Product::increment_stock(&tx, &item.product_id, item.quantity).await?;
+            if let Some(ref style_id) = item.style_id {
+                ProductStyle::increment_stock(&tx, style_id, item.quantity).await?;
+            }
+        }
+
+        enqueue_order_email(state, &tx, OrderEmail::Refund, &order).await?;
+        Ok::<bool, AppError>(true)
+    }
+    .await;
+
+    match result {
+        Ok(true) => {
+            tx.commit().await.map_err(AppError::from)?;
+            tracing::info!("Order {} marked as refunded and stock restored", order.id);
+        }
+        Ok(false) => {
+            let _ = tx.rollback().await;
+        }
+        Err(e) => {
+            let _ = tx.rollback().await;
+            return Err(e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Undo a refund that Stripe reported as failed: put the order back to where it was
+/// before the refund, take its stock out again, and alert the admins
+async fn revert_failed_refund(
+    state: &AppState,
+    conn: &Connection,
+    order: Order,
+    failure_reason: Option<&str>,
+) -> AppResult<()> {
+    tracing::error!(
+        "Refund failed for order {}: {}",
+        order.id,
+        failure_reason.unwrap_or("unknown reason")
+    );
+
+    let tx = conn.transaction().await.map_err(AppError::from)?;
+    let result = async {
+        // Only an order we already marked refunded needs reverting
+        if order.get_status() == Some(OrderStatus::Refunded) {
+            let previous = if order.tracking_number.is_some() {
+                OrderStatus::Shipped
+            } else {
+                OrderStatus::Paid
+            };
+            Order::update_status(&tx, &order.id, previous, true).await?;
+
+            // The refund put the stock back on sale
+            for item in Order::get_items(&tx, &order.id).await? {
+                Product::decrement_stock(&tx, &item.product_id, item.quantity).await?;
+                if let Some(ref style_id) = item.style_id {
+                    ProductStyle::decrement_stock(&tx, style_id, item.quantity).await?;
+                }
+            }
+        }
+
+        enqueue_refund_failed_alert(state, &tx, &order, failure_reason).await?;
+        Ok::<(), AppError>(())
+    }
+    .await;
+
+    match result {
+        Ok(()) => {
+            tx.commit().await.map_err(AppError::from)?;
+            Ok(())
+        }
+        Err(e) => {
+            let _ = tx.rollback().await;
+            Err(e)
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ShippoWebhookQuery {
+    token: Option<String>,
+}
+
+/// Queue a Shippo event for the webhook worker (see `process_shippo_event`).
+/// Shippo doesn't sign webhooks, so the URL registered with it carries a secret
+/// `?token=` that must match `SHIPPO_WEBHOOK_TOKEN`.
 async fn shippo_webhook(
     State(state): State<AppState>,
+    Query(query): Query<ShippoWebhookQuery>,
     body: Bytes,
 ) -> (StatusCode, Json<serde_json::Value>) {
+    let expected = match state.config.shippo_webhook_token.as_deref() {
+        Some(token) => token,
+        None => {
+            return (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(json!({"error": "Shippo webhooks not configured"})),
+            );
+        }
+    };
+    if !query.token.as_deref().is_some_and(|token| constant_time_eq(token, expected)) {
+        tracing::error!("Shippo webhook with a missing or wrong token");
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({"error": "Invalid token"})),
+        );
+    }
+
     let event: ShippoWebhookEvent = match serde_json::from_slice(&body) {
         Ok(e) => e,
         Err(e) => {
@@ -221,27 +927,257 @@ async fn shippo_webhook(
         Ok(c) => c,
         Err(e) => {
             tracing::error!("Database connection error: {}", e);
-            return (StatusCode::OK, Json(json!({"received": true})));
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": "Database error"})),
+            );
         }
     };
 
+    // from_slice succeeded above, so the body is valid UTF-8
+    let payload = String::from_utf8_lossy(&body);
+    if let Err(e) = WebhookJob::enqueue(&conn, WebhookJob::KIND_SHIPPO_EVENT, &payload).await {
+        tracing::error!("Failed to queue Shippo event: {}", e);
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({"error": "Database error"})),
+        );
+    }
+
+    (StatusCode::OK, Json(json!({"received": true})))
+}
+
+/// Bounce and complaint events from Resend. Affected addresses are suppressed right
+/// away (an idempotent upsert), so batch sends stop mailing them.
+async fn resend_webhook(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let resend = match &state.resend {
+        Some(resend) => resend,
+        None => {
+            return (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(json!({"error": "Resend not configured"})),
+            );
+        }
+    };
+
+    let header = |name: &str| headers.get(name).and_then(|h| h.to_str().ok()).unwrap_or("");
+    let payload = String::from_utf8_lossy(&body);
+
+    let event = match resend.verify_webhook(&payload, header("svix-id"), header("svix-timestamp"), header("svix-signature")) {
+        Ok(e) => e,
+        Err(e) => {
+            tracing::error!("Resend webhook verification failed: {}", e);
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({"error": "Invalid signature"})),
+            );
+        }
+    };
+
+    let conn = match state.db.connect() {
+        Ok(c) => c,
+        Err(e) => {
+            tracing::error!("Database connection error: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": "Database error"})),
+            );
+        }
+    };
+
+    let bounce = event.data.bounce.as_ref();
+    // A full mailbox or a greylisting server may accept mail later
+    let transient_bounce = bounce.and_then(|b| b.bounce_type.as_deref()) == Some("Transient");
+
+    let delivery_status = match event.event_type.as_str() {
+        "email.delivered" => Some("delivered"),
+        "email.delivery_delayed" => Some("delayed"),
+        "email.opened" => Some("opened"),
+        "email.clicked" => Some("clicked"),
+        "email.bounced" if transient_bounce => Some("delayed"),
+        "email.bounced" => Some("bounced"),
+        "email.complained" => Some("complained"),
+        _ => None,
+    };
+    if let (Some(status), Some(email_id)) = (delivery_status, event.data.email_id.as_deref()) {
+        if let Err(e) = EmailLog::record_delivery(&conn, email_id, status).await {
+            tracing::error!("Failed to record {} for email {}: {}", status, email_id, e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": "Database error"})),
+            );
+        }
+    }
+
+    let (reason, detail) = match event.event_type.as_str() {
+        "email.complained" => ("complaint", None),
+        "email.bounced" if !transient_bounce => {
+            ("bounce", bounce.and_then(|b| b.message.clone().or_else(|| b.bounce_type.clone())))
+        }
+        _ => return (StatusCode::OK, Json(json!({"received": true}))),
+    };
+
+    for email in &event.data.to {
+        if let Err(e) = EmailSuppression::suppress(&conn, email, reason, detail.as_deref()).await {
+            tracing::error!("Failed to suppress {}: {}", email, e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": "Database error"})),
+            );
+        }
+        tracing::info!("Suppressed {} after {}", email, reason);
+    }
+
+    (StatusCode::OK, Json(json!({"received": true})))
+}
+
+/// User and session events from Clerk. Created and updated accounts are
+/// upserted, deleted ones are deactivated, and ended sessions are added to the
+/// revocation list checked by the auth middleware.
+async fn clerk_webhook(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let header = |name: &str| headers.get(name).and_then(|h| h.to_str().ok()).unwrap_or("");
+    let payload = String::from_utf8_lossy(&body);
+
+    let event = match state.clerk.verify_webhook(&payload, header("svix-id"), header("svix-timestamp"), header("svix-signature")) {
+        Ok(e) => e,
+        Err(e) => {
+            tracing::error!("Clerk webhook verification failed: {}", e);
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({"error": "Invalid signature"})),
+            );
+        }
+    };
+
+    let conn = match state.db.connect() {
+        Ok(c) => c,
+        Err(e) => {
+            tracing::error!("Database connection error: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": "Database error"})),
+            );
+        }
+    };
+
+    let result = match event.event_type.as_str() {
+        "user.created" | "user.updated" => match serde_json::from_value::<ClerkUser>(event.data) {
+            Ok(clerk_user) => {
+                let name = ClerkService::get_full_name(&clerk_user);
+                match ClerkService::get_primary_email(&clerk_user) {
+                    Some(email) => User::upsert(&conn, CreateUser { clerk_id: clerk_user.id, email, name })
+                        .await
+                        .map(|user| tracing::info!("Synced user {} from Clerk {}", user.id, event.event_type)),
+                    // No primary email (e.g. mid-change): keep the address on file
+                    // rather than overwrite it with a placeholder
+                    None => match User::find_by_clerk_id(&conn, &clerk_user.id).await {
+                        Ok(Some(user)) => User::set_name(&conn, &user.id, name.as_deref())
+                            .await
+                            .map(|_| tracing::info!("Synced name of user {} from Clerk {}", user.id, event.event_type)),
+                        Ok(None) => {
+                            tracing::warn!(
+                                "Clerk {} for {} has no primary email; not creating a user",
+                                event.event_type,
+                                clerk_user.id
+                            );
+                            Ok(())
+                        }
+                        Err(e) => Err(e),
+                    },
+                }
+            }
+            Err(e) => {
+                tracing::error!("Invalid Clerk {} payload: {}", event.event_type, e);
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(json!({"error": "Invalid payload"})),
+                );
+            }
+        },
+        "user.deleted" => match event.data.get("id").and_then(|id| id.as_str()) {
+            Some(clerk_id) => User::deactivate_by_clerk_id(&conn, clerk_id).await.map(|user| match user {
+                Some(user) => tracing::info!("Deactivated user {} after Clerk account {} was deleted", user.id, clerk_id),
+                None => tracing::debug!("Deleted Clerk account {} has no local user", clerk_id),
+            }),
+            None => {
+                tracing::error!("Clerk user.deleted event without a user ID");
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(json!({"error": "Invalid payload"})),
+                );
+            }
+        },
+        "session.revoked" | "session.ended" | "session.removed" => match event.data.get("id").and_then(|id| id.as_str()) {
+            Some(session_id) => {
+                let clerk_user_id = event.data.get("user_id").and_then(|id| id.as_str());
+                RevokedSession::revoke(&conn, session_id, clerk_user_id, &event.event_type)
+                    .await
+                    .map(|_| tracing::info!("Revoked Clerk session {} ({})", session_id, event.event_type))
+            }
+            None => {
+                tracing::error!("Clerk {} event without a session ID", event.event_type);
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(json!({"error": "Invalid payload"})),
+                );
+            }
+        },
+        other => {
+            tracing::debug!("Ignoring Clerk webhook event {}", other);
+            Ok(())
+        }
+    };
+
+    if let Err(e) = result {
+        tracing::error!("Failed to apply Clerk {} event: {}", event.event_type, e);
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({"error": "Database error"})),
+        );
+    }
+
+    (StatusCode::OK, Json(json!({"received": true})))
+}
+
+/// Apply a queued Shippo event. Errors are returned so the job is retried.
+pub async fn process_shippo_event(state: &AppState, conn: &Connection, payload: &str) -> AppResult<()> {
+    let event: ShippoWebhookEvent = serde_json::from_str(payload)
+        .map_err(|e| AppError::Internal(format!("Invalid queued Shippo event: {}", e)))?;
+
     match event.event.as_str() {
         "track_updated" => {
             if let Some(tracking_data) = event.as_tracking() {
-                // Find order by tracking number
-                let orders = match Order::list_all(&conn).await {
-                    Ok(o) => o,
-                    Err(e) => {
-                        tracing::error!("Database error: {}", e);
-                        return (StatusCode::OK, Json(json!({"received": true})));
-                    }
-                };
+                // Fresh tracking data arrived - drop any cached history for this shipment
+                state
+                    .shippo
+                    .invalidate_tracking(&tracking_data.carrier, &tracking_data.tracking_number)
+                    .await;
 
-                let order = orders
+                // Find order by tracking number
+                let order = Order::list_all(conn)
+                    .await?
                     .into_iter()
                     .find(|o| o.tracking_number.as_deref() == Some(&tracking_data.tracking_number));
 
                 if let Some(order) = order {
+                    // Keep the carrier's delivery ETA current for the customer order view
+                    if let Some(eta_ts) = tracking_data
+                        .eta
+                        .as_deref()
+                        .and_then(|eta| chrono::DateTime::parse_from_rfc3339(eta).ok())
+                        .map(|dt| dt.timestamp())
+                    {
+                        Order::set_delivery_eta(conn, &order.id, eta_ts).await?;
+                    }
+
                     let shippo_status = tracking_data
                         .tracking_status
                         .as_ref()
@@ -251,24 +1187,32 @@ async fn shippo_webhook(
                     let order_status = ShippoService::map_status_to_order_status(shippo_status);
                     let new_status = OrderStatus::from_str(order_status);
 
+                    // Only apply forward transitions (tracking events can arrive out of order)
+                    let new_status = new_status.filter(|s| {
+                        order.get_status().map(|current| current.can_transition_to(*s)).unwrap_or(true)
+                    });
+
                     if let Some(status) = new_status {
-                        if let Err(e) = Order::update_status(&conn, &order.id, status).await {
-                            tracing::error!("Failed to update order status: {}", e);
-                        } else {
-                            tracing::info!("Order {} status updated to {:?}", order.id, status);
+                        let tx = conn.transaction().await.map_err(AppError::from)?;
+                        let result = async {
+                            Order::update_status(&tx, &order.id, status, false).await?;
 
                             // Send delivery email
                             if status == OrderStatus::Delivered {
-                                if let Some(ref email_service) = state.email {
-                                    if let Some(ref user_id) = order.user_id {
-                                        if let Ok(Some(user)) = User::find_by_id(&conn, user_id).await {
-                                            let name = user.name.as_deref().unwrap_or("Customer");
-                                            let _ = email_service
-                                                .send_order_delivered(&user.email, &order, name)
-                                                .await;
-                                        }
-                                    }
-                                }
+                                enqueue_order_email(state, &tx, OrderEmail::Delivered, &order).await?;
+                            }
+                            Ok::<(), AppError>(())
+                        }
+                        .await;
+
+                        match result {
+                            Ok(()) => {
+                                tx.commit().await.map_err(AppError::from)?;
+                                tracing::info!("Order {} status updated to {:?}", order.id, status);
+                            }
+                            Err(e) => {
+                                let _ = tx.rollback().await;
+                                return Err(e);
                             }
                         }
                     }
@@ -276,15 +1220,62 @@ async fn shippo_webhook(
             }
         }
         "transaction_created" | "transaction_updated" => {
-            if let Some(transaction) = event.as_transaction() {
+            if let Some(data) = event.as_transaction() {
+                // Only async label purchases wait on this; synchronous ones already have their label
+                let order = match Order::find_by_shippo_transaction(conn, &data.object_id).await? {
+                    Some(order) if order.label_url.is_none() => order,
+                    _ => return Ok(()),
+                };
+
+                // Anyone can post to this endpoint, so the label and tracking number come
+                // from Shippo's own record of the transaction, not the payload
+                let transaction = state.shippo.get_transaction(&data.object_id).await?;
                 tracing::info!(
                     "Transaction {} status: {}, tracking: {:?}",
                     transaction.object_id,
                     transaction.status,
                     transaction.tracking_number
                 );
-                // Transaction events are handled synchronously via the API response
-                // These webhooks are mainly for async label purchases which we don't use
+
+                match transaction.status.as_str() {
+                    "SUCCESS" => match (&transaction.tracking_number, &transaction.label_url) {
+                        (Some(tracking_number), Some(label_url)) => {
+                            complete_label_purchase(
+                                state,
+                                conn,
+                                &order.id,
+                                tracking_number,
+                                label_url,
+                                &transaction.object_id,
+                                transaction.rate.as_deref(),
+                            )
+                            .await?;
+                        }
+                        _ => {
+                            tracing::warn!(
+                                "Transaction {} succeeded without a label for order {}",
+                                transaction.object_id,
+                                order.id
+                            );
+                        }
+                    },
+                    "ERROR" => {
+                        let reason = transaction
+                            .messages
+                            .as_ref()
+                            .and_then(|msgs| msgs.first())
+                            .and_then(|m| m.text.clone())
+                            .unwrap_or_else(|| "unknown reason".to_string());
+                        Order::clear_pending_label(conn, &order.id).await?;
+                        tracing::error!(
+                            "Label purchase {} failed for order {}: {}",
+                            transaction.object_id,
+                            order.id,
+                            reason
+                        );
+                    }
+                    _ => {}
+                }
             }
         }
         "batch_created" | "batch_purchased" => {
@@ -295,5 +1286,5 @@ async fn shippo_webhook(
         }
     }
 
-    (StatusCode::OK, Json(json!({"received": true})))
+    Ok(())
 }
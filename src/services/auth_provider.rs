@@ -0,0 +1,147 @@
+use async_trait::async_trait;
+
+use crate::error::{AppError, AppResult};
+use crate::services::clerk::ClerkService;
+use crate::services::jwks::{JwksVerifier, JwtClaims};
+
+/// Providers AUTH_PROVIDER can name
+pub const AUTH_PROVIDERS: &[&str] = &["clerk", "jwt"];
+
+/// A verified session token, whichever provider issued it
+#[derive(Debug, Clone)]
+pub struct SessionClaims {
+    /// The user's ID at the provider (stored as `users.clerk_id`)
+    pub subject: String,
+    /// Session the token belongs to, checked against revoked sessions
+    pub session_id: Option<String>,
+    /// Minutes since the user last entered their password, when the provider says
+    pub first_factor_age_mins: Option<i64>,
+    /// Profile carried in the token itself, so the user can be created without
+    /// asking the provider
+    pub profile: Option<UserProfile>,
+}
+
+impl From<JwtClaims> for SessionClaims {
+    fn from(claims: JwtClaims) -> Self {
+        let profile = claims.email.map(|email| UserProfile {
+            id: claims.sub.clone(),
+            email,
+            name: claims.name,
+        });
+
+        Self {
+            first_factor_age_mins: claims.fva.and_then(|fva| fva.first().copied()),
+            subject: claims.sub,
+            session_id: claims.sid,
+            profile,
+        }
+    }
+}
+
+/// What the local user row is created and refreshed from
+#[derive(Debug, Clone)]
+pub struct UserProfile {
+    pub id: String,
+    pub email: String,
+    pub name: Option<String>,
+}
+
+/// Who signs customers and staff in. The middleware only sees verified claims
+/// and profiles; which provider is used is chosen by AUTH_PROVIDER.
+#[async_trait]
+pub trait AuthProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Check a session token and return its claims
+    async fn verify_token(&self, token: &str) -> AppResult<SessionClaims>;
+
+    /// The user's current profile
+    async fn fetch_user(&self, user_id: &str) -> AppResult<UserProfile>;
+
+    /// Change the user's name where the provider keeps it, so the next sign-in
+    /// doesn't undo it. Providers that don't keep names have nothing to do.
+    async fn update_name(&self, _user_id: &str, _first_name: &str, _last_name: &str) -> AppResult<()> {
+        Ok(())
+    }
+
+    /// Delete the user's account at the provider, signing them out everywhere
+    async fn delete_user(&self, _user_id: &str) -> AppResult<()> {
+        Ok(())
+    }
+}
+
+/// Clerk sessions: tokens checked against Clerk's JWKS, profiles from the Clerk API
+pub struct ClerkAuthProvider {
+    clerk: ClerkService,
+    jwks: JwksVerifier,
+}
+
+impl ClerkAuthProvider {
+    pub fn new(clerk: ClerkService, jwks: JwksVerifier) -> Self {
+        Self { clerk, jwks }
+    }
+}
+
+#[async_trait]
+impl AuthProvider for ClerkAuthProvider {
+    fn name(&self) -> &'static str {
+        "clerk"
+    }
+
+    async fn verify_token(&self, token: &str) -> AppResult<SessionClaims> {
+        Ok(self.jwks.verify_token(token).await?.into())
+    }
+
+    async fn fetch_user(&self, user_id: &str) -> AppResult<UserProfile> {
+        let clerk_user = self.clerk.get_user(user_id).await?;
+        Ok(UserProfile {
+            email: ClerkService::get_primary_email(&clerk_user).unwrap_or_else(|| "unknown@example.com".to_string()),
+            name: ClerkService::get_full_name(&clerk_user),
+            id: clerk_user.id,
+        })
+    }
+
+    async fn update_name(&self, user_id: &str, first_name: &str, last_name: &str) -> AppResult<()> {
+        self.clerk.update_name(user_id, first_name, last_name).await?;
+        Ok(())
+    }
+
+    async fn delete_user(&self, user_id: &str) -> AppResult<()> {
+        self.clerk.delete_user(user_id).await
+    }
+}
+
+/// A self-hosted (or any other) OpenID-style issuer: RS256 tokens checked
+/// against its JWKS. There's no profile API, so tokens must carry `email`
+/// (and optionally `name`) claims; the user is created from those on their
+/// first request.
+pub struct JwtAuthProvider {
+    jwks: JwksVerifier,
+}
+
+impl JwtAuthProvider {
+    pub fn new(jwks: JwksVerifier) -> Self {
+        Self { jwks }
+    }
+}
+
+#[async_trait]
+impl AuthProvider for JwtAuthProvider {
+    fn name(&self) -> &'static str {
+        "jwt"
+    }
+
+    async fn verify_token(&self, token: &str) -> AppResult<SessionClaims> {
+        let claims: SessionClaims = self.jwks.verify_token(token).await?.into();
+        if claims.profile.is_none() {
+            return Err(AppError::Unauthorized("Token has no email claim".to_string()));
+        }
+        Ok(claims)
+    }
+
+    async fn fetch_user(&self, _user_id: &str) -> AppResult<UserProfile> {
+        Err(AppError::BadRequest(
+            "Profiles come from session tokens with this auth provider; sign in instead".to_string(),
+        ))
+    }
+}
@@ -2,26 +2,42 @@ use reqwest::Client;
 use serde::{Deserialize, Serialize};
 
 use crate::error::{AppError, AppResult};
+use crate::services::svix;
 
 #[derive(Clone)]
 pub struct ClerkService {
     client: Client,
     secret_key: String,
+    /// Signs user events sent to /api/webhooks/clerk
+    webhook_secret: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ClerkUser {
     pub id: String,
     pub email_addresses: Vec<ClerkEmailAddress>,
+    #[serde(default)]
+    pub primary_email_address_id: Option<String>,
     pub first_name: Option<String>,
     pub last_name: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ClerkEmailAddress {
+    #[serde(default)]
+    pub id: Option<String>,
     pub email_address: String,
 }
 
+/// A user event from Clerk. `data` is a full user for user.created and
+/// user.updated, and `{id, deleted}` for user.deleted.
+#[derive(Debug, Deserialize)]
+pub struct ClerkWebhookEvent {
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub data: serde_json::Value,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ClerkJwks {
     pub keys: Vec<ClerkJwk>,
@@ -38,13 +54,31 @@ pub struct ClerkJwk {
 }
 
 impl ClerkService {
-    pub fn new(secret_key: &str) -> Self {
+    pub fn new(secret_key: &str, webhook_secret: Option<String>) -> Self {
         Self {
             client: Client::new(),
             secret_key: secret_key.to_string(),
+            webhook_secret,
         }
     }
 
+    /// Verify a webhook's Svix signature and parse the event
+    pub fn verify_webhook(
+        &self,
+        payload: &str,
+        message_id: &str,
+        timestamp: &str,
+        signature: &str,
+    ) -> AppResult<ClerkWebhookEvent> {
+        let secret = self.webhook_secret.as_deref().ok_or_else(|| {
+            AppError::ExternalService("Clerk webhook secret not configured. Set CLERK_WEBHOOK_SECRET.".to_string())
+        })?;
+        svix::verify_signature(secret, payload, message_id, timestamp, signature)?;
+
+        serde_json::from_str(payload)
+            .map_err(|e| AppError::ExternalService(format!("Invalid Clerk webhook payload: {}", e)))
+    }
+
     pub async fn get_user(&self, user_id: &str) -> AppResult<ClerkUser> {
         let url = format!("https://api.clerk.com/v1/users/{}", user_id);
 
@@ -71,6 +105,61 @@ impl ClerkService {
             .map_err(|e| AppError::ExternalService(format!("Failed to parse Clerk response: {}", e)))
     }
 
+    /// Change the user's name in Clerk, so the next sign-in sync keeps it
+    pub async fn update_name(&self, user_id: &str, first_name: &str, last_name: &str) -> AppResult<ClerkUser> {
+        let url = format!("https://api.clerk.com/v1/users/{}", user_id);
+
+        let response = self
+            .client
+            .patch(&url)
+            .header("Authorization", format!("Bearer {}", self.secret_key))
+            .json(&serde_json::json!({
+                "first_name": first_name,
+                "last_name": last_name,
+            }))
+            .send()
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Clerk API error: {}", e)))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(AppError::ExternalService(format!(
+                "Clerk API error {}: {}",
+                status, body
+            )));
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Failed to parse Clerk response: {}", e)))
+    }
+
+    /// Delete the Clerk account, signing the user out everywhere
+    pub async fn delete_user(&self, user_id: &str) -> AppResult<()> {
+        let url = format!("https://api.clerk.com/v1/users/{}", user_id);
+
+        let response = self
+            .client
+            .delete(&url)
+            .header("Authorization", format!("Bearer {}", self.secret_key))
+            .send()
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Clerk API error: {}", e)))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(AppError::ExternalService(format!(
+                "Clerk API error {}: {}",
+                status, body
+            )));
+        }
+
+        Ok(())
+    }
+
     pub async fn get_jwks(&self) -> AppResult<serde_json::Value> {
         let response = self
             .client
@@ -87,7 +176,12 @@ impl ClerkService {
     }
 
     pub fn get_primary_email(user: &ClerkUser) -> Option<String> {
-        user.email_addresses.first().map(|e| e.email_address.clone())
+        let primary = user.primary_email_address_id.as_deref();
+        user.email_addresses
+            .iter()
+            .find(|e| primary.is_some() && e.id.as_deref() == primary)
+            .or_else(|| user.email_addresses.first())
+            .map(|e| e.email_address.clone())
     }
 
     pub fn get_full_name(user: &ClerkUser) -> Option<String> {
@@ -1,31 +1,270 @@
-use lettre::{
-    message::header::ContentType,
-    transport::smtp::authentication::Credentials,
-    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
-};
+use std::sync::Arc;
+
+use libsql::Database;
 
 use crate::error::{AppError, AppResult};
-use crate::models::Order;
+use crate::models::{EmailBranding, EmailLog, Order, OrderItemDetail, Product, Setting};
+use crate::services::i18n::{t, t_with};
+use crate::services::mailer::{Mailer, OutgoingEmail};
 
+/// Order and admin emails, sent through whichever Mailer is configured
 #[derive(Clone)]
 pub struct EmailService {
-    mailer: AsyncSmtpTransport<Tokio1Executor>,
-    from_email: String,
+    mailer: Arc<dyn Mailer>,
+    /// Storefront URL product links point at
+    base_url: String,
+    /// Where every send attempt is logged
+    db: Arc<Database>,
 }
 
-impl EmailService {
-    pub fn new(smtp_host: &str, smtp_user: &str, smtp_pass: &str, from_email: &str) -> AppResult<Self> {
-        let creds = Credentials::new(smtp_user.to_string(), smtp_pass.to_string());
+/// Add a send attempt to the email log. Logging never fails the send itself.
+pub(crate) async fn log_email(
+    db: &Database,
+    recipient: &str,
+    email_type: &str,
+    subject: &str,
+    provider: &str,
+    outcome: &AppResult<Option<String>>,
+) {
+    let (provider_id, error) = match outcome {
+        Ok(provider_id) => (provider_id.as_deref(), None),
+        Err(e) => (None, Some(e.to_string())),
+    };
 
-        let mailer = AsyncSmtpTransport::<Tokio1Executor>::relay(smtp_host)
-            .map_err(|e| AppError::Internal(format!("Failed to create SMTP transport: {}", e)))?
-            .credentials(creds)
-            .build();
+    let logged = match db.connect() {
+        Ok(conn) => {
+            EmailLog::record(&conn, recipient, email_type, subject, provider, provider_id, error.as_deref()).await
+        }
+        Err(e) => Err(AppError::from(e)),
+    };
+    if let Err(e) = logged {
+        tracing::warn!("Failed to log {} email to {}: {}", email_type, recipient, e);
+    }
+}
 
-        Ok(Self {
-            mailer,
-            from_email: from_email.to_string(),
+/// The shop's email branding. Falls back to the default look if the settings
+/// can't be read, so a database hiccup never blocks an email.
+pub(crate) async fn load_branding(db: &Database) -> EmailBranding {
+    let loaded = match db.connect() {
+        Ok(conn) => Setting::get_email_branding(&conn).await,
+        Err(e) => Err(AppError::from(e)),
+    };
+    loaded.unwrap_or_else(|e| {
+        tracing::warn!("Failed to load email branding: {}", e);
+        EmailBranding::default()
+    })
+}
+
+/// The shop logo shown above an email's content, if one is set
+pub(crate) fn email_logo(branding: &EmailBranding) -> String {
+    match &branding.logo_url {
+        Some(url) => format!(
+            r#"<img src="{}" alt="Caterpillar Clay" style="max-width:160px;height:auto;margin-bottom:16px">"#,
+            escape_html(url)
+        ),
+        None => String::new(),
+    }
+}
+
+/// Footer text (custom, or the translated tagline) followed by the social links
+pub(crate) fn email_footer(branding: &EmailBranding, locale: &str) -> String {
+    let tagline = match &branding.footer_text {
+        Some(text) => escape_html(text),
+        None => t(locale, "footer-tagline"),
+    };
+
+    if branding.social_links.is_empty() {
+        return format!("<p>{}</p>", tagline);
+    }
+
+    let links: Vec<String> = branding
+        .social_links
+        .iter()
+        .map(|link| {
+            format!(
+                r#"<a href="{}" style="color:{}">{}</a>"#,
+                escape_html(&link.url),
+                branding.accent_color,
+                escape_html(&link.label)
+            )
         })
+        .collect();
+    format!("<p>{}</p><p>{}</p>", tagline, links.join(" &middot; "))
+}
+
+/// "Customers also bought" row of product cards for order emails; empty when there
+/// are no recommendations
+pub(crate) fn also_bought_block(
+    products: &[(Product, Option<String>)],
+    base_url: &str,
+    branding: &EmailBranding,
+    locale: &str,
+) -> String {
+    if products.is_empty() {
+        return String::new();
+    }
+
+    let cards: String = products
+        .iter()
+        .map(|(product, image_url)| {
+            let image_html = match image_url {
+                Some(url) => format!(
+                    r#"<img src="{}" alt="{}" style="width:120px;height:120px;object-fit:cover;border-radius:8px;border:2px solid #E0E0E0">"#,
+                    escape_html(url),
+                    escape_html(&product.name)
+                ),
+                None => String::from(r#"<div style="width:120px;height:120px;background:#E0E0E0;border-radius:8px"></div>"#),
+            };
+            format!(
+                r#"<a href="{}/?product={}" style="display:inline-block;text-align:center;margin:8px;text-decoration:none;color:{}">
+                    {}
+                    <p style="font-size:10px;margin:8px 0 4px">{}</p>
+                    <p style="font-size:12px;color:{}">${:.2}</p>
+                </a>"#,
+                base_url,
+                product.id,
+                branding.text_color,
+                image_html,
+                escape_html(&product.name),
+                branding.accent_color,
+                product.price_cents as f64 / 100.0
+            )
+        })
+        .collect();
+
+    format!(
+        r#"<div style="margin-top:32px;padding-top:20px;border-top:1px solid #E0E0E0;text-align:center">
+            <p style="font-size:12px;color:{}">{}</p>
+            {}
+        </div>"#,
+        branding.accent_color,
+        t(locale, "also-bought-title"),
+        cards
+    )
+}
+
+pub(crate) fn escape_html(input: &str) -> String {
+    input
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Plain-text alternative for an HTML email: styles and markup dropped, block
+/// elements on their own lines and links written out as "text (url)"
+pub(crate) fn html_to_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len() / 2);
+    let mut skip_until: Option<String> = None;
+    let mut link_href: Option<String> = None;
+    let mut rest = html;
+
+    while let Some(start) = rest.find('<') {
+        if skip_until.is_none() {
+            push_text(&mut text, &rest[..start]);
+        }
+        let end = match rest[start..].find('>') {
+            Some(end) => start + end,
+            None => {
+                rest = "";
+                break;
+            }
+        };
+        let tag = &rest[start + 1..end];
+        rest = &rest[end + 1..];
+
+        let closing = tag.starts_with('/');
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or("")
+            .to_ascii_lowercase();
+
+        if let Some(until) = &skip_until {
+            if closing && &name == until {
+                skip_until = None;
+            }
+            continue;
+        }
+
+        match (name.as_str(), closing) {
+            ("head" | "style" | "script" | "title", false) => skip_until = Some(name.clone()),
+            ("a", false) => link_href = tag_attribute(tag, "href"),
+            ("a", true) => {
+                if let Some(href) = link_href.take().filter(|h| h.starts_with("http") || h.starts_with("mailto:")) {
+                    text.push_str(&format!(" ({})", href));
+                }
+            }
+            ("br", _) => text.push('\n'),
+            ("li", false) => text.push_str("\n- "),
+            ("td" | "th", true) => text.push(' '),
+            ("p" | "div" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "tr" | "table" | "ul" | "ol" | "li" | "hr", _) => {
+                text.push('\n')
+            }
+            _ => {}
+        }
+    }
+    if skip_until.is_none() {
+        push_text(&mut text, rest);
+    }
+
+    // Trim every line and keep at most one blank line between blocks
+    let mut result = String::with_capacity(text.len());
+    let mut blank = true;
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() {
+            if !blank {
+                result.push('\n');
+                blank = true;
+            }
+            continue;
+        }
+        result.push_str(line);
+        result.push('\n');
+        blank = false;
+    }
+    result.trim_end().to_string()
+}
+
+/// Append a run of HTML text, collapsing whitespace and decoding common entities
+fn push_text(out: &mut String, raw: &str) {
+    let decoded = raw
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&middot;", "·")
+        .replace("&mdash;", "—")
+        .replace("&amp;", "&");
+
+    for c in decoded.chars() {
+        if c.is_whitespace() {
+            if !out.ends_with(|p: char| p.is_whitespace()) && !out.is_empty() {
+                out.push(' ');
+            }
+        } else {
+            out.push(c);
+        }
+    }
+}
+
+/// Value of a double-quoted attribute inside a tag
+fn tag_attribute(tag: &str, name: &str) -> Option<String> {
+    let lower = tag.to_ascii_lowercase();
+    let start = lower.find(&format!("{}=\"", name))? + name.len() + 2;
+    let end = tag[start..].find('"')? + start;
+    Some(tag[start..end].to_string())
+}
+
+impl EmailService {
+    pub fn new(mailer: Arc<dyn Mailer>, db: Arc<Database>, base_url: &str) -> Self {
+        Self {
+            mailer,
+            base_url: base_url.to_string(),
+            db,
+        }
     }
 
     pub async fn send_order_confirmation(
@@ -33,17 +272,21 @@ impl EmailService {
         to_email: &str,
         order: &Order,
         customer_name: &str,
+        recommendations: &[(Product, Option<String>)],
+        locale: &str,
     ) -> AppResult<()> {
-        let subject = format!("Order Confirmation - #{}", &order.id.to_string()[..8]);
+        let branding = self.branding().await;
+        let subject = t_with(locale, "order-confirmation-subject", &[("id", &order.id[..8])]);
+        let total = format!("{:.2}", order.total_cents as f64 / 100.0);
 
         let body = format!(
             r#"<!DOCTYPE html>
 <html>
 <head>
     <style>
-        body {{ font-family: 'Courier New', monospace; background: #f0e6d2; padding: 20px; }}
+        body {{ font-family: 'Courier New', monospace; background: {background}; padding: 20px; }}
         .container {{ max-width: 600px; margin: 0 auto; background: white; padding: 32px; }}
-        h1 {{ color: #8b5e3c; font-size: 18px; }}
+        h1 {{ color: {accent}; font-size: 18px; }}
         .order-id {{ color: #666; font-size: 12px; }}
         .total {{ font-size: 16px; color: #22c55e; margin-top: 20px; }}
         .footer {{ margin-top: 32px; font-size: 10px; color: #888; }}
@@ -51,24 +294,34 @@ impl EmailService {
 </head>
 <body>
     <div class="container">
-        <h1>Thank you for your order!</h1>
-        <p>Hi {},</p>
-        <p>We've received your order and are getting it ready for you.</p>
-        <p class="order-id">Order ID: {}</p>
-        <p class="total">Total: ${:.2}</p>
-        <p>We'll send you another email when your order ships.</p>
+        {logo}
+        <h1>{}</h1>
+        <p>{}</p>
+        <p>{}</p>
+        <p class="order-id">{}</p>
+        <p class="total">{}</p>
+        <p>{}</p>
+        {also_bought}
         <div class="footer">
-            <p>Caterpillar Clay - Handmade Pottery</p>
+            {footer}
         </div>
     </div>
 </body>
 </html>"#,
-            customer_name,
-            order.id,
-            order.total_cents as f64 / 100.0
+            t(locale, "order-confirmation-title"),
+            t_with(locale, "greeting", &[("name", customer_name)]),
+            t(locale, "order-confirmation-received"),
+            t_with(locale, "order-id", &[("id", &order.id)]),
+            t_with(locale, "order-confirmation-total", &[("total", &total)]),
+            t(locale, "order-confirmation-next"),
+            accent = branding.accent_color,
+            background = branding.background_color,
+            logo = email_logo(&branding),
+            also_bought = also_bought_block(recommendations, &self.base_url, &branding, locale),
+            footer = email_footer(&branding, locale)
         );
 
-        self.send_email(to_email, &subject, &body).await
+        self.send_order_email("order_confirmation", to_email, &subject, &body).await
     }
 
     pub async fn send_order_shipped(
@@ -77,40 +330,52 @@ impl EmailService {
         order: &Order,
         customer_name: &str,
         tracking_number: &str,
+        locale: &str,
     ) -> AppResult<()> {
-        let subject = format!("Your Order Has Shipped - #{}", &order.id.to_string()[..8]);
+        let branding = self.branding().await;
+        let subject = t_with(locale, "order-shipped-subject", &[("id", &order.id[..8])]);
 
         let body = format!(
             r#"<!DOCTYPE html>
 <html>
 <head>
     <style>
-        body {{ font-family: 'Courier New', monospace; background: #f0e6d2; padding: 20px; }}
+        body {{ font-family: 'Courier New', monospace; background: {background}; padding: 20px; }}
         .container {{ max-width: 600px; margin: 0 auto; background: white; padding: 32px; }}
-        h1 {{ color: #8b5e3c; font-size: 18px; }}
+        h1 {{ color: {accent}; font-size: 18px; }}
         .tracking {{ background: #f0fdf4; padding: 16px; margin: 20px 0; font-size: 14px; }}
         .footer {{ margin-top: 32px; font-size: 10px; color: #888; }}
     </style>
 </head>
 <body>
     <div class="container">
-        <h1>Your order is on its way!</h1>
-        <p>Hi {},</p>
-        <p>Great news! Your order has shipped.</p>
+        {logo}
+        <h1>{}</h1>
+        <p>{}</p>
+        <p>{}</p>
         <div class="tracking">
-            <strong>Tracking Number:</strong> {}
+            <strong>{}</strong> {}
         </div>
-        <p>You can track your package using the tracking number above.</p>
+        <p>{}</p>
         <div class="footer">
-            <p>Caterpillar Clay - Handmade Pottery</p>
+            {footer}
         </div>
     </div>
 </body>
 </html>"#,
-            customer_name, tracking_number
+            t(locale, "order-shipped-title"),
+            t_with(locale, "greeting", &[("name", customer_name)]),
+            t(locale, "order-shipped-intro"),
+            t(locale, "order-shipped-tracking"),
+            tracking_number,
+            t(locale, "order-shipped-track"),
+            accent = branding.accent_color,
+            background = branding.background_color,
+            logo = email_logo(&branding),
+            footer = email_footer(&branding, locale)
         );
 
-        self.send_email(to_email, &subject, &body).await
+        self.send_order_email("order_shipped", to_email, &subject, &body).await
     }
 
     pub async fn send_order_delivered(
@@ -118,36 +383,49 @@ impl EmailService {
         to_email: &str,
         order: &Order,
         customer_name: &str,
+        recommendations: &[(Product, Option<String>)],
+        locale: &str,
     ) -> AppResult<()> {
-        let subject = format!("Your Order Has Been Delivered - #{}", &order.id.to_string()[..8]);
+        let branding = self.branding().await;
+        let subject = t_with(locale, "order-delivered-subject", &[("id", &order.id[..8])]);
 
         let body = format!(
             r#"<!DOCTYPE html>
 <html>
 <head>
     <style>
-        body {{ font-family: 'Courier New', monospace; background: #f0e6d2; padding: 20px; }}
+        body {{ font-family: 'Courier New', monospace; background: {background}; padding: 20px; }}
         .container {{ max-width: 600px; margin: 0 auto; background: white; padding: 32px; }}
-        h1 {{ color: #8b5e3c; font-size: 18px; }}
+        h1 {{ color: {accent}; font-size: 18px; }}
         .footer {{ margin-top: 32px; font-size: 10px; color: #888; }}
     </style>
 </head>
 <body>
     <div class="container">
-        <h1>Your order has arrived!</h1>
-        <p>Hi {},</p>
-        <p>Your Caterpillar Clay order has been delivered!</p>
-        <p>We hope you love your new pottery. If you have any questions or concerns, please don't hesitate to reach out.</p>
+        {logo}
+        <h1>{}</h1>
+        <p>{}</p>
+        <p>{}</p>
+        <p>{}</p>
+        {also_bought}
         <div class="footer">
-            <p>Caterpillar Clay - Handmade Pottery</p>
+            {footer}
         </div>
     </div>
 </body>
 </html>"#,
-            customer_name
+            t(locale, "order-delivered-title"),
+            t_with(locale, "greeting", &[("name", customer_name)]),
+            t(locale, "order-delivered-intro"),
+            t(locale, "order-delivered-outro"),
+            accent = branding.accent_color,
+            background = branding.background_color,
+            logo = email_logo(&branding),
+            also_bought = also_bought_block(recommendations, &self.base_url, &branding, locale),
+            footer = email_footer(&branding, locale)
         );
 
-        self.send_email(to_email, &subject, &body).await
+        self.send_order_email("order_delivered", to_email, &subject, &body).await
     }
 
     pub async fn send_refund_confirmation(
@@ -155,62 +433,323 @@ impl EmailService {
         to_email: &str,
         order: &Order,
         customer_name: &str,
+        locale: &str,
+    ) -> AppResult<()> {
+        let branding = self.branding().await;
+        let subject = t_with(locale, "refund-subject", &[("id", &order.id[..8])]);
+        let amount = format!("{:.2}", order.total_cents as f64 / 100.0);
+
+        let body = format!(
+            r#"<!DOCTYPE html>
+<html>
+<head>
+    <style>
+        body {{ font-family: 'Courier New', monospace; background: {background}; padding: 20px; }}
+        .container {{ max-width: 600px; margin: 0 auto; background: white; padding: 32px; }}
+        h1 {{ color: {accent}; font-size: 18px; }}
+        .total {{ font-size: 16px; color: #22c55e; margin-top: 20px; }}
+        .footer {{ margin-top: 32px; font-size: 10px; color: #888; }}
+    </style>
+</head>
+<body>
+    <div class="container">
+        {logo}
+        <h1>{}</h1>
+        <p>{}</p>
+        <p>{}</p>
+        <p class="total">{}</p>
+        <p>{}</p>
+        <p>{}</p>
+        <div class="footer">
+            {footer}
+        </div>
+    </div>
+</body>
+</html>"#,
+            t(locale, "refund-title"),
+            t_with(locale, "greeting", &[("name", customer_name)]),
+            t(locale, "refund-intro"),
+            t_with(locale, "refund-amount", &[("amount", &amount)]),
+            t(locale, "refund-timing"),
+            t(locale, "refund-questions"),
+            accent = branding.accent_color,
+            background = branding.background_color,
+            logo = email_logo(&branding),
+            footer = email_footer(&branding, locale)
+        );
+
+        self.send_order_email("refund_confirmation", to_email, &subject, &body).await
+    }
+
+    pub async fn send_payment_link(
+        &self,
+        to_email: &str,
+        order: &Order,
+        customer_name: &str,
+        payment_url: &str,
+        locale: &str,
     ) -> AppResult<()> {
-        let subject = format!("Refund Processed - #{}", &order.id[..8]);
+        let branding = self.branding().await;
+        let subject = t_with(locale, "payment-link-subject", &[("id", &order.id[..8])]);
+        let total = format!("{:.2}", order.total_cents as f64 / 100.0);
 
         let body = format!(
             r#"<!DOCTYPE html>
 <html>
 <head>
     <style>
-        body {{ font-family: 'Courier New', monospace; background: #f0e6d2; padding: 20px; }}
+        body {{ font-family: 'Courier New', monospace; background: {background}; padding: 20px; }}
         .container {{ max-width: 600px; margin: 0 auto; background: white; padding: 32px; }}
-        h1 {{ color: #8b5e3c; font-size: 18px; }}
+        h1 {{ color: {accent}; font-size: 18px; }}
+        .order-id {{ color: #666; font-size: 12px; }}
         .total {{ font-size: 16px; color: #22c55e; margin-top: 20px; }}
+        .button {{ display: inline-block; background: {accent}; color: {text}; padding: 12px 24px; text-decoration: none; margin-top: 16px; }}
+        .footer {{ margin-top: 32px; font-size: 10px; color: #888; }}
+    </style>
+</head>
+<body>
+    <div class="container">
+        {logo}
+        <h1>{}</h1>
+        <p>{}</p>
+        <p>{}</p>
+        <p class="order-id">{}</p>
+        <p class="total">{}</p>
+        <a class="button" href="{}">{}</a>
+        <p>{}</p>
+        <div class="footer">
+            {footer}
+        </div>
+    </div>
+</body>
+</html>"#,
+            t(locale, "payment-link-title"),
+            t_with(locale, "greeting", &[("name", customer_name)]),
+            t(locale, "payment-link-intro"),
+            t_with(locale, "order-id", &[("id", &order.id)]),
+            t_with(locale, "payment-link-total", &[("total", &total)]),
+            payment_url,
+            t(locale, "payment-link-button"),
+            t(locale, "payment-link-outro"),
+            accent = branding.accent_color,
+            text = branding.text_color,
+            background = branding.background_color,
+            logo = email_logo(&branding),
+            footer = email_footer(&branding, locale)
+        );
+
+        self.send_order_email("payment_link", to_email, &subject, &body).await
+    }
+
+    /// Tell an admin that a refund Stripe had accepted later failed
+    pub async fn send_refund_failed_alert(
+        &self,
+        to_email: &str,
+        order: &Order,
+        reason: Option<&str>,
+    ) -> AppResult<()> {
+        let branding = self.branding().await;
+        let subject = format!("Refund Failed - #{}", &order.id[..8]);
+
+        let body = format!(
+            r#"<!DOCTYPE html>
+<html>
+<head>
+    <style>
+        body {{ font-family: 'Courier New', monospace; background: {background}; padding: 20px; }}
+        .container {{ max-width: 600px; margin: 0 auto; background: white; padding: 32px; }}
+        h1 {{ color: #b91c1c; font-size: 18px; }}
+        .order-id {{ color: #666; font-size: 12px; }}
         .footer {{ margin-top: 32px; font-size: 10px; color: #888; }}
     </style>
 </head>
 <body>
     <div class="container">
-        <h1>Your refund has been processed</h1>
-        <p>Hi {},</p>
-        <p>We've processed a refund for your order.</p>
-        <p class="total">Refund Amount: ${:.2}</p>
-        <p>The refund should appear on your statement within 5-10 business days, depending on your bank.</p>
-        <p>If you have any questions, please don't hesitate to reach out.</p>
+        {logo}
+        <h1>A refund failed</h1>
+        <p>Stripe could not complete the refund for this order, so it has been moved back to {}.</p>
+        <p class="order-id">Order ID: {}</p>
+        <p>Amount: ${:.2}</p>
+        <p>Reason: {}</p>
+        <p>Check the payment in the Stripe dashboard and contact the customer.</p>
         <div class="footer">
             <p>Caterpillar Clay - Handmade Pottery</p>
         </div>
     </div>
 </body>
 </html>"#,
-            customer_name,
-            order.total_cents as f64 / 100.0
+            order.status,
+            order.id,
+            order.total_cents as f64 / 100.0,
+            reason.unwrap_or("unknown"),
+            background = branding.background_color,
+            logo = email_logo(&branding)
         );
 
-        self.send_email(to_email, &subject, &body).await
+        self.send_email("refund_failed_alert", to_email, &subject, &body).await
     }
 
-    async fn send_email(&self, to: &str, subject: &str, html_body: &str) -> AppResult<()> {
-        let email = Message::builder()
-            .from(
-                self.from_email
-                    .parse()
-                    .map_err(|e| AppError::Internal(format!("Invalid from email: {}", e)))?,
-            )
-            .to(to
-                .parse()
-                .map_err(|e| AppError::Internal(format!("Invalid to email: {}", e)))?)
-            .subject(subject)
-            .header(ContentType::TEXT_HTML)
-            .body(html_body.to_string())
-            .map_err(|e| AppError::Internal(format!("Failed to build email: {}", e)))?;
-
-        self.mailer
-            .send(email)
-            .await
-            .map_err(|e| AppError::Internal(format!("Failed to send email: {}", e)))?;
-
-        Ok(())
+    /// Tell an owner that repeated failed admin sign-ins locked out an address or account
+    pub async fn send_lockout_alert(
+        &self,
+        to_email: &str,
+        target: &str,
+        attempts: u32,
+        lockout_minutes: u64,
+    ) -> AppResult<()> {
+        let branding = self.branding().await;
+        let subject = "Admin sign-in lockout".to_string();
+
+        let body = format!(
+            r#"<!DOCTYPE html>
+<html>
+<head>
+    <style>
+        body {{ font-family: 'Courier New', monospace; background: {background}; padding: 20px; }}
+        .container {{ max-width: 600px; margin: 0 auto; background: white; padding: 32px; }}
+        h1 {{ color: #b91c1c; font-size: 18px; }}
+        .target {{ color: #666; font-size: 12px; }}
+        .footer {{ margin-top: 32px; font-size: 10px; color: #888; }}
+    </style>
+</head>
+<body>
+    <div class="container">
+        {logo}
+        <h1>Repeated failed admin sign-ins</h1>
+        <p>{} failed attempts to access the admin came from the source below, so it has been locked out for {} minutes.</p>
+        <p class="target">{}</p>
+        <p>If this wasn't one of your staff, check the audit log and consider setting ADMIN_IP_ALLOWLIST.</p>
+        <div class="footer">
+            <p>Caterpillar Clay - Handmade Pottery</p>
+        </div>
+    </div>
+</body>
+</html>"#,
+            attempts,
+            lockout_minutes,
+            escape_html(target),
+            background = branding.background_color,
+            logo = email_logo(&branding)
+        );
+
+        self.send_email("lockout_alert", to_email, &subject, &body).await
+    }
+
+    /// Tell an admin an order was paid, optionally with the items to pull
+    pub async fn send_new_order_alert(
+        &self,
+        to_email: &str,
+        order: &Order,
+        pick_list: Option<&[OrderItemDetail]>,
+    ) -> AppResult<()> {
+        let branding = self.branding().await;
+        let subject = format!("New Order - #{} (${:.2})", &order.id[..8], order.total_cents as f64 / 100.0);
+
+        let shipping = match (&order.shipping_carrier, &order.shipping_service) {
+            (Some(carrier), Some(service)) => format!("{} {}", carrier, service),
+            (None, Some(service)) => service.clone(),
+            (Some(carrier), None) => carrier.clone(),
+            (None, None) => "Not chosen".to_string(),
+        };
+
+        let pick_list_html = match pick_list {
+            Some(items) => {
+                let rows: String = items
+                    .iter()
+                    .map(|item| {
+                        let name = item.product_name.as_deref().unwrap_or("Deleted product");
+                        let name = match &item.style_name {
+                            Some(style) => format!("{} ({})", name, style),
+                            None => name.to_string(),
+                        };
+                        format!("<tr><td>{}</td><td class=\"qty\">&times; {}</td></tr>", name, item.quantity)
+                    })
+                    .collect();
+
+                let mut extras = String::new();
+                if order.gift_wrap {
+                    extras.push_str("<p>Gift wrap requested.</p>");
+                }
+                if let Some(message) = order.gift_message.as_deref().filter(|m| !m.is_empty()) {
+                    extras.push_str(&format!("<p>Gift message: {}</p>", message));
+                }
+
+                format!("<h2>Pick list</h2><table>{}</table>{}", rows, extras)
+            }
+            None => String::new(),
+        };
+
+        let body = format!(
+            r#"<!DOCTYPE html>
+<html>
+<head>
+    <style>
+        body {{ font-family: 'Courier New', monospace; background: {background}; padding: 20px; }}
+        .container {{ max-width: 600px; margin: 0 auto; background: white; padding: 32px; }}
+        h1 {{ color: {accent}; font-size: 18px; }}
+        h2 {{ color: {accent}; font-size: 14px; margin-top: 24px; }}
+        .order-id {{ color: #666; font-size: 12px; }}
+        table {{ width: 100%; border-collapse: collapse; font-size: 12px; }}
+        td {{ padding: 6px 0; border-bottom: 1px solid #eee; }}
+        .qty {{ text-align: right; }}
+        .footer {{ margin-top: 32px; font-size: 10px; color: #888; }}
+    </style>
+</head>
+<body>
+    <div class="container">
+        {logo}
+        <h1>You have a new order!</h1>
+        <p class="order-id">Order ID: {}</p>
+        <p>Total: ${:.2}</p>
+        <p>Shipping: {}</p>
+        {}
+        <div class="footer">
+            <p>Caterpillar Clay - Handmade Pottery</p>
+        </div>
+    </div>
+</body>
+</html>"#,
+            order.id,
+            order.total_cents as f64 / 100.0,
+            shipping,
+            pick_list_html,
+            accent = branding.accent_color,
+            background = branding.background_color,
+            logo = email_logo(&branding)
+        );
+
+        self.send_email("new_order_alert", to_email, &subject, &body).await
+    }
+
+    async fn branding(&self) -> EmailBranding {
+        load_branding(&self.db).await
+    }
+
+    async fn send_email(&self, email_type: &str, to: &str, subject: &str, html_body: &str) -> AppResult<()> {
+        self.send(OutgoingEmail::new(email_type, to, subject, html_body.to_string())).await
+    }
+
+    /// Send a customer's order email, copying the shop's archive address if one is set
+    async fn send_order_email(&self, email_type: &str, to: &str, subject: &str, html_body: &str) -> AppResult<()> {
+        let bcc = match self.db.connect() {
+            Ok(conn) => Setting::get_order_email_bcc(&conn).await.unwrap_or_else(|e| {
+                tracing::warn!("Failed to load order email BCC address: {}", e);
+                None
+            }),
+            Err(e) => {
+                tracing::warn!("Failed to load order email BCC address: {}", e);
+                None
+            }
+        };
+
+        let mut email = OutgoingEmail::new(email_type, to, subject, html_body.to_string());
+        email.bcc = bcc;
+        self.send(email).await
+    }
+
+    async fn send(&self, email: OutgoingEmail) -> AppResult<()> {
+        let outcome = self.mailer.send_transactional(&email).await.map_err(AppError::from);
+        log_email(&self.db, &email.to, &email.email_type, &email.subject, self.mailer.provider(), &outcome).await;
+        outcome.map(|_| ())
     }
 }
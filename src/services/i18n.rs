@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use axum::http::{header, HeaderMap};
+
+/// Languages emails can be sent in; the first is the fallback
+pub const LOCALES: &[&str] = &["en", "es"];
+
+pub const DEFAULT_LOCALE: &str = "en";
+
+/// Message catalogs in a Fluent-style `key = value` format, one per locale
+const CATALOGS: &[(&str, &str)] = &[
+    ("en", include_str!("../../locales/en.ftl")),
+    ("es", include_str!("../../locales/es.ftl")),
+];
+
+type Catalog = HashMap<&'static str, &'static str>;
+
+fn catalogs() -> &'static HashMap<&'static str, Catalog> {
+    static CATALOGS_BY_LOCALE: OnceLock<HashMap<&'static str, Catalog>> = OnceLock::new();
+    CATALOGS_BY_LOCALE.get_or_init(|| {
+        CATALOGS
+            .iter()
+            .map(|(locale, source)| (*locale, parse_catalog(source)))
+            .collect()
+    })
+}
+
+/// Parse `key = value` lines, skipping blanks and `#` comments
+fn parse_catalog(source: &'static str) -> Catalog {
+    source
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once(" = "))
+        .map(|(key, value)| (key.trim(), value.trim()))
+        .collect()
+}
+
+/// The supported locale for a tag like "es-MX" or "ES", if any
+pub fn normalize_locale(tag: &str) -> Option<&'static str> {
+    let language = tag.trim().split(['-', '_']).next()?.to_lowercase();
+    LOCALES.iter().copied().find(|l| *l == language)
+}
+
+/// The first supported language in the request's Accept-Language header
+pub fn locale_from_headers(headers: &HeaderMap) -> Option<&'static str> {
+    let accept_language = headers.get(header::ACCEPT_LANGUAGE)?.to_str().ok()?;
+    accept_language
+        .split(',')
+        .filter_map(|part| part.split(';').next())
+        .find_map(normalize_locale)
+}
+
+/// A message in `locale`, falling back to English and then to the key itself
+pub fn t(locale: &str, key: &str) -> String {
+    t_with(locale, key, &[])
+}
+
+/// A message with its `{ $name }` placeholders filled in
+pub fn t_with(locale: &str, key: &str, args: &[(&str, &str)]) -> String {
+    let catalogs = catalogs();
+    let message = catalogs
+        .get(locale)
+        .and_then(|c| c.get(key))
+        .or_else(|| catalogs.get(DEFAULT_LOCALE).and_then(|c| c.get(key)))
+        .copied()
+        .unwrap_or(key);
+
+    args.iter().fold(message.to_string(), |text, (name, value)| {
+        text.replace(&format!("{{ ${} }}", name), value)
+    })
+}
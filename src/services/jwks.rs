@@ -24,31 +24,50 @@ pub struct Jwk {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ClerkClaims {
+pub struct JwtClaims {
     pub sub: String,
     pub exp: usize,
     pub iat: usize,
     pub azp: Option<String>,
+    /// Session the token was issued for
+    #[serde(default)]
+    pub sid: Option<String>,
+    /// Minutes since the first and second factor were last verified (Clerk
+    /// session token v2); -1 for a factor that wasn't used
+    #[serde(default)]
+    pub fva: Option<Vec<i64>>,
+    /// Profile claims, when the issuer includes them
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub name: Option<String>,
 }
 
+/// Verifies RS256 session tokens against an issuer's published JWKS
 pub struct JwksVerifier {
     client: Client,
     jwks_url: String,
     keys: Arc<RwLock<HashMap<String, DecodingKey>>>,
     max_retries: u32,
+    /// Required `iss` claim, if any
+    issuer: Option<String>,
+    /// Required `aud` claim; unset, tokens that name an audience are refused
+    audience: Option<String>,
 }
 
 impl JwksVerifier {
-    pub fn new(jwks_url: &str) -> Self {
+    pub fn new(jwks_url: &str, issuer: Option<String>, audience: Option<String>) -> Self {
         Self {
             client: Client::new(),
             jwks_url: jwks_url.to_string(),
             keys: Arc::new(RwLock::new(HashMap::new())),
             max_retries: 3,
+            issuer,
+            audience,
         }
     }
 
-    /// Fetch JWKS from Clerk and cache the keys
+    /// Fetch the JWKS and cache the keys
     pub async fn refresh_keys(&self) -> AppResult<()> {
         tracing::info!("Fetching JWKS from {}", self.jwks_url);
 
@@ -95,7 +114,7 @@ impl JwksVerifier {
     }
 
     /// Verify a JWT token and return the claims
-    pub async fn verify_token(&self, token: &str) -> AppResult<ClerkClaims> {
+    pub async fn verify_token(&self, token: &str) -> AppResult<JwtClaims> {
         // Try verification with cached keys first
         match self.try_verify(token).await {
             Ok(claims) => return Ok(claims),
@@ -133,7 +152,7 @@ impl JwksVerifier {
         ))
     }
 
-    async fn try_verify(&self, token: &str) -> AppResult<ClerkClaims> {
+    async fn try_verify(&self, token: &str) -> AppResult<JwtClaims> {
         // Decode header to get the key ID
         let header = decode_header(token)
             .map_err(|e| AppError::ExternalService(format!("Invalid token header: {}", e)))?;
@@ -151,8 +170,14 @@ impl JwksVerifier {
         // Verify and decode the token
         let mut validation = Validation::new(Algorithm::RS256);
         validation.validate_exp = true;
+        if let Some(ref issuer) = self.issuer {
+            validation.set_issuer(&[issuer]);
+        }
+        if let Some(ref audience) = self.audience {
+            validation.set_audience(&[audience]);
+        }
 
-        let token_data = decode::<ClerkClaims>(token, key, &validation)
+        let token_data = decode::<JwtClaims>(token, key, &validation)
             .map_err(|e| AppError::ExternalService(format!("Token verification failed: {}", e)))?;
 
         Ok(token_data.claims)
@@ -171,6 +196,8 @@ impl Clone for JwksVerifier {
             jwks_url: self.jwks_url.clone(),
             keys: self.keys.clone(),
             max_retries: self.max_retries,
+            issuer: self.issuer.clone(),
+            audience: self.audience.clone(),
         }
     }
 }
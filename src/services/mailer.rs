@@ -0,0 +1,439 @@
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use lettre::{
+    message::header::{HeaderName, HeaderValue},
+    message::MultiPart,
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use resend_rs::types::CreateEmailBaseOptions;
+use resend_rs::Resend;
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+use thiserror::Error;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use uuid::Uuid;
+
+use crate::services::email::html_to_text;
+
+/// Providers MAIL_PROVIDER can name
+pub const MAIL_PROVIDERS: &[&str] = &["smtp", "resend"];
+
+/// Longest a single send attempt may take before it is abandoned
+const SEND_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Attempts per email before a transient failure is returned to the caller
+const MAX_SEND_ATTEMPTS: u32 = 3;
+
+/// Delay before the first retry; doubles each attempt, plus up to as much again in jitter
+const BASE_RETRY_DELAY_MS: u64 = 500;
+
+/// SMTP connections a bulk send keeps open at once
+const SMTP_BULK_CONCURRENCY: usize = 8;
+
+const RESEND_BATCH_URL: &str = "https://api.resend.com/emails/batch";
+
+/// Most emails Resend accepts in one batch call
+const RESEND_BATCH_SIZE: usize = 100;
+
+/// Batch calls in flight at once; Resend's default rate limit is 2 requests a second
+const RESEND_BATCH_CONCURRENCY: usize = 2;
+
+/// Why an email could not be sent. Transient failures (timeouts, dropped connections,
+/// 4xx replies) may succeed later; permanent ones (bad addresses, 5xx replies) won't.
+#[derive(Error, Debug, Clone)]
+pub enum EmailError {
+    #[error("Temporary email failure: {0}")]
+    Transient(String),
+
+    #[error("Permanent email failure: {0}")]
+    Permanent(String),
+}
+
+impl EmailError {
+    pub fn is_transient(&self) -> bool {
+        matches!(self, EmailError::Transient(_))
+    }
+}
+
+impl From<lettre::transport::smtp::Error> for EmailError {
+    fn from(e: lettre::transport::smtp::Error) -> Self {
+        if e.is_permanent() || e.is_client() {
+            EmailError::Permanent(e.to_string())
+        } else {
+            EmailError::Transient(e.to_string())
+        }
+    }
+}
+
+/// A rendered email ready to hand to a Mailer
+#[derive(Debug, Clone)]
+pub struct OutgoingEmail {
+    /// Recorded in the email log, e.g. "order_confirmation"
+    pub email_type: String,
+    pub to: String,
+    /// Blind copy, e.g. the shop's order archive address
+    pub bcc: Option<String>,
+    pub subject: String,
+    pub html: String,
+    /// One-click unsubscribe URL for newsletter emails (RFC 8058)
+    pub list_unsubscribe: Option<String>,
+}
+
+impl OutgoingEmail {
+    pub fn new(email_type: &str, to: &str, subject: &str, html: String) -> Self {
+        Self {
+            email_type: email_type.to_string(),
+            to: to.to_string(),
+            bcc: None,
+            subject: subject.to_string(),
+            html,
+            list_unsubscribe: None,
+        }
+    }
+
+    /// Mark a newsletter email as unsubscribable in one click from the mail client
+    pub fn with_list_unsubscribe(mut self, url: String) -> Self {
+        self.list_unsubscribe = Some(url);
+        self
+    }
+
+    /// Extra headers to send: List-Unsubscribe and List-Unsubscribe-Post, which Gmail
+    /// and Yahoo require from bulk senders
+    pub fn headers(&self) -> Vec<(&'static str, String)> {
+        match &self.list_unsubscribe {
+            Some(url) => vec![
+                ("List-Unsubscribe", format!("<{}>", url)),
+                ("List-Unsubscribe-Post", "List-Unsubscribe=One-Click".to_string()),
+            ],
+            None => Vec::new(),
+        }
+    }
+}
+
+/// Delivers rendered emails. Templates live in EmailService and ResendService;
+/// this is only the transport, chosen by MAIL_PROVIDER.
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    /// Name recorded in the email log
+    fn provider(&self) -> &'static str;
+
+    /// Send one email now (order updates, confirmations, alerts). Returns the
+    /// provider's message ID when it gives one.
+    async fn send_transactional(&self, email: &OutgoingEmail) -> Result<Option<String>, EmailError>;
+
+    /// Send a newsletter-style batch. One result per email, in order.
+    async fn send_bulk(&self, emails: &[OutgoingEmail]) -> Vec<Result<Option<String>, EmailError>> {
+        let mut results = Vec::with_capacity(emails.len());
+        for email in emails {
+            results.push(self.send_transactional(email).await);
+        }
+        results
+    }
+}
+
+/// Sends over SMTP (Resend's SMTP relay by default)
+#[derive(Clone)]
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from_email: String,
+}
+
+impl SmtpMailer {
+    pub fn new(smtp_host: &str, smtp_user: &str, smtp_pass: &str, from_email: &str) -> Result<Self, EmailError> {
+        let creds = Credentials::new(smtp_user.to_string(), smtp_pass.to_string());
+
+        let transport = AsyncSmtpTransport::<Tokio1Executor>::relay(smtp_host)
+            .map_err(|e| EmailError::Permanent(format!("Failed to create SMTP transport: {}", e)))?
+            .credentials(creds)
+            .build();
+
+        Ok(Self {
+            transport,
+            from_email: from_email.to_string(),
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    fn provider(&self) -> &'static str {
+        "smtp"
+    }
+
+    /// The returned ID is the server's reply, which usually names the queue ID
+    async fn send_transactional(&self, email: &OutgoingEmail) -> Result<Option<String>, EmailError> {
+        let mut builder = Message::builder()
+            .from(
+                self.from_email
+                    .parse()
+                    .map_err(|e| EmailError::Permanent(format!("Invalid from email: {}", e)))?,
+            )
+            .to(email
+                .to
+                .parse()
+                .map_err(|e| EmailError::Permanent(format!("Invalid to email: {}", e)))?);
+        if let Some(bcc) = &email.bcc {
+            builder = builder.bcc(
+                bcc.parse()
+                    .map_err(|e| EmailError::Permanent(format!("Invalid BCC email: {}", e)))?,
+            );
+        }
+        for (name, value) in email.headers() {
+            builder = builder.raw_header(HeaderValue::new(HeaderName::new_from_ascii_str(name), value));
+        }
+
+        let message = builder
+            .subject(email.subject.as_str())
+            .multipart(MultiPart::alternative_plain_html(
+                html_to_text(&email.html),
+                email.html.clone(),
+            ))
+            .map_err(|e| EmailError::Permanent(format!("Failed to build email: {}", e)))?;
+
+        let transport = &self.transport;
+        with_retries(&email.to, || {
+            let message = message.clone();
+            async move {
+                let response = transport.send(message).await?;
+                let first = response.message().next().map(str::to_string);
+                Ok(first)
+            }
+        })
+        .await
+    }
+
+    /// Sends are spread over up to SMTP_BULK_CONCURRENCY connections at once
+    async fn send_bulk(&self, emails: &[OutgoingEmail]) -> Vec<Result<Option<String>, EmailError>> {
+        run_concurrently(emails.to_vec(), SMTP_BULK_CONCURRENCY, |email| {
+            let mailer = self.clone();
+            async move { mailer.send_transactional(&email).await }
+        })
+        .await
+        .into_iter()
+        .map(|result| result.unwrap_or_else(|| Err(EmailError::Transient("Send task panicked".to_string()))))
+        .collect()
+    }
+}
+
+/// Sends through the Resend API
+pub struct ResendMailer {
+    client: Resend,
+    http: Client,
+    api_key: String,
+    from_email: String,
+}
+
+impl ResendMailer {
+    pub fn new(api_key: &str, from_email: &str) -> Self {
+        Self {
+            client: Resend::new(api_key),
+            http: Client::new(),
+            api_key: api_key.to_string(),
+            from_email: from_email.to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ResendBatchResponse {
+    data: Vec<ResendBatchItem>,
+}
+
+#[derive(Deserialize)]
+struct ResendBatchItem {
+    id: String,
+}
+
+#[async_trait]
+impl Mailer for ResendMailer {
+    fn provider(&self) -> &'static str {
+        "resend"
+    }
+
+    /// API errors are treated as transient; Resend doesn't say which ones a retry can fix
+    async fn send_transactional(&self, email: &OutgoingEmail) -> Result<Option<String>, EmailError> {
+        let text = html_to_text(&email.html);
+        let client = &self.client;
+        with_retries(&email.to, || {
+            let mut options = CreateEmailBaseOptions::new(&self.from_email, [email.to.as_str()], email.subject.as_str())
+                .with_html(&email.html)
+                .with_text(&text);
+            if let Some(bcc) = &email.bcc {
+                options = options.with_bcc(bcc.as_str());
+            }
+            for (name, value) in email.headers() {
+                options = options.with_header(name, &value);
+            }
+            async move {
+                client
+                    .emails
+                    .send(options)
+                    .await
+                    .map(|response| Some(response.id.to_string()))
+                    .map_err(|e| EmailError::Transient(format!("Resend API error: {}", e)))
+            }
+        })
+        .await
+    }
+
+    /// Sends through Resend's batch endpoint, RESEND_BATCH_SIZE emails per call
+    async fn send_bulk(&self, emails: &[OutgoingEmail]) -> Vec<Result<Option<String>, EmailError>> {
+        let chunks: Vec<Vec<OutgoingEmail>> = emails.chunks(RESEND_BATCH_SIZE).map(|chunk| chunk.to_vec()).collect();
+        let chunk_sizes: Vec<usize> = chunks.iter().map(|chunk| chunk.len()).collect();
+
+        let results = run_concurrently(chunks, RESEND_BATCH_CONCURRENCY, |chunk| {
+            let http = self.http.clone();
+            let api_key = self.api_key.clone();
+            let from_email = self.from_email.clone();
+            async move { send_resend_batch(&http, &api_key, &from_email, &chunk).await }
+        })
+        .await;
+
+        results
+            .into_iter()
+            .zip(chunk_sizes)
+            .flat_map(|(result, size)| {
+                result.unwrap_or_else(|| vec![Err(EmailError::Transient("Send task panicked".to_string())); size])
+            })
+            .collect()
+    }
+}
+
+/// Send one batch call. Resend accepts or rejects a batch as a whole, so a failure
+/// is returned for every email in it.
+async fn send_resend_batch(
+    http: &Client,
+    api_key: &str,
+    from_email: &str,
+    emails: &[OutgoingEmail],
+) -> Vec<Result<Option<String>, EmailError>> {
+    let body: Vec<serde_json::Value> = emails
+        .iter()
+        .map(|email| {
+            let mut item = serde_json::json!({
+                "from": from_email,
+                "to": [email.to],
+                "subject": email.subject,
+                "html": email.html,
+                "text": html_to_text(&email.html),
+            });
+            if let Some(bcc) = &email.bcc {
+                item["bcc"] = serde_json::json!([bcc]);
+            }
+            let headers: serde_json::Map<String, serde_json::Value> = email
+                .headers()
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), serde_json::Value::String(value)))
+                .collect();
+            if !headers.is_empty() {
+                item["headers"] = serde_json::Value::Object(headers);
+            }
+            item
+        })
+        .collect();
+
+    let label = format!("a batch of {} recipients", emails.len());
+    let outcome = with_retries(&label, || {
+        let request = http.post(RESEND_BATCH_URL).bearer_auth(api_key).json(&body);
+        async move {
+            let response = request
+                .send()
+                .await
+                .map_err(|e| EmailError::Transient(format!("Resend API error: {}", e)))?;
+
+            let status = response.status();
+            if !status.is_success() {
+                let text = response.text().await.unwrap_or_default();
+                let message = format!("Resend rejected batch ({}): {}", status, text);
+                return Err(if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+                    EmailError::Transient(message)
+                } else {
+                    EmailError::Permanent(message)
+                });
+            }
+
+            response
+                .json::<ResendBatchResponse>()
+                .await
+                .map_err(|e| EmailError::Transient(format!("Failed to parse Resend batch response: {}", e)))
+        }
+    })
+    .await;
+
+    match outcome {
+        Ok(response) => {
+            let mut ids = response.data.into_iter().map(|item| item.id);
+            emails.iter().map(|_| Ok(ids.next())).collect()
+        }
+        Err(e) => vec![Err(e); emails.len()],
+    }
+}
+
+/// Run `send` on every item with at most `limit` in flight. Results come back in
+/// input order; None marks a task that panicked.
+async fn run_concurrently<T, R, F, Fut>(items: Vec<T>, limit: usize, send: F) -> Vec<Option<R>>
+where
+    F: Fn(T) -> Fut,
+    Fut: Future<Output = R> + Send + 'static,
+    R: Send + 'static,
+{
+    let semaphore = Arc::new(Semaphore::new(limit));
+    let mut tasks = JoinSet::new();
+    let mut results: Vec<Option<R>> = Vec::with_capacity(items.len());
+
+    for (index, item) in items.into_iter().enumerate() {
+        results.push(None);
+        let semaphore = semaphore.clone();
+        let task = send(item);
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            (index, task.await)
+        });
+    }
+
+    while let Some(joined) = tasks.join_next().await {
+        match joined {
+            Ok((index, result)) => results[index] = Some(result),
+            Err(e) => tracing::error!("Bulk send task failed: {}", e),
+        }
+    }
+
+    results
+}
+
+/// Run a send with a per-attempt timeout, retrying transient failures up to
+/// MAX_SEND_ATTEMPTS times with backoff and jitter
+async fn with_retries<T, F, Fut>(to: &str, mut attempt_send: F) -> Result<T, EmailError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, EmailError>>,
+{
+    let mut attempt = 1;
+    loop {
+        let error = match tokio::time::timeout(SEND_TIMEOUT, attempt_send()).await {
+            Ok(Ok(sent)) => return Ok(sent),
+            Ok(Err(e)) => e,
+            Err(_) => EmailError::Transient(format!("Send timed out after {}s", SEND_TIMEOUT.as_secs())),
+        };
+
+        if !error.is_transient() || attempt >= MAX_SEND_ATTEMPTS {
+            return Err(error);
+        }
+
+        tracing::warn!("Email to {} failed (attempt {} of {}), retrying: {}", to, attempt, MAX_SEND_ATTEMPTS, error);
+        tokio::time::sleep(retry_delay(attempt)).await;
+        attempt += 1;
+    }
+}
+
+/// Exponential backoff with random jitter so parallel sends don't retry in lockstep
+fn retry_delay(attempt: u32) -> Duration {
+    let base = BASE_RETRY_DELAY_MS << (attempt - 1).min(10);
+    let jitter = (Uuid::new_v4().as_u128() % (base as u128 + 1)) as u64;
+    Duration::from_millis(base + jitter)
+}
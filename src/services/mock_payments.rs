@@ -0,0 +1,125 @@
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::services::stripe::{CheckoutSessionResult, PaymentIntentResult, RefundResult};
+
+/// How long a simulated Checkout session stays open
+const MOCK_SESSION_TTL_SECS: i64 = 30 * 60;
+
+/// Stands in for Stripe in local testing mode. Sessions, payment intents and refunds are simulated,
+/// and the webhook events Stripe would send are built here and queued for the
+/// normal webhook worker, so tests exercise the real payment code paths.
+#[derive(Clone)]
+pub struct MockPaymentProvider {
+    base_url: String,
+}
+
+/// A Stripe-shaped webhook event, ready to be queued
+pub struct MockEvent {
+    pub id: String,
+    pub event_type: String,
+    pub payload: String,
+}
+
+impl MockPaymentProvider {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.to_string(),
+        }
+    }
+
+    /// A fake Checkout session whose URL completes payment immediately
+    pub fn create_checkout_session(&self) -> CheckoutSessionResult {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        let id = format!("cs_mock_{}", Uuid::new_v4().simple());
+
+        CheckoutSessionResult {
+            url: format!("{}/api/testing/checkout/{}/complete", self.base_url, id),
+            id,
+            expires_at: now + MOCK_SESSION_TTL_SECS,
+        }
+    }
+
+    /// A fake payment intent for the embedded Payment Element. It can't be confirmed
+    /// in the browser; paying is simulated by its testing route instead.
+    pub fn create_payment_intent(&self) -> PaymentIntentResult {
+        let id = format!("pi_mock_{}", Uuid::new_v4().simple());
+
+        PaymentIntentResult {
+            client_secret: format!("{}_secret_mock", id),
+            id,
+        }
+    }
+
+    /// A fake full refund that succeeds straight away
+    pub fn create_refund(&self, amount_cents: i64) -> RefundResult {
+        RefundResult {
+            id: format!("re_mock_{}", Uuid::new_v4().simple()),
+            status: "succeeded".to_string(),
+            amount: amount_cents,
+        }
+    }
+
+    /// checkout.session.completed for a paid session
+    pub fn checkout_completed_event(&self, session_id: &str, order_id: &str, amount_cents: i64) -> MockEvent {
+        let payment_intent_id = format!("pi_mock_{}", Uuid::new_v4().simple());
+        self.event(
+            "checkout.session.completed",
+            json!({
+                "id": session_id,
+                "object": "checkout.session",
+                "metadata": { "order_id": order_id },
+                "payment_intent": payment_intent_id,
+                "payment_status": "paid",
+                "amount_total": amount_cents,
+                "total_details": { "amount_discount": 0 },
+            }),
+        )
+    }
+
+    /// payment_intent.succeeded for a paid Payment Element intent
+    pub fn payment_intent_succeeded_event(&self, payment_intent_id: &str, order_id: &str, amount_cents: i64) -> MockEvent {
+        self.event(
+            "payment_intent.succeeded",
+            json!({
+                "id": payment_intent_id,
+                "object": "payment_intent",
+                "metadata": { "order_id": order_id },
+                "status": "succeeded",
+                "amount": amount_cents,
+            }),
+        )
+    }
+
+    /// refund.updated for a refund that went through
+    pub fn refund_succeeded_event(&self, refund: &RefundResult, payment_intent_id: &str) -> MockEvent {
+        self.event(
+            "refund.updated",
+            json!({
+                "id": refund.id,
+                "object": "refund",
+                "status": "succeeded",
+                "payment_intent": payment_intent_id,
+                "amount": refund.amount,
+            }),
+        )
+    }
+
+    fn event(&self, event_type: &str, object: serde_json::Value) -> MockEvent {
+        let id = format!("evt_mock_{}", Uuid::new_v4().simple());
+        let payload = json!({
+            "id": id,
+            "type": event_type,
+            "data": { "object": object },
+        });
+
+        MockEvent {
+            id,
+            event_type: event_type.to_string(),
+            payload: payload.to_string(),
+        }
+    }
+}
@@ -1,16 +1,27 @@
+pub mod auth_provider;
 pub mod clerk;
 pub mod email;
+pub mod i18n;
 pub mod image;
 pub mod jwks;
+pub mod mailer;
+pub mod mock_payments;
 pub mod rate_limiter;
 pub mod resend;
 pub mod shippo;
 pub mod stripe;
+pub mod svix;
+pub mod token;
+pub mod totp;
 
+pub use auth_provider::{AuthProvider, ClerkAuthProvider, JwtAuthProvider};
 pub use clerk::ClerkService;
 pub use email::EmailService;
 pub use jwks::JwksVerifier;
+pub use mailer::{Mailer, ResendMailer, SmtpMailer};
+pub use mock_payments::MockPaymentProvider;
 pub use rate_limiter::RateLimiter;
 pub use resend::ResendService;
 pub use shippo::ShippoService;
 pub use stripe::StripeService;
+pub use token::TokenService;
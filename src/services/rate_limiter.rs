@@ -1,24 +1,201 @@
-use redis::{AsyncCommands, Client};
+use redis::{AsyncCommands, Client, Script};
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
 
-/// Distributed rate limiter using Upstash Redis
+/// How often idle buckets are dropped from the in-process limiter
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// GCRA (generic cell rate algorithm) on one key. The key holds the
+/// theoretical arrival time (TAT) in milliseconds: when the caller's allowance
+/// would be fully spent if requests kept arriving at the steady rate. A request
+/// is allowed while the TAT is less than a burst's worth of intervals ahead of
+/// now. Uses the Redis clock so every instance agrees.
+///
+/// ARGV: emission interval in ms, burst size.
+/// Returns {allowed, remaining, ms until full allowance, ms until retry}.
+const GCRA_SCRIPT: &str = r#"
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+local interval = tonumber(ARGV[1])
+local burst = tonumber(ARGV[2])
+
+local tat = tonumber(redis.call('GET', KEYS[1])) or now
+if tat < now then
+    tat = now
+end
+
+local new_tat = tat + interval
+local allow_at = new_tat - interval * burst
+if now < allow_at then
+    return {0, 0, tat - now, allow_at - now}
+end
+
+redis.call('SET', KEYS[1], new_tat, 'PX', new_tat - now)
+return {1, math.floor((now - allow_at) / interval), new_tat - now, 0}
+"#;
+
+/// Outcome of a rate limit check, with what the caller needs for
+/// `RateLimit-*` and `Retry-After` headers
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    /// Requests per minute
+    pub limit: u32,
+    /// Requests the caller can still make right now
+    pub remaining: u32,
+    /// Seconds until the caller's full burst allowance is back
+    pub reset_secs: u64,
+    /// Seconds before the next request would be allowed; 0 when allowed
+    pub retry_after_secs: u64,
+}
+
+/// Rate limiter backed by Upstash Redis, so limits hold across server
+/// instances. Without Redis, or while it can't be reached, an in-process
+/// token bucket per key enforces the same limits for this instance.
+///
+/// Both allow `limit` requests per minute at a steady rate, and up to
+/// `burst_percent` of a minute's allowance at once.
 #[derive(Clone)]
 pub struct RateLimiter {
-    client: Client,
-    connection: Arc<Mutex<Option<redis::aio::MultiplexedConnection>>>,
+    redis: Option<RedisLimiter>,
+    local: LocalLimiter,
     requests_per_minute: u32,
+    burst_percent: u32,
 }
 
 impl RateLimiter {
-    pub fn new(redis_url: &str, requests_per_minute: u32) -> Result<Self, RateLimitError> {
+    pub fn new(redis_url: &str, requests_per_minute: u32, burst_percent: u32) -> Result<Self, RateLimitError> {
+        Ok(Self {
+            redis: Some(RedisLimiter::new(redis_url)?),
+            local: LocalLimiter::new(),
+            requests_per_minute,
+            burst_percent,
+        })
+    }
+
+    /// Limiter for a single instance, used when Upstash isn't configured
+    pub fn local(requests_per_minute: u32, burst_percent: u32) -> Self {
+        Self {
+            redis: None,
+            local: LocalLimiter::new(),
+            requests_per_minute,
+            burst_percent,
+        }
+    }
+
+    /// Whether limits are shared through Redis rather than per instance
+    pub fn is_distributed(&self) -> bool {
+        self.redis.is_some()
+    }
+
+    /// Count a request from the given IP against the general limit
+    pub async fn check_rate_limit(&self, ip: &str) -> Result<RateLimitDecision, RateLimitError> {
+        self.check_limit(&format!("rate_limit:{}", ip), self.requests_per_minute).await
+    }
+
+    /// Check a separate, usually stricter, per-minute limit for one endpoint or
+    /// route class. `caller` is an IP, or any other key the limit applies to.
+    pub async fn check_scoped_rate_limit(
+        &self,
+        scope: &str,
+        caller: &str,
+        limit: u32,
+    ) -> Result<RateLimitDecision, RateLimitError> {
+        self.check_limit(&format!("rate_limit:{}:{}", scope, caller), limit).await
+    }
+
+    /// Requests allowed at once for a per-minute `limit`
+    fn burst(&self, limit: u32) -> u32 {
+        ((limit as u64 * self.burst_percent as u64 / 100) as u32).max(1)
+    }
+
+    async fn check_limit(&self, key: &str, limit: u32) -> Result<RateLimitDecision, RateLimitError> {
+        let limit = limit.max(1);
+        let burst = self.burst(limit);
+
+        if let Some(ref redis) = self.redis {
+            match redis.check_limit(key, limit, burst).await {
+                Ok(decision) => return Ok(decision),
+                Err(e) => {
+                    tracing::warn!("Redis rate limiter unavailable ({}) - using in-process limits", e);
+                }
+            }
+        }
+
+        Ok(self.local.check_limit(key, limit, burst))
+    }
+
+    /// Count one event (e.g. a failed sign-in) against `key` in a fixed window
+    /// of `window_secs`. Returns the count so far in the window.
+    pub async fn record_event(&self, key: &str, window_secs: u64) -> Result<u32, RateLimitError> {
+        let key = format!("events:{}", key);
+
+        if let Some(ref redis) = self.redis {
+            match redis.incr_window(&key, window_secs).await {
+                Ok(count) => return Ok(count as u32),
+                Err(e) => {
+                    tracing::warn!("Redis rate limiter unavailable ({}) - using in-process limits", e);
+                }
+            }
+        }
+
+        Ok(self.local.record_event(&key, Duration::from_secs(window_secs)))
+    }
+
+    /// Lock `key` out for `secs`
+    pub async fn lock(&self, key: &str, secs: u64) -> Result<(), RateLimitError> {
+        let key = format!("lock:{}", key);
+
+        if let Some(ref redis) = self.redis {
+            match redis.set_flag(&key, secs).await {
+                Ok(()) => return Ok(()),
+                Err(e) => {
+                    tracing::warn!("Redis rate limiter unavailable ({}) - using in-process limits", e);
+                }
+            }
+        }
+
+        self.local.lock(&key, Duration::from_secs(secs));
+        Ok(())
+    }
+
+    /// Whether `key` is currently locked out
+    pub async fn is_locked(&self, key: &str) -> Result<bool, RateLimitError> {
+        let key = format!("lock:{}", key);
+
+        if let Some(ref redis) = self.redis {
+            match redis.has_flag(&key).await {
+                Ok(locked) => return Ok(locked),
+                Err(e) => {
+                    tracing::warn!("Redis rate limiter unavailable ({}) - using in-process limits", e);
+                }
+            }
+        }
+
+        Ok(self.local.is_locked(&key))
+    }
+}
+
+/// Request limits run `GCRA_SCRIPT` atomically in Redis; event counts
+/// (failed sign-ins) are fixed windows counted with INCR/EXPIRE
+#[derive(Clone)]
+struct RedisLimiter {
+    client: Client,
+    connection: Arc<Mutex<Option<redis::aio::MultiplexedConnection>>>,
+    gcra: Script,
+}
+
+impl RedisLimiter {
+    fn new(redis_url: &str) -> Result<Self, RateLimitError> {
         let client = Client::open(redis_url)
             .map_err(|e| RateLimitError::Connection(e.to_string()))?;
 
         Ok(Self {
             client,
             connection: Arc::new(Mutex::new(None)),
-            requests_per_minute,
+            gcra: Script::new(GCRA_SCRIPT),
         })
     }
 
@@ -38,41 +215,213 @@ impl RateLimiter {
         Ok(conn)
     }
 
-    /// Check if request is allowed for the given IP
-    /// Returns Ok(true) if allowed, Ok(false) if rate limited
-    pub async fn check_rate_limit(&self, ip: &str) -> Result<bool, RateLimitError> {
-        let key = format!("rate_limit:{}", ip);
-        let window_seconds: i64 = 60;
+    /// Drop the cached connection so the next request reconnects
+    async fn reset_connection(&self) {
+        *self.connection.lock().await = None;
+    }
 
+    async fn check_limit(&self, key: &str, limit: u32, burst: u32) -> Result<RateLimitDecision, RateLimitError> {
+        let mut conn = self.get_connection().await?;
+
+        // Milliseconds between requests at the steady rate
+        let interval_ms = (60_000 / limit as u64).max(1);
+        let result: Result<Vec<i64>, _> = self.gcra.key(key).arg(interval_ms).arg(burst).invoke_async(&mut conn).await;
+        let values = match result {
+            Ok(values) if values.len() == 4 => values,
+            Ok(values) => return Err(RateLimitError::Redis(format!("unexpected GCRA reply {:?}", values))),
+            Err(e) => {
+                self.reset_connection().await;
+                return Err(RateLimitError::Redis(e.to_string()));
+            }
+        };
+
+        Ok(RateLimitDecision {
+            allowed: values[0] == 1,
+            limit,
+            remaining: values[1].max(0) as u32,
+            reset_secs: ms_to_secs(values[2]),
+            retry_after_secs: ms_to_secs(values[3]),
+        })
+    }
+
+    /// Increment a counter that resets `window_secs` after its first increment
+    async fn incr_window(&self, key: &str, window_secs: u64) -> Result<i64, RateLimitError> {
         let mut conn = self.get_connection().await?;
 
         // Increment the counter
-        let count: i64 = conn.incr(&key, 1)
-            .await
-            .map_err(|e| RateLimitError::Redis(e.to_string()))?;
+        let count: i64 = match conn.incr(key, 1).await {
+            Ok(count) => count,
+            Err(e) => {
+                self.reset_connection().await;
+                return Err(RateLimitError::Redis(e.to_string()));
+            }
+        };
 
         // If this is the first request in the window, set expiry
         if count == 1 {
-            let _: () = conn.expire(&key, window_seconds)
+            let _: () = conn.expire(key, window_secs as i64)
                 .await
                 .map_err(|e| RateLimitError::Redis(e.to_string()))?;
         }
 
-        Ok(count <= self.requests_per_minute as i64)
+        Ok(count)
     }
 
-    /// Get remaining requests for the given IP
-    pub async fn get_remaining(&self, ip: &str) -> Result<u32, RateLimitError> {
-        let key = format!("rate_limit:{}", ip);
+    async fn set_flag(&self, key: &str, secs: u64) -> Result<(), RateLimitError> {
+        let mut conn = self.get_connection().await?;
 
+        let result: Result<(), _> = conn.set_ex(key, 1, secs).await;
+        match result {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                self.reset_connection().await;
+                Err(RateLimitError::Redis(e.to_string()))
+            }
+        }
+    }
+
+    async fn has_flag(&self, key: &str) -> Result<bool, RateLimitError> {
         let mut conn = self.get_connection().await?;
 
-        let count: Option<i64> = conn.get(&key)
-            .await
-            .map_err(|e| RateLimitError::Redis(e.to_string()))?;
+        let result: Result<bool, _> = conn.exists(key).await;
+        match result {
+            Ok(exists) => Ok(exists),
+            Err(e) => {
+                self.reset_connection().await;
+                Err(RateLimitError::Redis(e.to_string()))
+            }
+        }
+    }
+
+}
+
+/// Whole seconds, rounded up so a client waiting that long is allowed
+fn ms_to_secs(ms: i64) -> u64 {
+    (ms.max(0) as u64).div_ceil(1000)
+}
+
+/// In-memory token buckets, one per key. A bucket holds up to `burst` tokens
+/// and refills at `limit` per minute, which allows the same requests as GCRA
+/// in Redis.
+#[derive(Clone)]
+struct LocalLimiter {
+    state: Arc<std::sync::Mutex<LocalState>>,
+}
+
+struct LocalState {
+    buckets: HashMap<String, Bucket>,
+    /// Event counts and when their window ends
+    counters: HashMap<String, (u32, Instant)>,
+    /// Locked keys and when the lock ends
+    locks: HashMap<String, Instant>,
+    last_prune: Instant,
+}
+
+struct Bucket {
+    tokens: f64,
+    capacity: f64,
+    /// Tokens added per second
+    rate: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn new(capacity: u32, per_minute: u32, now: Instant) -> Self {
+        Self {
+            tokens: capacity as f64,
+            capacity: capacity as f64,
+            rate: per_minute as f64 / 60.0,
+            updated: now,
+        }
+    }
+
+    /// Add the tokens earned since the last update
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.updated = now;
+    }
+
+    /// Seconds until the bucket holds `tokens`
+    fn secs_until(&self, tokens: f64) -> u64 {
+        ((tokens - self.tokens).max(0.0) / self.rate).ceil() as u64
+    }
+}
+
+impl LocalLimiter {
+    fn new() -> Self {
+        Self {
+            state: Arc::new(std::sync::Mutex::new(LocalState {
+                buckets: HashMap::new(),
+                counters: HashMap::new(),
+                locks: HashMap::new(),
+                last_prune: Instant::now(),
+            })),
+        }
+    }
+
+    fn check_limit(&self, key: &str, limit: u32, burst: u32) -> RateLimitDecision {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        Self::prune_if_due(&mut state, now);
+
+        let bucket = state
+            .buckets
+            .entry(key.to_string())
+            .or_insert_with(|| Bucket::new(burst, limit, now));
+        bucket.refill(now);
+
+        let allowed = bucket.tokens >= 1.0;
+        if allowed {
+            bucket.tokens -= 1.0;
+        }
+
+        RateLimitDecision {
+            allowed,
+            limit,
+            remaining: bucket.tokens.floor() as u32,
+            reset_secs: bucket.secs_until(bucket.capacity),
+            retry_after_secs: if allowed { 0 } else { bucket.secs_until(1.0) },
+        }
+    }
+
+    fn record_event(&self, key: &str, window: Duration) -> u32 {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        Self::prune_if_due(&mut state, now);
+
+        let counter = state.counters.entry(key.to_string()).or_insert((0, now + window));
+        if counter.1 <= now {
+            *counter = (0, now + window);
+        }
+        counter.0 += 1;
+        counter.0
+    }
 
-        let count = count.unwrap_or(0) as u32;
-        Ok(self.requests_per_minute.saturating_sub(count))
+    fn lock(&self, key: &str, duration: Duration) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.locks.insert(key.to_string(), Instant::now() + duration);
+    }
+
+    fn is_locked(&self, key: &str) -> bool {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.locks.get(key).is_some_and(|until| *until > Instant::now())
+    }
+
+    /// Drop buckets that have refilled completely (a fresh bucket behaves the
+    /// same), and counters and locks that have run out
+    fn prune_if_due(state: &mut LocalState, now: Instant) {
+        if now.duration_since(state.last_prune) < PRUNE_INTERVAL {
+            return;
+        }
+        state.buckets.retain(|_, bucket| {
+            bucket.refill(now);
+            bucket.tokens < bucket.capacity
+        });
+        state.counters.retain(|_, (_, window_end)| *window_end > now);
+        state.locks.retain(|_, until| *until > now);
+        state.last_prune = now;
     }
 }
 
@@ -90,3 +439,78 @@ impl std::fmt::Display for RateLimitError {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn burst_is_a_share_of_the_per_minute_limit() {
+        assert_eq!(RateLimiter::local(60, 100).burst(60), 60);
+        assert_eq!(RateLimiter::local(60, 25).burst(60), 15);
+        assert_eq!(RateLimiter::local(60, 250).burst(10), 25);
+        // Never so small that nothing is allowed
+        assert_eq!(RateLimiter::local(60, 1).burst(10), 1);
+        assert_eq!(RateLimiter::local(60, 0).burst(60), 1);
+    }
+
+    #[test]
+    fn milliseconds_round_up_to_whole_seconds() {
+        assert_eq!(ms_to_secs(0), 0);
+        assert_eq!(ms_to_secs(1), 1);
+        assert_eq!(ms_to_secs(1000), 1);
+        assert_eq!(ms_to_secs(1001), 2);
+        assert_eq!(ms_to_secs(-500), 0);
+    }
+
+    #[test]
+    fn bucket_refills_at_the_steady_rate_up_to_capacity() {
+        let start = Instant::now();
+        let mut bucket = Bucket::new(10, 60, start);
+        bucket.tokens = 0.0;
+
+        bucket.refill(start + Duration::from_secs(3));
+        assert!((bucket.tokens - 3.0).abs() < 1e-9);
+        assert_eq!(bucket.secs_until(1.0), 0);
+        assert_eq!(bucket.secs_until(bucket.capacity), 7);
+
+        bucket.refill(start + Duration::from_secs(60));
+        assert_eq!(bucket.tokens, 10.0);
+    }
+
+    #[test]
+    fn local_limiter_allows_the_burst_then_refuses() {
+        let limiter = LocalLimiter::new();
+        for i in 0..5 {
+            let decision = limiter.check_limit("k", 30, 5);
+            assert!(decision.allowed, "request {}", i + 1);
+            assert_eq!(decision.remaining, 4 - i);
+            assert_eq!(decision.retry_after_secs, 0);
+        }
+
+        let refused = limiter.check_limit("k", 30, 5);
+        assert!(!refused.allowed);
+        assert_eq!(refused.remaining, 0);
+        assert_eq!(refused.limit, 30);
+        // 30 a minute is one every 2 seconds; the full burst of 5 takes 10
+        assert_eq!(refused.retry_after_secs, 2);
+        assert_eq!(refused.reset_secs, 10);
+    }
+
+    #[test]
+    fn local_limits_are_per_key() {
+        let limiter = LocalLimiter::new();
+        assert!(limiter.check_limit("a", 60, 1).allowed);
+        assert!(!limiter.check_limit("a", 60, 1).allowed);
+        assert!(limiter.check_limit("b", 60, 1).allowed);
+    }
+
+    #[test]
+    fn event_counts_reset_after_their_window() {
+        let limiter = LocalLimiter::new();
+        assert_eq!(limiter.record_event("e", Duration::from_secs(60)), 1);
+        assert_eq!(limiter.record_event("e", Duration::from_secs(60)), 2);
+        assert_eq!(limiter.record_event("f", Duration::ZERO), 1);
+        assert_eq!(limiter.record_event("f", Duration::ZERO), 1);
+    }
+}
@@ -1,68 +1,351 @@
-use resend_rs::types::CreateEmailBaseOptions;
-use resend_rs::Resend;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use libsql::Database;
+use reqwest::Client;
+use serde::Deserialize;
 
 use crate::error::{AppError, AppResult};
-use crate::models::Product;
+use crate::models::{DiscountCode, EmailBranding, EmailSuppression, NewsletterCampaign, NewsletterSubscriber, Product};
+use crate::services::email::{email_footer, email_logo, escape_html, load_branding, log_email};
+use crate::services::i18n::{t, t_with};
+use crate::services::mailer::{Mailer, OutgoingEmail};
+use crate::services::svix;
+
+const RESEND_API_URL: &str = "https://api.resend.com";
+
+/// Stands in for a subscriber's token in test sends; matches no subscriber
+const TEST_TOKEN: &str = "test";
 
+/// Newsletter emails, sent through whichever Mailer is configured, plus the Resend
+/// Audience and webhook APIs when a Resend API key is set
 #[derive(Clone)]
 pub struct ResendService {
-    client: Resend,
-    from_email: String,
+    mailer: Arc<dyn Mailer>,
+    /// Raw API access for the audience contact endpoints
+    http: Client,
+    api_key: Option<String>,
     base_url: String,
+    /// Resend Audience kept in step with confirmed subscribers, if configured
+    audience_id: Option<String>,
+    /// Signing secret (`whsec_...`) for bounce and complaint webhooks
+    webhook_secret: Option<String>,
+    /// Where every send attempt is logged
+    db: Arc<Database>,
+}
+
+/// A contact in the Resend Audience
+#[derive(Debug, Clone, Deserialize)]
+pub struct AudienceContact {
+    pub id: String,
+    pub email: String,
+    /// Set when the contact unsubscribed from a broadcast sent from the Resend dashboard
+    #[serde(default)]
+    pub unsubscribed: bool,
+}
+
+/// A Resend webhook event. Only the fields needed for delivery tracking and
+/// suppression are parsed.
+#[derive(Debug, Deserialize)]
+pub struct ResendWebhookEvent {
+    /// e.g. email.delivered, email.opened, email.bounced, email.complained
+    #[serde(rename = "type")]
+    pub event_type: String,
+    #[serde(default)]
+    pub data: ResendWebhookData,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ResendWebhookData {
+    /// ID Resend returned when the email was sent
+    pub email_id: Option<String>,
+    #[serde(default)]
+    pub to: Vec<String>,
+    pub bounce: Option<ResendBounce>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResendBounce {
+    /// Permanent, Transient or Undetermined
+    #[serde(rename = "type")]
+    pub bounce_type: Option<String>,
+    pub message: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct AudienceContactList {
+    data: Vec<AudienceContact>,
+    #[serde(default)]
+    has_more: bool,
 }
 
 impl ResendService {
-    pub fn new(api_key: &str, from_email: &str, base_url: &str) -> Self {
+    pub fn new(
+        mailer: Arc<dyn Mailer>,
+        api_key: Option<String>,
+        base_url: &str,
+        audience_id: Option<String>,
+        webhook_secret: Option<String>,
+        db: Arc<Database>,
+    ) -> Self {
         Self {
-            client: Resend::new(api_key),
-            from_email: from_email.to_string(),
+            mailer,
+            http: Client::new(),
+            api_key,
             base_url: base_url.to_string(),
+            audience_id,
+            webhook_secret,
+            db,
+        }
+    }
+
+    /// Verify a webhook's Svix signature (`svix-id`, `svix-timestamp` and
+    /// `svix-signature` headers) and parse the event
+    pub fn verify_webhook(
+        &self,
+        payload: &str,
+        message_id: &str,
+        timestamp: &str,
+        signature: &str,
+    ) -> AppResult<ResendWebhookEvent> {
+        let secret = self.webhook_secret.as_deref().ok_or_else(|| {
+            AppError::ExternalService("Resend webhook secret not configured. Set RESEND_WEBHOOK_SECRET.".to_string())
+        })?;
+        svix::verify_signature(secret, payload, message_id, timestamp, signature)?;
+
+        serde_json::from_str(payload)
+            .map_err(|e| AppError::ExternalService(format!("Invalid Resend webhook payload: {}", e)))
+    }
+
+    pub fn has_audience(&self) -> bool {
+        self.audience().is_some()
+    }
+
+    /// The audience ID and API key, when both are configured
+    fn audience(&self) -> Option<(&str, &str)> {
+        match (&self.audience_id, &self.api_key) {
+            (Some(audience_id), Some(api_key)) => Some((audience_id, api_key)),
+            _ => None,
+        }
+    }
+
+    /// Add a confirmed subscriber to the Resend Audience. No-op without an audience.
+    pub async fn add_contact(&self, email: &str) -> AppResult<()> {
+        let (audience_id, api_key) = match self.audience() {
+            Some(audience) => audience,
+            None => return Ok(()),
+        };
+
+        let response = self
+            .http
+            .post(format!("{}/audiences/{}/contacts", RESEND_API_URL, audience_id))
+            .bearer_auth(api_key)
+            .json(&serde_json::json!({ "email": email, "unsubscribed": false }))
+            .send()
+            .await
+            .map_err(|e| AppError::Internal(format!("Resend API error: {}", e)))?;
+
+        if !response.status().is_success() {
+            let text = response.text().await.unwrap_or_default();
+            return Err(AppError::Internal(format!("Failed to add audience contact: {}", text)));
         }
+
+        Ok(())
+    }
+
+    /// Remove a subscriber from the Resend Audience. No-op without an audience, and
+    /// a contact that is already gone counts as removed.
+    pub async fn remove_contact(&self, email: &str) -> AppResult<()> {
+        let (audience_id, api_key) = match self.audience() {
+            Some(audience) => audience,
+            None => return Ok(()),
+        };
+
+        let response = self
+            .http
+            .delete(format!("{}/audiences/{}/contacts/{}", RESEND_API_URL, audience_id, email))
+            .bearer_auth(api_key)
+            .send()
+            .await
+            .map_err(|e| AppError::Internal(format!("Resend API error: {}", e)))?;
+
+        if !response.status().is_success() && response.status() != reqwest::StatusCode::NOT_FOUND {
+            let text = response.text().await.unwrap_or_default();
+            return Err(AppError::Internal(format!("Failed to remove audience contact: {}", text)));
+        }
+
+        Ok(())
+    }
+
+    /// Every contact in the Resend Audience. Empty without an audience.
+    pub async fn list_contacts(&self) -> AppResult<Vec<AudienceContact>> {
+        let (audience_id, api_key) = match self.audience() {
+            Some(audience) => audience,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut contacts: Vec<AudienceContact> = Vec::new();
+        loop {
+            let mut url = format!("{}/audiences/{}/contacts?limit=100", RESEND_API_URL, audience_id);
+            if let Some(last) = contacts.last() {
+                url.push_str(&format!("&after={}", last.id));
+            }
+
+            let response = self
+                .http
+                .get(&url)
+                .bearer_auth(api_key)
+                .send()
+                .await
+                .map_err(|e| AppError::Internal(format!("Resend API error: {}", e)))?;
+
+            if !response.status().is_success() {
+                let text = response.text().await.unwrap_or_default();
+                return Err(AppError::Internal(format!("Failed to list audience contacts: {}", text)));
+            }
+
+            let page: AudienceContactList = response
+                .json()
+                .await
+                .map_err(|e| AppError::Internal(format!("Failed to parse audience contacts: {}", e)))?;
+
+            let done = !page.has_more || page.data.is_empty();
+            contacts.extend(page.data);
+            if done {
+                return Ok(contacts);
+            }
+        }
+    }
+
+    /// Addresses batch sends skip after a bounce or complaint
+    async fn suppressed_emails(&self) -> AppResult<HashSet<String>> {
+        let conn = self.db.connect().map_err(AppError::from)?;
+        EmailSuppression::emails(&conn).await
+    }
+
+    /// Preference center where a subscriber picks categories or unsubscribes
+    fn preferences_url(&self, unsubscribe_token: &str) -> String {
+        format!("{}/api/newsletter/preferences?token={}", self.base_url, unsubscribe_token)
+    }
+
+    /// Target of the List-Unsubscribe header; mail clients POST to it for one-click unsubscribe
+    fn unsubscribe_url(&self, unsubscribe_token: &str) -> String {
+        format!("{}/api/newsletter/unsubscribe?token={}", self.base_url, unsubscribe_token)
+    }
+
+    /// The shop's email branding, e.g. for rendering a campaign preview
+    pub async fn branding(&self) -> EmailBranding {
+        load_branding(&self.db).await
+    }
+
+    /// Double opt-in: ask a new subscriber to confirm before they get any campaigns
+    pub async fn send_confirmation_email(&self, to_email: &str, confirm_token: &str, locale: &str) -> AppResult<()> {
+        let branding = self.branding().await;
+        let confirm_url = format!("{}/api/newsletter/confirm?token={}", self.base_url, confirm_token);
+
+        let html = format!(
+            r#"<!DOCTYPE html>
+<html>
+<head>
+    <style>
+        body {{ font-family: 'Courier New', monospace; background: {background}; padding: 20px; margin: 0; }}
+        .container {{ max-width: 600px; margin: 0 auto; background: white; padding: 32px; border: 2px solid #E0E0E0; border-radius: 12px; text-align: center; }}
+        h1 {{ color: {accent}; font-size: 18px; margin-bottom: 20px; }}
+        p {{ color: {text}; font-size: 14px; line-height: 1.8; }}
+        .btn {{ display: inline-block; background: {accent}; color: {text}; padding: 14px 28px; text-decoration: none; font-size: 12px; border-radius: 8px; font-family: inherit; }}
+        .footer {{ margin-top: 32px; padding-top: 20px; border-top: 1px solid #E0E0E0; font-size: 10px; color: #666; }}
+    </style>
+</head>
+<body>
+    <div class="container">
+        {logo}
+        <h1>{}</h1>
+        <p>{}</p>
+        <a href="{}" class="btn">{}</a>
+        <div class="footer">
+            <p>{}</p>
+        </div>
+    </div>
+</body>
+</html>"#,
+            t(locale, "newsletter-confirm-title"),
+            t(locale, "newsletter-confirm-intro"),
+            confirm_url,
+            t(locale, "newsletter-confirm-button"),
+            t(locale, "newsletter-confirm-ignore"),
+            accent = branding.accent_color,
+            text = branding.text_color,
+            background = branding.background_color,
+            logo = email_logo(&branding)
+        );
+
+        self.send_email(
+            "newsletter_confirm",
+            to_email,
+            &t(locale, "newsletter-confirm-subject"),
+            &html,
+        ).await
     }
 
-    pub async fn send_welcome_email(&self, to_email: &str, unsubscribe_token: &str) -> AppResult<()> {
-        let unsubscribe_url = format!("{}/api/newsletter/unsubscribe?token={}", self.base_url, unsubscribe_token);
+    pub async fn send_welcome_email(&self, to_email: &str, unsubscribe_token: &str, locale: &str) -> AppResult<()> {
+        let branding = self.branding().await;
+        let preferences_url = self.preferences_url(unsubscribe_token);
 
         let html = format!(
             r#"<!DOCTYPE html>
 <html>
 <head>
     <style>
-        body {{ font-family: 'Courier New', monospace; background: #F8F8F8; padding: 20px; margin: 0; }}
+        body {{ font-family: 'Courier New', monospace; background: {background}; padding: 20px; margin: 0; }}
         .container {{ max-width: 600px; margin: 0 auto; background: white; padding: 32px; border: 2px solid #E0E0E0; border-radius: 12px; }}
-        h1 {{ color: #97BAD9; font-size: 18px; margin-bottom: 20px; }}
-        p {{ color: #18191B; font-size: 14px; line-height: 1.8; }}
+        h1 {{ color: {accent}; font-size: 18px; margin-bottom: 20px; }}
+        p {{ color: {text}; font-size: 14px; line-height: 1.8; }}
         .footer {{ margin-top: 32px; padding-top: 20px; border-top: 1px solid #E0E0E0; font-size: 10px; color: #666; }}
-        .footer a {{ color: #97BAD9; }}
+        .footer a {{ color: {accent}; }}
     </style>
 </head>
 <body>
     <div class="container">
-        <h1>Welcome to Caterpillar Clay!</h1>
-        <p>Thank you for subscribing to our newsletter!</p>
-        <p>You'll be the first to know when we add new handmade pottery pieces to our shop.</p>
-        <p>Each piece is crafted with care, featuring hand-painted designs inspired by local flowers.</p>
+        {logo}
+        <h1>{}</h1>
+        <p>{}</p>
+        <p>{}</p>
+        <p>{}</p>
         <div class="footer">
-            <p>Caterpillar Clay - Handmade Pottery</p>
-            <p><a href="{}">Unsubscribe</a></p>
+            {footer}
+            <p><a href="{}">{}</a></p>
         </div>
     </div>
 </body>
 </html>"#,
-            unsubscribe_url
+            t(locale, "newsletter-welcome-title"),
+            t(locale, "newsletter-welcome-thanks"),
+            t(locale, "newsletter-welcome-first"),
+            t(locale, "newsletter-welcome-craft"),
+            preferences_url,
+            t(locale, "footer-preferences"),
+            accent = branding.accent_color,
+            text = branding.text_color,
+            background = branding.background_color,
+            logo = email_logo(&branding),
+            footer = email_footer(&branding, locale)
         );
 
-        self.send_email(to_email, "Welcome to Caterpillar Clay!", &html).await
+        let email = OutgoingEmail::new("newsletter_welcome", to_email, &t(locale, "newsletter-welcome-subject"), html)
+            .with_list_unsubscribe(self.unsubscribe_url(unsubscribe_token));
+        self.send_one(email).await
     }
 
-    pub async fn send_new_product_notification(
+    fn new_product_email(
         &self,
         to_email: &str,
         unsubscribe_token: &str,
         product: &Product,
         product_image_url: Option<&str>,
-    ) -> AppResult<()> {
-        let unsubscribe_url = format!("{}/api/newsletter/unsubscribe?token={}", self.base_url, unsubscribe_token);
+        branding: &EmailBranding,
+        locale: &str,
+    ) -> OutgoingEmail {
+        let preferences_url = self.preferences_url(unsubscribe_token);
         let product_url = format!("{}/?product={}", self.base_url, product.id);
 
         let image_html = if let Some(img_url) = product_image_url {
@@ -76,68 +359,96 @@ impl ResendService {
 <html>
 <head>
     <style>
-        body {{ font-family: 'Courier New', monospace; background: #F8F8F8; padding: 20px; margin: 0; }}
+        body {{ font-family: 'Courier New', monospace; background: {background}; padding: 20px; margin: 0; }}
         .container {{ max-width: 600px; margin: 0 auto; background: white; padding: 32px; border: 2px solid #E0E0E0; border-radius: 12px; text-align: center; }}
-        h1 {{ color: #97BAD9; font-size: 16px; margin-bottom: 24px; }}
-        h2 {{ color: #18191B; font-size: 14px; margin: 16px 0 8px; }}
-        .price {{ color: #97BAD9; font-size: 18px; margin-bottom: 16px; }}
+        h1 {{ color: {accent}; font-size: 16px; margin-bottom: 24px; }}
+        h2 {{ color: {text}; font-size: 14px; margin: 16px 0 8px; }}
+        .price {{ color: {accent}; font-size: 18px; margin-bottom: 16px; }}
         .description {{ color: #666; font-size: 12px; line-height: 1.8; margin-bottom: 24px; }}
-        .btn {{ display: inline-block; background: #97BAD9; color: #18191B; padding: 14px 28px; text-decoration: none; font-size: 12px; border-radius: 8px; font-family: inherit; }}
+        .btn {{ display: inline-block; background: {accent}; color: {text}; padding: 14px 28px; text-decoration: none; font-size: 12px; border-radius: 8px; font-family: inherit; }}
         .footer {{ margin-top: 32px; padding-top: 20px; border-top: 1px solid #E0E0E0; font-size: 10px; color: #666; }}
-        .footer a {{ color: #97BAD9; }}
+        .footer a {{ color: {accent}; }}
     </style>
 </head>
 <body>
     <div class="container">
-        <h1>New Arrival!</h1>
+        {logo}
+        <h1>{}</h1>
         {}
         <h2>{}</h2>
         <p class="price">${:.2}</p>
         <p class="description">{}</p>
-        <a href="{}" class="btn">VIEW PRODUCT</a>
+        <a href="{}" class="btn">{}</a>
         <div class="footer">
-            <p>Caterpillar Clay - Handmade Pottery</p>
-            <p><a href="{}">Unsubscribe</a></p>
+            {footer}
+            <p><a href="{}">{}</a></p>
         </div>
     </div>
 </body>
 </html>"#,
+            t(locale, "new-arrival-title"),
             image_html,
             product.name,
             product.price_cents as f64 / 100.0,
             product.description.as_deref().unwrap_or(""),
             product_url,
-            unsubscribe_url
+            t(locale, "button-view-product"),
+            preferences_url,
+            t(locale, "footer-preferences"),
+            accent = branding.accent_color,
+            text = branding.text_color,
+            background = branding.background_color,
+            logo = email_logo(branding),
+            footer = email_footer(branding, locale)
         );
 
-        self.send_email(
+        OutgoingEmail::new(
+            "newsletter_new_product",
             to_email,
-            &format!("New Arrival: {} - Caterpillar Clay", product.name),
-            &html,
-        ).await
+            &t_with(locale, "new-arrival-subject", &[("name", &product.name)]),
+            html,
+        )
+        .with_list_unsubscribe(self.unsubscribe_url(unsubscribe_token))
     }
 
-    async fn send_email(&self, to: &str, subject: &str, html: &str) -> AppResult<()> {
-        let email = CreateEmailBaseOptions::new(&self.from_email, [to], subject)
-            .with_html(html);
+    async fn send_email(&self, email_type: &str, to: &str, subject: &str, html: &str) -> AppResult<()> {
+        self.send_one(OutgoingEmail::new(email_type, to, subject, html.to_string())).await
+    }
 
-        self.client
-            .emails
-            .send(email)
-            .await
-            .map_err(|e| AppError::Internal(format!("Failed to send email: {}", e)))?;
+    async fn send_one(&self, email: OutgoingEmail) -> AppResult<()> {
+        let outcome = self.mailer.send_transactional(&email).await.map_err(AppError::from);
+        log_email(&self.db, &email.to, &email.email_type, &email.subject, self.mailer.provider(), &outcome).await;
+        outcome.map(|_| ())
+    }
 
-        Ok(())
+    /// Send a batch through the mailer, logging each email. Returns how many went out.
+    async fn send_bulk(&self, emails: Vec<OutgoingEmail>) -> usize {
+        let results = self.mailer.send_bulk(&emails).await;
+        let mut sent_count = 0;
+
+        for (email, result) in emails.iter().zip(results) {
+            let outcome = result.map_err(AppError::from);
+            if let Err(e) = &outcome {
+                tracing::error!("Failed to send {} email to {}: {}", email.email_type, email.to, e);
+            } else {
+                sent_count += 1;
+            }
+            log_email(&self.db, &email.to, &email.email_type, &email.subject, self.mailer.provider(), &outcome).await;
+        }
+
+        sent_count
     }
 
-    pub async fn send_back_in_stock_notification(
+    fn back_in_stock_email(
         &self,
         to_email: &str,
         unsubscribe_token: &str,
         product: &Product,
         product_image_url: Option<&str>,
-    ) -> AppResult<()> {
-        let unsubscribe_url = format!("{}/api/newsletter/unsubscribe?token={}", self.base_url, unsubscribe_token);
+        branding: &EmailBranding,
+        locale: &str,
+    ) -> OutgoingEmail {
+        let preferences_url = self.preferences_url(unsubscribe_token);
         let product_url = format!("{}/?product={}", self.base_url, product.id);
 
         let image_html = if let Some(img_url) = product_image_url {
@@ -151,127 +462,133 @@ impl ResendService {
 <html>
 <head>
     <style>
-        body {{ font-family: 'Courier New', monospace; background: #F8F8F8; padding: 20px; margin: 0; }}
+        body {{ font-family: 'Courier New', monospace; background: {background}; padding: 20px; margin: 0; }}
         .container {{ max-width: 600px; margin: 0 auto; background: white; padding: 32px; border: 2px solid #E0E0E0; border-radius: 12px; text-align: center; }}
         h1 {{ color: #22c55e; font-size: 16px; margin-bottom: 24px; }}
-        h2 {{ color: #18191B; font-size: 14px; margin: 16px 0 8px; }}
-        .price {{ color: #97BAD9; font-size: 18px; margin-bottom: 16px; }}
+        h2 {{ color: {text}; font-size: 14px; margin: 16px 0 8px; }}
+        .price {{ color: {accent}; font-size: 18px; margin-bottom: 16px; }}
         .description {{ color: #666; font-size: 12px; line-height: 1.8; margin-bottom: 24px; }}
         .btn {{ display: inline-block; background: #22c55e; color: #fff; padding: 14px 28px; text-decoration: none; font-size: 12px; border-radius: 8px; font-family: inherit; }}
         .footer {{ margin-top: 32px; padding-top: 20px; border-top: 1px solid #E0E0E0; font-size: 10px; color: #666; }}
-        .footer a {{ color: #97BAD9; }}
+        .footer a {{ color: {accent}; }}
     </style>
 </head>
 <body>
     <div class="container">
-        <h1>Back in Stock!</h1>
+        {logo}
+        <h1>{}</h1>
         {}
         <h2>{}</h2>
         <p class="price">${:.2}</p>
-        <p class="description">Good news! This item is available again. Grab it before it's gone!</p>
-        <a href="{}" class="btn">SHOP NOW</a>
+        <p class="description">{}</p>
+        <a href="{}" class="btn">{}</a>
         <div class="footer">
-            <p>Caterpillar Clay - Handmade Pottery</p>
-            <p><a href="{}">Unsubscribe</a></p>
+            {footer}
+            <p><a href="{}">{}</a></p>
         </div>
     </div>
 </body>
 </html>"#,
+            t(locale, "back-in-stock-title"),
             image_html,
             product.name,
             product.price_cents as f64 / 100.0,
+            t(locale, "back-in-stock-intro"),
             product_url,
-            unsubscribe_url
+            t(locale, "button-shop-now"),
+            preferences_url,
+            t(locale, "footer-preferences"),
+            accent = branding.accent_color,
+            text = branding.text_color,
+            background = branding.background_color,
+            logo = email_logo(branding),
+            footer = email_footer(branding, locale)
         );
 
-        self.send_email(
+        OutgoingEmail::new(
+            "newsletter_back_in_stock",
             to_email,
-            &format!("Back in Stock: {} - Caterpillar Clay", product.name),
-            &html,
-        ).await
+            &t_with(locale, "back-in-stock-subject", &[("name", &product.name)]),
+            html,
+        )
+        .with_list_unsubscribe(self.unsubscribe_url(unsubscribe_token))
     }
 
     pub async fn send_batch_back_in_stock_notification(
         &self,
-        subscribers: &[(String, String)],
+        subscribers: &[NewsletterSubscriber],
         product: &Product,
         product_image_url: Option<&str>,
     ) -> AppResult<usize> {
-        let mut sent_count = 0;
+        let suppressed = self.suppressed_emails().await?;
+        let branding = self.branding().await;
+        let emails = subscribers
+            .iter()
+            .filter(|s| s.wants("restocks") && !suppressed.contains(&s.email.to_lowercase()))
+            .map(|subscriber| self.back_in_stock_email(&subscriber.email, &subscriber.unsubscribe_token, product, product_image_url, &branding, subscriber.email_locale()))
+            .collect();
 
-        for (email, token) in subscribers {
-            if let Err(e) = self.send_back_in_stock_notification(email, token, product, product_image_url).await {
-                tracing::error!("Failed to send back in stock notification to {}: {}", email, e);
-            } else {
-                sent_count += 1;
-            }
-        }
-
-        Ok(sent_count)
+        Ok(self.send_bulk(emails).await)
     }
 
     pub async fn send_batch_new_product_notification(
         &self,
-        subscribers: &[(String, String)], // (email, unsubscribe_token)
+        subscribers: &[NewsletterSubscriber],
         product: &Product,
         product_image_url: Option<&str>,
     ) -> AppResult<usize> {
-        let mut sent_count = 0;
-
-        for (email, token) in subscribers {
-            if let Err(e) = self.send_new_product_notification(email, token, product, product_image_url).await {
-                tracing::error!("Failed to send newsletter to {}: {}", email, e);
-            } else {
-                sent_count += 1;
-            }
-        }
+        let suppressed = self.suppressed_emails().await?;
+        let branding = self.branding().await;
+        let emails = subscribers
+            .iter()
+            .filter(|s| s.wants("new_drops") && !suppressed.contains(&s.email.to_lowercase()))
+            .map(|subscriber| self.new_product_email(&subscriber.email, &subscriber.unsubscribe_token, product, product_image_url, &branding, subscriber.email_locale()))
+            .collect();
 
-        Ok(sent_count)
+        Ok(self.send_bulk(emails).await)
     }
 
     pub async fn send_batch_multi_product_new(
         &self,
-        subscribers: &[(String, String)],
+        subscribers: &[NewsletterSubscriber],
         products: &[(Product, Option<String>)],
     ) -> AppResult<usize> {
-        let mut sent_count = 0;
+        let suppressed = self.suppressed_emails().await?;
+        let branding = self.branding().await;
+        let emails = subscribers
+            .iter()
+            .filter(|s| s.wants("new_drops") && !suppressed.contains(&s.email.to_lowercase()))
+            .map(|subscriber| self.multi_product_new_email(&subscriber.email, &subscriber.unsubscribe_token, products, &branding, subscriber.email_locale()))
+            .collect();
 
-        for (email, token) in subscribers {
-            if let Err(e) = self.send_multi_product_new_email(email, token, products).await {
-                tracing::error!("Failed to send multi-product new email to {}: {}", email, e);
-            } else {
-                sent_count += 1;
-            }
-        }
-
-        Ok(sent_count)
+        Ok(self.send_bulk(emails).await)
     }
 
     pub async fn send_batch_multi_product_restock(
         &self,
-        subscribers: &[(String, String)],
+        subscribers: &[NewsletterSubscriber],
         products: &[(Product, Option<String>)],
     ) -> AppResult<usize> {
-        let mut sent_count = 0;
+        let suppressed = self.suppressed_emails().await?;
+        let branding = self.branding().await;
+        let emails = subscribers
+            .iter()
+            .filter(|s| s.wants("restocks") && !suppressed.contains(&s.email.to_lowercase()))
+            .map(|subscriber| self.multi_product_restock_email(&subscriber.email, &subscriber.unsubscribe_token, products, &branding, subscriber.email_locale()))
+            .collect();
 
-        for (email, token) in subscribers {
-            if let Err(e) = self.send_multi_product_restock_email(email, token, products).await {
-                tracing::error!("Failed to send multi-product restock email to {}: {}", email, e);
-            } else {
-                sent_count += 1;
-            }
-        }
-
-        Ok(sent_count)
+        Ok(self.send_bulk(emails).await)
     }
 
-    async fn send_multi_product_new_email(
+    fn multi_product_new_email(
         &self,
         to_email: &str,
         unsubscribe_token: &str,
         products: &[(Product, Option<String>)],
-    ) -> AppResult<()> {
-        let unsubscribe_url = format!("{}/api/newsletter/unsubscribe?token={}", self.base_url, unsubscribe_token);
+        branding: &EmailBranding,
+        locale: &str,
+    ) -> OutgoingEmail {
+        let preferences_url = self.preferences_url(unsubscribe_token);
 
         let products_html: String = products.iter().map(|(product, image_url)| {
             let product_url = format!("{}/?product={}", self.base_url, product.id);
@@ -281,19 +598,21 @@ impl ResendService {
                 String::from(r#"<div style="width:120px;height:120px;background:#E0E0E0;border-radius:8px"></div>"#)
             };
             format!(
-                r#"<a href="{}" style="display:inline-block;text-align:center;margin:8px;text-decoration:none;color:#18191B">
+                r#"<a href="{}" style="display:inline-block;text-align:center;margin:8px;text-decoration:none;color:{text}">
                     {}
                     <p style="font-size:10px;margin:8px 0 4px;font-family:'Courier New',monospace">{}</p>
-                    <p style="font-size:12px;color:#97BAD9;font-family:'Courier New',monospace">${:.2}</p>
+                    <p style="font-size:12px;color:{accent};font-family:'Courier New',monospace">${:.2}</p>
                 </a>"#,
-                product_url, image_html, product.name, product.price_cents as f64 / 100.0
+                product_url, image_html, product.name, product.price_cents as f64 / 100.0,
+                accent = branding.accent_color,
+                text = branding.text_color
             )
         }).collect();
 
         let subject = if products.len() == 1 {
-            format!("New Arrival: {} - Caterpillar Clay", products[0].0.name)
+            t_with(locale, "new-arrival-subject", &[("name", &products[0].0.name)])
         } else {
-            format!("{} New Arrivals - Caterpillar Clay", products.len())
+            t_with(locale, "new-arrivals-subject", &[("count", &products.len().to_string())])
         };
 
         let html = format!(
@@ -301,43 +620,56 @@ impl ResendService {
 <html>
 <head>
     <style>
-        body {{ font-family: 'Courier New', monospace; background: #F8F8F8; padding: 20px; margin: 0; }}
+        body {{ font-family: 'Courier New', monospace; background: {background}; padding: 20px; margin: 0; }}
         .container {{ max-width: 600px; margin: 0 auto; background: white; padding: 32px; border: 2px solid #E0E0E0; border-radius: 12px; text-align: center; }}
-        h1 {{ color: #97BAD9; font-size: 16px; margin-bottom: 24px; }}
+        h1 {{ color: {accent}; font-size: 16px; margin-bottom: 24px; }}
         .products {{ margin: 24px 0; }}
-        .btn {{ display: inline-block; background: #97BAD9; color: #18191B; padding: 14px 28px; text-decoration: none; font-size: 12px; border-radius: 8px; font-family: inherit; }}
+        .btn {{ display: inline-block; background: {accent}; color: {text}; padding: 14px 28px; text-decoration: none; font-size: 12px; border-radius: 8px; font-family: inherit; }}
         .footer {{ margin-top: 32px; padding-top: 20px; border-top: 1px solid #E0E0E0; font-size: 10px; color: #666; }}
-        .footer a {{ color: #97BAD9; }}
+        .footer a {{ color: {accent}; }}
     </style>
 </head>
 <body>
     <div class="container">
-        <h1>New Arrivals!</h1>
-        <p style="font-size:12px;color:#666;margin-bottom:24px">Check out our latest handmade pottery pieces</p>
+        {logo}
+        <h1>{}</h1>
+        <p style="font-size:12px;color:#666;margin-bottom:24px">{}</p>
         <div class="products">{}</div>
-        <a href="{}" class="btn">SHOP NOW</a>
+        <a href="{}" class="btn">{}</a>
         <div class="footer">
-            <p>Caterpillar Clay - Handmade Pottery</p>
-            <p><a href="{}">Unsubscribe</a></p>
+            {footer}
+            <p><a href="{}">{}</a></p>
         </div>
     </div>
 </body>
 </html>"#,
+            t(locale, "new-arrivals-title"),
+            t(locale, "new-arrivals-intro"),
             products_html,
             self.base_url,
-            unsubscribe_url
+            t(locale, "button-shop-now"),
+            preferences_url,
+            t(locale, "footer-preferences"),
+            accent = branding.accent_color,
+            text = branding.text_color,
+            background = branding.background_color,
+            logo = email_logo(branding),
+            footer = email_footer(branding, locale)
         );
 
-        self.send_email(to_email, &subject, &html).await
+        OutgoingEmail::new("newsletter_new_products", to_email, &subject, html)
+            .with_list_unsubscribe(self.unsubscribe_url(unsubscribe_token))
     }
 
-    async fn send_multi_product_restock_email(
+    fn multi_product_restock_email(
         &self,
         to_email: &str,
         unsubscribe_token: &str,
         products: &[(Product, Option<String>)],
-    ) -> AppResult<()> {
-        let unsubscribe_url = format!("{}/api/newsletter/unsubscribe?token={}", self.base_url, unsubscribe_token);
+        branding: &EmailBranding,
+        locale: &str,
+    ) -> OutgoingEmail {
+        let preferences_url = self.preferences_url(unsubscribe_token);
 
         let products_html: String = products.iter().map(|(product, image_url)| {
             let product_url = format!("{}/?product={}", self.base_url, product.id);
@@ -347,19 +679,21 @@ impl ResendService {
                 String::from(r#"<div style="width:120px;height:120px;background:#E0E0E0;border-radius:8px"></div>"#)
             };
             format!(
-                r#"<a href="{}" style="display:inline-block;text-align:center;margin:8px;text-decoration:none;color:#18191B">
+                r#"<a href="{}" style="display:inline-block;text-align:center;margin:8px;text-decoration:none;color:{text}">
                     {}
                     <p style="font-size:10px;margin:8px 0 4px;font-family:'Courier New',monospace">{}</p>
-                    <p style="font-size:12px;color:#97BAD9;font-family:'Courier New',monospace">${:.2}</p>
+                    <p style="font-size:12px;color:{accent};font-family:'Courier New',monospace">${:.2}</p>
                 </a>"#,
-                product_url, image_html, product.name, product.price_cents as f64 / 100.0
+                product_url, image_html, product.name, product.price_cents as f64 / 100.0,
+                accent = branding.accent_color,
+                text = branding.text_color
             )
         }).collect();
 
         let subject = if products.len() == 1 {
-            format!("Back in Stock: {} - Caterpillar Clay", products[0].0.name)
+            t_with(locale, "back-in-stock-subject", &[("name", &products[0].0.name)])
         } else {
-            format!("{} Items Back in Stock - Caterpillar Clay", products.len())
+            t_with(locale, "back-in-stock-many-subject", &[("count", &products.len().to_string())])
         };
 
         let html = format!(
@@ -367,34 +701,142 @@ impl ResendService {
 <html>
 <head>
     <style>
-        body {{ font-family: 'Courier New', monospace; background: #F8F8F8; padding: 20px; margin: 0; }}
+        body {{ font-family: 'Courier New', monospace; background: {background}; padding: 20px; margin: 0; }}
         .container {{ max-width: 600px; margin: 0 auto; background: white; padding: 32px; border: 2px solid #E0E0E0; border-radius: 12px; text-align: center; }}
         h1 {{ color: #22c55e; font-size: 16px; margin-bottom: 24px; }}
         .products {{ margin: 24px 0; }}
         .btn {{ display: inline-block; background: #22c55e; color: #fff; padding: 14px 28px; text-decoration: none; font-size: 12px; border-radius: 8px; font-family: inherit; }}
         .footer {{ margin-top: 32px; padding-top: 20px; border-top: 1px solid #E0E0E0; font-size: 10px; color: #666; }}
-        .footer a {{ color: #97BAD9; }}
+        .footer a {{ color: {accent}; }}
     </style>
 </head>
 <body>
     <div class="container">
-        <h1>Back in Stock!</h1>
-        <p style="font-size:12px;color:#666;margin-bottom:24px">Good news! These items are available again</p>
+        {logo}
+        <h1>{}</h1>
+        <p style="font-size:12px;color:#666;margin-bottom:24px">{}</p>
         <div class="products">{}</div>
-        <a href="{}" class="btn">SHOP NOW</a>
+        <a href="{}" class="btn">{}</a>
         <div class="footer">
-            <p>Caterpillar Clay - Handmade Pottery</p>
-            <p><a href="{}">Unsubscribe</a></p>
+            {footer}
+            <p><a href="{}">{}</a></p>
         </div>
     </div>
 </body>
 </html>"#,
+            t(locale, "back-in-stock-title"),
+            t(locale, "back-in-stock-many-intro"),
             products_html,
             self.base_url,
-            unsubscribe_url
+            t(locale, "button-shop-now"),
+            preferences_url,
+            t(locale, "footer-preferences"),
+            accent = branding.accent_color,
+            background = branding.background_color,
+            logo = email_logo(branding),
+            footer = email_footer(branding, locale)
         );
 
-        self.send_email(to_email, &subject, &html).await
+        OutgoingEmail::new("newsletter_restock", to_email, &subject, html)
+            .with_list_unsubscribe(self.unsubscribe_url(unsubscribe_token))
+    }
+
+    /// Email a lapsed customer the pieces added since their last order, with their
+    /// single-use discount code when one was generated
+    pub async fn send_win_back_email(
+        &self,
+        subscriber: &NewsletterSubscriber,
+        products: &[(Product, Option<String>)],
+        discount: Option<&DiscountCode>,
+    ) -> AppResult<()> {
+        let branding = self.branding().await;
+        let locale = subscriber.email_locale();
+        let preferences_url = self.preferences_url(&subscriber.unsubscribe_token);
+
+        let products_html: String = products.iter().map(|(product, image_url)| {
+            let product_url = format!("{}/?product={}", self.base_url, product.id);
+            let image_html = if let Some(img_url) = image_url {
+                format!(r#"<img src="{}" alt="{}" style="width:120px;height:120px;object-fit:cover;border-radius:8px;border:2px solid #E0E0E0">"#, img_url, product.name)
+            } else {
+                String::from(r#"<div style="width:120px;height:120px;background:#E0E0E0;border-radius:8px"></div>"#)
+            };
+            format!(
+                r#"<a href="{}" style="display:inline-block;text-align:center;margin:8px;text-decoration:none;color:{text}">
+                    {}
+                    <p style="font-size:10px;margin:8px 0 4px;font-family:'Courier New',monospace">{}</p>
+                    <p style="font-size:12px;color:{accent};font-family:'Courier New',monospace">${:.2}</p>
+                </a>"#,
+                product_url, image_html, product.name, product.price_cents as f64 / 100.0,
+                accent = branding.accent_color,
+                text = branding.text_color
+            )
+        }).collect();
+
+        let discount_html = match discount {
+            Some(code) => {
+                let percent = code.percent_off.unwrap_or(0).to_string();
+                let expires = code
+                    .expires_ts
+                    .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0))
+                    .map(|dt| t_with(locale, "win-back-discount-expires", &[("date", &dt.format("%Y-%m-%d").to_string())]))
+                    .unwrap_or_default();
+                format!(
+                    r#"<div class="discount"><p>{}</p><p style="font-size:10px;color:#666">{}</p></div>"#,
+                    t_with(locale, "win-back-discount", &[("code", &code.code), ("percent", &percent)]),
+                    expires
+                )
+            }
+            None => String::new(),
+        };
+
+        let html = format!(
+            r#"<!DOCTYPE html>
+<html>
+<head>
+    <style>
+        body {{ font-family: 'Courier New', monospace; background: {background}; padding: 20px; margin: 0; }}
+        .container {{ max-width: 600px; margin: 0 auto; background: white; padding: 32px; border: 2px solid #E0E0E0; border-radius: 12px; text-align: center; }}
+        h1 {{ color: {accent}; font-size: 16px; margin-bottom: 24px; }}
+        .products {{ margin: 24px 0; }}
+        .discount {{ margin: 24px 0; padding: 16px; border: 2px dashed {accent}; border-radius: 8px; font-size: 12px; color: {text}; }}
+        .btn {{ display: inline-block; background: {accent}; color: {text}; padding: 14px 28px; text-decoration: none; font-size: 12px; border-radius: 8px; font-family: inherit; }}
+        .footer {{ margin-top: 32px; padding-top: 20px; border-top: 1px solid #E0E0E0; font-size: 10px; color: #666; }}
+        .footer a {{ color: {accent}; }}
+    </style>
+</head>
+<body>
+    <div class="container">
+        {logo}
+        <h1>{}</h1>
+        <p style="font-size:12px;color:#666;margin-bottom:24px">{}</p>
+        <div class="products">{}</div>
+        {}
+        <a href="{}" class="btn">{}</a>
+        <div class="footer">
+            {footer}
+            <p><a href="{}">{}</a></p>
+        </div>
+    </div>
+</body>
+</html>"#,
+            t(locale, "win-back-title"),
+            t(locale, "win-back-intro"),
+            products_html,
+            discount_html,
+            self.base_url,
+            t(locale, "button-shop-now"),
+            preferences_url,
+            t(locale, "footer-preferences"),
+            accent = branding.accent_color,
+            text = branding.text_color,
+            background = branding.background_color,
+            logo = email_logo(&branding),
+            footer = email_footer(&branding, locale)
+        );
+
+        let email = OutgoingEmail::new("newsletter_win_back", &subscriber.email, &t(locale, "win-back-subject"), html)
+            .with_list_unsubscribe(self.unsubscribe_url(&subscriber.unsubscribe_token));
+        self.send_one(email).await
     }
 
     /// Send a one-time "back in stock" notification for product-specific signups
@@ -404,8 +846,9 @@ impl ResendService {
         to_email: &str,
         product: &Product,
         product_image_url: Option<&str>,
+        locale: &str,
     ) -> AppResult<()> {
-        self.send_product_restock_alert_with_styles(to_email, product, product_image_url, &[]).await
+        self.send_product_restock_alert_with_styles(to_email, product, product_image_url, &[], locale).await
     }
 
     /// Send a restock notification with specific styles that are back in stock
@@ -415,7 +858,9 @@ impl ResendService {
         product: &Product,
         product_image_url: Option<&str>,
         styles: &[String],
+        locale: &str,
     ) -> AppResult<()> {
+        let branding = self.branding().await;
         let product_url = format!("{}/?product={}", self.base_url, product.id);
 
         let image_html = if let Some(img_url) = product_image_url {
@@ -431,9 +876,10 @@ impl ResendService {
                 .join("");
             format!(
                 r#"<div style="background:#f0fdf4;border:2px solid #22c55e;border-radius:8px;padding:16px;margin:20px 0;text-align:left">
-                    <p style="font-size:11px;color:#166534;margin:0 0 8px;font-weight:bold">Available Styles:</p>
+                    <p style="font-size:11px;color:#166534;margin:0 0 8px;font-weight:bold">{}</p>
                     <ul style="margin:0;padding-left:20px;font-size:11px;color:#166534">{}</ul>
                 </div>"#,
+                t(locale, "restock-alert-styles"),
                 style_list
             )
         } else {
@@ -445,11 +891,11 @@ impl ResendService {
 <html>
 <head>
     <style>
-        body {{ font-family: 'Courier New', monospace; background: #F8F8F8; padding: 20px; margin: 0; }}
+        body {{ font-family: 'Courier New', monospace; background: {background}; padding: 20px; margin: 0; }}
         .container {{ max-width: 600px; margin: 0 auto; background: white; padding: 32px; border: 2px solid #E0E0E0; border-radius: 12px; text-align: center; }}
         h1 {{ color: #22c55e; font-size: 16px; margin-bottom: 24px; }}
-        h2 {{ color: #18191B; font-size: 14px; margin: 16px 0 8px; }}
-        .price {{ color: #97BAD9; font-size: 18px; margin-bottom: 16px; }}
+        h2 {{ color: {text}; font-size: 14px; margin: 16px 0 8px; }}
+        .price {{ color: {accent}; font-size: 18px; margin-bottom: 16px; }}
         .description {{ color: #666; font-size: 12px; line-height: 1.8; margin-bottom: 24px; }}
         .btn {{ display: inline-block; background: #22c55e; color: #fff; padding: 14px 28px; text-decoration: none; font-size: 12px; border-radius: 8px; font-family: inherit; }}
         .footer {{ margin-top: 32px; padding-top: 20px; border-top: 1px solid #E0E0E0; font-size: 10px; color: #666; }}
@@ -457,34 +903,339 @@ impl ResendService {
 </head>
 <body>
     <div class="container">
-        <h1>It's Back!</h1>
-        <p style="font-size:12px;color:#666;margin-bottom:24px">The item you wanted is back in stock</p>
+        {logo}
+        <h1>{}</h1>
+        <p style="font-size:12px;color:#666;margin-bottom:24px">{}</p>
         {}
         <h2>{}</h2>
         <p class="price">${:.2}</p>
         {}
-        <p class="description">Grab it before it's gone again!</p>
-        <a href="{}" class="btn">SHOP NOW</a>
+        <p class="description">{}</p>
+        <a href="{}" class="btn">{}</a>
         <div class="footer">
-            <p>Caterpillar Clay - Handmade Pottery</p>
-            <p style="color:#999;font-size:9px">You received this email because you signed up to be notified when this item was back in stock. This is a one-time notification.</p>
+            {footer}
+            <p style="color:#999;font-size:9px">{}</p>
         </div>
     </div>
 </body>
 </html>"#,
+            t(locale, "restock-alert-title"),
+            t(locale, "restock-alert-intro"),
             image_html,
             product.name,
             product.price_cents as f64 / 100.0,
             styles_html,
-            product_url
+            t(locale, "restock-alert-hurry"),
+            product_url,
+            t(locale, "button-shop-now"),
+            t(locale, "restock-alert-footer"),
+            accent = branding.accent_color,
+            text = branding.text_color,
+            background = branding.background_color,
+            logo = email_logo(&branding),
+            footer = email_footer(&branding, locale)
         );
 
         let subject = if styles.len() == 1 {
-            format!("It's Back! {} - {} is in stock - Caterpillar Clay", product.name, styles[0])
+            t_with(locale, "restock-alert-style-subject", &[("name", &product.name), ("style", &styles[0])])
         } else {
-            format!("It's Back! {} is in stock - Caterpillar Clay", product.name)
+            t_with(locale, "restock-alert-subject", &[("name", &product.name)])
+        };
+
+        self.send_email("restock_alert", to_email, &subject, &html).await
+    }
+
+    /// Send one proof of a product notification to `to_email`, as a subscriber in `locale`
+    /// would get it. One product uses the single-product template, several the combined one.
+    pub async fn send_test_product_notification(
+        &self,
+        to_email: &str,
+        notify_type: &str,
+        products: &[(Product, Option<String>)],
+        locale: &str,
+    ) -> AppResult<()> {
+        let branding = self.branding().await;
+        let email = match (notify_type, products) {
+            ("new", [(product, image_url)]) => {
+                self.new_product_email(to_email, TEST_TOKEN, product, image_url.as_deref(), &branding, locale)
+            }
+            ("restock", [(product, image_url)]) => {
+                self.back_in_stock_email(to_email, TEST_TOKEN, product, image_url.as_deref(), &branding, locale)
+            }
+            ("new", _) => self.multi_product_new_email(to_email, TEST_TOKEN, products, &branding, locale),
+            _ => self.multi_product_restock_email(to_email, TEST_TOKEN, products, &branding, locale),
         };
+        self.send_test(email).await
+    }
+
+    /// Send one proof of a campaign to `to_email`, without open or click tracking
+    pub async fn send_test_campaign(
+        &self,
+        to_email: &str,
+        subject: &str,
+        body_html: &str,
+        products: &[(Product, Option<String>)],
+        locale: &str,
+    ) -> AppResult<()> {
+        let branding = self.branding().await;
+        let html = self.render_campaign(body_html, products, TEST_TOKEN, &branding, locale);
+        self.send_test(OutgoingEmail::new("newsletter_campaign", to_email, subject, html)).await
+    }
 
-        self.send_email(to_email, &subject, &html).await
+    /// Proofs are logged as tests, flagged in the subject, and carry no unsubscribe headers
+    async fn send_test(&self, mut email: OutgoingEmail) -> AppResult<()> {
+        email.email_type = "newsletter_test".to_string();
+        email.subject = format!("[TEST] {}", email.subject);
+        email.list_unsubscribe = None;
+        self.send_one(email).await
     }
+
+    /// Full HTML of a custom campaign: the composed body, then the featured products
+    pub fn render_campaign(
+        &self,
+        body_html: &str,
+        products: &[(Product, Option<String>)],
+        unsubscribe_token: &str,
+        branding: &EmailBranding,
+        locale: &str,
+    ) -> String {
+        let preferences_url = self.preferences_url(unsubscribe_token);
+
+        let products_html: String = products.iter().map(|(product, image_url)| {
+            let product_url = format!("{}/?product={}", self.base_url, product.id);
+            let image_html = if let Some(img_url) = image_url {
+                format!(r#"<img src="{}" alt="{}" style="width:120px;height:120px;object-fit:cover;border-radius:8px;border:2px solid #E0E0E0">"#, img_url, product.name)
+            } else {
+                String::from(r#"<div style="width:120px;height:120px;background:#E0E0E0;border-radius:8px"></div>"#)
+            };
+            format!(
+                r#"<a href="{}" style="display:inline-block;text-align:center;margin:8px;text-decoration:none;color:{text}">
+                    {}
+                    <p style="font-size:10px;margin:8px 0 4px;font-family:'Courier New',monospace">{}</p>
+                    <p style="font-size:12px;color:{accent};font-family:'Courier New',monospace">${:.2}</p>
+                </a>"#,
+                product_url, image_html, product.name, product.price_cents as f64 / 100.0,
+                accent = branding.accent_color,
+                text = branding.text_color
+            )
+        }).collect();
+
+        let products_section = if products.is_empty() {
+            String::new()
+        } else {
+            format!(r#"<div class="products">{}</div>"#, products_html)
+        };
+
+        format!(
+            r#"<!DOCTYPE html>
+<html>
+<head>
+    <style>
+        body {{ font-family: 'Courier New', monospace; background: {background}; padding: 20px; margin: 0; }}
+        .container {{ max-width: 600px; margin: 0 auto; background: white; padding: 32px; border: 2px solid #E0E0E0; border-radius: 12px; }}
+        .content {{ color: {text}; font-size: 14px; line-height: 1.8; }}
+        .content h1, .content h2, .content h3 {{ color: {accent}; }}
+        .content a {{ color: {accent}; }}
+        .products {{ margin: 24px 0; text-align: center; }}
+        .btn {{ display: inline-block; background: {accent}; color: {text}; padding: 14px 28px; text-decoration: none; font-size: 12px; border-radius: 8px; font-family: inherit; }}
+        .footer {{ margin-top: 32px; padding-top: 20px; border-top: 1px solid #E0E0E0; font-size: 10px; color: #666; text-align: center; }}
+        .footer a {{ color: {accent}; }}
+    </style>
+</head>
+<body>
+    <div class="container">
+        {logo}
+        <div class="content">{}</div>
+        {}
+        <div style="text-align:center"><a href="{}" class="btn">{}</a></div>
+        <div class="footer">
+            {footer}
+            <p><a href="{}">{}</a></p>
+        </div>
+    </div>
+</body>
+</html>"#,
+            body_html,
+            products_section,
+            self.base_url,
+            t(locale, "button-shop-now"),
+            preferences_url,
+            t(locale, "footer-preferences"),
+            accent = branding.accent_color,
+            text = branding.text_color,
+            background = branding.background_color,
+            logo = email_logo(branding),
+            footer = email_footer(branding, locale)
+        )
+    }
+
+    /// Send a campaign to each subscriber opted into its category, with its links
+    /// routed through the click tracker (`links` maps each href to its tracked link
+    /// ID) and an open pixel
+    pub async fn send_batch_campaign(
+        &self,
+        campaign: &NewsletterCampaign,
+        subscribers: &[NewsletterSubscriber],
+        body_html: &str,
+        products: &[(Product, Option<String>)],
+        links: &HashMap<String, String>,
+    ) -> AppResult<usize> {
+        let suppressed = self.suppressed_emails().await?;
+        let branding = self.branding().await;
+        let emails = subscribers
+            .iter()
+            .filter(|s| s.wants(&campaign.category) && !suppressed.contains(&s.email.to_lowercase()))
+            .map(|subscriber| {
+                let html = self.render_campaign(body_html, products, &subscriber.unsubscribe_token, &branding, subscriber.email_locale());
+                let html = self.add_tracking(&html, &campaign.id, &subscriber.id, links);
+                OutgoingEmail::new("newsletter_campaign", &subscriber.email, &campaign.subject, html)
+                    .with_list_unsubscribe(self.unsubscribe_url(&subscriber.unsubscribe_token))
+            })
+            .collect();
+
+        Ok(self.send_bulk(emails).await)
+    }
+
+    fn add_tracking(
+        &self,
+        html: &str,
+        campaign_id: &str,
+        subscriber_id: &str,
+        links: &HashMap<String, String>,
+    ) -> String {
+        let mut html = html.to_string();
+        for (href, link_id) in links {
+            let tracked_url = format!(
+                "{}/api/newsletter/track/click/{}?s={}",
+                self.base_url, link_id, subscriber_id
+            );
+            html = html.replace(&format!("href=\"{}\"", href), &format!("href=\"{}\"", tracked_url));
+        }
+
+        let pixel = format!(
+            r#"<img src="{}/api/newsletter/track/open/{}?s={}" width="1" height="1" alt="" style="display:block;border:0">"#,
+            self.base_url, campaign_id, subscriber_id
+        );
+        html.replacen("</body>", &format!("{}\n</body>", pixel), 1)
+    }
+}
+
+/// Links in a rendered campaign worth tracking: web links, except the shop's own
+/// newsletter links (unsubscribe and the like)
+pub fn trackable_links(html: &str) -> Vec<String> {
+    let mut links = Vec::new();
+    let mut rest = html;
+
+    while let Some(start) = rest.find("href=\"") {
+        rest = &rest[start + 6..];
+        let end = match rest.find('"') {
+            Some(end) => end,
+            None => break,
+        };
+        let href = &rest[..end];
+        rest = &rest[end..];
+
+        let is_web = href.starts_with("http://") || href.starts_with("https://");
+        if is_web && !href.contains("/api/newsletter/") && !links.iter().any(|l| l == href) {
+            links.push(href.to_string());
+        }
+    }
+
+    links
+}
+
+/// Render the small subset of Markdown campaigns are written in: `#` headings,
+/// `-`/`*` lists, paragraphs, `**bold**`, `*italic*` and `[links](url)`.
+/// Everything else is shown as text.
+pub fn markdown_to_html(markdown: &str) -> String {
+    let mut html = String::new();
+    let mut paragraph: Vec<String> = Vec::new();
+    let mut list: Vec<String> = Vec::new();
+
+    fn flush(html: &mut String, paragraph: &mut Vec<String>, list: &mut Vec<String>) {
+        if !paragraph.is_empty() {
+            html.push_str(&format!("<p>{}</p>\n", paragraph.join("<br>")));
+            paragraph.clear();
+        }
+        if !list.is_empty() {
+            let items: String = list.iter().map(|item| format!("<li>{}</li>", item)).collect();
+            html.push_str(&format!("<ul>{}</ul>\n", items));
+            list.clear();
+        }
+    }
+
+    for line in markdown.lines().map(str::trim_end) {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() {
+            flush(&mut html, &mut paragraph, &mut list);
+            continue;
+        }
+
+        let heading_level = trimmed.chars().take_while(|c| *c == '#').count();
+        if (1..=3).contains(&heading_level) && trimmed[heading_level..].starts_with(' ') {
+            flush(&mut html, &mut paragraph, &mut list);
+            html.push_str(&format!(
+                "<h{level}>{}</h{level}>\n",
+                markdown_inline(trimmed[heading_level..].trim()),
+                level = heading_level
+            ));
+            continue;
+        }
+
+        if let Some(item) = trimmed.strip_prefix("- ").or_else(|| trimmed.strip_prefix("* ")) {
+            if !paragraph.is_empty() {
+                flush(&mut html, &mut paragraph, &mut Vec::new());
+            }
+            list.push(markdown_inline(item.trim()));
+            continue;
+        }
+
+        if !list.is_empty() {
+            flush(&mut html, &mut Vec::new(), &mut list);
+        }
+        paragraph.push(markdown_inline(trimmed));
+    }
+    flush(&mut html, &mut paragraph, &mut list);
+
+    html
+}
+
+/// Inline Markdown for one line of text, HTML-escaped
+fn markdown_inline(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("**") {
+            if let Some(end) = after.find("**") {
+                out.push_str(&format!("<strong>{}</strong>", markdown_inline(&after[..end])));
+                rest = &after[end + 2..];
+                continue;
+            }
+        } else if let Some(after) = rest.strip_prefix('*') {
+            if let Some(end) = after.find('*').filter(|end| *end > 0) {
+                out.push_str(&format!("<em>{}</em>", markdown_inline(&after[..end])));
+                rest = &after[end + 1..];
+                continue;
+            }
+        } else if let Some(after) = rest.strip_prefix('[') {
+            if let Some(close) = after.find("](") {
+                if let Some(url_end) = after[close + 2..].find(')') {
+                    let label = &after[..close];
+                    let url = &after[close + 2..close + 2 + url_end];
+                    if url.starts_with("http://") || url.starts_with("https://") || url.starts_with("mailto:") {
+                        out.push_str(&format!(r#"<a href="{}">{}</a>"#, escape_html(url), markdown_inline(label)));
+                        rest = &after[close + 3 + url_end..];
+                        continue;
+                    }
+                }
+            }
+        }
+
+        let c = rest.chars().next().unwrap();
+        out.push_str(&escape_html(&c.to_string()));
+        rest = &rest[c.len_utf8()..];
+    }
+
+    out
 }
@@ -1,12 +1,28 @@
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
 
 use crate::error::{AppError, AppResult};
+use crate::models::RateFilter;
+
+/// How long a fetched tracking history is reused before asking Shippo again
+const TRACKING_CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// How long quoted rates are reused for delivery estimates (not for checkout or labels)
+const RATE_CACHE_TTL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Cached values keyed by lookup, each with the time it was fetched
+type Cache<T> = Arc<RwLock<HashMap<String, (Instant, T)>>>;
 
 #[derive(Clone)]
 pub struct ShippoService {
     client: Client,
     api_key: String,
+    tracking_cache: Cache<ShippoTracking>,
+    rate_cache: Cache<Vec<ShippoRate>>,
 }
 
 #[derive(Debug, Serialize)]
@@ -15,7 +31,7 @@ pub struct RegisterTrackingRequest {
     pub tracking_number: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ShippoTracking {
     pub tracking_number: String,
     pub carrier: String,
@@ -24,7 +40,7 @@ pub struct ShippoTracking {
     pub eta: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TrackingStatus {
     pub status: String,
     pub status_details: Option<String>,
@@ -32,7 +48,7 @@ pub struct TrackingStatus {
     pub location: Option<TrackingLocation>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TrackingEvent {
     pub status: String,
     pub status_details: Option<String>,
@@ -40,7 +56,7 @@ pub struct TrackingEvent {
     pub location: Option<TrackingLocation>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TrackingLocation {
     pub city: Option<String>,
     pub state: Option<String>,
@@ -68,7 +84,7 @@ impl ShippoWebhookEvent {
 
 // ============ SHIPPING RATES TYPES ============
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ShippoAddress {
     pub name: String,
     pub street1: String,
@@ -115,6 +131,11 @@ pub struct ShippoRate {
     pub currency: String,
     pub estimated_days: Option<i32>,
     pub duration_terms: Option<String>,
+    /// Shipment the rate was quoted for
+    #[serde(default)]
+    pub shipment: Option<String>,
+    #[serde(default)]
+    pub carrier_account: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -123,6 +144,67 @@ pub struct ShippoServiceLevel {
     pub token: String,
 }
 
+/// A created shipment, read back to re-rate it with insurance
+#[derive(Debug, Deserialize)]
+struct ShippoShipmentDetail {
+    address_from: ShippoObjectRef,
+    address_to: ShippoObjectRef,
+    parcels: Vec<ShippoObjectRef>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ShippoObjectRef {
+    object_id: String,
+}
+
+/// Same shipment as an existing one (by object IDs), with extras added
+#[derive(Debug, Serialize)]
+struct CreateShipmentWithExtrasRequest {
+    address_from: String,
+    address_to: String,
+    parcels: Vec<String>,
+    extra: ShipmentExtra,
+    #[serde(rename = "async")]
+    async_mode: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct ShipmentExtra {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    insurance: Option<ShipmentInsurance>,
+    /// STANDARD or ADULT
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signature_confirmation: Option<String>,
+}
+
+/// Signature options the shop offers on labels
+pub const SIGNATURE_TYPES: &[&str] = &["standard", "adult"];
+
+/// Label formats Shippo can generate: letter-size PDF, or 4x6 PDF, PNG and ZPL
+/// for thermal printers
+pub const LABEL_FILE_TYPES: &[&str] = &["PDF", "PDF_4x6", "PNG", "ZPLII"];
+
+/// Shipment extras bought with a label
+#[derive(Debug, Clone, Default)]
+pub struct LabelExtras {
+    pub insurance_amount_cents: Option<i64>,
+    /// One of SIGNATURE_TYPES
+    pub signature_confirmation: Option<String>,
+}
+
+impl LabelExtras {
+    fn is_empty(&self) -> bool {
+        self.insurance_amount_cents.map(|c| c <= 0).unwrap_or(true) && self.signature_confirmation.is_none()
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ShipmentInsurance {
+    amount: String,
+    currency: String,
+    content: String,
+}
+
 // ============ TRANSACTION/LABEL TYPES ============
 
 #[derive(Debug, Serialize)]
@@ -139,11 +221,48 @@ pub struct ShippoTransaction {
     pub status: String,
     pub tracking_number: Option<String>,
     pub label_url: Option<String>,
-    pub tracking_url_provider: Option<String>,
     pub rate: Option<String>,
     pub messages: Option<Vec<ShippoMessage>>,
 }
 
+#[derive(Debug, Serialize)]
+struct CreateRefundRequest {
+    transaction: String,
+    #[serde(rename = "async")]
+    async_mode: bool,
+}
+
+/// Refund request for an unused label; Shippo approves it with the carrier later
+#[derive(Debug, Deserialize)]
+pub struct ShippoRefund {
+    pub object_id: String,
+    /// QUEUED, PENDING, SUCCESS or ERROR
+    pub status: String,
+}
+
+// ============ MANIFEST TYPES ============
+
+#[derive(Debug, Serialize)]
+struct CreateManifestRequest {
+    carrier_account: String,
+    shipment_date: String,
+    address_from: ShippoAddress,
+    transactions: Vec<String>,
+    #[serde(rename = "async")]
+    async_mode: bool,
+}
+
+/// A carrier scan form covering several labels
+#[derive(Debug, Deserialize)]
+pub struct ShippoManifest {
+    pub object_id: String,
+    /// QUEUED, SUCCESS or ERROR
+    pub status: String,
+    /// Scan form PDFs to print and hand to the carrier
+    #[serde(default)]
+    pub documents: Vec<String>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ShippoMessage {
     pub source: Option<String>,
@@ -151,13 +270,11 @@ pub struct ShippoMessage {
     pub text: Option<String>,
 }
 
+/// Transaction named by a webhook. Only its ID is used; the transaction itself is
+/// fetched from Shippo, since webhook payloads aren't signed.
 #[derive(Debug, Deserialize)]
 pub struct ShippoTransactionWebhookData {
     pub object_id: String,
-    pub status: String,
-    pub tracking_number: Option<String>,
-    pub label_url: Option<String>,
-    pub rate: Option<String>,
 }
 
 impl ShippoService {
@@ -165,6 +282,8 @@ impl ShippoService {
         Self {
             client: Client::new(),
             api_key: api_key.to_string(),
+            tracking_cache: Arc::new(RwLock::new(HashMap::new())),
+            rate_cache: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
@@ -237,6 +356,43 @@ impl ShippoService {
             .map_err(|e| AppError::ExternalService(format!("Failed to parse Shippo response: {}", e)))
     }
 
+    /// Get tracking status, reusing a recent response from the in-memory cache
+    pub async fn get_tracking_cached(
+        &self,
+        carrier: &str,
+        tracking_number: &str,
+    ) -> AppResult<ShippoTracking> {
+        let key = format!("{}/{}", carrier, tracking_number);
+
+        {
+            let cache = self.tracking_cache.read().await;
+            if let Some((fetched_at, tracking)) = cache.get(&key) {
+                if fetched_at.elapsed() < TRACKING_CACHE_TTL {
+                    return Ok(tracking.clone());
+                }
+            }
+        }
+
+        let tracking = self.get_tracking(carrier, tracking_number).await?;
+
+        let mut cache = self.tracking_cache.write().await;
+        cache.retain(|_, (fetched_at, _)| fetched_at.elapsed() < TRACKING_CACHE_TTL);
+        cache.insert(key, (Instant::now(), tracking.clone()));
+
+        Ok(tracking)
+    }
+
+    /// Drop a cached tracking response (e.g. when a webhook reports fresh data)
+    pub async fn invalidate_tracking(&self, carrier: &str, tracking_number: &str) {
+        let key = format!("{}/{}", carrier, tracking_number);
+        self.tracking_cache.write().await.remove(&key);
+    }
+
+    /// Convert a rate provider name ("USPS", "DHL Express") to a Shippo carrier token
+    pub fn carrier_token(provider: &str) -> String {
+        provider.trim().to_lowercase().replace(' ', "_")
+    }
+
     /// Map Shippo tracking status to order status
     pub fn map_status_to_order_status(shippo_status: &str) -> &'static str {
         match shippo_status.to_uppercase().as_str() {
@@ -248,12 +404,13 @@ impl ShippoService {
         }
     }
 
-    /// Get shipping rates for a shipment
+    /// Get shipping rates for a shipment, leaving out carriers and service levels the filter hides
     pub async fn get_rates(
         &self,
         from_address: ShippoAddress,
         to_address: ShippoAddress,
         parcels: Vec<ShippoParcel>,
+        filter: &RateFilter,
     ) -> AppResult<Vec<ShippoRate>> {
         let request = CreateShipmentRequest {
             address_from: from_address,
@@ -285,11 +442,12 @@ impl ShippoService {
             .await
             .map_err(|e| AppError::ExternalService(format!("Failed to parse Shippo response: {}", e)))?;
 
-        // Filter for USD rates and sort by price
+        // Filter for USD rates the shop offers and sort by price
         let mut rates: Vec<ShippoRate> = shipment
             .rates
             .into_iter()
             .filter(|r| r.currency == "USD")
+            .filter(|r| filter.allows(&Self::carrier_token(&r.provider), &r.servicelevel.token.to_lowercase()))
             .collect();
 
         rates.sort_by(|a, b| {
@@ -301,14 +459,168 @@ impl ShippoService {
         Ok(rates)
     }
 
-    /// Purchase a shipping label using a rate object_id
-    pub async fn purchase_label(&self, rate_id: &str) -> AppResult<ShippoTransaction> {
-        let request = CreateTransactionRequest {
-            rate: rate_id.to_string(),
-            label_file_type: "PDF".to_string(),
+    /// Get shipping rates, reusing ones quoted for the same `cache_key` recently.
+    /// Only for estimates: cached rate IDs may have expired and can't be purchased.
+    pub async fn get_rates_cached(
+        &self,
+        cache_key: &str,
+        from_address: ShippoAddress,
+        to_address: ShippoAddress,
+        parcels: Vec<ShippoParcel>,
+        filter: &RateFilter,
+    ) -> AppResult<Vec<ShippoRate>> {
+        {
+            let cache = self.rate_cache.read().await;
+            if let Some((fetched_at, rates)) = cache.get(cache_key) {
+                if fetched_at.elapsed() < RATE_CACHE_TTL {
+                    return Ok(rates.clone());
+                }
+            }
+        }
+
+        let rates = self.get_rates(from_address, to_address, parcels, filter).await?;
+
+        let mut cache = self.rate_cache.write().await;
+        cache.retain(|_, (fetched_at, _)| fetched_at.elapsed() < RATE_CACHE_TTL);
+        cache.insert(cache_key.to_string(), (Instant::now(), rates.clone()));
+
+        Ok(rates)
+    }
+
+    /// Fetch a single rate by its object_id
+    pub async fn get_rate(&self, rate_id: &str) -> AppResult<ShippoRate> {
+        let response = self
+            .client
+            .get(format!("https://api.goshippo.com/rates/{}", rate_id))
+            .header("Authorization", format!("ShippoToken {}", self.api_key))
+            .send()
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Shippo API error: {}", e)))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(AppError::ExternalService(format!(
+                "Shippo API error {}: {}",
+                status, body
+            )));
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Failed to parse Shippo response: {}", e)))
+    }
+
+    /// Insurance and signatures are set on the shipment, not the rate, so re-rate the
+    /// rate's shipment with the extras added and return the matching carrier/service rate
+    async fn rate_with_extras(&self, rate_id: &str, extras: &LabelExtras) -> AppResult<ShippoRate> {
+        let rate = self.get_rate(rate_id).await?;
+        let shipment_id = rate
+            .shipment
+            .as_deref()
+            .ok_or_else(|| AppError::ExternalService("Rate has no shipment".to_string()))?;
+
+        let response = self
+            .client
+            .get(format!("https://api.goshippo.com/shipments/{}", shipment_id))
+            .header("Authorization", format!("ShippoToken {}", self.api_key))
+            .send()
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Shippo API error: {}", e)))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(AppError::ExternalService(format!(
+                "Shippo API error {}: {}",
+                status, body
+            )));
+        }
+
+        let shipment: ShippoShipmentDetail = response
+            .json()
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Failed to parse Shippo response: {}", e)))?;
+
+        let insurance = extras
+            .insurance_amount_cents
+            .filter(|cents| *cents > 0)
+            .map(|cents| ShipmentInsurance {
+                amount: format!("{}.{:02}", cents / 100, cents % 100),
+                currency: "USD".to_string(),
+                content: "Handmade pottery".to_string(),
+            });
+
+        let request = CreateShipmentWithExtrasRequest {
+            address_from: shipment.address_from.object_id,
+            address_to: shipment.address_to.object_id,
+            parcels: shipment.parcels.into_iter().map(|p| p.object_id).collect(),
+            extra: ShipmentExtra {
+                insurance,
+                signature_confirmation: extras.signature_confirmation.as_ref().map(|s| s.to_uppercase()),
+            },
             async_mode: false,
         };
 
+        let response = self
+            .client
+            .post("https://api.goshippo.com/shipments/")
+            .header("Authorization", format!("ShippoToken {}", self.api_key))
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Shippo API error: {}", e)))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(AppError::ExternalService(format!(
+                "Shippo API error {}: {}",
+                status, body
+            )));
+        }
+
+        let rerated: ShippoShipment = response
+            .json()
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Failed to parse Shippo response: {}", e)))?;
+
+        rerated
+            .rates
+            .into_iter()
+            .find(|r| r.carrier_account == rate.carrier_account && r.servicelevel.token == rate.servicelevel.token)
+            .ok_or_else(|| {
+                AppError::ExternalService(format!(
+                    "{} {} doesn't offer the requested insurance or signature for this shipment",
+                    rate.provider, rate.servicelevel.name
+                ))
+            })
+    }
+
+    /// Purchase a shipping label using a rate object_id, with optional insurance
+    /// and delivery signature, in one of LABEL_FILE_TYPES. With `async_mode` Shippo
+    /// returns a queued transaction right away and reports the label through a
+    /// transaction webhook.
+    pub async fn purchase_label(
+        &self,
+        rate_id: &str,
+        extras: &LabelExtras,
+        label_file_type: &str,
+        async_mode: bool,
+    ) -> AppResult<ShippoTransaction> {
+        let rate_id = if extras.is_empty() {
+            rate_id.to_string()
+        } else {
+            self.rate_with_extras(rate_id, extras).await?.object_id
+        };
+
+        let request = CreateTransactionRequest {
+            rate: rate_id,
+            label_file_type: label_file_type.to_string(),
+            async_mode,
+        };
+
         let response = self
             .client
             .post("https://api.goshippo.com/transactions/")
@@ -345,4 +657,113 @@ impl ShippoService {
 
         Ok(transaction)
     }
+
+    /// Fetch a label purchase (transaction) by its object_id
+    pub async fn get_transaction(&self, transaction_id: &str) -> AppResult<ShippoTransaction> {
+        let response = self
+            .client
+            .get(format!("https://api.goshippo.com/transactions/{}", transaction_id))
+            .header("Authorization", format!("ShippoToken {}", self.api_key))
+            .send()
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Shippo API error: {}", e)))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(AppError::ExternalService(format!(
+                "Shippo API error {}: {}",
+                status, body
+            )));
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Failed to parse Shippo response: {}", e)))
+    }
+
+    /// Ask Shippo to refund (void) an unused label
+    pub async fn refund_label(&self, transaction_id: &str) -> AppResult<ShippoRefund> {
+        let request = CreateRefundRequest {
+            transaction: transaction_id.to_string(),
+            async_mode: false,
+        };
+
+        let response = self
+            .client
+            .post("https://api.goshippo.com/refunds/")
+            .header("Authorization", format!("ShippoToken {}", self.api_key))
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Shippo API error: {}", e)))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(AppError::ExternalService(format!(
+                "Shippo API error {}: {}",
+                status, body
+            )));
+        }
+
+        let refund: ShippoRefund = response
+            .json()
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Failed to parse Shippo response: {}", e)))?;
+
+        if refund.status == "ERROR" {
+            return Err(AppError::ExternalService("Shippo rejected the label refund".to_string()));
+        }
+
+        Ok(refund)
+    }
+
+    /// Create a manifest (scan form) so the carrier accepts every listed label
+    /// with a single scan. `shipment_date` is an ISO 8601 datetime.
+    pub async fn create_manifest(
+        &self,
+        carrier_account: &str,
+        shipment_date: &str,
+        address_from: ShippoAddress,
+        transaction_ids: Vec<String>,
+    ) -> AppResult<ShippoManifest> {
+        let request = CreateManifestRequest {
+            carrier_account: carrier_account.to_string(),
+            shipment_date: shipment_date.to_string(),
+            address_from,
+            transactions: transaction_ids,
+            async_mode: false,
+        };
+
+        let response = self
+            .client
+            .post("https://api.goshippo.com/manifests/")
+            .header("Authorization", format!("ShippoToken {}", self.api_key))
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Shippo API error: {}", e)))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(AppError::ExternalService(format!(
+                "Shippo API error {}: {}",
+                status, body
+            )));
+        }
+
+        let manifest: ShippoManifest = response
+            .json()
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Failed to parse Shippo response: {}", e)))?;
+
+        if manifest.status == "ERROR" {
+            return Err(AppError::ExternalService("Shippo could not create the manifest".to_string()));
+        }
+
+        Ok(manifest)
+    }
 }
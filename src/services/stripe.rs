@@ -1,33 +1,171 @@
 use stripe::{
-    CheckoutSession, CheckoutSessionMode, Client, CreateCheckoutSession,
-    CreateCheckoutSessionLineItems, CreateCheckoutSessionLineItemsPriceData,
-    CreateCheckoutSessionLineItemsPriceDataProductData, CreateCheckoutSessionShippingAddressCollection,
-    CreateCheckoutSessionShippingAddressCollectionAllowedCountries, CreatePrice,
-    CreateProduct, CreateRefund, Currency, IdOrCreate, Price, Product as StripeProduct,
-    Refund, UpdatePrice, UpdateProduct,
+    Account, AccountId, AccountLink, AccountLinkType, AccountType, CancelPaymentIntent, CapturePaymentIntent, CheckoutSession, CheckoutSessionId,
+    CheckoutSessionMode, Client, Coupon, CouponDuration, CreateCheckoutSession,
+    CreateCheckoutSessionDiscounts, CreateCheckoutSessionLineItems,
+    CreateCheckoutSessionLineItemsPriceData, CreateCheckoutSessionLineItemsPriceDataProductData,
+    CreateCheckoutSessionPaymentIntentData, CreateCheckoutSessionPaymentIntentDataCaptureMethod,
+    CreateCheckoutSessionPaymentIntentDataSetupFutureUsage,
+    CreateCheckoutSessionPaymentIntentDataTransferData, CreateCheckoutSessionPaymentMethodTypes,
+    CreateCheckoutSessionShippingAddressCollection,
+    CreateCheckoutSessionShippingAddressCollectionAllowedCountries,
+    CreateCheckoutSessionSubscriptionData, CreateAccount,
+    CreateAccountCapabilities, CreateAccountCapabilitiesCardPayments,
+    CreateAccountCapabilitiesTransfers, CreateAccountLink, CreateCoupon, CreateCustomer,
+    CreatePaymentIntent, CreatePaymentIntentAutomaticPaymentMethods,
+    CreatePaymentIntentTransferData, CreatePaymentLink,
+    CreatePaymentLinkLineItems, CreatePaymentLinkPaymentIntentData, CreatePaymentLinkRestrictions,
+    CreatePaymentLinkRestrictionsCompletedSessions, CreatePrice, CreatePriceProductData, CreatePriceRecurring,
+    CreatePriceRecurringInterval,
+    CreateProduct, CreatePromotionCode, CreatePromotionCodeRestrictions, CreateRefund, Currency,
+    Customer, CustomerId, IdOrCreate, PaymentIntent, ListCoupons, ListProducts, ListPromotionCodes,
+    PaymentIntentCancellationReason, PaymentIntentCaptureMethod, PaymentIntentSetupFutureUsage,
+    PaymentLink, PaymentLinkId, Price, PromotionCode, Product as StripeProduct, Refund,
+    Subscription, SubscriptionId, UpdatePaymentLink, UpdatePrice, UpdateProduct, UpdateSubscription,
 };
 use hmac::{Hmac, Mac};
 use sha2::Sha256;
 
 use crate::error::{AppError, AppResult};
 
+/// Metadata key linking a Stripe product back to our product ID
+const LOCAL_PRODUCT_ID_KEY: &str = "product_id";
+
+fn local_product_metadata(local_product_id: &str) -> std::collections::HashMap<String, String> {
+    let mut metadata = std::collections::HashMap::new();
+    metadata.insert(LOCAL_PRODUCT_ID_KEY.to_string(), local_product_id.to_string());
+    metadata
+}
+
+/// Stripe's limits for a buy-now-pay-later method on USD payments
+struct BnplRule {
+    method: &'static str,
+    label: &'static str,
+    min_cents: i64,
+    max_cents: i64,
+    countries: &'static [&'static str],
+}
+
+const BNPL_RULES: &[BnplRule] = &[
+    BnplRule { method: "klarna", label: "Klarna", min_cents: 100, max_cents: 1_000_000, countries: &["US"] },
+    BnplRule { method: "afterpay_clearpay", label: "Afterpay", min_cents: 100, max_cents: 400_000, countries: &["US"] },
+];
+
 #[derive(Clone)]
 pub struct StripeService {
     client: Client,
     webhook_secret: String,
+    payment_method_types: Vec<String>,
+    bnpl_methods: Vec<String>,
+    manual_capture: bool,
 }
 
 impl StripeService {
-    pub fn new(secret_key: &str, webhook_secret: &str) -> Self {
+    pub fn new(
+        secret_key: &str,
+        webhook_secret: &str,
+        payment_method_types: Vec<String>,
+        bnpl_methods: Vec<String>,
+        manual_capture: bool,
+    ) -> Self {
         Self {
             client: Client::new(secret_key),
             webhook_secret: webhook_secret.to_string(),
+            payment_method_types,
+            bnpl_methods,
+            manual_capture,
         }
     }
 
+    /// Whether payments are only authorized at checkout and captured when the label is bought
+    pub fn manual_capture(&self) -> bool {
+        self.manual_capture
+    }
+
+    /// Configured payment method types plus any buy-now-pay-later methods this order
+    /// qualifies for, in the form Checkout understands; unknown names are skipped
+    fn checkout_payment_method_types(
+        &self,
+        bnpl_methods: &[String],
+    ) -> Option<Vec<CreateCheckoutSessionPaymentMethodTypes>> {
+        let mut names = self.payment_method_types.clone();
+        if !bnpl_methods.is_empty() {
+            // Listing methods explicitly drops the dashboard defaults, so keep cards
+            if names.is_empty() {
+                names.push("card".to_string());
+            }
+            for method in bnpl_methods {
+                if !names.contains(method) {
+                    names.push(method.clone());
+                }
+            }
+        }
+
+        if names.is_empty() {
+            return None;
+        }
+
+        let types: Vec<CreateCheckoutSessionPaymentMethodTypes> = names
+            .iter()
+            .filter_map(|t| match serde_json::from_value(serde_json::Value::String(t.clone())) {
+                Ok(parsed) => Some(parsed),
+                Err(_) => {
+                    tracing::warn!("Ignoring unknown Stripe payment method type: {}", t);
+                    None
+                }
+            })
+            .collect();
+
+        if types.is_empty() { None } else { Some(types) }
+    }
+
+    /// Why an order can't be paid with a buy-now-pay-later method, if it can't
+    pub fn check_bnpl_eligibility(
+        &self,
+        method: &str,
+        amount_cents: i64,
+        country: Option<&str>,
+    ) -> Result<(), String> {
+        if !self.bnpl_methods.iter().any(|m| m == method) {
+            return Err(format!("Payment method {} is not available", method));
+        }
+
+        let rule = match BNPL_RULES.iter().find(|r| r.method == method) {
+            Some(rule) => rule,
+            None => return Err(format!("Payment method {} is not supported", method)),
+        };
+
+        if amount_cents < rule.min_cents || amount_cents > rule.max_cents {
+            return Err(format!(
+                "{} is available for orders between ${:.2} and ${:.2}",
+                rule.label,
+                rule.min_cents as f64 / 100.0,
+                rule.max_cents as f64 / 100.0
+            ));
+        }
+
+        match country {
+            Some(c) if rule.countries.iter().any(|allowed| allowed.eq_ignore_ascii_case(c)) => Ok(()),
+            _ => Err(format!(
+                "{} is only available for shipping to {}",
+                rule.label,
+                rule.countries.join(", ")
+            )),
+        }
+    }
+
+    /// Enabled buy-now-pay-later methods this order qualifies for
+    pub fn eligible_bnpl_methods(&self, amount_cents: i64, country: Option<&str>) -> Vec<String> {
+        self.bnpl_methods
+            .iter()
+            .filter(|m| self.check_bnpl_eligibility(m, amount_cents, country).is_ok())
+            .cloned()
+            .collect()
+    }
+
     /// Create a product in Stripe, returns (product_id, price_id)
     pub async fn create_product(
         &self,
+        local_product_id: &str,
         name: &str,
         description: Option<&str>,
         price_cents: i64,
@@ -41,6 +179,7 @@ impl StripeService {
         if !images.is_empty() {
             create_product.images = Some(images);
         }
+        create_product.metadata = Some(local_product_metadata(local_product_id));
 
         let product = StripeProduct::create(&self.client, create_product)
             .await
@@ -88,6 +227,80 @@ impl StripeService {
         Ok(())
     }
 
+    /// Push name, description, images and the local product link in a single update
+    pub async fn reconcile_product(
+        &self,
+        product_id: &str,
+        local_product_id: &str,
+        name: &str,
+        description: Option<&str>,
+        images: Vec<String>,
+    ) -> AppResult<()> {
+        let product_id: stripe::ProductId = product_id.parse().map_err(|_| {
+            AppError::ExternalService("Invalid Stripe product ID".to_string())
+        })?;
+
+        let update = UpdateProduct {
+            name: Some(name),
+            description: Some(description.unwrap_or_default().to_string()),
+            images: Some(images),
+            active: Some(true),
+            metadata: Some(local_product_metadata(local_product_id)),
+            ..Default::default()
+        };
+
+        StripeProduct::update(&self.client, &product_id, update)
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Stripe product update error: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Fetch a price's (unit_amount, active)
+    pub async fn get_price(&self, price_id: &str) -> AppResult<(Option<i64>, bool)> {
+        let price_id: stripe::PriceId = price_id.parse().map_err(|_| {
+            AppError::ExternalService("Invalid Stripe price ID".to_string())
+        })?;
+
+        let price = Price::retrieve(&self.client, &price_id, &[])
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Stripe price retrieve error: {}", e)))?;
+
+        Ok((price.unit_amount, price.active.unwrap_or(false)))
+    }
+
+    /// All active Stripe products as (stripe_product_id, local product_id from metadata)
+    pub async fn list_active_products(&self) -> AppResult<Vec<(String, Option<String>)>> {
+        let mut products = Vec::new();
+        let mut starting_after: Option<stripe::ProductId> = None;
+
+        loop {
+            let mut params = ListProducts::new();
+            params.active = Some(true);
+            params.limit = Some(100);
+            params.starting_after = starting_after.clone();
+
+            let page = StripeProduct::list(&self.client, &params)
+                .await
+                .map_err(|e| AppError::ExternalService(format!("Stripe product list error: {}", e)))?;
+
+            starting_after = page.data.last().map(|p| p.id.clone());
+            for product in &page.data {
+                let local_id = product
+                    .metadata
+                    .as_ref()
+                    .and_then(|m| m.get(LOCAL_PRODUCT_ID_KEY).cloned());
+                products.push((product.id.to_string(), local_id));
+            }
+
+            if !page.has_more || starting_after.is_none() {
+                break;
+            }
+        }
+
+        Ok(products)
+    }
+
     /// Update product price (creates new price, archives old one)
     pub async fn update_price(
         &self,
@@ -113,8 +326,10 @@ impl StripeService {
             tracing::info!("Archiving old Stripe price: {}", old_id);
             match old_id.parse::<stripe::PriceId>() {
                 Ok(old_price_id_parsed) => {
-                    let mut update = UpdatePrice::default();
-                    update.active = Some(false);
+                    let update = UpdatePrice {
+                        active: Some(false),
+                        ..Default::default()
+                    };
                     match Price::update(&self.client, &old_price_id_parsed, update).await {
                         Ok(_) => tracing::info!("Successfully archived old price {}", old_id),
                         Err(e) => tracing::error!("Failed to archive old price {}: {}", old_id, e),
@@ -133,8 +348,10 @@ impl StripeService {
             AppError::ExternalService("Invalid Stripe product ID".to_string())
         })?;
 
-        let mut update = UpdateProduct::default();
-        update.active = Some(false);
+        let update = UpdateProduct {
+            active: Some(false),
+            ..Default::default()
+        };
 
         StripeProduct::update(&self.client, &product_id, update)
             .await
@@ -144,31 +361,37 @@ impl StripeService {
     }
 
     /// Create a checkout session for an order
-    pub async fn create_checkout_session(
-        &self,
-        items: Vec<CheckoutItem>,
-        success_url: &str,
-        cancel_url: &str,
-        customer_email: Option<&str>,
-        order_id: &str,
-    ) -> AppResult<CheckoutSessionResult> {
+    pub async fn create_checkout_session(&self, session: CheckoutSessionParams<'_>) -> AppResult<CheckoutSessionResult> {
+        let CheckoutSessionParams {
+            items,
+            success_url,
+            cancel_url,
+            customer_email,
+            customer_id,
+            order_id,
+            coupon_id,
+            bnpl_methods,
+            destination,
+        } = session;
+
         let line_items: Vec<CreateCheckoutSessionLineItems> = items
             .into_iter()
             .map(|item| {
-                let mut line_item = CreateCheckoutSessionLineItems::default();
-                line_item.price_data = Some(CreateCheckoutSessionLineItemsPriceData {
-                    currency: Currency::USD,
-                    product_data: Some(CreateCheckoutSessionLineItemsPriceDataProductData {
-                        name: item.name,
-                        description: item.description,
-                        images: item.images,
+                CreateCheckoutSessionLineItems {
+                    price_data: Some(CreateCheckoutSessionLineItemsPriceData {
+                        currency: Currency::USD,
+                        product_data: Some(CreateCheckoutSessionLineItemsPriceDataProductData {
+                            name: item.name,
+                            description: item.description,
+                            images: item.images,
+                            ..Default::default()
+                        }),
+                        unit_amount: Some(item.price_cents),
                         ..Default::default()
                     }),
-                    unit_amount: Some(item.price_cents),
+                    quantity: Some(item.quantity as u64),
                     ..Default::default()
-                });
-                line_item.quantity = Some(item.quantity as u64);
-                line_item
+                }
             })
             .collect();
 
@@ -177,11 +400,51 @@ impl StripeService {
         params.mode = Some(CheckoutSessionMode::Payment);
         params.success_url = Some(success_url);
         params.cancel_url = Some(cancel_url);
+        params.payment_method_types = self.checkout_payment_method_types(bnpl_methods);
+
+        let mut payment_intent_data = CreateCheckoutSessionPaymentIntentData::default();
 
-        if let Some(email) = customer_email {
+        // A known customer gets their saved cards offered, and new cards are saved for next time
+        if let Some(customer) = customer_id.and_then(|id| id.parse::<CustomerId>().ok()) {
+            params.customer = Some(customer);
+            payment_intent_data.setup_future_usage =
+                Some(CreateCheckoutSessionPaymentIntentDataSetupFutureUsage::OnSession);
+        } else if let Some(email) = customer_email {
             params.customer_email = Some(email);
         }
 
+        // Authorize only; the charge is captured when the shipping label is bought
+        if self.manual_capture {
+            payment_intent_data.capture_method = Some(CreateCheckoutSessionPaymentIntentDataCaptureMethod::Manual);
+        }
+
+        // Marketplace orders are paid out to the artist, minus the shop's fee
+        if let Some(destination) = destination {
+            payment_intent_data.application_fee_amount = Some(destination.application_fee_cents);
+            payment_intent_data.transfer_data = Some(CreateCheckoutSessionPaymentIntentDataTransferData {
+                amount: None,
+                destination: destination.account_id.clone(),
+            });
+        }
+
+        if payment_intent_data.setup_future_usage.is_some()
+            || payment_intent_data.capture_method.is_some()
+            || payment_intent_data.transfer_data.is_some()
+        {
+            params.payment_intent_data = Some(payment_intent_data);
+        }
+
+        // Stripe rejects a session with both a discount and the promotion code field,
+        // so customers can only enter a Stripe promotion code when no local code was applied
+        if let Some(coupon) = coupon_id {
+            params.discounts = Some(vec![CreateCheckoutSessionDiscounts {
+                coupon: Some(coupon.to_string()),
+                promotion_code: None,
+            }]);
+        } else {
+            params.allow_promotion_codes = Some(true);
+        }
+
         // Add shipping address collection for physical goods
         params.shipping_address_collection = Some(CreateCheckoutSessionShippingAddressCollection {
             allowed_countries: vec![
@@ -200,12 +463,417 @@ impl StripeService {
 
         Ok(CheckoutSessionResult {
             id: session.id.to_string(),
+            expires_at: session.expires_at,
             url: session.url.ok_or_else(|| {
                 AppError::ExternalService("No checkout URL returned".to_string())
             })?,
         })
     }
 
+    /// Create a recurring price for a subscription product, returns the price ID
+    pub async fn create_recurring_price(
+        &self,
+        product_id: &str,
+        amount_cents: i64,
+        interval: &str,
+    ) -> AppResult<String> {
+        let product_id: stripe::ProductId = product_id.parse().map_err(|_| {
+            AppError::ExternalService("Invalid Stripe product ID".to_string())
+        })?;
+
+        let interval = match interval {
+            "year" => CreatePriceRecurringInterval::Year,
+            _ => CreatePriceRecurringInterval::Month,
+        };
+
+        let mut create_price = CreatePrice::new(Currency::USD);
+        create_price.product = Some(IdOrCreate::Id(&product_id));
+        create_price.unit_amount = Some(amount_cents);
+        create_price.recurring = Some(CreatePriceRecurring {
+            interval,
+            ..Default::default()
+        });
+
+        let price = Price::create(&self.client, create_price)
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Stripe price creation error: {}", e)))?;
+
+        Ok(price.id.to_string())
+    }
+
+    /// Create a Checkout session that starts a subscription at a recurring price.
+    /// `subscription_id` is our local subscription, stored in the session and subscription metadata.
+    pub async fn create_subscription_checkout(
+        &self,
+        price_id: &str,
+        success_url: &str,
+        cancel_url: &str,
+        customer_email: Option<&str>,
+        customer_id: Option<&str>,
+        subscription_id: &str,
+    ) -> AppResult<CheckoutSessionResult> {
+        let mut metadata = std::collections::HashMap::new();
+        metadata.insert("subscription_id".to_string(), subscription_id.to_string());
+
+        let mut params = CreateCheckoutSession::new();
+        params.line_items = Some(vec![CreateCheckoutSessionLineItems {
+            price: Some(price_id.to_string()),
+            quantity: Some(1),
+            ..Default::default()
+        }]);
+        params.mode = Some(CheckoutSessionMode::Subscription);
+        params.success_url = Some(success_url);
+        params.cancel_url = Some(cancel_url);
+        if let Some(customer) = customer_id.and_then(|id| id.parse::<CustomerId>().ok()) {
+            params.customer = Some(customer);
+        } else if let Some(email) = customer_email {
+            params.customer_email = Some(email);
+        }
+        params.subscription_data = Some(CreateCheckoutSessionSubscriptionData {
+            metadata: Some(metadata.clone()),
+            ..Default::default()
+        });
+        params.metadata = Some(metadata);
+
+        let session = CheckoutSession::create(&self.client, params)
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Stripe checkout error: {}", e)))?;
+
+        Ok(CheckoutSessionResult {
+            id: session.id.to_string(),
+            expires_at: session.expires_at,
+            url: session.url.ok_or_else(|| {
+                AppError::ExternalService("No checkout URL returned".to_string())
+            })?,
+        })
+    }
+
+    /// Stop a subscription from renewing; the current period still ships
+    pub async fn cancel_subscription_at_period_end(&self, subscription_id: &str) -> AppResult<()> {
+        let subscription_id: SubscriptionId = subscription_id.parse().map_err(|_| {
+            AppError::ExternalService("Invalid Stripe subscription ID".to_string())
+        })?;
+
+        let mut update = UpdateSubscription::new();
+        update.cancel_at_period_end = Some(true);
+
+        Subscription::update(&self.client, &subscription_id, update)
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Stripe subscription update error: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Create a single-use Payment Link charging an order's total
+    pub async fn create_payment_link(
+        &self,
+        order_id: &str,
+        description: &str,
+        amount_cents: i64,
+    ) -> AppResult<PaymentLinkResult> {
+        // Payment Links only take saved prices, so create a one-off price for the order total
+        let mut create_price = CreatePrice::new(Currency::USD);
+        create_price.unit_amount = Some(amount_cents);
+        create_price.product_data = Some(CreatePriceProductData {
+            name: description.to_string(),
+            ..Default::default()
+        });
+
+        let price = Price::create(&self.client, create_price)
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Stripe price creation error: {}", e)))?;
+
+        let mut metadata = std::collections::HashMap::new();
+        metadata.insert("order_id".to_string(), order_id.to_string());
+
+        let mut params = CreatePaymentLink::new(vec![CreatePaymentLinkLineItems {
+            price: price.id.to_string(),
+            quantity: 1,
+            ..Default::default()
+        }]);
+        // Sessions created from the link inherit this, so checkout.session.completed finds the order
+        params.metadata = Some(metadata.clone());
+        params.payment_intent_data = Some(CreatePaymentLinkPaymentIntentData {
+            metadata: Some(metadata),
+            ..Default::default()
+        });
+        params.restrictions = Some(CreatePaymentLinkRestrictions {
+            completed_sessions: CreatePaymentLinkRestrictionsCompletedSessions { limit: 1 },
+        });
+
+        let link = PaymentLink::create(&self.client, params)
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Stripe payment link error: {}", e)))?;
+
+        Ok(PaymentLinkResult {
+            id: link.id.to_string(),
+            url: link.url,
+        })
+    }
+
+    /// Deactivate a Payment Link so it can no longer be paid
+    pub async fn deactivate_payment_link(&self, link_id: &str) -> AppResult<()> {
+        let link_id: PaymentLinkId = link_id.parse().map_err(|_| {
+            AppError::ExternalService("Invalid Stripe payment link ID".to_string())
+        })?;
+
+        let mut update = UpdatePaymentLink::new();
+        update.active = Some(false);
+
+        PaymentLink::update(&self.client, &link_id, update)
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Stripe payment link update error: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Create a PaymentIntent for the embedded Payment Element flow
+    pub async fn create_payment_intent(
+        &self,
+        amount_cents: i64,
+        customer_email: Option<&str>,
+        customer_id: Option<&str>,
+        order_id: &str,
+        destination: Option<&DestinationCharge>,
+    ) -> AppResult<PaymentIntentResult> {
+        let mut params = CreatePaymentIntent::new(amount_cents, Currency::USD);
+        if self.payment_method_types.is_empty() {
+            params.automatic_payment_methods = Some(CreatePaymentIntentAutomaticPaymentMethods {
+                allow_redirects: None,
+                enabled: true,
+            });
+        } else {
+            params.payment_method_types = Some(self.payment_method_types.clone());
+        }
+        params.receipt_email = customer_email;
+        if let Some(customer) = customer_id.and_then(|id| id.parse::<CustomerId>().ok()) {
+            params.customer = Some(customer);
+            params.setup_future_usage = Some(PaymentIntentSetupFutureUsage::OnSession);
+        }
+        if self.manual_capture {
+            params.capture_method = Some(PaymentIntentCaptureMethod::Manual);
+        }
+        if let Some(destination) = destination {
+            params.application_fee_amount = Some(destination.application_fee_cents);
+            params.transfer_data = Some(CreatePaymentIntentTransferData {
+                amount: None,
+                destination: destination.account_id.clone(),
+            });
+        }
+
+        // Store order ID in metadata
+        let mut metadata = std::collections::HashMap::new();
+        metadata.insert("order_id".to_string(), order_id.to_string());
+        params.metadata = Some(metadata);
+
+        let payment_intent = PaymentIntent::create(&self.client, params)
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Stripe payment intent error: {}", e)))?;
+
+        Ok(PaymentIntentResult {
+            id: payment_intent.id.to_string(),
+            client_secret: payment_intent.client_secret.ok_or_else(|| {
+                AppError::ExternalService("No client secret returned".to_string())
+            })?,
+        })
+    }
+
+    /// Create a Stripe Customer for a local user, returns the customer ID
+    pub async fn create_customer(&self, email: &str, name: Option<&str>, user_id: &str) -> AppResult<String> {
+        let mut params = CreateCustomer::new();
+        params.email = Some(email);
+        params.name = name;
+
+        let mut metadata = std::collections::HashMap::new();
+        metadata.insert("user_id".to_string(), user_id.to_string());
+        params.metadata = Some(metadata);
+
+        let customer = Customer::create(&self.client, params)
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Stripe customer error: {}", e)))?;
+
+        Ok(customer.id.to_string())
+    }
+
+    /// Create a single-use coupon for a fixed discount, returns the coupon ID
+    pub async fn create_discount_coupon(&self, name: &str, amount_off_cents: i64) -> AppResult<String> {
+        let mut params = CreateCoupon::new();
+        params.name = Some(name);
+        params.amount_off = Some(amount_off_cents);
+        params.currency = Some(Currency::USD);
+        params.duration = Some(CouponDuration::Once);
+        params.max_redemptions = Some(1);
+
+        let coupon = Coupon::create(&self.client, params)
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Stripe coupon creation error: {}", e)))?;
+
+        Ok(coupon.id.to_string())
+    }
+
+    /// Create a reusable coupon that promotion codes can point at
+    pub async fn create_coupon(
+        &self,
+        name: &str,
+        percent_off: Option<f64>,
+        amount_off_cents: Option<i64>,
+        max_redemptions: Option<i64>,
+        redeem_by: Option<i64>,
+    ) -> AppResult<StripeCouponSummary> {
+        let mut params = CreateCoupon::new();
+        params.name = Some(name);
+        params.percent_off = percent_off;
+        if amount_off_cents.is_some() {
+            params.amount_off = amount_off_cents;
+            params.currency = Some(Currency::USD);
+        }
+        params.duration = Some(CouponDuration::Once);
+        params.max_redemptions = max_redemptions;
+        params.redeem_by = redeem_by;
+
+        let coupon = Coupon::create(&self.client, params)
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Stripe coupon error: {}", e)))?;
+
+        Ok(StripeCouponSummary::from(coupon))
+    }
+
+    pub async fn list_coupons(&self) -> AppResult<Vec<StripeCouponSummary>> {
+        let mut params = ListCoupons::new();
+        params.limit = Some(100);
+
+        let coupons = Coupon::list(&self.client, &params)
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Stripe coupon list error: {}", e)))?;
+
+        Ok(coupons.data.into_iter().map(StripeCouponSummary::from).collect())
+    }
+
+    /// Create a customer-facing promotion code for an existing coupon
+    pub async fn create_promotion_code(
+        &self,
+        coupon_id: &str,
+        code: &str,
+        max_redemptions: Option<i64>,
+        expires_at: Option<i64>,
+        minimum_amount_cents: Option<i64>,
+    ) -> AppResult<StripePromotionCodeSummary> {
+        let mut params = CreatePromotionCode::new(coupon_id);
+        params.code = Some(code);
+        params.max_redemptions = max_redemptions;
+        params.expires_at = expires_at;
+        if let Some(minimum) = minimum_amount_cents {
+            let mut restrictions = CreatePromotionCodeRestrictions::new();
+            restrictions.minimum_amount = Some(minimum);
+            restrictions.minimum_amount_currency = Some(Currency::USD);
+            params.restrictions = Some(restrictions);
+        }
+
+        let promotion_code = params
+            .send(&self.client)
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Stripe promotion code error: {}", e)))?;
+
+        Ok(StripePromotionCodeSummary::from(promotion_code))
+    }
+
+    pub async fn list_promotion_codes(&self) -> AppResult<Vec<StripePromotionCodeSummary>> {
+        let mut params = ListPromotionCodes::new();
+        params.limit = Some(100);
+
+        let codes = PromotionCode::list(&self.client, &params)
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Stripe promotion code list error: {}", e)))?;
+
+        Ok(codes.data.into_iter().map(StripePromotionCodeSummary::from).collect())
+    }
+
+    /// The promotion code a customer entered on a Checkout session, with the amount it took off
+    pub async fn get_session_promotion_code(&self, session_id: &str) -> AppResult<Option<(String, i64)>> {
+        let id: CheckoutSessionId = session_id.parse().map_err(|_| {
+            AppError::ExternalService("Invalid checkout session ID".to_string())
+        })?;
+
+        let session = CheckoutSession::retrieve(
+            &self.client,
+            &id,
+            &["total_details.breakdown.discounts.discount.promotion_code"],
+        )
+        .await
+        .map_err(|e| AppError::ExternalService(format!("Stripe checkout session error: {}", e)))?;
+
+        let applied = session
+            .total_details
+            .and_then(|totals| totals.breakdown)
+            .into_iter()
+            .flat_map(|breakdown| breakdown.discounts)
+            .find_map(|discount| {
+                let code = discount.discount.promotion_code.as_ref()?.as_object()?.code.clone();
+                Some((code, discount.amount))
+            });
+
+        Ok(applied)
+    }
+
+    /// Whether a PaymentIntent is holding an authorization that still needs capturing
+    pub async fn payment_intent_requires_capture(&self, payment_intent_id: &str) -> AppResult<bool> {
+        let pi_id: stripe::PaymentIntentId = payment_intent_id.parse().map_err(|_| {
+            AppError::ExternalService("Invalid payment intent ID".to_string())
+        })?;
+
+        let payment_intent = PaymentIntent::retrieve(&self.client, &pi_id, &[])
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Stripe payment intent error: {}", e)))?;
+
+        Ok(payment_intent.status == stripe::PaymentIntentStatus::RequiresCapture)
+    }
+
+    /// Capture an authorized PaymentIntent in full
+    pub async fn capture_payment_intent(&self, payment_intent_id: &str) -> AppResult<()> {
+        PaymentIntent::capture(&self.client, payment_intent_id, CapturePaymentIntent::default())
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Stripe capture error: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Release an uncaptured authorization
+    pub async fn cancel_payment_intent(&self, payment_intent_id: &str) -> AppResult<()> {
+        let params = CancelPaymentIntent {
+            cancellation_reason: Some(PaymentIntentCancellationReason::Abandoned),
+        };
+
+        PaymentIntent::cancel(&self.client, payment_intent_id, params)
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Stripe cancel error: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Cancel a PaymentIntent that was never paid, so it can't be paid later.
+    /// Returns false if the customer has already paid (or is paying) and the
+    /// intent was left alone.
+    pub async fn cancel_unpaid_payment_intent(&self, payment_intent_id: &str) -> AppResult<bool> {
+        let pi_id: stripe::PaymentIntentId = payment_intent_id.parse().map_err(|_| {
+            AppError::ExternalService("Invalid payment intent ID".to_string())
+        })?;
+
+        let payment_intent = PaymentIntent::retrieve(&self.client, &pi_id, &[])
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Stripe payment intent error: {}", e)))?;
+
+        match payment_intent.status {
+            stripe::PaymentIntentStatus::Canceled => Ok(true),
+            stripe::PaymentIntentStatus::Succeeded
+            | stripe::PaymentIntentStatus::Processing
+            | stripe::PaymentIntentStatus::RequiresCapture => Ok(false),
+            _ => {
+                self.cancel_payment_intent(payment_intent_id).await?;
+                Ok(true)
+            }
+        }
+    }
+
     /// Create a refund for a payment intent
     /// Returns the refund ID if successful
     pub async fn create_refund(
@@ -213,19 +881,28 @@ impl StripeService {
         payment_intent_id: &str,
         amount_cents: Option<i64>,
         reason: Option<&str>,
+        reverse_transfer: bool,
     ) -> AppResult<RefundResult> {
         let pi_id: stripe::PaymentIntentId = payment_intent_id.parse().map_err(|_| {
             AppError::ExternalService("Invalid payment intent ID".to_string())
         })?;
 
-        let mut params = CreateRefund::default();
-        params.payment_intent = Some(pi_id);
+        let mut params = CreateRefund {
+            payment_intent: Some(pi_id),
+            ..Default::default()
+        };
 
         // If amount is specified, do partial refund; otherwise full refund
         if let Some(amount) = amount_cents {
             params.amount = Some(amount);
         }
 
+        // Pull the money back from the artist's account and return the shop's fee with it
+        if reverse_transfer {
+            params.reverse_transfer = Some(true);
+            params.refund_application_fee = Some(true);
+        }
+
         // Map reason string to Stripe RefundReasonFilter enum
         if let Some(r) = reason {
             params.reason = match r {
@@ -246,6 +923,160 @@ impl StripeService {
         })
     }
 
+    /// Fetch the Radar risk assessment and payment method for a payment intent's latest charge
+    pub async fn get_payment_details(&self, payment_intent_id: &str) -> AppResult<Option<PaymentDetails>> {
+        let pi_id: stripe::PaymentIntentId = payment_intent_id.parse().map_err(|_| {
+            AppError::ExternalService("Invalid payment intent ID".to_string())
+        })?;
+
+        let payment_intent = PaymentIntent::retrieve(&self.client, &pi_id, &["latest_charge"])
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Stripe payment intent error: {}", e)))?;
+
+        let charge = match payment_intent.latest_charge.and_then(|charge| charge.into_object()) {
+            Some(charge) => charge,
+            None => return Ok(None),
+        };
+
+        let (risk_level, risk_score) = match charge.outcome {
+            Some(o) => (o.risk_level, o.risk_score),
+            None => (None, None),
+        };
+
+        let method = charge.payment_method_details;
+        let card = method.as_ref().and_then(|m| m.card.as_ref());
+
+        Ok(Some(PaymentDetails {
+            risk_level,
+            risk_score,
+            payment_method_type: method.as_ref().map(|m| m.type_.clone()),
+            card_brand: card.and_then(|c| c.brand.clone()),
+            wallet: card
+                .and_then(|c| c.wallet.as_ref())
+                .map(|w| w.type_.as_str().to_string()),
+        }))
+    }
+
+    /// Register a domain for wallet payments (Apple Pay, Google Pay, Link)
+    pub async fn register_payment_method_domain(&self, domain: &str) -> AppResult<PaymentMethodDomain> {
+        self.client
+            .post_form("/payment_method_domains", serde_json::json!({"domain_name": domain}))
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Stripe domain registration error: {}", e)))
+    }
+
+    /// List the domains registered for wallet payments
+    pub async fn list_payment_method_domains(&self) -> AppResult<Vec<PaymentMethodDomain>> {
+        let list: PaymentMethodDomainList = self
+            .client
+            .get_query("/payment_method_domains", serde_json::json!({"limit": 100}))
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Stripe domain list error: {}", e)))?;
+        Ok(list.data)
+    }
+
+    /// Create an Express connected account for an artist, returns the account ID
+    pub async fn create_connected_account(&self, artist_id: &str, email: Option<&str>) -> AppResult<String> {
+        let mut params = CreateAccount::new();
+        params.type_ = Some(AccountType::Express);
+        params.email = email;
+        params.capabilities = Some(CreateAccountCapabilities {
+            card_payments: Some(CreateAccountCapabilitiesCardPayments { requested: Some(true) }),
+            transfers: Some(CreateAccountCapabilitiesTransfers { requested: Some(true) }),
+            ..Default::default()
+        });
+
+        let mut metadata = std::collections::HashMap::new();
+        metadata.insert("artist_id".to_string(), artist_id.to_string());
+        params.metadata = Some(metadata);
+
+        let account = Account::create(&self.client, params)
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Stripe account creation error: {}", e)))?;
+
+        Ok(account.id.to_string())
+    }
+
+    /// One-time link to Stripe's hosted onboarding for a connected account
+    pub async fn create_onboarding_link(
+        &self,
+        account_id: &str,
+        refresh_url: &str,
+        return_url: &str,
+    ) -> AppResult<String> {
+        let account_id: AccountId = account_id.parse().map_err(|_| {
+            AppError::ExternalService("Invalid Stripe account ID".to_string())
+        })?;
+
+        let mut params = CreateAccountLink::new(account_id, AccountLinkType::AccountOnboarding);
+        params.refresh_url = Some(refresh_url);
+        params.return_url = Some(return_url);
+
+        let link = AccountLink::create(&self.client, params)
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Stripe account link error: {}", e)))?;
+
+        Ok(link.url)
+    }
+
+    /// Whether a connected account can take payments and receive payouts yet
+    pub async fn get_account_status(&self, account_id: &str) -> AppResult<(bool, bool)> {
+        let account_id: AccountId = account_id.parse().map_err(|_| {
+            AppError::ExternalService("Invalid Stripe account ID".to_string())
+        })?;
+
+        let account = Account::retrieve(&self.client, &account_id, &[])
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Stripe account error: {}", e)))?;
+
+        Ok((
+            account.charges_enabled.unwrap_or(false),
+            account.payouts_enabled.unwrap_or(false),
+        ))
+    }
+
+    /// Most recent payouts to the bank account, newest first
+    pub async fn list_payouts(&self, limit: i64) -> AppResult<Vec<StripePayout>> {
+        let list: StripeList<StripePayout> = self
+            .client
+            .get_query("/payouts", serde_json::json!({"limit": limit}))
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Stripe payout list error: {}", e)))?;
+        Ok(list.data)
+    }
+
+    /// Every balance transaction settled in a payout, with the payment intent it came from
+    pub async fn list_payout_transactions(&self, payout_id: &str) -> AppResult<Vec<PayoutTransaction>> {
+        let mut transactions = Vec::new();
+        let mut starting_after: Option<String> = None;
+
+        loop {
+            let mut params = serde_json::json!({
+                "payout": payout_id,
+                "limit": 100,
+                "expand": ["data.source"],
+            });
+            if let Some(ref last) = starting_after {
+                params["starting_after"] = serde_json::Value::String(last.clone());
+            }
+
+            let page: StripeList<RawBalanceTransaction> = self
+                .client
+                .get_query("/balance_transactions", params)
+                .await
+                .map_err(|e| AppError::ExternalService(format!("Stripe balance transaction error: {}", e)))?;
+
+            starting_after = page.data.last().map(|t| t.id.clone());
+            transactions.extend(page.data.into_iter().map(PayoutTransaction::from));
+
+            if !page.has_more || starting_after.is_none() {
+                break;
+            }
+        }
+
+        Ok(transactions)
+    }
+
     /// Verify webhook signature and parse event
     pub fn verify_webhook(&self, payload: &str, signature: &str) -> AppResult<StripeWebhookEvent> {
         // Parse the Stripe-Signature header
@@ -305,6 +1136,7 @@ impl StripeService {
 /// Stripe webhook event structure
 #[derive(Debug, serde::Deserialize)]
 pub struct StripeWebhookEvent {
+    pub id: String,
     #[serde(rename = "type")]
     pub event_type: String,
     pub data: StripeWebhookData,
@@ -315,6 +1147,12 @@ pub struct StripeWebhookData {
     pub object: serde_json::Value,
 }
 
+/// Where a marketplace payment goes: the artist's connected account, minus the shop's fee
+pub struct DestinationCharge {
+    pub account_id: String,
+    pub application_fee_cents: i64,
+}
+
 pub struct CheckoutItem {
     pub name: String,
     pub description: Option<String>,
@@ -323,9 +1161,173 @@ pub struct CheckoutItem {
     pub quantity: i32,
 }
 
+/// What a Checkout session is opened for
+pub struct CheckoutSessionParams<'a> {
+    pub items: Vec<CheckoutItem>,
+    pub success_url: &'a str,
+    pub cancel_url: &'a str,
+    pub customer_email: Option<&'a str>,
+    /// Saved Stripe customer; takes precedence over `customer_email`
+    pub customer_id: Option<&'a str>,
+    pub order_id: &'a str,
+    pub coupon_id: Option<&'a str>,
+    pub bnpl_methods: &'a [String],
+    pub destination: Option<&'a DestinationCharge>,
+}
+
 pub struct CheckoutSessionResult {
     pub id: String,
     pub url: String,
+    pub expires_at: i64,
+}
+
+pub struct PaymentIntentResult {
+    pub id: String,
+    pub client_secret: String,
+}
+
+pub struct PaymentLinkResult {
+    pub id: String,
+    pub url: String,
+}
+
+/// A domain registered with Stripe, with per-wallet verification status
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct PaymentMethodDomain {
+    pub id: String,
+    pub domain_name: String,
+    pub enabled: bool,
+    pub apple_pay: Option<PaymentMethodDomainStatus>,
+    pub google_pay: Option<PaymentMethodDomainStatus>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct PaymentMethodDomainStatus {
+    /// "active" or "inactive"
+    pub status: String,
+}
+
+#[derive(serde::Deserialize)]
+struct PaymentMethodDomainList {
+    data: Vec<PaymentMethodDomain>,
+}
+
+#[derive(serde::Deserialize)]
+struct StripeList<T> {
+    data: Vec<T>,
+    #[serde(default)]
+    has_more: bool,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct StripePayout {
+    pub id: String,
+    pub amount: i64,
+    pub currency: String,
+    /// paid, pending, in_transit, canceled, or failed
+    pub status: String,
+    pub arrival_date: i64,
+    pub created: i64,
+}
+
+#[derive(serde::Deserialize)]
+struct RawBalanceTransaction {
+    id: String,
+    #[serde(rename = "type")]
+    kind: String,
+    amount: i64,
+    fee: i64,
+    net: i64,
+    /// Expanded charge or refund
+    source: Option<serde_json::Value>,
+}
+
+/// A balance transaction within a payout
+pub struct PayoutTransaction {
+    pub id: String,
+    /// charge, refund, payout, stripe_fee, adjustment, ...
+    pub kind: String,
+    pub amount: i64,
+    pub fee: i64,
+    pub net: i64,
+    pub payment_intent_id: Option<String>,
+}
+
+impl From<RawBalanceTransaction> for PayoutTransaction {
+    fn from(raw: RawBalanceTransaction) -> Self {
+        // Charges and refunds both point back at their payment intent
+        let payment_intent_id = raw
+            .source
+            .as_ref()
+            .and_then(|s| s.get("payment_intent"))
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string());
+
+        Self {
+            id: raw.id,
+            kind: raw.kind,
+            amount: raw.amount,
+            fee: raw.fee,
+            net: raw.net,
+            payment_intent_id,
+        }
+    }
+}
+
+/// Admin-facing view of a Stripe coupon
+#[derive(Debug, serde::Serialize)]
+pub struct StripeCouponSummary {
+    pub id: String,
+    pub name: Option<String>,
+    pub percent_off: Option<f64>,
+    pub amount_off_cents: Option<i64>,
+    pub max_redemptions: Option<i64>,
+    pub times_redeemed: i64,
+    pub redeem_by: Option<i64>,
+    pub valid: bool,
+}
+
+impl From<Coupon> for StripeCouponSummary {
+    fn from(coupon: Coupon) -> Self {
+        Self {
+            id: coupon.id.to_string(),
+            name: coupon.name,
+            percent_off: coupon.percent_off,
+            amount_off_cents: coupon.amount_off,
+            max_redemptions: coupon.max_redemptions,
+            times_redeemed: coupon.times_redeemed.unwrap_or(0),
+            redeem_by: coupon.redeem_by,
+            valid: coupon.valid.unwrap_or(false),
+        }
+    }
+}
+
+/// Admin-facing view of a Stripe promotion code
+#[derive(Debug, serde::Serialize)]
+pub struct StripePromotionCodeSummary {
+    pub id: String,
+    pub code: String,
+    pub coupon_id: String,
+    pub active: bool,
+    pub max_redemptions: Option<i64>,
+    pub times_redeemed: i64,
+    pub expires_at: Option<i64>,
+    pub minimum_amount_cents: Option<i64>,
+}
+
+impl From<PromotionCode> for StripePromotionCodeSummary {
+    fn from(code: PromotionCode) -> Self {
+        Self {
+            id: code.id.to_string(),
+            code: code.code,
+            coupon_id: code.coupon.id.to_string(),
+            active: code.active,
+            max_redemptions: code.max_redemptions,
+            times_redeemed: code.times_redeemed,
+            expires_at: code.expires_at,
+            minimum_amount_cents: code.restrictions.minimum_amount,
+        }
+    }
 }
 
 pub struct RefundResult {
@@ -333,3 +1335,69 @@ pub struct RefundResult {
     pub status: String,
     pub amount: i64,
 }
+
+pub struct PaymentDetails {
+    pub risk_level: Option<String>,
+    pub risk_score: Option<i64>,
+    /// e.g. card, klarna, us_bank_account
+    pub payment_method_type: Option<String>,
+    pub card_brand: Option<String>,
+    /// e.g. apple_pay, google_pay, link
+    pub wallet: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StripeService;
+
+    fn service(bnpl_methods: &[&str]) -> StripeService {
+        StripeService::new(
+            "sk_test_unused",
+            "whsec_unused",
+            Vec::new(),
+            bnpl_methods.iter().map(|m| m.to_string()).collect(),
+            false,
+        )
+    }
+
+    #[test]
+    fn bnpl_limits_are_inclusive() {
+        let stripe = service(&["klarna", "afterpay_clearpay"]);
+        assert!(stripe.check_bnpl_eligibility("klarna", 100, Some("US")).is_ok());
+        assert!(stripe.check_bnpl_eligibility("klarna", 1_000_000, Some("US")).is_ok());
+        assert!(stripe.check_bnpl_eligibility("afterpay_clearpay", 400_000, Some("US")).is_ok());
+    }
+
+    #[test]
+    fn bnpl_outside_limits_is_refused() {
+        let stripe = service(&["klarna", "afterpay_clearpay"]);
+        assert!(stripe.check_bnpl_eligibility("klarna", 99, Some("US")).is_err());
+        assert!(stripe.check_bnpl_eligibility("klarna", 1_000_001, Some("US")).is_err());
+        assert_eq!(
+            stripe.check_bnpl_eligibility("afterpay_clearpay", 400_001, Some("US")),
+            Err("Afterpay is available for orders between $1.00 and $4000.00".to_string())
+        );
+    }
+
+    #[test]
+    fn bnpl_needs_a_supported_country() {
+        let stripe = service(&["klarna"]);
+        assert!(stripe.check_bnpl_eligibility("klarna", 5_000, Some("us")).is_ok());
+        assert!(stripe.check_bnpl_eligibility("klarna", 5_000, Some("CA")).is_err());
+        assert!(stripe.check_bnpl_eligibility("klarna", 5_000, None).is_err());
+    }
+
+    #[test]
+    fn bnpl_methods_must_be_enabled() {
+        let stripe = service(&["klarna"]);
+        assert!(stripe.check_bnpl_eligibility("afterpay_clearpay", 5_000, Some("US")).is_err());
+        assert_eq!(stripe.eligible_bnpl_methods(5_000, Some("US")), vec!["klarna".to_string()]);
+    }
+
+    #[test]
+    fn eligible_methods_follow_the_order_total() {
+        let stripe = service(&["klarna", "afterpay_clearpay"]);
+        assert_eq!(stripe.eligible_bnpl_methods(500_000, Some("US")), vec!["klarna".to_string()]);
+        assert_eq!(stripe.eligible_bnpl_methods(50, Some("US")), Vec::<String>::new());
+    }
+}
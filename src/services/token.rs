@@ -0,0 +1,161 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD as BASE64URL, Engine as _};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::sync::Arc;
+
+use crate::error::{AppError, AppResult};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// What a capability token lets its holder do, always for one resource
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    /// View one order (items, status, tracking) without signing in
+    ViewOrder,
+    /// Download one order's shipping label
+    DownloadLabel,
+}
+
+/// The signed part of a token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenClaims {
+    #[serde(rename = "cap")]
+    pub capability: Capability,
+    /// ID of the resource the token is for, e.g. an order ID
+    #[serde(rename = "sub")]
+    pub subject: String,
+    #[serde(rename = "exp")]
+    pub expires_ts: i64,
+}
+
+/// Issues and checks signed, expiring capability tokens, for links that must
+/// work without a Clerk session. A token is `base64url(claims).base64url(HMAC-SHA256)`;
+/// nothing is stored, so a token can't be revoked before it expires.
+#[derive(Clone)]
+pub struct TokenService {
+    key: Arc<Vec<u8>>,
+}
+
+fn now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
+
+impl TokenService {
+    pub fn new(secret: &[u8]) -> Self {
+        Self {
+            key: Arc::new(secret.to_vec()),
+        }
+    }
+
+    fn mac(&self) -> HmacSha256 {
+        HmacSha256::new_from_slice(&self.key).expect("HMAC accepts keys of any length")
+    }
+
+    /// A token granting `capability` on `subject` for `ttl_secs`, and when it expires
+    pub fn issue(&self, capability: Capability, subject: &str, ttl_secs: i64) -> (String, i64) {
+        let expires_ts = now() + ttl_secs;
+        let claims = TokenClaims {
+            capability,
+            subject: subject.to_string(),
+            expires_ts,
+        };
+        let payload = BASE64URL.encode(serde_json::to_vec(&claims).expect("claims serialize"));
+
+        let mut mac = self.mac();
+        mac.update(payload.as_bytes());
+        let signature = BASE64URL.encode(mac.finalize().into_bytes());
+
+        (format!("{}.{}", payload, signature), expires_ts)
+    }
+
+    /// Check a token's signature, expiry and capability and return its claims
+    pub fn verify(&self, token: &str, capability: Capability) -> AppResult<TokenClaims> {
+        let invalid = || AppError::Unauthorized("Invalid or expired link".to_string());
+
+        let (payload, signature) = token.split_once('.').ok_or_else(invalid)?;
+        let signature = BASE64URL.decode(signature).map_err(|_| invalid())?;
+
+        let mut mac = self.mac();
+        mac.update(payload.as_bytes());
+        mac.verify_slice(&signature).map_err(|_| invalid())?;
+
+        let claims: TokenClaims = BASE64URL
+            .decode(payload)
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .ok_or_else(invalid)?;
+
+        if claims.capability != capability || claims.expires_ts < now() {
+            return Err(invalid());
+        }
+        Ok(claims)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service() -> TokenService {
+        TokenService::new(b"test-secret")
+    }
+
+    #[test]
+    fn issued_tokens_verify() {
+        let tokens = service();
+        let (token, expires_ts) = tokens.issue(Capability::ViewOrder, "order-1", 60);
+
+        let claims = tokens.verify(&token, Capability::ViewOrder).unwrap();
+        assert_eq!(claims.subject, "order-1");
+        assert_eq!(claims.capability, Capability::ViewOrder);
+        assert_eq!(claims.expires_ts, expires_ts);
+    }
+
+    #[test]
+    fn tokens_only_grant_their_capability() {
+        let tokens = service();
+        let (token, _) = tokens.issue(Capability::ViewOrder, "order-1", 60);
+        assert!(tokens.verify(&token, Capability::DownloadLabel).is_err());
+    }
+
+    #[test]
+    fn expired_tokens_are_refused() {
+        let tokens = service();
+        let (token, _) = tokens.issue(Capability::DownloadLabel, "order-1", -1);
+        assert!(tokens.verify(&token, Capability::DownloadLabel).is_err());
+    }
+
+    #[test]
+    fn tokens_from_another_key_are_refused() {
+        let (token, _) = TokenService::new(b"other-secret").issue(Capability::ViewOrder, "order-1", 60);
+        assert!(service().verify(&token, Capability::ViewOrder).is_err());
+    }
+
+    #[test]
+    fn edited_claims_are_refused() {
+        let tokens = service();
+        let (token, _) = tokens.issue(Capability::ViewOrder, "order-1", 60);
+        let (_, signature) = token.split_once('.').unwrap();
+
+        let forged = TokenClaims {
+            capability: Capability::ViewOrder,
+            subject: "order-2".to_string(),
+            expires_ts: i64::MAX,
+        };
+        let payload = BASE64URL.encode(serde_json::to_vec(&forged).unwrap());
+        assert!(tokens.verify(&format!("{}.{}", payload, signature), Capability::ViewOrder).is_err());
+    }
+
+    #[test]
+    fn malformed_tokens_are_refused() {
+        let tokens = service();
+        for token in ["", "no-dot", "a.b", "!!.!!"] {
+            assert!(tokens.verify(token, Capability::ViewOrder).is_err(), "{:?}", token);
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+
+/// Seconds each code is valid for (RFC 6238 default, what authenticator apps use)
+const STEP_SECS: i64 = 30;
+
+/// Codes from this many steps before or after now are accepted, for clock drift
+const ALLOWED_DRIFT_STEPS: i64 = 1;
+
+const DIGITS: u32 = 6;
+
+/// Bytes of randomness in a secret (160 bits, as RFC 4226 recommends)
+const SECRET_BYTES: usize = 20;
+
+/// A new random secret from the OS CSPRNG, base32 encoded for authenticator apps
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; SECRET_BYTES];
+    getrandom::getrandom(&mut bytes).expect("OS random number generator unavailable");
+    BASE32_NOPAD.encode(&bytes)
+}
+
+/// otpauth:// URL for a QR code or manual entry in an authenticator app
+pub fn otpauth_url(secret: &str, account: &str, issuer: &str) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        url_encode(issuer),
+        url_encode(account),
+        secret,
+        url_encode(issuer),
+        DIGITS,
+        STEP_SECS
+    )
+}
+
+/// The time step a code matches, if it's valid for `secret` around `now`.
+/// Callers should reject steps at or before the last one accepted.
+pub fn verify(secret: &str, code: &str, now: i64) -> Option<i64> {
+    let code = code.trim().replace(' ', "");
+    if code.len() != DIGITS as usize || !code.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let key = base32_decode(secret)?;
+    let current = now / STEP_SECS;
+
+    (current - ALLOWED_DRIFT_STEPS..=current + ALLOWED_DRIFT_STEPS)
+        .find(|step| hotp(&key, *step as u64) == code)
+}
+
+/// RFC 4226 HOTP code for one counter value
+fn hotp(key: &[u8], counter: u64) -> String {
+    let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(&counter.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    let offset = (hash[19] & 0x0f) as usize;
+    let binary = ((hash[offset] as u32 & 0x7f) << 24)
+        | ((hash[offset + 1] as u32) << 16)
+        | ((hash[offset + 2] as u32) << 8)
+        | hash[offset + 3] as u32;
+    format!("{:0width$}", binary % 10u32.pow(DIGITS), width = DIGITS as usize)
+}
+
+/// Decode a base32 secret, ignoring case, spaces and padding as authenticator apps do
+fn base32_decode(s: &str) -> Option<Vec<u8>> {
+    let normalized: String = s
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '=')
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    BASE32_NOPAD.decode(normalized.as_bytes()).ok()
+}
+
+fn url_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// ASCII "12345678901234567890", the key in RFC 4226 appendix D and RFC 6238 appendix B
+    const RFC_SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    #[test]
+    fn hotp_matches_rfc_4226_vectors() {
+        let key = base32_decode(RFC_SECRET).unwrap();
+        assert_eq!(key, b"12345678901234567890");
+
+        let expected = [
+            "755224", "287082", "359152", "969429", "338314", "254676", "287922", "162583", "399871", "520489",
+        ];
+        for (counter, code) in expected.iter().enumerate() {
+            assert_eq!(hotp(&key, counter as u64), *code, "counter {}", counter);
+        }
+    }
+
+    #[test]
+    fn totp_matches_rfc_6238_vectors() {
+        // The RFC lists 8-digit SHA-1 codes; ours are their last 6 digits
+        let vectors = [
+            (59, "287082"),
+            (1111111109, "081804"),
+            (1111111111, "050471"),
+            (1234567890, "005924"),
+            (2000000000, "279037"),
+        ];
+        for (now, code) in vectors {
+            assert_eq!(verify(RFC_SECRET, code, now), Some(now / STEP_SECS), "time {}", now);
+        }
+    }
+
+    #[test]
+    fn codes_from_the_next_and_previous_step_are_accepted() {
+        // 287082 is the code for step 1 (time 30-59)
+        assert_eq!(verify(RFC_SECRET, "287082", 29), Some(1));
+        assert_eq!(verify(RFC_SECRET, "287082", 89), Some(1));
+        assert_eq!(verify(RFC_SECRET, "287082", 90), None);
+    }
+
+    #[test]
+    fn malformed_codes_are_rejected() {
+        assert_eq!(verify(RFC_SECRET, "28708", 59), None);
+        assert_eq!(verify(RFC_SECRET, "2870822", 59), None);
+        assert_eq!(verify(RFC_SECRET, "28708a", 59), None);
+        assert_eq!(verify(RFC_SECRET, "287 082", 59), Some(1));
+    }
+
+    #[test]
+    fn secrets_are_decoded_like_authenticator_apps_do() {
+        assert_eq!(base32_decode("gezd gnbv gy3t qojq gezd gnbv gy3t qojq").unwrap(), b"12345678901234567890");
+        assert_eq!(base32_decode("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ====").unwrap(), b"12345678901234567890");
+        assert_eq!(base32_decode("GEZDGNBV1"), None);
+    }
+
+    #[test]
+    fn generated_secrets_are_random_160_bit_keys() {
+        let secret = generate_secret();
+        assert_eq!(base32_decode(&secret).unwrap().len(), SECRET_BYTES);
+        assert_ne!(secret, generate_secret());
+    }
+}